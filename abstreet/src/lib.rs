@@ -0,0 +1,174 @@
+//! # A/B Street as a library
+//!
+//! Load maps, run a traffic simulation over a scenario, and read the results, without depending on
+//! the game or the map editor.
+//!
+//! The functions and [`Simulation`] here follow semver: they only change in a breaking way along
+//! with a new major version (or minor version, before 1.0). The crates underneath -- `map_model`,
+//! `sim`, `geom`, and `abstutil` -- change all the time. The types re-exported from them keep
+//! their names and stay available here, but their own fields and methods carry no such promise,
+//! and neither does anything reached through [`Simulation::sim`].
+//!
+//! Like the rest of A/B Street, paths are relative to a checkout of the repository, so run from a
+//! directory next to `data/`.
+//!
+//! ```no_run
+//! use abstreet::{Duration, SimOptions, Simulation, Time};
+//!
+//! let opts = SimOptions::new("example");
+//! let mut sim = Simulation::new("montlake", "weekday", None, opts).unwrap();
+//! sim.run_until(Time::START_OF_DAY + Duration::hours(9));
+//! println!("{} trips finished", sim.analytics().finished_trips.len());
+//! ```
+
+use abstutil::Timer;
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+pub use geom::{Distance, Duration, LonLat, Pt2D, Speed, Time};
+pub use map_model::{
+    Building, BuildingID, Intersection, IntersectionID, Lane, LaneID, LaneType, Map, MapEdits,
+    Road, RoadID,
+};
+pub use sim::{AlertHandler, Analytics, PersonID, Scenario, SimOptions, TripID, TripMode};
+
+/// The same seed the game and command-line tools use by default, so results match theirs.
+pub const DEFAULT_RNG_SEED: u8 = 42;
+
+/// The names of all maps that can be loaded.
+pub fn list_maps() -> Vec<String> {
+    abstutil::list_all_objects(abstutil::path_all_maps())
+}
+
+/// The names of all scenarios for one map.
+pub fn list_scenarios(map_name: &str) -> Vec<String> {
+    abstutil::list_all_objects(abstutil::path_all_scenarios(map_name))
+}
+
+/// Loads a map by name, like "montlake".
+pub fn load_map(map_name: &str) -> Result<Map, String> {
+    let path = abstutil::path_map(map_name);
+    if !abstutil::file_exists(path.clone()) {
+        return Err(format!("No map at {}", path));
+    }
+    Ok(Map::new(path, &mut Timer::throwaway()))
+}
+
+/// A map and a traffic simulation running on it.
+pub struct Simulation {
+    map: Map,
+    sim: sim::Sim,
+}
+
+impl Simulation {
+    /// Starts a simulation of one scenario at midnight. `edits_name` names map edits saved from
+    /// the game, or None to use the map as it is.
+    pub fn new(
+        map_name: &str,
+        scenario_name: &str,
+        edits_name: Option<&str>,
+        opts: SimOptions,
+    ) -> Result<Simulation, String> {
+        Simulation::new_with_seed(map_name, scenario_name, edits_name, opts, DEFAULT_RNG_SEED)
+    }
+
+    /// Like `new`, but the random choices made while creating the people in the scenario come
+    /// from a different seed.
+    pub fn new_with_seed(
+        map_name: &str,
+        scenario_name: &str,
+        edits_name: Option<&str>,
+        opts: SimOptions,
+        rng_seed: u8,
+    ) -> Result<Simulation, String> {
+        if let Some((ref path, _)) = opts.script {
+            sim::check_script(path)?;
+        }
+        let mut timer = Timer::throwaway();
+        let mut map = load_map(map_name)?;
+        if let Some(name) = edits_name {
+            let edits = MapEdits::load(&map, name, &mut timer)?;
+            map.apply_edits(edits, &mut timer);
+            map.recalculate_pathfinding_after_edits(&mut timer);
+        }
+
+        let scenario: Scenario = abstutil::maybe_read_binary(
+            abstutil::path_scenario(map_name, scenario_name),
+            &mut timer,
+        )
+        .map_err(|err| format!("Couldn't load scenario {}: {}", scenario_name, err))?;
+
+        let mut sim = sim::Sim::new(&map, opts, &mut timer);
+        let mut rng = XorShiftRng::from_seed([rng_seed; 16]);
+        scenario.instantiate(&mut sim, &map, &mut rng, &mut timer);
+        Ok(Simulation { map, sim })
+    }
+
+    /// Picks up a simulation exactly where a checkpoint saved by `save_checkpoint`, the game, or
+    /// the headless tool left off.
+    pub fn from_checkpoint(path: &str) -> Result<Simulation, String> {
+        let mut timer = Timer::throwaway();
+        if !abstutil::file_exists(path.to_string()) {
+            return Err(format!("No checkpoint at {}", path));
+        }
+        let sim = sim::Sim::load_checkpoint(path.to_string(), &mut timer)
+            .map_err(|err| format!("Couldn't load checkpoint {}: {}", path, err))?;
+
+        let mut map = load_map(sim.get_map_name())?;
+        if sim.get_edits_name() != "untitled edits" {
+            let edits = MapEdits::load(&map, sim.get_edits_name(), &mut timer)?;
+            map.apply_edits(edits, &mut timer);
+            map.recalculate_pathfinding_after_edits(&mut timer);
+        }
+        // Unlike savestates, checkpoints keep all of the paths
+        Ok(Simulation { map, sim })
+    }
+
+    /// Saves everything about the simulation right now, returning the path to the file.
+    pub fn save_checkpoint(&mut self, name: &str) -> String {
+        self.sim.save_checkpoint(&self.map, name)
+    }
+
+    /// Advances the simulation until this time. Does nothing if it's already later.
+    pub fn run_until(&mut self, time: Time) {
+        let now = self.sim.time();
+        if time > now {
+            self.sim
+                .timed_step(&self.map, time - now, &mut None, &mut Timer::throwaway());
+        }
+    }
+
+    /// Runs the simulation until every trip is done.
+    pub fn run_until_done(&mut self) {
+        self.sim.run_until_done(&self.map, |_, _| {}, None);
+    }
+
+    pub fn time(&self) -> Time {
+        self.sim.time()
+    }
+
+    /// True once every trip is done.
+    pub fn is_done(&self) -> bool {
+        self.sim.is_done()
+    }
+
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    /// Everything recorded so far: throughput, trip times, delays, and so on.
+    pub fn analytics(&self) -> &Analytics {
+        self.sim.get_analytics()
+    }
+
+    /// How long a trip took from start to finish, and how much of that was spent delayed. None
+    /// if the trip hasn't finished.
+    pub fn finished_trip_time(&self, trip: TripID) -> Option<(Duration, Duration)> {
+        self.sim.finished_trip_time(trip)
+    }
+
+    /// Direct access to the underlying simulation. This isn't covered by semver.
+    pub fn sim(&self) -> &sim::Sim {
+        &self.sim
+    }
+}
@@ -0,0 +1,219 @@
+//! # Python bindings
+//!
+//! A thin layer over the `abstreet` crate, so experiments can be scripted from Python. Times and
+//! durations are seconds (since midnight, for times), distances are meters, and speeds are meters
+//! per second. Roads, intersections, and trips are referred to by their numeric IDs.
+//!
+//! ```python
+//! import abstreet_py
+//!
+//! sim = abstreet_py.Simulation("montlake", "weekday")
+//! sim.run_until(9 * 3600)
+//! for finished_at, trip, mode, duration in sim.finished_trips():
+//!     print(trip, mode, duration)
+//! ```
+//!
+//! Like everything else, this has to run from a directory next to `data/`.
+
+use abstreet::{Duration, RoadID, Time, TripID, TripMode};
+use pyo3::exceptions::ValueError;
+use pyo3::prelude::*;
+use pyo3::wrap_pyfunction;
+
+/// The names of all maps that can be loaded.
+#[pyfunction]
+fn list_maps() -> Vec<String> {
+    abstreet::list_maps()
+}
+
+/// The names of all scenarios for one map.
+#[pyfunction]
+fn list_scenarios(map: &str) -> Vec<String> {
+    abstreet::list_scenarios(map)
+}
+
+/// A map, just for looking at. Simulations load their own copy.
+#[pyclass(unsendable)]
+struct Map {
+    map: abstreet::Map,
+}
+
+#[pymethods]
+impl Map {
+    #[new]
+    fn new(name: &str) -> PyResult<Map> {
+        let map = abstreet::load_map(name).map_err(ValueError::py_err)?;
+        Ok(Map { map })
+    }
+
+    fn name(&self) -> String {
+        self.map.get_name().to_string()
+    }
+
+    /// Every road, as (ID, name, OSM way ID, speed limit, length).
+    fn roads(&self) -> Vec<(usize, String, i64, f64, f64)> {
+        self.map
+            .all_roads()
+            .iter()
+            .map(|r| {
+                (
+                    r.id.0,
+                    r.get_name(),
+                    r.orig_id.osm_way_id,
+                    r.speed_limit.inner_meters_per_second(),
+                    r.center_pts.length().inner_meters(),
+                )
+            })
+            .collect()
+    }
+
+    /// Every intersection, as (ID, OSM node ID, (longitude, latitude)).
+    fn intersections(&self) -> Vec<(usize, i64, (f64, f64))> {
+        let gps_bounds = self.map.get_gps_bounds();
+        self.map
+            .all_intersections()
+            .iter()
+            .map(|i| {
+                let gps = i.polygon.center().forcibly_to_gps(gps_bounds);
+                (i.id.0, i.orig_id.osm_node_id, (gps.x(), gps.y()))
+            })
+            .collect()
+    }
+}
+
+/// A traffic simulation of one scenario on a map, starting at midnight.
+#[pyclass(unsendable)]
+struct Simulation {
+    sim: abstreet::Simulation,
+}
+
+#[pymethods]
+impl Simulation {
+    /// Optionally applies map edits saved from the game, and uses a different random seed to
+    /// create the people in the scenario.
+    #[new]
+    #[args(edits = "None", seed = "abstreet::DEFAULT_RNG_SEED")]
+    fn new(map: &str, scenario: &str, edits: Option<&str>, seed: u8) -> PyResult<Simulation> {
+        let sim = abstreet::Simulation::new_with_seed(
+            map,
+            scenario,
+            edits,
+            abstreet::SimOptions::new("python"),
+            seed,
+        )
+        .map_err(ValueError::py_err)?;
+        Ok(Simulation { sim })
+    }
+
+    /// Resumes from a checkpoint saved by save_checkpoint, the game, or the headless tool.
+    #[staticmethod]
+    fn from_checkpoint(path: &str) -> PyResult<Simulation> {
+        let sim = abstreet::Simulation::from_checkpoint(path).map_err(ValueError::py_err)?;
+        Ok(Simulation { sim })
+    }
+
+    /// Saves everything about the simulation right now, returning the path to the file.
+    fn save_checkpoint(&mut self, name: &str) -> String {
+        self.sim.save_checkpoint(name)
+    }
+
+    fn time(&self) -> f64 {
+        self.sim.time().inner_seconds()
+    }
+
+    fn is_done(&self) -> bool {
+        self.sim.is_done()
+    }
+
+    /// Advances the simulation by this many seconds.
+    fn step(&mut self, seconds: f64) -> PyResult<()> {
+        if seconds < 0.0 {
+            return Err(ValueError::py_err("can't step backwards"));
+        }
+        let time = self.sim.time() + Duration::seconds(seconds);
+        self.sim.run_until(time);
+        Ok(())
+    }
+
+    /// Advances the simulation until this many seconds after midnight.
+    fn run_until(&mut self, time: f64) {
+        self.sim
+            .run_until(Time::START_OF_DAY + Duration::seconds(time));
+    }
+
+    /// Runs the simulation until every trip is done.
+    fn run_until_done(&mut self) {
+        self.sim.run_until_done();
+    }
+
+    /// Every trip that's finished so far, as (finish time, trip ID, mode, duration). Trips that
+    /// couldn't be completed have no mode.
+    fn finished_trips(&self) -> Vec<(f64, usize, Option<&'static str>, f64)> {
+        self.sim
+            .analytics()
+            .finished_trips
+            .iter()
+            .map(|(t, id, mode, dt)| {
+                (
+                    t.inner_seconds(),
+                    id.0,
+                    mode.map(mode_name),
+                    dt.inner_seconds(),
+                )
+            })
+            .collect()
+    }
+
+    /// For a finished trip, the total time and the time spent delayed. None if it hasn't
+    /// finished.
+    fn trip_time(&self, trip: usize) -> Option<(f64, f64)> {
+        self.sim
+            .finished_trip_time(TripID(trip))
+            .map(|(total, delay)| (total.inner_seconds(), delay.inner_seconds()))
+    }
+
+    /// How many agents crossed each road so far, as (road ID, mode, hour, count).
+    fn road_throughput(&self) -> Vec<(usize, &'static str, usize, usize)> {
+        self.sim
+            .analytics()
+            .road_thruput
+            .counts
+            .iter()
+            .map(|((r, mode, hour), count)| (r.0, mode_name(*mode), *hour, *count))
+            .collect()
+    }
+
+    /// How many agents crossed each intersection so far, as (intersection ID, mode, hour, count).
+    fn intersection_throughput(&self) -> Vec<(usize, &'static str, usize, usize)> {
+        self.sim
+            .analytics()
+            .intersection_thruput
+            .counts
+            .iter()
+            .map(|((i, mode, hour), count)| (i.0, mode_name(*mode), *hour, *count))
+            .collect()
+    }
+
+    /// How many agents have crossed one road so far.
+    fn total_road_throughput(&self, road: usize) -> usize {
+        self.sim.analytics().road_thruput.total_for(RoadID(road))
+    }
+}
+
+fn mode_name(mode: TripMode) -> &'static str {
+    match mode {
+        TripMode::Walk => "walk",
+        TripMode::Bike => "bike",
+        TripMode::Transit => "transit",
+        TripMode::Drive => "drive",
+    }
+}
+
+#[pymodule]
+fn abstreet_py(_: Python, m: &PyModule) -> PyResult<()> {
+    m.add_wrapped(wrap_pyfunction!(list_maps))?;
+    m.add_wrapped(wrap_pyfunction!(list_scenarios))?;
+    m.add_class::<Map>()?;
+    m.add_class::<Simulation>()?;
+    Ok(())
+}
@@ -102,6 +102,15 @@ pub fn serialized_size_bytes<T: Serialize>(obj: &T) -> usize {
     bincode::serialized_size(obj).unwrap() as usize
 }
 
+// For keeping something compact in memory, instead of writing it to a file
+pub fn to_binary<T: Serialize>(obj: &T) -> Vec<u8> {
+    bincode::serialize(obj).unwrap()
+}
+
+pub fn from_binary<T: DeserializeOwned>(raw: &[u8]) -> Result<T, Error> {
+    bincode::deserialize(raw).map_err(|err| Error::new(ErrorKind::Other, err))
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn write_binary<T: Serialize>(path: String, obj: &T) {
     if let Err(err) = maybe_write_binary(&path, obj) {
@@ -16,9 +16,10 @@ pub use crate::collections::{
 pub use crate::error::Error;
 pub use crate::io::{
     basename, deserialize_btreemap, deserialize_multimap, file_exists, find_next_file,
-    find_prev_file, list_all_objects, list_dir, load_all_objects, maybe_read_binary,
+    find_prev_file, from_binary, list_all_objects, list_dir, load_all_objects, maybe_read_binary,
     maybe_read_json, read_binary, read_json, serialize_btreemap, serialize_multimap,
-    serialized_size_bytes, slurp_file, to_json, write_binary, write_json, FileWithProgress,
+    serialized_size_bytes, slurp_file, to_binary, to_json, write_binary, write_json,
+    FileWithProgress,
 };
 pub use crate::logs::Warn;
 pub use crate::random::{fork_rng, WeightedUsizeChoice};
@@ -87,6 +88,17 @@ pub fn path_all_scenarios(map_name: &str) -> String {
     format!("../data/system/scenarios/{}", map_name)
 }
 
+pub fn path_weather(map_name: &str, scenario_name: &str) -> String {
+    format!("../data/system/weather/{}/{}.json", map_name, scenario_name)
+}
+
+pub fn path_car_following(map_name: &str, scenario_name: &str) -> String {
+    format!(
+        "../data/system/car_following/{}/{}.json",
+        map_name, scenario_name
+    )
+}
+
 pub fn path_synthetic_map(map_name: &str) -> String {
     format!("../data/system/synthetic_maps/{}.json", map_name)
 }
@@ -107,6 +119,16 @@ pub fn path_all_edits(map_name: &str) -> String {
     format!("../data/player/edits/{}", map_name)
 }
 
+pub fn path_signal_plan(map_name: &str, plan_name: &str) -> String {
+    format!(
+        "../data/player/signal_plans/{}/{}.json",
+        map_name, plan_name
+    )
+}
+pub fn path_all_signal_plans(map_name: &str) -> String {
+    format!("../data/player/signal_plans/{}", map_name)
+}
+
 pub fn path_save(map_name: &str, edits_name: &str, run_name: &str, time: String) -> String {
     format!(
         "../data/player/saves/{}/{}_{}/{}.bin",
@@ -120,6 +142,22 @@ pub fn path_all_saves(map_name: &str, edits_name: &str, run_name: &str) -> Strin
     )
 }
 
+pub fn path_checkpoint(map_name: &str, edits_name: &str, name: &str) -> String {
+    format!(
+        "../data/player/checkpoints/{}/{}/{}.bin",
+        map_name, edits_name, name
+    )
+}
+pub fn path_replay(map_name: &str, name: &str) -> String {
+    format!("../data/player/replays/{}/{}.bin", map_name, name)
+}
+pub fn path_all_replays(map_name: &str) -> String {
+    format!("../data/player/replays/{}", map_name)
+}
+pub fn path_all_checkpoints(map_name: &str, edits_name: &str) -> String {
+    format!("../data/player/checkpoints/{}/{}", map_name, edits_name)
+}
+
 // Input data (For developers to build maps, not needed at runtime)
 
 pub fn path_pending_screenshots(map_name: &str) -> String {
@@ -51,6 +51,15 @@ impl<T> Warn<T> {
         self.value
     }
 
+    // For callers that'd rather fall back to something else than live with the warnings
+    pub fn ok_without_warnings(self) -> Option<T> {
+        if self.warnings.is_empty() {
+            Some(self.value)
+        } else {
+            None
+        }
+    }
+
     pub fn get(self, timer: &mut Timer) -> T {
         // TODO Context from the current Timer phase, caller
         for line in self.warnings {
@@ -0,0 +1,571 @@
+use std::collections::BTreeMap;
+
+// Just enough of TIFF (https://www.adobe.io/open/standards/TIFF.html) and GeoTIFF
+// (http://docs.opengeospatial.org/is/19-008r4/19-008r4.html) to read a single-band elevation
+// raster: uncompressed or LZW, in strips or tiles, with integer or floating point samples. Only
+// the first image in the file is read.
+pub struct Raster {
+    pub width: usize,
+    pub height: usize,
+    // Row-major, starting from the top row
+    pub samples: Vec<f64>,
+    // From GDAL's nodata tag
+    pub no_data: Option<f64>,
+    // The size of a pixel in model units, like degrees for lon/lat rasters
+    pub pixel_width: f64,
+    pub pixel_height: f64,
+    // The center of the top-left pixel, in model units
+    pub origin_x: f64,
+    pub origin_y: f64,
+    // The GeoKeys say the model isn't lon/lat
+    pub projected: bool,
+}
+
+const IMAGE_WIDTH: u16 = 256;
+const IMAGE_LENGTH: u16 = 257;
+const BITS_PER_SAMPLE: u16 = 258;
+const COMPRESSION: u16 = 259;
+const STRIP_OFFSETS: u16 = 273;
+const SAMPLES_PER_PIXEL: u16 = 277;
+const ROWS_PER_STRIP: u16 = 278;
+const STRIP_BYTE_COUNTS: u16 = 279;
+const PREDICTOR: u16 = 317;
+const TILE_WIDTH: u16 = 322;
+const TILE_LENGTH: u16 = 323;
+const TILE_OFFSETS: u16 = 324;
+const TILE_BYTE_COUNTS: u16 = 325;
+const SAMPLE_FORMAT: u16 = 339;
+const MODEL_PIXEL_SCALE: u16 = 33550;
+const MODEL_TIEPOINT: u16 = 33922;
+const GEO_KEY_DIRECTORY: u16 = 34735;
+const GDAL_NODATA: u16 = 42113;
+
+const GT_MODEL_TYPE: u16 = 1024;
+const GT_RASTER_TYPE: u16 = 1025;
+const MODEL_TYPE_GEOGRAPHIC: u16 = 2;
+const RASTER_PIXEL_IS_POINT: u16 = 2;
+
+pub fn read(bytes: &[u8]) -> Result<Raster, String> {
+    let little_endian = match bytes.get(0..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => {
+            return Err("not a TIFF file".to_string());
+        }
+    };
+    let r = Reader {
+        bytes,
+        little_endian,
+    };
+    match r.u16(2)? {
+        42 => {}
+        43 => {
+            return Err("BigTIFF isn't supported".to_string());
+        }
+        x => {
+            return Err(format!("not a TIFF file (version {})", x));
+        }
+    }
+    let tags = r.ifd(r.u32(4)? as usize)?;
+    let single = |tag: u16| -> Result<Option<usize>, String> {
+        match tags.get(&tag) {
+            Some(entry) => Ok(r.values(entry)?.get(0).map(|x| *x as usize)),
+            None => Ok(None),
+        }
+    };
+    let required = |tag: u16, name: &str| -> Result<usize, String> {
+        single(tag)?.ok_or_else(|| format!("no {}", name))
+    };
+
+    let width = required(IMAGE_WIDTH, "image width")?;
+    let height = required(IMAGE_LENGTH, "image length")?;
+    if single(SAMPLES_PER_PIXEL)?.unwrap_or(1) != 1 {
+        return Err("only single-band images are supported".to_string());
+    }
+    let sample_format = single(SAMPLE_FORMAT)?.unwrap_or(1);
+    let bits = single(BITS_PER_SAMPLE)?.unwrap_or(1);
+    let decode = match (sample_format, bits) {
+        (1, 8) | (1, 16) | (1, 32) => SampleType::Unsigned(bits),
+        (2, 8) | (2, 16) | (2, 32) => SampleType::Signed(bits),
+        (3, 32) | (3, 64) => SampleType::Float(bits),
+        _ => {
+            return Err(format!(
+                "{}-bit samples of format {} aren't supported",
+                bits, sample_format
+            ));
+        }
+    };
+    let lzw = match single(COMPRESSION)?.unwrap_or(1) {
+        1 => false,
+        5 => true,
+        x => {
+            return Err(format!(
+                "compression {} isn't supported; only uncompressed and LZW are",
+                x
+            ));
+        }
+    };
+    let predictor = single(PREDICTOR)?.unwrap_or(1);
+    match (predictor, decode) {
+        (1, _) => {}
+        (2, SampleType::Unsigned(_)) | (2, SampleType::Signed(_)) => {}
+        _ => {
+            return Err(format!(
+                "predictor {} isn't supported for these samples",
+                predictor
+            ));
+        }
+    }
+
+    // Strips are just tiles as wide as the image
+    let (chunk_width, chunk_height, offsets, counts) = if tags.contains_key(&TILE_WIDTH) {
+        (
+            required(TILE_WIDTH, "tile width")?,
+            required(TILE_LENGTH, "tile length")?,
+            tags.get(&TILE_OFFSETS).ok_or("no tile offsets")?,
+            tags.get(&TILE_BYTE_COUNTS).ok_or("no tile byte counts")?,
+        )
+    } else {
+        (
+            width,
+            single(ROWS_PER_STRIP)?.unwrap_or(height).min(height),
+            tags.get(&STRIP_OFFSETS).ok_or("no strip offsets")?,
+            tags.get(&STRIP_BYTE_COUNTS).ok_or("no strip byte counts")?,
+        )
+    };
+    if width == 0 || height == 0 || chunk_width == 0 || chunk_height == 0 {
+        return Err("empty image".to_string());
+    }
+    let offsets = r.values(offsets)?;
+    let counts = r.values(counts)?;
+    let chunks_across = (width + chunk_width - 1) / chunk_width;
+    let chunks_down = (height + chunk_height - 1) / chunk_height;
+    if offsets.len() < chunks_across * chunks_down || counts.len() < offsets.len() {
+        return Err("not enough strips or tiles for the image".to_string());
+    }
+
+    let bytes_per_sample = decode.bits() / 8;
+    let mut samples = vec![0.0; width * height];
+    for chunk_row in 0..chunks_down {
+        for chunk_col in 0..chunks_across {
+            let idx = chunk_row * chunks_across + chunk_col;
+            let start = offsets[idx] as usize;
+            let raw = bytes
+                .get(start..start + counts[idx] as usize)
+                .ok_or("a strip or tile goes past the end of the file")?;
+            let data = if lzw { decode_lzw(raw)? } else { raw.to_vec() };
+
+            let top = chunk_row * chunk_height;
+            let left = chunk_col * chunk_width;
+            // The last strip can be shorter. Tiles are always full-size, padded past the edges.
+            let rows = if tags.contains_key(&TILE_WIDTH) {
+                chunk_height
+            } else {
+                chunk_height.min(height - top)
+            };
+            if data.len() < rows * chunk_width * bytes_per_sample {
+                return Err("a strip or tile is missing samples".to_string());
+            }
+            for row in 0..rows {
+                let mut prev = 0;
+                for col in 0..chunk_width {
+                    let at = (row * chunk_width + col) * bytes_per_sample;
+                    let mut value = r.sample_bits(&data[at..at + bytes_per_sample]);
+                    if predictor == 2 {
+                        // Each sample is stored as the difference from the one to its left
+                        value = value.wrapping_add(prev) & decode.mask();
+                        prev = value;
+                    }
+                    let (y, x) = (top + row, left + col);
+                    if y < height && x < width {
+                        samples[y * width + x] = decode.to_f64(value);
+                    }
+                }
+            }
+        }
+    }
+
+    let scale = tags
+        .get(&MODEL_PIXEL_SCALE)
+        .map(|e| r.values(e))
+        .transpose()?
+        .filter(|x| x.len() >= 2)
+        .ok_or("no pixel scale; only georeferencing by pixel scale and tiepoint works")?;
+    let tiepoint = tags
+        .get(&MODEL_TIEPOINT)
+        .map(|e| r.values(e))
+        .transpose()?
+        .filter(|x| x.len() >= 6)
+        .ok_or("no tiepoint; only georeferencing by pixel scale and tiepoint works")?;
+    let mut geo_keys = BTreeMap::new();
+    if let Some(entry) = tags.get(&GEO_KEY_DIRECTORY) {
+        let dir = r.values(entry)?;
+        // A header, then (key, location, count, value) for each key. Only keys with the value
+        // right there matter here.
+        for key in dir.get(4..).unwrap_or(&[]).chunks(4) {
+            if key.len() == 4 && key[1] == 0.0 {
+                geo_keys.insert(key[0] as u16, key[3] as u16);
+            }
+        }
+    }
+    let no_data = match tags.get(&GDAL_NODATA) {
+        Some(entry) => Some(
+            r.ascii(entry)?
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| "bad nodata value".to_string())?,
+        ),
+        None => None,
+    };
+
+    // The tiepoint ties a raster position to a model position. Usually the raster position is
+    // the top-left corner of the image, unless the pixels are points instead of areas.
+    let pixel_offset = if geo_keys.get(&GT_RASTER_TYPE) == Some(&RASTER_PIXEL_IS_POINT) {
+        0.0
+    } else {
+        0.5
+    };
+    Ok(Raster {
+        width,
+        height,
+        samples,
+        no_data,
+        pixel_width: scale[0],
+        pixel_height: scale[1],
+        origin_x: tiepoint[3] + (pixel_offset - tiepoint[0]) * scale[0],
+        origin_y: tiepoint[4] - (pixel_offset - tiepoint[1]) * scale[1],
+        projected: geo_keys
+            .get(&GT_MODEL_TYPE)
+            .map(|x| *x != MODEL_TYPE_GEOGRAPHIC)
+            .unwrap_or(false),
+    })
+}
+
+#[derive(Clone, Copy)]
+enum SampleType {
+    Unsigned(usize),
+    Signed(usize),
+    Float(usize),
+}
+
+impl SampleType {
+    fn bits(self) -> usize {
+        match self {
+            SampleType::Unsigned(bits) | SampleType::Signed(bits) | SampleType::Float(bits) => bits,
+        }
+    }
+
+    fn mask(self) -> u64 {
+        if self.bits() == 64 {
+            std::u64::MAX
+        } else {
+            (1 << self.bits()) - 1
+        }
+    }
+
+    fn to_f64(self, value: u64) -> f64 {
+        match self {
+            SampleType::Unsigned(_) => value as f64,
+            SampleType::Signed(bits) => {
+                // Sign-extend
+                let shift = 64 - bits;
+                (((value << shift) as i64) >> shift) as f64
+            }
+            SampleType::Float(32) => f64::from(f32::from_bits(value as u32)),
+            SampleType::Float(_) => f64::from_bits(value),
+        }
+    }
+}
+
+struct Entry {
+    field_type: u16,
+    count: usize,
+    // Where the values start
+    offset: usize,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn get(&self, at: usize, len: usize) -> Result<&'a [u8], String> {
+        self.bytes
+            .get(at..at + len)
+            .ok_or_else(|| "the file is cut off".to_string())
+    }
+
+    // Any unsigned integer up to 8 bytes, in the file's byte order
+    fn uint(&self, raw: &[u8]) -> u64 {
+        let mut value = 0;
+        if self.little_endian {
+            for b in raw.iter().rev() {
+                value = (value << 8) | u64::from(*b);
+            }
+        } else {
+            for b in raw {
+                value = (value << 8) | u64::from(*b);
+            }
+        }
+        value
+    }
+
+    fn u16(&self, at: usize) -> Result<u16, String> {
+        Ok(self.uint(self.get(at, 2)?) as u16)
+    }
+
+    fn u32(&self, at: usize) -> Result<u32, String> {
+        Ok(self.uint(self.get(at, 4)?) as u32)
+    }
+
+    fn sample_bits(&self, raw: &[u8]) -> u64 {
+        self.uint(raw)
+    }
+
+    fn ifd(&self, at: usize) -> Result<BTreeMap<u16, Entry>, String> {
+        let mut tags = BTreeMap::new();
+        for idx in 0..(self.u16(at)? as usize) {
+            let entry = at + 2 + 12 * idx;
+            let field_type = self.u16(entry + 2)?;
+            let count = self.u32(entry + 4)? as usize;
+            let size = type_size(field_type) * count;
+            let offset = if size <= 4 {
+                entry + 8
+            } else {
+                self.u32(entry + 8)? as usize
+            };
+            tags.insert(
+                self.u16(entry)?,
+                Entry {
+                    field_type,
+                    count,
+                    offset,
+                },
+            );
+        }
+        Ok(tags)
+    }
+
+    fn values(&self, entry: &Entry) -> Result<Vec<f64>, String> {
+        let size = type_size(entry.field_type);
+        let mut values = Vec::with_capacity(entry.count);
+        for idx in 0..entry.count {
+            let value = self.uint(self.get(entry.offset + idx * size, size)?);
+            values.push(match entry.field_type {
+                1 | 3 | 4 => value as f64,
+                6 => f64::from(value as u8 as i8),
+                8 => f64::from(value as u16 as i16),
+                9 => f64::from(value as u32 as i32),
+                11 => f64::from(f32::from_bits(value as u32)),
+                12 => f64::from_bits(value),
+                x => {
+                    return Err(format!("tag values of type {} aren't supported", x));
+                }
+            });
+        }
+        Ok(values)
+    }
+
+    fn ascii(&self, entry: &Entry) -> Result<String, String> {
+        let raw = self.get(entry.offset, entry.count)?;
+        Ok(String::from_utf8_lossy(raw)
+            .trim_end_matches('\0')
+            .to_string())
+    }
+}
+
+fn type_size(field_type: u16) -> usize {
+    match field_type {
+        3 | 8 => 2,
+        4 | 9 | 11 => 4,
+        5 | 10 | 12 => 8,
+        _ => 1,
+    }
+}
+
+// TIFF's flavor of LZW: codes are written most significant bit first, and the code width grows
+// one code earlier than in GIF.
+fn decode_lzw(input: &[u8]) -> Result<Vec<u8>, String> {
+    const CLEAR: usize = 256;
+    const END: usize = 257;
+    const MAX_CODES: usize = 4096;
+
+    let reset = |table: &mut Vec<Vec<u8>>| {
+        table.clear();
+        for b in 0..256 {
+            table.push(vec![b as u8]);
+        }
+        // CLEAR and END
+        table.push(Vec::new());
+        table.push(Vec::new());
+    };
+    let mut table = Vec::new();
+    reset(&mut table);
+    let mut out = Vec::new();
+    let mut width = 9;
+    let mut bit = 0;
+    let mut prev: Option<usize> = None;
+    // Some encoders leave off the END code
+    while bit + width <= input.len() * 8 {
+        let mut code = 0;
+        for _ in 0..width {
+            let b = (input[bit / 8] >> (7 - bit % 8)) & 1;
+            code = (code << 1) | (b as usize);
+            bit += 1;
+        }
+        if code == CLEAR {
+            reset(&mut table);
+            width = 9;
+            prev = None;
+            continue;
+        }
+        if code == END {
+            break;
+        }
+
+        let entry = if code < table.len() {
+            table[code].clone()
+        } else if code == table.len() && prev.is_some() {
+            // The code being defined right now: the previous string plus its own first byte
+            let mut entry = table[prev.unwrap()].clone();
+            entry.push(entry[0]);
+            entry
+        } else {
+            return Err(format!("bad LZW code {}", code));
+        };
+        out.extend_from_slice(&entry);
+        if let Some(p) = prev {
+            if table.len() < MAX_CODES {
+                let mut new = table[p].clone();
+                new.push(entry[0]);
+                table.push(new);
+            }
+        }
+        prev = Some(code);
+        width = if table.len() + 1 >= 2048 {
+            12
+        } else if table.len() + 1 >= 1024 {
+            11
+        } else if table.len() + 1 >= 512 {
+            10
+        } else {
+            9
+        };
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A little-endian TIFF with the image data first, then one IFD with these (tag, type, count,
+    // raw values) entries
+    fn make_tiff(image: Vec<u8>, entries: Vec<(u16, u16, u32, Vec<u8>)>) -> Vec<u8> {
+        let ifd_offset = 8 + image.len();
+        let mut extra_offset = ifd_offset + 2 + 12 * entries.len() + 4;
+        let mut ifd = Vec::new();
+        let mut extra = Vec::new();
+        ifd.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for (tag, field_type, count, raw) in entries {
+            ifd.extend_from_slice(&tag.to_le_bytes());
+            ifd.extend_from_slice(&field_type.to_le_bytes());
+            ifd.extend_from_slice(&count.to_le_bytes());
+            if raw.len() <= 4 {
+                let mut inline = raw.clone();
+                inline.resize(4, 0);
+                ifd.extend_from_slice(&inline);
+            } else {
+                ifd.extend_from_slice(&(extra_offset as u32).to_le_bytes());
+                extra_offset += raw.len();
+                extra.extend_from_slice(&raw);
+            }
+        }
+        ifd.extend_from_slice(&0_u32.to_le_bytes());
+
+        let mut bytes = b"II".to_vec();
+        bytes.extend_from_slice(&42_u16.to_le_bytes());
+        bytes.extend_from_slice(&(ifd_offset as u32).to_le_bytes());
+        bytes.extend(image);
+        bytes.extend(ifd);
+        bytes.extend(extra);
+        bytes
+    }
+
+    fn short(x: u16) -> (u16, u32, Vec<u8>) {
+        (3, 1, x.to_le_bytes().to_vec())
+    }
+
+    fn doubles(xs: Vec<f64>) -> (u16, u32, Vec<u8>) {
+        let raw = xs.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect();
+        (12, xs.len() as u32, raw)
+    }
+
+    fn entry(tag: u16, (field_type, count, raw): (u16, u32, Vec<u8>)) -> (u16, u16, u32, Vec<u8>) {
+        (tag, field_type, count, raw)
+    }
+
+    #[test]
+    fn test_read_int16_strip() {
+        let image: Vec<u8> = vec![100_i16, -5, 300, -9999]
+            .into_iter()
+            .flat_map(|x| x.to_le_bytes().to_vec())
+            .collect();
+        let bytes = make_tiff(
+            image,
+            vec![
+                entry(IMAGE_WIDTH, short(2)),
+                entry(IMAGE_LENGTH, short(2)),
+                entry(BITS_PER_SAMPLE, short(16)),
+                entry(COMPRESSION, short(1)),
+                entry(STRIP_OFFSETS, (4, 1, 8_u32.to_le_bytes().to_vec())),
+                entry(ROWS_PER_STRIP, short(2)),
+                entry(STRIP_BYTE_COUNTS, (4, 1, 8_u32.to_le_bytes().to_vec())),
+                entry(SAMPLE_FORMAT, short(2)),
+                entry(MODEL_PIXEL_SCALE, doubles(vec![0.5, 0.25, 0.0])),
+                entry(
+                    MODEL_TIEPOINT,
+                    doubles(vec![0.0, 0.0, 0.0, -122.0, 48.0, 0.0]),
+                ),
+                entry(GDAL_NODATA, (2, 6, b"-9999\0".to_vec())),
+            ],
+        );
+
+        let raster = read(&bytes).unwrap();
+        assert_eq!(raster.width, 2);
+        assert_eq!(raster.height, 2);
+        assert_eq!(raster.samples, vec![100.0, -5.0, 300.0, -9999.0]);
+        assert_eq!(raster.no_data, Some(-9999.0));
+        // The tiepoint is the corner of the top-left pixel
+        assert_eq!(raster.origin_x, -121.75);
+        assert_eq!(raster.origin_y, 47.875);
+        assert!(!raster.projected);
+    }
+
+    #[test]
+    fn test_bad_input() {
+        assert!(read(b"").is_err());
+        assert!(read(b"GIF89a").is_err());
+        // BigTIFF
+        assert!(read(&[b'I', b'I', 43, 0, 8, 0, 0, 0]).is_err());
+        // The IFD is past the end
+        assert!(read(&[b'I', b'I', 42, 0, 100, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_lzw() {
+        // CLEAR, 'A', 'B', END as 9-bit codes
+        assert_eq!(
+            decode_lzw(&[0x80, 0x10, 0x48, 0x50, 0x10]).unwrap(),
+            b"AB".to_vec()
+        );
+        // CLEAR, 'A', then the code being defined ("AA"), END
+        assert_eq!(
+            decode_lzw(&[0x80, 0x10, 0x60, 0x50, 0x10]).unwrap(),
+            b"AAA".to_vec()
+        );
+        // A code that's never been defined
+        assert!(decode_lzw(&[0x80, 0x10, 0x65, 0x90, 0x10]).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 mod clip;
+mod geotiff;
 mod osm_reader;
 mod split_ways;
 mod srtm;
@@ -8,9 +9,12 @@ use geom::{Distance, FindClosest, PolyLine, Pt2D};
 use kml::ExtraShapes;
 use map_model::osm;
 use map_model::raw::{DrivingSide, OriginalBuilding, OriginalRoad, RawMap};
+use std::collections::BTreeMap;
 
 // Just used for matching hints to different sides of a road.
 const DIRECTED_ROAD_THICKNESS: Distance = Distance::const_meters(2.5);
+// Curb ramps for crossing a road are somewhere along this much of each end
+const CROSSING_SEARCH_LENGTH: Distance = Distance::const_meters(20.0);
 
 pub struct Options {
     pub osm_input: String,
@@ -21,6 +25,8 @@ pub struct Options {
     pub public_offstreet_parking: Option<String>,
     pub private_offstreet_parking: PrivateOffstreetParking,
     pub sidewalks: Option<String>,
+    // Points where curb ramps exist. Without this, every crossing is assumed to have them.
+    pub curb_ramps: Option<String>,
     pub gtfs: Option<String>,
     pub elevation: Option<String>,
     pub clip: Option<String>,
@@ -68,6 +74,9 @@ pub fn convert(opts: Options, timer: &mut abstutil::Timer) -> RawMap {
     if let Some(ref path) = opts.sidewalks {
         use_sidewalk_hints(&mut map, path.clone(), timer);
     }
+    if let Some(ref path) = opts.curb_ramps {
+        use_curb_ramps(&mut map, path.clone(), timer);
+    }
     if let Some(ref path) = opts.gtfs {
         timer.start("load GTFS");
         map.bus_routes = gtfs::load(path);
@@ -319,6 +328,67 @@ fn use_sidewalk_hints(map: &mut RawMap, path: String, timer: &mut Timer) {
     timer.stop("apply sidewalk hints");
 }
 
+fn use_curb_ramps(map: &mut RawMap, path: String, timer: &mut Timer) {
+    timer.start("match curb ramps to crossings");
+    let shapes: ExtraShapes = abstutil::read_binary(path, timer);
+
+    // Each end of each road, true for the start
+    let mut closest: FindClosest<(OriginalRoad, bool)> =
+        FindClosest::new(&map.gps_bounds.to_bounds());
+    for (id, r) in &map.roads {
+        let center = PolyLine::new(r.center_points.clone());
+        let len = center.length().min(CROSSING_SEARCH_LENGTH);
+        closest.add(
+            (*id, true),
+            center.exact_slice(Distance::ZERO, len).points(),
+        );
+        closest.add(
+            (*id, false),
+            center
+                .exact_slice(center.length() - len, center.length())
+                .points(),
+        );
+    }
+
+    // A ramp at the corner might serve crossings over both roads there, so count it for every
+    // road end nearby.
+    let mut ramps: BTreeMap<(OriginalRoad, bool), usize> = BTreeMap::new();
+    for s in shapes.shapes {
+        if let Some(pt) = s
+            .points
+            .get(0)
+            .and_then(|pt| Pt2D::from_gps(*pt, &map.gps_bounds))
+        {
+            for (key, _, _) in closest.all_close_pts(pt, DIRECTED_ROAD_THICKNESS * 4.0) {
+                *ramps.entry(key).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // Both sides of the road need a ramp
+    let mut missing = 0;
+    for (id, r) in map.roads.iter_mut() {
+        let start = ramps.get(&(*id, true)).cloned().unwrap_or(0) < 2;
+        let end = ramps.get(&(*id, false)).cloned().unwrap_or(0) < 2;
+        let value = match (start, end) {
+            (true, true) => "both",
+            (true, false) => "start",
+            (false, true) => "end",
+            (false, false) => {
+                continue;
+            }
+        };
+        r.osm_tags
+            .insert(osm::MISSING_CURB_RAMPS.to_string(), value.to_string());
+        missing += 1;
+    }
+    timer.note(format!(
+        "{} roads have crossings missing curb ramps",
+        missing
+    ));
+    timer.stop("match curb ramps to crossings");
+}
+
 fn use_amenities(map: &mut RawMap, amenities: Vec<(Pt2D, String, String)>, timer: &mut Timer) {
     let mut closest: FindClosest<OriginalBuilding> = FindClosest::new(&map.gps_bounds.to_bounds());
     for (id, b) in &map.buildings {
@@ -340,8 +410,19 @@ fn use_amenities(map: &mut RawMap, amenities: Vec<(Pt2D, String, String)>, timer
 fn use_elevation(map: &mut RawMap, path: &str, timer: &mut Timer) {
     timer.start("apply elevation data to intersections");
     let elevation = srtm::Elevation::load(path).unwrap();
+    let mut missing = 0;
     for i in map.intersections.values_mut() {
-        i.elevation = elevation.get(i.point.forcibly_to_gps(&map.gps_bounds));
+        if let Some(e) = elevation.get(i.point.forcibly_to_gps(&map.gps_bounds)) {
+            i.elevation = e;
+        } else {
+            missing += 1;
+        }
+    }
+    if missing > 0 {
+        timer.warn(format!(
+            "{} intersections aren't covered by {}; leaving their elevation at 0",
+            missing, path
+        ));
     }
     timer.stop("apply elevation data to intersections");
 }
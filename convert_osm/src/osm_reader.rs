@@ -219,6 +219,7 @@ pub fn extract_osm(
             map.parking_lots.push(RawParkingLot {
                 polygon: Polygon::new(&pts),
                 osm_id: way.id,
+                capacity: tags.get("capacity").and_then(|x| x.parse::<usize>().ok()),
             });
         } else if tags.get("highway") == Some(&"service".to_string()) {
             map.parking_aisles.push(pts);
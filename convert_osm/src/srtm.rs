@@ -1,47 +1,381 @@
+use crate::geotiff;
 use byteorder::{BigEndian, ReadBytesExt};
 use geom::{Distance, LonLat};
 use std::fs::File;
-use std::io;
-
-// TODO Make sure this actually works, and link to references describing the format.
-// - It's VERY wrong! Look at 19th and Boyer, and Delmar.
-// TODO Use http://gis.ess.washington.edu/data/raster/tenmeter/byquad/seattle/index.html or
-// something else instead?
-
-// Assuming the 3-second arcs
-const GRID_DIM: usize = 1201;
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::path::Path;
 
+// A digital elevation model: a regular grid of samples covering a lon/lat rectangle. Three formats
+// are understood:
+//
+// - SRTM .hgt tiles (https://dds.cr.usgs.gov/srtm/version2_1/Documentation/SRTM_Topo.pdf). The
+//   filename names the southwest corner, like N47W122.hgt. Both the 1 and 3 arc-second variants
+//   work; the resolution is inferred from the file size.
+// - ESRI ASCII grids (.asc).
+// - Single-band GeoTIFFs (.tif), like the 10m Puget Sound DEMs. See geotiff.rs for what's
+//   supported.
+//
+// Grids besides .hgt must use WGS84 lon/lat.
 pub struct Elevation {
-    lon_offset: f64,
-    lat_offset: f64,
-    data: Vec<i16>,
+    // The center of the southwest cell
+    min_lon: f64,
+    min_lat: f64,
+    // In degrees
+    cell_width: f64,
+    cell_height: f64,
+    cols: usize,
+    rows: usize,
+    // Row-major, starting from the northernmost row. None means the source has no data there.
+    data: Vec<Option<f64>>,
 }
 
 impl Elevation {
-    pub fn load(path: &str) -> Result<Elevation, io::Error> {
+    pub fn load(path: &str) -> Result<Elevation, Error> {
         println!("Reading elevation data from {}", path);
-        let mut f = File::open(path).unwrap();
-
-        let mut e = Elevation {
-            // TODO dont hardcode
-            lon_offset: -122.0,
-            lat_offset: 47.0,
-            data: Vec::with_capacity(GRID_DIM.pow(2)),
-        };
-        // TODO off by one?
-        for _ in 0..GRID_DIM.pow(2) {
-            e.data.push(f.read_i16::<BigEndian>().unwrap());
-        }
-        Ok(e)
-    }
-
-    pub fn get(&self, pt: LonLat) -> Distance {
-        // TODO assert the (lon, lat) match the offsets
-        // TODO not tons of confidence in any of this.
-        // TODO interpolate from the 4 matching tiles?
-        let x = ((pt.x() - self.lon_offset).abs() * (GRID_DIM as f64)) as usize;
-        let y = ((pt.y() - self.lat_offset).abs() * (GRID_DIM as f64)) as usize;
-        let i = x + (y * GRID_DIM);
-        Distance::meters(f64::from(self.data[i]))
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_lowercase());
+        match ext.as_ref().map(|x| x.as_str()) {
+            Some("hgt") => Elevation::load_hgt(path),
+            Some("asc") => Elevation::load_ascii_grid(path),
+            Some("tif") | Some("tiff") => Elevation::load_geotiff(path),
+            _ => Err(Error::new(
+                ErrorKind::Other,
+                format!("{} isn't a .hgt, .asc, or .tif file", path),
+            )),
+        }
+    }
+
+    fn load_hgt(path: &str) -> Result<Elevation, Error> {
+        let (min_lon, min_lat) = Path::new(path)
+            .file_stem()
+            .and_then(|x| x.to_str())
+            .and_then(parse_hgt_name)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Other,
+                    format!("{} isn't named like N47W122.hgt", path),
+                )
+            })?;
+
+        let bytes = std::fs::read(path)?;
+        Elevation::from_hgt(min_lon, min_lat, &bytes)
+            .map_err(|msg| Error::new(ErrorKind::Other, format!("{}: {}", path, msg)))
+    }
+
+    fn from_hgt(min_lon: f64, min_lat: f64, mut bytes: &[u8]) -> Result<Elevation, String> {
+        let num_samples = bytes.len() / 2;
+        let dim = (num_samples as f64).sqrt() as usize;
+        if dim * dim != num_samples || dim < 2 || bytes.len() % 2 != 0 {
+            return Err("doesn't contain a square grid".to_string());
+        }
+
+        let mut data = Vec::with_capacity(num_samples);
+        for _ in 0..num_samples {
+            let x = bytes
+                .read_i16::<BigEndian>()
+                .map_err(|err| err.to_string())?;
+            // Voids are marked like this
+            data.push(if x == -32768 {
+                None
+            } else {
+                Some(f64::from(x))
+            });
+        }
+        // Neighboring tiles overlap by one row and column, so the edges land exactly on whole
+        // degrees.
+        Ok(Elevation {
+            min_lon,
+            min_lat,
+            cell_width: 1.0 / ((dim - 1) as f64),
+            cell_height: 1.0 / ((dim - 1) as f64),
+            cols: dim,
+            rows: dim,
+            data,
+        })
+    }
+
+    fn load_ascii_grid(path: &str) -> Result<Elevation, Error> {
+        Elevation::from_ascii_grid(BufReader::new(File::open(path)?))
+            .map_err(|msg| Error::new(ErrorKind::Other, format!("{}: {}", path, msg)))
+    }
+
+    fn from_ascii_grid<R: BufRead>(reader: R) -> Result<Elevation, String> {
+        let mut ncols = None;
+        let mut nrows = None;
+        let mut x = None;
+        let mut y = None;
+        let mut corner = true;
+        let mut cell_size = None;
+        let mut no_data = None;
+        let mut data = Vec::new();
+        for line in reader.lines() {
+            let line = line.map_err(|err| err.to_string())?;
+            let mut parts = line.split_whitespace().peekable();
+            let key = match parts.peek() {
+                Some(key) => key.to_lowercase(),
+                None => {
+                    continue;
+                }
+            };
+            if key.starts_with(|c: char| c.is_alphabetic()) {
+                let value = parts
+                    .nth(1)
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .ok_or_else(|| format!("bad header line {}", line))?;
+                match key.as_ref() {
+                    "ncols" => {
+                        ncols = Some(value as usize);
+                    }
+                    "nrows" => {
+                        nrows = Some(value as usize);
+                    }
+                    "xllcorner" | "xllcenter" => {
+                        corner = key == "xllcorner";
+                        x = Some(value);
+                    }
+                    "yllcorner" | "yllcenter" => {
+                        y = Some(value);
+                    }
+                    "cellsize" => {
+                        cell_size = Some(value);
+                    }
+                    "nodata_value" => {
+                        no_data = Some(value);
+                    }
+                    _ => {
+                        return Err(format!("unknown header {}", key));
+                    }
+                }
+                continue;
+            }
+            for v in parts {
+                let v = v.parse::<f64>().map_err(|_| format!("bad value {}", v))?;
+                data.push(if Some(v) == no_data { None } else { Some(v) });
+            }
+        }
+
+        let missing = |name: &str| format!("no {} in the header", name);
+        let cols = ncols.ok_or_else(|| missing("ncols"))?;
+        let rows = nrows.ok_or_else(|| missing("nrows"))?;
+        let cell_size = cell_size.ok_or_else(|| missing("cellsize"))?;
+        let mut min_lon = x.ok_or_else(|| missing("xllcorner"))?;
+        let mut min_lat = y.ok_or_else(|| missing("yllcorner"))?;
+        if corner {
+            min_lon += cell_size / 2.0;
+            min_lat += cell_size / 2.0;
+        }
+        if data.len() != cols * rows {
+            return Err(format!(
+                "expected {}x{} values, but got {}",
+                cols,
+                rows,
+                data.len()
+            ));
+        }
+        check_lon_lat(min_lon, min_lat)?;
+
+        Ok(Elevation {
+            min_lon,
+            min_lat,
+            cell_width: cell_size,
+            cell_height: cell_size,
+            cols,
+            rows,
+            data,
+        })
+    }
+
+    fn load_geotiff(path: &str) -> Result<Elevation, Error> {
+        let bytes = std::fs::read(path)?;
+        Elevation::from_geotiff(&bytes)
+            .map_err(|msg| Error::new(ErrorKind::Other, format!("{}: {}", path, msg)))
+    }
+
+    fn from_geotiff(bytes: &[u8]) -> Result<Elevation, String> {
+        let raster = geotiff::read(bytes)?;
+        if raster.projected {
+            return Err("the raster isn't lon/lat. Reproject to WGS84 first.".to_string());
+        }
+        if raster.width < 2 || raster.height < 2 {
+            return Err("the raster needs at least 2x2 samples".to_string());
+        }
+        if !(raster.pixel_width > 0.0 && raster.pixel_height > 0.0) {
+            return Err("the raster must be oriented north-up".to_string());
+        }
+        let min_lon = raster.origin_x;
+        let min_lat = raster.origin_y - raster.pixel_height * ((raster.height - 1) as f64);
+        check_lon_lat(min_lon, min_lat)?;
+
+        let no_data = raster.no_data;
+        Ok(Elevation {
+            min_lon,
+            min_lat,
+            cell_width: raster.pixel_width,
+            cell_height: raster.pixel_height,
+            cols: raster.width,
+            rows: raster.height,
+            data: raster
+                .samples
+                .into_iter()
+                .map(|x| {
+                    if Some(x) == no_data || !x.is_finite() {
+                        None
+                    } else {
+                        Some(x)
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    // Bilinearly interpolates between the 4 closest samples, ignoring any voids. None if the point
+    // is outside the grid or only surrounded by voids.
+    pub fn get(&self, pt: LonLat) -> Option<Distance> {
+        let col = (pt.x() - self.min_lon) / self.cell_width;
+        // Counting up from the south
+        let row = (pt.y() - self.min_lat) / self.cell_height;
+        let max_col = (self.cols - 1) as f64;
+        let max_row = (self.rows - 1) as f64;
+        if col < 0.0 || row < 0.0 || col > max_col || row > max_row {
+            return None;
+        }
+
+        let col0 = col.floor().min(max_col - 1.0).max(0.0);
+        let row0 = row.floor().min(max_row - 1.0).max(0.0);
+        let dx = col - col0;
+        let dy = row - row0;
+
+        let mut total = 0.0;
+        let mut total_weight = 0.0;
+        for (c, r, weight) in vec![
+            (col0, row0, (1.0 - dx) * (1.0 - dy)),
+            (col0 + 1.0, row0, dx * (1.0 - dy)),
+            (col0, row0 + 1.0, (1.0 - dx) * dy),
+            (col0 + 1.0, row0 + 1.0, dx * dy),
+        ] {
+            let c = (c as usize).min(self.cols - 1);
+            let r = (r as usize).min(self.rows - 1);
+            let idx = (self.rows - 1 - r) * self.cols + c;
+            if let Some(value) = self.data[idx] {
+                total += weight * value;
+                total_weight += weight;
+            }
+        }
+        if total_weight == 0.0 {
+            return None;
+        }
+        Some(Distance::meters(total / total_weight))
+    }
+}
+
+fn check_lon_lat(min_lon: f64, min_lat: f64) -> Result<(), String> {
+    if min_lon < -180.0 || min_lon > 180.0 || min_lat < -90.0 || min_lat > 90.0 {
+        return Err(format!(
+            "the southwest corner ({}, {}) isn't lon/lat. Reproject to WGS84 first.",
+            min_lon, min_lat
+        ));
+    }
+    Ok(())
+}
+
+// N47W122 -> (-122, 47)
+fn parse_hgt_name(name: &str) -> Option<(f64, f64)> {
+    let name = name.to_uppercase();
+    // Slicing by bytes below is only safe for ASCII
+    if !name.is_ascii() || name.len() != 7 {
+        return None;
+    }
+    let lat = name[1..3].parse::<f64>().ok()?;
+    let lon = name[4..7].parse::<f64>().ok()?;
+    let lat = match &name[0..1] {
+        "N" => lat,
+        "S" => -lat,
+        _ => {
+            return None;
+        }
+    };
+    let lon = match &name[3..4] {
+        "E" => lon,
+        "W" => -lon,
+        _ => {
+            return None;
+        }
+    };
+    Some((lon, lat))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hgt_name() {
+        assert_eq!(parse_hgt_name("N47W122"), Some((-122.0, 47.0)));
+        assert_eq!(parse_hgt_name("s01e003"), Some((3.0, -1.0)));
+        assert_eq!(parse_hgt_name("N47W12"), None);
+        assert_eq!(parse_hgt_name("N47W1222"), None);
+        assert_eq!(parse_hgt_name("X47W122"), None);
+        assert_eq!(parse_hgt_name("N47X122"), None);
+        assert_eq!(parse_hgt_name("NabWxyz"), None);
+        assert_eq!(parse_hgt_name(""), None);
+        // 7 bytes, but a character boundary falls inside the latitude
+        assert_eq!(parse_hgt_name("N4\u{e9}W12"), None);
+    }
+
+    #[test]
+    fn test_hgt_samples() {
+        // North row first: 100, 200, then 300 and a void
+        let bytes = vec![0, 100, 0, 200, 1, 44, 0x80, 0];
+        let elevation = Elevation::from_hgt(-122.0, 47.0, &bytes).unwrap();
+        assert_eq!(
+            elevation.data,
+            vec![Some(100.0), Some(200.0), Some(300.0), None]
+        );
+
+        let get = |lon, lat| elevation.get(LonLat::new(lon, lat));
+        assert_eq!(get(-122.0, 47.0), Some(Distance::meters(300.0)));
+        assert_eq!(get(-121.0, 48.0), Some(Distance::meters(200.0)));
+        assert_eq!(get(-122.0, 48.0), Some(Distance::meters(100.0)));
+        // The void is skipped
+        assert_eq!(get(-121.5, 47.5), Some(Distance::meters(200.0)));
+        assert_eq!(get(-121.0, 47.0), None);
+        assert_eq!(get(-122.5, 47.5), None);
+
+        // Not square
+        assert!(Elevation::from_hgt(-122.0, 47.0, &[0, 1, 0, 2, 0, 3]).is_err());
+        // Too small
+        assert!(Elevation::from_hgt(-122.0, 47.0, &[0, 1]).is_err());
+        // A stray byte
+        assert!(Elevation::from_hgt(-122.0, 47.0, &[0, 1, 0, 2, 0, 3, 0, 4, 0]).is_err());
+    }
+
+    #[test]
+    fn test_ascii_grid() {
+        let grid = "ncols 2\nnrows 2\nxllcorner -122\nyllcorner 47\ncellsize 0.5\n\
+                    NODATA_value -9999\n10 20\n-9999 40\n";
+        let elevation = Elevation::from_ascii_grid(grid.as_bytes()).unwrap();
+        assert_eq!(elevation.min_lon, -121.75);
+        assert_eq!(elevation.min_lat, 47.25);
+        assert_eq!(
+            elevation.data,
+            vec![Some(10.0), Some(20.0), None, Some(40.0)]
+        );
+        assert_eq!(
+            elevation.get(LonLat::new(-121.25, 47.75)),
+            Some(Distance::meters(20.0))
+        );
+
+        assert!(Elevation::from_ascii_grid("ncols 2\nnrows 2\n1 2 3 4\n".as_bytes()).is_err());
+        assert!(Elevation::from_ascii_grid(
+            "ncols 1\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\nabc\n".as_bytes()
+        )
+        .is_err());
+        // Projected coordinates
+        assert!(Elevation::from_ascii_grid(
+            "ncols 1\nnrows 1\nxllcorner 550000\nyllcorner 5270000\ncellsize 10\n1\n".as_bytes()
+        )
+        .is_err());
     }
 }
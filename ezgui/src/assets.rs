@@ -1,4 +1,4 @@
-use crate::text::Font;
+use crate::text::{Font, LineKey};
 use crate::{text, GeomBatch};
 use geom::Bounds;
 use lru::LruCache;
@@ -12,6 +12,8 @@ pub struct Assets {
     pub default_font_size: RefCell<usize>,
     pub scale_factor: RefCell<f64>,
     text_cache: RefCell<LruCache<String, GeomBatch>>,
+    // Individual lines, so a Text that changes by one line doesn't have to reshape the rest
+    line_cache: RefCell<LruCache<LineKey, GeomBatch>>,
     line_height_cache: RefCell<HashMap<(Font, usize), f64>>,
     // Keyed by filename, then scale factor mangled into a hashable form. Tuple doesn't work
     // because of borrowing.
@@ -26,6 +28,7 @@ impl Assets {
             default_font_size: RefCell::new(default_font_size),
             scale_factor: RefCell::new(scale_factor),
             text_cache: RefCell::new(LruCache::new(500)),
+            line_cache: RefCell::new(LruCache::new(1000)),
             line_height_cache: RefCell::new(HashMap::new()),
             svg_cache: RefCell::new(HashMap::new()),
             text_opts: Options::default(),
@@ -81,6 +84,13 @@ impl Assets {
         self.text_cache.borrow_mut().put(key, geom);
     }
 
+    pub fn get_cached_line(&self, key: &LineKey) -> Option<GeomBatch> {
+        self.line_cache.borrow_mut().get(key).cloned()
+    }
+    pub fn cache_line(&self, key: LineKey, geom: GeomBatch) {
+        self.line_cache.borrow_mut().put(key, geom);
+    }
+
     pub fn get_cached_svg(&self, key: &str, scale_factor: f64) -> Option<(GeomBatch, Bounds)> {
         self.svg_cache
             .borrow()
@@ -98,6 +108,7 @@ impl Assets {
     pub fn set_scale_factor(&self, scale_factor: f64) {
         *self.scale_factor.borrow_mut() = scale_factor;
         self.text_cache.borrow_mut().clear();
+        self.line_cache.borrow_mut().clear();
         self.line_height_cache.borrow_mut().clear();
         *self.default_line_height.borrow_mut() =
             self.line_height(text::DEFAULT_FONT, *self.default_font_size.borrow());
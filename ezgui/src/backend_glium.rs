@@ -1,4 +1,4 @@
-use crate::drawing::Uniforms;
+use crate::drawing::{Instance, Uniforms};
 use crate::{Canvas, Color, FancyColor, ScreenDims, ScreenRectangle};
 use geom::Polygon;
 use glium::uniforms::UniformValue;
@@ -51,22 +51,12 @@ pub fn setup(
         (&vert, &frag)
     };*/
 
-    let program = glium::Program::new(
+    let program = compile(&display, vertex_shader, fragment_shader);
+    let instanced_program = compile(
         &display,
-        glium::program::ProgramCreationInput::SourceCode {
-            vertex_shader,
-            tessellation_control_shader: None,
-            tessellation_evaluation_shader: None,
-            geometry_shader: None,
-            fragment_shader,
-            transform_feedback_varyings: None,
-            // Without this, SRGB gets enabled and post-processes the color from the fragment
-            // shader.
-            outputs_srgb: true,
-            uses_point_size: false,
-        },
-    )
-    .unwrap();
+        include_str!("shaders/vertex_instanced_140.glsl"),
+        fragment_shader,
+    );
 
     let window_size = display.gl_window().window().inner_size();
     println!(
@@ -79,6 +69,7 @@ pub fn setup(
         PrerenderInnards {
             display,
             program,
+            instanced_program,
             total_bytes_uploaded: Cell::new(0),
         },
         event_loop,
@@ -86,6 +77,25 @@ pub fn setup(
     )
 }
 
+fn compile(display: &glium::Display, vertex_shader: &str, fragment_shader: &str) -> glium::Program {
+    glium::Program::new(
+        display,
+        glium::program::ProgramCreationInput::SourceCode {
+            vertex_shader,
+            tessellation_control_shader: None,
+            tessellation_evaluation_shader: None,
+            geometry_shader: None,
+            fragment_shader,
+            transform_feedback_varyings: None,
+            // Without this, SRGB gets enabled and post-processes the color from the fragment
+            // shader.
+            outputs_srgb: true,
+            uses_point_size: false,
+        },
+    )
+    .unwrap()
+}
+
 struct InnerUniforms<'a> {
     values: &'a Uniforms,
 }
@@ -94,6 +104,7 @@ impl<'b> glium::uniforms::Uniforms for InnerUniforms<'b> {
     fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
         output("transform", UniformValue::Vec3(self.values.transform));
         output("window", UniformValue::Vec3(self.values.window));
+        output("opacity", UniformValue::Float(self.values.opacity));
     }
 }
 
@@ -123,6 +134,32 @@ impl<'a> GfxCtxInnards<'a> {
             .unwrap();
     }
 
+    pub fn redraw_instanced(
+        &mut self,
+        mesh: &Drawable,
+        instances: &[Instance],
+        uniforms: &Uniforms,
+        prerender: &PrerenderInnards,
+    ) {
+        let per_instance: Vec<InstanceAttrs> = instances
+            .iter()
+            .map(|i| InstanceAttrs {
+                instance_transform: i.transform(),
+                instance_color: i.rgba(),
+            })
+            .collect();
+        let instance_buffer = glium::VertexBuffer::new(&prerender.display, &per_instance).unwrap();
+        self.target
+            .draw(
+                (&mesh.vertex_buffer, instance_buffer.per_instance().unwrap()),
+                &mesh.index_buffer,
+                &prerender.instanced_program,
+                &InnerUniforms { values: uniforms },
+                &self.params,
+            )
+            .unwrap();
+    }
+
     pub fn enable_clipping(&mut self, rect: ScreenRectangle, canvas: &Canvas) {
         assert!(self.params.scissor.is_none());
         // The scissor rectangle has to be in device coordinates, so you would think some transform
@@ -171,9 +208,21 @@ struct Vertex {
 
 glium::implement_vertex!(Vertex, position, style);
 
+#[derive(Copy, Clone)]
+struct InstanceAttrs {
+    // (x, y, angle in radians, scale)
+    instance_transform: [f32; 4],
+    // RGBA
+    instance_color: [f32; 4],
+}
+
+glium::implement_vertex!(InstanceAttrs, instance_transform, instance_color);
+
 pub struct PrerenderInnards {
     display: glium::Display,
     program: glium::Program,
+    // Draws copies of one mesh, each with its own transform and color
+    instanced_program: glium::Program,
 
     // TODO Prerender doesn't know what things are temporary and permanent. Could make the API more
     // detailed.
@@ -274,4 +323,11 @@ impl PrerenderInnards {
     pub fn monitor_scale_factor(&self) -> f64 {
         self.display.gl_window().window().scale_factor()
     }
+
+    // The last frame drawn, right side up
+    pub fn read_front_buffer(&self) -> Option<image::RgbaImage> {
+        let raw: glium::texture::RawImage2d<u8> = self.display.read_front_buffer().ok()?;
+        let img = image::RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned())?;
+        Some(image::imageops::flip_vertical(&img))
+    }
 }
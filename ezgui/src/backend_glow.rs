@@ -1,4 +1,4 @@
-use crate::drawing::Uniforms;
+use crate::drawing::{Instance, Uniforms};
 use crate::{Canvas, Color, FancyColor, ScreenDims, ScreenRectangle};
 use geom::Polygon;
 use glow::HasContext;
@@ -24,38 +24,24 @@ pub fn setup(
     let windowed_context = unsafe { context.make_current().unwrap() };
     let gl =
         glow::Context::from_loader_function(|s| windowed_context.get_proc_address(s) as *const _);
-    let program = unsafe { gl.create_program().expect("Cannot create program") };
+    let program = unsafe {
+        compile(
+            &gl,
+            include_str!("shaders/vertex_140.glsl"),
+            include_str!("shaders/fragment_140.glsl"),
+        )
+    };
+    // Like the main program, this relies on attributes getting locations in the order the shader
+    // declares them.
+    let instanced_program = unsafe {
+        compile(
+            &gl,
+            include_str!("shaders/vertex_instanced_140.glsl"),
+            include_str!("shaders/fragment_140.glsl"),
+        )
+    };
 
     unsafe {
-        let shaders = [
-            (glow::VERTEX_SHADER, include_str!("shaders/vertex_140.glsl")),
-            (
-                glow::FRAGMENT_SHADER,
-                include_str!("shaders/fragment_140.glsl"),
-            ),
-        ]
-        .iter()
-        .map(|(shader_type, source)| {
-            let shader = gl
-                .create_shader(*shader_type)
-                .expect("Cannot create shader");
-            gl.shader_source(shader, source);
-            gl.compile_shader(shader);
-            if !gl.get_shader_compile_status(shader) {
-                panic!(gl.get_shader_info_log(shader));
-            }
-            gl.attach_shader(program, shader);
-            shader
-        })
-        .collect::<Vec<_>>();
-        gl.link_program(program);
-        if !gl.get_program_link_status(program) {
-            panic!(gl.get_program_info_log(program));
-        }
-        for shader in shaders {
-            gl.detach_shader(program, shader);
-            gl.delete_shader(shader);
-        }
         gl.use_program(Some(program));
 
         gl.enable(glow::SCISSOR_TEST);
@@ -84,6 +70,7 @@ pub fn setup(
         PrerenderInnards {
             gl,
             program,
+            instanced_program,
             windowed_context,
             total_bytes_uploaded: Cell::new(0),
         },
@@ -92,11 +79,47 @@ pub fn setup(
     )
 }
 
+unsafe fn compile(
+    gl: &glow::Context,
+    vertex_shader: &str,
+    fragment_shader: &str,
+) -> <glow::Context as HasContext>::Program {
+    let program = gl.create_program().expect("Cannot create program");
+    let shaders = [
+        (glow::VERTEX_SHADER, vertex_shader),
+        (glow::FRAGMENT_SHADER, fragment_shader),
+    ]
+    .iter()
+    .map(|(shader_type, source)| {
+        let shader = gl
+            .create_shader(*shader_type)
+            .expect("Cannot create shader");
+        gl.shader_source(shader, source);
+        gl.compile_shader(shader);
+        if !gl.get_shader_compile_status(shader) {
+            panic!(gl.get_shader_info_log(shader));
+        }
+        gl.attach_shader(program, shader);
+        shader
+    })
+    .collect::<Vec<_>>();
+    gl.link_program(program);
+    if !gl.get_program_link_status(program) {
+        panic!(gl.get_program_info_log(program));
+    }
+    for shader in shaders {
+        gl.detach_shader(program, shader);
+        gl.delete_shader(shader);
+    }
+    program
+}
+
 // Represents one frame that's gonna be drawn
 pub struct GfxCtxInnards<'a> {
     gl: &'a glow::Context,
     windowed_context: &'a glutin::WindowedContext<glutin::PossiblyCurrent>,
     program: &'a <glow::Context as glow::HasContext>::Program,
+    instanced_program: &'a <glow::Context as glow::HasContext>::Program,
 
     current_clip: Option<[i32; 4]>,
 }
@@ -126,6 +149,11 @@ impl<'a> GfxCtxInnards<'a> {
                 .unwrap();
             self.gl
                 .uniform_3_f32_slice(Some(window_loc), &uniforms.window);
+            let opacity_loc = self
+                .gl
+                .get_uniform_location(*self.program, "opacity")
+                .unwrap();
+            self.gl.uniform_1_f32(Some(opacity_loc), uniforms.opacity);
 
             self.gl.bind_vertex_array(Some(obj.vert_array));
             self.gl
@@ -134,6 +162,86 @@ impl<'a> GfxCtxInnards<'a> {
         }
     }
 
+    pub fn redraw_instanced(
+        &mut self,
+        mesh: &Drawable,
+        instances: &[Instance],
+        uniforms: &Uniforms,
+        _: &PrerenderInnards,
+    ) {
+        let mut per_instance: Vec<[f32; 8]> = Vec::new();
+        for i in instances {
+            let t = i.transform();
+            let c = i.rgba();
+            per_instance.push([t[0], t[1], t[2], t[3], c[0], c[1], c[2], c[3]]);
+        }
+
+        unsafe {
+            self.gl.use_program(Some(*self.instanced_program));
+            let transform_loc = self
+                .gl
+                .get_uniform_location(*self.instanced_program, "transform")
+                .unwrap();
+            self.gl
+                .uniform_3_f32_slice(Some(transform_loc), &uniforms.transform);
+            let window_loc = self
+                .gl
+                .get_uniform_location(*self.instanced_program, "window")
+                .unwrap();
+            self.gl
+                .uniform_3_f32_slice(Some(window_loc), &uniforms.window);
+            let opacity_loc = self
+                .gl
+                .get_uniform_location(*self.instanced_program, "opacity")
+                .unwrap();
+            self.gl.uniform_1_f32(Some(opacity_loc), uniforms.opacity);
+
+            self.gl.bind_vertex_array(Some(mesh.vert_array));
+            let instance_buffer = self.gl.create_buffer().unwrap();
+            self.gl
+                .bind_buffer(glow::ARRAY_BUFFER, Some(instance_buffer));
+            self.gl.buffer_data_u8_slice(
+                glow::ARRAY_BUFFER,
+                &per_instance.align_to::<u8>().1,
+                glow::STREAM_DRAW,
+            );
+
+            let stride = 8 * std::mem::size_of::<f32>() as i32;
+            // instance_transform is vec4
+            self.gl.enable_vertex_attrib_array(2);
+            self.gl
+                .vertex_attrib_pointer_f32(2, 4, glow::FLOAT, false, stride, 0);
+            self.gl.vertex_attrib_divisor(2, 1);
+            // instance_color is vec4
+            self.gl.enable_vertex_attrib_array(3);
+            self.gl.vertex_attrib_pointer_f32(
+                3,
+                4,
+                glow::FLOAT,
+                false,
+                stride,
+                4 * std::mem::size_of::<f32>() as i32,
+            );
+            self.gl.vertex_attrib_divisor(3, 1);
+
+            self.gl.draw_elements_instanced(
+                glow::TRIANGLES,
+                mesh.num_indices,
+                glow::UNSIGNED_INT,
+                0,
+                instances.len() as i32,
+            );
+
+            // The mesh's vertex array is also used for regular draws, which don't have these.
+            self.gl.disable_vertex_attrib_array(2);
+            self.gl.disable_vertex_attrib_array(3);
+            self.gl.bind_vertex_array(None);
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, None);
+            self.gl.delete_buffer(instance_buffer);
+            self.gl.use_program(Some(*self.program));
+        }
+    }
+
     pub fn enable_clipping(&mut self, rect: ScreenRectangle, canvas: &Canvas) {
         assert!(self.current_clip.is_none());
         // The scissor rectangle has to be in device coordinates, so you would think some transform
@@ -194,6 +302,8 @@ pub struct PrerenderInnards {
     gl: glow::Context,
     windowed_context: glutin::WindowedContext<glutin::PossiblyCurrent>,
     program: <glow::Context as glow::HasContext>::Program,
+    // Draws copies of one mesh, each with its own transform and color
+    instanced_program: <glow::Context as glow::HasContext>::Program,
 
     // TODO Prerender doesn't know what things are temporary and permanent. Could make the API more
     // detailed.
@@ -303,6 +413,7 @@ impl PrerenderInnards {
             gl: &self.gl,
             windowed_context: &self.windowed_context,
             program: &self.program,
+            instanced_program: &self.instanced_program,
             current_clip: None,
         }
     }
@@ -329,4 +440,25 @@ impl PrerenderInnards {
     pub fn monitor_scale_factor(&self) -> f64 {
         self.windowed_context.window().scale_factor()
     }
+
+    // The last frame drawn, right side up
+    pub fn read_front_buffer(&self) -> Option<image::RgbaImage> {
+        let (width, height) = self.get_inner_size();
+        let (width, height) = (width as u32, height as u32);
+        let mut pixels = vec![0; (width * height * 4) as usize];
+        unsafe {
+            self.gl.read_buffer(glow::FRONT);
+            self.gl.read_pixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                &mut pixels,
+            );
+        }
+        let img = image::RgbaImage::from_raw(width, height, pixels)?;
+        Some(image::imageops::flip_vertical(&img))
+    }
 }
@@ -195,6 +195,10 @@ impl Canvas {
         ScreenPt::new(self.cursor_x, self.cursor_y)
     }
 
+    pub fn is_lshift_held(&self) -> bool {
+        self.lshift_held
+    }
+
     pub fn get_cursor_in_screen_space(&self) -> Option<ScreenPt> {
         if self.window_has_cursor && self.get_cursor_in_map_space().is_none() {
             Some(self.get_cursor())
@@ -1,11 +1,12 @@
 use crate::assets::Assets;
 use crate::backend::{GfxCtxInnards, PrerenderInnards};
+use crate::tools::screenshot::Recording;
 use crate::{
     Canvas, Color, Drawable, FancyColor, GeomBatch, ScreenDims, ScreenPt, ScreenRectangle, Style,
     Text,
 };
-use geom::{ArrowCap, Bounds, Circle, Distance, Line, Polygon, Pt2D};
-use std::cell::Cell;
+use geom::{Angle, ArrowCap, Bounds, Circle, Distance, Line, Polygon, Pt2D};
+use std::cell::{Cell, RefCell};
 
 // Lower is more on top
 const MAPSPACE_Z: f32 = 1.0;
@@ -17,6 +18,8 @@ pub struct Uniforms {
     pub transform: [f32; 3],
     // (window_width, window_height, Z values)
     pub window: [f32; 3],
+    // Multiplies the alpha of everything drawn
+    pub opacity: f32,
 }
 
 impl Uniforms {
@@ -32,10 +35,58 @@ impl Uniforms {
                 canvas.window_height as f32,
                 MAPSPACE_Z,
             ],
+            opacity: 1.0,
         }
     }
 }
 
+// One copy of a shared mesh, placed and colored on the GPU. See GfxCtx::draw_instances.
+#[derive(Clone, Copy, Debug)]
+pub struct Instance {
+    // Where the mesh's origin winds up
+    pub pos: Pt2D,
+    // Rotates the mesh around its origin
+    pub angle: Angle,
+    pub scale: f64,
+    // Multiplies the mesh's own colors, so a white mesh becomes exactly this color
+    pub color: Color,
+}
+
+impl Instance {
+    pub fn new(pos: Pt2D, color: Color) -> Instance {
+        Instance {
+            pos,
+            angle: Angle::ZERO,
+            scale: 1.0,
+            color,
+        }
+    }
+
+    pub fn angle(mut self, angle: Angle) -> Instance {
+        self.angle = angle;
+        self
+    }
+
+    pub fn scale(mut self, scale: f64) -> Instance {
+        self.scale = scale;
+        self
+    }
+
+    // (x, y, angle in radians, scale)
+    pub(crate) fn transform(&self) -> [f32; 4] {
+        [
+            self.pos.x() as f32,
+            self.pos.y() as f32,
+            self.angle.normalized_radians() as f32,
+            self.scale as f32,
+        ]
+    }
+
+    pub(crate) fn rgba(&self) -> [f32; 4] {
+        [self.color.r, self.color.g, self.color.b, self.color.a]
+    }
+}
+
 pub struct GfxCtx<'a> {
     pub(crate) inner: GfxCtxInnards<'a>,
     uniforms: Uniforms,
@@ -107,10 +158,17 @@ impl<'a> GfxCtx<'a> {
     }
 
     pub fn unfork(&mut self) {
+        let opacity = self.uniforms.opacity;
         self.uniforms = Uniforms::new(&self.canvas);
+        self.uniforms.opacity = opacity;
         self.num_forks += 1;
     }
 
+    // Fades everything drawn afterwards, across forks. Up to the caller to set it back to 1.0!
+    pub fn set_opacity(&mut self, opacity: f64) {
+        self.uniforms.opacity = opacity as f32;
+    }
+
     pub fn clear(&mut self, color: Color) {
         self.inner.clear(color);
     }
@@ -169,6 +227,18 @@ impl<'a> GfxCtx<'a> {
         // println!("{:?}", backtrace::Backtrace::new());
     }
 
+    // Draws many copies of one mesh in a single draw call, instead of tessellating and uploading
+    // every copy. The mesh is usually uploaded once, centered on the origin; only the instances
+    // are uploaded each time.
+    pub fn draw_instances(&mut self, mesh: &Drawable, instances: &[Instance]) {
+        if instances.is_empty() {
+            return;
+        }
+        self.inner
+            .redraw_instanced(mesh, instances, &self.uniforms, &self.prerender.inner);
+        self.num_draw_calls += 1;
+    }
+
     pub fn redraw_at(&mut self, top_left: ScreenPt, obj: &Drawable) {
         self.fork(Pt2D::new(0.0, 0.0), top_left, 1.0, None);
         self.redraw(obj);
@@ -268,6 +338,7 @@ pub struct Prerender {
     pub(crate) inner: PrerenderInnards,
     pub(crate) assets: Assets,
     pub(crate) num_uploads: Cell<usize>,
+    pub(crate) recording: RefCell<Option<Recording>>,
 }
 
 impl Prerender {
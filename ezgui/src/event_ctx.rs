@@ -1,9 +1,10 @@
+use crate::tools::screenshot::Recording;
 use crate::{
     svg, text, Canvas, Color, Drawable, Event, GeomBatch, GfxCtx, Line, Prerender, ScreenPt, Style,
     Text, UserInput,
 };
 use abstutil::{elapsed_seconds, Timer, TimerSink};
-use geom::Polygon;
+use geom::{Duration, Polygon};
 use instant::Instant;
 use std::collections::VecDeque;
 
@@ -109,6 +110,25 @@ impl<'a> EventCtx<'a> {
             .set_cursor_icon(winit::window::CursorIcon::Hand);
     }
 
+    // Save every frame drawn from now on, at most once per interval, as PNGs in this directory
+    pub fn start_recording_frames(&self, dir: String, interval: Duration) {
+        *self.prerender.recording.borrow_mut() = Some(Recording::new(dir, interval));
+    }
+
+    // Returns how many frames were saved
+    pub fn stop_recording_frames(&self) -> usize {
+        self.prerender
+            .recording
+            .borrow_mut()
+            .take()
+            .map(|r| r.num_frames)
+            .unwrap_or(0)
+    }
+
+    pub fn is_recording_frames(&self) -> bool {
+        self.prerender.recording.borrow().is_some()
+    }
+
     pub fn style(&self) -> &Style {
         &self.style
     }
@@ -131,6 +131,12 @@ impl GeomBatch {
         svg::load_svg(prerender, filename, 1.0).0
     }
 
+    /// Returns a batch containing an SVG from a file. Doesn't need a Prerender, so it works off
+    /// the main thread, but nothing is cached.
+    pub fn mapspace_svg_uncached(filename: &str) -> GeomBatch {
+        svg::load_svg_uncached(filename, 1.0).0
+    }
+
     /// Returns a batch containing an SVG from a file. Uses the current screen's scale factor.
     pub fn screenspace_svg(prerender: &Prerender, filename: &str) -> GeomBatch {
         svg::load_svg(prerender, filename, *prerender.assets.scale_factor.borrow()).0
@@ -47,7 +47,7 @@ mod widgets;
 pub use crate::backend::Drawable;
 pub use crate::canvas::{Canvas, HorizontalAlignment, VerticalAlignment};
 pub use crate::color::{Color, FancyColor, LinearGradient};
-pub use crate::drawing::{GfxCtx, Prerender};
+pub use crate::drawing::{GfxCtx, Instance, Prerender};
 pub use crate::event::{hotkey, hotkeys, lctrl, Event, Key, MultiKey};
 pub use crate::event_ctx::EventCtx;
 pub use crate::geom::{GeomBatch, RewriteColor};
@@ -691,6 +691,10 @@ impl Composite {
     }
 
     pub fn draw(&self, g: &mut GfxCtx) {
+        // Screenshots are of the map; panels would just repeat in every tile
+        if g.is_screencap() {
+            return;
+        }
         if let Some(ref rect) = self.clip_rect {
             g.enable_clipping(rect.clone());
             g.canvas.mark_covered_area(rect.clone());
@@ -1,10 +1,10 @@
 use crate::assets::Assets;
-use crate::tools::screenshot::screenshot_everything;
+use crate::tools::screenshot::{screenshot_current_view, screenshot_everything};
 use crate::{text, Canvas, Event, EventCtx, GfxCtx, Key, Prerender, Style, UserInput};
 use geom::Duration;
 use image::{GenericImageView, Pixel};
 use instant::Instant;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::panic;
 use winit::window::Icon;
 
@@ -29,6 +29,13 @@ pub enum EventLoopMode {
         max_x: f64,
         max_y: f64,
     },
+    // Render the current view at any resolution, without panels, and save it as a PNG. Whether
+    // the event loop keeps running or not is unchanged.
+    ScreenCapture {
+        filename: String,
+        width: u32,
+        height: u32,
+    },
 }
 
 pub(crate) struct State<G: GUI> {
@@ -240,6 +247,7 @@ pub fn run<G: 'static + GUI, F: FnOnce(&mut EventCtx) -> G>(settings: Settings,
                 .unwrap_or_else(|| prerender_innards.monitor_scale_factor()),
         ),
         num_uploads: Cell::new(0),
+        recording: RefCell::new(None),
         inner: prerender_innards,
     };
     let mut style = Style::standard();
@@ -292,6 +300,9 @@ pub fn run<G: 'static + GUI, F: FnOnce(&mut EventCtx) -> G>(settings: Settings,
             winit::event::Event::RedrawRequested(_) => {
                 state.draw(&prerender, false);
                 prerender.num_uploads.set(0);
+                if let Some(ref mut recording) = *prerender.recording.borrow_mut() {
+                    recording.after_draw(&prerender);
+                }
                 return;
             }
             winit::event::Event::MainEventsCleared => {
@@ -344,6 +355,15 @@ pub fn run<G: 'static + GUI, F: FnOnce(&mut EventCtx) -> G>(settings: Settings,
             } => {
                 screenshot_everything(&mut state, &dir, &prerender, zoom, max_x, max_y);
             }
+            EventLoopMode::ScreenCapture {
+                filename,
+                width,
+                height,
+            } => {
+                screenshot_current_view(&mut state, &prerender, &filename, width, height);
+                // Put back what was on the screen before
+                prerender.request_redraw();
+            }
         }
     });
 }
@@ -6,13 +6,15 @@ precision mediump float;
 uniform vec3 transform;
 // (window width, window height, z value)
 uniform vec3 window;
+// Fades everything drawn, for overlays stacked on the map
+uniform float opacity;
 
 layout (location = 0) in vec2 position;
 layout (location = 1) in vec4 style;
 out vec4 pass_style;
 
 void main() {
-    pass_style = style;
+    pass_style = vec4(style.rgb, style[3] * opacity);
 
     // This is map_to_screen
     float screen_x = (position[0] * transform[2]) - transform[0];
@@ -0,0 +1,40 @@
+#version 140
+
+// (x offset, y offset, zoom)
+uniform vec3 transform;
+// (window width, window height, z value)
+uniform vec3 window;
+// Fades everything drawn, for overlays stacked on the map
+uniform float opacity;
+
+// The shared mesh, centered on the origin
+in vec2 position;
+in vec4 style;
+// Per instance: (x, y, angle in radians, scale)
+in vec4 instance_transform;
+// Per instance: multiplies the mesh's own color
+in vec4 instance_color;
+out vec4 pass_style;
+
+void main() {
+    pass_style = style * instance_color;
+    pass_style[3] *= opacity;
+
+    // Scale and rotate the mesh around its origin, then move it into place
+    float c = cos(instance_transform[2]);
+    float s = sin(instance_transform[2]);
+    vec2 pt = instance_transform[3] * vec2(
+        position[0] * c - position[1] * s,
+        position[0] * s + position[1] * c
+    ) + instance_transform.xy;
+
+    // This is map_to_screen
+    float screen_x = (pt[0] * transform[2]) - transform[0];
+    float screen_y = (pt[1] * transform[2]) - transform[1];
+    // Translate that to clip-space or whatever it's called
+    float x = (screen_x / window[0] * 2.0) - 1.0;
+    float y = (screen_y / window[1] * 2.0) - 1.0;
+
+    // Note the y inversion
+    gl_Position = vec4(x, -y, window[2], 1.0);
+}
@@ -17,6 +17,19 @@ pub fn load_svg(prerender: &Prerender, filename: &str, scale_factor: f64) -> (Ge
         return pair;
     }
 
+    let (batch, bounds) = load_svg_uncached(filename, scale_factor);
+    prerender.assets.cache_svg(
+        filename.to_string(),
+        scale_factor,
+        batch.clone(),
+        bounds.clone(),
+    );
+    (batch, bounds)
+}
+
+// Doesn't need a Prerender, so this works off the main thread, but the caller has to hang onto
+// the result.
+pub fn load_svg_uncached(filename: &str, scale_factor: f64) -> (GeomBatch, Bounds) {
     let raw = if let Ok(raw) = abstutil::slurp_file(&filename) {
         raw
     } else {
@@ -25,15 +38,7 @@ pub fn load_svg(prerender: &Prerender, filename: &str, scale_factor: f64) -> (Ge
     let svg_tree = usvg::Tree::from_data(&raw, &usvg::Options::default()).unwrap();
     let mut batch = GeomBatch::new();
     match add_svg_inner(&mut batch, svg_tree, HIGH_QUALITY, scale_factor) {
-        Ok(bounds) => {
-            prerender.assets.cache_svg(
-                filename.to_string(),
-                scale_factor,
-                batch.clone(),
-                bounds.clone(),
-            );
-            (batch, bounds)
-        }
+        Ok(bounds) => (batch, bounds),
         Err(err) => panic!("{}: {}", filename, err),
     }
 }
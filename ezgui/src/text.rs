@@ -371,11 +371,38 @@ impl Text {
     }
 }
 
+// Everything a rendered line depends on: the text, font, size, and color of each span, then the
+// tolerance. Floats are compared bit-for-bit.
+pub(crate) type LineKey = (Vec<(String, Font, usize, [u32; 4])>, u32);
+
 fn render_line(spans: Vec<TextSpan>, tolerance: f32, assets: &Assets) -> GeomBatch {
     // TODO This assumes size and font don't change mid-line. We might be able to support that now,
     // actually.
     // https://www.oreilly.com/library/view/svg-text-layout/9781491933817/ch04.html
 
+    // Shaping and tessellating is the expensive part. Dashboards often change just one line of a
+    // Text, and wrapping measures the same words over and over, so remember every line. Text is
+    // drawn as tessellated outlines, not sampled from a texture, so there's no glyph atlas and
+    // positions are never snapped to pixels anyway. Fonts are still the fixed set above.
+    let key: LineKey = (
+        spans
+            .iter()
+            .map(|span| {
+                let c = span.fg_color;
+                (
+                    span.text.clone(),
+                    span.font,
+                    span.size,
+                    [c.r.to_bits(), c.g.to_bits(), c.b.to_bits(), c.a.to_bits()],
+                )
+            })
+            .collect(),
+        tolerance.to_bits(),
+    );
+    if let Some(batch) = assets.get_cached_line(&key) {
+        return batch;
+    }
+
     // Just set a sufficiently large view box
     let mut svg = r##"<svg width="9999" height="9999" viewBox="0 0 9999 9999" xmlns="http://www.w3.org/2000/svg">"##.to_string();
 
@@ -411,15 +438,16 @@ fn render_line(spans: Vec<TextSpan>, tolerance: f32, assets: &Assets) -> GeomBat
         Err(err) => panic!("render_line({}): {}", contents, err),
     };
     let mut batch = GeomBatch::new();
-    match crate::svg::add_svg_inner(
+    if let Err(err) = crate::svg::add_svg_inner(
         &mut batch,
         svg_tree,
         tolerance,
         *assets.scale_factor.borrow(),
     ) {
-        Ok(_) => batch,
-        Err(err) => panic!("render_line({}): {}", contents, err),
+        panic!("render_line({}): {}", contents, err);
     }
+    assets.cache_line(key, batch.clone());
+    batch
 }
 
 pub trait TextExt {
@@ -1,7 +1,10 @@
 use crate::runner::{State, GUI};
 use crate::Prerender;
 use abstutil::Timer;
+use geom::Duration;
+use instant::Instant;
 use std::io::Write;
+use std::path::Path;
 use std::{fs, process, thread, time};
 
 pub(crate) fn screenshot_everything<G: GUI>(
@@ -52,6 +55,112 @@ pub(crate) fn screenshot_everything<G: GUI>(
     finish(dir_path, filenames, num_tiles_x, num_tiles_y);
 }
 
+// Renders the current view at any resolution, independent of the window size, by drawing
+// window-sized tiles at a proportionally higher zoom and reading each one back from the GPU.
+// Panels don't draw in screencap mode, so only the map winds up in the image.
+pub(crate) fn screenshot_current_view<G: GUI>(
+    state: &mut State<G>,
+    prerender: &Prerender,
+    filename: &str,
+    width: u32,
+    height: u32,
+) {
+    let window_width = state.canvas.window_width;
+    let window_height = state.canvas.window_height;
+    let orig_zoom = state.canvas.cam_zoom;
+    let orig_x = state.canvas.cam_x;
+    let orig_y = state.canvas.cam_y;
+
+    // Keep the same center, and fit the same width of the map into the image
+    let center = state.canvas.center_to_map_pt();
+    let zoom = orig_zoom * (width as f64) / window_width;
+    let x1 = center.x() * zoom - (width as f64) / 2.0;
+    let y1 = center.y() * zoom - (height as f64) / 2.0;
+    let num_tiles_x = ((width as f64) / window_width).ceil() as u32;
+    let num_tiles_y = ((height as f64) / window_height).ceil() as u32;
+
+    let mut timer = Timer::new("capturing screen");
+    timer.start_iter("rendering tiles", (num_tiles_x * num_tiles_y) as usize);
+    let mut image = image::RgbaImage::new(width, height);
+    state.canvas.cam_zoom = zoom;
+    let mut ok = true;
+    'tiles: for tile_y in 0..num_tiles_y {
+        for tile_x in 0..num_tiles_x {
+            timer.next();
+            state.canvas.cam_x = x1 + (tile_x as f64) * window_width;
+            state.canvas.cam_y = y1 + (tile_y as f64) * window_height;
+            state.draw(prerender, true);
+
+            if let Some(tile) = prerender.inner.read_front_buffer() {
+                // Tiles along the right and bottom get clipped
+                image::imageops::replace(
+                    &mut image,
+                    &tile,
+                    tile_x * (window_width as u32),
+                    tile_y * (window_height as u32),
+                );
+            } else {
+                println!("Screencapping isn't supported with this backend");
+                ok = false;
+                break 'tiles;
+            }
+        }
+    }
+
+    state.canvas.cam_zoom = orig_zoom;
+    state.canvas.cam_x = orig_x;
+    state.canvas.cam_y = orig_y;
+    if !ok {
+        return;
+    }
+    if let Some(dir) = Path::new(filename).parent() {
+        fs::create_dir_all(dir).unwrap();
+    }
+    match image.save(filename) {
+        Ok(_) => println!("Saved {}", filename),
+        Err(err) => println!("Couldn't save {}: {}", filename, err),
+    }
+}
+
+// Saves frames as they're drawn, at most once per interval, as numbered PNGs in a directory.
+// Something like ffmpeg can turn them into a video. Panels are included, since these are just
+// whatever's in the window.
+pub(crate) struct Recording {
+    dir: String,
+    interval: Duration,
+    last_frame: Option<Instant>,
+    pub(crate) num_frames: usize,
+}
+
+impl Recording {
+    pub fn new(dir: String, interval: Duration) -> Recording {
+        fs::create_dir_all(&dir).unwrap();
+        Recording {
+            dir,
+            interval,
+            last_frame: None,
+            num_frames: 0,
+        }
+    }
+
+    pub fn after_draw(&mut self, prerender: &Prerender) {
+        if let Some(t) = self.last_frame {
+            if Duration::realtime_elapsed(t) < self.interval {
+                return;
+            }
+        }
+        self.last_frame = Some(Instant::now());
+
+        if let Some(frame) = prerender.inner.read_front_buffer() {
+            self.num_frames += 1;
+            let path = format!("{}/frame_{:05}.png", self.dir, self.num_frames);
+            if let Err(err) = frame.save(&path) {
+                println!("Couldn't save {}: {}", path, err);
+            }
+        }
+    }
+}
+
 fn screencap(filename: &str) -> bool {
     if !process::Command::new("scrot")
         .args(&[
@@ -108,14 +108,18 @@ impl<T: 'static + Clone> WidgetImpl for Autocomplete<T> {
                     // Mutating choices is fine, because we're supposed to be consumed by the
                     // caller immediately after this.
                     if name.starts_with("anything matching") {
+                        // Same fuzzy matching as the menu, so small typos still find things
                         let mut matches = Vec::new();
-                        for (name, choices) in self.choices.drain() {
-                            if name
-                                .to_ascii_lowercase()
-                                .contains(&self.current_line.to_ascii_lowercase())
-                            {
+                        if self.current_line.is_empty() {
+                            for (_, choices) in self.choices.drain() {
                                 matches.extend(choices);
                             }
+                        } else {
+                            for idx in self.search.search(&self.current_line) {
+                                if let Some(choices) = self.choices.remove(&self.search_map[idx]) {
+                                    matches.extend(choices);
+                                }
+                            }
                         }
                         self.chosen_values = Some(matches);
                     } else {
@@ -1,14 +1,17 @@
 use crate::{
-    Btn, Button, Choice, Color, EventCtx, GeomBatch, GfxCtx, InputResult, Menu, ScreenDims,
-    ScreenPt, ScreenRectangle, WidgetImpl, WidgetOutput,
+    Btn, Button, Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, InputResult, Menu,
+    ScreenDims, ScreenPt, ScreenRectangle, WidgetImpl, WidgetOutput,
 };
 use geom::{Distance, Polygon, Pt2D};
 
+const MENU_PAD: f64 = 5.0;
+
 pub struct Dropdown<T: Clone> {
     current_idx: usize,
     btn: Button,
     // TODO Why not T?
-    menu: Option<Menu<usize>>,
+    // The menu and its background
+    menu: Option<(Menu<usize>, Drawable)>,
     label: String,
     blank_btn_label: bool,
 
@@ -70,7 +73,17 @@ impl<T: 'static + Clone> Dropdown<T> {
                 self.btn.top_left.y - 15.0 - menu.get_dims().height
             },
         ));
-        self.menu = Some(menu);
+
+        // We need a background too! Add some padding and an outline.
+        // TODO Little embedded Composite could make more sense?
+        let width = menu.get_dims().width + 2.0 * MENU_PAD;
+        let height = menu.get_dims().height + 2.0 * MENU_PAD;
+        let rect = Polygon::rounded_rectangle(width, height, Some(5.0));
+        let draw_bg = ctx.upload(GeomBatch::from(vec![
+            (Color::grey(0.3), rect.clone()),
+            (Color::WHITE, rect.to_outline(Distance::meters(3.0))),
+        ]));
+        self.menu = Some((menu, draw_bg));
     }
 }
 
@@ -84,7 +97,7 @@ impl<T: 'static + Clone> WidgetImpl for Dropdown<T> {
     }
 
     fn event(&mut self, ctx: &mut EventCtx, output: &mut WidgetOutput) {
-        if let Some(ref mut m) = self.menu {
+        if let Some((ref mut m, _)) = self.menu {
             m.event(ctx, output);
             match m.state {
                 InputResult::StillActive => {}
@@ -115,25 +128,15 @@ impl<T: 'static + Clone> WidgetImpl for Dropdown<T> {
 
     fn draw(&self, g: &mut GfxCtx) {
         self.btn.draw(g);
-        if let Some(ref m) = self.menu {
-            // We need a background too! Add some padding and an outline.
-            // TODO Little embedded Composite could make more sense?
-            let pad = 5.0;
-            let width = m.get_dims().width + 2.0 * pad;
-            let height = m.get_dims().height + 2.0 * pad;
-            let rect = Polygon::rounded_rectangle(width, height, Some(5.0));
-            let draw_bg = g.upload(GeomBatch::from(vec![
-                (Color::grey(0.3), rect.clone()),
-                (Color::WHITE, rect.to_outline(Distance::meters(3.0))),
-            ]));
+        if let Some((ref m, ref draw_bg)) = self.menu {
             g.fork(
                 Pt2D::new(0.0, 0.0),
-                ScreenPt::new(m.top_left.x - pad, m.top_left.y - pad),
+                ScreenPt::new(m.top_left.x - MENU_PAD, m.top_left.y - MENU_PAD),
                 1.0,
                 // Between SCREENSPACE_Z and TOOLTIP_Z
                 Some(0.1),
             );
-            g.redraw(&draw_bg);
+            g.redraw(draw_bg);
             g.unfork();
 
             m.draw(g);
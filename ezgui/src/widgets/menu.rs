@@ -1,6 +1,6 @@
 use crate::{
-    hotkey, text, Choice, EventCtx, GfxCtx, InputResult, Key, Line, ScreenDims, ScreenPt,
-    ScreenRectangle, Text, Widget, WidgetImpl, WidgetOutput,
+    hotkey, text, Choice, Drawable, EventCtx, GeomBatch, GfxCtx, InputResult, Key, Line,
+    ScreenDims, ScreenPt, ScreenRectangle, Text, Widget, WidgetImpl, WidgetOutput,
 };
 use geom::Pt2D;
 
@@ -9,6 +9,7 @@ pub struct Menu<T: Clone> {
     current_idx: usize,
 
     pub(crate) state: InputResult<T>,
+    draw: Drawable,
 
     pub(crate) top_left: ScreenPt,
     dims: ScreenDims,
@@ -21,14 +22,20 @@ impl<T: 'static + Clone> Menu<T> {
             current_idx: 0,
 
             state: InputResult::StillActive,
+            draw: ctx.upload(GeomBatch::new()),
 
             top_left: ScreenPt::new(0.0, 0.0),
             dims: ScreenDims::new(0.0, 0.0),
         };
         m.dims = m.calculate_txt().dims(&ctx.prerender.assets);
+        m.recalc(ctx);
         Widget::new(Box::new(m))
     }
 
+    fn recalc(&mut self, ctx: &EventCtx) {
+        self.draw = ctx.upload(self.calculate_txt().render_ctx(ctx));
+    }
+
     pub fn current_choice(&self) -> &T {
         &self.choices[self.current_idx].data
     }
@@ -68,18 +75,8 @@ impl<T: 'static + Clone> Menu<T> {
         }
         txt
     }
-}
-
-impl<T: 'static + Clone> WidgetImpl for Menu<T> {
-    fn get_dims(&self) -> ScreenDims {
-        self.dims
-    }
 
-    fn set_pos(&mut self, top_left: ScreenPt) {
-        self.top_left = top_left;
-    }
-
-    fn event(&mut self, ctx: &mut EventCtx, _output: &mut WidgetOutput) {
+    fn inner_event(&mut self, ctx: &mut EventCtx) {
         if self.choices.is_empty() {
             return;
         }
@@ -167,16 +164,33 @@ impl<T: 'static + Clone> WidgetImpl for Menu<T> {
             }
         }
     }
+}
+
+impl<T: 'static + Clone> WidgetImpl for Menu<T> {
+    fn get_dims(&self) -> ScreenDims {
+        self.dims
+    }
+
+    fn set_pos(&mut self, top_left: ScreenPt) {
+        self.top_left = top_left;
+    }
+
+    fn event(&mut self, ctx: &mut EventCtx, _output: &mut WidgetOutput) {
+        let before = self.current_idx;
+        self.inner_event(ctx);
+        if self.current_idx != before {
+            self.recalc(ctx);
+        }
+    }
 
     fn draw(&self, g: &mut GfxCtx) {
         if self.choices.is_empty() {
             return;
         }
 
-        let draw = g.upload(self.calculate_txt().render_g(g));
         // In between tooltip and normal screenspace
         g.fork(Pt2D::new(0.0, 0.0), self.top_left, 1.0, Some(0.1));
-        g.redraw(&draw);
+        g.redraw(&self.draw);
         g.unfork();
 
         if let Some(ref info) = self.choices[self.current_idx].tooltip {
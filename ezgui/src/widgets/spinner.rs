@@ -1,6 +1,6 @@
 use crate::{
-    text, Btn, Button, EventCtx, GeomBatch, GfxCtx, Line, ScreenDims, ScreenPt, ScreenRectangle,
-    Text, Widget, WidgetImpl, WidgetOutput,
+    text, Btn, Button, Drawable, EventCtx, GeomBatch, GfxCtx, Line, ScreenDims, ScreenPt,
+    ScreenRectangle, Text, Widget, WidgetImpl, WidgetOutput,
 };
 use geom::{Polygon, Pt2D};
 
@@ -17,6 +17,7 @@ pub struct Spinner {
 
     up: Button,
     down: Button,
+    draw: Drawable,
 
     top_left: ScreenPt,
     dims: ScreenDims,
@@ -36,38 +37,36 @@ impl Spinner {
             up.get_dims().height + down.get_dims().height,
         );
 
-        Widget::new(Box::new(Spinner {
+        let mut spinner = Spinner {
             low,
             high,
             current,
 
             up,
             down,
+            draw: ctx.upload(GeomBatch::new()),
 
             top_left: ScreenPt::new(0.0, 0.0),
             dims,
-        }))
+        };
+        spinner.recalc(ctx);
+        Widget::new(Box::new(spinner))
     }
-}
 
-impl WidgetImpl for Spinner {
-    fn get_dims(&self) -> ScreenDims {
-        self.dims
-    }
-
-    fn set_pos(&mut self, top_left: ScreenPt) {
-        // TODO This works, but it'd be kind of cool if we could construct a tiny little Composite
-        // here and use that. Wait, why can't we? ...
-        self.top_left = top_left;
-        self.up
-            .set_pos(ScreenPt::new(top_left.x + TEXT_WIDTH, top_left.y));
-        self.down.set_pos(ScreenPt::new(
-            top_left.x + TEXT_WIDTH,
-            top_left.y + self.up.get_dims().height,
-        ));
+    fn recalc(&mut self, ctx: &EventCtx) {
+        let mut batch = GeomBatch::from(vec![(
+            text::BG_COLOR,
+            Polygon::rounded_rectangle(self.dims.width, self.dims.height, Some(5.0)),
+        )]);
+        batch.append(
+            Text::from(Line(self.current.to_string()))
+                .render_to_batch(ctx.prerender)
+                .centered_on(Pt2D::new(TEXT_WIDTH / 2.0, self.dims.height / 2.0)),
+        );
+        self.draw = ctx.upload(batch);
     }
 
-    fn event(&mut self, ctx: &mut EventCtx, output: &mut WidgetOutput) {
+    fn inner_event(&mut self, ctx: &mut EventCtx, output: &mut WidgetOutput) {
         self.up.event(ctx, output);
         if output.outcome.take().is_some() {
             if self.current != self.high {
@@ -98,20 +97,35 @@ impl WidgetImpl for Spinner {
             }
         }
     }
+}
+
+impl WidgetImpl for Spinner {
+    fn get_dims(&self) -> ScreenDims {
+        self.dims
+    }
+
+    fn set_pos(&mut self, top_left: ScreenPt) {
+        // TODO This works, but it'd be kind of cool if we could construct a tiny little Composite
+        // here and use that. Wait, why can't we? ...
+        self.top_left = top_left;
+        self.up
+            .set_pos(ScreenPt::new(top_left.x + TEXT_WIDTH, top_left.y));
+        self.down.set_pos(ScreenPt::new(
+            top_left.x + TEXT_WIDTH,
+            top_left.y + self.up.get_dims().height,
+        ));
+    }
+
+    fn event(&mut self, ctx: &mut EventCtx, output: &mut WidgetOutput) {
+        let before = self.current;
+        self.inner_event(ctx, output);
+        if self.current != before {
+            self.recalc(ctx);
+        }
+    }
 
     fn draw(&self, g: &mut GfxCtx) {
-        // TODO Cache
-        let mut batch = GeomBatch::from(vec![(
-            text::BG_COLOR,
-            Polygon::rounded_rectangle(self.dims.width, self.dims.height, Some(5.0)),
-        )]);
-        batch.append(
-            Text::from(Line(self.current.to_string()))
-                .render_to_batch(g.prerender)
-                .centered_on(Pt2D::new(TEXT_WIDTH / 2.0, self.dims.height / 2.0)),
-        );
-        let draw = g.upload(batch);
-        g.redraw_at(self.top_left, &draw);
+        g.redraw_at(self.top_left, &self.draw);
 
         self.up.draw(g);
         self.down.draw(g);
@@ -1,6 +1,6 @@
 use crate::{
-    text, EventCtx, GeomBatch, GfxCtx, Key, Line, ScreenDims, ScreenPt, ScreenRectangle, Text,
-    WidgetImpl, WidgetOutput,
+    text, Drawable, EventCtx, GeomBatch, GfxCtx, Key, Line, ScreenDims, ScreenPt, ScreenRectangle,
+    Text, WidgetImpl, WidgetOutput,
 };
 use geom::Polygon;
 
@@ -12,6 +12,7 @@ pub struct TextBox {
     has_focus: bool,
     hovering: bool,
     autofocus: bool,
+    draw: Drawable,
 
     top_left: ScreenPt,
     dims: ScreenDims,
@@ -19,19 +20,31 @@ pub struct TextBox {
 
 impl TextBox {
     pub fn new(ctx: &EventCtx, max_chars: usize, prefilled: String, autofocus: bool) -> TextBox {
-        TextBox {
+        let mut tb = TextBox {
             cursor_x: prefilled.len(),
             line: prefilled,
             has_focus: false,
             hovering: false,
             autofocus,
+            draw: ctx.upload(GeomBatch::new()),
 
             top_left: ScreenPt::new(0.0, 0.0),
             dims: ScreenDims::new(
                 (max_chars as f64) * text::MAX_CHAR_WIDTH,
                 ctx.default_line_height(),
             ),
-        }
+        };
+        tb.recalc(ctx);
+        tb
+    }
+
+    fn recalc(&mut self, ctx: &EventCtx) {
+        let mut batch = GeomBatch::from(vec![(
+            text::BG_COLOR,
+            Polygon::rectangle(self.dims.width, self.dims.height),
+        )]);
+        batch.append(self.calculate_text().render_to_batch(ctx.prerender));
+        self.draw = ctx.upload(batch);
     }
 
     fn calculate_text(&self) -> Text {
@@ -107,17 +120,11 @@ impl WidgetImpl for TextBox {
                     }
                 }
             };
+            self.recalc(ctx);
         }
     }
 
     fn draw(&self, g: &mut GfxCtx) {
-        // TODO Cache
-        let mut batch = GeomBatch::from(vec![(
-            text::BG_COLOR,
-            Polygon::rectangle(self.dims.width, self.dims.height),
-        )]);
-        batch.append(self.calculate_text().render_to_batch(g.prerender));
-        let draw = g.upload(batch);
-        g.redraw_at(self.top_left, &draw);
+        g.redraw_at(self.top_left, &self.draw);
     }
 }
@@ -1,16 +1,20 @@
 use crate::challenges::HighScore;
 use crate::colors::ColorScheme;
 use crate::helpers::ID;
-use crate::layer::Layer;
+use crate::layer::{Layer, PinnedLayer};
 use crate::options::Options;
-use crate::render::{AgentCache, AgentColorScheme, DrawMap, DrawOptions, Renderable};
+use crate::render::{
+    darkness, draw_night, AgentCache, AgentColorScheme, DrawBuilding, DrawMap, DrawMapGeometry,
+    DrawOptions, Renderable,
+};
 use crate::sandbox::{GameplayMode, TutorialState};
 use abstutil::{MeasureMemory, Timer};
-use ezgui::{EventCtx, GfxCtx, Prerender};
+use ezgui::{EventCtx, GeomBatch, GfxCtx, Prerender};
 use geom::{Bounds, Circle, Distance, Duration, Pt2D, Time};
-use map_model::{IntersectionID, Map, Traversable};
+use map_model::{Building, ControlTrafficSignal, IntersectionID, Map, MapEdits, Traversable};
 use rand::seq::SliceRandom;
-use sim::{Analytics, GetDrawAgents, Sim, SimCallback, SimFlags};
+use rand_xorshift::XorShiftRng;
+use sim::{Analytics, GetDrawAgents, Sim, SimCallback, SimFlags, Snapshot};
 use std::collections::BTreeMap;
 
 pub struct App {
@@ -27,7 +31,10 @@ pub struct App {
     pub opts: Options,
 
     pub per_obj: PerObjectActions,
+    // The layer whose controls and legend are shown
     pub layer: Option<Box<dyn Layer>>,
+    // Kept on the map beneath the active layer, bottom to top
+    pub pinned_layers: Vec<PinnedLayer>,
 
     // Static data that lasts the entire session. Use sparingly.
     pub session: SessionState,
@@ -44,7 +51,16 @@ impl App {
         let primary = ctx.loading_screen("load map", |ctx, mut timer| {
             PerMap::new(flags, &cs, ctx, &mut timer)
         });
+        App::with_primary(primary, cs, opts, ctx, splash)
+    }
 
+    fn with_primary(
+        primary: PerMap,
+        cs: ColorScheme,
+        opts: Options,
+        ctx: &mut EventCtx,
+        splash: bool,
+    ) -> App {
         let mut rng = primary.current_flags.sim_flags.make_rng();
         let rand_focus_pt = primary
             .map
@@ -79,6 +95,7 @@ impl App {
             opts,
             per_obj: PerObjectActions::new(),
             layer: None,
+            pinned_layers: Vec::new(),
             session: SessionState::empty(),
             suspended_sim: None,
         }
@@ -112,6 +129,10 @@ impl App {
                     "- bus_passengers_waiting: {} bytes",
                     prettyprint_usize(serialized_size_bytes(&a.bus_passengers_waiting))
                 );
+                println!(
+                    "- bus_boardings: {} bytes",
+                    prettyprint_usize(serialized_size_bytes(&a.bus_boardings))
+                );
                 println!(
                     "- started_trips: {} bytes",
                     prettyprint_usize(serialized_size_bytes(&a.started_trips))
@@ -128,6 +149,10 @@ impl App {
                     "- intersection_delays: {} bytes",
                     prettyprint_usize(serialized_size_bytes(&a.intersection_delays))
                 );
+                println!(
+                    "- compacted: {} bytes",
+                    prettyprint_usize(serialized_size_bytes(&a.compacted))
+                );
                 println!(
                     "- parking_lane_changes: {} bytes",
                     prettyprint_usize(serialized_size_bytes(&a.parking_lane_changes))
@@ -149,6 +174,30 @@ impl App {
         self.session = session;
     }
 
+    // Like switch_map, but the map, sim, and the geometry for drawing were already prepared
+    // elsewhere (like a background thread), using a fresh color scheme. Only uploading to the GPU
+    // happens here.
+    pub fn switch_to_loaded_map(
+        &mut self,
+        ctx: &mut EventCtx,
+        map: Map,
+        sim: Sim,
+        geom: DrawMapGeometry,
+        flags: Flags,
+    ) {
+        ctx.canvas.save_camera_state(self.primary.map.get_name());
+        let cs = ColorScheme::new(self.opts.color_scheme);
+        let primary = ctx.loading_screen("upload map", |ctx, timer| {
+            timer.start("upload draw_map");
+            let draw_map = DrawMap::upload(geom, &map, ctx, timer);
+            timer.stop("upload draw_map");
+            PerMap::from_loaded(map, draw_map, sim, flags)
+        });
+        let session = std::mem::replace(&mut self.session, SessionState::empty());
+        *self = App::with_primary(primary, cs, self.opts.clone(), ctx, false);
+        self.session = session;
+    }
+
     pub fn draw(
         &self,
         g: &mut GfxCtx,
@@ -160,26 +209,44 @@ impl App {
 
         g.clear(self.cs.void_background);
         g.redraw(&self.primary.draw_map.boundary_polygon);
+        if self.opts.show_underlay {
+            if let Some(ref draw) = self.primary.draw_map.underlay {
+                g.redraw(draw);
+            }
+        }
 
         if g.canvas.cam_zoom < self.opts.min_zoom_for_detail && !g.is_screencap() {
             // Unzoomed mode
             let layers = show_objs.layers();
-            if layers.show_areas {
-                g.redraw(&self.primary.draw_map.draw_all_areas);
-            }
-            if layers.show_parking_lots {
-                g.redraw(&self.primary.draw_map.draw_all_unzoomed_parking_lots);
-            }
-            // Render bridges over intersections in the correct order
-            if layers.show_intersections {
-                g.redraw(&self.primary.draw_map.draw_all_unzoomed_intersections);
-            }
-            if layers.show_lanes {
-                g.redraw(&self.primary.draw_map.draw_all_thick_roads);
-            }
-            if layers.show_buildings {
-                g.redraw(&self.primary.draw_map.draw_all_buildings);
-                // Not the building paths
+            let level = self.primary.draw_map.tiles.level(&g.get_screen_bounds());
+            if level > 0 {
+                // Only the part of the map on screen
+                self.primary.draw_map.tiles.draw(
+                    g,
+                    level,
+                    layers,
+                    &self.primary.map,
+                    &self.cs,
+                    &self.primary.draw_map.icons,
+                );
+            } else {
+                if layers.show_areas {
+                    g.redraw(&self.primary.draw_map.draw_all_areas);
+                }
+                if layers.show_parking_lots {
+                    g.redraw(&self.primary.draw_map.draw_all_unzoomed_parking_lots);
+                }
+                // Render bridges over intersections in the correct order
+                if layers.show_intersections {
+                    g.redraw(&self.primary.draw_map.draw_all_unzoomed_intersections);
+                }
+                if layers.show_lanes {
+                    g.redraw(&self.primary.draw_map.draw_all_thick_roads);
+                }
+                if layers.show_buildings {
+                    g.redraw(&self.primary.draw_map.draw_all_buildings);
+                    // Not the building paths
+                }
             }
 
             // Still show some shape selection when zoomed out.
@@ -214,12 +281,23 @@ impl App {
                 source,
                 &self.primary.map,
                 &self.agent_cs,
+                &self.primary.sim,
                 g,
                 if self.opts.large_unzoomed_agents {
                     Some(Distance::meters(10.0) / g.canvas.cam_zoom)
                 } else {
                     None
                 },
+                if g.canvas.cam_zoom < self.opts.max_zoom_for_dot_map {
+                    Some(g.canvas.cam_zoom)
+                } else {
+                    None
+                },
+                if self.opts.merge_agent_clusters {
+                    Some(g.canvas.cam_zoom)
+                } else {
+                    None
+                },
             );
         } else {
             let mut cache = self.primary.draw_map.agents.borrow_mut();
@@ -243,6 +321,9 @@ impl App {
                             g.redraw(&self.primary.draw_map.draw_all_building_paths);
                             g.redraw(&self.primary.draw_map.draw_all_buildings);
                             g.redraw(&self.primary.draw_map.draw_all_building_outlines);
+                            if self.opts.extrude_buildings {
+                                self.draw_building_extrusions(g);
+                            }
                             drawn_all_buildings = true;
                         }
                     }
@@ -267,6 +348,16 @@ impl App {
             }
         }
 
+        // Over everything else, so names aren't covered up
+        self.primary.draw_map.labels.draw(g, self);
+
+        if self.opts.night_mode {
+            let dark = darkness(&self.primary.map, source.time(), self.opts.day_of_year);
+            if dark > 0.0 {
+                draw_night(g, self, source, dark);
+            }
+        }
+
         if let Some(i) = sample_intersection {
             g.set_screencap_naming_hint(i);
         }
@@ -356,6 +447,32 @@ impl App {
 
     // TODO This could probably belong to DrawMap again, but it's annoying to plumb things that
     // State does, like show_icons_for() and show().
+    fn draw_building_extrusions(&self, g: &mut GfxCtx) {
+        let mut draw = self
+            .primary
+            .draw_map
+            .draw_all_building_extrusions
+            .borrow_mut();
+        if draw.is_none() {
+            let map = &self.primary.map;
+            let mut bldgs: Vec<&Building> = map.all_buildings().iter().collect();
+            // Painter's algorithm; roofs shift upwards, so draw from the top of the screen down
+            bldgs.sort_by(|b1, b2| {
+                b1.polygon
+                    .get_bounds()
+                    .max_y
+                    .partial_cmp(&b2.polygon.get_bounds().max_y)
+                    .unwrap()
+            });
+            let mut batch = GeomBatch::new();
+            for b in bldgs {
+                DrawBuilding::extrude(b, &self.cs, &mut batch);
+            }
+            *draw = Some(g.prerender.upload(batch));
+        }
+        g.redraw(draw.as_ref().unwrap());
+    }
+
     fn get_renderables_back_to_front<'a>(
         &'a self,
         bounds: Bounds,
@@ -497,6 +614,8 @@ pub struct Flags {
     // Number of agents to generate when requested. If unspecified, trips to/from borders will be
     // included.
     pub num_agents: Option<usize>,
+    // XYZ tiles or a georeferenced image to draw beneath the map
+    pub underlay: Option<String>,
 }
 
 // All of the state that's bound to a specific map+edit has to live here.
@@ -511,8 +630,24 @@ pub struct PerMap {
     pub sim_cb: Option<Box<dyn SimCallback>>,
     // If we ever left edit mode and resumed without restarting from midnight, this is true.
     pub dirty_from_edits: bool,
+    // Copied in the traffic signal editor, to paste onto other intersections
+    pub signal_clipboard: Option<ControlTrafficSignal>,
+    // Moments to undo back to while tinkering, oldest first, with the edits in effect then
+    pub undo_points: Vec<UndoPoint>,
+    // For random choices made while playing, like where to send emergency vehicles. Seeded once,
+    // so doing the same thing twice doesn't pick the same places.
+    pub rng: XorShiftRng,
+}
+
+pub struct UndoPoint {
+    pub label: String,
+    pub edits: MapEdits,
+    pub snapshot: Snapshot,
 }
 
+// Each snapshot is a full copy of the simulation (minus recordings), so only keep a few
+const MAX_UNDO_POINTS: usize = 5;
+
 impl PerMap {
     pub fn new(flags: Flags, cs: &ColorScheme, ctx: &mut EventCtx, timer: &mut Timer) -> PerMap {
         let mut mem = MeasureMemory::new();
@@ -524,6 +659,10 @@ impl PerMap {
         timer.stop("draw_map");
         mem.reset("DrawMap", timer);
 
+        PerMap::from_loaded(map, draw_map, sim, flags)
+    }
+
+    pub fn from_loaded(map: Map, draw_map: DrawMap, sim: Sim, flags: Flags) -> PerMap {
         PerMap {
             map,
             draw_map,
@@ -533,9 +672,23 @@ impl PerMap {
             last_warped_from: None,
             sim_cb: None,
             dirty_from_edits: false,
+            signal_clipboard: None,
+            undo_points: Vec::new(),
+            rng: flags.sim_flags.make_rng(),
         }
     }
 
+    pub fn mark_undo_point(&mut self, label: String) {
+        if self.undo_points.len() == MAX_UNDO_POINTS {
+            self.undo_points.remove(0);
+        }
+        self.undo_points.push(UndoPoint {
+            label,
+            edits: self.map.get_edits().clone(),
+            snapshot: self.sim.snapshot(),
+        });
+    }
+
     // Returns whatever was there
     pub fn clear_sim(&mut self) -> Sim {
         self.dirty_from_edits = false;
@@ -1,5 +1,6 @@
 use crate::common::ColorScale;
 use ezgui::{Choice, Color, Style};
+use std::collections::BTreeMap;
 
 // I've gone back and forth how to organize color scheme code. I was previously against having one
 // centralized place with all definitions, because careful naming or comments are needed to explain
@@ -21,6 +22,9 @@ pub enum ColorSchemeChoice {
     BAP,
     OSM,
     Starcat,
+    DeuteranopiaSafe,
+    HighContrast,
+    DarkMode,
 }
 
 impl ColorSchemeChoice {
@@ -33,6 +37,9 @@ impl ColorSchemeChoice {
             Choice::new("bap", ColorSchemeChoice::BAP),
             Choice::new("osm", ColorSchemeChoice::OSM),
             Choice::new("starcat", ColorSchemeChoice::Starcat),
+            Choice::new("colorblind safe", ColorSchemeChoice::DeuteranopiaSafe),
+            Choice::new("high contrast", ColorSchemeChoice::HighContrast),
+            Choice::new("dark mode", ColorSchemeChoice::DarkMode),
         ]
     }
 }
@@ -51,6 +58,10 @@ pub struct ColorScheme {
     pub bottom_bar_id: Color,
     pub bottom_bar_name: Color,
     pub fade_map_dark: Color,
+    pub signal_diagram_bg: Color,
+    pub signal_diagram_selected: Color,
+    pub signal_diagram_hovered: Color,
+    pub signal_diagram_separator: Color,
     pub gui_style: Style,
 
     // Roads
@@ -76,6 +87,15 @@ pub struct ColorScheme {
     pub signal_box: Color,
     pub signal_spinner: Color,
     pub signal_turn_block_bg: Color,
+    pub signal_yellow: Color,
+    pub signal_yield_outline: Color,
+    pub signal_housing: Color,
+    pub signal_lamp_red: Color,
+    pub signal_lamp_yellow: Color,
+    pub signal_lamp_green: Color,
+    pub signal_editor_banned: Color,
+    pub signal_editor_arrow: Color,
+    pub yield_sign_inner: Color,
 
     // Other static elements
     pub void_background: Color,
@@ -86,6 +106,14 @@ pub struct ColorScheme {
     pub parking_lot: Color,
     pub grass: Color,
     pub water: Color,
+    pub pedestrian_island: Color,
+    pub map_label: Color,
+
+    // Day and night
+    pub dusk_sky: Color,
+    pub night_sky: Color,
+    pub lit_window: Color,
+    pub night_pavement: Color,
 
     // Unzoomed dynamic elements
     pub unzoomed_car: Color,
@@ -98,6 +126,7 @@ pub struct ColorScheme {
     pub route: Color,
     pub turn_arrow: Color,
     pub brake_light: Color,
+    pub headlight: Color,
     pub bus_body: Color,
     pub bus_label: Color,
     pub ped_head: Color,
@@ -106,12 +135,16 @@ pub struct ColorScheme {
     pub ped_crowd: Color,
     pub bike_frame: Color,
     pub parked_car: Color,
+    pub agent_not_delayed: Color,
+    pub agent_some_delay: Color,
+    pub agent_heavily_delayed: Color,
 
     // Layers
     pub good_to_bad_red: ColorScale,
     pub good_to_bad_green: ColorScale,
     pub bus_layer: Color,
     pub edits_layer: Color,
+    pub notes_layer: Color,
 
     // Misc
     pub parking_trip: Color,
@@ -129,6 +162,9 @@ impl ColorScheme {
             ColorSchemeChoice::BAP => ColorScheme::bap(),
             ColorSchemeChoice::OSM => ColorScheme::osm(),
             ColorSchemeChoice::Starcat => ColorScheme::starcat(),
+            ColorSchemeChoice::DeuteranopiaSafe => ColorScheme::deuteranopia_safe(),
+            ColorSchemeChoice::HighContrast => ColorScheme::high_contrast(),
+            ColorSchemeChoice::DarkMode => ColorScheme::dark_mode(),
         }
     }
 
@@ -148,6 +184,10 @@ impl ColorScheme {
             bottom_bar_id: Color::RED,
             bottom_bar_name: Color::CYAN,
             fade_map_dark: Color::BLACK.alpha(0.6),
+            signal_diagram_bg: Color::BLACK,
+            signal_diagram_selected: hex("#2A2A2A"),
+            signal_diagram_hovered: Color::RED,
+            signal_diagram_separator: Color::WHITE,
             gui_style,
 
             // Roads
@@ -173,6 +213,15 @@ impl ColorScheme {
             signal_box: Color::grey(0.5),
             signal_spinner: hex("#F2994A"),
             signal_turn_block_bg: Color::grey(0.6),
+            signal_yellow: Color::YELLOW,
+            signal_yield_outline: Color::BLACK,
+            signal_housing: Color::grey(0.2),
+            signal_lamp_red: Color::RED,
+            signal_lamp_yellow: Color::YELLOW,
+            signal_lamp_green: Color::GREEN,
+            signal_editor_banned: hex("#EB3223"),
+            signal_editor_arrow: Color::WHITE,
+            yield_sign_inner: Color::WHITE,
 
             // Other static elements
             void_background: Color::BLACK,
@@ -183,6 +232,14 @@ impl ColorScheme {
             parking_lot: Color::grey(0.7),
             grass: hex("#94C84A"),
             water: Color::rgb(164, 200, 234),
+            pedestrian_island: Color::grey(0.3),
+            map_label: Color::BLACK,
+
+            // Day and night
+            dusk_sky: hex("#5B2C55"),
+            night_sky: hex("#050A24"),
+            lit_window: hex("#FFD27A"),
+            night_pavement: Color::BLACK,
 
             // Unzoomed dynamic elements
             unzoomed_car: hex("#A32015"),
@@ -201,6 +258,7 @@ impl ColorScheme {
             route: Color::ORANGE.alpha(0.5),
             turn_arrow: hex("#DF8C3D"),
             brake_light: hex("#FF1300"),
+            headlight: hex("#FFF5B6"),
             bus_body: Color::rgb(50, 133, 117),
             bus_label: Color::rgb(249, 206, 24),
             ped_head: Color::rgb(139, 69, 19),
@@ -209,12 +267,16 @@ impl ColorScheme {
             ped_crowd: Color::rgb_f(0.2, 0.7, 0.7),
             bike_frame: Color::hex("#AAA9AD"),
             parked_car: Color::hex("#938E85"),
+            agent_not_delayed: hex("#7FFA4D"),
+            agent_some_delay: hex("#F4DA22"),
+            agent_heavily_delayed: hex("#EB3223"),
 
             // Layers
             good_to_bad_red: ColorScale(vec![hex("#F19A93"), hex("#A32015")]),
             good_to_bad_green: ColorScale(vec![hex("#BEDB92"), hex("#397A4C")]),
             bus_layer: hex("#4CA7E9"),
             edits_layer: hex("#12409D"),
+            notes_layer: hex("#F2A93B"),
 
             // Misc
             parking_trip: hex("#4E30A6"),
@@ -239,6 +301,119 @@ impl ColorScheme {
     pub fn rotating_color_agents(&self, idx: usize) -> Color {
         modulo_color(&self.agent_colors, idx)
     }
+
+    // Every color that can be tweaked in the editor or overridden by a saved theme, by field name
+    pub fn named_colors(&mut self) -> Vec<(&'static str, &mut Color)> {
+        vec![
+            ("hovering", &mut self.hovering),
+            ("panel_bg", &mut self.panel_bg),
+            ("section_bg", &mut self.section_bg),
+            ("inner_panel", &mut self.inner_panel),
+            ("day_time_slider", &mut self.day_time_slider),
+            ("night_time_slider", &mut self.night_time_slider),
+            ("selected", &mut self.selected),
+            ("current_object", &mut self.current_object),
+            ("perma_selected_object", &mut self.perma_selected_object),
+            ("bottom_bar_id", &mut self.bottom_bar_id),
+            ("bottom_bar_name", &mut self.bottom_bar_name),
+            ("fade_map_dark", &mut self.fade_map_dark),
+            ("signal_diagram_bg", &mut self.signal_diagram_bg),
+            ("signal_diagram_selected", &mut self.signal_diagram_selected),
+            ("signal_diagram_hovered", &mut self.signal_diagram_hovered),
+            (
+                "signal_diagram_separator",
+                &mut self.signal_diagram_separator,
+            ),
+            ("driving_lane", &mut self.driving_lane),
+            ("bus_lane", &mut self.bus_lane),
+            ("parking_lane", &mut self.parking_lane),
+            ("bike_lane", &mut self.bike_lane),
+            ("sidewalk", &mut self.sidewalk),
+            ("sidewalk_lines", &mut self.sidewalk_lines),
+            ("general_road_marking", &mut self.general_road_marking),
+            ("road_center_line", &mut self.road_center_line),
+            ("unzoomed_highway", &mut self.unzoomed_highway),
+            ("unzoomed_arterial", &mut self.unzoomed_arterial),
+            ("unzoomed_residential", &mut self.unzoomed_residential),
+            ("normal_intersection", &mut self.normal_intersection),
+            ("stop_sign", &mut self.stop_sign),
+            ("stop_sign_pole", &mut self.stop_sign_pole),
+            ("signal_protected_turn", &mut self.signal_protected_turn),
+            ("signal_permitted_turn", &mut self.signal_permitted_turn),
+            ("signal_banned_turn", &mut self.signal_banned_turn),
+            ("signal_box", &mut self.signal_box),
+            ("signal_spinner", &mut self.signal_spinner),
+            ("signal_turn_block_bg", &mut self.signal_turn_block_bg),
+            ("signal_yellow", &mut self.signal_yellow),
+            ("signal_yield_outline", &mut self.signal_yield_outline),
+            ("signal_housing", &mut self.signal_housing),
+            ("signal_lamp_red", &mut self.signal_lamp_red),
+            ("signal_lamp_yellow", &mut self.signal_lamp_yellow),
+            ("signal_lamp_green", &mut self.signal_lamp_green),
+            ("signal_editor_banned", &mut self.signal_editor_banned),
+            ("signal_editor_arrow", &mut self.signal_editor_arrow),
+            ("yield_sign_inner", &mut self.yield_sign_inner),
+            ("void_background", &mut self.void_background),
+            ("map_background", &mut self.map_background),
+            (
+                "unzoomed_interesting_intersection",
+                &mut self.unzoomed_interesting_intersection,
+            ),
+            ("building", &mut self.building),
+            ("building_outline", &mut self.building_outline),
+            ("parking_lot", &mut self.parking_lot),
+            ("grass", &mut self.grass),
+            ("water", &mut self.water),
+            ("pedestrian_island", &mut self.pedestrian_island),
+            ("map_label", &mut self.map_label),
+            ("dusk_sky", &mut self.dusk_sky),
+            ("night_sky", &mut self.night_sky),
+            ("lit_window", &mut self.lit_window),
+            ("night_pavement", &mut self.night_pavement),
+            ("unzoomed_car", &mut self.unzoomed_car),
+            ("unzoomed_bike", &mut self.unzoomed_bike),
+            ("unzoomed_bus", &mut self.unzoomed_bus),
+            ("unzoomed_pedestrian", &mut self.unzoomed_pedestrian),
+            ("route", &mut self.route),
+            ("turn_arrow", &mut self.turn_arrow),
+            ("brake_light", &mut self.brake_light),
+            ("headlight", &mut self.headlight),
+            ("bus_body", &mut self.bus_body),
+            ("bus_label", &mut self.bus_label),
+            ("ped_head", &mut self.ped_head),
+            ("ped_foot", &mut self.ped_foot),
+            ("ped_preparing_bike_body", &mut self.ped_preparing_bike_body),
+            ("ped_crowd", &mut self.ped_crowd),
+            ("bike_frame", &mut self.bike_frame),
+            ("parked_car", &mut self.parked_car),
+            ("agent_not_delayed", &mut self.agent_not_delayed),
+            ("agent_some_delay", &mut self.agent_some_delay),
+            ("agent_heavily_delayed", &mut self.agent_heavily_delayed),
+            ("bus_layer", &mut self.bus_layer),
+            ("edits_layer", &mut self.edits_layer),
+            ("notes_layer", &mut self.notes_layer),
+            ("parking_trip", &mut self.parking_trip),
+            ("before_changes", &mut self.before_changes),
+            ("after_changes", &mut self.after_changes),
+        ]
+    }
+
+    pub fn export_theme(&mut self) -> BTreeMap<String, Color> {
+        self.named_colors()
+            .into_iter()
+            .map(|(name, color)| (name.to_string(), *color))
+            .collect()
+    }
+
+    // Colors missing from the theme keep their current value, and unknown names are skipped, so
+    // themes saved before a color was added or renamed still load.
+    pub fn import_theme(&mut self, theme: &BTreeMap<String, Color>) {
+        for (name, color) in self.named_colors() {
+            if let Some(c) = theme.get(name) {
+                *color = *c;
+            }
+        }
+    }
 }
 
 fn modulo_color(colors: &Vec<Color>, idx: usize) -> Color {
@@ -337,3 +512,104 @@ impl ColorScheme {
         cs
     }
 }
+
+// Accessible schemes
+impl ColorScheme {
+    // Built on the Okabe-Ito palette. Nothing is told apart only by red versus green: signals,
+    // lanes, and good-to-bad scales lean on blue versus orange instead.
+    fn deuteranopia_safe() -> ColorScheme {
+        let mut cs = ColorScheme::standard();
+        cs.agent_colors = vec![
+            hex("#E69F00"),
+            hex("#56B4E9"),
+            hex("#009E73"),
+            hex("#F0E442"),
+            hex("#0072B2"),
+            hex("#D55E00"),
+            hex("#CC79A7"),
+        ];
+        cs.bus_lane = hex("#CC79A7");
+        cs.bike_lane = hex("#009E73");
+        cs.signal_protected_turn = hex("#0072B2");
+        cs.signal_permitted_turn = hex("#56B4E9");
+        cs.signal_spinner = hex("#E69F00");
+        cs.signal_lamp_red = hex("#D55E00");
+        cs.signal_lamp_yellow = hex("#F0E442");
+        cs.signal_lamp_green = hex("#56B4E9");
+        cs.signal_editor_banned = hex("#D55E00");
+        cs.unzoomed_car = hex("#D55E00");
+        cs.unzoomed_bike = hex("#009E73");
+        cs.unzoomed_bus = hex("#0072B2");
+        cs.unzoomed_pedestrian = hex("#CC79A7");
+        cs.agent_not_delayed = hex("#56B4E9");
+        cs.agent_some_delay = hex("#F0E442");
+        cs.agent_heavily_delayed = hex("#D55E00");
+        cs.good_to_bad_red = ColorScale(vec![hex("#FDD0A2"), hex("#D55E00")]);
+        cs.good_to_bad_green = ColorScale(vec![hex("#C6DBEF"), hex("#0072B2")]);
+        cs.before_changes = hex("#0072B2");
+        cs.after_changes = hex("#E69F00");
+        cs
+    }
+
+    // Black and white wherever possible, with a few saturated colors for the things that matter
+    fn high_contrast() -> ColorScheme {
+        let mut cs = ColorScheme::standard();
+        cs.panel_bg = Color::BLACK;
+        cs.gui_style.panel_bg = Color::BLACK;
+        cs.section_bg = Color::grey(0.25);
+        cs.selected = Color::YELLOW.alpha(0.8);
+        cs.current_object = Color::YELLOW;
+        cs.perma_selected_object = Color::CYAN;
+
+        cs.driving_lane = Color::BLACK;
+        cs.parking_lane = Color::grey(0.35);
+        cs.sidewalk = Color::WHITE;
+        cs.sidewalk_lines = Color::BLACK;
+        cs.general_road_marking = Color::WHITE;
+        cs.road_center_line = Color::YELLOW;
+        cs.unzoomed_highway = Color::BLACK;
+        cs.unzoomed_arterial = Color::grey(0.3);
+        cs.unzoomed_residential = Color::grey(0.55);
+        cs.normal_intersection = Color::BLACK;
+
+        cs.map_background = Color::WHITE;
+        cs.grass = Color::grey(0.85);
+        cs.water = hex("#0033CC");
+        cs.building = Color::grey(0.6);
+        cs.building_outline = Color::BLACK;
+        cs.parking_lot = Color::grey(0.45);
+        cs.pedestrian_island = Color::grey(0.45);
+
+        cs.signal_protected_turn = Color::GREEN;
+        cs.signal_permitted_turn = Color::CYAN;
+        cs.signal_turn_block_bg = Color::grey(0.8);
+        cs.route = Color::CYAN.alpha(0.8);
+        cs
+    }
+
+    // Unlike night mode, this just darkens the map; it doesn't try to be pretty
+    fn dark_mode() -> ColorScheme {
+        let mut cs = ColorScheme::standard();
+        cs.map_background = hex("#1E1E1E");
+        cs.grass = hex("#1F3A1F");
+        cs.water = hex("#0E2A47");
+        cs.building = hex("#3A3A3A");
+        cs.building_outline = hex("#555555");
+        cs.parking_lot = Color::grey(0.25);
+        cs.pedestrian_island = Color::grey(0.25);
+        cs.map_label = Color::WHITE;
+
+        cs.driving_lane = hex("#101010");
+        cs.parking_lane = hex("#202020");
+        cs.sidewalk = hex("#4A4A4A");
+        cs.sidewalk_lines = hex("#3A3A3A");
+        cs.general_road_marking = Color::grey(0.6);
+        cs.normal_intersection = hex("#101010");
+        cs.unzoomed_highway = hex("#A0526A");
+        cs.unzoomed_arterial = hex("#B8860B");
+        cs.unzoomed_residential = Color::grey(0.4);
+        cs.unzoomed_interesting_intersection = Color::grey(0.6);
+        cs.signal_turn_block_bg = Color::grey(0.3);
+        cs
+    }
+}
@@ -1,4 +1,5 @@
 use crate::app::App;
+use crate::common::map_loader::switch_map;
 use crate::game::{DrawBaselayer, State, Transition};
 use crate::helpers::nice_map_name;
 use crate::render::DrawArea;
@@ -107,6 +108,13 @@ impl CityPicker {
     }
 }
 
+impl CityPicker {
+    // The picker is replaced once a map is chosen, so the callback moves to the loader.
+    fn take_on_load(&mut self) -> Box<dyn Fn(&mut EventCtx, &mut App) -> Transition> {
+        std::mem::replace(&mut self.on_load, Box::new(|_, _| Transition::Keep))
+    }
+}
+
 impl State for CityPicker {
     fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
         match self.composite.event(ctx) {
@@ -115,10 +123,8 @@ impl State for CityPicker {
                     return Transition::Pop;
                 }
                 name => {
-                    return ctx.loading_screen("switch map", |ctx, _| {
-                        app.switch_map(ctx, abstutil::path_map(name));
-                        (self.on_load)(ctx, app)
-                    });
+                    let on_load = self.take_on_load();
+                    return switch_map(ctx, app, abstutil::path_map(name), on_load);
                 }
             },
             None => {}
@@ -152,10 +158,9 @@ impl State for CityPicker {
                 .per_obj
                 .left_click(ctx, format!("switch to {}", nice_map_name(name)))
             {
-                return ctx.loading_screen("switch map", |ctx, _| {
-                    app.switch_map(ctx, abstutil::path_map(name));
-                    (self.on_load)(ctx, app)
-                });
+                let path = abstutil::path_map(name);
+                let on_load = self.take_on_load();
+                return switch_map(ctx, app, path, on_load);
             }
         }
 
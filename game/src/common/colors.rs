@@ -2,7 +2,9 @@ use crate::app::App;
 use abstutil::Counter;
 use ezgui::{Color, Drawable, EventCtx, FancyColor, GeomBatch, Line, LinearGradient, Text, Widget};
 use geom::{Circle, Distance, Line, Polygon, Pt2D};
-use map_model::{BuildingID, BusStopID, IntersectionID, LaneID, Map, ParkingLotID, RoadID};
+use map_model::{
+    AreaID, BuildingID, BusStopID, IntersectionID, LaneID, Map, ParkingLotID, RoadID, TurnID,
+};
 use std::collections::HashMap;
 
 pub struct ColorDiscrete<'a> {
@@ -62,6 +64,15 @@ impl<'a> ColorDiscrete<'a> {
             .push(color.alpha(0.4), self.map.get_i(i).polygon.clone());
     }
 
+    pub fn add_t(&mut self, t: TurnID, category: &'static str) {
+        let color = self.colors[category];
+        let geom = &self.map.get_t(t).geom;
+        self.unzoomed
+            .push(color, geom.make_polygons(Distance::meters(5.0)));
+        self.zoomed
+            .push(color.alpha(0.4), geom.make_polygons(Distance::meters(2.0)));
+    }
+
     pub fn add_b(&mut self, b: BuildingID, category: &'static str) {
         let color = self.colors[category];
         self.unzoomed.push(color, self.map.get_b(b).polygon.clone());
@@ -69,6 +80,21 @@ impl<'a> ColorDiscrete<'a> {
             .push(color.alpha(0.4), self.map.get_b(b).polygon.clone());
     }
 
+    pub fn add_pl(&mut self, pl: ParkingLotID, category: &'static str) {
+        let color = self.colors[category];
+        self.unzoomed
+            .push(color, self.map.get_pl(pl).polygon.clone());
+        self.zoomed
+            .push(color.alpha(0.4), self.map.get_pl(pl).polygon.clone());
+    }
+
+    pub fn add_a(&mut self, a: AreaID, category: &'static str) {
+        let color = self.colors[category];
+        self.unzoomed.push(color, self.map.get_a(a).polygon.clone());
+        self.zoomed
+            .push(color.alpha(0.4), self.map.get_a(a).polygon.clone());
+    }
+
     pub fn add_bs(&mut self, bs: BusStopID, category: &'static str) {
         let color = self.colors[category];
         let pt = self.map.get_bs(bs).sidewalk_pos.pt(self.map);
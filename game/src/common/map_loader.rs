@@ -0,0 +1,171 @@
+use crate::app::{App, Flags};
+use crate::colors::ColorScheme;
+use crate::game::{msg, DrawBaselayer, State, Transition};
+use crate::helpers::nice_map_name;
+use crate::render::{DrawMap, DrawMapGeometry};
+use abstutil::{Timer, TimerSink};
+use ezgui::{
+    Composite, EventCtx, EventLoopMode, GfxCtx, HorizontalAlignment, Line, Text, VerticalAlignment,
+    Widget,
+};
+use map_model::Map;
+use sim::Sim;
+use std::collections::VecDeque;
+use std::sync::mpsc;
+
+const MAX_LINES: usize = 20;
+
+// Switch to another map, then run on_load, which should return a transition as if it were
+// replacing the current state. Loading the map and sim and making all of the geometry to draw it
+// happen on a background thread, so the window stays responsive and shows progress. Only uploading
+// that geometry to the GPU has to happen on the main thread afterwards.
+pub fn switch_map(
+    ctx: &mut EventCtx,
+    app: &mut App,
+    path: String,
+    on_load: Box<dyn Fn(&mut EventCtx, &mut App) -> Transition>,
+) -> Transition {
+    // No threads in wasm
+    #[cfg(target_arch = "wasm32")]
+    {
+        ctx.loading_screen("switch map", |ctx, _| {
+            app.switch_map(ctx, path);
+            (on_load)(ctx, app)
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Transition::Replace(MapLoader::new(ctx, app, path, on_load))
+    }
+}
+
+enum Msg {
+    Println(String),
+    Reprintln(String),
+    Done(Map, Sim, DrawMapGeometry),
+}
+
+// Forwards everything the Timer in the background thread prints.
+struct ChannelSink(mpsc::Sender<Msg>);
+
+impl TimerSink for ChannelSink {
+    fn println(&mut self, line: String) {
+        // If the receiver is gone, nobody's watching anymore.
+        let _ = self.0.send(Msg::Println(line));
+    }
+
+    fn reprintln(&mut self, line: String) {
+        let _ = self.0.send(Msg::Reprintln(line));
+    }
+}
+
+struct MapLoader {
+    name: String,
+    rx: mpsc::Receiver<Msg>,
+    lines: VecDeque<String>,
+    composite: Composite,
+    flags: Flags,
+    on_load: Box<dyn Fn(&mut EventCtx, &mut App) -> Transition>,
+}
+
+impl MapLoader {
+    fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        path: String,
+        on_load: Box<dyn Fn(&mut EventCtx, &mut App) -> Transition>,
+    ) -> Box<dyn State> {
+        let mut flags = app.primary.current_flags.clone();
+        flags.sim_flags.load = path;
+        let name = abstutil::basename(&flags.sim_flags.load);
+
+        let (tx, rx) = mpsc::channel();
+        let thread_flags = flags.clone();
+        let color_scheme = app.opts.color_scheme;
+        std::thread::spawn(move || {
+            let mut timer = Timer::new_with_sink("load map", Box::new(ChannelSink(tx.clone())));
+            let (map, sim, _) = thread_flags.sim_flags.load(&mut timer);
+            // switch_to_loaded_map uses a fresh color scheme too
+            let cs = ColorScheme::new(color_scheme);
+            let geom = DrawMap::prepare(&map, &thread_flags, &cs, &mut timer);
+            drop(timer);
+            let _ = tx.send(Msg::Done(map, sim, geom));
+        });
+
+        let lines = VecDeque::new();
+        Box::new(MapLoader {
+            composite: make_panel(ctx, app, &name, &lines),
+            name,
+            rx,
+            lines,
+            flags,
+            on_load,
+        })
+    }
+}
+
+impl State for MapLoader {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        let mut changed = false;
+        loop {
+            match self.rx.try_recv() {
+                Ok(Msg::Println(line)) => {
+                    if self.lines.len() == MAX_LINES {
+                        self.lines.pop_front();
+                    }
+                    self.lines.push_back(line);
+                    changed = true;
+                }
+                Ok(Msg::Reprintln(line)) => {
+                    self.lines.pop_back();
+                    self.lines.push_back(line);
+                    changed = true;
+                }
+                Ok(Msg::Done(map, sim, geom)) => {
+                    app.switch_to_loaded_map(ctx, map, sim, geom, self.flags.clone());
+                    return (self.on_load)(ctx, app);
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    break;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    // The thread panicked; the details are on STDOUT.
+                    return Transition::Replace(msg(
+                        "Error",
+                        vec![format!("Couldn't load {}", nice_map_name(&self.name))],
+                    ));
+                }
+            }
+        }
+        if changed {
+            self.composite = make_panel(ctx, app, &self.name, &self.lines);
+        }
+
+        // Keep polling for progress
+        Transition::KeepWithMode(EventLoopMode::Animation)
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::PreviousState
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        State::grey_out_map(g, app);
+        self.composite.draw(g);
+    }
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App, name: &str, lines: &VecDeque<String>) -> Composite {
+    let mut txt = Text::from(Line(format!("Loading {}", nice_map_name(name))).small_heading());
+    for l in lines {
+        txt.add(Line(l).secondary());
+    }
+    Composite::new(
+        Widget::col(vec![txt.draw(ctx)])
+            .padding(10)
+            .bg(app.cs.panel_bg),
+    )
+    .aligned(HorizontalAlignment::Center, VerticalAlignment::Center)
+    .build(ctx)
+}
@@ -1,13 +1,15 @@
 use crate::app::App;
-use crate::common::{navigate, Warping};
+use crate::common::{make_heatmap, navigate, HeatmapOptions, Warping};
 use crate::game::Transition;
-use crate::layer::PickLayer;
+use crate::layer::{PickLayer, PinnedLayer};
+use crate::render::ColorAgentsBy;
 use abstutil::clamp;
 use ezgui::{
-    hotkey, Btn, Checkbox, Color, Composite, EventCtx, Filler, GeomBatch, GfxCtx,
+    hotkey, Btn, Checkbox, Color, Composite, Drawable, EventCtx, Filler, GeomBatch, GfxCtx,
     HorizontalAlignment, Key, Line, Outcome, ScreenDims, ScreenPt, VerticalAlignment, Widget,
 };
-use geom::{Distance, Polygon, Pt2D, Ring};
+use geom::{Distance, Polygon, Pt2D, Ring, Time};
+use sim::GetDrawAgents;
 
 // TODO Some of the math in here might assume map bound minimums start at (0, 0).
 pub struct Minimap {
@@ -16,6 +18,11 @@ pub struct Minimap {
     // Update panel when other things change
     zoomed: bool,
     layer: bool,
+    color_agents_by: ColorAgentsBy,
+    // Shade the minimap by agent density, instead of drawing individual agents. Recalculated
+    // whenever the sim time changes.
+    show_density: bool,
+    density: Option<(Time, Drawable)>,
 
     // [0, 3], with 0 meaning the most unzoomed
     zoom_lvl: usize,
@@ -33,9 +40,12 @@ impl Minimap {
         let base_zoom = 0.15 * ctx.canvas.window_width / bounds.width().min(bounds.height());
         let mut m = Minimap {
             dragging: false,
-            composite: make_minimap_panel(ctx, app, 0),
+            composite: make_minimap_panel(ctx, app, 0, false),
             zoomed: ctx.canvas.cam_zoom >= app.opts.min_zoom_for_detail,
             layer: app.layer.is_none(),
+            color_agents_by: app.agent_cs.color_by,
+            show_density: false,
+            density: None,
 
             zoom_lvl: 0,
             base_zoom,
@@ -63,7 +73,7 @@ impl Minimap {
         let zoom_speed: f64 = 2.0;
         self.zoom_lvl = zoom_lvl;
         self.zoom = self.base_zoom * zoom_speed.powi(self.zoom_lvl as i32);
-        self.composite = make_minimap_panel(ctx, app, self.zoom_lvl);
+        self.composite = make_minimap_panel(ctx, app, self.zoom_lvl, self.show_density);
 
         // Find the new offset
         let map_center = ctx.canvas.center_to_map_pt();
@@ -90,12 +100,14 @@ impl Minimap {
     pub fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<Transition> {
         let zoomed = ctx.canvas.cam_zoom >= app.opts.min_zoom_for_detail;
         let layer = app.layer.is_none();
-        if zoomed != self.zoomed || layer != self.layer {
+        let color_agents_by = app.agent_cs.color_by;
+        if zoomed != self.zoomed || layer != self.layer || color_agents_by != self.color_agents_by {
             let just_zoomed_in = zoomed && !self.zoomed;
 
             self.zoomed = zoomed;
             self.layer = layer;
-            self.composite = make_minimap_panel(ctx, app, self.zoom_lvl);
+            self.color_agents_by = color_agents_by;
+            self.composite = make_minimap_panel(ctx, app, self.zoom_lvl, self.show_density);
 
             if just_zoomed_in {
                 self.recenter(ctx, app);
@@ -200,7 +212,37 @@ impl Minimap {
         }
         if let Some(label) = toggle {
             app.agent_cs.toggle(label);
-            self.composite = make_minimap_panel(ctx, app, self.zoom_lvl);
+            self.composite = make_minimap_panel(ctx, app, self.zoom_lvl, self.show_density);
+        }
+        // The checkbox only exists while zoomed in, so remember it separately
+        if self.composite.has_widget("agent density") {
+            self.show_density = self.composite.is_checked("agent density");
+        }
+        if !self.show_density {
+            self.density = None;
+        } else if self
+            .density
+            .as_ref()
+            .map(|(t, _)| *t != app.primary.sim.time())
+            .unwrap_or(true)
+        {
+            let pts = app
+                .primary
+                .sim
+                .get_unzoomed_agents(&app.primary.map)
+                .into_iter()
+                .map(|a| a.pos)
+                .collect();
+            let mut batch = GeomBatch::new();
+            // The legend isn't shown; the minimap is too small to fit one.
+            make_heatmap(
+                ctx,
+                &mut batch,
+                app.primary.map.get_bounds(),
+                pts,
+                &HeatmapOptions::new(),
+            );
+            self.density = Some((app.primary.sim.time(), batch.upload(ctx)));
         }
 
         if self.zoomed {
@@ -264,22 +306,30 @@ impl Minimap {
         g.redraw(&app.primary.draw_map.draw_all_thick_roads);
         g.redraw(&app.primary.draw_map.draw_all_buildings);
         // Not the building or parking lot paths
+        PinnedLayer::draw_all_minimap(g, app);
         if let Some(ref l) = app.layer {
             l.draw_minimap(g);
         }
 
-        let mut cache = app.primary.draw_map.agents.borrow_mut();
-        cache.draw_unzoomed_agents(
-            &app.primary.sim,
-            &app.primary.map,
-            &app.agent_cs,
-            g,
-            if app.opts.large_unzoomed_agents {
-                Some(Distance::meters(2.0 + (self.zoom_lvl as f64)) / self.zoom)
-            } else {
-                None
-            },
-        );
+        if let Some((_, ref draw)) = self.density {
+            g.redraw(draw);
+        } else {
+            let mut cache = app.primary.draw_map.agents.borrow_mut();
+            cache.draw_unzoomed_agents(
+                &app.primary.sim,
+                &app.primary.map,
+                &app.agent_cs,
+                &app.primary.sim,
+                g,
+                if app.opts.large_unzoomed_agents {
+                    Some(Distance::meters(2.0 + (self.zoom_lvl as f64)) / self.zoom)
+                } else {
+                    None
+                },
+                None,
+                None,
+            );
+        }
 
         // The cursor
         let (x1, y1) = {
@@ -308,7 +358,12 @@ impl Minimap {
     }
 }
 
-fn make_minimap_panel(ctx: &mut EventCtx, app: &App, zoom_lvl: usize) -> Composite {
+fn make_minimap_panel(
+    ctx: &mut EventCtx,
+    app: &App,
+    zoom_lvl: usize,
+    show_density: bool,
+) -> Composite {
     if ctx.canvas.cam_zoom < app.opts.min_zoom_for_detail {
         return Composite::new(Widget::row(vec![
             make_tool_panel(ctx, app).align_right().margin_right(16),
@@ -380,7 +435,10 @@ fn make_minimap_panel(ctx: &mut EventCtx, app: &App, zoom_lvl: usize) -> Composi
         make_tool_panel(ctx, app).margin_right(16),
         Widget::col(vec![
             Widget::row(vec![minimap_controls, zoom_col]),
-            make_horiz_viz_panel(ctx, app),
+            Widget::row(vec![
+                make_horiz_viz_panel(ctx, app).margin_right(24),
+                Checkbox::text(ctx, "agent density", None, show_density),
+            ]),
         ])
         .padding(7)
         .bg(app.cs.panel_bg),
@@ -1,6 +1,7 @@
 mod city_picker;
 mod colors;
 mod heatmap;
+mod map_loader;
 mod minimap;
 mod navigate;
 mod panels;
@@ -132,6 +133,13 @@ impl CommonState {
                     Line(format!("{} of ", map.get_l(l).lane_type.describe())),
                     Line(map.get_parent(l).get_name()).fg(name_color),
                 ]);
+                let r = map.get_parent(l);
+                if !r.timed_speed_limits.is_empty() {
+                    osd.append(Line(format!(
+                        " (limit now {})",
+                        r.speed_limit_at(app.primary.sim.time(), false)
+                    )));
+                }
             }
             ID::Building(b) => {
                 if app.opts.dev {
@@ -2,13 +2,21 @@ use crate::app::App;
 use crate::common::Warping;
 use crate::game::{State, Transition};
 use crate::helpers::ID;
+use crate::layer::bus::ShowBusRoute;
 use ezgui::{
-    hotkey, Autocomplete, Btn, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx, Key, Line,
-    Outcome, Text, Widget,
+    hotkey, Autocomplete, Btn, Checkbox, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx,
+    Key, Line, Outcome, Text, Widget,
 };
-use map_model::RoadID;
+use map_model::{BuildingID, BusRouteID, RoadID};
 use std::collections::HashSet;
 
+#[derive(Clone)]
+enum Target {
+    Road(RoadID),
+    Building(BuildingID),
+    BusRoute(BusRouteID),
+}
+
 // TODO Canonicalize names, handling abbreviations like east/e and street/st
 pub struct Navigator {
     composite: Composite,
@@ -16,25 +24,34 @@ pub struct Navigator {
 
 impl Navigator {
     pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State> {
+        let map = &app.primary.map;
+        let mut choices = Vec::new();
+        for r in map.all_roads() {
+            choices.push((r.get_name(), Target::Road(r.id)));
+        }
+        for b in map.all_buildings() {
+            choices.push((b.address.clone(), Target::Building(b.id)));
+            if let Some(ref name) = b.name {
+                choices.push((name.clone(), Target::Building(b.id)));
+            }
+        }
+        for r in map.get_all_bus_routes() {
+            choices.push((format!("{} (bus route)", r.name), Target::BusRoute(r.id)));
+        }
+
         Box::new(Navigator {
             composite: Composite::new(
                 Widget::col(vec![
                     Widget::row(vec![
-                        Line("Enter a street name").small_heading().draw(ctx),
+                        Line("Search for a street, building, or bus route")
+                            .small_heading()
+                            .draw(ctx),
                         Btn::text_fg("X")
                             .build(ctx, "close", hotkey(Key::Escape))
                             .align_right(),
                     ]),
-                    Autocomplete::new(
-                        ctx,
-                        app.primary
-                            .map
-                            .all_roads()
-                            .iter()
-                            .map(|r| (r.get_name(), r.id))
-                            .collect(),
-                    )
-                    .named("street"),
+                    Checkbox::text(ctx, "show details when found", None, true),
+                    Autocomplete::new(ctx, choices).named("search"),
                 ])
                 .bg(app.cs.panel_bg),
             )
@@ -54,11 +71,58 @@ impl State for Navigator {
             },
             None => {}
         }
-        if let Some(roads) = self.composite.autocomplete_done("street") {
-            if roads.is_empty() {
-                return Transition::Pop;
+        if let Some(targets) = self.composite.autocomplete_done("search") {
+            let show_info = self.composite.is_checked("show details when found");
+            let roads: Vec<RoadID> = targets
+                .iter()
+                .filter_map(|t| match t {
+                    Target::Road(r) => Some(*r),
+                    _ => None,
+                })
+                .collect();
+            if !roads.is_empty() {
+                return Transition::Replace(CrossStreet::new(ctx, app, roads, show_info));
+            }
+            // A vague search mixes streets with every building along them, so only go somewhere
+            // more specific when no street matched.
+            match targets.into_iter().next() {
+                Some(Target::Building(b)) => {
+                    return Transition::Replace(Warping::new(
+                        ctx,
+                        app.primary.map.get_b(b).label_center,
+                        Some(app.opts.min_zoom_for_detail),
+                        if show_info {
+                            Some(ID::Building(b))
+                        } else {
+                            None
+                        },
+                        &mut app.primary,
+                    ));
+                }
+                Some(Target::BusRoute(r)) => {
+                    app.layer = Some(Box::new(ShowBusRoute::new(ctx, app, r)));
+                    let stop = app.primary.map.get_br(r).stops[0];
+                    return Transition::Replace(Warping::new(
+                        ctx,
+                        app.primary
+                            .map
+                            .get_bs(stop)
+                            .sidewalk_pos
+                            .pt(&app.primary.map),
+                        Some(app.opts.min_zoom_for_detail),
+                        if show_info {
+                            Some(ID::BusStop(stop))
+                        } else {
+                            None
+                        },
+                        &mut app.primary,
+                    ));
+                }
+                Some(Target::Road(_)) => unreachable!(),
+                None => {
+                    return Transition::Pop;
+                }
             }
-            return Transition::Replace(CrossStreet::new(ctx, app, roads));
         }
 
         if self.composite.clicked_outside(ctx) {
@@ -76,12 +140,13 @@ impl State for Navigator {
 
 struct CrossStreet {
     first: Vec<RoadID>,
+    show_info: bool,
     composite: Composite,
     draw: Drawable,
 }
 
 impl CrossStreet {
-    fn new(ctx: &mut EventCtx, app: &App, first: Vec<RoadID>) -> Box<dyn State> {
+    fn new(ctx: &mut EventCtx, app: &App, first: Vec<RoadID>, show_info: bool) -> Box<dyn State> {
         let map = &app.primary.map;
         let mut cross_streets = HashSet::new();
         let mut batch = GeomBatch::new();
@@ -132,6 +197,7 @@ impl CrossStreet {
             )
             .build(ctx),
             first,
+            show_info,
             draw: ctx.upload(batch),
         })
     }
@@ -175,7 +241,11 @@ impl State for CrossStreet {
                     ctx,
                     map.get_i(i).polygon.center(),
                     Some(app.opts.min_zoom_for_detail),
-                    Some(ID::Intersection(i)),
+                    if self.show_info {
+                        Some(ID::Intersection(i))
+                    } else {
+                        None
+                    },
                     &mut app.primary,
                 ));
             } else {
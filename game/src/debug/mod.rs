@@ -13,7 +13,7 @@ use ezgui::{
     hotkey, lctrl, Btn, Checkbox, Color, Composite, Drawable, EventCtx, EventLoopMode, GeomBatch,
     GfxCtx, HorizontalAlignment, Key, Line, Outcome, Text, VerticalAlignment, Widget, Wizard,
 };
-use geom::Pt2D;
+use geom::{Duration, Pt2D};
 use map_model::{ControlTrafficSignal, NORMAL_LANE_THICKNESS};
 use sim::{AgentID, Sim, TripID};
 use std::collections::HashSet;
@@ -29,6 +29,8 @@ pub struct DebugMode {
     all_routes: Option<(usize, Drawable)>,
 
     highlighted_agents: Option<(ID, Drawable)>,
+    // Resolution picked for a screenshot, taken on the next event
+    pending_screenshot: Option<(u32, u32)>,
 }
 
 impl DebugMode {
@@ -55,6 +57,12 @@ impl DebugMode {
                         vec![
                             (lctrl(Key::H), "unhide everything"),
                             (None, "screenshot everything"),
+                            (None, "screenshot current view"),
+                            if ctx.is_recording_frames() {
+                                (None, "stop recording frames")
+                            } else {
+                                (None, "record frames")
+                            },
                             (hotkey(Key::Slash), "search OSM metadata"),
                             (lctrl(Key::Slash), "clear OSM search results"),
                             (hotkey(Key::O), "save sim state"),
@@ -83,6 +91,7 @@ impl DebugMode {
             search_results: None,
             all_routes: None,
             highlighted_agents: None,
+            pending_screenshot: None,
         }
     }
 
@@ -110,6 +119,18 @@ impl DebugMode {
 
 impl State for DebugMode {
     fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if let Some((width, height)) = self.pending_screenshot.take() {
+            return Transition::KeepWithMode(EventLoopMode::ScreenCapture {
+                filename: format!(
+                    "{}/{}.png",
+                    abstutil::path_pending_screenshots(app.primary.map.get_name()),
+                    app.primary.sim.time().as_filename()
+                ),
+                width,
+                height,
+            });
+        }
+
         ctx.canvas_movement();
 
         if ctx.redo_mouseover() {
@@ -210,6 +231,56 @@ impl State for DebugMode {
                         max_y: bounds.max_y,
                     });
                 }
+                "screenshot current view" => {
+                    return Transition::Push(WizardState::new(Box::new(|wiz, ctx, _| {
+                        let choice = wiz.wrap(ctx).choose_string("What resolution?", || {
+                            vec!["1920x1080", "3840x2160", "7680x4320"]
+                        })?;
+                        let mut dims = choice.split('x').map(|x| x.parse::<u32>().unwrap());
+                        let (width, height) = (dims.next().unwrap(), dims.next().unwrap());
+                        Some(Transition::PopWithData(Box::new(move |state, _, _| {
+                            state
+                                .downcast_mut::<DebugMode>()
+                                .unwrap()
+                                .pending_screenshot = Some((width, height));
+                        })))
+                    })));
+                }
+                "record frames" => {
+                    ctx.start_recording_frames(
+                        format!(
+                            "{}/frames_{}",
+                            abstutil::path_pending_screenshots(app.primary.map.get_name()),
+                            app.primary.sim.time().as_filename()
+                        ),
+                        Duration::seconds(1.0 / 30.0),
+                    );
+                    self.composite.replace(
+                        ctx,
+                        "record frames",
+                        Btn::text_fg("stop recording frames")
+                            .build_def(ctx, None)
+                            .margin_below(5),
+                    );
+                }
+                "stop recording frames" => {
+                    let frames = ctx.stop_recording_frames();
+                    self.composite.replace(
+                        ctx,
+                        "stop recording frames",
+                        Btn::text_fg("record frames")
+                            .build_def(ctx, None)
+                            .margin_below(5),
+                    );
+                    return Transition::Push(msg(
+                        "Recording finished",
+                        vec![format!(
+                            "Saved {} frames in {}",
+                            frames,
+                            abstutil::path_pending_screenshots(app.primary.map.get_name())
+                        )],
+                    ));
+                }
                 "find bad traffic signals" => {
                     find_bad_signals(app);
                 }
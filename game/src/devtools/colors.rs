@@ -0,0 +1,244 @@
+use crate::app::App;
+use crate::game::{msg, DrawBaselayer, State, Transition, WizardState};
+use crate::render::{AgentColorScheme, DrawMap};
+use abstutil::Timer;
+use ezgui::{
+    hotkey, Btn, Color, Composite, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Slider, Text, TextExt, VerticalAlignment, Widget,
+};
+use geom::Polygon;
+use std::collections::BTreeMap;
+
+const THEMES_DIR: &str = "../data/player/color_themes";
+
+// Tweak any color in the scheme and see the result on the current map. Finished themes can be
+// saved and loaded by name.
+pub struct ColorSchemeEditor {
+    list: Composite,
+    picker: Composite,
+    selected: &'static str,
+    // Tracks the sliders, so the swatch can be drawn while dragging
+    color: Color,
+    // The map and panels are only redrawn once the mouse is released
+    dirty: bool,
+}
+
+impl ColorSchemeEditor {
+    pub fn new(ctx: &mut EventCtx, app: &mut App) -> Box<dyn State> {
+        let (selected, color) = {
+            let (name, color) = app.cs.named_colors().into_iter().next().unwrap();
+            (name, *color)
+        };
+        Box::new(ColorSchemeEditor {
+            list: make_list(ctx, app, selected),
+            picker: make_picker(ctx, app, selected, color),
+            selected,
+            color,
+            dirty: false,
+        })
+    }
+
+    fn select(&mut self, ctx: &mut EventCtx, app: &mut App, name: &str) {
+        let (name, color) = app
+            .cs
+            .named_colors()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(n, c)| (n, *c))
+            .unwrap();
+        self.selected = name;
+        self.color = color;
+        self.redo_panels(ctx, app);
+    }
+
+    fn redo_panels(&mut self, ctx: &mut EventCtx, app: &mut App) {
+        let mut list = make_list(ctx, app, self.selected);
+        list.restore(ctx, &self.list);
+        self.list = list;
+        self.picker = make_picker(ctx, app, self.selected, self.color);
+    }
+
+    fn set_color(&mut self, app: &mut App, color: Color) {
+        self.color = color;
+        for (name, c) in app.cs.named_colors() {
+            if name == self.selected {
+                *c = color;
+            }
+        }
+        // Agents are colored as they're drawn, so they change right away
+        app.agent_cs = AgentColorScheme::new(&app.cs);
+        self.dirty = true;
+    }
+
+    fn apply(&mut self, ctx: &mut EventCtx, app: &mut App) {
+        let draw_map = ctx.loading_screen("redraw map", |ctx, timer| {
+            DrawMap::new(
+                &app.primary.map,
+                &app.primary.current_flags,
+                &app.cs,
+                ctx,
+                timer,
+            )
+        });
+        app.primary.draw_map = draw_map;
+        app.agent_cs = AgentColorScheme::new(&app.cs);
+        self.dirty = false;
+        self.redo_panels(ctx, app);
+    }
+}
+
+impl State for ColorSchemeEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.list.event(ctx) {
+            Some(Outcome::Clicked(x)) => {
+                self.select(ctx, app, &x);
+                return Transition::Keep;
+            }
+            None => {}
+        }
+
+        match self.picker.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "save theme" => {
+                    return Transition::Push(WizardState::new(Box::new(|wiz, ctx, app| {
+                        let name = wiz.wrap(ctx).input_string("Name this theme")?;
+                        let path = format!("{}/{}.json", THEMES_DIR, name);
+                        abstutil::write_json(path.clone(), &app.cs.export_theme());
+                        Some(Transition::Replace(msg(
+                            "Saved theme",
+                            vec![format!("Saved to {}", path)],
+                        )))
+                    })));
+                }
+                "load theme" => {
+                    return Transition::Push(WizardState::new(Box::new(|wiz, ctx, app| {
+                        let name = wiz.wrap(ctx).choose_string("Load which theme?", || {
+                            abstutil::list_all_objects(THEMES_DIR.to_string())
+                        })?;
+                        let theme: BTreeMap<String, Color> = abstutil::read_json(
+                            format!("{}/{}.json", THEMES_DIR, name),
+                            &mut Timer::throwaway(),
+                        );
+                        app.cs.import_theme(&theme);
+                        Some(Transition::PopWithData(Box::new(|state, ctx, app| {
+                            let editor = state.downcast_mut::<ColorSchemeEditor>().unwrap();
+                            let selected = editor.selected;
+                            editor.select(ctx, app, selected);
+                            editor.apply(ctx, app);
+                        })))
+                    })));
+                }
+                _ => unreachable!(),
+            },
+            None => {}
+        }
+
+        let color = Color::rgba_f(
+            self.picker.slider("red").get_percent() as f32,
+            self.picker.slider("green").get_percent() as f32,
+            self.picker.slider("blue").get_percent() as f32,
+            self.picker.slider("alpha").get_percent() as f32,
+        );
+        if color != self.color {
+            self.set_color(app, color);
+        }
+        if self.dirty && ctx.input.left_mouse_button_released() {
+            self.apply(ctx, app);
+        }
+
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::DefaultMap
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.list.draw(g);
+        self.picker.draw(g);
+
+        g.fork_screenspace();
+        g.draw_polygon(self.color, &self.picker.rect_of("swatch").to_polygon());
+        g.unfork();
+    }
+}
+
+fn make_list(ctx: &mut EventCtx, app: &mut App, selected: &str) -> Composite {
+    let mut col = vec![Line("Colors").small_heading().draw(ctx).margin_below(5)];
+    for (name, color) in app.cs.named_colors() {
+        let swatch = GeomBatch::from(vec![(*color, Polygon::rectangle(20.0, 20.0))]);
+        let btn = if name == selected {
+            Btn::text_bg2(name).inactive(ctx)
+        } else {
+            Btn::text_fg(name).build_def(ctx, None)
+        };
+        col.push(Widget::row(vec![
+            Widget::draw_batch(ctx, swatch)
+                .margin_right(10)
+                .centered_vert(),
+            btn,
+        ]));
+    }
+    Composite::new(Widget::col(col).padding(10).bg(app.cs.panel_bg))
+        .aligned(HorizontalAlignment::Left, VerticalAlignment::Top)
+        .max_size_percent(30, 80)
+        .build(ctx)
+}
+
+fn make_picker(ctx: &mut EventCtx, app: &App, name: &str, color: Color) -> Composite {
+    let mut txt = Text::from(Line(name).small_heading());
+    txt.add(Line(format!("{}, alpha {:.2}", color.to_hex(), color.a)));
+
+    let mut col = vec![
+        Widget::row(vec![
+            Line("Color scheme editor")
+                .small_heading()
+                .draw(ctx)
+                .margin_right(10),
+            Btn::text_fg("X")
+                .build(ctx, "close", hotkey(Key::Escape))
+                .align_right(),
+        ])
+        .margin_below(5),
+        Widget::row(vec![
+            Widget::draw_batch(
+                ctx,
+                GeomBatch::from(vec![(Color::INVISIBLE, Polygon::rectangle(50.0, 50.0))]),
+            )
+            .named("swatch")
+            .margin_right(10),
+            txt.draw(ctx),
+        ])
+        .margin_below(5),
+    ];
+    for (label, value) in vec![
+        ("red", color.r),
+        ("green", color.g),
+        ("blue", color.b),
+        ("alpha", color.a),
+    ] {
+        col.push(Widget::row(vec![
+            label.draw_text(ctx).margin_right(10),
+            Slider::horizontal(ctx, 200.0, 25.0, value as f64)
+                .named(label)
+                .align_right(),
+        ]));
+    }
+    col.push(
+        Widget::row(vec![
+            Btn::text_fg("save theme").build_def(ctx, hotkey(Key::S)),
+            Btn::text_fg("load theme").build_def(ctx, hotkey(Key::L)),
+        ])
+        .evenly_spaced()
+        .margin_above(5),
+    );
+
+    Composite::new(Widget::col(col).padding(10).bg(app.cs.panel_bg))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+        .build(ctx)
+}
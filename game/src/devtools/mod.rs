@@ -1,4 +1,5 @@
 mod blocks;
+mod colors;
 mod destinations;
 mod kml;
 pub mod mapping;
@@ -48,6 +49,7 @@ impl DevToolsMode {
                         Btn::text_fg("load scenario").build_def(ctx, hotkey(Key::W)),
                         Btn::text_fg("view KML").build_def(ctx, hotkey(Key::K)),
                         Btn::text_fg("story maps").build_def(ctx, hotkey(Key::S)),
+                        Btn::text_fg("edit colors").build_def(ctx, hotkey(Key::C)),
                     ])
                     .flex_wrap(ctx, 60),
                 ])
@@ -87,6 +89,9 @@ impl State for DevToolsMode {
                 "story maps" => {
                     return Transition::Push(story::StoryMapEditor::new(ctx, app));
                 }
+                "edit colors" => {
+                    return Transition::Push(colors::ColorSchemeEditor::new(ctx, app));
+                }
                 "change map" => {
                     return Transition::Push(CityPicker::new(
                         ctx,
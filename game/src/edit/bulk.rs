@@ -1,5 +1,6 @@
 use crate::app::{App, ShowEverything};
 use crate::common::CommonState;
+use crate::edit::find_replace::FindReplace;
 use crate::edit::lanes::try_change_lane_type;
 use crate::edit::{apply_map_edits, change_speed_limit};
 use crate::game::{msg, State, Transition};
@@ -8,12 +9,15 @@ use ezgui::{
     hotkey, Btn, Choice, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx,
     HorizontalAlignment, Key, Line, Outcome, RewriteColor, TextExt, VerticalAlignment, Widget,
 };
-use geom::{Distance, Speed};
+use geom::{Distance, PolyLine, Polygon, Pt2D, Speed};
 use map_model::{EditCmd, IntersectionID, LaneType, Map, RoadID};
 use petgraph::graphmap::UnGraphMap;
 use sim::DontDrawAgents;
 use std::collections::BTreeSet;
 
+// Don't record every single mouse movement while drawing a lasso.
+const LASSO_RESOLUTION: Distance = Distance::const_meters(5.0);
+
 struct RouteSelect {
     composite: Composite,
     i1: Option<IntersectionID>,
@@ -130,7 +134,7 @@ impl State for RouteSelect {
                     return Transition::Pop;
                 }
                 "Select roads free-hand / paint mode" => {
-                    return Transition::Replace(PaintSelect::new(ctx, app));
+                    return Transition::Replace(PaintSelect::new(ctx, app, BTreeSet::new()));
                 }
                 _ => unreachable!(),
             },
@@ -152,7 +156,7 @@ impl State for RouteSelect {
 }
 
 // Simple search along undirected roads
-fn pathfind(map: &Map, i1: IntersectionID, i2: IntersectionID) -> Option<Vec<RoadID>> {
+pub fn pathfind(map: &Map, i1: IntersectionID, i2: IntersectionID) -> Option<Vec<RoadID>> {
     let mut graph: UnGraphMap<IntersectionID, RoadID> = UnGraphMap::new();
     for r in map.all_roads() {
         graph.add_edge(r.src_i, r.dst_i, r.id);
@@ -225,6 +229,30 @@ impl BulkEdit {
                             .align_right(),
                     ])
                     .margin_below(5),
+                    Widget::row(vec![
+                        "Change the rightmost lane on each side to"
+                            .draw_text(ctx)
+                            .centered_vert()
+                            .margin_right(5),
+                        Widget::dropdown(
+                            ctx,
+                            "rightmost lt",
+                            LaneType::Bus,
+                            vec![
+                                Choice::new("driving", LaneType::Driving),
+                                Choice::new("bike", LaneType::Biking),
+                                Choice::new("bus", LaneType::Bus),
+                                Choice::new("construction", LaneType::Construction),
+                            ],
+                        ),
+                        Btn::text_fg("Confirm")
+                            .build(ctx, "confirm rightmost lanes", None)
+                            .align_right(),
+                    ])
+                    .margin_below(5),
+                    Btn::text_fg("Find and replace within these roads")
+                        .build_def(ctx, hotkey(Key::F))
+                        .margin_below(5),
                     Btn::text_fg("Quit").build_def(ctx, hotkey(Key::Escape)),
                 ])
                 .bg(app.cs.panel_bg)
@@ -260,6 +288,9 @@ impl State for BulkEdit {
                     apply_map_edits(ctx, app, edits);
                     return Transition::Keep;
                 }
+                "Find and replace within these roads" => {
+                    return Transition::Push(FindReplace::new(ctx, app, Some(self.roads.clone())));
+                }
                 "confirm lanes" => {
                     return Transition::Push(msg(
                         "Edited lane types",
@@ -272,6 +303,17 @@ impl State for BulkEdit {
                         ),
                     ));
                 }
+                "confirm rightmost lanes" => {
+                    return Transition::Push(msg(
+                        "Edited lane types",
+                        change_rightmost_lanes(
+                            ctx,
+                            app,
+                            &self.roads,
+                            self.composite.dropdown_value("rightmost lt"),
+                        ),
+                    ));
+                }
                 _ => unreachable!(),
             },
             None => {}
@@ -291,6 +333,7 @@ pub enum Mode {
     Pan,
     Paint,
     Erase,
+    Lasso,
 }
 
 pub struct PaintSelect {
@@ -299,24 +342,73 @@ pub struct PaintSelect {
     preview: Option<Drawable>,
     mode: Mode,
     dragging: bool,
+    // The free-hand polygon being drawn in Lasso mode
+    lasso: Vec<Pt2D>,
 }
 
 impl PaintSelect {
-    pub fn new(ctx: &mut EventCtx, app: &mut App) -> Box<dyn State> {
+    pub fn new(ctx: &mut EventCtx, app: &mut App, roads: BTreeSet<RoadID>) -> Box<dyn State> {
         app.primary.current_selection = None;
-        Box::new(PaintSelect {
-            composite: make_paint_composite(ctx, app, Mode::Paint, &BTreeSet::new()),
-            roads: BTreeSet::new(),
+        let mut state = PaintSelect {
+            composite: make_paint_composite(ctx, app, Mode::Paint, &roads),
+            roads,
             preview: None,
             mode: Mode::Paint,
             dragging: false,
-        })
+            lasso: Vec::new(),
+        };
+        state.update_preview(ctx, app);
+        Box::new(state)
+    }
+
+    fn update_preview(&mut self, ctx: &mut EventCtx, app: &App) {
+        if self.roads.is_empty() {
+            self.preview = None;
+        } else {
+            let mut batch = GeomBatch::new();
+            for r in &self.roads {
+                batch.push(
+                    Color::BLUE.alpha(0.5),
+                    app.primary
+                        .map
+                        .get_r(*r)
+                        .get_thick_polygon(&app.primary.map)
+                        .unwrap(),
+                );
+            }
+            for i in intersections_from_roads(&self.roads, &app.primary.map) {
+                batch.push(
+                    Color::BLUE.alpha(0.5),
+                    app.primary.map.get_i(i).polygon.clone(),
+                );
+            }
+            self.preview = Some(ctx.upload(batch));
+        }
+        self.composite = make_paint_composite(ctx, app, self.mode, &self.roads);
+    }
+
+    fn finish_lasso(&mut self, ctx: &mut EventCtx, app: &App) {
+        let pts = std::mem::replace(&mut self.lasso, Vec::new());
+        if pts.len() < 3 {
+            return;
+        }
+        let polygon = Polygon::new(&pts);
+        let mut changed = false;
+        for r in app.primary.map.all_roads() {
+            if polygon.contains_pt(r.center_pts.middle()) && self.roads.insert(r.id) {
+                changed = true;
+            }
+        }
+        if changed {
+            self.update_preview(ctx, app);
+        }
     }
 }
 
 impl State for PaintSelect {
     fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
-        if self.mode != Mode::Pan && ctx.redo_mouseover() {
+        // Even while panning, holding shift lets individual roads be toggled.
+        if (self.mode != Mode::Pan || ctx.canvas.is_lshift_held()) && ctx.redo_mouseover() {
             app.primary.current_selection = app.calculate_current_selection(
                 ctx,
                 &DontDrawAgents {},
@@ -335,55 +427,47 @@ impl State for PaintSelect {
 
         if self.mode == Mode::Pan {
             ctx.canvas_movement();
+            if ctx.canvas.is_lshift_held() {
+                if let Some(ID::Road(r)) = app.primary.current_selection {
+                    if app.per_obj.left_click(ctx, "toggle road") {
+                        if !self.roads.remove(&r) {
+                            self.roads.insert(r);
+                        }
+                        self.update_preview(ctx, app);
+                    }
+                }
+            }
         } else {
             if self.dragging && ctx.input.left_mouse_button_released() {
                 self.dragging = false;
+                if self.mode == Mode::Lasso {
+                    self.finish_lasso(ctx, app);
+                }
             } else if !self.dragging && ctx.input.left_mouse_button_pressed() {
                 self.dragging = true;
             }
         }
 
-        if self.dragging {
+        if self.dragging && self.mode == Mode::Lasso {
+            if let Some(pt) = ctx.canvas.get_cursor_in_map_space() {
+                if self
+                    .lasso
+                    .last()
+                    .map(|last| !last.approx_eq(pt, LASSO_RESOLUTION))
+                    .unwrap_or(true)
+                {
+                    self.lasso.push(pt);
+                }
+            }
+        } else if self.dragging {
             if let Some(ID::Road(r)) = app.primary.current_selection {
                 let change = match self.mode {
-                    Mode::Paint => {
-                        if self.roads.contains(&r) {
-                            false
-                        } else {
-                            self.roads.insert(r);
-                            true
-                        }
-                    }
-                    Mode::Erase => {
-                        if self.roads.contains(&r) {
-                            self.roads.remove(&r);
-                            true
-                        } else {
-                            false
-                        }
-                    }
-                    Mode::Pan => unreachable!(),
+                    Mode::Paint => self.roads.insert(r),
+                    Mode::Erase => self.roads.remove(&r),
+                    Mode::Pan | Mode::Lasso => unreachable!(),
                 };
                 if change {
-                    let mut batch = GeomBatch::new();
-                    for r in &self.roads {
-                        batch.push(
-                            Color::BLUE.alpha(0.5),
-                            app.primary
-                                .map
-                                .get_r(*r)
-                                .get_thick_polygon(&app.primary.map)
-                                .unwrap(),
-                        );
-                    }
-                    for i in intersections_from_roads(&self.roads, &app.primary.map) {
-                        batch.push(
-                            Color::BLUE.alpha(0.5),
-                            app.primary.map.get_i(i).polygon.clone(),
-                        );
-                    }
-                    self.preview = Some(ctx.upload(batch));
-                    self.composite = make_paint_composite(ctx, app, self.mode, &self.roads);
+                    self.update_preview(ctx, app);
                 }
             }
         }
@@ -400,6 +484,12 @@ impl State for PaintSelect {
                     self.mode = Mode::Erase;
                     self.composite = make_paint_composite(ctx, app, self.mode, &self.roads);
                 }
+                "lasso" => {
+                    app.primary.current_selection = None;
+                    self.dragging = false;
+                    self.mode = Mode::Lasso;
+                    self.composite = make_paint_composite(ctx, app, self.mode, &self.roads);
+                }
                 "pan" => {
                     app.primary.current_selection = None;
                     self.dragging = false;
@@ -412,6 +502,9 @@ impl State for PaintSelect {
                 "Select roads along a route" => {
                     return Transition::Replace(RouteSelect::new(ctx, app));
                 }
+                "Find and replace on the whole map" => {
+                    return Transition::Replace(FindReplace::new(ctx, app, None));
+                }
                 "edit roads" => {
                     return Transition::Replace(BulkEdit::new(
                         ctx,
@@ -432,15 +525,23 @@ impl State for PaintSelect {
         if let Some(ref p) = self.preview {
             g.redraw(p);
         }
+        if self.lasso.len() >= 2 {
+            g.draw_polygon(
+                Color::GREEN,
+                &PolyLine::unchecked_new(self.lasso.clone())
+                    .make_polygons(Distance::meters(2.0) / g.canvas.cam_zoom),
+            );
+        }
         if self.mode != Mode::Pan && g.canvas.get_cursor_in_map_space().is_some() {
             let mut batch = GeomBatch::new();
             batch.append(
                 GeomBatch::screenspace_svg(
                     g.prerender,
-                    if self.mode == Mode::Paint {
-                        "../data/system/assets/tools/pencil.svg"
-                    } else {
-                        "../data/system/assets/tools/eraser.svg"
+                    match self.mode {
+                        Mode::Paint => "../data/system/assets/tools/pencil.svg",
+                        Mode::Erase => "../data/system/assets/tools/eraser.svg",
+                        Mode::Lasso => "../data/system/assets/tools/select.svg",
+                        Mode::Pan => unreachable!(),
                     },
                 )
                 .centered_on(g.canvas.get_cursor().to_pt())
@@ -498,6 +599,60 @@ fn change_lane_types(
     errors
 }
 
+// Along a corridor, the rightmost lane on each side that vehicles actually move in. Parking and
+// sidewalks are skipped over.
+fn change_rightmost_lanes(
+    ctx: &mut EventCtx,
+    app: &mut App,
+    roads: &Vec<RoadID>,
+    to: LaneType,
+) -> Vec<String> {
+    let mut changes = 0;
+    let mut errors = Vec::new();
+    ctx.loading_screen("change rightmost lanes", |ctx, _| {
+        for r in roads {
+            for fwds in vec![true, false] {
+                let road = app.primary.map.get_r(*r);
+                let l = if let Some(l) = road
+                    .lanes_on_side(fwds)
+                    .into_iter()
+                    .rev()
+                    .find(|l| app.primary.map.get_l(*l).lane_type.is_for_moving_vehicles())
+                {
+                    l
+                } else {
+                    continue;
+                };
+                if app.primary.map.get_l(l).lane_type == to {
+                    continue;
+                }
+                match try_change_lane_type(l, to, &app.primary.map) {
+                    Ok(cmd) => {
+                        let mut edits = app.primary.map.get_edits().clone();
+                        edits.commands.push(cmd);
+                        apply_map_edits(ctx, app, edits);
+                        changes += 1;
+                    }
+                    Err(err) => {
+                        errors.push(err);
+                    }
+                }
+            }
+        }
+    });
+
+    errors.insert(
+        0,
+        format!(
+            "Changed {} rightmost lanes to {:?} lanes. {} errors",
+            changes,
+            to,
+            errors.len()
+        ),
+    );
+    errors
+}
+
 fn make_paint_composite(
     ctx: &mut EventCtx,
     app: &App,
@@ -537,6 +692,19 @@ fn make_paint_composite(
                         hotkey(Key::Backspace),
                     )
                 },
+                if mode == Mode::Lasso {
+                    Widget::draw_svg_transform(
+                        ctx,
+                        "../data/system/assets/tools/select.svg",
+                        RewriteColor::ChangeAll(Color::hex("#4CA7E9")),
+                    )
+                } else {
+                    Btn::svg_def("../data/system/assets/tools/select.svg").build(
+                        ctx,
+                        "lasso",
+                        hotkey(Key::L),
+                    )
+                },
                 if mode == Mode::Pan {
                     Widget::draw_svg_transform(
                         ctx,
@@ -553,6 +721,7 @@ fn make_paint_composite(
             ])
             .evenly_spaced(),
             Btn::text_fg("Select roads along a route").build_def(ctx, None),
+            Btn::text_fg("Find and replace on the whole map").build_def(ctx, hotkey(Key::F)),
             Widget::row(vec![
                 if roads.is_empty() {
                     Btn::text_fg("Edit 0 roads").inactive(ctx)
@@ -0,0 +1,358 @@
+use crate::app::App;
+use crate::common::CommonState;
+use crate::edit::lanes::try_change_lane_type;
+use crate::edit::{apply_map_edits, change_speed_limit};
+use crate::game::{msg, State, Transition};
+use ezgui::{
+    hotkey, Btn, Choice, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx,
+    HorizontalAlignment, Key, Line, Outcome, Text, TextExt, VerticalAlignment, Widget,
+};
+use geom::Speed;
+use map_model::{EditCmd, LaneType, Map, Road, RoadID};
+use std::collections::BTreeSet;
+
+// Only list this many changes; the rest are just counted.
+const MAX_LISTED: usize = 20;
+
+#[derive(Clone, Copy, PartialEq)]
+enum RoadKind {
+    Any,
+    Residential,
+    Arterial,
+    Highway,
+}
+
+impl RoadKind {
+    // Based on the same ranking used to decide stop signs
+    fn matches(self, r: &Road) -> bool {
+        let rank = r.get_rank();
+        match self {
+            RoadKind::Any => true,
+            RoadKind::Residential => rank < 6,
+            RoadKind::Arterial => rank >= 9 && rank < 18,
+            RoadKind::Highway => rank >= 18,
+        }
+    }
+}
+
+// Find roads matching a query, either across the whole map or within some already selected roads,
+// then list every change before applying any of it.
+pub struct FindReplace {
+    composite: Composite,
+    // None means the whole map
+    scope: Option<Vec<RoadID>>,
+}
+
+impl FindReplace {
+    pub fn new(ctx: &mut EventCtx, app: &App, scope: Option<Vec<RoadID>>) -> Box<dyn State> {
+        let lane_types = || {
+            vec![
+                Choice::new("driving", LaneType::Driving),
+                Choice::new("parking", LaneType::Parking),
+                Choice::new("bike", LaneType::Biking),
+                Choice::new("bus", LaneType::Bus),
+                Choice::new("construction", LaneType::Construction),
+            ]
+        };
+        let mut speed_choices = vec![Choice::new("any", None)];
+        for mph in (10..=70).step_by(5) {
+            speed_choices.push(Choice::new(
+                format!("{} mph", mph),
+                Some(Speed::miles_per_hour(mph as f64)),
+            ));
+        }
+
+        let composite = Composite::new(
+            Widget::col(vec![
+                Line("Find and replace").small_heading().draw(ctx),
+                if let Some(ref roads) = scope {
+                    format!("Searching {} selected roads", roads.len()).draw_text(ctx)
+                } else {
+                    "Searching the whole map".draw_text(ctx)
+                }
+                .margin_below(10),
+                Widget::row(vec![
+                    "Find".draw_text(ctx).centered_vert().margin_right(5),
+                    Widget::dropdown(
+                        ctx,
+                        "road kind",
+                        RoadKind::Any,
+                        vec![
+                            Choice::new("any roads", RoadKind::Any),
+                            Choice::new("residential streets", RoadKind::Residential),
+                            Choice::new("arterials", RoadKind::Arterial),
+                            Choice::new("highways", RoadKind::Highway),
+                        ],
+                    )
+                    .margin_right(5),
+                    "with speed limit"
+                        .draw_text(ctx)
+                        .centered_vert()
+                        .margin_right(5),
+                    Widget::dropdown(ctx, "find speed limit", None, speed_choices),
+                ])
+                .margin_below(10),
+                Widget::row(vec![
+                    change_speed_limit(ctx, Speed::miles_per_hour(20.0)),
+                    Btn::text_fg("Preview")
+                        .build(ctx, "preview speed limits", None)
+                        .align_right(),
+                ])
+                .margin_below(5),
+                Widget::row(vec![
+                    "Change all".draw_text(ctx).centered_vert().margin_right(5),
+                    Widget::dropdown(ctx, "from lt", LaneType::Parking, lane_types())
+                        .margin_right(5),
+                    "lanes to".draw_text(ctx).centered_vert().margin_right(5),
+                    Widget::dropdown(ctx, "to lt", LaneType::Bus, lane_types()),
+                    Btn::text_fg("Preview")
+                        .build(ctx, "preview lanes", None)
+                        .align_right(),
+                ])
+                .margin_below(5),
+                Btn::text_fg("Quit").build_def(ctx, hotkey(Key::Escape)),
+            ])
+            .bg(app.cs.panel_bg)
+            .padding(10),
+        )
+        .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+        .build(ctx);
+
+        Box::new(FindReplace { composite, scope })
+    }
+
+    fn find_roads(&self, map: &Map) -> Vec<RoadID> {
+        let kind: RoadKind = self.composite.dropdown_value("road kind");
+        let speed: Option<Speed> = self.composite.dropdown_value("find speed limit");
+        let candidates: Vec<RoadID> = if let Some(ref roads) = self.scope {
+            roads.clone()
+        } else {
+            map.all_roads().iter().map(|r| r.id).collect()
+        };
+        candidates
+            .into_iter()
+            .filter(|r| {
+                let r = map.get_r(*r);
+                kind.matches(r)
+                    && speed
+                        .map(|s| round_mph(s) == round_mph(r.speed_limit))
+                        .unwrap_or(true)
+            })
+            .collect()
+    }
+}
+
+impl State for FindReplace {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "Quit" => {
+                    return Transition::Pop;
+                }
+                "preview speed limits" => {
+                    let speed = self.composite.dropdown_value("speed limit");
+                    let map = &app.primary.map;
+                    let cmds = self
+                        .find_roads(map)
+                        .into_iter()
+                        .filter(|r| map.get_r(*r).speed_limit != speed)
+                        .map(|r| EditCmd::ChangeSpeedLimit {
+                            id: r,
+                            new: speed,
+                            old: map.get_r(r).speed_limit,
+                        })
+                        .collect();
+                    return Transition::Push(ReviewChanges::new(ctx, app, cmds, Vec::new()));
+                }
+                "preview lanes" => {
+                    let from: LaneType = self.composite.dropdown_value("from lt");
+                    let to: LaneType = self.composite.dropdown_value("to lt");
+                    let map = &app.primary.map;
+                    let mut cmds = Vec::new();
+                    let mut errors = Vec::new();
+                    for r in self.find_roads(map) {
+                        for l in map.get_r(r).all_lanes() {
+                            if map.get_l(l).lane_type != from {
+                                continue;
+                            }
+                            match try_change_lane_type(l, to, map) {
+                                Ok(cmd) => cmds.push(cmd),
+                                Err(err) => errors.push(err),
+                            }
+                        }
+                    }
+                    return Transition::Push(ReviewChanges::new(ctx, app, cmds, errors));
+                }
+                _ => unreachable!(),
+            },
+            None => {}
+        }
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+// Lists every change a query would make, and highlights the roads involved, before applying them.
+struct ReviewChanges {
+    composite: Composite,
+    cmds: Vec<EditCmd>,
+    preview: Drawable,
+}
+
+impl ReviewChanges {
+    fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        cmds: Vec<EditCmd>,
+        errors: Vec<String>,
+    ) -> Box<dyn State> {
+        let map = &app.primary.map;
+        let mut roads = BTreeSet::new();
+        for cmd in &cmds {
+            match cmd {
+                EditCmd::ChangeSpeedLimit { id, .. } => {
+                    roads.insert(*id);
+                }
+                EditCmd::ChangeLaneType { id, .. } => {
+                    roads.insert(map.get_l(*id).parent);
+                }
+                _ => unreachable!(),
+            }
+        }
+        let mut batch = GeomBatch::new();
+        for r in &roads {
+            batch.push(
+                Color::RED.alpha(0.5),
+                map.get_r(*r).get_thick_polygon(map).unwrap(),
+            );
+        }
+
+        let mut txt = Text::new();
+        for cmd in cmds.iter().take(MAX_LISTED) {
+            txt.add(Line(cmd.short_name()));
+        }
+        if cmds.len() > MAX_LISTED {
+            txt.add(Line(format!("... and {} more", cmds.len() - MAX_LISTED)).secondary());
+        }
+        if !errors.is_empty() {
+            txt.add(Line(format!("{} lanes can't be changed:", errors.len())));
+            for err in errors.iter().take(MAX_LISTED) {
+                txt.add(Line(err).secondary());
+            }
+        }
+
+        Box::new(ReviewChanges {
+            composite: Composite::new(
+                Widget::col(vec![
+                    Line(format!("{} changes to {} roads", cmds.len(), roads.len()))
+                        .small_heading()
+                        .draw(ctx),
+                    txt.draw(ctx).margin_below(10),
+                    Widget::row(vec![
+                        if cmds.is_empty() {
+                            Btn::text_fg("Apply").inactive(ctx)
+                        } else {
+                            Btn::text_fg("Apply").build_def(ctx, hotkey(Key::Enter))
+                        }
+                        .margin_right(10),
+                        Btn::text_fg("Cancel").build_def(ctx, hotkey(Key::Escape)),
+                    ]),
+                ])
+                .bg(app.cs.panel_bg)
+                .padding(10),
+            )
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            cmds,
+            preview: ctx.upload(batch),
+        })
+    }
+}
+
+impl State for ReviewChanges {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "Cancel" => {
+                    return Transition::Pop;
+                }
+                "Apply" => {
+                    let cmds = std::mem::replace(&mut self.cmds, Vec::new());
+                    return Transition::Replace(msg(
+                        "Find and replace",
+                        apply_changes(ctx, app, cmds),
+                    ));
+                }
+                _ => unreachable!(),
+            },
+            None => {}
+        }
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.preview);
+        self.composite.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn apply_changes(ctx: &mut EventCtx, app: &mut App, cmds: Vec<EditCmd>) -> Vec<String> {
+    let total = cmds.len();
+    let mut changes = 0;
+    let mut errors = Vec::new();
+    ctx.loading_screen("find and replace", |ctx, _| {
+        let mut edits = app.primary.map.get_edits().clone();
+        let mut lane_changes = Vec::new();
+        for cmd in cmds {
+            if let EditCmd::ChangeLaneType { id, lt, .. } = cmd {
+                lane_changes.push((id, lt));
+            } else {
+                edits.commands.push(cmd);
+                changes += 1;
+            }
+        }
+        if changes > 0 {
+            apply_map_edits(ctx, app, edits);
+        }
+
+        // Earlier lane changes might make later ones invalid, so check each one again as it
+        // happens.
+        for (l, lt) in lane_changes {
+            match try_change_lane_type(l, lt, &app.primary.map) {
+                Ok(cmd) => {
+                    let mut edits = app.primary.map.get_edits().clone();
+                    edits.commands.push(cmd);
+                    apply_map_edits(ctx, app, edits);
+                    changes += 1;
+                }
+                Err(err) => {
+                    errors.push(err);
+                }
+            }
+        }
+    });
+
+    errors.insert(
+        0,
+        format!(
+            "Made {} of {} changes. {} errors",
+            changes,
+            total,
+            errors.len()
+        ),
+    );
+    errors
+}
+
+fn round_mph(s: Speed) -> f64 {
+    (s.inner_meters_per_second() / Speed::miles_per_hour(1.0).inner_meters_per_second()).round()
+}
@@ -0,0 +1,151 @@
+use crate::app::{App, ShowEverything};
+use crate::common::CommonState;
+use crate::edit::apply_map_edits;
+use crate::game::{State, Transition};
+use crate::helpers::ID;
+use ezgui::{
+    hotkey, Btn, Color, Composite, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome,
+    TextExt, VerticalAlignment, Widget,
+};
+use geom::{Circle, Distance, FindClosest};
+use map_model::{EditCmd, Footbridge, LaneID, Position, RoadID};
+use sim::DontDrawAgents;
+
+// Click a spot on one sidewalk of a road, then a spot on the other, to build a footbridge or
+// underpass between them.
+pub struct FootbridgePlacer {
+    composite: Composite,
+    r: RoadID,
+    underpass: bool,
+    from: Option<Position>,
+}
+
+impl FootbridgePlacer {
+    pub fn new(ctx: &mut EventCtx, app: &mut App, r: RoadID, underpass: bool) -> Box<dyn State> {
+        app.primary.current_selection = None;
+        Box::new(FootbridgePlacer {
+            composite: make_panel(ctx, app, underpass, false),
+            r,
+            underpass,
+            from: None,
+        })
+    }
+
+    // Where on the sidewalk the cursor is
+    fn cursor_pos(&self, ctx: &EventCtx, app: &App, l: LaneID) -> Option<Position> {
+        let lane = app.primary.map.get_l(l);
+        let mut closest = FindClosest::new(app.primary.map.get_bounds());
+        closest.add((), lane.lane_center_pts.points());
+        let pt = ctx.canvas.get_cursor_in_map_space()?;
+        let (_, pt) = closest.closest_pt(pt, lane.width * 2.0)?;
+        let (dist, _) = lane.lane_center_pts.dist_along_of_point(pt)?;
+        Some(Position::new(l, dist))
+    }
+}
+
+impl State for FootbridgePlacer {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.primary.current_selection = app.calculate_current_selection(
+                ctx,
+                &DontDrawAgents {},
+                &ShowEverything::new(),
+                false,
+                false,
+                false,
+            );
+            // Only the sidewalks of this road, and the second click has to be on the other side
+            match app.primary.current_selection {
+                Some(ID::Lane(l))
+                    if app.primary.map.get_l(l).parent == self.r
+                        && app.primary.map.get_l(l).is_sidewalk()
+                        && self.from.map(|pos| pos.lane() != l).unwrap_or(true) => {}
+                _ => {
+                    app.primary.current_selection = None;
+                }
+            }
+        }
+
+        if let Some(ID::Lane(l)) = app.primary.current_selection {
+            let label = if self.from.is_none() {
+                "start here"
+            } else {
+                "end here"
+            };
+            if app.per_obj.left_click(ctx, label) {
+                if let Some(pos) = self.cursor_pos(ctx, app, l) {
+                    if let Some(from) = self.from {
+                        let r = app.primary.map.get_r(self.r);
+                        let mut new = r.footbridges.clone();
+                        new.push(Footbridge {
+                            from,
+                            to: pos,
+                            underpass: self.underpass,
+                        });
+                        let mut edits = app.primary.map.get_edits().clone();
+                        edits.commands.push(EditCmd::ChangeFootbridges {
+                            id: self.r,
+                            new,
+                            old: r.footbridges.clone(),
+                        });
+                        apply_map_edits(ctx, app, edits);
+                        return Transition::Pop;
+                    }
+                    self.from = Some(pos);
+                    self.composite = make_panel(ctx, app, self.underpass, true);
+                    app.primary.current_selection = None;
+                }
+            }
+        }
+
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "Quit" => {
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            },
+            None => {}
+        }
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        if let Some(pos) = self.from {
+            g.draw_circle(
+                Color::RED,
+                &Circle::new(pos.pt(&app.primary.map), Distance::meters(1.0)),
+            );
+        }
+        self.composite.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App, underpass: bool, started: bool) -> Composite {
+    Composite::new(
+        Widget::col(vec![
+            Line(if underpass {
+                "Build an underpass"
+            } else {
+                "Build a footbridge"
+            })
+            .small_heading()
+            .draw(ctx),
+            if started {
+                "Click where it reaches the other sidewalk"
+            } else {
+                "Click where it starts on one sidewalk"
+            }
+            .draw_text(ctx),
+            Btn::text_fg("Quit")
+                .build_def(ctx, hotkey(Key::Escape))
+                .margin_above(10),
+        ])
+        .bg(app.cs.panel_bg)
+        .padding(10),
+    )
+    .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+    .build(ctx)
+}
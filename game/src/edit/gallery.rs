@@ -0,0 +1,257 @@
+use crate::app::App;
+use crate::edit::{apply_map_edits, cmd_to_id};
+use crate::game::{msg, State, Transition};
+use crate::helpers::ID;
+use ezgui::{
+    hotkey, Btn, Composite, EventCtx, EventLoopMode, GfxCtx, Key, Line, Outcome, Text, Widget,
+};
+use geom::{Polygon, Pt2D};
+use map_model::{Map, MapEdits};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+
+struct Target {
+    id: ID,
+    center: Pt2D,
+    zoom: f64,
+    // Short descriptions of every change touching this object
+    changes: Vec<String>,
+}
+
+// Takes a before and after screenshot of every object the current edits touch, one per event,
+// then writes an HTML page showing them side by side. Can be stopped partway; the proposal is
+// restored either way.
+pub struct Gallery {
+    dir: String,
+    targets: Vec<Target>,
+    // The proposal, restored once the "before" pictures are taken
+    edits: MapEdits,
+    before: bool,
+    idx: usize,
+    started: bool,
+    orig_camera: (f64, f64, f64),
+    // Not in the screenshots
+    composite: Composite,
+}
+
+impl Gallery {
+    pub fn new(ctx: &mut EventCtx, app: &mut App) -> Box<dyn State> {
+        let map = &app.primary.map;
+        let edits = map.get_edits().clone();
+        let mut changes: BTreeMap<ID, Vec<String>> = BTreeMap::new();
+        for cmd in &edits.commands {
+            changes
+                .entry(cmd_to_id(cmd))
+                .or_insert_with(Vec::new)
+                .push(cmd.short_name());
+        }
+        // Frame each object the same way before and after, based on what it looks like with the
+        // edits.
+        let targets = changes
+            .into_iter()
+            .map(|(id, changes)| {
+                let bounds = outline(&id, map).get_bounds();
+                // Leave plenty of context around the object
+                let zoom = (ctx.canvas.window_width / bounds.width())
+                    .min(ctx.canvas.window_height / bounds.height())
+                    * 0.3;
+                Target {
+                    center: id.canonical_point(&app.primary).unwrap(),
+                    id,
+                    zoom: zoom.max(1.0).min(20.0),
+                    changes,
+                }
+            })
+            .collect();
+
+        let dir = format!(
+            "{}/{}",
+            abstutil::path_pending_screenshots(map.get_name()),
+            edits.edits_name.replace(" ", "_")
+        );
+        let orig_camera = (ctx.canvas.cam_x, ctx.canvas.cam_y, ctx.canvas.cam_zoom);
+        app.primary.current_selection = None;
+        apply_map_edits(ctx, app, MapEdits::new());
+
+        let composite = make_panel(ctx, app, true, 0, targets.len());
+        let gallery = Gallery {
+            dir,
+            targets,
+            edits,
+            before: true,
+            idx: 0,
+            started: false,
+            orig_camera,
+            composite,
+        };
+        // Don't mistake pictures from an earlier run for new ones
+        for idx in 0..gallery.targets.len() {
+            let _ = std::fs::remove_file(gallery.filename(true, idx));
+            let _ = std::fs::remove_file(gallery.filename(false, idx));
+        }
+        Box::new(gallery)
+    }
+
+    fn filename(&self, before: bool, idx: usize) -> String {
+        format!(
+            "{}/{}_{}.png",
+            self.dir,
+            if before { "before" } else { "after" },
+            idx
+        )
+    }
+
+    // Puts back the proposal and the camera, then reports what actually got saved. Some backends
+    // can't take screenshots at all, and only print why.
+    fn finish(&self, ctx: &mut EventCtx, app: &mut App, stopped: bool) -> Transition {
+        if self.before {
+            apply_map_edits(ctx, app, self.edits.clone());
+        }
+        let (x, y, zoom) = self.orig_camera;
+        ctx.canvas.cam_x = x;
+        ctx.canvas.cam_y = y;
+        ctx.canvas.cam_zoom = zoom;
+
+        let saved: Vec<usize> = (0..self.targets.len())
+            .filter(|idx| {
+                Path::new(&self.filename(true, *idx)).exists()
+                    && Path::new(&self.filename(false, *idx)).exists()
+            })
+            .collect();
+        let mut lines = Vec::new();
+        if stopped {
+            lines.push("Stopped early".to_string());
+        }
+        if saved.is_empty() {
+            lines.push("No screenshots were saved. Check STDOUT for errors.".to_string());
+        } else {
+            lines.push(match self.write_html(&saved) {
+                Ok(path) => format!(
+                    "Saved {} of {} before/after pairs; open {}",
+                    saved.len(),
+                    self.targets.len(),
+                    path
+                ),
+                Err(err) => format!("Couldn't write the gallery: {}", err),
+            });
+        }
+        Transition::Replace(msg("Screenshots", lines))
+    }
+
+    // Only includes the pairs that were saved
+    fn write_html(&self, saved: &Vec<usize>) -> std::io::Result<String> {
+        let path = format!("{}/index.html", self.dir);
+        let mut f = File::create(&path)?;
+        let title = escape_html(&self.edits.edits_name);
+        writeln!(f, "<html><head><title>{}</title></head><body>", title)?;
+        writeln!(f, "<h1>{}</h1>", title)?;
+        for idx in saved {
+            let target = &self.targets[*idx];
+            writeln!(f, "<h2>{}</h2>", escape_html(&format!("{:?}", target.id)))?;
+            writeln!(f, "<ul>")?;
+            for change in &target.changes {
+                writeln!(f, "<li>{}</li>", escape_html(change))?;
+            }
+            writeln!(f, "</ul>")?;
+            writeln!(
+                f,
+                "<img src=\"before_{}.png\" width=\"45%\"> <img src=\"after_{}.png\" \
+                 width=\"45%\">",
+                idx, idx
+            )?;
+        }
+        writeln!(f, "</body></html>")?;
+        Ok(path)
+    }
+}
+
+impl State for Gallery {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "stop" => {
+                    return self.finish(ctx, app, true);
+                }
+                _ => unreachable!(),
+            },
+            None => {}
+        }
+
+        // Screencapping doesn't keep events coming by itself
+        if !self.started {
+            self.started = true;
+            return Transition::KeepWithMode(EventLoopMode::Animation);
+        }
+        if self.idx < self.targets.len() {
+            let target = &self.targets[self.idx];
+            ctx.canvas.cam_zoom = target.zoom;
+            ctx.canvas.center_on_map_pt(target.center);
+            let filename = self.filename(self.before, self.idx);
+            self.idx += 1;
+            self.composite = make_panel(ctx, app, self.before, self.idx, self.targets.len());
+            return Transition::KeepWithMode(EventLoopMode::ScreenCapture {
+                filename,
+                width: WIDTH,
+                height: HEIGHT,
+            });
+        }
+
+        if self.before {
+            self.before = false;
+            self.idx = 0;
+            apply_map_edits(ctx, app, self.edits.clone());
+            return Transition::KeepWithMode(EventLoopMode::Animation);
+        }
+
+        self.finish(ctx, app, false)
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.composite.draw(g);
+    }
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App, before: bool, done: usize, total: usize) -> Composite {
+    Composite::new(
+        Widget::col(vec![
+            Text::from(Line(format!(
+                "Taking {} screenshots: {}/{}",
+                if before { "before" } else { "after" },
+                done,
+                total
+            )))
+            .draw(ctx),
+            Btn::text_bg2("stop")
+                .build_def(ctx, hotkey(Key::Escape))
+                .centered_horiz(),
+        ])
+        .padding(10)
+        .bg(app.cs.panel_bg),
+    )
+    .build(ctx)
+}
+
+fn escape_html(x: &str) -> String {
+    x.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Roughly what needs to fit in the picture
+fn outline(id: &ID, map: &Map) -> Polygon {
+    match id {
+        ID::Lane(l) => {
+            let lane = map.get_l(*l);
+            lane.lane_center_pts.make_polygons(lane.width)
+        }
+        ID::Road(r) => map.get_r(*r).get_thick_polygon(map).unwrap(),
+        ID::Intersection(i) => map.get_i(*i).polygon.clone(),
+        _ => unreachable!(),
+    }
+}
@@ -1,27 +1,89 @@
 use crate::app::App;
 use crate::common::CommonState;
+use crate::edit::footbridges::FootbridgePlacer;
 use crate::edit::{apply_map_edits, can_edit_lane, change_speed_limit};
 use crate::game::{msg, State, Transition};
 use crate::helpers::ID;
 use crate::render::Renderable;
 use crate::sandbox::GameplayMode;
 use ezgui::{
-    hotkey, Btn, Color, Composite, EventCtx, GfxCtx, HorizontalAlignment, Key, Outcome,
-    RewriteColor, TextExt, VerticalAlignment, Widget,
+    hotkey, Btn, Checkbox, Choice, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx,
+    HorizontalAlignment, Key, Outcome, RewriteColor, TextExt, VerticalAlignment, Widget,
+};
+use geom::{Distance, Duration, Speed};
+use map_model::{
+    propose_detour, EditCmd, LaneID, LaneType, Map, Median, ParkingRestrictions, ScooterParking,
+    TimedSpeedLimit,
 };
-use map_model::{EditCmd, LaneID, LaneType, Map};
 use std::collections::BTreeSet;
 
 pub struct LaneEditor {
     l: LaneID,
     mode: GameplayMode,
     composite: Composite,
+    // The signed detour around this lane's road, if it's closed
+    detour: Option<Drawable>,
 }
 
 impl LaneEditor {
     pub fn new(ctx: &mut EventCtx, app: &App, l: LaneID, mode: GameplayMode) -> LaneEditor {
-        let mut row = Vec::new();
         let lt = app.primary.map.get_l(l).lane_type;
+        // Sidewalks can't change type, but they can change where scooters may be left.
+        if lt == LaneType::Sidewalk {
+            let parent = app.primary.map.get_parent(l);
+            let mut col = vec![
+                format!(
+                    "Scooter parking along this sidewalk of {}",
+                    parent.get_name()
+                )
+                .draw_text(ctx)
+                .centered_horiz()
+                .margin_below(5),
+                change_scooter_parking(ctx, app.primary.map.get_l(l).scooter_parking)
+                    .margin_below(5),
+                change_lane_width(ctx, app.primary.map.get_l(l).width).margin_below(5),
+            ];
+            // Footbridges need somewhere to land on the other side
+            let num_sidewalks = parent
+                .all_lanes()
+                .into_iter()
+                .filter(|l| app.primary.map.get_l(*l).is_sidewalk())
+                .count();
+            if num_sidewalks == 2 {
+                col.push(
+                    Widget::row(vec![
+                        Btn::text_fg("build a footbridge").build_def(ctx, None),
+                        Btn::text_fg("build an underpass").build_def(ctx, None),
+                    ])
+                    .centered()
+                    .margin_below(5),
+                );
+            }
+            if !parent.footbridges.is_empty() {
+                col.push(
+                    Btn::text_fg(format!("remove {} footbridges", parent.footbridges.len()))
+                        .build(ctx, "remove footbridges", None)
+                        .centered_horiz()
+                        .margin_below(5),
+                );
+            }
+            col.push(
+                Btn::text_fg("Finish")
+                    .build_def(ctx, hotkey(Key::Escape))
+                    .centered_horiz(),
+            );
+            let composite = Composite::new(Widget::col(col).bg(app.cs.panel_bg).padding(10))
+                .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+                .build(ctx);
+            return LaneEditor {
+                l,
+                mode,
+                composite,
+                detour: None,
+            };
+        }
+
+        let mut row = Vec::new();
         for (icon, label, key, active) in vec![
             (
                 "driving",
@@ -74,17 +136,89 @@ impl LaneEditor {
         }
 
         let parent = app.primary.map.get_parent(l);
-        let col = vec![
+        let mut col = vec![
             format!("Convert this lane of {} to what type?", parent.get_name())
                 .draw_text(ctx)
                 .centered_horiz(),
             Widget::row(row).centered().margin_below(5),
+            change_lane_width(ctx, app.primary.map.get_l(l).width).margin_below(5),
             change_speed_limit(ctx, parent.speed_limit).margin_below(5),
+            change_timed_speed_limits(ctx, &parent.timed_speed_limits).margin_below(5),
+            Checkbox::text(ctx, "snow route", None, parent.snow_route).margin_below(5),
+            change_median(ctx, parent.median).margin_below(5),
+        ];
+        if parent.can_consolidate_junction(&app.primary.map) {
+            col.push(
+                Checkbox::text(
+                    ctx,
+                    "one junction with both ends",
+                    None,
+                    parent.consolidated_junction,
+                )
+                .margin_below(5),
+            );
+        }
+        if parent.has_sidewalks() {
+            for i in vec![parent.src_i, parent.dst_i] {
+                col.push(
+                    Checkbox::text(
+                        ctx,
+                        &format!("curb ramps at {}", i),
+                        None,
+                        !parent.missing_curb_ramps.contains(&i),
+                    )
+                    .margin_below(5),
+                );
+            }
+        }
+        if lt == LaneType::Parking {
+            col.push(
+                change_parking_restrictions(ctx, &app.primary.map.get_l(l).parking_restrictions)
+                    .margin_below(5),
+            );
+        }
+        let mut detour = None;
+        if parent.is_closed() {
+            if parent.detour.is_empty() {
+                col.push(
+                    Btn::text_fg("sign a detour around this road")
+                        .build_def(ctx, None)
+                        .centered_horiz()
+                        .margin_below(5),
+                );
+            } else {
+                col.push(
+                    Btn::text_fg("remove the detour")
+                        .build_def(ctx, None)
+                        .centered_horiz()
+                        .margin_below(5),
+                );
+                let mut batch = GeomBatch::new();
+                for r in &parent.detour {
+                    batch.push(
+                        Color::ORANGE.alpha(0.5),
+                        app.primary
+                            .map
+                            .get_r(*r)
+                            .get_thick_polygon(&app.primary.map)
+                            .unwrap(),
+                    );
+                }
+                detour = Some(ctx.upload(batch));
+            }
+        }
+        col.push(
             Widget::row(vec![
                 Btn::text_fg("Finish").build_def(ctx, hotkey(Key::Escape)),
                 // TODO Handle reverting speed limit too...
                 if app.primary.map.get_edits().original_lts.contains_key(&l)
                     || app.primary.map.get_edits().reversed_lanes.contains(&l)
+                    || app
+                        .primary
+                        .map
+                        .get_edits()
+                        .original_lane_widths
+                        .contains_key(&l)
                 {
                     Btn::text_fg("Revert").build_def(ctx, hotkey(Key::R))
                 } else {
@@ -92,13 +226,18 @@ impl LaneEditor {
                 },
             ])
             .centered(),
-        ];
+        );
 
         let composite = Composite::new(Widget::col(col).bg(app.cs.panel_bg).padding(10))
             .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
             .build(ctx);
 
-        LaneEditor { l, mode, composite }
+        LaneEditor {
+            l,
+            mode,
+            composite,
+            detour,
+        }
     }
 }
 
@@ -147,6 +286,45 @@ impl State for LaneEditor {
                         try_change_lane_type(self.l, LaneType::Construction, map)
                     }
                     "reverse lane direction" => try_reverse(self.l, map),
+                    "sign a detour around this road" => {
+                        let r = map.get_parent(self.l);
+                        match propose_detour(map, r.id) {
+                            Some(new) => Ok(EditCmd::SignDetour {
+                                id: r.id,
+                                new,
+                                old: r.detour.clone(),
+                            }),
+                            None => Err(format!(
+                                "There's no way for drivers around {}",
+                                r.get_name()
+                            )),
+                        }
+                    }
+                    "remove the detour" => {
+                        let r = map.get_parent(self.l);
+                        Ok(EditCmd::SignDetour {
+                            id: r.id,
+                            new: Vec::new(),
+                            old: r.detour.clone(),
+                        })
+                    }
+                    "build a footbridge" | "build an underpass" => {
+                        let r = map.get_l(self.l).parent;
+                        return Transition::Push(FootbridgePlacer::new(
+                            ctx,
+                            app,
+                            r,
+                            x == "build an underpass",
+                        ));
+                    }
+                    "remove footbridges" => {
+                        let r = map.get_parent(self.l);
+                        Ok(EditCmd::ChangeFootbridges {
+                            id: r.id,
+                            new: Vec::new(),
+                            old: r.footbridges.clone(),
+                        })
+                    }
                     "Finish" => {
                         return Transition::Pop;
                     }
@@ -154,6 +332,13 @@ impl State for LaneEditor {
                         // TODO It's hard to revert both changes at once.
                         if let Some(lt) = map.get_edits().original_lts.get(&self.l) {
                             try_change_lane_type(self.l, *lt, map)
+                        } else if let Some(old) = map.get_edits().original_lane_widths.get(&self.l)
+                        {
+                            Ok(EditCmd::ChangeLaneWidth {
+                                id: self.l,
+                                new: *old,
+                                old: map.get_l(self.l).width,
+                            })
                         } else {
                             try_reverse(self.l, map)
                         }
@@ -179,15 +364,102 @@ impl State for LaneEditor {
             }
             None => {
                 let parent = app.primary.map.get_parent(self.l);
-                let new = self.composite.dropdown_value("speed limit");
-                let old = parent.speed_limit;
-                if new != old {
-                    let mut edits = app.primary.map.get_edits().clone();
-                    edits.commands.push(EditCmd::ChangeSpeedLimit {
-                        id: parent.id,
-                        new,
-                        old,
+                let lane = app.primary.map.get_l(self.l);
+                let mut cmd = None;
+                let new_width = self.composite.dropdown_value("lane width");
+                if new_width != lane.width {
+                    cmd = Some(EditCmd::ChangeLaneWidth {
+                        id: self.l,
+                        new: new_width,
+                        old: lane.width,
                     });
+                } else if lane.is_sidewalk() {
+                    let new = self.composite.dropdown_value("scooter parking");
+                    let old = lane.scooter_parking;
+                    if new != old {
+                        cmd = Some(EditCmd::ChangeScooterParking {
+                            id: self.l,
+                            new,
+                            old,
+                        });
+                    }
+                } else {
+                    let new = self.composite.dropdown_value("speed limit");
+                    let old = parent.speed_limit;
+                    if new != old {
+                        cmd = Some(EditCmd::ChangeSpeedLimit {
+                            id: parent.id,
+                            new,
+                            old,
+                        });
+                    } else if self
+                        .composite
+                        .dropdown_value::<Vec<TimedSpeedLimit>>("time-of-day speed limits")
+                        != parent.timed_speed_limits
+                    {
+                        cmd = Some(EditCmd::ChangeTimedSpeedLimits {
+                            id: parent.id,
+                            new: self.composite.dropdown_value("time-of-day speed limits"),
+                            old: parent.timed_speed_limits.clone(),
+                        });
+                    } else if self.composite.dropdown_value::<Option<Median>>("median")
+                        != parent.median
+                    {
+                        cmd = Some(EditCmd::ChangeMedian {
+                            id: parent.id,
+                            new: self.composite.dropdown_value("median"),
+                            old: parent.median,
+                        });
+                    } else if self.composite.is_checked("snow route") != parent.snow_route {
+                        cmd = Some(EditCmd::ChangeSnowRoute {
+                            id: parent.id,
+                            new: !parent.snow_route,
+                            old: parent.snow_route,
+                        });
+                    } else if self.composite.has_widget("one junction with both ends")
+                        && self.composite.is_checked("one junction with both ends")
+                            != parent.consolidated_junction
+                    {
+                        cmd = Some(EditCmd::ConsolidateJunction {
+                            id: parent.id,
+                            new: !parent.consolidated_junction,
+                            old: parent.consolidated_junction,
+                        });
+                    } else if lane.lane_type == LaneType::Parking {
+                        let old = lane.parking_restrictions.clone();
+                        let (pudo_zone, loading_zone) = self.composite.dropdown_value("curb use");
+                        let new = ParkingRestrictions {
+                            cents_per_hour: self.composite.dropdown_value("parking price"),
+                            max_duration: self.composite.dropdown_value("parking time limit"),
+                            banned_hours: self.composite.dropdown_value("no parking"),
+                            pudo_zone,
+                            loading_zone,
+                        };
+                        if new != old {
+                            cmd = Some(EditCmd::ChangeParkingRestrictions {
+                                id: self.l,
+                                new,
+                                old,
+                            });
+                        }
+                    }
+                }
+                if cmd.is_none() && parent.has_sidewalks() {
+                    for i in vec![parent.src_i, parent.dst_i] {
+                        let old = !parent.missing_curb_ramps.contains(&i);
+                        if self.composite.is_checked(&format!("curb ramps at {}", i)) != old {
+                            cmd = Some(EditCmd::ChangeCurbRamps {
+                                id: parent.id,
+                                i,
+                                new: !old,
+                                old,
+                            });
+                        }
+                    }
+                }
+                if let Some(cmd) = cmd {
+                    let mut edits = app.primary.map.get_edits().clone();
+                    edits.commands.push(cmd);
                     apply_map_edits(ctx, app, edits);
                     return Transition::Replace(Box::new(LaneEditor::new(
                         ctx,
@@ -210,11 +482,192 @@ impl State for LaneEditor {
                 .get_l(self.l)
                 .get_outline(&app.primary.map),
         );
+        if let Some(ref d) = self.detour {
+            g.redraw(d);
+        }
         self.composite.draw(g);
         CommonState::draw_osd(g, app);
     }
 }
 
+fn change_parking_restrictions(ctx: &mut EventCtx, current: &ParkingRestrictions) -> Widget {
+    let mut prices: Vec<Choice<usize>> = vec![Choice::new("free", 0)];
+    for dollars in 1..=5 {
+        prices.push(Choice::new(format!("${} / hour", dollars), dollars * 100));
+    }
+    let mut limits = vec![Choice::new("no time limit", None)];
+    for hours in vec![1, 2, 4] {
+        limits.push(Choice::new(
+            format!("{} hour max", hours),
+            Some(Duration::hours(hours)),
+        ));
+    }
+    let mut bans = vec![Choice::new("no bans", None)];
+    for (label, hours) in vec![
+        ("morning rush (7-9am)", (7, 9)),
+        ("evening rush (4-6pm)", (16, 18)),
+        ("both rush hours (7am-6pm)", (7, 18)),
+        ("overnight (2-6am)", (2, 6)),
+    ] {
+        bans.push(Choice::new(label, Some(hours)));
+    }
+    // Edits loaded from a file might have other values
+    if !prices.iter().any(|c| c.data == current.cents_per_hour) {
+        prices.push(Choice::new(
+            format!("{} cents / hour", current.cents_per_hour),
+            current.cents_per_hour,
+        ));
+    }
+    if !limits.iter().any(|c| c.data == current.max_duration) {
+        limits.push(Choice::new(
+            format!("{} max", current.max_duration.unwrap()),
+            current.max_duration,
+        ));
+    }
+    if !bans.iter().any(|c| c.data == current.banned_hours) {
+        let (start, end) = current.banned_hours.unwrap();
+        bans.push(Choice::new(
+            format!("{}:00 - {}:00", start, end),
+            current.banned_hours,
+        ));
+    }
+
+    Widget::col(vec![
+        "Parking rules:".draw_text(ctx).margin_below(5),
+        Widget::row(vec![
+            Widget::dropdown(ctx, "parking price", current.cents_per_hour, prices).margin_right(5),
+            Widget::dropdown(ctx, "parking time limit", current.max_duration, limits)
+                .margin_right(5),
+            Widget::dropdown(ctx, "no parking", current.banned_hours, bans).margin_right(5),
+            Widget::dropdown(
+                ctx,
+                "curb use",
+                (current.pudo_zone, current.loading_zone),
+                vec![
+                    Choice::new("parking", (false, false)),
+                    Choice::new("pickup / drop-off zone", (true, false)),
+                    Choice::new("loading zone", (false, true)),
+                ],
+            ),
+        ]),
+    ])
+}
+
+fn change_timed_speed_limits(ctx: &mut EventCtx, current: &Vec<TimedSpeedLimit>) -> Widget {
+    let slow = |mph: f64, hours: (usize, usize), trucks_only: bool| TimedSpeedLimit {
+        limit: Speed::miles_per_hour(mph),
+        hours,
+        trucks_only,
+    };
+    let mut choices = vec![
+        Choice::new("none", Vec::new()),
+        Choice::new(
+            "school zone (20 mph 7-9am, 2-4pm)",
+            vec![slow(20.0, (7, 9), false), slow(20.0, (14, 16), false)],
+        ),
+        Choice::new(
+            "school zone (20 mph 7am-4pm)",
+            vec![slow(20.0, (7, 16), false)],
+        ),
+        Choice::new(
+            "night slow zone (20 mph 10pm-6am)",
+            vec![slow(20.0, (22, 6), false)],
+        ),
+        Choice::new(
+            "night truck limit (25 mph 10pm-6am)",
+            vec![slow(25.0, (22, 6), true)],
+        ),
+    ];
+    // Edits and OSM data might have other values
+    if !choices.iter().any(|c| &c.data == current) {
+        choices.push(Choice::new(
+            current
+                .iter()
+                .map(|x| x.describe())
+                .collect::<Vec<_>>()
+                .join(", "),
+            current.clone(),
+        ));
+    }
+
+    Widget::row(vec![
+        "Time-of-day speed limits:"
+            .draw_text(ctx)
+            .centered_vert()
+            .margin_right(15),
+        Widget::dropdown(ctx, "time-of-day speed limits", current.clone(), choices),
+    ])
+}
+
+fn change_scooter_parking(ctx: &mut EventCtx, current: ScooterParking) -> Widget {
+    let mut choices = vec![Choice::new("anywhere", ScooterParking::Anywhere)];
+    for n in vec![5, 10, 20] {
+        choices.push(Choice::new(
+            format!("in a corral for {}", n),
+            ScooterParking::Corral(n),
+        ));
+    }
+    choices.push(Choice::new("banned", ScooterParking::Banned));
+    // Edits loaded from a file might have other values
+    if !choices.iter().any(|c| c.data == current) {
+        choices.push(Choice::new(current.describe(), current));
+    }
+    Widget::row(vec![
+        "Scooters may be left:".draw_text(ctx).margin_right(5),
+        Widget::dropdown(ctx, "scooter parking", current, choices),
+    ])
+}
+
+fn change_median(ctx: &mut EventCtx, current: Option<Median>) -> Widget {
+    let median = |meters: f64, no_left_turns: bool| {
+        Some(Median {
+            width: Distance::meters(meters),
+            no_left_turns,
+        })
+    };
+    let mut choices = vec![
+        Choice::new("none", None),
+        Choice::new("narrow refuge island", median(2.0, false)),
+        Choice::new("wide refuge island", median(4.0, false)),
+        Choice::new("wide, no left turns", median(4.0, true)),
+    ];
+    // Edits loaded from a file might have other values
+    if !choices.iter().any(|c| c.data == current) {
+        let m = current.unwrap();
+        choices.push(Choice::new(
+            if m.no_left_turns {
+                format!("{}, no left turns", m.width)
+            } else {
+                m.width.to_string()
+            },
+            current,
+        ));
+    }
+    Widget::row(vec![
+        "Median:".draw_text(ctx).centered_vert().margin_right(15),
+        Widget::dropdown(ctx, "median", current, choices),
+    ])
+}
+
+fn change_lane_width(ctx: &mut EventCtx, current: Distance) -> Widget {
+    let mut choices = Vec::new();
+    for tenths in (10..=45).step_by(5) {
+        let width = Distance::meters(tenths as f64 / 10.0);
+        choices.push(Choice::new(width.to_string(), width));
+    }
+    // OSM data and edits might have other values
+    if !choices.iter().any(|c| c.data == current) {
+        choices.push(Choice::new(current.to_string(), current));
+    }
+    Widget::row(vec![
+        "Lane width:"
+            .draw_text(ctx)
+            .centered_vert()
+            .margin_right(15),
+        Widget::dropdown(ctx, "lane width", current, choices),
+    ])
+}
+
 fn can_change_lane_type(l: LaneID, new_lt: LaneType, map: &Map) -> Option<String> {
     let r = map.get_parent(l);
     let (fwds, idx) = r.dir_and_offset(l);
@@ -0,0 +1,310 @@
+use crate::app::App;
+use crate::common::{ColorDiscrete, CommonState, Warping};
+use crate::edit::apply_map_edits;
+use crate::game::{State, Transition};
+use crate::helpers::ID;
+use abstutil::Timer;
+use ezgui::{
+    hotkey, Btn, Color, Composite, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, TextExt, VerticalAlignment, Widget,
+};
+use map_model::{
+    connectivity, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, LaneID,
+    LaneType, Map, PathConstraints, PathRequest, PathStep, RoadID, TurnGroupID,
+};
+use std::collections::BTreeSet;
+
+// Only list this many problems; the rest are just counted.
+const MAX_LISTED: usize = 10;
+
+struct Problem {
+    id: ID,
+    description: String,
+    // What the fix does, and the edit to make it
+    fix: Option<(String, EditCmd)>,
+}
+
+// Checks the current edits for common mistakes before running a simulation with them. Each
+// problem can be jumped to, and most have a suggested fix.
+pub struct Lint {
+    composite: Composite,
+    problems: Vec<Problem>,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+}
+
+impl Lint {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State> {
+        let problems = find_problems(&app.primary.map);
+
+        let mut c = ColorDiscrete::new(app, vec![("problem", Color::RED)]);
+        for p in &problems {
+            match p.id {
+                ID::Lane(l) => c.add_l(l, "problem"),
+                ID::Intersection(i) => c.add_i(i, "problem"),
+                _ => unreachable!(),
+            }
+        }
+        let (unzoomed, zoomed, _) = c.build(ctx);
+
+        let mut col = vec![Widget::row(vec![
+            Line("Check edits").small_heading().draw(ctx),
+            Btn::plaintext("X")
+                .build(ctx, "close", hotkey(Key::Escape))
+                .align_right(),
+        ])
+        .margin_below(10)];
+        if problems.is_empty() {
+            col.push("No problems found".draw_text(ctx));
+        }
+        for (idx, p) in problems.iter().enumerate().take(MAX_LISTED) {
+            col.push(
+                Widget::row(vec![
+                    Btn::plaintext(&p.description).build(ctx, format!("go to #{}", idx), None),
+                    if let Some((ref label, _)) = p.fix {
+                        Btn::text_fg(label)
+                            .build(ctx, format!("fix #{}", idx), None)
+                            .align_right()
+                    } else {
+                        Widget::nothing()
+                    },
+                ])
+                .margin_below(5),
+            );
+        }
+        if problems.len() > MAX_LISTED {
+            col.push(format!("{} more...", problems.len() - MAX_LISTED).draw_text(ctx));
+        }
+
+        Box::new(Lint {
+            composite: Composite::new(Widget::col(col).padding(10).bg(app.cs.panel_bg))
+                .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+                .build(ctx),
+            problems,
+            unzoomed,
+            zoomed,
+        })
+    }
+}
+
+impl State for Lint {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                x => {
+                    if x.starts_with("go to #") {
+                        let idx = x["go to #".len()..].parse::<usize>().unwrap();
+                        let id = self.problems[idx].id.clone();
+                        return Transition::Push(Warping::new(
+                            ctx,
+                            id.canonical_point(&app.primary).unwrap(),
+                            Some(10.0),
+                            Some(id),
+                            &mut app.primary,
+                        ));
+                    }
+                    let idx = x["fix #".len()..].parse::<usize>().unwrap();
+                    let (_, cmd) = self.problems[idx].fix.clone().unwrap();
+                    let mut edits = app.primary.map.get_edits().clone();
+                    edits.commands.push(cmd);
+                    apply_map_edits(ctx, app, edits);
+                    // Fixing one thing might fix or cause others, so check everything again
+                    return Transition::Replace(Lint::new(ctx, app));
+                }
+            },
+            None => {}
+        }
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+        self.composite.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn find_problems(map: &Map) -> Vec<Problem> {
+    let edits = map.get_edits();
+    let mut problems = Vec::new();
+
+    // Only look at lanes the edits touched; the original map has plenty of its own quirks.
+    let mut new_bus_lanes = Vec::new();
+    for (l, orig_lt) in &edits.original_lts {
+        let lane = map.get_l(*l);
+        let revert = || {
+            Some((
+                format!("change back to {}", orig_lt.short_name()),
+                EditCmd::ChangeLaneType {
+                    id: *l,
+                    lt: *orig_lt,
+                    orig_lt: lane.lane_type,
+                },
+            ))
+        };
+        match lane.lane_type {
+            LaneType::Biking => {
+                if !map.get_i(lane.dst_i).is_border()
+                    && !map
+                        .get_turns_from_lane(*l)
+                        .into_iter()
+                        .any(|t| PathConstraints::Bike.can_use(map.get_l(t.id.dst), map))
+                {
+                    problems.push(Problem {
+                        id: ID::Lane(*l),
+                        description: format!("Bike lane {} dead-ends", l),
+                        fix: revert(),
+                    });
+                }
+            }
+            LaneType::Bus => {
+                new_bus_lanes.push((*l, revert()));
+            }
+            _ => {}
+        }
+    }
+    if !new_bus_lanes.is_empty() {
+        let bus_roads = roads_with_bus_routes(map);
+        for (l, fix) in new_bus_lanes {
+            if !bus_roads.contains(&map.get_l(l).parent) {
+                problems.push(Problem {
+                    id: ID::Lane(l),
+                    description: format!("Bus lane {} isn't on any bus route", l),
+                    fix,
+                });
+            }
+        }
+    }
+
+    let mut closed = Vec::new();
+    for (i, orig) in &edits.original_intersections {
+        if let Some(signal) = map.maybe_get_traffic_signal(*i) {
+            let mut unserved: BTreeSet<TurnGroupID> = signal.turn_groups.keys().cloned().collect();
+            for phase in &signal.phases {
+                for g in phase
+                    .protected_groups
+                    .iter()
+                    .chain(phase.yield_groups.iter())
+                {
+                    unserved.remove(g);
+                }
+            }
+            if !unserved.is_empty() {
+                problems.push(Problem {
+                    id: ID::Intersection(*i),
+                    description: format!("{} movements at {} never get a green", unserved.len(), i),
+                    fix: Some((
+                        "reset timing".to_string(),
+                        EditCmd::ChangeIntersection {
+                            i: *i,
+                            old: map.get_i_edit(*i),
+                            new: EditIntersection::TrafficSignal(ControlTrafficSignal::new(
+                                map,
+                                *i,
+                                &mut Timer::throwaway(),
+                            )),
+                        },
+                    )),
+                });
+            }
+        }
+        if map.get_i(*i).is_closed() {
+            closed.push((*i, orig.clone()));
+        }
+    }
+
+    if !closed.is_empty() {
+        let (_, disconnected) = connectivity::find_scc(map, PathConstraints::Pedestrian);
+        let stranded: Vec<LaneID> = map
+            .all_buildings()
+            .iter()
+            .map(|b| b.sidewalk())
+            .filter(|l| disconnected.contains(l))
+            .collect();
+        for (i, orig) in closed {
+            if stranded.is_empty() {
+                break;
+            }
+            // Only blame this closure for the buildings that re-opening just it would reconnect
+            let (_, still_disconnected) = connectivity::find_scc_with_extra_turns(
+                map,
+                PathConstraints::Pedestrian,
+                reopened_sidewalks(map, i),
+            );
+            let count = stranded
+                .iter()
+                .filter(|l| !still_disconnected.contains(l))
+                .count();
+            if count > 0 {
+                problems.push(Problem {
+                    id: ID::Intersection(i),
+                    description: format!(
+                        "Closing {} leaves {} buildings unreachable on foot",
+                        i, count
+                    ),
+                    fix: Some((
+                        "re-open".to_string(),
+                        EditCmd::ChangeIntersection {
+                            i,
+                            old: EditIntersection::Closed,
+                            new: orig,
+                        },
+                    )),
+                });
+            }
+        }
+    }
+
+    problems
+}
+
+// Every road some bus route drives along between its stops
+fn roads_with_bus_routes(map: &Map) -> BTreeSet<RoadID> {
+    let mut roads = BTreeSet::new();
+    for route in map.get_all_bus_routes() {
+        for (stop1, stop2) in route.stops.iter().zip(route.stops.iter().skip(1)) {
+            if let Some(path) = map.pathfind(PathRequest {
+                start: map.get_bs(*stop1).driving_pos,
+                end: map.get_bs(*stop2).driving_pos,
+                constraints: PathConstraints::Bus,
+            }) {
+                for step in path.get_steps() {
+                    if let PathStep::Lane(l) = step {
+                        roads.insert(map.get_l(*l).parent);
+                    }
+                }
+            }
+        }
+    }
+    roads
+}
+
+// An open intersection lets pedestrians get between any of its sidewalks
+fn reopened_sidewalks(map: &Map, i: IntersectionID) -> Vec<(LaneID, LaneID)> {
+    let int = map.get_i(i);
+    let sidewalks: BTreeSet<LaneID> = int
+        .incoming_lanes
+        .iter()
+        .chain(int.outgoing_lanes.iter())
+        .filter(|l| map.get_l(**l).is_sidewalk())
+        .cloned()
+        .collect();
+    let mut pairs = Vec::new();
+    for l1 in &sidewalks {
+        for l2 in &sidewalks {
+            if l1 != l2 {
+                pairs.push((*l1, *l2));
+            }
+        }
+    }
+    pairs
+}
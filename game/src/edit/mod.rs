@@ -1,6 +1,11 @@
 mod bulk;
 mod cluster_traffic_signals;
+mod find_replace;
+mod footbridges;
+mod gallery;
 mod lanes;
+mod lint;
+mod signal_corridor;
 mod stop_signs;
 mod traffic_signals;
 
@@ -67,6 +72,15 @@ impl EditMode {
         }
     }
 
+    // Can everything changed since entering edit mode be applied to the running simulation?
+    fn only_live_edits(&self, app: &App) -> bool {
+        let orig = &self.orig_edits.commands;
+        let cmds = &app.primary.map.get_edits().commands;
+        cmds.len() >= orig.len()
+            && cmds[..orig.len()] == orig[..]
+            && cmds[orig.len()..].iter().all(|cmd| cmd.can_apply_live())
+    }
+
     fn quit(&self, ctx: &mut EventCtx, app: &mut App) -> Transition {
         let old_sim = app.suspended_sim.take().unwrap();
 
@@ -94,16 +108,26 @@ impl EditMode {
                 app.primary.map.save_edits();
             }
             if app.opts.resume_after_edit {
-                if self.mode.reset_after_edits() {
-                    Transition::PopThenReplaceThenPush(
-                        Box::new(SandboxMode::new(ctx, app, self.mode.clone())),
-                        TimeWarpScreen::new(ctx, app, old_sim.time(), false),
-                    )
-                } else {
+                let resume_time = old_sim.time();
+                // Try to keep the current simulation going. Only restart and warp back to the
+                // same time when the edits change the structure of the map.
+                if !self.mode.reset_after_edits() || self.only_live_edits(app) {
                     app.primary.sim = old_sim;
-                    app.primary.dirty_from_edits = true;
-                    Transition::Pop
+                    match app.primary.sim.handle_live_edits(&app.primary.map) {
+                        Ok(()) => {
+                            app.primary.dirty_from_edits = true;
+                            return Transition::Pop;
+                        }
+                        Err(err) => {
+                            println!("Can't apply edits live, so restarting: {}", err);
+                            app.primary.clear_sim();
+                        }
+                    }
                 }
+                Transition::PopThenReplaceThenPush(
+                    Box::new(SandboxMode::new(ctx, app, self.mode.clone())),
+                    TimeWarpScreen::new(ctx, app, resume_time, None),
+                )
             } else {
                 Transition::PopThenReplace(Box::new(SandboxMode::new(ctx, app, self.mode.clone())))
             }
@@ -159,7 +183,10 @@ impl State for EditMode {
         match self.top_center.event(ctx) {
             Some(Outcome::Clicked(x)) => match x.as_ref() {
                 "bulk edit" => {
-                    return Transition::Push(bulk::PaintSelect::new(ctx, app));
+                    return Transition::Push(bulk::PaintSelect::new(ctx, app, BTreeSet::new()));
+                }
+                "check edits" => {
+                    return Transition::Push(lint::Lint::new(ctx, app));
                 }
                 "finish editing" => {
                     return self.quit(ctx, app);
@@ -199,6 +226,9 @@ impl State for EditMode {
                         &mut app.primary,
                     ));
                 }
+                "screenshot before and after" => {
+                    return Transition::Push(gallery::Gallery::new(ctx, app));
+                }
                 x => {
                     let idx = x["most recent change #".len()..].parse::<usize>().unwrap();
                     let id = cmd_to_id(
@@ -268,6 +298,21 @@ impl State for EditMode {
                     apply_map_edits(ctx, app, edits);
                 }
             }
+            // Shift-clicking starts selecting many roads at once
+            if ctx.canvas.is_lshift_held() && self.mode.can_edit_lanes() {
+                let road = match app.primary.current_selection {
+                    Some(ID::Lane(l)) => Some(app.primary.map.get_l(l).parent),
+                    Some(ID::Road(r)) => Some(r),
+                    _ => None,
+                };
+                if let Some(r) = road {
+                    if app.per_obj.left_click(ctx, "select multiple roads") {
+                        let mut roads = BTreeSet::new();
+                        roads.insert(r);
+                        return Transition::Push(bulk::PaintSelect::new(ctx, app, roads));
+                    }
+                }
+            }
             if let Some(ID::Lane(l)) = app.primary.current_selection {
                 if app.per_obj.left_click(ctx, "edit lane") {
                     return Transition::Push(Box::new(LaneEditor::new(
@@ -419,6 +464,9 @@ fn make_topcenter(ctx: &mut EventCtx, app: &App, mode: &GameplayMode) -> Composi
                     Btn::text_fg("bulk edit").inactive(ctx)
                 }
                 .margin_right(15),
+                Btn::text_fg("check edits")
+                    .build_def(ctx, hotkey(Key::C))
+                    .margin_right(15),
                 PersistentSplit::new(
                     ctx,
                     "finish editing",
@@ -450,11 +498,13 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
 
     let (roads_changed, turns_deleted, turns_added, mut modified_intersections) =
         app.primary.map.apply_edits(edits, &mut timer);
+    let mut changed_ids = Vec::new();
 
     for r in roads_changed {
+        changed_ids.push(ID::Road(r));
         let road = app.primary.map.get_r(r);
         app.primary.draw_map.roads[r.0] =
-            DrawRoad::new(road, &app.primary.map, &app.cs, ctx.prerender);
+            DrawRoad::new(road, &app.primary.map, &app.cs).finish(ctx.prerender);
 
         // An edit to one lane potentially affects markings in all lanes in the same road, because
         // of one-way markings, driving lines, etc.
@@ -465,9 +515,10 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
                 &app.primary.map,
                 app.primary.current_flags.draw_lane_markings,
                 &app.cs,
+                &app.primary.draw_map.icons,
                 &mut timer,
             )
-            .finish(ctx.prerender, &app.cs, lane);
+            .finish(ctx.prerender);
         }
     }
 
@@ -482,24 +533,30 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
     }
 
     for i in modified_intersections {
+        changed_ids.push(ID::Intersection(i));
         app.primary.draw_map.intersections[i.0] = DrawIntersection::new(
             app.primary.map.get_i(i),
             &app.primary.map,
             &app.cs,
-            ctx.prerender,
+            &app.primary.draw_map.icons,
             &mut timer,
-        );
+        )
+        .finish(ctx.prerender);
     }
 
+    app.primary
+        .draw_map
+        .tiles
+        .invalidate(changed_ids, &app.primary.map);
+
     if app.layer.as_ref().and_then(|l| l.name()) == Some("map edits") {
         app.layer = Some(Box::new(crate::layer::map::Static::edits(ctx, app)));
     }
 }
 
 pub fn can_edit_lane(mode: &GameplayMode, l: LaneID, app: &App) -> bool {
-    mode.can_edit_lanes()
-        && !app.primary.map.get_l(l).is_sidewalk()
-        && app.primary.map.get_l(l).lane_type != LaneType::SharedLeftTurn
+    // Sidewalks can't change type, but their scooter parking rules can be edited
+    mode.can_edit_lanes() && app.primary.map.get_l(l).lane_type != LaneType::SharedLeftTurn
 }
 
 pub fn close_intersection(
@@ -660,10 +717,44 @@ fn make_changelist(ctx: &mut EventCtx, app: &App) -> Composite {
         Text::from_multiline(vec![
             Line(format!("{} lane types changed", edits.original_lts.len())),
             Line(format!("{} lanes reversed", edits.reversed_lanes.len())),
+            Line(format!(
+                "{} lane widths changed",
+                edits.original_lane_widths.len()
+            )),
             Line(format!(
                 "{} speed limits changed",
                 edits.changed_speed_limits.len()
             )),
+            Line(format!(
+                "{} time-of-day speed limits changed",
+                edits.changed_timed_speed_limits.len()
+            )),
+            Line(format!(
+                "{} snow routes designated",
+                edits.changed_snow_routes.len()
+            )),
+            Line(format!(
+                "{} roads folded into junctions",
+                edits.consolidated_junctions.len()
+            )),
+            Line(format!("{} detours signed", edits.signed_detours.len())),
+            Line(format!(
+                "{} crossings' curb ramps changed",
+                edits.changed_curb_ramps.len()
+            )),
+            Line(format!(
+                "{} roads' footbridges changed",
+                edits.changed_footbridges.len()
+            )),
+            Line(format!("{} medians changed", edits.changed_medians.len())),
+            Line(format!(
+                "{} parking rules changed",
+                edits.changed_parking_restrictions.len()
+            )),
+            Line(format!(
+                "{} scooter parking rules changed",
+                edits.changed_scooter_parking.len()
+            )),
             Line(format!(
                 "{} intersections changed",
                 edits.original_intersections.len()
@@ -672,6 +763,13 @@ fn make_changelist(ctx: &mut EventCtx, app: &App) -> Composite {
         .draw(ctx)
         .margin_below(10),
     ];
+    if !edits.commands.is_empty() {
+        col.push(
+            Btn::text_fg("screenshot before and after")
+                .build_def(ctx, None)
+                .margin_below(10),
+        );
+    }
 
     for (idx, cmd) in edits.commands.iter().rev().take(5).enumerate() {
         col.push(
@@ -695,7 +793,17 @@ fn cmd_to_id(cmd: &EditCmd) -> ID {
     match cmd {
         EditCmd::ChangeLaneType { id, .. } => ID::Lane(*id),
         EditCmd::ReverseLane { l, .. } => ID::Lane(*l),
-        EditCmd::ChangeSpeedLimit { id, .. } => ID::Road(*id),
+        EditCmd::ChangeLaneWidth { id, .. } => ID::Lane(*id),
+        EditCmd::ChangeSpeedLimit { id, .. }
+        | EditCmd::ChangeTimedSpeedLimits { id, .. }
+        | EditCmd::ChangeSnowRoute { id, .. }
+        | EditCmd::ConsolidateJunction { id, .. }
+        | EditCmd::ChangeCurbRamps { id, .. }
+        | EditCmd::ChangeFootbridges { id, .. }
+        | EditCmd::ChangeMedian { id, .. }
+        | EditCmd::SignDetour { id, .. } => ID::Road(*id),
+        EditCmd::ChangeParkingRestrictions { id, .. }
+        | EditCmd::ChangeScooterParking { id, .. } => ID::Lane(*id),
         EditCmd::ChangeIntersection { i, .. } => ID::Intersection(*i),
     }
 }
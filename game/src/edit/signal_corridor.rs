@@ -0,0 +1,212 @@
+use crate::app::{App, ShowEverything};
+use crate::common::CommonState;
+use crate::edit::apply_map_edits;
+use crate::edit::bulk::pathfind;
+use crate::game::{msg, State, Transition};
+use crate::helpers::ID;
+use ezgui::{
+    hotkey, Btn, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key,
+    Line, Outcome, TextExt, VerticalAlignment, Widget,
+};
+use geom::Duration;
+use map_model::{
+    ControlTrafficSignal, DirectedRoadID, EditCmd, EditIntersection, IntersectionID, Map, RoadID,
+};
+use sim::DontDrawAgents;
+
+// Pick a path of roads starting from one traffic signal, then shift the offsets of every signal
+// along it, so that a platoon leaving the first signal at the speed limit hits consecutive greens.
+// The first signal's offset is the reference and isn't changed.
+pub struct CoordinateSignals {
+    composite: Composite,
+    i1: IntersectionID,
+    preview_path: Option<(IntersectionID, Vec<RoadID>, Drawable)>,
+}
+
+impl CoordinateSignals {
+    pub fn new(ctx: &mut EventCtx, app: &mut App, i1: IntersectionID) -> Box<dyn State> {
+        app.primary.current_selection = None;
+        Box::new(CoordinateSignals {
+            composite: Composite::new(
+                Widget::col(vec![
+                    Line("Coordinate a green wave").small_heading().draw(ctx),
+                    "Click the intersection at the end of the corridor".draw_text(ctx),
+                    Btn::text_fg("Quit")
+                        .build_def(ctx, hotkey(Key::Escape))
+                        .margin_above(10),
+                ])
+                .bg(app.cs.panel_bg)
+                .padding(10),
+            )
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            i1,
+            preview_path: None,
+        })
+    }
+}
+
+impl State for CoordinateSignals {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.primary.current_selection = app.calculate_current_selection(
+                ctx,
+                &DontDrawAgents {},
+                &ShowEverything::new(),
+                false,
+                true,
+                false,
+            );
+            match app.primary.current_selection {
+                Some(ID::Intersection(i)) if i != self.i1 => {}
+                _ => {
+                    app.primary.current_selection = None;
+                }
+            }
+        }
+
+        if let Some(ID::Intersection(i2)) = app.primary.current_selection {
+            if self
+                .preview_path
+                .as_ref()
+                .map(|(i, _, _)| *i != i2)
+                .unwrap_or(true)
+            {
+                let mut batch = GeomBatch::new();
+                let roads = pathfind(&app.primary.map, self.i1, i2).unwrap_or_else(Vec::new);
+                for r in &roads {
+                    let r = app.primary.map.get_r(*r);
+                    batch.push(
+                        Color::RED.alpha(0.5),
+                        r.get_thick_polygon(&app.primary.map).unwrap(),
+                    );
+                    for i in &[r.src_i, r.dst_i] {
+                        if app.primary.map.get_i(*i).is_traffic_signal() {
+                            batch.push(
+                                Color::GREEN.alpha(0.5),
+                                app.primary.map.get_i(*i).polygon.clone(),
+                            );
+                        }
+                    }
+                }
+                self.preview_path = Some((i2, roads, ctx.upload(batch)));
+            }
+
+            if self
+                .preview_path
+                .as_ref()
+                .map(|(_, roads, _)| !roads.is_empty())
+                .unwrap_or(false)
+                && app
+                    .per_obj
+                    .left_click(ctx, "coordinate signals along this path")
+            {
+                let (_, roads, _) = self.preview_path.take().unwrap();
+                let signals = green_wave(&app.primary.map, self.i1, &roads);
+                let num_signals = signals.len();
+                let mut edits = app.primary.map.get_edits().clone();
+                for signal in signals {
+                    edits.commands.push(EditCmd::ChangeIntersection {
+                        i: signal.id,
+                        old: app.primary.map.get_i_edit(signal.id),
+                        new: EditIntersection::TrafficSignal(signal),
+                    });
+                }
+                apply_map_edits(ctx, app, edits);
+                return Transition::Replace(msg(
+                    "Green wave",
+                    vec![format!(
+                        "Changed the offset of {} traffic signals along {} roads",
+                        num_signals,
+                        roads.len()
+                    )],
+                ));
+            }
+        } else {
+            self.preview_path = None;
+        }
+
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "Quit" => {
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            },
+            None => {}
+        }
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.draw_polygon(Color::GREEN, &app.primary.map.get_i(self.i1).polygon);
+        if let Some((_, _, ref p)) = self.preview_path {
+            g.redraw(p);
+        }
+        self.composite.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+// Returns the signals along the path whose offset needs to change.
+fn green_wave(map: &Map, i1: IntersectionID, roads: &[RoadID]) -> Vec<ControlTrafficSignal> {
+    // When does the first signal let the platoon onto the corridor?
+    let first = map.get_traffic_signal(i1);
+    let depart = first
+        .start_of_protected_phase(None, Some(leaving(map, roads[0], i1)))
+        .unwrap_or(Duration::ZERO)
+        - first.offset;
+
+    let mut results = Vec::new();
+    let mut travel_time = Duration::ZERO;
+    let mut i = i1;
+    for (idx, r) in roads.iter().enumerate() {
+        let dr = leaving(map, *r, i);
+        let road = map.get_r(*r);
+        travel_time += road.center_pts.length() / road.speed_limit;
+        i = dr.dst_i(map);
+
+        // Actuated signals don't run on a fixed cycle, so there's no offset to shift
+        if let Some(signal) = map.maybe_get_traffic_signal(i).filter(|s| !s.actuated) {
+            // Prefer the phase that lets the platoon continue along the corridor, but at the end
+            // of it, any movement from the arriving road will do.
+            let next = roads.get(idx + 1).map(|r| leaving(map, *r, i));
+            if let Some(start) = signal
+                .start_of_protected_phase(Some(dr), next)
+                .or_else(|| signal.start_of_protected_phase(Some(dr), None))
+            {
+                // Edits only remember whole seconds
+                let offset = Duration::seconds(
+                    wrap_into_cycle(start - depart - travel_time, signal.cycle_length())
+                        .inner_seconds()
+                        .floor(),
+                );
+                if offset != signal.offset {
+                    let mut new_signal = signal.clone();
+                    new_signal.offset = offset;
+                    results.push(new_signal);
+                }
+            }
+        }
+    }
+    results
+}
+
+fn leaving(map: &Map, r: RoadID, i: IntersectionID) -> DirectedRoadID {
+    if map.get_r(r).src_i == i {
+        r.forwards()
+    } else {
+        r.backwards()
+    }
+}
+
+// Like %, but always lands in [0, cycle_length)
+pub fn wrap_into_cycle(x: Duration, cycle_length: Duration) -> Duration {
+    let x = x % cycle_length;
+    if x < Duration::ZERO {
+        x + cycle_length
+    } else {
+        x
+    }
+}
@@ -1,5 +1,6 @@
 use crate::app::{App, ShowEverything};
 use crate::common::CommonState;
+use crate::edit::signal_corridor::{wrap_into_cycle, CoordinateSignals};
 use crate::edit::{apply_map_edits, close_intersection, StopSignEditor};
 use crate::game::{msg, DrawBaselayer, State, Transition, WizardState};
 use crate::render::{
@@ -10,12 +11,12 @@ use abstutil::Timer;
 use ezgui::{
     hotkey, lctrl, Btn, Choice, Color, Composite, EventCtx, EventLoopMode, GeomBatch, GfxCtx,
     HorizontalAlignment, Key, Line, Outcome, RewriteColor, Text, TextExt, VerticalAlignment,
-    Widget,
+    Widget, WrappedWizard,
 };
-use geom::{ArrowCap, Distance, Duration};
+use geom::{ArrowCap, Distance, Duration, Time};
 use map_model::{
     ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, Phase,
-    TurnGroupID, TurnPriority,
+    SignalTimingPlan, TurnGroupID, TurnPriority,
 };
 use std::collections::BTreeSet;
 
@@ -152,6 +153,22 @@ impl State for TrafficSignalEditor {
                     self.change_phase(len - 1, ctx, app);
                     return Transition::Keep;
                 }
+                x if x == "Add pedestrian scramble" => {
+                    let mut new_signal = orig_signal.clone();
+                    if !new_signal.convert_to_ped_scramble() {
+                        return Transition::Push(msg(
+                            "Error",
+                            vec!["This signal already has a pedestrian scramble"],
+                        ));
+                    }
+                    let len = new_signal.phases.len();
+                    self.command_stack.push(orig_signal.clone());
+                    self.redo_stack.clear();
+                    self.top_panel = make_top_panel(ctx, app, true, false);
+                    change_traffic_signal(new_signal, ctx, app);
+                    self.change_phase(len - 1, ctx, app);
+                    return Transition::Keep;
+                }
                 x if x.starts_with("phase ") => {
                     let idx = x["phase ".len()..].parse::<usize>().unwrap() - 1;
                     self.change_phase(idx, ctx, app);
@@ -308,7 +325,7 @@ impl State for TrafficSignalEditor {
                 // expressed more composably like SVG, using lyon.
                 let block_color = match self.group_selected.unwrap().1 {
                     Some(TurnPriority::Protected) => {
-                        let green = Color::hex("#72CE36");
+                        let green = app.cs.signal_protected_turn;
                         batch.push(
                             green.alpha(0.5),
                             signal.turn_groups[&g.id]
@@ -330,7 +347,7 @@ impl State for TrafficSignalEditor {
                             // TODO Ideally the inner part would be the lower opacity blue, but
                             // can't yet express that it should cover up the thicker solid blue
                             // beneath it
-                            Color::BLACK.alpha(0.8),
+                            app.cs.signal_yield_outline.alpha(0.8),
                             signal.turn_groups[&g.id].geom.dashed_arrow(
                                 BIG_ARROW_THICKNESS,
                                 Distance::meters(1.2),
@@ -356,7 +373,7 @@ impl State for TrafficSignalEditor {
                         app.cs.signal_permitted_turn
                     }
                     Some(TurnPriority::Banned) => {
-                        let red = Color::hex("#EB3223");
+                        let red = app.cs.signal_editor_banned;
                         batch.push(
                             red.alpha(0.5),
                             signal.turn_groups[&g.id]
@@ -376,7 +393,7 @@ impl State for TrafficSignalEditor {
                     None => app.cs.signal_turn_block_bg,
                 };
                 batch.push(block_color, g.block.clone());
-                batch.push(Color::WHITE, g.arrow.clone());
+                batch.push(app.cs.signal_editor_arrow, g.arrow.clone());
             } else {
                 batch.push(app.cs.signal_turn_block_bg, g.block.clone());
                 let arrow_color = match phase.get_priority_of_group(g.id) {
@@ -497,32 +514,53 @@ pub fn change_traffic_signal(signal: ControlTrafficSignal, ctx: &mut EventCtx, a
 }
 
 fn edit_entire_signal(app: &App, i: IntersectionID, mode: GameplayMode) -> Box<dyn State> {
-    let has_sidewalks = app
-        .primary
-        .map
-        .get_turns_in_intersection(i)
-        .iter()
-        .any(|t| t.between_sidewalks());
     let current_offset = app.primary.map.get_traffic_signal(i).offset;
+    let is_actuated = app.primary.map.get_traffic_signal(i).actuated;
+    let can_paste = app
+        .primary
+        .signal_clipboard
+        .as_ref()
+        .map(|s| s.id != i)
+        .unwrap_or(false);
+    let map_name = app.primary.map.get_name().to_string();
+    let can_import =
+        !abstutil::list_all_objects(abstutil::path_all_signal_plans(&map_name)).is_empty();
 
     WizardState::new(Box::new(move |wiz, ctx, app| {
         let use_template = "use template";
-        let all_walk = "add an all-walk phase at the end";
         let stop_sign = "convert to stop signs";
         let close = "close intersection for construction";
         let offset = "edit signal offset";
+        let corridor = "coordinate offsets along a corridor";
+        let actuated = "switch to actuated control";
+        let fixed_time = "switch to fixed-time control";
+        let copy = "copy this signal's timing";
+        let paste = "paste copied timing";
+        let export = "export timing plan";
+        let import = "import timing plan";
         let reset = "reset to default";
 
         let mut choices = vec![use_template];
-        if has_sidewalks {
-            choices.push(all_walk);
-        }
         // TODO Conflating stop signs and construction here
         if mode.can_edit_stop_signs() {
             choices.push(stop_sign);
             choices.push(close);
         }
-        choices.push(offset);
+        if is_actuated {
+            choices.push(fixed_time);
+        } else {
+            choices.push(actuated);
+            choices.push(offset);
+            choices.push(corridor);
+        }
+        choices.push(copy);
+        if can_paste {
+            choices.push(paste);
+        }
+        choices.push(export);
+        if can_import {
+            choices.push(import);
+        }
         choices.push(reset);
 
         let mut wizard = wiz.wrap(ctx);
@@ -547,20 +585,6 @@ fn edit_entire_signal(app: &App, i: IntersectionID, mode: GameplayMode) -> Box<d
                     editor.change_phase(0, ctx, app);
                 })))
             }
-            x if x == all_walk => {
-                Some(Transition::PopWithData(Box::new(move |state, ctx, app| {
-                    let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
-                    let orig_signal = app.primary.map.get_traffic_signal(editor.i);
-                    let mut new_signal = orig_signal.clone();
-                    if new_signal.convert_to_ped_scramble() {
-                        editor.command_stack.push(orig_signal.clone());
-                        editor.redo_stack.clear();
-                        editor.top_panel = make_top_panel(ctx, app, true, false);
-                        change_traffic_signal(new_signal, ctx, app);
-                        editor.change_phase(0, ctx, app);
-                    }
-                })))
-            }
             x if x == stop_sign => {
                 let mut edits = app.primary.map.get_edits().clone();
                 edits.commands.push(EditCmd::ChangeIntersection {
@@ -593,6 +617,95 @@ fn edit_entire_signal(app: &App, i: IntersectionID, mode: GameplayMode) -> Box<d
                     editor.change_phase(editor.current_phase, ctx, app);
                 })))
             }
+            x if x == actuated || x == fixed_time => {
+                Some(Transition::PopWithData(Box::new(move |state, ctx, app| {
+                    let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+                    let mut signal = app.primary.map.get_traffic_signal(editor.i).clone();
+                    editor.command_stack.push(signal.clone());
+                    editor.redo_stack.clear();
+                    editor.top_panel = make_top_panel(ctx, app, true, false);
+                    signal.actuated = !signal.actuated;
+                    change_traffic_signal(signal, ctx, app);
+                    editor.change_phase(editor.current_phase, ctx, app);
+                })))
+            }
+            x if x == corridor => Some(Transition::Replace(CoordinateSignals::new(ctx, app, i))),
+            x if x == copy => {
+                app.primary.signal_clipboard = Some(app.primary.map.get_traffic_signal(i).clone());
+                Some(Transition::Pop)
+            }
+            x if x == paste => {
+                let copied = app.primary.signal_clipboard.as_ref().unwrap();
+                match copied.transfer(i, &app.primary.map) {
+                    Ok(new_signal) => {
+                        Some(Transition::PopWithData(Box::new(move |state, ctx, app| {
+                            let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+                            editor
+                                .command_stack
+                                .push(app.primary.map.get_traffic_signal(editor.i).clone());
+                            editor.redo_stack.clear();
+                            editor.top_panel = make_top_panel(ctx, app, true, false);
+                            change_traffic_signal(new_signal, ctx, app);
+                            editor.change_phase(0, ctx, app);
+                        })))
+                    }
+                    Err(err) => Some(Transition::Replace(msg("Can't paste timing", vec![err]))),
+                }
+            }
+            x if x == export => {
+                let plan = app
+                    .primary
+                    .map
+                    .get_traffic_signal(i)
+                    .export_plan(&app.primary.map);
+                let name = wizard.input_string_prefilled(
+                    "Name this timing plan",
+                    plan.intersection_osm_node_id.to_string(),
+                )?;
+                let path = abstutil::path_signal_plan(&map_name, &name);
+                abstutil::write_json(path.clone(), &plan);
+                Some(Transition::Replace(msg(
+                    "Exported timing plan",
+                    vec![format!("Saved to {}", path)],
+                )))
+            }
+            x if x == import => {
+                let name = wizard.choose_string("Import which timing plan?", || {
+                    abstutil::list_all_objects(abstutil::path_all_signal_plans(&map_name))
+                })?;
+                // Plans name their own intersection; move them here if it's a different one.
+                let result = abstutil::maybe_read_json::<SignalTimingPlan>(
+                    abstutil::path_signal_plan(&map_name, &name),
+                    &mut Timer::throwaway(),
+                )
+                .map_err(|err| err.to_string())
+                .and_then(|plan| ControlTrafficSignal::import_plan(plan, &app.primary.map))
+                .and_then(|signal| {
+                    if signal.id == i {
+                        Ok(signal)
+                    } else {
+                        signal.transfer(i, &app.primary.map)
+                    }
+                });
+                match result {
+                    Ok(new_signal) => {
+                        Some(Transition::PopWithData(Box::new(move |state, ctx, app| {
+                            let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+                            editor
+                                .command_stack
+                                .push(app.primary.map.get_traffic_signal(editor.i).clone());
+                            editor.redo_stack.clear();
+                            editor.top_panel = make_top_panel(ctx, app, true, false);
+                            change_traffic_signal(new_signal, ctx, app);
+                            editor.change_phase(0, ctx, app);
+                        })))
+                    }
+                    Err(err) => Some(Transition::Replace(msg(
+                        "Can't import timing plan",
+                        vec![err],
+                    ))),
+                }
+            }
             x if x == reset => {
                 Some(Transition::PopWithData(Box::new(move |state, ctx, app| {
                     let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
@@ -620,11 +733,30 @@ fn edit_entire_signal(app: &App, i: IntersectionID, mode: GameplayMode) -> Box<d
 }
 
 fn change_duration(app: &App, i: IntersectionID, idx: usize) -> Box<dyn State> {
-    let current_duration = app.primary.map.get_traffic_signal(i).phases[idx].duration;
+    let signal = app.primary.map.get_traffic_signal(i);
+    if signal.actuated {
+        return change_actuated_greens(signal, idx);
+    }
+    let current_duration = signal.phases[idx].duration;
+    let has_crosswalks = signal.phases[idx]
+        .protected_groups
+        .iter()
+        .any(|g| g.crosswalk);
+    let current_lpi = signal.phases[idx].leading_pedestrian_interval;
+    let current_yellow = signal.phases[idx].yellow;
+    let query = if has_crosswalks {
+        format!(
+            "How long should this phase be (seconds)? Pedestrians need {} to cross.",
+            signal.min_crossing_time(idx, &app.primary.map)
+        )
+    } else {
+        "How long should this phase be (seconds)?".to_string()
+    };
 
     WizardState::new(Box::new(move |wiz, ctx, _| {
-        let new_duration = wiz.wrap(ctx).input_something(
-            "How long should this phase be (seconds)?",
+        let mut wizard = wiz.wrap(ctx);
+        let new_duration = wizard.input_something(
+            &query,
             Some(format!("{}", current_duration.inner_seconds() as usize)),
             Box::new(|line| {
                 line.parse::<usize>()
@@ -632,12 +764,85 @@ fn change_duration(app: &App, i: IntersectionID, idx: usize) -> Box<dyn State> {
                     .and_then(|n| if n != 0 { Some(n) } else { None })
             }),
         )?;
+        let yellow = wizard.input_something(
+            "How long should the light stay yellow at the end of this phase (seconds)?",
+            Some(format!(
+                "{}",
+                (current_yellow.inner_seconds() as usize).min(new_duration - 1)
+            )),
+            Box::new(move |line| {
+                line.parse::<usize>()
+                    .ok()
+                    .and_then(|n| if n < new_duration { Some(n) } else { None })
+            }),
+        )?;
+        let lpi = if has_crosswalks {
+            input_leading_pedestrian_interval(&mut wizard, current_lpi, new_duration - yellow)?
+        } else {
+            0
+        };
         Some(Transition::PopWithData(Box::new(move |state, ctx, app| {
             let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
             let orig_signal = app.primary.map.get_traffic_signal(editor.i);
 
             let mut new_signal = orig_signal.clone();
             new_signal.phases[idx].duration = Duration::seconds(new_duration as f64);
+            new_signal.phases[idx].yellow = Duration::seconds(yellow as f64);
+            new_signal.phases[idx].leading_pedestrian_interval = Duration::seconds(lpi as f64);
+            editor.command_stack.push(orig_signal.clone());
+            editor.redo_stack.clear();
+            editor.top_panel = make_top_panel(ctx, app, true, false);
+            change_traffic_signal(new_signal, ctx, app);
+            editor.change_phase(idx, ctx, app);
+        })))
+    }))
+}
+
+fn change_actuated_greens(signal: &ControlTrafficSignal, idx: usize) -> Box<dyn State> {
+    let current_min = signal.phases[idx].min_green;
+    let current_max = signal.phases[idx].max_green;
+    let has_crosswalks = signal.phases[idx]
+        .protected_groups
+        .iter()
+        .any(|g| g.crosswalk);
+    let current_lpi = signal.phases[idx].leading_pedestrian_interval;
+
+    WizardState::new(Box::new(move |wiz, ctx, _| {
+        let mut wizard = wiz.wrap(ctx);
+        let min_green = wizard.input_something(
+            "What's the shortest this phase should stay green (seconds)?",
+            Some(format!("{}", current_min.inner_seconds() as usize)),
+            Box::new(|line| {
+                line.parse::<usize>()
+                    .ok()
+                    .and_then(|n| if n != 0 { Some(n) } else { None })
+            }),
+        )?;
+        let max_green = wizard.input_something(
+            "What's the longest this phase should stay green (seconds)?",
+            Some(format!(
+                "{}",
+                (current_max.inner_seconds() as usize).max(min_green)
+            )),
+            Box::new(move |line| {
+                line.parse::<usize>()
+                    .ok()
+                    .and_then(|n| if n >= min_green { Some(n) } else { None })
+            }),
+        )?;
+        let lpi = if has_crosswalks {
+            input_leading_pedestrian_interval(&mut wizard, current_lpi, min_green)?
+        } else {
+            0
+        };
+        Some(Transition::PopWithData(Box::new(move |state, ctx, app| {
+            let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+            let orig_signal = app.primary.map.get_traffic_signal(editor.i);
+
+            let mut new_signal = orig_signal.clone();
+            new_signal.phases[idx].min_green = Duration::seconds(min_green as f64);
+            new_signal.phases[idx].max_green = Duration::seconds(max_green as f64);
+            new_signal.phases[idx].leading_pedestrian_interval = Duration::seconds(lpi as f64);
             editor.command_stack.push(orig_signal.clone());
             editor.redo_stack.clear();
             editor.top_panel = make_top_panel(ctx, app, true, false);
@@ -647,6 +852,26 @@ fn change_duration(app: &App, i: IntersectionID, idx: usize) -> Box<dyn State> {
     }))
 }
 
+// The head start has to end before the phase could, or before its yellow starts
+fn input_leading_pedestrian_interval(
+    wizard: &mut WrappedWizard,
+    current: Duration,
+    shortest_phase: usize,
+) -> Option<usize> {
+    wizard.input_something(
+        "How long of a head start should pedestrians get before vehicles (seconds, 0 for none)?",
+        Some(format!(
+            "{}",
+            (current.inner_seconds() as usize).min(shortest_phase - 1)
+        )),
+        Box::new(move |line| {
+            line.parse::<usize>()
+                .ok()
+                .and_then(|n| if n < shortest_phase { Some(n) } else { None })
+        }),
+    )
+}
+
 fn check_for_missing_groups(
     mut signal: ControlTrafficSignal,
     composite: &mut Composite,
@@ -713,11 +938,14 @@ fn make_previewer(i: IntersectionID, phase: usize) -> Box<dyn State> {
             x if x == random => {
                 // Start at the current phase
                 let signal = app.primary.map.get_traffic_signal(i);
-                // TODO Use the offset correctly
-                let mut step = Duration::ZERO;
+                let mut phase_start = Duration::ZERO;
                 for idx in 0..phase {
-                    step += signal.phases[idx].duration;
+                    phase_start += signal.phases[idx].duration;
                 }
+                let step = wrap_into_cycle(
+                    phase_start - signal.offset - (app.primary.sim.time() - Time::START_OF_DAY),
+                    signal.cycle_length(),
+                );
                 app.primary.sim.timed_step(
                     &app.primary.map,
                     step,
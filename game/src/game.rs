@@ -24,17 +24,15 @@ impl Game {
     ) -> Game {
         let title = !opts.dev
             && !flags.sim_flags.load.contains("data/player/save")
+            && !flags.sim_flags.load.contains("data/player/checkpoints")
             && !flags.sim_flags.load.contains("data/system/scenarios")
             && maybe_mode.is_none();
         let mut app = App::new(flags, opts, ctx, title);
 
-        // Handle savestates
-        let savestate = if app
-            .primary
-            .current_flags
-            .sim_flags
-            .load
-            .starts_with("../data/player/saves/")
+        // Handle savestates and checkpoints
+        let load = &app.primary.current_flags.sim_flags.load;
+        let savestate = if load.starts_with("../data/player/saves/")
+            || load.starts_with("../data/player/checkpoints/")
         {
             assert!(maybe_mode.is_none());
             Some(app.primary.clear_sim())
@@ -1,7 +1,9 @@
 use crate::app::{App, PerMap};
 use ezgui::{hotkey, Btn, Color, EventCtx, Key, Line, Text, TextSpan, Widget};
 use geom::{Duration, Pt2D};
-use map_model::{AreaID, BuildingID, BusStopID, IntersectionID, LaneID, ParkingLotID, RoadID};
+use map_model::{
+    AreaID, BuildingID, BusStopID, IntersectionID, LaneID, Map, NoteTarget, ParkingLotID, RoadID,
+};
 use sim::{AgentID, CarID, PedestrianID, TripMode, TripPhaseType};
 use std::collections::BTreeSet;
 
@@ -63,6 +65,19 @@ impl ID {
             ID::Area(id) => primary.map.maybe_get_a(id).map(|a| a.polygon.center()),
         }
     }
+
+    // Lanes share their road's notes. Agents and bus stops can't have notes.
+    pub fn note_target(&self, map: &Map) -> Option<NoteTarget> {
+        match *self {
+            ID::Road(r) => Some(NoteTarget::Road(r)),
+            ID::Lane(l) => Some(NoteTarget::Road(map.get_l(l).parent)),
+            ID::Intersection(i) => Some(NoteTarget::Intersection(i)),
+            ID::Building(b) => Some(NoteTarget::Building(b)),
+            ID::ParkingLot(pl) => Some(NoteTarget::ParkingLot(pl)),
+            ID::Area(a) => Some(NoteTarget::Area(a)),
+            _ => None,
+        }
+    }
 }
 
 pub fn list_names<F: Fn(TextSpan) -> TextSpan>(txt: &mut Text, styler: F, names: BTreeSet<String>) {
@@ -140,6 +155,8 @@ pub fn color_for_trip_phase(app: &App, tpt: TripPhaseType) -> Color {
         TripPhaseType::Aborted | TripPhaseType::Finished => unreachable!(),
         TripPhaseType::DelayedStart => Color::YELLOW,
         TripPhaseType::Remote => Color::PINK,
+        TripPhaseType::WaitingForRideHail => Color::CYAN,
+        TripPhaseType::Delivering => Color::ORANGE,
     }
 }
 
@@ -1,6 +1,7 @@
 use crate::app::App;
 use crate::info::{header_btns, make_table, make_tabs, Details, Tab};
 use crate::render::DrawPedestrian;
+use abstutil::prettyprint_usize;
 use ezgui::{Btn, Color, EventCtx, Line, Text, TextExt, Widget};
 use geom::{Angle, Circle, Distance, Speed, Time};
 use map_model::{BuildingID, LaneID, Traversable, SIDEWALK_THICKNESS};
@@ -17,9 +18,25 @@ pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingID
     if let Some(ref name) = b.name {
         kv.push(("Name", name.clone()));
     }
-    if app.opts.dev {
-        kv.push(("OSM ID", format!("{}", b.osm_way_id)));
+    kv.push(("OSM ID", format!("{}", b.osm_way_id)));
+    if let Some(height) = b.height {
+        kv.push(("Height", height.to_string()));
     }
+    kv.push((
+        "Estimated occupancy",
+        format!(
+            "{} residents, {} jobs",
+            prettyprint_usize(b.occupancy.residents),
+            prettyprint_usize(b.occupancy.jobs)
+        ),
+    ));
+    kv.push((
+        "Expected trips",
+        format!(
+            "~{} per weekday",
+            prettyprint_usize(b.occupancy.expected_trips_per_day())
+        ),
+    ));
 
     if let Some(ref p) = b.parking {
         let free = app.primary.sim.get_free_offstreet_spots(b.id).len();
@@ -5,7 +5,7 @@ use ezgui::{
     Btn, Color, EventCtx, GeomBatch, Line, LinePlot, PlotOptions, RewriteColor, Series, Text,
     TextExt, Widget,
 };
-use geom::{Circle, Distance, Polygon, Pt2D, Statistic, Time};
+use geom::{Circle, Distance, Duration, Polygon, Pt2D, Statistic};
 use map_model::{BusRouteID, BusStopID};
 use sim::{AgentID, CarID};
 
@@ -19,8 +19,15 @@ pub fn stop(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusStopID)
         header_btns(ctx),
     ]));
     rows.push(format!("On {}", app.primary.map.get_parent(id.sidewalk).get_name()).draw_text(ctx));
+    if let Some(ref gtfs_id) = app.primary.map.get_bs(id).gtfs_id {
+        rows.push(
+            Line(format!("GTFS stop ID: {}", gtfs_id))
+                .secondary()
+                .draw(ctx),
+        );
+    }
 
-    let all_arrivals = &sim.get_analytics().bus_arrivals;
+    let upcoming = sim.predict_bus_arrivals(id, &app.primary.map);
     for r in app.primary.map.get_routes_serving_stop(id) {
         let buses = app.primary.sim.status_of_buses(r.id);
         if buses.is_empty() {
@@ -32,15 +39,16 @@ pub fn stop(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusStopID)
                 .insert(format!("Route {}", r.name), Tab::BusStatus(buses[0].0));
         }
 
-        let arrivals: Vec<(Time, CarID)> = all_arrivals
-            .iter()
-            .filter(|(_, _, route, stop)| r.id == *route && id == *stop)
-            .map(|(t, car, _, _)| (*t, *car))
-            .collect();
         let mut txt = Text::new();
-        if let Some((t, _)) = arrivals.last() {
+        if let Some((t, _, _)) = upcoming.iter().find(|(_, _, route)| *route == r.id) {
+            txt.add(Line(format!(
+                "  Next bus in {}",
+                (*t - sim.time()).max(Duration::ZERO)
+            )));
+        }
+        if let Some(t) = sim.get_analytics().last_bus_arrival(sim.time(), r.id, id) {
             // TODO Button to jump to the bus
-            txt.add(Line(format!("  Last bus arrived {} ago", sim.time() - *t)).secondary());
+            txt.add(Line(format!("  Last bus arrived {} ago", sim.time() - t)).secondary());
         } else {
             txt.add(Line("  No arrivals yet").secondary());
         }
@@ -63,10 +71,13 @@ pub fn stop(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusStopID)
 pub fn bus_status(ctx: &mut EventCtx, app: &App, details: &mut Details, id: CarID) -> Vec<Widget> {
     let mut rows = bus_header(ctx, app, details, id, Tab::BusStatus(id));
 
-    let kv = app.primary.sim.bus_properties(id, &app.primary.map);
+    let route = app.primary.sim.bus_route_id(id).unwrap();
+    let mut kv = app.primary.sim.bus_properties(id, &app.primary.map);
+    if let Some(ref gtfs_id) = app.primary.map.get_br(route).gtfs_id {
+        kv.push(("GTFS route ID".to_string(), gtfs_id.clone()));
+    }
     rows.extend(make_table(ctx, kv));
 
-    let route = app.primary.sim.bus_route_id(id).unwrap();
     rows.push(passenger_delay(ctx, app, details, route));
 
     rows
@@ -159,7 +170,25 @@ fn passenger_delay(ctx: &mut EventCtx, app: &App, details: &mut Details, id: Bus
         .sim
         .get_analytics()
         .bus_passenger_delays(app.primary.sim.time(), id);
+    let denied_per_stop = app
+        .primary
+        .sim
+        .get_analytics()
+        .denied_boardings(app.primary.sim.time(), id);
     for idx in 0..route.stops.len() {
+        let mut txt = if let Some(hgram) = delay_per_stop.remove(&route.stops[idx]) {
+            format!(
+                ": {} (avg {})",
+                hgram.count(),
+                hgram.select(Statistic::Mean)
+            )
+        } else {
+            ": nobody".to_string()
+        };
+        let denied = denied_per_stop.get(route.stops[idx]);
+        if denied > 0 {
+            txt.push_str(&format!(", {} turned away by full buses", denied));
+        }
         col.push(Widget::row(vec![
             format!("Stop {}", idx + 1).draw_text(ctx),
             Btn::svg(
@@ -167,16 +196,7 @@ fn passenger_delay(ctx: &mut EventCtx, app: &App, details: &mut Details, id: Bus
                 RewriteColor::Change(Color::hex("#CC4121"), app.cs.hovering),
             )
             .build(ctx, format!("Stop {}", idx + 1), None),
-            if let Some(hgram) = delay_per_stop.remove(&route.stops[idx]) {
-                format!(
-                    ": {} (avg {})",
-                    hgram.count(),
-                    hgram.select(Statistic::Mean)
-                )
-                .draw_text(ctx)
-            } else {
-                ": nobody".draw_text(ctx)
-            },
+            txt.draw_text(ctx),
         ]));
         details
             .warpers
@@ -12,7 +12,7 @@ pub fn area(ctx: &EventCtx, app: &App, _: &mut Details, id: AreaID) -> Vec<Widge
     ]));
 
     let a = app.primary.map.get_a(id);
-    let mut kv = Vec::new();
+    let mut kv = vec![("OSM ID".to_string(), a.osm_id.to_string())];
     for (k, v) in &a.osm_tags {
         kv.push((k.to_string(), v.to_string()));
     }
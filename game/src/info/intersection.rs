@@ -21,9 +21,32 @@ pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: IntersectionID
         // TODO The spacing is ignored, so use -
         txt.add(Line(format!("- {}", r)));
     }
-    if app.opts.dev {
-        txt.add(Line(format!("OSM node ID: {}", i.orig_id.osm_node_id)).secondary());
+    if !i.is_border() {
+        txt.add(Line(
+            if app
+                .primary
+                .sim
+                .enforces_dont_block_the_box(id, &app.primary.map)
+            {
+                "Vehicles wait for room before entering"
+            } else {
+                "Vehicles may block the box"
+            },
+        ));
     }
+    let junction = app.primary.map.get_consolidated_junction(id);
+    if junction.len() > 1 {
+        txt.add(Line(format!(
+            "One junction with {}",
+            junction
+                .into_iter()
+                .filter(|x| *x != id)
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+    txt.add(Line(format!("OSM node ID: {}", i.orig_id.osm_node_id)).secondary());
     rows.push(txt.draw(ctx));
 
     rows
@@ -196,13 +219,8 @@ fn delay_plot(ctx: &EventCtx, app: &App, i: IntersectionID, opts: &DataOptions)
     } else {
         app.primary.sim.time()
     };
-    if let Some(list) = data.intersection_delays.get(&i) {
-        for (t, dt, mode) in list {
-            if *t > limit {
-                break;
-            }
-            by_mode.get_mut(mode).unwrap().push((*t, *dt));
-        }
+    for (t, dt, mode) in data.delays_through(i, limit) {
+        by_mode.get_mut(&mode).unwrap().push((t, dt));
     }
     let series: Vec<Series<Duration>> = by_mode
         .into_iter()
@@ -26,11 +26,33 @@ pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Vec
                 l.number_parking_spots()
             ),
         ));
+        kv.push(("Parking rules", l.parking_restrictions.describe()));
     } else {
         kv.push(("Speed limit", r.speed_limit.to_string()));
+        if !r.timed_speed_limits.is_empty() {
+            kv.push((
+                "Time-of-day limits",
+                r.timed_speed_limits
+                    .iter()
+                    .map(|x| x.describe())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+            kv.push((
+                "Speed limit now",
+                r.speed_limit_at(app.primary.sim.time(), false).to_string(),
+            ));
+        }
+    }
+    if l.is_sidewalk() {
+        kv.push(("Scooter parking", l.scooter_parking.describe()));
+        if let Some(cnt) = app.primary.sim.get_available_scooters().get(&l.id) {
+            kv.push(("Scooters available", cnt.to_string()));
+        }
     }
 
     kv.push(("Length", l.length().describe_rounded()));
+    kv.push(("OSM way ID", r.orig_id.osm_way_id.to_string()));
 
     rows.extend(make_table(ctx, kv));
 
@@ -175,6 +197,16 @@ pub fn traffic(
         "Since midnight: {} agents crossed",
         prettyprint_usize(app.primary.sim.get_analytics().road_thruput.total_for(r.id))
     )));
+    txt.add(Line(format!(
+        "Since midnight: {} trucks entered",
+        prettyprint_usize(
+            app.primary
+                .sim
+                .get_analytics()
+                .truck_road_thruput
+                .total_for(r.id)
+        )
+    )));
     rows.push(txt.draw(ctx));
 
     rows.push(opts.to_controls(ctx, app).margin_below(10));
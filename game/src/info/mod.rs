@@ -11,7 +11,7 @@ use crate::app::App;
 use crate::common::Warping;
 use crate::game::Transition;
 use crate::helpers::{color_for_mode, hotkey_btn, ID};
-use crate::sandbox::{SandboxMode, TimeWarpScreen};
+use crate::sandbox::{SandboxMode, TimeWarpScreen, WarpTrigger};
 use ezgui::{
     hotkey, Btn, Checkbox, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx,
     HorizontalAlignment, Key, Line, LinePlot, Outcome, PlotOptions, Series, TextExt,
@@ -431,7 +431,20 @@ impl InfoPanel {
                                     &mut actions,
                                 );
 
-                                vec![sandbox, TimeWarpScreen::new(ctx, app, time, false)]
+                                // The trip might start later than scheduled, so run until it
+                                // does. Only one sim callback works at a time, so if the
+                                // gameplay mode already has one, just go to the scheduled time.
+                                let warp = if app.primary.sim_cb.is_none() {
+                                    TimeWarpScreen::new(
+                                        ctx,
+                                        app,
+                                        app.primary.sim.get_end_of_day(),
+                                        Some(WarpTrigger::TripStarts(trip)),
+                                    )
+                                } else {
+                                    TimeWarpScreen::new(ctx, app, time, None)
+                                };
+                                vec![sandbox, warp]
                             },
                         ))),
                     )
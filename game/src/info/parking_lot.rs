@@ -8,7 +8,7 @@ use std::collections::HashSet;
 pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: ParkingLotID) -> Vec<Widget> {
     let mut rows = header(ctx, details, id, Tab::ParkingLot(id));
     let pl = app.primary.map.get_pl(id);
-    let capacity = pl.spots.len();
+    let capacity = pl.capacity();
 
     rows.push(
         format!(
@@ -18,6 +18,7 @@ pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: ParkingLot
         )
         .draw_text(ctx),
     );
+    rows.push(Line(format!("OSM ID: {}", pl.osm_id)).secondary().draw(ctx));
 
     let mut series = vec![Series {
         label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
@@ -439,12 +439,18 @@ pub fn parked_car(
 
     // TODO how long idle, prev trips, next trips, etc
 
-    let p = app.primary.sim.get_owner_of_car(id).unwrap();
-    rows.push(Btn::text_bg2(format!("Owned by {}", p)).build_def(ctx, None));
-    details.hyperlinks.insert(
-        format!("Owned by {}", p),
-        Tab::PersonTrips(p, BTreeMap::new()),
-    );
+    if let Some(p) = app.primary.sim.get_owner_of_car(id) {
+        rows.push(Btn::text_bg2(format!("Owned by {}", p)).build_def(ctx, None));
+        details.hyperlinks.insert(
+            format!("Owned by {}", p),
+            Tab::PersonTrips(p, BTreeMap::new()),
+        );
+    } else if app.primary.sim.is_emergency_vehicle(id) {
+        rows.push("Emergency vehicle".draw_text(ctx));
+    } else {
+        // Otherwise, only ride-hail vehicles belong to nobody
+        rows.push("Ride-hail vehicle without a passenger".draw_text(ctx));
+    }
 
     if let Some(p) = app.primary.sim.lookup_parked_car(id) {
         match p.spot {
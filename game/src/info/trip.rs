@@ -98,6 +98,23 @@ pub fn ongoing(
             ]),
         ]));
     }
+    // The panel is rebuilt every time the sim advances, so this stays live
+    if let Some(arrival) = app.primary.sim.predict_arrival(agent, &app.primary.map) {
+        col.push(Widget::row(vec![
+            Widget::row(vec![Line("Arrival").secondary().draw(ctx)])
+                .force_width_pct(ctx, col_width),
+            Text::from_all(vec![
+                Line(arrival.ampm_tostring()),
+                Line(format!(
+                    " done {} in {}",
+                    activity,
+                    arrival - app.primary.sim.time()
+                ))
+                .secondary(),
+            ])
+            .draw(ctx),
+        ]));
+    }
     {
         col.push(Widget::row(vec![
             Widget::row(vec![Line("Waiting").secondary().draw(ctx)])
@@ -504,6 +521,10 @@ fn make_timeline(
                     }
                     // TODO What icon should represent this?
                     TripPhaseType::Remote => "../data/system/assets/timeline/delayed_start.svg",
+                    TripPhaseType::WaitingForRideHail => {
+                        "../data/system/assets/timeline/waiting_for_bus.svg"
+                    }
+                    TripPhaseType::Delivering => "../data/system/assets/timeline/parking.svg",
                 },
             )
             .centered_on(
@@ -570,11 +591,9 @@ fn make_timeline(
     }
 
     let mut col = vec![
-        Widget::row(vec![
-            start_btn,
-            Widget::row(timeline),
-            goal_btn,
-        ]).evenly_spaced().margin_above(25),
+        Widget::row(vec![start_btn, Widget::row(timeline), goal_btn])
+            .evenly_spaced()
+            .margin_above(25),
         Widget::row(vec![
             start_time.ampm_tostring().draw_text(ctx),
             if let Some(t) = end_time {
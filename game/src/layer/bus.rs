@@ -6,7 +6,10 @@ use ezgui::{
     Line, Text, TextExt, VerticalAlignment, Widget,
 };
 use geom::{Circle, Distance, Pt2D, Time};
-use map_model::{BusRouteID, PathConstraints, PathRequest, PathStep};
+use map_model::{BusRouteID, BusStopID, PathConstraints, PathRequest, PathStep};
+
+// How many upcoming buses the arrivals board lists
+const MAX_ARRIVALS: usize = 5;
 
 // TODO This maybe shouldn't be a layer
 pub struct ShowBusRoute {
@@ -37,12 +40,15 @@ impl Layer for ShowBusRoute {
         Layer::simple_event(ctx, minimap, &mut self.composite)
     }
     fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.draw_contents(g, app);
+        self.composite.draw(g);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
         if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             g.redraw(&self.unzoomed);
         } else {
             g.redraw(&self.zoomed);
         }
-        self.composite.draw(g);
 
         // TODO Do this once
         let mut screen_batch = GeomBatch::new();
@@ -146,3 +152,74 @@ impl ShowBusRoute {
         }
     }
 }
+
+// Like the board at a real stop, counting down to the next few buses. Stays open while exploring
+// the rest of the map.
+pub struct ArrivalsBoard {
+    time: Time,
+    stop: BusStopID,
+    composite: Composite,
+}
+
+impl Layer for ArrivalsBoard {
+    fn name(&self) -> Option<&'static str> {
+        None
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            *self = ArrivalsBoard::new(ctx, app, self.stop);
+        }
+
+        Layer::simple_event(ctx, minimap, &mut self.composite)
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.composite.draw(g);
+    }
+    fn draw_contents(&self, _: &mut GfxCtx, _: &App) {}
+    fn draw_minimap(&self, _: &mut GfxCtx) {}
+}
+
+impl ArrivalsBoard {
+    pub fn new(ctx: &mut EventCtx, app: &App, stop: BusStopID) -> ArrivalsBoard {
+        let map = &app.primary.map;
+        let now = app.primary.sim.time();
+
+        let mut col = vec![Widget::row(vec![
+            Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg").margin_right(10),
+            Line(format!(
+                "Arrivals on {}",
+                map.get_parent(stop.sidewalk).get_name()
+            ))
+            .draw(ctx),
+            Btn::plaintext("X")
+                .build(ctx, "close", hotkey(Key::Escape))
+                .align_right(),
+        ])];
+        let arrivals = app.primary.sim.predict_bus_arrivals(stop, map);
+        if arrivals.is_empty() {
+            col.push("No buses on the way".draw_text(ctx));
+        }
+        for (time, _, route) in arrivals.into_iter().take(MAX_ARRIVALS) {
+            let mut txt = Text::from(Line(&map.get_br(route).name));
+            if time <= now {
+                txt.append(Line(" boarding now").secondary());
+            } else {
+                txt.append(Line(format!(" in {}", time - now)).secondary());
+            }
+            col.push(txt.draw(ctx));
+        }
+
+        ArrivalsBoard {
+            time: now,
+            stop,
+            composite: Composite::new(Widget::col(col).padding(5).bg(app.cs.panel_bg))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+                .build(ctx),
+        }
+    }
+}
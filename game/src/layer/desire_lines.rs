@@ -0,0 +1,244 @@
+use crate::app::App;
+use crate::helpers::{color_for_mode, ID};
+use crate::layer::{Layer, LayerOutcome};
+use abstutil::{prettyprint_usize, Counter};
+use ezgui::{
+    hotkey, Btn, Checkbox, Composite, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment,
+    Key, Line, Outcome, Spinner, TextExt, VerticalAlignment, Widget,
+};
+use geom::{Distance, Duration, HashablePt2D, Pt2D, Time};
+use map_model::RoadID;
+use sim::{TripEndpoint, TripMode};
+use std::collections::BTreeSet;
+
+// Straight lines from where each trip starts to where it ends, ignoring the route actually taken.
+pub struct DesireLines {
+    opts: Options,
+    draw: Drawable,
+    composite: Composite,
+}
+
+impl Layer for DesireLines {
+    fn name(&self) -> Option<&'static str> {
+        Some("desire lines")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        self.composite.align_above(ctx, minimap);
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                "only trips crossing the road under the cursor" => {
+                    let crossing = match app.primary.current_selection {
+                        Some(ID::Lane(l)) => Some(app.primary.map.get_l(l).parent),
+                        Some(ID::Road(r)) => Some(r),
+                        _ => None,
+                    };
+                    if crossing.is_some() {
+                        let mut new_opts = self.opts.clone();
+                        new_opts.crossing = crossing;
+                        *self = DesireLines::new(ctx, app, new_opts);
+                        self.composite.align_above(ctx, minimap);
+                    }
+                }
+                "any road" => {
+                    let mut new_opts = self.opts.clone();
+                    new_opts.crossing = None;
+                    *self = DesireLines::new(ctx, app, new_opts);
+                    self.composite.align_above(ctx, minimap);
+                }
+                _ => unreachable!(),
+            },
+            None => {
+                let new_opts = self.options();
+                if self.opts != new_opts {
+                    *self = DesireLines::new(ctx, app, new_opts);
+                    self.composite.align_above(ctx, minimap);
+                }
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.draw);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw);
+    }
+}
+
+impl DesireLines {
+    pub fn new(ctx: &mut EventCtx, app: &App, opts: Options) -> DesireLines {
+        let map = &app.primary.map;
+        let sim = &app.primary.sim;
+        let start_time = Time::START_OF_DAY + Duration::hours(opts.from_hour);
+        let end_time = Time::START_OF_DAY + Duration::hours(opts.to_hour);
+        let crossing = opts.crossing.map(|r| &map.get_r(r).center_pts);
+
+        // Trips between the same two places share one line, drawn more solidly the more trips use
+        // it.
+        let mut lines: Counter<(TripMode, HashablePt2D, HashablePt2D)> = Counter::new();
+        let mut num_trips = 0;
+        for person in sim.get_all_people() {
+            for t in &person.trips {
+                let (departure, from, to, mode) = sim.trip_info(*t);
+                if !opts.modes.contains(&mode) || departure < start_time || departure >= end_time {
+                    continue;
+                }
+                let pt1 = endpoint_pt(&from, app);
+                let pt2 = endpoint_pt(&to, app);
+                let line = match geom::Line::maybe_new(pt1, pt2) {
+                    Some(l) => l,
+                    None => {
+                        continue;
+                    }
+                };
+                if let Some(pl) = crossing {
+                    if pl.intersection(&line.to_polyline()).is_none() {
+                        continue;
+                    }
+                }
+                num_trips += 1;
+                lines.inc((mode, pt1.to_hashable(), pt2.to_hashable()));
+            }
+        }
+
+        // Nothing might match the filters. Then there are no lines to scale against the busiest.
+        let max = if num_trips == 0 { 0 } else { lines.max() };
+        let mut batch = GeomBatch::new();
+        for ((mode, pt1, pt2), cnt) in lines.consume() {
+            let opacity = 0.2 + 0.8 * (cnt as f32) / (max as f32);
+            batch.push(
+                color_for_mode(app, mode).alpha(opacity),
+                geom::Line::new(pt1.to_pt2d(), pt2.to_pt2d()).make_polygons(Distance::meters(3.0)),
+            );
+        }
+        if let Some(pl) = crossing {
+            batch.push(app.cs.selected, pl.make_polygons(Distance::meters(10.0)));
+        }
+
+        let composite = make_controls(ctx, app, &opts, num_trips);
+        DesireLines {
+            opts,
+            draw: ctx.upload(batch),
+            composite,
+        }
+    }
+
+    fn options(&self) -> Options {
+        let mut modes = BTreeSet::new();
+        for m in TripMode::all() {
+            if self.composite.is_checked(m.ongoing_verb()) {
+                modes.insert(m);
+            }
+        }
+        Options {
+            modes,
+            from_hour: self.composite.spinner("from hour"),
+            to_hour: self.composite.spinner("to hour"),
+            crossing: self.opts.crossing,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Options {
+    pub modes: BTreeSet<TripMode>,
+    // Only trips departing in [from_hour, to_hour)
+    pub from_hour: usize,
+    pub to_hour: usize,
+    // Only trips whose straight line crosses this road
+    pub crossing: Option<RoadID>,
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            modes: TripMode::all().into_iter().collect(),
+            from_hour: 0,
+            to_hour: 24,
+            crossing: None,
+        }
+    }
+}
+
+fn make_controls(ctx: &mut EventCtx, app: &App, opts: &Options, num_trips: usize) -> Composite {
+    let mut col = vec![Widget::row(vec![
+        Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg").margin_right(10),
+        Line(format!(
+            "Desire lines: {} trips",
+            prettyprint_usize(num_trips)
+        ))
+        .draw(ctx),
+        Btn::plaintext("X")
+            .build(ctx, "close", hotkey(Key::Escape))
+            .align_right(),
+    ])];
+
+    for m in TripMode::all() {
+        col.push(
+            Widget::row(vec![
+                Checkbox::colored(
+                    ctx,
+                    m.ongoing_verb(),
+                    color_for_mode(app, m),
+                    opts.modes.contains(&m),
+                )
+                .margin_right(5),
+                m.ongoing_verb().draw_text(ctx),
+            ])
+            .margin_below(5),
+        );
+    }
+
+    col.push(
+        Widget::row(vec![
+            "Departing from".draw_text(ctx).margin_right(5),
+            Spinner::new(ctx, (0, 24), opts.from_hour)
+                .named("from hour")
+                .margin_right(5),
+            "to".draw_text(ctx).margin_right(5),
+            Spinner::new(ctx, (0, 24), opts.to_hour).named("to hour"),
+        ])
+        .margin_below(5),
+    );
+
+    if let Some(r) = opts.crossing {
+        col.push(
+            Widget::row(vec![
+                format!("Crossing {}", app.primary.map.get_r(r).get_name())
+                    .draw_text(ctx)
+                    .margin_right(5),
+                Btn::text_fg("any road").build_def(ctx, None),
+            ])
+            .margin_below(5),
+        );
+    }
+    col.push(
+        Btn::text_fg("only trips crossing the road under the cursor")
+            .build_def(ctx, hotkey(Key::C)),
+    );
+
+    Composite::new(Widget::col(col).padding(5).bg(app.cs.panel_bg))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx)
+}
+
+fn endpoint_pt(endpt: &TripEndpoint, app: &App) -> Pt2D {
+    match endpt {
+        TripEndpoint::Bldg(b) => app.primary.map.get_b(*b).polygon.center(),
+        TripEndpoint::Border(i, _) => app.primary.map.get_i(*i).polygon.center(),
+    }
+}
@@ -0,0 +1,337 @@
+use crate::app::App;
+use crate::common::ColorDiscrete;
+use crate::helpers::amenity_type;
+use crate::layer::{Layer, LayerOutcome};
+use abstutil::{prettyprint_usize, Counter};
+use ezgui::{
+    hotkey, Btn, Checkbox, Composite, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Text, TextExt, VerticalAlignment, Widget,
+};
+use geom::{Distance, Duration, FindClosest, Pt2D, Speed};
+use map_model::{
+    AreaType, BuildingID, IntersectionID, LaneID, Map, PathConstraints, Position, BIKING_SPEED,
+    WALKING_SPEED,
+};
+use std::collections::{BTreeMap, HashMap};
+
+// Everyday needs that should be within a short trip of home
+const AMENITIES: [&str; 5] = ["groceries", "school", "park", "pharmacy", "transit"];
+// Indexed by how many amenities are missing
+const SCORES: [&str; 6] = ["all 5", "4 of 5", "3 of 5", "2 of 5", "1 of 5", "none"];
+
+const TIME_BUDGET: Duration = Duration::const_seconds(15.0 * 60.0);
+// An intersection this close to the edge of a park counts as an entrance
+const PARK_ENTRANCE_DIST: Distance = Distance::const_meters(50.0);
+
+pub struct FifteenMinuteCity {
+    opts: Options,
+    composite: Composite,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+}
+
+impl Layer for FifteenMinuteCity {
+    fn name(&self) -> Option<&'static str> {
+        Some("15-minute city")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        self.composite.align_above(ctx, minimap);
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            },
+            None => {
+                let new_opts = Options {
+                    biking: self.composite.is_checked("by bike"),
+                };
+                if self.opts != new_opts {
+                    *self = FifteenMinuteCity::new(ctx, app, new_opts);
+                    self.composite.align_above(ctx, minimap);
+                }
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Options {
+    // Otherwise walking
+    pub biking: bool,
+}
+
+impl FifteenMinuteCity {
+    pub fn new(ctx: &mut EventCtx, app: &App, opts: Options) -> FifteenMinuteCity {
+        let map = &app.primary.map;
+        let (constraints, speed) = if opts.biking {
+            (PathConstraints::Bike, BIKING_SPEED)
+        } else {
+            (PathConstraints::Pedestrian, WALKING_SPEED)
+        };
+        let sources = amenity_sources(map);
+        let homes: Vec<BuildingID> = map
+            .all_buildings()
+            .iter()
+            .filter(|b| b.occupancy.residents > 0)
+            .map(|b| b.id)
+            .collect();
+        let sources_ref = &sources;
+        // Per home, the amenities out of reach
+        let missing_per_home: Vec<(BuildingID, Vec<&'static str>)> =
+            ctx.loading_screen("find amenities near each home", |_, timer| {
+                timer.parallelize("check homes", homes, |b| {
+                    let times = map.travel_times(b, constraints, TIME_BUDGET);
+                    let home = map.get_b(b).front_path.sidewalk;
+                    let missing = sources_ref
+                        .iter()
+                        .filter(|(_, sources)| !sources.reachable(map, home, &times, speed))
+                        .map(|(amenity, _)| *amenity)
+                        .collect();
+                    (b, missing)
+                })
+            });
+
+        let mut colorer = ColorDiscrete::new(
+            app,
+            SCORES
+                .iter()
+                .enumerate()
+                .map(|(idx, label)| {
+                    (
+                        *label,
+                        app.cs
+                            .good_to_bad_red
+                            .eval((idx as f64) / (AMENITIES.len() as f64)),
+                    )
+                })
+                .collect(),
+        );
+        let mut num_homes = 0;
+        let mut total_score = 0;
+        // Per area, how many homes there are and how many are missing each amenity
+        let mut gaps: BTreeMap<&'static str, (usize, Counter<&'static str>)> = BTreeMap::new();
+        for (b, missing) in missing_per_home {
+            let area = gaps
+                .entry(compass_area(map.get_b(b).polygon.center(), map))
+                .or_insert_with(|| (0, Counter::new()));
+            area.0 += 1;
+            for amenity in &missing {
+                area.1.inc(*amenity);
+            }
+            num_homes += 1;
+            total_score += AMENITIES.len() - missing.len();
+            colorer.add_b(b, SCORES[missing.len()]);
+        }
+
+        let mut txt = Text::from(Line(format!(
+            "{} homes, with an average of {:.1} / {} amenities within 15 minutes {}",
+            prettyprint_usize(num_homes),
+            if num_homes == 0 {
+                0.0
+            } else {
+                (total_score as f64) / (num_homes as f64)
+            },
+            AMENITIES.len(),
+            if opts.biking { "biking" } else { "walking" }
+        )));
+        txt.add(Line("Homes missing each amenity, by area:"));
+        for (area, (homes, missing)) in gaps {
+            let list: Vec<String> = AMENITIES
+                .iter()
+                .filter(|a| missing.get(**a) > 0)
+                .map(|a| format!("{} ({}%)", a, 100 * missing.get(*a) / homes))
+                .collect();
+            txt.add(
+                Line(format!(
+                    "{}: {}",
+                    area,
+                    if list.is_empty() {
+                        "nothing".to_string()
+                    } else {
+                        list.join(", ")
+                    }
+                ))
+                .small(),
+            );
+        }
+
+        let (unzoomed, zoomed, legend) = colorer.build(ctx);
+        let composite = Composite::new(
+            Widget::col(vec![
+                Widget::row(vec![
+                    Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg")
+                        .margin_right(10),
+                    "15-minute city".draw_text(ctx),
+                    Btn::plaintext("X")
+                        .build(ctx, "close", hotkey(Key::Escape))
+                        .align_right(),
+                ]),
+                Checkbox::text(ctx, "by bike", None, opts.biking).margin_below(5),
+                txt.draw(ctx).margin_below(10),
+                legend,
+            ])
+            .padding(5)
+            .bg(app.cs.panel_bg),
+        )
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        FifteenMinuteCity {
+            opts,
+            composite,
+            unzoomed,
+            zoomed,
+        }
+    }
+}
+
+// Where to find one type of amenity
+#[derive(Default)]
+struct Sources {
+    // Distance along each sidewalk
+    per_sidewalk: BTreeMap<LaneID, Vec<Distance>>,
+    intersections: Vec<IntersectionID>,
+}
+
+impl Sources {
+    fn add_pos(&mut self, pos: Position) {
+        self.per_sidewalk
+            .entry(pos.lane())
+            .or_insert_with(Vec::new)
+            .push(pos.dist_along());
+    }
+
+    // Given the travel times from a home, is any source close enough? Like the opportunities
+    // layer, the last stretch from an intersection goes along the source's road in either
+    // direction.
+    fn reachable(
+        &self,
+        map: &Map,
+        home: Position,
+        times: &HashMap<IntersectionID, Duration>,
+        speed: Speed,
+    ) -> bool {
+        if self.intersections.iter().any(|i| times.contains_key(i)) {
+            return true;
+        }
+        for (l, dists) in &self.per_sidewalk {
+            let lane = map.get_l(*l);
+            let from_src = times.get(&lane.src_i);
+            let from_dst = times.get(&lane.dst_i);
+            for dist in dists {
+                // Right down the street
+                if home.lane() == *l {
+                    let along = if *dist > home.dist_along() {
+                        *dist - home.dist_along()
+                    } else {
+                        home.dist_along() - *dist
+                    };
+                    if along / speed <= TIME_BUDGET {
+                        return true;
+                    }
+                }
+                if from_src
+                    .map(|t| *t + *dist / speed <= TIME_BUDGET)
+                    .unwrap_or(false)
+                    || from_dst
+                        .map(|t| *t + (lane.length() - *dist) / speed <= TIME_BUDGET)
+                        .unwrap_or(false)
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+fn amenity_sources(map: &Map) -> Vec<(&'static str, Sources)> {
+    let mut sources: BTreeMap<&'static str, Sources> = BTreeMap::new();
+    for a in &AMENITIES {
+        sources.insert(*a, Sources::default());
+    }
+
+    for b in map.all_buildings() {
+        for (_, a) in &b.amenities {
+            let amenity = if amenity_type(a) == Some("groceries") {
+                "groceries"
+            } else if a == "school" || a == "kindergarten" {
+                "school"
+            } else if a == "pharmacy" {
+                "pharmacy"
+            } else {
+                continue;
+            };
+            sources
+                .get_mut(amenity)
+                .unwrap()
+                .add_pos(b.front_path.sidewalk);
+        }
+    }
+
+    // Parks are areas, not buildings
+    let mut parks = FindClosest::new(map.get_bounds());
+    for a in map.all_areas() {
+        if a.area_type == AreaType::Park {
+            parks.add(a.id, a.polygon.points());
+        }
+    }
+    for i in map.all_intersections() {
+        if parks
+            .closest_pt(i.polygon.center(), PARK_ENTRANCE_DIST)
+            .is_some()
+        {
+            sources.get_mut("park").unwrap().intersections.push(i.id);
+        }
+    }
+
+    for bs in map.all_bus_stops().values() {
+        sources.get_mut("transit").unwrap().add_pos(bs.sidewalk_pos);
+    }
+
+    AMENITIES
+        .iter()
+        .map(|a| (*a, sources.remove(a).unwrap()))
+        .collect()
+}
+
+// Split the map into rough quarters, the same way agents are colored by origin
+fn compass_area(pt: Pt2D, map: &Map) -> &'static str {
+    let center = map.get_bounds().get_rectangle().center();
+    let dx = pt.x() - center.x();
+    // Y increases going south
+    let dy = pt.y() - center.y();
+    if dx.abs() > dy.abs() {
+        if dx > 0.0 {
+            "East"
+        } else {
+            "West"
+        }
+    } else if dy > 0.0 {
+        "South"
+    } else {
+        "North"
+    }
+}
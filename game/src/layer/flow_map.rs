@@ -0,0 +1,220 @@
+use crate::app::App;
+use crate::common::ColorLegend;
+use crate::helpers::color_for_mode;
+use crate::layer::{Layer, LayerOutcome};
+use abstutil::{prettyprint_usize, Counter};
+use ezgui::{
+    hotkey, Btn, Checkbox, Composite, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment,
+    Key, Line, Outcome, Spinner, Text, TextExt, VerticalAlignment, Widget,
+};
+use geom::Distance;
+use map_model::DirectedRoadID;
+use sim::TripMode;
+use std::collections::BTreeSet;
+
+// The busiest direction of any road gets a band this wide.
+const MAX_BAND_WIDTH: Distance = Distance::const_meters(20.0);
+
+// Vehicle volumes along each side of every road over a window of hours, drawn as bands as wide as
+// the volume.
+pub struct FlowMap {
+    opts: Options,
+    draw: Drawable,
+    composite: Composite,
+}
+
+impl Layer for FlowMap {
+    fn name(&self) -> Option<&'static str> {
+        Some("flow map")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        self.composite.align_above(ctx, minimap);
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                "export to CSV" => {
+                    let path = format!(
+                        "../data/player/flow_map_{}_{}.csv",
+                        app.primary.map.get_name(),
+                        app.primary.sim.time().as_filename()
+                    );
+                    app.primary.sim.get_analytics().export_flow_map(
+                        path,
+                        &app.primary.map,
+                        self.opts.from_hour,
+                        self.opts.to_hour,
+                    );
+                }
+                _ => unreachable!(),
+            },
+            None => {
+                let new_opts = self.options();
+                if self.opts != new_opts {
+                    *self = FlowMap::new(ctx, app, new_opts);
+                    self.composite.align_above(ctx, minimap);
+                }
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.draw);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw);
+    }
+}
+
+impl FlowMap {
+    pub fn new(ctx: &mut EventCtx, app: &App, opts: Options) -> FlowMap {
+        let map = &app.primary.map;
+        let mut volumes: Counter<DirectedRoadID> = Counter::new();
+        for ((dr, mode, hour), count) in
+            &app.primary.sim.get_analytics().directed_road_thruput.counts
+        {
+            if opts.modes.contains(mode) && (opts.from_hour..opts.to_hour).contains(hour) {
+                volumes.add(*dr, *count);
+            }
+        }
+
+        // Early in the day or with picky filters, there may be nothing to show yet
+        let max = if volumes.borrow().is_empty() {
+            None
+        } else {
+            Some(volumes.max())
+        };
+        let mut batch = GeomBatch::new();
+        for (dr, cnt) in volumes.consume() {
+            let pct = (cnt as f64) / (max.unwrap() as f64);
+            let width = (MAX_BAND_WIDTH * pct).max(Distance::meters(1.0));
+            let r = map.get_r(dr.id);
+            let center = if dr.forwards {
+                r.center_pts.clone()
+            } else {
+                r.center_pts.reversed()
+            };
+            // Each direction's band sits on the side of the road it drives on.
+            let pl = map.right_shift(center, width / 2.0).unwrap();
+            batch.push(app.cs.good_to_bad_red.eval(pct), pl.make_polygons(width));
+        }
+
+        let composite = make_controls(ctx, app, &opts, max);
+        FlowMap {
+            opts,
+            draw: ctx.upload(batch),
+            composite,
+        }
+    }
+
+    fn options(&self) -> Options {
+        let mut modes = BTreeSet::new();
+        for m in vehicle_modes() {
+            if self.composite.is_checked(m.ongoing_verb()) {
+                modes.insert(m);
+            }
+        }
+        Options {
+            modes,
+            from_hour: self.composite.spinner("from hour"),
+            to_hour: self.composite.spinner("to hour"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Options {
+    pub modes: BTreeSet<TripMode>,
+    // Only count vehicles in [from_hour, to_hour)
+    pub from_hour: usize,
+    pub to_hour: usize,
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            modes: vehicle_modes().into_iter().collect(),
+            from_hour: 0,
+            to_hour: 24,
+        }
+    }
+}
+
+// Sidewalks don't have a direction, so pedestrians are only in the CSV export.
+fn vehicle_modes() -> Vec<TripMode> {
+    TripMode::all()
+        .into_iter()
+        .filter(|m| *m != TripMode::Walk)
+        .collect()
+}
+
+fn make_controls(ctx: &mut EventCtx, app: &App, opts: &Options, max: Option<usize>) -> Composite {
+    let mut col = vec![
+        Widget::row(vec![
+            Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg").margin_right(10),
+            "Flow map".draw_text(ctx),
+            Btn::plaintext("X")
+                .build(ctx, "close", hotkey(Key::Escape))
+                .align_right(),
+        ]),
+        Text::from(
+            Line("Band width shows how many vehicles traveled each direction of a road")
+                .secondary(),
+        )
+        .wrap_to_pct(ctx, 15)
+        .draw(ctx)
+        .margin_below(5),
+    ];
+
+    for m in vehicle_modes() {
+        col.push(
+            Widget::row(vec![
+                Checkbox::colored(
+                    ctx,
+                    m.ongoing_verb(),
+                    color_for_mode(app, m),
+                    opts.modes.contains(&m),
+                )
+                .margin_right(5),
+                m.ongoing_verb().draw_text(ctx),
+            ])
+            .margin_below(5),
+        );
+    }
+
+    col.push(
+        Widget::row(vec![
+            "From hour".draw_text(ctx).margin_right(5),
+            Spinner::new(ctx, (0, 24), opts.from_hour)
+                .named("from hour")
+                .margin_right(5),
+            "to".draw_text(ctx).margin_right(5),
+            Spinner::new(ctx, (0, 24), opts.to_hour).named("to hour"),
+        ])
+        .margin_below(5),
+    );
+    if let Some(max) = max {
+        col.push(ColorLegend::gradient(
+            ctx,
+            &app.cs.good_to_bad_red,
+            vec!["0".to_string(), prettyprint_usize(max)],
+        ));
+    } else {
+        col.push("No traffic yet".draw_text(ctx).margin_below(5));
+    }
+    col.push(Btn::text_fg("export to CSV").build_def(ctx, None));
+
+    Composite::new(Widget::col(col).padding(5).bg(app.cs.panel_bg))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx)
+}
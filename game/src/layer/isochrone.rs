@@ -0,0 +1,174 @@
+use crate::app::App;
+use crate::common::ColorLegend;
+use crate::layer::{Layer, LayerOutcome};
+use ezgui::{
+    hotkey, Btn, Choice, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx,
+    HorizontalAlignment, Key, Line, Outcome, Text, TextExt, VerticalAlignment, Widget,
+};
+use geom::{Bounds, Distance, Duration, Grid};
+use map_model::{BuildingID, PathConstraints, WALKING_SPEED};
+
+// In minutes
+const THRESHOLDS: [usize; 3] = [5, 10, 15];
+// How finely to sample travel times. Smaller is smoother, but slower.
+const RESOLUTION: Distance = Distance::const_meters(20.0);
+// Places off the network, like the middle of a block, are reached on foot from the closest
+// intersections.
+const OFF_NETWORK_RADIUS: Distance = Distance::const_meters(200.0);
+
+// How far somebody can walk or drive from one building in a few minutes, ignoring traffic
+pub struct Isochrone {
+    start: BuildingID,
+    constraints: PathConstraints,
+    draw: Drawable,
+    composite: Composite,
+}
+
+impl Layer for Isochrone {
+    fn name(&self) -> Option<&'static str> {
+        None
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        self.composite.align_above(ctx, minimap);
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            },
+            None => {
+                let constraints = self.composite.dropdown_value("mode");
+                if constraints != self.constraints {
+                    *self = Isochrone::new(ctx, app, self.start, constraints);
+                    self.composite.align_above(ctx, minimap);
+                }
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.draw_contents(g, app);
+        self.composite.draw(g);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.draw);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw);
+    }
+}
+
+impl Isochrone {
+    pub fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        start: BuildingID,
+        constraints: PathConstraints,
+    ) -> Isochrone {
+        let map = &app.primary.map;
+        let limit = Duration::minutes(*THRESHOLDS.last().unwrap());
+        let times = map.travel_times(start, constraints, limit);
+
+        let mut batch = GeomBatch::new();
+        if !times.is_empty() {
+            let mut bounds = Bounds::new();
+            for i in times.keys() {
+                bounds.union(map.get_i(*i).polygon.get_bounds());
+            }
+            let pad = OFF_NETWORK_RADIUS.inner_meters();
+            bounds.min_x -= pad;
+            bounds.min_y -= pad;
+            bounds.max_x += pad;
+            bounds.max_y += pad;
+
+            // Anything not reached stays past every threshold
+            let mut grid = Grid::new(&bounds, RESOLUTION, 2.0 * limit.inner_seconds());
+            let per_meter = 1.0 / WALKING_SPEED.inner_meters_per_second();
+            for (i, time) in &times {
+                grid.splat_min(
+                    map.get_i(*i).polygon.center(),
+                    OFF_NETWORK_RADIUS,
+                    time.inner_seconds(),
+                    per_meter,
+                );
+            }
+            // The farthest band goes first, so the closer ones are drawn over it
+            for (idx, minutes) in THRESHOLDS.iter().enumerate().rev() {
+                if let Some(p) = grid.contour_below(Duration::minutes(*minutes).inner_seconds()) {
+                    batch.push(color(app, idx).alpha(0.5), p);
+                }
+            }
+        }
+        batch.push(Color::BLACK, map.get_b(start).polygon.clone());
+
+        let mut legend = Vec::new();
+        for (idx, minutes) in THRESHOLDS.iter().enumerate() {
+            legend.push(ColorLegend::row(
+                ctx,
+                color(app, idx),
+                format!("within {} minutes", minutes),
+            ));
+        }
+
+        let composite = Composite::new(
+            Widget::col(vec![
+                Widget::row(vec![
+                    Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg")
+                        .margin_right(10),
+                    "Isochrones".draw_text(ctx),
+                    Btn::plaintext("X")
+                        .build(ctx, "close", hotkey(Key::Escape))
+                        .align_right(),
+                ]),
+                Text::from(
+                    Line(format!(
+                        "How far you can get from {}, without any traffic or waiting at \
+                         intersections",
+                        map.get_b(start).address
+                    ))
+                    .secondary(),
+                )
+                .wrap_to_pct(ctx, 15)
+                .draw(ctx)
+                .margin_below(5),
+                Widget::row(vec![
+                    "Travel by".draw_text(ctx).centered_vert().margin_right(5),
+                    Widget::dropdown(
+                        ctx,
+                        "mode",
+                        constraints,
+                        vec![
+                            Choice::new("walking", PathConstraints::Pedestrian),
+                            Choice::new("driving", PathConstraints::Car),
+                        ],
+                    ),
+                ])
+                .margin_below(5),
+                Widget::col(legend),
+            ])
+            .padding(5)
+            .bg(app.cs.panel_bg),
+        )
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        Isochrone {
+            start,
+            constraints,
+            draw: ctx.upload(batch),
+            composite,
+        }
+    }
+}
+
+fn color(app: &App, idx: usize) -> Color {
+    app.cs
+        .good_to_bad_red
+        .eval((idx as f64) / ((THRESHOLDS.len() - 1) as f64))
+}
@@ -8,8 +8,9 @@ use ezgui::{
     Text, TextExt, VerticalAlignment, Widget,
 };
 use geom::{Distance, Time};
-use map_model::LaneType;
-use sim::TripMode;
+use map_model::{LaneType, TurnType};
+use sim::{TripMode, WeatherConditions};
+use std::collections::BTreeSet;
 
 pub struct BikeNetwork {
     composite: Composite,
@@ -36,6 +37,9 @@ impl Layer for BikeNetwork {
     }
     fn draw(&self, g: &mut GfxCtx, app: &App) {
         self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
         if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             g.redraw(&self.unzoomed);
         } else {
@@ -150,6 +154,150 @@ impl BikeNetwork {
     }
 }
 
+// Lanes closed by incidents, refreshed as the sim runs
+pub struct Incidents {
+    time: Time,
+    inner: Static,
+}
+
+impl Layer for Incidents {
+    fn name(&self) -> Option<&'static str> {
+        Some("incidents")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            *self = Incidents::new(ctx, app);
+        }
+        self.inner.event(ctx, app, minimap)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.inner.draw(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        self.inner.draw_contents(g, app);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        self.inner.draw_minimap(g);
+    }
+}
+
+impl Incidents {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Incidents {
+        let mut colorer = ColorDiscrete::new(
+            app,
+            vec![("closed now", Color::RED), ("closing later", Color::ORANGE)],
+        );
+        let now = app.primary.sim.time();
+        let blocked = app.primary.sim.get_blocked_lanes();
+        let mut upcoming = 0;
+        for incident in app.primary.sim.get_incidents() {
+            if incident.start > now {
+                upcoming += 1;
+                for l in &incident.lanes {
+                    if !blocked.contains(l) {
+                        colorer.add_l(*l, "closing later");
+                    }
+                }
+            }
+        }
+        for l in &blocked {
+            colorer.add_l(*l, "closed now");
+        }
+
+        Incidents {
+            time: now,
+            inner: Static::new(
+                ctx,
+                app,
+                colorer,
+                "incidents",
+                "Incidents".to_string(),
+                Text::from_multiline(vec![
+                    Line(format!("{} lanes closed right now", blocked.len())),
+                    Line(format!("{} more incidents scheduled", upcoming)),
+                ])
+                .draw(ctx),
+            ),
+        }
+    }
+}
+
+pub struct Weather {
+    time: Time,
+    inner: Static,
+}
+
+impl Layer for Weather {
+    fn name(&self) -> Option<&'static str> {
+        Some("weather")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            *self = Weather::new(ctx, app);
+        }
+        self.inner.event(ctx, app, minimap)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.inner.draw(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        self.inner.draw_contents(g, app);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        self.inner.draw_minimap(g);
+    }
+}
+
+impl Weather {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Weather {
+        let mut colorer = ColorDiscrete::new(
+            app,
+            vec![
+                ("rain", Color::BLUE),
+                ("snow", Color::WHITE),
+                ("ice", Color::CYAN),
+            ],
+        );
+        let now = app.primary.sim.time();
+        let weather = app.primary.sim.get_weather();
+        for r in app.primary.map.all_roads() {
+            match weather.conditions_at(r.id, now) {
+                WeatherConditions::Clear => {}
+                x => {
+                    colorer.add_r(r.id, x.describe());
+                }
+            }
+        }
+        let upcoming = weather.spells.iter().filter(|s| s.start > now).count();
+
+        Weather {
+            time: now,
+            inner: Static::new(
+                ctx,
+                app,
+                colorer,
+                "weather",
+                "Weather".to_string(),
+                Text::from_multiline(vec![
+                    Line(format!("Right now: {}", weather.current(now).describe())),
+                    Line(format!("{} more spells of weather scheduled", upcoming)),
+                ])
+                .draw(ctx),
+            ),
+        }
+    }
+}
+
 pub struct Static {
     composite: Composite,
     pub unzoomed: Drawable,
@@ -171,6 +319,9 @@ impl Layer for Static {
     }
     fn draw(&self, g: &mut GfxCtx, app: &App) {
         self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
         if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             g.redraw(&self.unzoomed);
         } else {
@@ -247,6 +398,79 @@ impl Static {
         )
     }
 
+    pub fn snow_routes(ctx: &mut EventCtx, app: &App) -> Static {
+        let mut colorer = ColorDiscrete::new(app, vec![("snow route", Color::CYAN)]);
+        let mut cnt = 0;
+        for r in app.primary.map.all_roads() {
+            if r.snow_route {
+                colorer.add_r(r.id, "snow route");
+                cnt += 1;
+            }
+        }
+
+        Static::new(
+            ctx,
+            app,
+            colorer,
+            "snow routes",
+            "Snow routes".to_string(),
+            format!(
+                "{} roads stay plowed; others slow down when the sim runs with --snow",
+                cnt
+            )
+            .draw_text(ctx),
+        )
+    }
+
+    pub fn accessibility_gaps(ctx: &mut EventCtx, app: &App) -> Static {
+        let mut colorer = ColorDiscrete::new(
+            app,
+            vec![
+                ("no curb ramps, on walking trips", Color::RED),
+                ("no curb ramps", Color::YELLOW),
+            ],
+        );
+        let map = &app.primary.map;
+        let crossings = &app.primary.sim.get_analytics().crossings;
+        // Each crosswalk has a turn going each way
+        let mut used = BTreeSet::new();
+        let mut unused = BTreeSet::new();
+        for t in map.all_turns().values() {
+            if t.turn_type != TurnType::Crosswalk && t.turn_type != TurnType::DiagonalCrosswalk {
+                continue;
+            }
+            if map.crossing_has_curb_ramps(t.id) {
+                continue;
+            }
+            let key = (t.id.src.min(t.id.dst), t.id.src.max(t.id.dst));
+            if crossings.contains_key(&t.id) {
+                colorer.add_t(t.id, "no curb ramps, on walking trips");
+                used.insert(key);
+            } else {
+                colorer.add_t(t.id, "no curb ramps");
+                unused.insert(key);
+            }
+        }
+        let unused = unused.difference(&used).count();
+
+        Static::new(
+            ctx,
+            app,
+            colorer,
+            "accessibility gaps",
+            "Accessibility gaps".to_string(),
+            Text::from_multiline(vec![
+                Line(format!(
+                    "{} crossings without curb ramps are on somebody's shortest walking path",
+                    used.len()
+                )),
+                Line(format!("{} more crossings are missing curb ramps", unused)),
+                Line("Run with --wheelchair_share to route some people around them"),
+            ])
+            .draw(ctx),
+        )
+    }
+
     pub fn edits(ctx: &mut EventCtx, app: &App) -> Static {
         let mut colorer = ColorDiscrete::new(
             app,
@@ -254,7 +478,12 @@ impl Static {
         );
 
         let edits = app.primary.map.get_edits();
-        for l in edits.original_lts.keys().chain(&edits.reversed_lanes) {
+        for l in edits
+            .original_lts
+            .keys()
+            .chain(&edits.reversed_lanes)
+            .chain(edits.original_lane_widths.keys())
+        {
             colorer.add_l(*l, "modified lane/intersection");
         }
         for i in edits.original_intersections.keys() {
@@ -273,6 +502,10 @@ impl Static {
             Text::from_multiline(vec![
                 Line(format!("{} lane types changed", edits.original_lts.len())),
                 Line(format!("{} lanes reversed", edits.reversed_lanes.len())),
+                Line(format!(
+                    "{} lane widths changed",
+                    edits.original_lane_widths.len()
+                )),
                 Line(format!(
                     "{} speed limits changed",
                     edits.changed_speed_limits.len()
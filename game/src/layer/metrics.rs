@@ -0,0 +1,123 @@
+use crate::app::App;
+use crate::common::{ColorLegend, ColorNetwork};
+use crate::layer::{Layer, LayerOutcome};
+use ezgui::{
+    hotkey, Btn, Composite, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, LinePlot,
+    PlotOptions, Series, Text, TextExt, VerticalAlignment, Widget,
+};
+use geom::Time;
+use sim::Measure;
+
+// Each custom metric from --metrics, plotted so far, with the roads and intersections it watches
+pub struct CustomMetrics {
+    time: Time,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    composite: Composite,
+}
+
+impl Layer for CustomMetrics {
+    fn name(&self) -> Option<&'static str> {
+        Some("custom metrics")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            *self = CustomMetrics::new(ctx, app);
+        }
+
+        Layer::simple_event(ctx, minimap, &mut self.composite)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl CustomMetrics {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> CustomMetrics {
+        let now = app.primary.sim.time();
+        let mut colorer = ColorNetwork::new(app);
+        let mut col = vec![Widget::row(vec![
+            Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg").margin_right(10),
+            "Custom metrics".draw_text(ctx),
+            Btn::plaintext("X")
+                .build(ctx, "close", hotkey(Key::Escape))
+                .align_right(),
+        ])];
+
+        for (idx, m) in app
+            .primary
+            .sim
+            .get_analytics()
+            .custom_metrics
+            .iter()
+            .enumerate()
+        {
+            let color = app.cs.rotating_color_agents(idx);
+            for r in &m.metric.roads {
+                colorer.add_r(*r, color);
+            }
+            for i in &m.metric.intersections {
+                colorer.add_i(*i, color);
+            }
+
+            col.push(ColorLegend::row(ctx, color, &m.metric.name));
+            let label = format!("{:?}", m.metric.event);
+            col.push(match m.metric.measure {
+                Measure::Count => LinePlot::new(
+                    ctx,
+                    vec![Series {
+                        label,
+                        color,
+                        pts: m.count_over_time(now),
+                    }],
+                    PlotOptions::fixed(),
+                ),
+                Measure::TotalDuration => LinePlot::new(
+                    ctx,
+                    vec![Series {
+                        label,
+                        color,
+                        pts: m.total_over_time(now),
+                    }],
+                    PlotOptions::fixed(),
+                ),
+            });
+        }
+        col.push(
+            Text::from(
+                Line("Metrics without a road or intersection filter cover the whole map")
+                    .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .draw(ctx),
+        );
+
+        let composite = Composite::new(Widget::col(col).padding(5).bg(app.cs.panel_bg))
+            .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+            .build(ctx);
+        let (unzoomed, zoomed) = colorer.build(ctx);
+
+        CustomMetrics {
+            time: now,
+            unzoomed,
+            zoomed,
+            composite,
+        }
+    }
+}
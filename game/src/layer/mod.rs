@@ -1,16 +1,27 @@
 pub mod bus;
+mod desire_lines;
 mod elevation;
+mod fifteen_min;
+mod flow_map;
+pub mod isochrone;
 pub mod map;
+mod metrics;
+mod notes;
+mod opportunities;
 mod pandemic;
 mod parking;
 mod population;
 pub mod traffic;
+mod trails;
 
 use crate::app::App;
 use crate::common::HeatmapOptions;
 use crate::game::{DrawBaselayer, State, Transition};
 use crate::helpers::hotkey_btn;
-use ezgui::{hotkey, Btn, Composite, EventCtx, GfxCtx, Key, Line, Outcome, TextExt, Widget};
+use crate::render::{AgentColorScheme, ColorAgentsBy};
+use ezgui::{
+    hotkey, Btn, Composite, EventCtx, GfxCtx, Key, Line, Outcome, Slider, TextExt, Widget,
+};
 
 // TODO Good ideas in
 // https://towardsdatascience.com/top-10-map-types-in-data-visualization-b3a80898ea70
@@ -25,8 +36,14 @@ pub trait Layer {
     ) -> Option<LayerOutcome>;
     // Draw both controls and, if zoomed, the layer contents
     fn draw(&self, g: &mut GfxCtx, app: &App);
+    // Just draw the layer contents, like draw does, without the controls
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App);
     // Just draw contents and do it always
     fn draw_minimap(&self, g: &mut GfxCtx);
+    // Keep redrawing, even while the sim is paused
+    fn animated(&self) -> bool {
+        false
+    }
 }
 
 impl dyn Layer {
@@ -51,6 +68,37 @@ pub enum LayerOutcome {
     Close,
 }
 
+// A layer pinned beneath the active one, like a stack of overlays in GIS software. Pinned layers
+// don't handle events, so they show what they did when they were pinned, and only their contents
+// are drawn; showing the legend makes one active again.
+pub struct PinnedLayer {
+    pub layer: Box<dyn Layer>,
+    pub opacity: f64,
+}
+
+impl PinnedLayer {
+    // Under the active layer in the map view. Only the active layer's controls are shown.
+    pub fn draw_all(g: &mut GfxCtx, app: &App) {
+        for pinned in &app.pinned_layers {
+            g.set_opacity(pinned.opacity);
+            pinned.layer.draw_contents(g, app);
+        }
+        g.set_opacity(1.0);
+    }
+
+    pub fn draw_all_minimap(g: &mut GfxCtx, app: &App) {
+        for pinned in &app.pinned_layers {
+            g.set_opacity(pinned.opacity);
+            pinned.layer.draw_minimap(g);
+        }
+        g.set_opacity(1.0);
+    }
+
+    fn name(&self) -> &'static str {
+        self.layer.name().unwrap_or("custom layer")
+    }
+}
+
 // TODO Maybe overkill, but could embed a minimap and preview the layer on hover
 pub struct PickLayer {
     composite: Composite,
@@ -104,30 +152,99 @@ impl PickLayer {
                 Widget::col(vec![
                     "Traffic".draw_text(ctx).margin_below(10),
                     btn("delay", Key::D),
+                    btn("pedestrian delay", Key::Num3),
                     btn("throughput", Key::T),
                     btn("traffic jams", Key::J),
+                    btn("desire lines", Key::W),
+                    btn("flow particles", Key::F),
+                    btn("flow map", Key::K),
+                    btn("agent trails", Key::Num6),
+                    btn("congestion", Key::Num1),
                 ]),
                 Widget::col(vec![
                     "Map".draw_text(ctx).margin_below(10),
                     btn("map edits", Key::E),
                     btn("parking occupancy", Key::P),
+                    btn("overnight parking", Key::Num4),
                     btn("bike network", Key::B),
                     btn("bus network", Key::U),
+                    btn("snow routes", Key::V),
                     btn("population map", Key::X),
+                    btn("notes", Key::Q),
                 ]),
             ])
             .evenly_spaced(),
         );
 
+        let agents_btn = |name: &str, color_by, key| {
+            if app.agent_cs.color_by == color_by {
+                Btn::text_bg2(name).inactive(ctx)
+            } else {
+                hotkey_btn(ctx, app, name, key)
+            }
+            .margin_below(10)
+        };
+        col.extend(vec![
+            "Color agents by".draw_text(ctx).margin_below(10),
+            Widget::row(vec![
+                agents_btn("mode", ColorAgentsBy::Mode, Key::M).margin_right(10),
+                agents_btn("trip purpose", ColorAgentsBy::TripPurpose, Key::R).margin_right(10),
+                agents_btn("delay so far", ColorAgentsBy::Delay, Key::L).margin_right(10),
+                agents_btn("origin zone", ColorAgentsBy::OriginZone, Key::O),
+            ]),
+        ]);
+
         col.extend(vec![
             "Experimental".draw_text(ctx).margin_below(10),
             btn("amenities", Key::A),
             btn("backpressure", Key::Z),
             btn("elevation", Key::S),
+            btn("15-minute city", Key::C),
+            btn("accessibility gaps", Key::G),
+            btn("access to opportunities", Key::Num2),
+            btn("incidents", Key::I),
+            btn("weather", Key::H),
         ]);
         if app.primary.sim.get_pandemic_model().is_some() {
             col.push(btn("pandemic model", Key::Y));
         }
+        if !app.primary.sim.get_analytics().custom_metrics.is_empty() {
+            col.push(btn("custom metrics", Key::Num7));
+        }
+
+        if app.layer.is_some() || !app.pinned_layers.is_empty() {
+            col.push(
+                Widget::row(vec![
+                    "Stacked layers".draw_text(ctx),
+                    if app.layer.is_some() {
+                        Btn::text_fg("pin current layer")
+                            .build_def(ctx, hotkey(Key::Num5))
+                            .align_right()
+                    } else {
+                        Widget::nothing()
+                    },
+                ])
+                .margin_above(10)
+                .margin_below(10),
+            );
+        }
+        // Topmost first, like the layers panel in GIS software
+        for (idx, pinned) in app.pinned_layers.iter().enumerate().rev() {
+            col.push(
+                Widget::row(vec![
+                    pinned.name().draw_text(ctx).margin_right(10),
+                    Slider::horizontal(ctx, 100.0, 25.0, pinned.opacity)
+                        .named(format!("opacity {}", idx))
+                        .margin_right(10),
+                    Btn::plaintext("raise").build(ctx, format!("raise {}", idx), None),
+                    Btn::plaintext("lower").build(ctx, format!("lower {}", idx), None),
+                    Btn::plaintext("legend").build(ctx, format!("legend {}", idx), None),
+                    Btn::plaintext("remove").build(ctx, format!("remove {}", idx), None),
+                ])
+                .evenly_spaced()
+                .margin_below(10),
+            );
+        }
 
         Box::new(PickLayer {
             composite: Composite::new(Widget::col(col).bg(app.cs.panel_bg).padding(16))
@@ -142,6 +259,50 @@ impl State for PickLayer {
         match self.composite.event(ctx) {
             Some(Outcome::Clicked(x)) => match x.as_ref() {
                 "close" => {}
+                "pin current layer" => {
+                    let layer = app.layer.take().unwrap();
+                    app.pinned_layers.push(PinnedLayer {
+                        layer,
+                        opacity: 0.5,
+                    });
+                    return Transition::Replace(PickLayer::pick(ctx, app));
+                }
+                x if x.starts_with("raise ") => {
+                    let idx = x["raise ".len()..].parse::<usize>().unwrap();
+                    if idx != app.pinned_layers.len() - 1 {
+                        app.pinned_layers.swap(idx, idx + 1);
+                    }
+                    return Transition::Replace(PickLayer::pick(ctx, app));
+                }
+                x if x.starts_with("lower ") => {
+                    let idx = x["lower ".len()..].parse::<usize>().unwrap();
+                    if idx != 0 {
+                        app.pinned_layers.swap(idx, idx - 1);
+                    }
+                    return Transition::Replace(PickLayer::pick(ctx, app));
+                }
+                x if x.starts_with("remove ") => {
+                    let idx = x["remove ".len()..].parse::<usize>().unwrap();
+                    app.pinned_layers.remove(idx);
+                    return Transition::Replace(PickLayer::pick(ctx, app));
+                }
+                x if x.starts_with("legend ") => {
+                    // The active layer takes its place in the stack
+                    let idx = x["legend ".len()..].parse::<usize>().unwrap();
+                    match app.layer.take() {
+                        Some(layer) => {
+                            let opacity = app.pinned_layers[idx].opacity;
+                            let pinned = std::mem::replace(
+                                &mut app.pinned_layers[idx],
+                                PinnedLayer { layer, opacity },
+                            );
+                            app.layer = Some(pinned.layer);
+                        }
+                        None => {
+                            app.layer = Some(app.pinned_layers.remove(idx).layer);
+                        }
+                    }
+                }
                 "None" => {
                     app.layer = None;
                 }
@@ -150,14 +311,64 @@ impl State for PickLayer {
                         ctx, app, true, true, true, false,
                     )));
                 }
+                "overnight parking" => {
+                    app.layer = Some(Box::new(parking::Overnight::new(ctx, app)));
+                }
+                "mode" => {
+                    app.agent_cs = AgentColorScheme::new(&app.cs);
+                }
+                "trip purpose" => {
+                    app.agent_cs = AgentColorScheme::by(&app.cs, ColorAgentsBy::TripPurpose);
+                }
+                "delay so far" => {
+                    app.agent_cs = AgentColorScheme::by(&app.cs, ColorAgentsBy::Delay);
+                }
+                "origin zone" => {
+                    app.agent_cs = AgentColorScheme::by(&app.cs, ColorAgentsBy::OriginZone);
+                }
                 "delay" => {
                     app.layer = Some(Box::new(traffic::Delay::new(ctx, app, false)));
                 }
+                "pedestrian delay" => {
+                    app.layer = Some(Box::new(traffic::PedestrianDelay::new(ctx, app)));
+                }
+                "congestion" => {
+                    app.layer = Some(Box::new(traffic::Congestion::new(ctx, app)));
+                }
                 "traffic jams" => {
                     app.layer = Some(Box::new(traffic::TrafficJams::new(ctx, app)));
                 }
                 "throughput" => {
-                    app.layer = Some(Box::new(traffic::Throughput::new(ctx, app, false)));
+                    app.layer = Some(Box::new(traffic::Throughput::new(
+                        ctx, app, false, false, None,
+                    )));
+                }
+                "desire lines" => {
+                    app.layer = Some(Box::new(desire_lines::DesireLines::new(
+                        ctx,
+                        app,
+                        desire_lines::Options::new(),
+                    )));
+                }
+                "flow particles" => {
+                    app.layer = Some(Box::new(traffic::FlowParticles::new(ctx, app)));
+                }
+                "flow map" => {
+                    app.layer = Some(Box::new(flow_map::FlowMap::new(
+                        ctx,
+                        app,
+                        flow_map::Options::new(),
+                    )));
+                }
+                "agent trails" => {
+                    app.layer = Some(Box::new(trails::Trails::new(
+                        ctx,
+                        app,
+                        trails::Options::new(),
+                    )));
+                }
+                "custom metrics" => {
+                    app.layer = Some(Box::new(metrics::CustomMetrics::new(ctx, app)));
                 }
                 "backpressure" => {
                     app.layer = Some(Box::new(traffic::Backpressure::new(ctx, app)));
@@ -168,9 +379,38 @@ impl State for PickLayer {
                 "bus network" => {
                     app.layer = Some(Box::new(map::Static::bus_network(ctx, app)));
                 }
+                "snow routes" => {
+                    app.layer = Some(Box::new(map::Static::snow_routes(ctx, app)));
+                }
                 "elevation" => {
                     app.layer = Some(Box::new(elevation::Elevation::new(ctx, app)));
                 }
+                "15-minute city" => {
+                    app.layer = Some(Box::new(fifteen_min::FifteenMinuteCity::new(
+                        ctx,
+                        app,
+                        fifteen_min::Options { biking: false },
+                    )));
+                }
+                "access to opportunities" => {
+                    app.layer = Some(Box::new(opportunities::Opportunities::new(
+                        ctx,
+                        app,
+                        opportunities::Options::new(),
+                    )));
+                }
+                "incidents" => {
+                    app.layer = Some(Box::new(map::Incidents::new(ctx, app)));
+                }
+                "weather" => {
+                    app.layer = Some(Box::new(map::Weather::new(ctx, app)));
+                }
+                "accessibility gaps" => {
+                    app.layer = Some(Box::new(map::Static::accessibility_gaps(ctx, app)));
+                }
+                "notes" => {
+                    app.layer = Some(Box::new(notes::Notes::new(ctx, app, None)));
+                }
                 "map edits" => {
                     app.layer = Some(Box::new(map::Static::edits(ctx, app)));
                 }
@@ -199,6 +439,12 @@ impl State for PickLayer {
                 _ => unreachable!(),
             },
             None => {
+                for (idx, pinned) in app.pinned_layers.iter_mut().enumerate() {
+                    pinned.opacity = self
+                        .composite
+                        .slider(&format!("opacity {}", idx))
+                        .get_percent();
+                }
                 if self.composite.clicked_outside(ctx) {
                     return Transition::Pop;
                 }
@@ -0,0 +1,171 @@
+use crate::app::App;
+use crate::common::ColorDiscrete;
+use crate::layer::{Layer, LayerOutcome};
+use ezgui::{
+    hotkey, Btn, Choice, Composite, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Text, TextExt, VerticalAlignment, Widget,
+};
+use map_model::{NoteTarget, ObjectNote};
+use std::collections::BTreeMap;
+
+// Don't make the panel huge; the CSV export has everything.
+const MAX_LISTED: usize = 10;
+
+// Objects that analysts have written notes about, optionally just the ones with one tag.
+pub struct Notes {
+    tag: Option<String>,
+    // To notice when somebody writes a new note while the layer is open
+    notes: BTreeMap<NoteTarget, ObjectNote>,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    composite: Composite,
+}
+
+impl Layer for Notes {
+    fn name(&self) -> Option<&'static str> {
+        Some("notes")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        self.composite.align_above(ctx, minimap);
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                "export to CSV" => {
+                    let path = format!(
+                        "../data/player/notes_{}_{}.csv",
+                        app.primary.map.get_name(),
+                        self.tag.clone().unwrap_or_else(|| "all".to_string())
+                    );
+                    app.primary.map.get_edits().export_notes(
+                        path,
+                        self.tag.as_ref().map(|t| t.as_str()),
+                        &app.primary.map,
+                    );
+                }
+                _ => unreachable!(),
+            },
+            None => {
+                let tag: Option<String> = self.composite.dropdown_value("tag");
+                if tag != self.tag || self.notes != app.primary.map.get_edits().notes {
+                    *self = Notes::new(ctx, app, tag);
+                    self.composite.align_above(ctx, minimap);
+                }
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl Notes {
+    pub fn new(ctx: &mut EventCtx, app: &App, tag: Option<String>) -> Notes {
+        let map = &app.primary.map;
+        let edits = map.get_edits();
+        // The tag might've been removed from the last object that had it
+        let tag = tag.filter(|t| edits.all_tags().contains(t));
+        let targets = match tag {
+            Some(ref t) => edits.tagged(t),
+            None => edits.notes.keys().cloned().collect(),
+        };
+
+        let mut colorer = ColorDiscrete::new(app, vec![("has a note", app.cs.notes_layer)]);
+        for target in &targets {
+            match *target {
+                NoteTarget::Road(r) => colorer.add_r(r, "has a note"),
+                NoteTarget::Intersection(i) => colorer.add_i(i, "has a note"),
+                NoteTarget::Building(b) => colorer.add_b(b, "has a note"),
+                NoteTarget::ParkingLot(pl) => colorer.add_pl(pl, "has a note"),
+                NoteTarget::Area(a) => colorer.add_a(a, "has a note"),
+            }
+        }
+        let (unzoomed, zoomed, legend) = colorer.build(ctx);
+
+        let mut choices = vec![Choice::new("any tag", None)];
+        for t in edits.all_tags() {
+            choices.push(Choice::new(t.clone(), Some(t)));
+        }
+
+        let mut txt = Text::new();
+        for target in targets.iter().take(MAX_LISTED) {
+            let note = &edits.notes[target];
+            txt.add(Line(target.describe(map)));
+            if !note.tags.is_empty() {
+                txt.add(
+                    Line(format!(
+                        "  {}",
+                        note.tags.iter().cloned().collect::<Vec<_>>().join(", ")
+                    ))
+                    .secondary(),
+                );
+            }
+            if !note.note.is_empty() {
+                txt.add(Line(format!("  {}", note.note)).secondary());
+            }
+        }
+        if targets.len() > MAX_LISTED {
+            txt.add(Line(format!("... and {} more", targets.len() - MAX_LISTED)).secondary());
+        }
+
+        let composite = Composite::new(
+            Widget::col(vec![
+                Widget::row(vec![
+                    Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg")
+                        .margin_right(10),
+                    format!("Notes ({})", edits.edits_name).draw_text(ctx),
+                    Btn::plaintext("X")
+                        .build(ctx, "close", hotkey(Key::Escape))
+                        .align_right(),
+                ]),
+                Text::from(
+                    Line("Select something and press N to write a note about it").secondary(),
+                )
+                .wrap_to_pct(ctx, 15)
+                .draw(ctx)
+                .margin_below(5),
+                Widget::row(vec![
+                    "Show".draw_text(ctx).centered_vert().margin_right(5),
+                    Widget::dropdown(ctx, "tag", tag.clone(), choices),
+                ])
+                .margin_below(5),
+                format!("{} objects", targets.len())
+                    .draw_text(ctx)
+                    .margin_below(5),
+                txt.wrap_to_pct(ctx, 20).draw(ctx).margin_below(5),
+                legend,
+                Btn::text_fg("export to CSV").build_def(ctx, None),
+            ])
+            .padding(5)
+            .bg(app.cs.panel_bg),
+        )
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        Notes {
+            tag,
+            notes: edits.notes.clone(),
+            unzoomed,
+            zoomed,
+            composite,
+        }
+    }
+}
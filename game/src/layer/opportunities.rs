@@ -0,0 +1,321 @@
+use crate::app::App;
+use crate::common::{ColorLegend, ColorNetwork};
+use crate::layer::{Layer, LayerOutcome};
+use abstutil::prettyprint_usize;
+use ezgui::{
+    hotkey, Btn, Choice, Composite, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Text, TextExt, VerticalAlignment, Widget,
+};
+use geom::{Distance, Duration};
+use map_model::{BuildingID, LaneID, Map, PathConstraints, WALKING_SPEED};
+use std::collections::BTreeMap;
+
+// Scores every home by how many amenities or jobs are reachable within a few minutes, using the
+// same travel times as the isochrones.
+pub struct Opportunities {
+    opts: Options,
+    // Per home, how many opportunities are in reach
+    scores: Vec<(BuildingID, usize)>,
+    composite: Composite,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+}
+
+impl Layer for Opportunities {
+    fn name(&self) -> Option<&'static str> {
+        Some("access to opportunities")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        self.composite.align_above(ctx, minimap);
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                "export to CSV" => {
+                    let path = format!(
+                        "../data/player/opportunities_{}_{}_{}_{}min.csv",
+                        app.primary.map.get_name(),
+                        if self.opts.jobs { "jobs" } else { "amenities" },
+                        mode_name(self.opts.mode),
+                        self.opts.minutes
+                    );
+                    self.export_csv(path, &app.primary.map);
+                }
+                _ => unreachable!(),
+            },
+            None => {
+                let new_opts = Options {
+                    mode: self.composite.dropdown_value("mode"),
+                    minutes: self.composite.dropdown_value("minutes"),
+                    jobs: self.composite.dropdown_value("opportunities"),
+                };
+                if self.opts != new_opts {
+                    *self = Opportunities::new(ctx, app, new_opts);
+                    self.composite.align_above(ctx, minimap);
+                }
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Options {
+    // Pedestrian or Car
+    pub mode: PathConstraints,
+    pub minutes: usize,
+    // Otherwise amenities
+    pub jobs: bool,
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            mode: PathConstraints::Pedestrian,
+            minutes: 15,
+            jobs: false,
+        }
+    }
+}
+
+impl Opportunities {
+    pub fn new(ctx: &mut EventCtx, app: &App, opts: Options) -> Opportunities {
+        let map = &app.primary.map;
+        let sources = Sources::new(map, opts.jobs);
+        let limit = Duration::minutes(opts.minutes);
+        let homes: Vec<BuildingID> = map
+            .all_buildings()
+            .iter()
+            .filter(|b| b.occupancy.residents > 0)
+            .map(|b| b.id)
+            .collect();
+        let mode = opts.mode;
+        let sources_ref = &sources;
+        let scores: Vec<(BuildingID, usize)> =
+            ctx.loading_screen("score access to opportunities", |_, timer| {
+                timer.parallelize("score homes", homes, |b| {
+                    (b, sources_ref.reachable(map, b, mode, limit))
+                })
+            });
+
+        let max = scores.iter().map(|(_, cnt)| *cnt).max().unwrap_or(0);
+        let mut colorer = ColorNetwork::new(app);
+        for (b, cnt) in &scores {
+            let pct = if max == 0 {
+                0.0
+            } else {
+                (*cnt as f64) / (max as f64)
+            };
+            colorer.add_b(*b, app.cs.good_to_bad_green.eval(pct));
+        }
+        let (unzoomed, zoomed) = colorer.build(ctx);
+
+        let noun = if opts.jobs { "jobs" } else { "amenities" };
+        let mut txt = Text::from(Line(format!(
+            "{} homes, with an average of {} {} within {} minutes {}",
+            prettyprint_usize(scores.len()),
+            prettyprint_usize(if scores.is_empty() {
+                0
+            } else {
+                scores.iter().map(|(_, cnt)| *cnt).sum::<usize>() / scores.len()
+            }),
+            noun,
+            opts.minutes,
+            mode_name(opts.mode)
+        )));
+        txt.add(
+            Line(format!(
+                "{} homes can't reach any",
+                prettyprint_usize(scores.iter().filter(|(_, cnt)| *cnt == 0).count())
+            ))
+            .secondary(),
+        );
+
+        let composite = Composite::new(
+            Widget::col(vec![
+                Widget::row(vec![
+                    Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg")
+                        .margin_right(10),
+                    "Access to opportunities".draw_text(ctx),
+                    Btn::plaintext("X")
+                        .build(ctx, "close", hotkey(Key::Escape))
+                        .align_right(),
+                ]),
+                Widget::row(vec![
+                    "Count".draw_text(ctx).centered_vert().margin_right(5),
+                    Widget::dropdown(
+                        ctx,
+                        "opportunities",
+                        opts.jobs,
+                        vec![Choice::new("amenities", false), Choice::new("jobs", true)],
+                    ),
+                ])
+                .margin_below(5),
+                Widget::row(vec![
+                    "within".draw_text(ctx).centered_vert().margin_right(5),
+                    Widget::dropdown(
+                        ctx,
+                        "minutes",
+                        opts.minutes,
+                        vec![5, 10, 15, 20, 30]
+                            .into_iter()
+                            .map(|m| Choice::new(format!("{} minutes", m), m))
+                            .collect(),
+                    )
+                    .margin_right(5),
+                    Widget::dropdown(
+                        ctx,
+                        "mode",
+                        opts.mode,
+                        vec![
+                            Choice::new(
+                                mode_name(PathConstraints::Pedestrian),
+                                PathConstraints::Pedestrian,
+                            ),
+                            Choice::new(mode_name(PathConstraints::Car), PathConstraints::Car),
+                        ],
+                    ),
+                ])
+                .margin_below(5),
+                txt.wrap_to_pct(ctx, 20).draw(ctx).margin_below(10),
+                ColorLegend::gradient(
+                    ctx,
+                    &app.cs.good_to_bad_green,
+                    vec!["0".to_string(), prettyprint_usize(max)],
+                ),
+                Btn::text_fg("export to CSV").build_def(ctx, None),
+            ])
+            .padding(5)
+            .bg(app.cs.panel_bg),
+        )
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        Opportunities {
+            opts,
+            scores,
+            composite,
+            unzoomed,
+            zoomed,
+        }
+    }
+
+    fn export_csv(&self, path: String, map: &Map) {
+        let mut out = String::from("building,osm_way_id,address,residents,reachable\n");
+        for (b, cnt) in &self.scores {
+            let bldg = map.get_b(*b);
+            out.push_str(&format!(
+                "{},{},\"{}\",{},{}\n",
+                b.0,
+                bldg.osm_way_id,
+                bldg.address.replace("\"", "'"),
+                bldg.occupancy.residents,
+                cnt
+            ));
+        }
+        if let Err(err) = std::fs::write(&path, out) {
+            panic!("Can't write {}: {}", path, err);
+        }
+        println!("Wrote {}", path);
+    }
+}
+
+fn mode_name(mode: PathConstraints) -> &'static str {
+    match mode {
+        PathConstraints::Car => "driving",
+        _ => "walking",
+    }
+}
+
+// Opportunities grouped by the sidewalk in front of them, with their distance along it
+struct Sources {
+    per_sidewalk: BTreeMap<LaneID, Vec<(Distance, usize)>>,
+}
+
+impl Sources {
+    fn new(map: &Map, jobs: bool) -> Sources {
+        let mut per_sidewalk = BTreeMap::new();
+        for b in map.all_buildings() {
+            let cnt = if jobs {
+                b.occupancy.jobs
+            } else {
+                b.amenities.len()
+            };
+            if cnt > 0 {
+                per_sidewalk
+                    .entry(b.sidewalk())
+                    .or_insert_with(Vec::new)
+                    .push((b.front_path.sidewalk.dist_along(), cnt));
+            }
+        }
+        Sources { per_sidewalk }
+    }
+
+    // The last stretch from an intersection to each opportunity is along its road, at walking
+    // speed or the speed limit. The direction of that road is ignored.
+    fn reachable(
+        &self,
+        map: &Map,
+        home: BuildingID,
+        mode: PathConstraints,
+        limit: Duration,
+    ) -> usize {
+        let times = map.travel_times(home, mode, limit);
+        let home_pos = map.get_b(home).front_path.sidewalk;
+        let mut total = 0;
+        for (l, list) in &self.per_sidewalk {
+            let lane = map.get_l(*l);
+            let speed = if mode == PathConstraints::Car {
+                map.get_parent(*l).speed_limit
+            } else {
+                WALKING_SPEED
+            };
+            let from_src = times.get(&lane.src_i).cloned();
+            let from_dst = times.get(&lane.dst_i).cloned();
+            for (dist, cnt) in list {
+                let mut best = None;
+                if let Some(t) = from_src {
+                    best = Some(t + *dist / speed);
+                }
+                if let Some(t) = from_dst {
+                    let t = t + (lane.length() - *dist) / speed;
+                    best = Some(best.map(|b: Duration| b.min(t)).unwrap_or(t));
+                }
+                // Right down the street
+                if home_pos.lane() == *l {
+                    let along = if *dist > home_pos.dist_along() {
+                        *dist - home_pos.dist_along()
+                    } else {
+                        home_pos.dist_along() - *dist
+                    };
+                    let t = along / WALKING_SPEED;
+                    best = Some(best.map(|b: Duration| b.min(t)).unwrap_or(t));
+                }
+                if best.map(|t| t <= limit).unwrap_or(false) {
+                    total += cnt;
+                }
+            }
+        }
+        total
+    }
+}
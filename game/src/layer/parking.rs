@@ -3,14 +3,17 @@ use crate::common::{ColorLegend, ColorNetwork};
 use crate::layer::{Layer, LayerOutcome};
 use abstutil::{prettyprint_usize, Counter};
 use ezgui::{
-    hotkey, Btn, Checkbox, Composite, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Key, Line,
-    Outcome, Text, TextExt, VerticalAlignment, Widget,
+    hotkey, Btn, Checkbox, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx,
+    HorizontalAlignment, Key, Line, Outcome, Text, TextExt, VerticalAlignment, Widget,
 };
-use geom::Time;
-use map_model::{BuildingID, Map, ParkingLotID, RoadID};
-use sim::{ParkingSpot, VehicleType};
+use geom::{Distance, Polygon, Pt2D, Time};
+use map_model::{BuildingID, LaneType, Map, ParkingLotID, RoadID, PARKING_SPOT_LENGTH};
+use sim::{ParkingSpot, TripEndpoint, TripMode, VehicleType};
 use std::collections::HashSet;
 
+// The overnight parking projection groups everything into squares this big
+const NEIGHBORHOOD_SIZE: Distance = Distance::const_meters(400.0);
+
 pub struct Occupancy {
     time: Time,
     onstreet: bool,
@@ -77,6 +80,9 @@ impl Layer for Occupancy {
     }
     fn draw(&self, g: &mut GfxCtx, app: &App) {
         self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
         if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             g.redraw(&self.unzoomed);
         } else {
@@ -248,6 +254,169 @@ impl Occupancy {
     }
 }
 
+// Where every car will want to park at the end of the day, compared to the spots there. Each car
+// spends the night where the last trip driving it ends, or where it started the day if it's never
+// driven. Neighborhoods short on parking are shaded, distinguishing the ones that only come up
+// short because edits removed on-street parking.
+pub struct Overnight {
+    draw: Drawable,
+    composite: Composite,
+}
+
+impl Layer for Overnight {
+    fn name(&self) -> Option<&'static str> {
+        Some("overnight parking")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        _: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        Layer::simple_event(ctx, minimap, &mut self.composite)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.draw);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw);
+    }
+}
+
+impl Overnight {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Overnight {
+        let map = &app.primary.map;
+        let sim = &app.primary.sim;
+        let bounds = map.get_bounds();
+        let size = NEIGHBORHOOD_SIZE.inner_meters();
+        let neighborhood = |pt: Pt2D| {
+            (
+                ((pt.x() - bounds.min_x) / size).floor() as usize,
+                ((pt.y() - bounds.min_y) / size).floor() as usize,
+            )
+        };
+
+        let mut demand = Counter::new();
+        for person in sim.get_all_people() {
+            let num_cars = person
+                .vehicles
+                .iter()
+                .filter(|v| v.vehicle_type == VehicleType::Car)
+                .count();
+            if num_cars == 0 || person.trips.is_empty() {
+                continue;
+            }
+            let mut overnight = sim.trip_info(person.trips[0]).1;
+            for t in &person.trips {
+                let (_, _, end, mode) = sim.trip_info(*t);
+                if mode == TripMode::Drive {
+                    overnight = end;
+                }
+            }
+            // Cars leaving the map don't need a spot in it
+            if let TripEndpoint::Bldg(b) = overnight {
+                demand.add(neighborhood(map.get_b(b).polygon.center()), num_cars);
+            }
+        }
+
+        let mut supply = Counter::new();
+        let (filled_spots, avail_spots) = sim.get_all_parking_spots();
+        for spot in filled_spots.into_iter().chain(avail_spots.into_iter()) {
+            let pt = match spot {
+                ParkingSpot::Onstreet(l, _) => map.get_l(l).lane_center_pts.middle(),
+                ParkingSpot::Offstreet(b, _) => map.get_b(b).polygon.center(),
+                ParkingSpot::Lot(pl, _) => map.get_pl(pl).polygon.center(),
+            };
+            supply.inc(neighborhood(pt));
+        }
+
+        // Roughly how many spots did each parking lane the edits changed have?
+        let mut removed = Counter::new();
+        for (l, orig_lt) in &map.get_edits().original_lts {
+            let lane = map.get_l(*l);
+            if *orig_lt != LaneType::Parking || lane.lane_type == LaneType::Parking {
+                continue;
+            }
+            let spots = ((lane.length() / PARKING_SPOT_LENGTH).floor() - 2.0).max(0.0) as usize;
+            removed.add(neighborhood(lane.lane_center_pts.middle()), spots);
+        }
+
+        let short_before = Color::hex("#F2994A").alpha(0.5);
+        let short_after_edits = Color::hex("#A32015").alpha(0.7);
+        let cars = demand.sum();
+        let mut batch = GeomBatch::new();
+        let mut num_short = 0;
+        let mut num_short_from_edits = 0;
+        let mut shortfall = 0;
+        for (cell, cnt) in demand.consume() {
+            let spots = supply.get(cell);
+            if cnt <= spots {
+                continue;
+            }
+            num_short += 1;
+            shortfall += cnt - spots;
+            let color = if cnt <= spots + removed.get(cell) {
+                num_short_from_edits += 1;
+                short_after_edits
+            } else {
+                short_before
+            };
+            batch.push(
+                color,
+                Polygon::rectangle(size, size).translate(
+                    bounds.min_x + size * (cell.0 as f64),
+                    bounds.min_y + size * (cell.1 as f64),
+                ),
+            );
+        }
+
+        let composite = Composite::new(
+            Widget::col(vec![
+                Widget::row(vec![
+                    Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg")
+                        .margin_right(10),
+                    "Overnight parking".draw_text(ctx),
+                    Btn::plaintext("X")
+                        .build(ctx, "close", hotkey(Key::Escape))
+                        .align_right(),
+                ]),
+                Text::from_multiline(vec![
+                    Line(format!(
+                        "{} cars park in the map at the end of the day",
+                        prettyprint_usize(cars)
+                    )),
+                    Line(format!(
+                        "{} neighborhoods are short {} spots",
+                        prettyprint_usize(num_short),
+                        prettyprint_usize(shortfall)
+                    )),
+                    Line(format!(
+                        "{} of them only because of edits removing parking",
+                        prettyprint_usize(num_short_from_edits)
+                    )),
+                ])
+                .draw(ctx)
+                .margin_below(10),
+                ColorLegend::row(ctx, short_before, "more cars than spots"),
+                ColorLegend::row(ctx, short_after_edits, "more cars than spots after edits"),
+            ])
+            .padding(5)
+            .bg(app.cs.panel_bg),
+        )
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        Overnight {
+            draw: ctx.upload(batch),
+            composite,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 enum Loc {
     Road(RoadID),
@@ -55,6 +55,9 @@ impl Layer for PopulationMap {
     }
     fn draw(&self, g: &mut GfxCtx, app: &App) {
         self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
         if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             g.redraw(&self.draw);
         }
@@ -1,14 +1,16 @@
 use crate::app::App;
 use crate::common::{ColorLegend, ColorNetwork, ColorScale, DivergingScale};
 use crate::layer::{Layer, LayerOutcome};
-use abstutil::Counter;
+use abstutil::{prettyprint_usize, Counter};
 use ezgui::{
-    hotkey, Btn, Checkbox, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx,
+    hotkey, Btn, Checkbox, Choice, Color, Composite, Drawable, EventCtx, GeomBatch, GfxCtx,
     HorizontalAlignment, Key, Line, Outcome, Text, TextExt, VerticalAlignment, Widget,
 };
-use geom::{Distance, Duration, Polygon, Time};
+use geom::{Circle, Distance, Duration, PolyLine, Polygon, Pt2D, Speed, Time};
+use instant::Instant;
 use map_model::{IntersectionID, Map, Traversable};
 use maplit::btreeset;
+use sim::TripMode;
 use std::collections::BTreeSet;
 
 pub struct Backpressure {
@@ -36,6 +38,9 @@ impl Layer for Backpressure {
     }
     fn draw(&self, g: &mut GfxCtx, app: &App) {
         self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
         if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             g.redraw(&self.unzoomed);
         } else {
@@ -106,10 +111,129 @@ impl Backpressure {
     }
 }
 
+// How far apart particles start along a lane
+const PARTICLE_SPACING: Distance = Distance::const_meters(50.0);
+// Particles move this many times faster than the traffic they represent, so the motion is visible
+// from far away.
+const PARTICLE_TIME_SCALE: f64 = 10.0;
+
+// Not real agents; just dots flowing along every lane at the current average speed of the
+// vehicles on it, to get a feel for the whole city at a glance.
+pub struct FlowParticles {
+    time: Time,
+    started: Instant,
+    lanes: Vec<(PolyLine, Speed, Color)>,
+    composite: Composite,
+}
+
+impl Layer for FlowParticles {
+    fn name(&self) -> Option<&'static str> {
+        Some("flow particles")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            self.time = app.primary.sim.time();
+            self.lanes = FlowParticles::lanes(app);
+        }
+
+        Layer::simple_event(ctx, minimap, &mut self.composite)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        if g.canvas.cam_zoom >= app.opts.min_zoom_for_detail {
+            return;
+        }
+
+        let elapsed = PARTICLE_TIME_SCALE * Duration::realtime_elapsed(self.started);
+        let circle = Circle::new(Pt2D::new(0.0, 0.0), Distance::meters(5.0)).to_polygon();
+        let mut batch = GeomBatch::new();
+        for (pl, speed, color) in &self.lanes {
+            let mut dist = Distance::meters(
+                (elapsed * *speed).inner_meters() % PARTICLE_SPACING.inner_meters(),
+            );
+            while dist < pl.length() {
+                let (pt, _) = pl.dist_along(dist);
+                batch.push(*color, circle.translate(pt.x(), pt.y()));
+                dist += PARTICLE_SPACING;
+            }
+        }
+        batch.draw(g);
+    }
+    fn draw_minimap(&self, _: &mut GfxCtx) {}
+    fn animated(&self) -> bool {
+        true
+    }
+}
+
+impl FlowParticles {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> FlowParticles {
+        let composite = Composite::new(
+            Widget::col(vec![
+                Widget::row(vec![
+                    Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg")
+                        .margin_right(10),
+                    "Flow particles".draw_text(ctx),
+                    Btn::plaintext("X")
+                        .build(ctx, "close", hotkey(Key::Escape))
+                        .align_right(),
+                ]),
+                Text::from(
+                    Line(format!(
+                        "Particles move {}x faster than the traffic on each lane",
+                        PARTICLE_TIME_SCALE
+                    ))
+                    .secondary(),
+                )
+                .wrap_to_pct(ctx, 15)
+                .draw(ctx),
+                ColorLegend::gradient(ctx, &app.cs.good_to_bad_red, vec!["speed limit", "stopped"]),
+            ])
+            .padding(5)
+            .bg(app.cs.panel_bg),
+        )
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        FlowParticles {
+            time: app.primary.sim.time(),
+            started: Instant::now(),
+            lanes: FlowParticles::lanes(app),
+            composite,
+        }
+    }
+
+    fn lanes(app: &App) -> Vec<(PolyLine, Speed, Color)> {
+        let map = &app.primary.map;
+        let mut lanes = Vec::new();
+        for (l, speed) in app.primary.sim.average_speeds() {
+            let lane = map.get_l(l);
+            let limit = map.get_r(lane.parent).speed_limit;
+            let pct = (speed.inner_meters_per_second() / limit.inner_meters_per_second()).min(1.0);
+            lanes.push((
+                lane.lane_center_pts.clone(),
+                speed,
+                app.cs.good_to_bad_red.eval(1.0 - pct),
+            ));
+        }
+        lanes
+    }
+}
+
 // TODO Filter by mode
 pub struct Throughput {
     time: Time,
     compare: bool,
+    trucks: bool,
+    // Only count the last few hours. None means since midnight.
+    window: Option<usize>,
     unzoomed: Drawable,
     zoomed: Drawable,
     composite: Composite,
@@ -126,7 +250,7 @@ impl Layer for Throughput {
         minimap: &Composite,
     ) -> Option<LayerOutcome> {
         if app.primary.sim.time() != self.time {
-            *self = Throughput::new(ctx, app, self.compare);
+            *self = Throughput::new(ctx, app, self.compare, self.trucks, self.window);
         }
 
         self.composite.align_above(ctx, minimap);
@@ -135,13 +259,34 @@ impl Layer for Throughput {
                 "close" => {
                     return Some(LayerOutcome::Close);
                 }
+                "export road volumes" => {
+                    let path = format!(
+                        "../data/player/road_volumes_{}_{}.csv",
+                        app.primary.map.get_name(),
+                        app.primary.sim.time().as_filename()
+                    );
+                    app.primary
+                        .sim
+                        .get_analytics()
+                        .export_road_volumes(path, &app.primary.map);
+                }
                 _ => unreachable!(),
             },
             None => {
                 let new_compare = self.composite.has_widget("Compare before edits")
                     && self.composite.is_checked("Compare before edits");
-                if new_compare != self.compare {
-                    *self = Throughput::new(ctx, app, new_compare);
+                let new_trucks = self.composite.has_widget("only trucks")
+                    && self.composite.is_checked("only trucks");
+                let new_window = if self.composite.has_widget("window") {
+                    self.composite.dropdown_value("window")
+                } else {
+                    self.window
+                };
+                if new_compare != self.compare
+                    || new_trucks != self.trucks
+                    || new_window != self.window
+                {
+                    *self = Throughput::new(ctx, app, new_compare, new_trucks, new_window);
                     self.composite.align_above(ctx, minimap);
                 }
             }
@@ -150,6 +295,9 @@ impl Layer for Throughput {
     }
     fn draw(&self, g: &mut GfxCtx, app: &App) {
         self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
         if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             g.redraw(&self.unzoomed);
         } else {
@@ -161,11 +309,32 @@ impl Layer for Throughput {
     }
 }
 
+// Hour blocks, since that's how finely the analytics count
+fn window_choices() -> Vec<Choice<Option<usize>>> {
+    vec![
+        Choice::new("since midnight", None),
+        Choice::new("this hour", Some(1)),
+        Choice::new("last 3 hours", Some(3)),
+        Choice::new("last 6 hours", Some(6)),
+    ]
+}
+
 impl Throughput {
-    pub fn new(ctx: &mut EventCtx, app: &App, compare: bool) -> Throughput {
+    pub fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        compare: bool,
+        trucks: bool,
+        window: Option<usize>,
+    ) -> Throughput {
         if compare {
-            return Throughput::compare_throughput(ctx, app);
+            return Throughput::compare_throughput(ctx, app, window);
         }
+        let since = match window {
+            Some(1) => "in the current hour".to_string(),
+            Some(hours) => format!("in the last {} hours", hours),
+            None => "since midnight".to_string(),
+        };
         let composite = Composite::new(
             Widget::col(vec![
                 Widget::row(vec![
@@ -176,19 +345,38 @@ impl Throughput {
                         .build(ctx, "close", hotkey(Key::Escape))
                         .align_right(),
                 ]),
-                Text::from(Line("This counts all people crossing since midnight").secondary())
-                    .wrap_to_pct(ctx, 15)
-                    .draw(ctx),
-                if app.has_prebaked().is_some() {
+                Text::from(
+                    Line(if trucks {
+                        format!(
+                            "This counts box trucks and tractor-trailers entering each road {}",
+                            since
+                        )
+                    } else {
+                        format!("This counts all people crossing {}", since)
+                    })
+                    .secondary(),
+                )
+                .wrap_to_pct(ctx, 15)
+                .draw(ctx),
+                Widget::row(vec![
+                    "Count".draw_text(ctx).centered_vert().margin_right(5),
+                    Widget::dropdown(ctx, "window", window, window_choices()),
+                ])
+                .margin_below(5),
+                if app.has_prebaked().is_some() && !trucks {
                     Checkbox::text(ctx, "Compare before edits", None, false).margin_below(5)
                 } else {
                     Widget::nothing()
                 },
+                Checkbox::text(ctx, "only trucks", None, trucks).margin_below(5),
                 ColorLegend::gradient(
                     ctx,
                     &app.cs.good_to_bad_red,
                     vec!["lowest count", "highest"],
                 ),
+                Btn::text_fg("export road volumes")
+                    .build_def(ctx, None)
+                    .margin_above(5),
             ])
             .padding(5)
             .bg(app.cs.panel_bg),
@@ -198,39 +386,61 @@ impl Throughput {
 
         let mut colorer = ColorNetwork::new(app);
         let stats = &app.primary.sim.get_analytics();
-        colorer.ranked_roads(
-            stats.road_thruput.all_total_counts(),
-            &app.cs.good_to_bad_red,
-        );
-        colorer.ranked_intersections(
-            stats.intersection_thruput.all_total_counts(),
-            &app.cs.good_to_bad_red,
-        );
+        let now = app.primary.sim.time();
+        if trucks {
+            colorer.ranked_roads(
+                match window {
+                    Some(hours) => stats.truck_road_thruput.recent_counts(now, hours),
+                    None => stats.truck_road_thruput.all_total_counts(),
+                },
+                &app.cs.good_to_bad_red,
+            );
+        } else {
+            colorer.ranked_roads(
+                match window {
+                    Some(hours) => stats.road_thruput.recent_counts(now, hours),
+                    None => stats.road_thruput.all_total_counts(),
+                },
+                &app.cs.good_to_bad_red,
+            );
+            colorer.ranked_intersections(
+                match window {
+                    Some(hours) => stats.intersection_thruput.recent_counts(now, hours),
+                    None => stats.intersection_thruput.all_total_counts(),
+                },
+                &app.cs.good_to_bad_red,
+            );
+        }
         let (unzoomed, zoomed) = colorer.build(ctx);
 
         Throughput {
             time: app.primary.sim.time(),
             compare: false,
+            trucks,
+            window,
             unzoomed,
             zoomed,
             composite,
         }
     }
 
-    fn compare_throughput(ctx: &mut EventCtx, app: &App) -> Throughput {
+    fn compare_throughput(ctx: &mut EventCtx, app: &App, window: Option<usize>) -> Throughput {
         let after = app.primary.sim.get_analytics();
         let before = app.prebaked();
         let hour = app.primary.sim.time().get_hours();
+        let first_hour = window.map(|h| (hour + 1).saturating_sub(h)).unwrap_or(0);
 
         let mut after_road = Counter::new();
         let mut before_road = Counter::new();
         {
-            for ((r, _, _), count) in &after.road_thruput.counts {
-                after_road.add(*r, *count);
+            for ((r, _, hr), count) in &after.road_thruput.counts {
+                if *hr >= first_hour {
+                    after_road.add(*r, *count);
+                }
             }
             // TODO ew. lerp?
             for ((r, _, hr), count) in &before.road_thruput.counts {
-                if *hr <= hour {
+                if *hr >= first_hour && *hr <= hour {
                     before_road.add(*r, *count);
                 }
             }
@@ -238,12 +448,14 @@ impl Throughput {
         let mut after_intersection = Counter::new();
         let mut before_intersection = Counter::new();
         {
-            for ((i, _, _), count) in &after.intersection_thruput.counts {
-                after_intersection.add(*i, *count);
+            for ((i, _, hr), count) in &after.intersection_thruput.counts {
+                if *hr >= first_hour {
+                    after_intersection.add(*i, *count);
+                }
             }
             // TODO ew. lerp?
             for ((i, _, hr), count) in &before.intersection_thruput.counts {
-                if *hr <= hour {
+                if *hr >= first_hour && *hr <= hour {
                     before_intersection.add(*i, *count);
                 }
             }
@@ -277,6 +489,11 @@ impl Throughput {
                         .align_right(),
                 ]),
                 Checkbox::text(ctx, "Compare before edits", None, true).margin_below(5),
+                Widget::row(vec![
+                    "Count".draw_text(ctx).centered_vert().margin_right(5),
+                    Widget::dropdown(ctx, "window", window, window_choices()),
+                ])
+                .margin_below(5),
                 scale.make_legend(ctx, vec!["less traffic", "same", "more"]),
             ])
             .padding(5)
@@ -289,6 +506,8 @@ impl Throughput {
         Throughput {
             time: app.primary.sim.time(),
             compare: true,
+            trucks: false,
+            window,
             unzoomed,
             zoomed,
             composite,
@@ -339,6 +558,9 @@ impl Layer for Delay {
     }
     fn draw(&self, g: &mut GfxCtx, app: &App) {
         self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
         if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             g.redraw(&self.unzoomed);
         } else {
@@ -472,6 +694,234 @@ impl Delay {
     }
 }
 
+// Only ranks crossings with at least this many people waiting, so one unlucky pedestrian doesn't
+// top the list
+const MIN_PEDESTRIANS_WAITING: usize = 5;
+
+// How long people on foot wait to cross at each intersection, averaged over the day so far. This
+// complements the delay layer, which is dominated by vehicles.
+pub struct PedestrianDelay {
+    time: Time,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    composite: Composite,
+}
+
+impl Layer for PedestrianDelay {
+    fn name(&self) -> Option<&'static str> {
+        Some("pedestrian delay")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            *self = PedestrianDelay::new(ctx, app);
+        }
+
+        Layer::simple_event(ctx, minimap, &mut self.composite)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl PedestrianDelay {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> PedestrianDelay {
+        let map = &app.primary.map;
+        let mut delays: Vec<(IntersectionID, Duration, usize)> = app
+            .primary
+            .sim
+            .get_analytics()
+            .average_delays(TripMode::Walk, app.primary.sim.time())
+            .into_iter()
+            .filter(|(_, _, cnt)| *cnt >= MIN_PEDESTRIANS_WAITING)
+            .collect();
+        delays.sort_by_key(|(_, dt, _)| *dt);
+        delays.reverse();
+
+        let mut colorer = ColorNetwork::new(app);
+        for (i, dt, _) in &delays {
+            colorer.add_i(
+                *i,
+                app.cs
+                    .good_to_bad_red
+                    .eval((*dt / Duration::minutes(2)).min(1.0)),
+            );
+        }
+        let (unzoomed, zoomed) = colorer.build(ctx);
+
+        let mut txt = Text::from(
+            Line(format!(
+                "Average wait to cross, at intersections where at least {} people have crossed",
+                MIN_PEDESTRIANS_WAITING
+            ))
+            .secondary(),
+        );
+        if delays.is_empty() {
+            txt.add(Line("Nobody has waited to cross yet"));
+        } else {
+            txt.add(Line("Longest waits:"));
+            for (i, dt, cnt) in delays.iter().take(5) {
+                txt.add(
+                    Line(format!(
+                        "{}: {} ({} people)",
+                        map.get_i(*i).name(map),
+                        dt,
+                        prettyprint_usize(*cnt)
+                    ))
+                    .small(),
+                );
+            }
+        }
+
+        let composite = Composite::new(
+            Widget::col(vec![
+                Widget::row(vec![
+                    Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg")
+                        .margin_right(10),
+                    "Pedestrian delay".draw_text(ctx),
+                    Btn::plaintext("X")
+                        .build(ctx, "close", hotkey(Key::Escape))
+                        .align_right(),
+                ]),
+                txt.wrap_to_pct(ctx, 20).draw(ctx).margin_below(5),
+                ColorLegend::gradient(
+                    ctx,
+                    &app.cs.good_to_bad_red,
+                    vec!["0s", "30s", "1m", "1m30s", "2m+"],
+                ),
+            ])
+            .padding(5)
+            .bg(app.cs.panel_bg),
+        )
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        PedestrianDelay {
+            time: app.primary.sim.time(),
+            unzoomed,
+            zoomed,
+            composite,
+        }
+    }
+}
+
+// Above this fraction of free-flow speed, traffic is moving freely...
+const CONGESTION_FREE: f64 = 0.75;
+// ... and below this, it's heavily congested.
+const CONGESTION_HEAVY: f64 = 0.4;
+
+pub struct Congestion {
+    time: Time,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    composite: Composite,
+}
+
+impl Layer for Congestion {
+    fn name(&self) -> Option<&'static str> {
+        Some("congestion")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            *self = Congestion::new(ctx, app);
+        }
+
+        Layer::simple_event(ctx, minimap, &mut self.composite)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl Congestion {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Congestion {
+        let green = Color::hex("#5D9630");
+        let yellow = Color::hex("#F2C94C");
+        let red = Color::hex("#A32015");
+
+        let mut colorer = ColorNetwork::new(app);
+        let (mut free, mut slow, mut heavy) = (0, 0, 0);
+        for (r, ratio) in app.primary.sim.road_speed_ratios(&app.primary.map) {
+            let color = if ratio >= CONGESTION_FREE {
+                free += 1;
+                green
+            } else if ratio >= CONGESTION_HEAVY {
+                slow += 1;
+                yellow
+            } else {
+                heavy += 1;
+                red
+            };
+            colorer.add_r(r, color);
+        }
+
+        let composite = Composite::new(
+            Widget::col(vec![
+                Widget::row(vec![
+                    Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg")
+                        .margin_right(10),
+                    "Congestion".draw_text(ctx),
+                    Btn::plaintext("X")
+                        .build(ctx, "close", hotkey(Key::Escape))
+                        .align_right(),
+                ]),
+                Text::from(
+                    Line("Average speed of vehicles along each road, compared to the speed limit")
+                        .secondary(),
+                )
+                .wrap_to_pct(ctx, 15)
+                .draw(ctx)
+                .margin_below(5),
+                ColorLegend::row(ctx, green, format!("moving freely ({} roads)", free)),
+                ColorLegend::row(ctx, yellow, format!("slow ({} roads)", slow)),
+                ColorLegend::row(ctx, red, format!("heavy congestion ({} roads)", heavy)),
+            ])
+            .padding(5)
+            .bg(app.cs.panel_bg),
+        )
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        Congestion {
+            time: app.primary.sim.time(),
+            unzoomed: ctx.upload(colorer.unzoomed),
+            zoomed: ctx.upload(colorer.zoomed),
+            composite,
+        }
+    }
+}
+
 pub struct TrafficJams {
     time: Time,
     unzoomed: Drawable,
@@ -497,6 +947,9 @@ impl Layer for TrafficJams {
     }
     fn draw(&self, g: &mut GfxCtx, app: &App) {
         self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, app: &App) {
         if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             g.redraw(&self.unzoomed);
         } else {
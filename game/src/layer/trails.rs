@@ -0,0 +1,203 @@
+use crate::app::App;
+use crate::helpers::color_for_mode;
+use crate::layer::{Layer, LayerOutcome};
+use ezgui::{
+    hotkey, Btn, Checkbox, Composite, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment,
+    Key, Line, Outcome, Spinner, Text, TextExt, VerticalAlignment, Widget,
+};
+use geom::{Distance, Duration, Pt2D, Time};
+use sim::{AgentID, TripMode, VehicleType};
+use std::collections::{BTreeMap, VecDeque};
+
+// Fading lines behind moving agents, tracing where they've been recently. Good for seeing how
+// traffic flows and weaves through complicated intersections.
+pub struct Trails {
+    time: Time,
+    opts: Options,
+    // Oldest first
+    history: BTreeMap<AgentID, VecDeque<(Time, Pt2D)>>,
+    // The last agent hovered on, if only following one
+    selected: Option<AgentID>,
+    draw: Drawable,
+    composite: Composite,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Options {
+    pub seconds: usize,
+    pub only_selected: bool,
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            seconds: 30,
+            only_selected: false,
+        }
+    }
+}
+
+impl Layer for Trails {
+    fn name(&self) -> Option<&'static str> {
+        Some("agent trails")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Composite,
+    ) -> Option<LayerOutcome> {
+        if let Some(a) = app
+            .primary
+            .current_selection
+            .as_ref()
+            .and_then(|id| id.agent_id())
+        {
+            self.selected = Some(a);
+        }
+        if app.primary.sim.time() != self.time {
+            self.record(ctx, app);
+        }
+
+        self.composite.align_above(ctx, minimap);
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            },
+            None => {
+                let new_opts = Options {
+                    seconds: self.composite.spinner("seconds"),
+                    only_selected: self.composite.is_checked("only the hovered agent"),
+                };
+                if self.opts != new_opts {
+                    self.opts = new_opts;
+                    self.redraw(ctx, app);
+                }
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.composite.draw(g);
+        self.draw_contents(g, app);
+    }
+    fn draw_contents(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.draw);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw);
+    }
+}
+
+impl Trails {
+    pub fn new(ctx: &mut EventCtx, app: &App, opts: Options) -> Trails {
+        let mut trails = Trails {
+            time: app.primary.sim.time(),
+            history: BTreeMap::new(),
+            selected: None,
+            draw: ctx.upload(GeomBatch::new()),
+            composite: make_controls(ctx, app, &opts),
+            opts,
+        };
+        trails.record(ctx, app);
+        trails
+    }
+
+    // Sample where everybody is now, and forget anything too old
+    fn record(&mut self, ctx: &mut EventCtx, app: &App) {
+        let now = app.primary.sim.time();
+        // Time went backwards, probably because the sim was reset
+        if now < self.time {
+            self.history.clear();
+        }
+        self.time = now;
+
+        let agents = if self.opts.only_selected {
+            self.selected.into_iter().collect()
+        } else {
+            app.primary.sim.active_agents()
+        };
+        for a in agents {
+            if let Some(pt) = app.primary.sim.canonical_pt_for_agent(a, &app.primary.map) {
+                self.history
+                    .entry(a)
+                    .or_insert_with(VecDeque::new)
+                    .push_back((now, pt));
+            }
+        }
+        self.redraw(ctx, app);
+    }
+
+    fn redraw(&mut self, ctx: &mut EventCtx, app: &App) {
+        let window = Duration::seconds(self.opts.seconds as f64);
+        let now = self.time;
+        for pts in self.history.values_mut() {
+            while pts.front().map(|(t, _)| now - *t > window).unwrap_or(false) {
+                pts.pop_front();
+            }
+        }
+        self.history.retain(|_, pts| !pts.is_empty());
+
+        let mut batch = GeomBatch::new();
+        for (a, pts) in &self.history {
+            if self.opts.only_selected && Some(*a) != self.selected {
+                continue;
+            }
+            let color = color_for_mode(app, mode(*a));
+            for ((_, pt1), (t2, pt2)) in pts.iter().zip(pts.iter().skip(1)) {
+                if let Some(line) = geom::Line::maybe_new(*pt1, *pt2) {
+                    // Newer pieces are more solid
+                    let age = (now - *t2) / window;
+                    batch.push(
+                        color.alpha((0.9 * (1.0 - age)) as f32),
+                        line.make_polygons(Distance::meters(1.5)),
+                    );
+                }
+            }
+        }
+        self.draw = ctx.upload(batch);
+    }
+}
+
+fn mode(a: AgentID) -> TripMode {
+    match a {
+        AgentID::Pedestrian(_) => TripMode::Walk,
+        AgentID::Car(c) => match c.1 {
+            VehicleType::Car => TripMode::Drive,
+            VehicleType::Bike => TripMode::Bike,
+            VehicleType::Bus => TripMode::Transit,
+        },
+        AgentID::BusPassenger(_, _) => TripMode::Transit,
+    }
+}
+
+fn make_controls(ctx: &mut EventCtx, app: &App, opts: &Options) -> Composite {
+    Composite::new(
+        Widget::col(vec![
+            Widget::row(vec![
+                Widget::draw_svg(ctx, "../data/system/assets/tools/layers.svg").margin_right(10),
+                "Agent trails".draw_text(ctx),
+                Btn::plaintext("X")
+                    .build(ctx, "close", hotkey(Key::Escape))
+                    .align_right(),
+            ]),
+            Text::from(Line("Where agents have been recently, fading with age").secondary())
+                .wrap_to_pct(ctx, 15)
+                .draw(ctx)
+                .margin_below(5),
+            Widget::row(vec![
+                "Seconds of history".draw_text(ctx).margin_right(5),
+                Spinner::new(ctx, (5, 300), opts.seconds).named("seconds"),
+            ])
+            .margin_below(5),
+            Checkbox::text(ctx, "only the hovered agent", None, opts.only_selected),
+        ])
+        .padding(5)
+        .bg(app.cs.panel_bg),
+    )
+    .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+    .build(ctx)
+}
@@ -32,6 +32,7 @@ fn main() {
         sim_flags: SimFlags::from_args(&mut args),
         draw_lane_markings: !args.enabled("--dont_draw_lane_markings"),
         num_agents: args.optional_parse("--num_agents", |s| s.parse()),
+        underlay: args.optional("--underlay"),
     };
     let mut opts = options::Options::default();
     opts.dev = args.enabled("--dev");
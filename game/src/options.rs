@@ -2,7 +2,8 @@ use crate::app::App;
 use crate::colors::ColorSchemeChoice;
 use crate::game::{State, Transition};
 use ezgui::{
-    hotkey, Btn, Checkbox, Choice, Composite, EventCtx, GfxCtx, Key, Line, Outcome, TextExt, Widget,
+    hotkey, Btn, Checkbox, Choice, Composite, EventCtx, GfxCtx, Key, Line, Outcome, Spinner,
+    TextExt, Widget,
 };
 use geom::Duration;
 
@@ -12,10 +13,24 @@ pub struct Options {
     pub dev: bool,
 
     pub label_roads: bool,
+    // Names of buildings and businesses, once zoomed in
+    pub label_places: bool,
+    // Only if an underlay was loaded with --underlay
+    pub show_underlay: bool,
+    // Once zoomed in, draw buildings as boxes, as tall as OSM says
+    pub extrude_buildings: bool,
     pub traffic_signal_style: TrafficSignalStyle,
     pub color_scheme: ColorSchemeChoice,
     pub min_zoom_for_detail: f64,
     pub large_unzoomed_agents: bool,
+    // Below this zoom, draw a dot map aggregating agents per road instead of every agent
+    pub max_zoom_for_dot_map: f64,
+    // Keep unzoomed agents a few pixels wide, and merge crowded spots into heat blobs
+    pub merge_agent_clusters: bool,
+    // After dusk, darken the map and draw headlights and lit signal heads
+    pub night_mode: bool,
+    // Which day (1 to 365) to figure out sunrise and sunset for
+    pub day_of_year: usize,
 
     pub time_increment: Duration,
     pub resume_after_edit: bool,
@@ -27,10 +42,18 @@ impl Options {
             dev: false,
 
             label_roads: true,
+            label_places: true,
+            show_underlay: true,
+            extrude_buildings: false,
             traffic_signal_style: TrafficSignalStyle::BAP,
             color_scheme: ColorSchemeChoice::Standard,
             min_zoom_for_detail: 4.0,
             large_unzoomed_agents: false,
+            max_zoom_for_dot_map: 1.0,
+            merge_agent_clusters: true,
+            night_mode: false,
+            // The spring equinox
+            day_of_year: 80,
 
             time_increment: Duration::minutes(10),
             resume_after_edit: true,
@@ -103,6 +126,17 @@ impl OptionsPanel {
                     Widget::col(vec![
                         Checkbox::text(ctx, "Draw road names", None, app.opts.label_roads)
                             .margin_below(10),
+                        Checkbox::text(ctx, "Draw names of places", None, app.opts.label_places)
+                            .margin_below(10),
+                        Checkbox::text(ctx, "Draw the underlay", None, app.opts.show_underlay)
+                            .margin_below(10),
+                        Checkbox::text(
+                            ctx,
+                            "Draw buildings in 3D",
+                            None,
+                            app.opts.extrude_buildings,
+                        )
+                        .margin_below(10),
                         Widget::row(vec![
                             "Traffic signal rendering:".draw_text(ctx).margin_right(15),
                             Widget::dropdown(
@@ -185,12 +219,45 @@ impl OptionsPanel {
                             ),
                         ])
                         .margin_below(10),
+                        Widget::row(vec![
+                            "Camera zoom to aggregate agents per road"
+                                .draw_text(ctx)
+                                .margin_right(15),
+                            Widget::dropdown(
+                                ctx,
+                                "dot map zoom",
+                                app.opts.max_zoom_for_dot_map,
+                                vec![
+                                    Choice::new("never", 0.0),
+                                    Choice::new("0.5", 0.5),
+                                    Choice::new("1.0", 1.0),
+                                    Choice::new("2.0", 2.0),
+                                ],
+                            ),
+                        ])
+                        .margin_below(10),
                         Checkbox::text(
                             ctx,
                             "Draw enlarged unzoomed agents",
                             None,
                             app.opts.large_unzoomed_agents,
-                        ),
+                        )
+                        .margin_below(10),
+                        Checkbox::text(
+                            ctx,
+                            "Merge crowds of unzoomed agents into heat blobs",
+                            None,
+                            app.opts.merge_agent_clusters,
+                        )
+                        .margin_below(10),
+                        Checkbox::text(ctx, "Darken the map at night", None, app.opts.night_mode)
+                            .margin_below(10),
+                        Widget::row(vec![
+                            "Day of the year, for sunrise and sunset:"
+                                .draw_text(ctx)
+                                .margin_right(15),
+                            Spinner::new(ctx, (1, 365), app.opts.day_of_year).named("day of year"),
+                        ]),
                     ])
                     .bg(app.cs.section_bg)
                     .padding(8)
@@ -229,6 +296,9 @@ impl State for OptionsPanel {
                     ctx.canvas.edge_auto_panning = self.composite.is_checked("autopan");
 
                     app.opts.label_roads = self.composite.is_checked("Draw road names");
+                    app.opts.label_places = self.composite.is_checked("Draw names of places");
+                    app.opts.show_underlay = self.composite.is_checked("Draw the underlay");
+                    app.opts.extrude_buildings = self.composite.is_checked("Draw buildings in 3D");
                     let style = self.composite.dropdown_value("Traffic signal rendering");
                     if app.opts.traffic_signal_style != style {
                         app.opts.traffic_signal_style = style;
@@ -250,8 +320,14 @@ impl State for OptionsPanel {
                     }
 
                     app.opts.min_zoom_for_detail = self.composite.dropdown_value("min zoom");
+                    app.opts.max_zoom_for_dot_map = self.composite.dropdown_value("dot map zoom");
                     app.opts.large_unzoomed_agents =
                         self.composite.is_checked("Draw enlarged unzoomed agents");
+                    app.opts.merge_agent_clusters = self
+                        .composite
+                        .is_checked("Merge crowds of unzoomed agents into heat blobs");
+                    app.opts.night_mode = self.composite.is_checked("Darken the map at night");
+                    app.opts.day_of_year = self.composite.spinner("day of year");
 
                     return Transition::Pop;
                 }
@@ -20,7 +20,7 @@ impl DrawArea {
         match area_type {
             AreaType::Park => cs.grass,
             AreaType::Water => cs.water,
-            AreaType::PedestrianIsland => Color::grey(0.3),
+            AreaType::PedestrianIsland => cs.pedestrian_island,
             AreaType::Island => cs.map_background,
         }
     }
@@ -1,8 +1,8 @@
 use crate::app::App;
 use crate::colors::ColorScheme;
 use crate::helpers::ID;
-use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
-use ezgui::{Color, Drawable, GeomBatch, GfxCtx, Line, Prerender, Text};
+use crate::render::{DrawOptions, MapIcons, Renderable, OUTLINE_THICKNESS};
+use ezgui::{Drawable, GeomBatch, GfxCtx, Line, Text};
 use geom::{Distance, Line, Polygon, Pt2D};
 use map_model::{Building, BuildingID, Map, NORMAL_LANE_THICKNESS, SIDEWALK_THICKNESS};
 use std::cell::RefCell;
@@ -13,14 +13,23 @@ pub struct DrawBuilding {
 }
 
 impl DrawBuilding {
-    pub fn new(
+    pub fn new(bldg: &Building) -> DrawBuilding {
+        DrawBuilding {
+            id: bldg.id,
+            label: RefCell::new(None),
+        }
+    }
+
+    // Buildings are only drawn as part of the whole-map batches. This doesn't need the GPU, so it
+    // can happen on a background thread.
+    pub fn add_to_batches(
         bldg: &Building,
         cs: &ColorScheme,
         bldg_batch: &mut GeomBatch,
         paths_batch: &mut GeomBatch,
         outlines_batch: &mut GeomBatch,
-        prerender: &Prerender,
-    ) -> DrawBuilding {
+        icons: &MapIcons,
+    ) {
         // Trim the front path line away from the sidewalk's center line, so that it doesn't
         // overlap. For now, this cleanup is visual; it doesn't belong in the map_model layer.
         let mut front_path_line = bldg.front_path.line.clone();
@@ -33,7 +42,7 @@ impl DrawBuilding {
             );
         }
 
-        bldg_batch.push(cs.building, bldg.polygon.clone());
+        bldg_batch.append(DrawBuilding::base(bldg, cs, icons));
         paths_batch.push(
             cs.sidewalk,
             front_path_line.make_polygons(NORMAL_LANE_THICKNESS),
@@ -41,7 +50,12 @@ impl DrawBuilding {
         if let Some(p) = bldg.polygon.maybe_to_outline(Distance::meters(0.1)) {
             outlines_batch.push(cs.building_outline, p);
         }
+    }
 
+    // The building itself, without the front path or outline
+    pub fn base(bldg: &Building, cs: &ColorScheme, icons: &MapIcons) -> GeomBatch {
+        let mut batch = GeomBatch::new();
+        batch.push(cs.building, bldg.polygon.clone());
         if bldg
             .parking
             .as_ref()
@@ -49,20 +63,49 @@ impl DrawBuilding {
             .unwrap_or(false)
         {
             // Might need to scale down more for some buildings, but so far, this works everywhere.
-            bldg_batch.append(
-                GeomBatch::mapspace_svg(prerender, "../data/system/assets/map/parking.svg")
+            batch.append(
+                icons
+                    .parking
+                    .clone()
                     .scale(0.1)
                     .centered_on(bldg.label_center),
             );
         }
+        batch
+    }
 
-        DrawBuilding {
-            id: bldg.id,
-            label: RefCell::new(None),
+    // A cheap oblique projection: the roof is the footprint shifted up and over in proportion to
+    // the height, joined to the footprint by walls. Buildings need to be added to the batch from
+    // the back of the screen (smallest y) to the front.
+    pub fn extrude(bldg: &Building, cs: &ColorScheme, batch: &mut GeomBatch) {
+        let height = bldg.height.unwrap_or(DEFAULT_HEIGHT).inner_meters();
+        let (dx, dy) = (EXTRUDE_X * height, -EXTRUDE_Y * height);
+
+        let pts = bldg.polygon.points();
+        for (pt1, pt2) in pts.iter().zip(pts.iter().skip(1).chain(pts.first())) {
+            batch.push(
+                cs.building_outline,
+                Polygon::precomputed(
+                    vec![*pt1, *pt2, pt2.offset(dx, dy), pt1.offset(dx, dy)],
+                    vec![0, 1, 2, 0, 2, 3],
+                ),
+            );
+        }
+        let roof = bldg.polygon.translate(dx, dy);
+        let outline = roof.maybe_to_outline(Distance::meters(0.1));
+        batch.push(cs.building, roof);
+        if let Some(p) = outline {
+            batch.push(cs.building_outline, p);
         }
     }
 }
 
+// Buildings without height tags are probably a story or two
+const DEFAULT_HEIGHT: Distance = Distance::const_meters(4.0);
+// How far the roof shifts per meter of height
+const EXTRUDE_X: f64 = 0.2;
+const EXTRUDE_Y: f64 = 0.4;
+
 impl Renderable for DrawBuilding {
     fn get_id(&self) -> ID {
         ID::Building(self.id)
@@ -77,9 +120,11 @@ impl Renderable for DrawBuilding {
                 let mut batch = GeomBatch::new();
                 let b = app.primary.map.get_b(self.id);
                 if let Some((name, _)) = b.amenities.iter().next() {
-                    let mut txt = Text::from(Line(name).fg(Color::BLACK));
+                    let mut txt = Text::from(Line(name).fg(app.cs.map_label));
                     if b.amenities.len() > 1 {
-                        txt.append(Line(format!(" (+{})", b.amenities.len() - 1)).fg(Color::BLACK));
+                        txt.append(
+                            Line(format!(" (+{})", b.amenities.len() - 1)).fg(app.cs.map_label),
+                        );
                     }
                     batch.append(
                         txt.render_to_batch(g.prerender)
@@ -1,13 +1,33 @@
 use crate::app::App;
 use crate::colors::ColorScheme;
 use crate::helpers::ID;
-use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
+use crate::render::{DrawOptions, MapIcons, Renderable, OUTLINE_THICKNESS};
 use ezgui::{Drawable, GeomBatch, GfxCtx, Prerender};
 use geom::{Angle, Circle, Distance, Line, Polygon, Pt2D};
 use map_model::{BusStop, BusStopID, Map};
 
 const RADIUS: Distance = Distance::const_meters(1.0);
 
+// Split into two phases like this, because AlmostDrawBusStop can be created on a background
+// thread, but GPU upload has to happen on the main one.
+pub struct AlmostDrawBusStop {
+    pub id: BusStopID,
+    center: Pt2D,
+    zorder: isize,
+    draw_default: GeomBatch,
+}
+
+impl AlmostDrawBusStop {
+    pub fn finish(self, prerender: &Prerender) -> DrawBusStop {
+        DrawBusStop {
+            id: self.id,
+            center: self.center,
+            zorder: self.zorder,
+            draw_default: prerender.upload(self.draw_default),
+        }
+    }
+}
+
 pub struct DrawBusStop {
     pub id: BusStopID,
     center: Pt2D,
@@ -17,7 +37,7 @@ pub struct DrawBusStop {
 }
 
 impl DrawBusStop {
-    pub fn new(stop: &BusStop, map: &Map, cs: &ColorScheme, prerender: &Prerender) -> DrawBusStop {
+    pub fn new(stop: &BusStop, map: &Map, cs: &ColorScheme, icons: &MapIcons) -> AlmostDrawBusStop {
         let (pt, angle) = stop.sidewalk_pos.pt_and_angle(map);
         let center = pt.project_away(
             map.get_l(stop.sidewalk_pos.lane()).width / 2.0,
@@ -25,11 +45,7 @@ impl DrawBusStop {
         );
 
         let mut icon = GeomBatch::new();
-        icon.append(
-            GeomBatch::mapspace_svg(prerender, "../data/system/assets/meters/bus.svg")
-                .scale(0.05)
-                .centered_on(center),
-        );
+        icon.append(icons.bus.clone().scale(0.05).centered_on(center));
         let mut batch = GeomBatch::new();
         batch.push(
             cs.bus_layer.alpha(0.8),
@@ -45,11 +61,11 @@ impl DrawBusStop {
             .make_polygons(Distance::meters(0.3)),
         );
 
-        DrawBusStop {
+        AlmostDrawBusStop {
             id: stop.id,
             center,
             zorder: map.get_parent(stop.sidewalk_pos.lane()).zorder,
-            draw_default: prerender.upload(batch),
+            draw_default: batch,
         }
     }
 }
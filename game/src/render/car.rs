@@ -111,7 +111,10 @@ impl DrawCar {
                         );
                     }
                     TurnType::Straight | TurnType::LaneChangeLeft | TurnType::LaneChangeRight => {}
-                    TurnType::Crosswalk | TurnType::SharedSidewalkCorner => unreachable!(),
+                    TurnType::Crosswalk
+                    | TurnType::SharedSidewalkCorner
+                    | TurnType::DiagonalCrosswalk
+                    | TurnType::Footbridge => unreachable!(),
                 }
 
                 // Always draw the brake light
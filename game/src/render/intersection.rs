@@ -3,7 +3,8 @@ use crate::colors::ColorScheme;
 use crate::helpers::ID;
 use crate::options::TrafficSignalStyle;
 use crate::render::{
-    draw_signal_phase, DrawOptions, Renderable, CROSSWALK_LINE_THICKNESS, OUTLINE_THICKNESS,
+    draw_signal_heads, draw_signal_phase, DrawOptions, MapIcons, Renderable,
+    CROSSWALK_LINE_THICKNESS, OUTLINE_THICKNESS,
 };
 use abstutil::Timer;
 use ezgui::{Color, Drawable, GeomBatch, GfxCtx, Line, Prerender, RewriteColor, Text};
@@ -14,6 +15,27 @@ use map_model::{
 };
 use std::cell::RefCell;
 
+// Split into two phases like this, because AlmostDrawIntersection can be created on a background
+// thread, but GPU upload has to happen on the main one.
+pub struct AlmostDrawIntersection {
+    id: IntersectionID,
+    intersection_type: IntersectionType,
+    zorder: isize,
+    draw_default: GeomBatch,
+}
+
+impl AlmostDrawIntersection {
+    pub fn finish(self, prerender: &Prerender) -> DrawIntersection {
+        DrawIntersection {
+            id: self.id,
+            intersection_type: self.intersection_type,
+            zorder: self.zorder,
+            draw_default: prerender.upload(self.draw_default),
+            draw_traffic_signal: RefCell::new(None),
+        }
+    }
+}
+
 pub struct DrawIntersection {
     pub id: IntersectionID,
     intersection_type: IntersectionType,
@@ -28,9 +50,9 @@ impl DrawIntersection {
         i: &Intersection,
         map: &Map,
         cs: &ColorScheme,
-        prerender: &Prerender,
+        icons: &MapIcons,
         timer: &mut Timer,
-    ) -> DrawIntersection {
+    ) -> AlmostDrawIntersection {
         // Order matters... main polygon first, then sidewalk corners.
         let mut default_geom = GeomBatch::new();
         default_geom.push(cs.normal_intersection, i.polygon.clone());
@@ -54,24 +76,33 @@ impl DrawIntersection {
                 );
             }
             IntersectionType::StopSign => {
-                for ss in map.get_stop_sign(i.id).roads.values() {
-                    if ss.must_stop {
+                // At an all-way stop, everybody takes turns. Where only the minor approaches stop,
+                // they have to yield to traffic on the major road, which never does.
+                let signs = &map.get_stop_sign(i.id).roads;
+                let all_way = signs.values().all(|ss| ss.must_stop);
+                for ss in signs.values().filter(|ss| ss.must_stop) {
+                    if all_way {
                         if let Some((octagon, pole)) = DrawIntersection::stop_sign_geom(ss, map) {
                             default_geom.push(cs.stop_sign, octagon);
                             default_geom.push(cs.stop_sign_pole, pole);
                         }
+                    } else if let Some((triangle, inside, pole)) =
+                        DrawIntersection::yield_sign_geom(ss, map)
+                    {
+                        default_geom.push(cs.stop_sign_pole, pole);
+                        default_geom.push(cs.stop_sign, triangle);
+                        default_geom.push(cs.yield_sign_inner, inside);
                     }
                 }
             }
             IntersectionType::Construction => {
                 // TODO Centering seems weird
                 default_geom.append(
-                    GeomBatch::mapspace_svg(
-                        prerender,
-                        "../data/system/assets/map/under_construction.svg",
-                    )
-                    .scale(0.08)
-                    .centered_on(i.polygon.center()),
+                    icons
+                        .under_construction
+                        .clone()
+                        .scale(0.08)
+                        .centered_on(i.polygon.center()),
                 );
             }
             IntersectionType::TrafficSignal => {}
@@ -82,45 +113,67 @@ impl DrawIntersection {
             default_geom = default_geom.color(RewriteColor::ChangeAlpha(0.5));
         }
 
-        DrawIntersection {
+        AlmostDrawIntersection {
             id: i.id,
             intersection_type: i.intersection_type,
             zorder,
-            draw_default: prerender.upload(default_geom),
-            draw_traffic_signal: RefCell::new(None),
+            draw_default: default_geom,
         }
     }
 
     // Returns the (octagon, pole) if there's room to draw it.
     pub fn stop_sign_geom(ss: &RoadWithStopSign, map: &Map) -> Option<(Polygon, Polygon)> {
-        let trim_back = Distance::meters(0.1);
-        let rightmost = map.get_l(ss.rightmost_lane);
-        // TODO The dream of trimming f64's was to isolate epsilon checks like this...
-        if rightmost.length() - trim_back <= EPSILON_DIST {
-            // TODO warn
-            return None;
-        }
-        let last_line = map.right_shift_line(
+        let last_line = sign_position(ss, map)?;
+        let octagon = make_octagon(last_line.pt2(), Distance::meters(1.0), last_line.angle());
+        Some((octagon, make_sign_pole(&last_line)))
+    }
+
+    // Returns the (triangle, the white inside of it, pole) if there's room to draw it. The triangle
+    // points back at approaching drivers, like the yield markings painted on the road.
+    pub fn yield_sign_geom(
+        ss: &RoadWithStopSign,
+        map: &Map,
+    ) -> Option<(Polygon, Polygon, Polygon)> {
+        let last_line = sign_position(ss, map)?;
+        let facing = last_line.angle().opposite();
+        let triangle = make_triangle(last_line.pt2(), Distance::meters(1.2), facing);
+        let inside = make_triangle(last_line.pt2(), Distance::meters(0.7), facing);
+        Some((triangle, inside, make_sign_pole(&last_line)))
+    }
+}
+
+// Signs go on the right side of the rightmost lane, just before the intersection. Returns the last
+// line of the lane shifted over there, or None if there's no room.
+fn sign_position(ss: &RoadWithStopSign, map: &Map) -> Option<Line> {
+    let trim_back = Distance::meters(0.1);
+    let rightmost = map.get_l(ss.rightmost_lane);
+    // TODO The dream of trimming f64's was to isolate epsilon checks like this...
+    if rightmost.length() - trim_back <= EPSILON_DIST {
+        // TODO warn
+        return None;
+    }
+    Some(
+        map.right_shift_line(
             rightmost
                 .lane_center_pts
                 .exact_slice(Distance::ZERO, rightmost.length() - trim_back)
                 .last_line(),
             rightmost.width,
-        );
+        ),
+    )
+}
 
-        let octagon = make_octagon(last_line.pt2(), Distance::meters(1.0), last_line.angle());
-        let pole = Line::new(
-            last_line
-                .pt2()
-                .project_away(Distance::meters(1.5), last_line.angle().opposite()),
-            // TODO Slightly < 0.9
-            last_line
-                .pt2()
-                .project_away(Distance::meters(0.9), last_line.angle().opposite()),
-        )
-        .make_polygons(Distance::meters(0.3));
-        Some((octagon, pole))
-    }
+fn make_sign_pole(last_line: &Line) -> Polygon {
+    Line::new(
+        last_line
+            .pt2()
+            .project_away(Distance::meters(1.5), last_line.angle().opposite()),
+        // TODO Slightly < 0.9
+        last_line
+            .pt2()
+            .project_away(Distance::meters(0.9), last_line.angle().opposite()),
+    )
+    .make_polygons(Distance::meters(0.3))
 }
 
 impl Renderable for DrawIntersection {
@@ -134,15 +187,16 @@ impl Renderable for DrawIntersection {
         if self.intersection_type == IntersectionType::TrafficSignal
             && !opts.suppress_traffic_signal_details.contains(&self.id)
         {
-            let signal = app.primary.map.get_traffic_signal(self.id);
             let mut maybe_redraw = self.draw_traffic_signal.borrow_mut();
             let recalc = maybe_redraw
                 .as_ref()
                 .map(|(t, _)| *t != app.primary.sim.time())
                 .unwrap_or(true);
             if recalc {
-                let (idx, phase, t) =
-                    signal.current_phase_and_remaining_time(app.primary.sim.time());
+                let (idx, phase, t) = app
+                    .primary
+                    .sim
+                    .current_phase_and_remaining_time(self.id, &app.primary.map);
                 let mut batch = GeomBatch::new();
                 draw_signal_phase(
                     g.prerender,
@@ -153,6 +207,7 @@ impl Renderable for DrawIntersection {
                     app,
                     app.opts.traffic_signal_style.clone(),
                 );
+                draw_signal_heads(phase, self.id, t, &mut batch, app);
                 if app.opts.traffic_signal_style != TrafficSignalStyle::BAP {
                     batch.append(
                         Text::from(Line(format!("{}", idx + 1)))
@@ -318,6 +373,15 @@ fn make_octagon(center: Pt2D, radius: Distance, facing: Angle) -> Polygon {
     )
 }
 
+// One corner points towards facing
+fn make_triangle(center: Pt2D, radius: Distance, facing: Angle) -> Polygon {
+    Polygon::new(
+        &(0..3)
+            .map(|i| center.project_away(radius, facing.rotate_degs(f64::from(i * 120))))
+            .collect(),
+    )
+}
+
 pub fn make_crosswalk(batch: &mut GeomBatch, turn: &Turn, map: &Map, cs: &ColorScheme) {
     if make_rainbow_crosswalk(batch, turn, map) {
         return;
@@ -386,6 +450,7 @@ fn make_rainbow_crosswalk(batch: &mut GeomBatch, turn: &Turn, map: &Map) -> bool
     }
 
     let total_width = map.get_l(turn.id.src).width;
+    // These match the paint on the real crosswalks, so they stay the same in every color scheme
     let colors = vec![
         Color::WHITE,
         Color::RED,
@@ -0,0 +1,269 @@
+use crate::app::App;
+use aabb_quadtree::QuadTree;
+use ezgui::{Drawable, GeomBatch, GfxCtx, Line, Prerender, Text};
+use geom::{Bounds, Distance, PolyLine, Polygon, Pt2D};
+use map_model::{BuildingID, Map, RoadID};
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+// Zoom levels within this factor of each other share the same labels
+const ZOOM_STEP: f64 = 1.25;
+// Labels are placed over this many extra screens in every direction, so small pans don't redo
+// everything
+const PAD_SCREENS: f64 = 0.5;
+// Empty space kept around every label, in pixels
+const MARGIN_PIXELS: f64 = 4.0;
+// Don't repeat the same street name closer than this many pixels
+const REPEAT_PIXELS: f64 = 400.0;
+// Skip street names that would bend sharper than this anywhere, since the letters pile up
+const MAX_BEND_DEGREES: f64 = 30.0;
+
+// Street and place names, always the same size on screen. Street names follow the curve of the
+// road. Labels never overlap, so which ones appear depends on the zoom: zoomed out, only the names
+// of major roads fit, and zooming in makes room for smaller streets, then the names of places.
+pub struct MapLabels {
+    // Most important first
+    roads: Vec<(RoadID, String)>,
+    places: Vec<(BuildingID, String)>,
+    // The zoom step, whether roads and places are shown, the area covered, and the labels
+    cache: RefCell<Option<(i32, bool, bool, Bounds, Drawable)>>,
+}
+
+impl MapLabels {
+    pub fn new(map: &Map) -> MapLabels {
+        let mut roads: Vec<(RoadID, String)> = map
+            .all_roads()
+            .iter()
+            .map(|r| (r.id, r.get_name()))
+            .filter(|(_, name)| name != "???")
+            .collect();
+        roads.sort_by_key(|(r, _)| {
+            let r = map.get_r(*r);
+            (Reverse(r.get_rank()), Reverse(r.center_pts.length()))
+        });
+
+        let mut places: Vec<(BuildingID, String)> = map
+            .all_buildings()
+            .iter()
+            .filter_map(|b| {
+                b.name
+                    .clone()
+                    .or_else(|| b.amenities.iter().next().map(|(name, _)| name.clone()))
+                    .map(|name| (b.id, name))
+            })
+            .collect();
+        // Buildings with more going on first
+        places.sort_by_key(|(b, _)| Reverse(map.get_b(*b).amenities.len()));
+
+        MapLabels {
+            roads,
+            places,
+            cache: RefCell::new(None),
+        }
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let show_roads = app.opts.label_roads;
+        let show_places =
+            app.opts.label_places && g.canvas.cam_zoom >= app.opts.min_zoom_for_detail;
+        if !show_roads && !show_places {
+            return;
+        }
+
+        let step = (g.canvas.cam_zoom.ln() / ZOOM_STEP.ln()).round() as i32;
+        let screen = g.get_screen_bounds();
+        let mut cache = self.cache.borrow_mut();
+        let stale = match *cache {
+            Some((s, r, p, ref area, _)) => {
+                s != step
+                    || r != show_roads
+                    || p != show_places
+                    || !area.contains(Pt2D::new(screen.min_x, screen.min_y))
+                    || !area.contains(Pt2D::new(screen.max_x, screen.max_y))
+            }
+            None => true,
+        };
+        if stale {
+            let mut area = screen.clone();
+            let (dx, dy) = (PAD_SCREENS * screen.width(), PAD_SCREENS * screen.height());
+            area.min_x -= dx;
+            area.min_y -= dy;
+            area.max_x += dx;
+            area.max_y += dy;
+            let batch = self.place(
+                g.prerender,
+                app,
+                ZOOM_STEP.powi(step),
+                show_roads,
+                show_places,
+                &area,
+            );
+            *cache = Some((step, show_roads, show_places, area, g.upload(batch)));
+        }
+        g.redraw(&cache.as_ref().unwrap().4);
+    }
+
+    // Greedily keeps every label that doesn't overlap a more important one
+    fn place(
+        &self,
+        prerender: &Prerender,
+        app: &App,
+        zoom: f64,
+        show_roads: bool,
+        show_places: bool,
+        area: &Bounds,
+    ) -> GeomBatch {
+        let map = &app.primary.map;
+        let mut taken: QuadTree<()> = QuadTree::default(area.as_bbox());
+        let margin = MARGIN_PIXELS / zoom;
+        let mut batch = GeomBatch::new();
+
+        if show_roads {
+            let mut labeled: HashMap<&str, Vec<Pt2D>> = HashMap::new();
+            for (r, name) in &self.roads {
+                let road = map.get_r(*r);
+                let (pt, angle) = road.center_pts.dist_along(road.center_pts.length() / 2.0);
+                if !area.contains(pt) {
+                    continue;
+                }
+                if labeled
+                    .get(name.as_str())
+                    .map(|pts| {
+                        pts.iter()
+                            .any(|p| p.dist_to(pt) < Distance::meters(REPEAT_PIXELS / zoom))
+                    })
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+                let label = Text::from(Line(name).fg(app.cs.road_center_line))
+                    .render_to_batch(prerender)
+                    .scale(1.0 / zoom);
+                let dims = label.get_dims();
+                // Names have to fit along the road
+                let width = Distance::meters(dims.width);
+                let length = road.center_pts.length();
+                if width > length {
+                    continue;
+                }
+                let start = (length - width) / 2.0;
+                let path = match road.center_pts.slice(start, start + width) {
+                    Some((pl, _)) => pl,
+                    None => {
+                        continue;
+                    }
+                };
+                // Keep the text upright
+                let theta = angle.normalized_degrees();
+                let path = if theta > 90.0 && theta <= 270.0 {
+                    path.reversed()
+                } else {
+                    path
+                };
+                if path
+                    .lines()
+                    .windows(2)
+                    .any(|pair| !pair[0].angle().approx_eq(pair[1].angle(), MAX_BEND_DEGREES))
+                {
+                    continue;
+                }
+                if reserve_along(&mut taken, &path, dims.height, margin) {
+                    batch.push(
+                        app.cs.driving_lane,
+                        path.make_polygons(Distance::meters(dims.height)),
+                    );
+                    for (color, poly) in label.consume() {
+                        batch.fancy_push(color, bend(&poly, &path, dims.height));
+                    }
+                    labeled
+                        .entry(name.as_str())
+                        .or_insert_with(Vec::new)
+                        .push(pt);
+                }
+            }
+        }
+
+        if show_places {
+            for (b, name) in &self.places {
+                let pt = map.get_b(*b).label_center;
+                if !area.contains(pt) {
+                    continue;
+                }
+                let label = Text::from(Line(name).small().fg(app.cs.map_label))
+                    .render_to_batch(prerender)
+                    .scale(1.0 / zoom);
+                let dims = label.get_dims();
+                if reserve(&mut taken, pt, dims.width, dims.height, margin) {
+                    batch.append(label.centered_on(pt));
+                }
+            }
+        }
+
+        batch
+    }
+}
+
+// Claims the space for a label, unless something's already there
+fn reserve(taken: &mut QuadTree<()>, center: Pt2D, width: f64, height: f64, margin: f64) -> bool {
+    let bbox = Polygon::rectangle_centered(
+        center,
+        Distance::meters(width + 2.0 * margin),
+        Distance::meters(height + 2.0 * margin),
+    )
+    .get_bounds()
+    .as_bbox();
+    if !taken.query(bbox).is_empty() {
+        return false;
+    }
+    taken.insert_with_box((), bbox);
+    true
+}
+
+// Like reserve, but for a label following a path. The path is claimed a little at a time, so
+// curved labels don't claim their whole bounding box.
+fn reserve_along(taken: &mut QuadTree<()>, path: &PolyLine, height: f64, margin: f64) -> bool {
+    let pad = height / 2.0 + margin;
+    let length = path.length().inner_meters();
+    let mut boxes = Vec::new();
+    let mut dist = 0.0;
+    while dist < length {
+        let end = (dist + height).min(length);
+        let mut b = Bounds::from(&vec![
+            path.dist_along(Distance::meters(dist)).0,
+            path.dist_along(Distance::meters(end)).0,
+        ]);
+        b.min_x -= pad;
+        b.min_y -= pad;
+        b.max_x += pad;
+        b.max_y += pad;
+        boxes.push(b.as_bbox());
+        dist = end;
+    }
+    if boxes.iter().any(|b| !taken.query(*b).is_empty()) {
+        return false;
+    }
+    for b in boxes {
+        taken.insert_with_box((), b);
+    }
+    true
+}
+
+// Text is laid out left to right from the origin. Moves every point the same distance along the
+// path, and the same distance to the side of it.
+fn bend(poly: &Polygon, path: &PolyLine, height: f64) -> Polygon {
+    let length = path.length().inner_meters();
+    let (pts, indices) = poly.raw_for_rendering();
+    Polygon::precomputed(
+        pts.iter()
+            .map(|pt| {
+                let (on_path, angle) =
+                    path.dist_along(Distance::meters(pt.x().max(0.0).min(length)));
+                let (sin, cos) = angle.normalized_radians().sin_cos();
+                let side = pt.y() - height / 2.0;
+                Pt2D::new(on_path.x() - side * sin, on_path.y() + side * cos)
+            })
+            .collect(),
+        indices.clone(),
+    )
+}
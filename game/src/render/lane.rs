@@ -1,14 +1,14 @@
 use crate::app::App;
 use crate::colors::ColorScheme;
 use crate::helpers::ID;
-use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
+use crate::render::{DrawOptions, MapIcons, Renderable, OUTLINE_THICKNESS};
 use abstutil::Timer;
 use ezgui::{Drawable, GeomBatch, GfxCtx, Prerender, RewriteColor};
 use geom::{Angle, ArrowCap, Distance, Line, PolyLine, Polygon, Pt2D};
 use map_model::{Lane, LaneID, LaneType, Map, Road, TurnType, PARKING_SPOT_LENGTH};
 
-// Split into two phases like this, because AlmostDrawLane can be created in parallel, but GPU
-// upload has to be serial.
+// Split into two phases like this, because AlmostDrawLane can be created in parallel and on a
+// background thread, but GPU upload has to be serial and on the main thread.
 pub struct AlmostDrawLane {
     pub id: LaneID,
     polygon: Polygon,
@@ -17,56 +17,7 @@ pub struct AlmostDrawLane {
 }
 
 impl AlmostDrawLane {
-    pub fn finish(mut self, prerender: &Prerender, _: &ColorScheme, lane: &Lane) -> DrawLane {
-        // Need prerender to load the (cached) SVGs
-        if lane.is_bus() || lane.is_biking() || lane.lane_type == LaneType::Construction {
-            let buffer = Distance::meters(2.0);
-            let btwn = Distance::meters(30.0);
-            let len = lane.lane_center_pts.length();
-
-            let mut dist = buffer;
-            while dist + buffer <= len {
-                let (pt, angle) = lane.lane_center_pts.dist_along(dist);
-                if lane.is_bus() {
-                    self.draw_default.append(
-                        GeomBatch::mapspace_svg(
-                            prerender,
-                            "../data/system/assets/map/bus_only.svg",
-                        )
-                        .scale(0.06)
-                        .centered_on(pt)
-                        .rotate(angle.shortest_rotation_towards(Angle::new_degs(-90.0))),
-                    );
-                } else if lane.is_biking() {
-                    self.draw_default.append(
-                        GeomBatch::mapspace_svg(prerender, "../data/system/assets/meters/bike.svg")
-                            .scale(0.06)
-                            .centered_on(pt)
-                            .rotate(angle.shortest_rotation_towards(Angle::new_degs(-90.0))),
-                    );
-                } else if lane.lane_type == LaneType::Construction {
-                    // TODO Still not quite centered right, but close enough
-                    self.draw_default.append(
-                        GeomBatch::mapspace_svg(
-                            prerender,
-                            "../data/system/assets/map/under_construction.svg",
-                        )
-                        .scale(0.05)
-                        .rotate_around_batch_center(
-                            angle.shortest_rotation_towards(Angle::new_degs(-90.0)),
-                        )
-                        .autocrop()
-                        .centered_on(pt),
-                    );
-                }
-                dist += btwn;
-            }
-        }
-
-        if self.zorder < 0 {
-            self.draw_default = self.draw_default.color(RewriteColor::ChangeAlpha(0.5));
-        }
-
+    pub fn finish(self, prerender: &Prerender) -> DrawLane {
         DrawLane {
             id: self.id,
             polygon: self.polygon,
@@ -90,6 +41,7 @@ impl DrawLane {
         map: &Map,
         draw_lane_markings: bool,
         cs: &ColorScheme,
+        icons: &MapIcons,
         timer: &mut Timer,
     ) -> AlmostDrawLane {
         let road = map.get_r(lane.parent);
@@ -151,6 +103,54 @@ impl DrawLane {
             };
         }
 
+        if lane.is_bus() || lane.is_biking() || lane.lane_type == LaneType::Construction {
+            let buffer = Distance::meters(2.0);
+            let btwn = Distance::meters(30.0);
+            let len = lane.lane_center_pts.length();
+
+            let mut dist = buffer;
+            while dist + buffer <= len {
+                let (pt, angle) = lane.lane_center_pts.dist_along(dist);
+                if lane.is_bus() {
+                    draw.append(
+                        icons
+                            .bus_only
+                            .clone()
+                            .scale(0.06)
+                            .centered_on(pt)
+                            .rotate(angle.shortest_rotation_towards(Angle::new_degs(-90.0))),
+                    );
+                } else if lane.is_biking() {
+                    draw.append(
+                        icons
+                            .bike
+                            .clone()
+                            .scale(0.06)
+                            .centered_on(pt)
+                            .rotate(angle.shortest_rotation_towards(Angle::new_degs(-90.0))),
+                    );
+                } else if lane.lane_type == LaneType::Construction {
+                    // TODO Still not quite centered right, but close enough
+                    draw.append(
+                        icons
+                            .under_construction
+                            .clone()
+                            .scale(0.05)
+                            .rotate_around_batch_center(
+                                angle.shortest_rotation_towards(Angle::new_degs(-90.0)),
+                            )
+                            .autocrop()
+                            .centered_on(pt),
+                    );
+                }
+                dist += btwn;
+            }
+        }
+
+        if road.zorder < 0 {
+            draw = draw.color(RewriteColor::ChangeAlpha(0.5));
+        }
+
         AlmostDrawLane {
             id: lane.id,
             polygon,
@@ -276,6 +276,22 @@ fn calculate_turn_markings(map: &Map, lane: &Lane, timer: &mut Timer) -> Vec<Pol
         return results;
     }
 
+    // One head per movement the lane feeds, no matter how many lanes it can turn into.
+    // Lane-changing turns don't count.
+    let mut heads = Vec::new();
+    for turn in map.get_turns_from_lane(lane.id) {
+        if !heads.contains(&turn.turn_type)
+            && (turn.turn_type == TurnType::Straight
+                || turn.turn_type == TurnType::Left
+                || turn.turn_type == TurnType::Right)
+        {
+            heads.push(turn.turn_type);
+        }
+    }
+    if heads.is_empty() {
+        return results;
+    }
+
     let thickness = Distance::meters(0.2);
 
     let common_base = lane.lane_center_pts.exact_slice(
@@ -284,28 +300,19 @@ fn calculate_turn_markings(map: &Map, lane: &Lane, timer: &mut Timer) -> Vec<Pol
     );
     results.push(common_base.make_polygons(thickness));
 
-    // TODO Maybe draw arrows per target road, not lane
-    for turn in map.get_turns_from_lane(lane.id) {
-        if turn.turn_type == TurnType::LaneChangeLeft || turn.turn_type == TurnType::LaneChangeRight
-        {
-            continue;
-        }
+    let (pt, angle) = (common_base.last_pt(), common_base.last_line().angle());
+    for turn_type in heads {
+        let head_angle = match turn_type {
+            TurnType::Left => angle.rotate_degs(-90.0),
+            TurnType::Right => angle.rotate_degs(90.0),
+            _ => angle,
+        };
         results.push(
-            PolyLine::new(vec![
-                common_base.last_pt(),
-                common_base
-                    .last_pt()
-                    .project_away(lane.width / 2.0, turn.angle()),
-            ])
-            .make_arrow(thickness, ArrowCap::Triangle)
-            .with_context(timer, format!("turn_markings for {}", turn.id)),
+            PolyLine::new(vec![pt, pt.project_away(lane.width / 2.0, head_angle)])
+                .make_arrow(thickness, ArrowCap::Triangle)
+                .with_context(timer, format!("turn_markings for {}", lane.id)),
         );
     }
-
-    // Just lane-changing turns after all (common base + 2 for the arrow)
-    if results.len() == 3 {
-        return Vec::new();
-    }
     results
 }
 
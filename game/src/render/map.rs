@@ -2,24 +2,38 @@ use crate::app::{App, Flags};
 use crate::colors::ColorScheme;
 use crate::helpers::ID;
 use crate::render::building::DrawBuilding;
-use crate::render::bus_stop::DrawBusStop;
-use crate::render::intersection::DrawIntersection;
-use crate::render::lane::DrawLane;
-use crate::render::parking_lot::DrawParkingLot;
-use crate::render::road::DrawRoad;
-use crate::render::{draw_vehicle, DrawArea, DrawPedCrowd, DrawPedestrian, Renderable};
+use crate::render::bus_stop::{AlmostDrawBusStop, DrawBusStop};
+use crate::render::intersection::{AlmostDrawIntersection, DrawIntersection};
+use crate::render::labels::MapLabels;
+use crate::render::lane::{AlmostDrawLane, DrawLane};
+use crate::render::parking_lot::{AlmostDrawParkingLot, DrawParkingLot};
+use crate::render::road::{AlmostDrawRoad, DrawRoad};
+use crate::render::tiles::{BaseLayer, BaseMapTiles};
+use crate::render::underlay::load_underlay;
+use crate::render::{draw_vehicle, DrawArea, DrawPedCrowd, DrawPedestrian, MapIcons, Renderable};
 use aabb_quadtree::QuadTree;
 use abstutil::Timer;
-use ezgui::{Color, Drawable, EventCtx, GeomBatch, GfxCtx, Prerender};
-use geom::{Bounds, Circle, Distance, Pt2D, Time};
+use ezgui::{Color, Drawable, EventCtx, GeomBatch, GfxCtx, Instance, Line, Prerender, Text};
+use geom::{Bounds, Circle, Distance, Duration, Pt2D, Time};
 use map_model::{
-    AreaID, BuildingID, BusStopID, Intersection, IntersectionID, LaneID, Map, ParkingLotID, Road,
-    RoadID, Traversable, NORMAL_LANE_THICKNESS, SIDEWALK_THICKNESS,
+    AreaID, BuildingID, BusStopID, Intersection, IntersectionID, LaneID, Map, ParkingLotID, RoadID,
+    Traversable, NORMAL_LANE_THICKNESS, SIDEWALK_THICKNESS,
+};
+use sim::{
+    GetDrawAgents, PersonState, Sim, TripEndpoint, TripID, TripResult, UnzoomedAgent, VehicleType,
 };
-use sim::{GetDrawAgents, UnzoomedAgent, VehicleType};
 use std::borrow::Borrow;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+// In the dot map, only label dots with at least this many agents
+const DOT_MAP_MIN_LABEL: usize = 10;
+// When merging clusters, unzoomed agents never shrink below this many pixels
+const MIN_DOT_PIXELS: f64 = 2.0;
+// Agents are grouped into square cells this many pixels wide...
+const CLUSTER_CELL_PIXELS: f64 = 30.0;
+// ... and cells with at least this many agents become one heat blob
+const CLUSTER_MIN_AGENTS: usize = 8;
 
 pub struct DrawMap {
     pub roads: Vec<DrawRoad>,
@@ -34,17 +48,51 @@ pub struct DrawMap {
     pub agents: RefCell<AgentCache>,
 
     pub boundary_polygon: Drawable,
+    pub underlay: Option<Drawable>,
     pub draw_all_thick_roads: Drawable,
     pub draw_all_unzoomed_intersections: Drawable,
     pub draw_all_buildings: Drawable,
     pub draw_all_building_paths: Drawable,
     pub draw_all_building_outlines: Drawable,
+    // Only built once somebody asks for 3D buildings
+    pub draw_all_building_extrusions: RefCell<Option<Drawable>>,
     pub draw_all_unzoomed_parking_lots: Drawable,
     pub draw_all_areas: Drawable,
+    pub icons: MapIcons,
+    // The same unzoomed layers, split up so only what's on screen is drawn
+    pub tiles: BaseMapTiles,
+    pub labels: MapLabels,
+    // The darkness step, the hour, and the dark pavement and lit windows for them
+    pub night: RefCell<Option<(usize, usize, Drawable)>>,
 
     quadtree: QuadTree<ID>,
 }
 
+// Everything for DrawMap that doesn't need the GPU, so it can be made on a background thread
+// while the map loads. Only uploading has to happen on the main thread.
+pub struct DrawMapGeometry {
+    icons: MapIcons,
+    roads: Vec<AlmostDrawRoad>,
+    lanes: Vec<AlmostDrawLane>,
+    intersections: Vec<AlmostDrawIntersection>,
+    parking_lots: Vec<AlmostDrawParkingLot>,
+    bus_stops: Vec<AlmostDrawBusStop>,
+    areas: Vec<DrawArea>,
+
+    roads_per_zorder: BTreeMap<isize, Vec<RoadID>>,
+    intersections_per_zorder: BTreeMap<isize, Vec<IntersectionID>>,
+
+    boundary_polygon: GeomBatch,
+    underlay: Option<GeomBatch>,
+    all_thick_roads: GeomBatch,
+    all_unzoomed_intersections: GeomBatch,
+    all_buildings: GeomBatch,
+    all_building_paths: GeomBatch,
+    all_building_outlines: GeomBatch,
+    all_unzoomed_parking_lots: GeomBatch,
+    all_areas: GeomBatch,
+}
+
 impl DrawMap {
     pub fn new(
         map: &Map,
@@ -53,122 +101,116 @@ impl DrawMap {
         ctx: &EventCtx,
         timer: &mut Timer,
     ) -> DrawMap {
-        let mut roads: Vec<DrawRoad> = Vec::new();
+        let geom = DrawMap::prepare(map, flags, cs, timer);
+        DrawMap::upload(geom, map, ctx, timer)
+    }
+
+    // Doesn't touch the GPU, so this can run on any thread.
+    pub fn prepare(
+        map: &Map,
+        flags: &Flags,
+        cs: &ColorScheme,
+        timer: &mut Timer,
+    ) -> DrawMapGeometry {
+        let icons = MapIcons::load();
+
+        let mut roads: Vec<AlmostDrawRoad> = Vec::new();
         timer.start_iter("make DrawRoads", map.all_roads().len());
         for r in map.all_roads() {
             timer.next();
-            roads.push(DrawRoad::new(r, map, cs, ctx.prerender));
+            roads.push(DrawRoad::new(r, map, cs));
         }
 
         timer.start("generate thick roads");
-        let mut road_refs: Vec<&Road> = map.all_roads().iter().collect();
-        road_refs.sort_by_key(|r| r.zorder);
-        let mut all_roads = GeomBatch::new();
-        for r in road_refs {
-            all_roads.push(
+        // Roads at each z-order are kept separately, for the tiles
+        let mut roads_per_zorder: BTreeMap<isize, Vec<RoadID>> = BTreeMap::new();
+        for r in map.all_roads() {
+            roads_per_zorder
+                .entry(r.zorder)
+                .or_insert_with(Vec::new)
+                .push(r.id);
+        }
+        let mut all_thick_roads = GeomBatch::new();
+        for r in roads_per_zorder.values().flatten() {
+            let r = map.get_r(*r);
+            all_thick_roads.push(
                 osm_rank_to_color(cs, r.get_rank()),
                 r.get_thick_polygon(map).get(timer),
             );
-            /*if false {
-                all_roads.push(
-                    color,
-                    roads[r.id.0].get_outline(map),
-                );
-            }*/
         }
-        let draw_all_thick_roads = all_roads.upload(ctx);
         timer.stop("generate thick roads");
 
-        let almost_lanes =
-            timer.parallelize("prepare DrawLanes", map.all_lanes().iter().collect(), |l| {
-                DrawLane::new(
-                    l,
-                    map,
-                    flags.draw_lane_markings,
-                    cs,
-                    // TODO Really parallelize should give us something thread-safe that can at
-                    // least take notes.
-                    &mut Timer::throwaway(),
-                )
-            });
-        timer.start_iter("finalize DrawLanes", almost_lanes.len());
-        let mut lanes: Vec<DrawLane> = Vec::new();
-        for almost in almost_lanes {
-            timer.next();
-            let lane = map.get_l(almost.id);
-            lanes.push(almost.finish(ctx.prerender, cs, lane));
-        }
-
-        let mut intersections: Vec<DrawIntersection> = Vec::new();
+        let lanes = timer.parallelize("prepare DrawLanes", map.all_lanes().iter().collect(), |l| {
+            DrawLane::new(
+                l,
+                map,
+                flags.draw_lane_markings,
+                cs,
+                &icons,
+                // TODO Really parallelize should give us something thread-safe that can at
+                // least take notes.
+                &mut Timer::throwaway(),
+            )
+        });
+
+        let mut intersections: Vec<AlmostDrawIntersection> = Vec::new();
         timer.start_iter("make DrawIntersections", map.all_intersections().len());
         for i in map.all_intersections() {
             timer.next();
-            intersections.push(DrawIntersection::new(i, map, cs, ctx.prerender, timer));
+            intersections.push(DrawIntersection::new(i, map, cs, &icons, timer));
         }
 
         timer.start("generate unzoomed intersections");
-        let mut intersection_refs: Vec<&Intersection> = map.all_intersections().iter().collect();
-        intersection_refs.sort_by_key(|i| i.get_zorder(map));
-        let mut all_intersections = GeomBatch::new();
-        for i in intersection_refs {
-            // TODO Would be neat to show closed intersections here, but then edits need to
-            // regenerate this
-            if i.is_stop_sign() {
-                all_intersections.push(osm_rank_to_color(cs, i.get_rank(map)), i.polygon.clone());
-            /*if false {
-                all_intersections.push(
-                    color,
-                    intersections[i.id.0].get_outline(map),
-                );
-            }*/
-            } else {
-                all_intersections.push(cs.unzoomed_interesting_intersection, i.polygon.clone());
-            }
+        // Intersections at each z-order are kept separately, for the tiles
+        let mut intersections_per_zorder: BTreeMap<isize, Vec<IntersectionID>> = BTreeMap::new();
+        for i in map.all_intersections() {
+            intersections_per_zorder
+                .entry(i.get_zorder(map))
+                .or_insert_with(Vec::new)
+                .push(i.id);
+        }
+        let mut all_unzoomed_intersections = GeomBatch::new();
+        for i in intersections_per_zorder.values().flatten() {
+            let i = map.get_i(*i);
+            all_unzoomed_intersections
+                .push(unzoomed_intersection_color(i, map, cs), i.polygon.clone());
         }
-        let draw_all_unzoomed_intersections = all_intersections.upload(ctx);
         timer.stop("generate unzoomed intersections");
 
-        let mut buildings: Vec<DrawBuilding> = Vec::new();
         let mut all_buildings = GeomBatch::new();
         let mut all_building_paths = GeomBatch::new();
         let mut all_building_outlines = GeomBatch::new();
         timer.start_iter("make DrawBuildings", map.all_buildings().len());
         for b in map.all_buildings() {
             timer.next();
-            buildings.push(DrawBuilding::new(
+            DrawBuilding::add_to_batches(
                 b,
                 cs,
                 &mut all_buildings,
                 &mut all_building_paths,
                 &mut all_building_outlines,
-                ctx.prerender,
-            ));
+                &icons,
+            );
         }
-        timer.start("upload all buildings");
-        let draw_all_buildings = all_buildings.upload(ctx);
-        let draw_all_building_paths = all_building_paths.upload(ctx);
-        let draw_all_building_outlines = all_building_outlines.upload(ctx);
-        timer.stop("upload all buildings");
 
         timer.start("make DrawParkingLot");
-        let mut parking_lots: Vec<DrawParkingLot> = Vec::new();
+        let mut parking_lots: Vec<AlmostDrawParkingLot> = Vec::new();
         let mut all_unzoomed_parking_lots = GeomBatch::new();
         for pl in map.all_parking_lots() {
             parking_lots.push(DrawParkingLot::new(
                 pl,
                 cs,
                 &mut all_unzoomed_parking_lots,
-                ctx.prerender,
+                &icons,
             ));
         }
-        let draw_all_unzoomed_parking_lots = all_unzoomed_parking_lots.upload(ctx);
         timer.stop("make DrawParkingLot");
 
         timer.start_iter("make DrawBusStop", map.all_bus_stops().len());
-        let mut bus_stops: HashMap<BusStopID, DrawBusStop> = HashMap::new();
+        let mut bus_stops: Vec<AlmostDrawBusStop> = Vec::new();
         for s in map.all_bus_stops().values() {
             timer.next();
-            bus_stops.insert(s.id, DrawBusStop::new(s, map, cs, ctx.prerender));
+            bus_stops.push(DrawBusStop::new(s, map, cs, &icons));
         }
 
         let mut areas: Vec<DrawArea> = Vec::new();
@@ -178,14 +220,138 @@ impl DrawMap {
             timer.next();
             areas.push(DrawArea::new(a, cs, &mut all_areas));
         }
-        timer.start("upload all areas");
-        let draw_all_areas = all_areas.upload(ctx);
-        timer.stop("upload all areas");
 
-        let boundary_polygon = ctx.prerender.upload(GeomBatch::from(vec![(
+        let boundary_polygon = GeomBatch::from(vec![(
             cs.map_background,
             map.get_boundary_polygon().clone(),
-        )]));
+        )]);
+
+        let underlay = flags.underlay.as_ref().map(|path| {
+            timer.start("load underlay");
+            let batch = load_underlay(path, map.get_gps_bounds(), timer);
+            timer.stop("load underlay");
+            batch
+        });
+
+        DrawMapGeometry {
+            icons,
+            roads,
+            lanes,
+            intersections,
+            parking_lots,
+            bus_stops,
+            areas,
+
+            roads_per_zorder,
+            intersections_per_zorder,
+
+            boundary_polygon,
+            underlay,
+            all_thick_roads,
+            all_unzoomed_intersections,
+            all_buildings,
+            all_building_paths,
+            all_building_outlines,
+            all_unzoomed_parking_lots,
+            all_areas,
+        }
+    }
+
+    // Has to happen on the main thread.
+    pub fn upload(geom: DrawMapGeometry, map: &Map, ctx: &EventCtx, timer: &mut Timer) -> DrawMap {
+        let prerender = ctx.prerender;
+
+        timer.start_iter("upload DrawRoads", geom.roads.len());
+        let mut roads: Vec<DrawRoad> = Vec::new();
+        for almost in geom.roads {
+            timer.next();
+            roads.push(almost.finish(prerender));
+        }
+
+        timer.start_iter("upload DrawLanes", geom.lanes.len());
+        let mut lanes: Vec<DrawLane> = Vec::new();
+        for almost in geom.lanes {
+            timer.next();
+            lanes.push(almost.finish(prerender));
+        }
+
+        timer.start_iter("upload DrawIntersections", geom.intersections.len());
+        let mut intersections: Vec<DrawIntersection> = Vec::new();
+        for almost in geom.intersections {
+            timer.next();
+            intersections.push(almost.finish(prerender));
+        }
+
+        timer.start("upload DrawParkingLots and DrawBusStops");
+        let parking_lots: Vec<DrawParkingLot> = geom
+            .parking_lots
+            .into_iter()
+            .map(|almost| almost.finish(prerender))
+            .collect();
+        let mut bus_stops: HashMap<BusStopID, DrawBusStop> = HashMap::new();
+        for almost in geom.bus_stops {
+            bus_stops.insert(almost.id, almost.finish(prerender));
+        }
+        timer.stop("upload DrawParkingLots and DrawBusStops");
+
+        timer.start("upload all unzoomed geometry");
+        let boundary_polygon = prerender.upload(geom.boundary_polygon);
+        let underlay = geom.underlay.map(|batch| prerender.upload(batch));
+        let draw_all_thick_roads = prerender.upload(geom.all_thick_roads);
+        let draw_all_unzoomed_intersections = prerender.upload(geom.all_unzoomed_intersections);
+        let draw_all_buildings = prerender.upload(geom.all_buildings);
+        let draw_all_building_paths = prerender.upload(geom.all_building_paths);
+        let draw_all_building_outlines = prerender.upload(geom.all_building_outlines);
+        let draw_all_unzoomed_parking_lots = prerender.upload(geom.all_unzoomed_parking_lots);
+        let draw_all_areas = prerender.upload(geom.all_areas);
+        timer.stop("upload all unzoomed geometry");
+
+        timer.start("split the base map into tiles");
+        let mut tiles = BaseMapTiles::new(map.get_bounds().clone());
+        tiles.add(
+            BaseLayer::Areas,
+            map.all_areas().iter().map(|a| ID::Area(a.id)).collect(),
+            map,
+        );
+        tiles.add(
+            BaseLayer::ParkingLots,
+            map.all_parking_lots()
+                .iter()
+                .map(|pl| ID::ParkingLot(pl.id))
+                .collect(),
+            map,
+        );
+        for (_, ids) in geom.intersections_per_zorder {
+            tiles.add(
+                BaseLayer::Intersections,
+                ids.into_iter().map(ID::Intersection).collect(),
+                map,
+            );
+        }
+        for (_, ids) in geom.roads_per_zorder {
+            tiles.add(
+                BaseLayer::Roads,
+                ids.into_iter().map(ID::Road).collect(),
+                map,
+            );
+        }
+        tiles.add(
+            BaseLayer::Buildings,
+            map.all_buildings()
+                .iter()
+                .map(|b| ID::Building(b.id))
+                .collect(),
+            map,
+        );
+        timer.stop("split the base map into tiles");
+
+        timer.start("prepare labels");
+        let labels = MapLabels::new(map);
+        timer.stop("prepare labels");
+
+        let buildings: Vec<DrawBuilding> =
+            map.all_buildings().iter().map(DrawBuilding::new).collect();
+        let areas = geom.areas;
 
         timer.start("create quadtree");
         let mut quadtree = QuadTree::default(map.get_bounds().as_bbox());
@@ -213,7 +379,7 @@ impl DrawMap {
 
         timer.note(format!(
             "static DrawMap consumes {} MB on the GPU",
-            abstutil::prettyprint_usize(ctx.prerender.get_total_bytes_uploaded() / 1024 / 1024)
+            abstutil::prettyprint_usize(prerender.get_total_bytes_uploaded() / 1024 / 1024)
         ));
 
         DrawMap {
@@ -225,18 +391,25 @@ impl DrawMap {
             bus_stops,
             areas,
             boundary_polygon,
+            underlay,
             draw_all_thick_roads,
             draw_all_unzoomed_intersections,
             draw_all_buildings,
             draw_all_building_paths,
             draw_all_building_outlines,
+            draw_all_building_extrusions: RefCell::new(None),
             draw_all_unzoomed_parking_lots,
             draw_all_areas,
+            icons: geom.icons,
+            tiles,
+            labels,
+            night: RefCell::new(None),
 
             agents: RefCell::new(AgentCache {
                 time: None,
                 agents_per_on: HashMap::new(),
                 unzoomed: None,
+                unit_circle: None,
             }),
 
             quadtree,
@@ -342,8 +515,23 @@ pub struct AgentCache {
     // This time applies to agents_per_on. unzoomed has its own possibly separate Time!
     time: Option<Time>,
     agents_per_on: HashMap<Traversable, Vec<Box<dyn Renderable>>>,
-    // agent radius also matters
-    unzoomed: Option<(Time, Option<Distance>, AgentColorScheme, Drawable)>,
+    // agent radius, the dot map zoom, and the cluster zoom also matter
+    unzoomed: Option<(
+        Time,
+        Option<Distance>,
+        Option<f64>,
+        Option<f64>,
+        AgentColorScheme,
+        UnzoomedAgents,
+    )>,
+    // Every unzoomed agent is a copy of this, placed by the GPU. Uploaded the first time it's
+    // needed.
+    unit_circle: Option<Drawable>,
+}
+
+enum UnzoomedAgents {
+    DotMap(Drawable),
+    Circles(Vec<Instance>),
 }
 
 impl AgentCache {
@@ -397,69 +585,263 @@ impl AgentCache {
         source: &dyn GetDrawAgents,
         map: &Map,
         acs: &AgentColorScheme,
+        sim: &Sim,
         g: &mut GfxCtx,
         maybe_radius: Option<Distance>,
+        // When zoomed out far enough, aggregate agents per road instead.
+        dot_map_zoom: Option<f64>,
+        // If present, keep agents a few pixels wide at this zoom, and merge crowded spots into
+        // heat blobs.
+        cluster_zoom: Option<f64>,
     ) {
         let now = source.time();
-        if let Some((time, r, ref orig_acs, ref draw)) = self.unzoomed {
-            if now == time && maybe_radius == r && acs == orig_acs {
-                g.redraw(draw);
-                return;
-            }
+        if self.unit_circle.is_none() {
+            self.unit_circle = Some(g.upload(GeomBatch::from(vec![(
+                Color::WHITE,
+                Circle::new(Pt2D::new(0.0, 0.0), Distance::meters(1.0)).to_polygon(),
+            )])));
         }
+        let fresh = match self.unzoomed {
+            Some((time, r, zoom, cluster, ref orig_acs, _)) => {
+                !(now == time
+                    && maybe_radius == r
+                    && dot_map_zoom == zoom
+                    && cluster_zoom == cluster
+                    && acs == orig_acs)
+            }
+            None => true,
+        };
 
-        let mut batch = GeomBatch::new();
-        // It's quite silly to produce triangles for the same circle over and over again. ;)
-        if let Some(r) = maybe_radius {
-            let circle = Circle::new(Pt2D::new(0.0, 0.0), r).to_polygon();
-            for agent in source.get_unzoomed_agents(map) {
-                if let Some(color) = acs.color(&agent) {
-                    batch.push(color, circle.translate(agent.pos.x(), agent.pos.y()));
+        if fresh {
+            let agents = if let Some(zoom) = dot_map_zoom {
+                UnzoomedAgents::DotMap(g.upload(draw_dot_map(
+                    source,
+                    map,
+                    acs,
+                    sim,
+                    g.prerender,
+                    zoom,
+                )))
+            } else {
+                // Lane thickness is a little hard to see, so double it. Most of the time, the
+                // circles don't leak out of the road too much.
+                let (mut car_radius, mut ped_radius) = match maybe_radius {
+                    Some(r) => (r, r),
+                    None => (4.0 * NORMAL_LANE_THICKNESS, 4.0 * SIDEWALK_THICKNESS),
+                };
+                if let Some(zoom) = cluster_zoom {
+                    let min_radius = Distance::meters(MIN_DOT_PIXELS) / zoom;
+                    car_radius = car_radius.max(min_radius);
+                    ped_radius = ped_radius.max(min_radius);
                 }
-            }
-        } else {
-            // Lane thickness is a little hard to see, so double it. Most of the time, the circles
-            // don't leak out of the road too much.
-            let car_circle =
-                Circle::new(Pt2D::new(0.0, 0.0), 4.0 * NORMAL_LANE_THICKNESS).to_polygon();
-            let ped_circle =
-                Circle::new(Pt2D::new(0.0, 0.0), 4.0 * SIDEWALK_THICKNESS).to_polygon();
-            for agent in source.get_unzoomed_agents(map) {
-                if let Some(color) = acs.color(&agent) {
-                    if agent.vehicle_type.is_some() {
-                        batch.push(color, car_circle.translate(agent.pos.x(), agent.pos.y()));
-                    } else {
-                        batch.push(color, ped_circle.translate(agent.pos.x(), agent.pos.y()));
+                let mut dots = Vec::new();
+                for agent in source.get_unzoomed_agents(map) {
+                    if let Some(row) = acs.row(&agent, sim, map) {
+                        let radius = if agent.vehicle_type.is_some() {
+                            car_radius
+                        } else {
+                            ped_radius
+                        };
+                        dots.push((agent.pos, row, radius));
                     }
                 }
+                UnzoomedAgents::Circles(match cluster_zoom {
+                    Some(zoom) => merge_clusters(dots, acs, zoom),
+                    None => dots
+                        .into_iter()
+                        .map(|(pt, row, radius)| {
+                            Instance::new(pt, acs.rows[row].1).scale(radius.inner_meters())
+                        })
+                        .collect(),
+                })
+            };
+            self.unzoomed = Some((
+                now,
+                maybe_radius,
+                dot_map_zoom,
+                cluster_zoom,
+                acs.clone(),
+                agents,
+            ));
+        }
+
+        match self.unzoomed.as_ref().unwrap().5 {
+            UnzoomedAgents::DotMap(ref draw) => {
+                g.redraw(draw);
             }
+            UnzoomedAgents::Circles(ref instances) => {
+                g.draw_instances(self.unit_circle.as_ref().unwrap(), instances);
+            }
+        }
+    }
+}
+
+// Agents sharing a crowded patch of the screen get replaced by one translucent blob in the most
+// common color there, growing and getting more opaque with the crowd. Everybody else stays a dot.
+fn merge_clusters(
+    dots: Vec<(Pt2D, usize, Distance)>,
+    acs: &AgentColorScheme,
+    zoom: f64,
+) -> Vec<Instance> {
+    let cell = CLUSTER_CELL_PIXELS / zoom;
+    let mut cells: BTreeMap<(i64, i64), Vec<(Pt2D, usize, Distance)>> = BTreeMap::new();
+    for dot in dots {
+        let key = (
+            (dot.0.x() / cell).floor() as i64,
+            (dot.0.y() / cell).floor() as i64,
+        );
+        cells.entry(key).or_insert_with(Vec::new).push(dot);
+    }
+
+    let mut instances = Vec::new();
+    for (_, members) in cells {
+        if members.len() < CLUSTER_MIN_AGENTS {
+            for (pt, row, radius) in members {
+                instances.push(Instance::new(pt, acs.rows[row].1).scale(radius.inner_meters()));
+            }
+            continue;
         }
 
-        let draw = g.upload(batch);
-        g.redraw(&draw);
-        self.unzoomed = Some((now, maybe_radius, acs.clone(), draw));
+        let n = members.len() as f64;
+        let mut per_row: BTreeMap<usize, usize> = BTreeMap::new();
+        let (mut x, mut y) = (0.0, 0.0);
+        for (pt, row, _) in &members {
+            *per_row.entry(*row).or_insert(0) += 1;
+            x += pt.x();
+            y += pt.y();
+        }
+        let row = per_row
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .unwrap()
+            .0;
+        let density = (n / (4.0 * CLUSTER_MIN_AGENTS as f64)).min(1.0);
+        let radius = cell / 2.0 * (n / CLUSTER_MIN_AGENTS as f64).sqrt().min(2.0);
+        instances.push(
+            Instance::new(
+                Pt2D::new(x / n, y / n),
+                acs.rows[row].1.alpha(0.4 + 0.4 * density),
+            )
+            .scale(radius),
+        );
+    }
+    instances
+}
+
+// One dot per road for each kind of agent on it, sized by how many there are.
+fn draw_dot_map(
+    source: &dyn GetDrawAgents,
+    map: &Map,
+    acs: &AgentColorScheme,
+    sim: &Sim,
+    prerender: &Prerender,
+    zoom: f64,
+) -> GeomBatch {
+    // Per road, count agents in each row of the color scheme
+    let mut counts: BTreeMap<RoadID, BTreeMap<usize, usize>> = BTreeMap::new();
+    for agent in source.get_unzoomed_agents(map) {
+        if let Some(row) = acs.row(&agent, sim, map) {
+            let r = match agent.on {
+                Traversable::Lane(l) => map.get_l(l).parent,
+                Traversable::Turn(t) => map.get_l(t.dst).parent,
+            };
+            *counts
+                .entry(r)
+                .or_insert_with(BTreeMap::new)
+                .entry(row)
+                .or_insert(0) += 1;
+        }
+    }
+
+    // Sizes are in pixels, so they stay legible at any zoom
+    let base_radius = Distance::meters(3.0) / zoom;
+    let mut batch = GeomBatch::new();
+    for (r, per_row) in counts {
+        let pl = &map.get_r(r).center_pts;
+        let num_dots = per_row.len() as f64;
+        for (idx, (row, count)) in per_row.into_iter().enumerate() {
+            // Spread out the dots for different rows along the road
+            let (pt, _) = pl.dist_along(pl.length() * ((idx as f64 + 1.0) / (num_dots + 1.0)));
+            let radius = base_radius * (count as f64).sqrt().min(4.0);
+            batch.push(acs.rows[row].1, Circle::new(pt, radius).to_polygon());
+            if count >= DOT_MAP_MIN_LABEL {
+                batch.append(
+                    Text::from(Line(count.to_string()).small())
+                        .render_to_batch(prerender)
+                        .scale(1.0 / zoom)
+                        .centered_on(pt),
+                );
+            }
+        }
     }
+    batch
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorAgentsBy {
+    Mode,
+    // There's no real trip purpose in the scenarios, so guess from the destination
+    TripPurpose,
+    // How much of the current leg of the trip has been spent waiting
+    Delay,
+    // Which part of the map the trip started from
+    OriginZone,
 }
 
 #[derive(PartialEq, Clone)]
 pub struct AgentColorScheme {
-    // TODO Could consider specializing this more?
+    pub color_by: ColorAgentsBy,
     pub rows: Vec<(String, Color, bool)>,
 }
 
 impl AgentColorScheme {
     pub fn new(cs: &ColorScheme) -> AgentColorScheme {
-        AgentColorScheme {
-            rows: vec![
-                ("Car".to_string(), cs.unzoomed_car.alpha(0.8), true),
-                ("Bike".to_string(), cs.unzoomed_bike.alpha(0.8), true),
-                ("Bus".to_string(), cs.unzoomed_bus.alpha(0.8), true),
+        AgentColorScheme::by(cs, ColorAgentsBy::Mode)
+    }
+
+    pub fn by(cs: &ColorScheme, color_by: ColorAgentsBy) -> AgentColorScheme {
+        let rows = match color_by {
+            ColorAgentsBy::Mode => vec![
+                ("Car".to_string(), cs.unzoomed_car.alpha(0.8)),
+                ("Bike".to_string(), cs.unzoomed_bike.alpha(0.8)),
+                ("Bus".to_string(), cs.unzoomed_bus.alpha(0.8)),
+                ("Pedestrian".to_string(), cs.unzoomed_pedestrian.alpha(0.8)),
+            ],
+            ColorAgentsBy::TripPurpose => vec![
+                "Going home",
+                "To shops and amenities",
+                "To other buildings",
+                "Leaving the map",
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, name)| (name.to_string(), cs.rotating_color_agents(idx).alpha(0.8)))
+            .collect(),
+            ColorAgentsBy::Delay => vec![
+                ("Moving freely".to_string(), cs.agent_not_delayed.alpha(0.8)),
+                ("Some delay".to_string(), cs.agent_some_delay.alpha(0.8)),
                 (
-                    "Pedestrian".to_string(),
-                    cs.unzoomed_pedestrian.alpha(0.8),
-                    true,
+                    "Heavily delayed".to_string(),
+                    cs.agent_heavily_delayed.alpha(0.8),
                 ),
             ],
+            ColorAgentsBy::OriginZone => vec!["North", "East", "South", "West", "Off-map"]
+                .into_iter()
+                .enumerate()
+                .map(|(idx, name)| {
+                    (
+                        format!("From {}", name),
+                        cs.rotating_color_agents(idx).alpha(0.8),
+                    )
+                })
+                .collect(),
+        };
+        AgentColorScheme {
+            color_by,
+            rows: rows
+                .into_iter()
+                .map(|(name, color)| (name, color, true))
+                .collect(),
         }
     }
 
@@ -473,26 +855,120 @@ impl AgentColorScheme {
         panic!("Can't toggle category {}", name);
     }
 
-    fn color(&self, agent: &UnzoomedAgent) -> Option<Color> {
-        let category = match agent.vehicle_type {
-            Some(VehicleType::Car) => "Car".to_string(),
-            Some(VehicleType::Bike) => "Bike".to_string(),
-            Some(VehicleType::Bus) => "Bus".to_string(),
-            None => "Pedestrian".to_string(),
+    // Which row does this agent belong to, if that row is enabled?
+    fn row(&self, agent: &UnzoomedAgent, sim: &Sim, map: &Map) -> Option<usize> {
+        let category = if self.color_by == ColorAgentsBy::Mode {
+            match agent.vehicle_type {
+                Some(VehicleType::Car) => "Car".to_string(),
+                Some(VehicleType::Bike) => "Bike".to_string(),
+                Some(VehicleType::Bus) => "Bus".to_string(),
+                None => "Pedestrian".to_string(),
+            }
+        } else {
+            // Buses aren't part of any trip, so they don't show up in the other schemes.
+            let trip = match sim.get_person(agent.person?).state {
+                PersonState::Trip(t) => t,
+                _ => {
+                    return None;
+                }
+            };
+            self.trip_category(trip, sim, map)?
         };
-        for (name, color, enabled) in &self.rows {
+        for (idx, (name, _, enabled)) in self.rows.iter().enumerate() {
             if name == &category {
                 if *enabled {
-                    return Some(*color);
+                    return Some(idx);
                 }
                 return None;
             }
         }
         panic!("Unknown AgentColorScheme category {}", category);
     }
+
+    fn trip_category(&self, trip: TripID, sim: &Sim, map: &Map) -> Option<String> {
+        let (_, start, end, _) = sim.trip_info(trip);
+        match self.color_by {
+            ColorAgentsBy::Mode => unreachable!(),
+            ColorAgentsBy::TripPurpose => Some(
+                match end {
+                    TripEndpoint::Bldg(b) => {
+                        let first_trip = sim.get_person(sim.trip_to_person(trip)).trips[0];
+                        if sim.trip_info(first_trip).1 == TripEndpoint::Bldg(b) {
+                            "Going home"
+                        } else if !map.get_b(b).amenities.is_empty() {
+                            "To shops and amenities"
+                        } else {
+                            "To other buildings"
+                        }
+                    }
+                    TripEndpoint::Border(_, _) => "Leaving the map",
+                }
+                .to_string(),
+            ),
+            ColorAgentsBy::Delay => {
+                let agent = match sim.trip_to_agent(trip) {
+                    TripResult::Ok(a) => a,
+                    _ => {
+                        return None;
+                    }
+                };
+                let props = sim.agent_properties(agent);
+                let pct_waiting = if props.total_time == Duration::ZERO {
+                    0.0
+                } else {
+                    props.total_waiting / props.total_time
+                };
+                Some(
+                    if pct_waiting < 0.1 {
+                        "Moving freely"
+                    } else if pct_waiting < 0.3 {
+                        "Some delay"
+                    } else {
+                        "Heavily delayed"
+                    }
+                    .to_string(),
+                )
+            }
+            ColorAgentsBy::OriginZone => {
+                let pt = match start {
+                    TripEndpoint::Bldg(b) => map.get_b(b).polygon.center(),
+                    TripEndpoint::Border(_, _) => {
+                        return Some("From Off-map".to_string());
+                    }
+                };
+                let center = map.get_bounds().get_rectangle().center();
+                let dx = pt.x() - center.x();
+                // Y increases going south
+                let dy = pt.y() - center.y();
+                Some(
+                    if dx.abs() > dy.abs() {
+                        if dx > 0.0 {
+                            "From East"
+                        } else {
+                            "From West"
+                        }
+                    } else if dy > 0.0 {
+                        "From South"
+                    } else {
+                        "From North"
+                    }
+                    .to_string(),
+                )
+            }
+        }
+    }
+}
+
+// TODO Would be neat to show closed intersections here too
+pub fn unzoomed_intersection_color(i: &Intersection, map: &Map, cs: &ColorScheme) -> Color {
+    if i.is_stop_sign() {
+        osm_rank_to_color(cs, i.get_rank(map))
+    } else {
+        cs.unzoomed_interesting_intersection
+    }
 }
 
-fn osm_rank_to_color(cs: &ColorScheme, rank: usize) -> Color {
+pub fn osm_rank_to_color(cs: &ColorScheme, rank: usize) -> Color {
     if rank >= 16 {
         cs.unzoomed_highway
     } else if rank >= 6 {
@@ -4,28 +4,39 @@ mod building;
 mod bus_stop;
 mod car;
 mod intersection;
+mod labels;
 mod lane;
 mod map;
+mod night;
 mod parking_lot;
 mod pedestrian;
 mod road;
+mod tiles;
 mod traffic_signal;
 mod turn;
+mod underlay;
 
 use crate::app::App;
 use crate::colors::ColorScheme;
 use crate::helpers::ID;
 pub use crate::render::area::DrawArea;
 use crate::render::bike::DrawBike;
+pub use crate::render::building::DrawBuilding;
 use crate::render::car::DrawCar;
 pub use crate::render::intersection::{calculate_corners, DrawIntersection};
+pub use crate::render::labels::MapLabels;
 pub use crate::render::lane::DrawLane;
-pub use crate::render::map::{AgentCache, AgentColorScheme, DrawMap};
+pub use crate::render::map::{
+    AgentCache, AgentColorScheme, ColorAgentsBy, DrawMap, DrawMapGeometry,
+};
+pub use crate::render::night::{darkness, draw_night};
 pub use crate::render::pedestrian::{DrawPedCrowd, DrawPedestrian};
 pub use crate::render::road::DrawRoad;
-pub use crate::render::traffic_signal::{draw_signal_phase, make_signal_diagram};
+pub use crate::render::traffic_signal::{
+    draw_signal_heads, draw_signal_phase, make_signal_diagram,
+};
 pub use crate::render::turn::{DrawTurnGroup, DrawUberTurnGroup};
-use ezgui::{GfxCtx, Prerender};
+use ezgui::{GeomBatch, GfxCtx, Prerender};
 use geom::{Distance, Polygon, Pt2D};
 use map_model::{IntersectionID, Map};
 use sim::{DrawCarInput, VehicleType};
@@ -85,3 +96,28 @@ impl DrawOptions {
         }
     }
 }
+
+// The SVGs drawn as part of the map. They're loaded once up-front without a Prerender, so the
+// map's geometry can be made off the main thread.
+#[derive(Clone)]
+pub struct MapIcons {
+    pub bike: GeomBatch,
+    pub bus: GeomBatch,
+    pub bus_only: GeomBatch,
+    pub parking: GeomBatch,
+    pub under_construction: GeomBatch,
+}
+
+impl MapIcons {
+    pub fn load() -> MapIcons {
+        MapIcons {
+            bike: GeomBatch::mapspace_svg_uncached("../data/system/assets/meters/bike.svg"),
+            bus: GeomBatch::mapspace_svg_uncached("../data/system/assets/meters/bus.svg"),
+            bus_only: GeomBatch::mapspace_svg_uncached("../data/system/assets/map/bus_only.svg"),
+            parking: GeomBatch::mapspace_svg_uncached("../data/system/assets/map/parking.svg"),
+            under_construction: GeomBatch::mapspace_svg_uncached(
+                "../data/system/assets/map/under_construction.svg",
+            ),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::app::App;
+use ezgui::{Drawable, GeomBatch, GfxCtx};
+use geom::{Circle, Distance, Polygon, Pt2D, Time};
+use map_model::{DirectedRoadID, Map, TurnPriority};
+use sim::{CarStatus, GetDrawAgents};
+use std::collections::BTreeMap;
+
+// When it's fully dark, everything's covered by the night sky color at this opacity
+const MAX_DARKNESS: f32 = 0.7;
+const HEADLIGHT_REACH: Distance = Distance::const_meters(25.0);
+// The beam spreads this many degrees to either side
+const HEADLIGHT_SPREAD_DEGS: f64 = 20.0;
+const SIGNAL_HEAD_RADIUS: Distance = Distance::const_meters(0.8);
+const SIGNAL_GLOW_RADIUS: Distance = Distance::const_meters(3.0);
+// Roads and intersections get this much darker than everything else
+const PAVEMENT_DARKNESS: f32 = 0.3;
+const WINDOW_GLOW: f32 = 0.6;
+// Darkness is rounded to this many steps, so the pavement and windows are only redone a few times
+// each evening
+const DARKNESS_STEPS: f64 = 10.0;
+
+// How dark it is, from 0 while the sun's up to 1 once civil twilight ends. This uses the map's
+// latitude and some day of the year. Sim times don't have a time zone, so solar noon is 12:00.
+pub fn darkness(map: &Map, time: Time, day_of_year: usize) -> f64 {
+    let lat = map
+        .get_bounds()
+        .center()
+        .forcibly_to_gps(map.get_gps_bounds())
+        .y()
+        .to_radians();
+    let (hours, minutes, seconds, _) = time.get_parts();
+    let hours = ((hours % 24) as f64) + (minutes as f64) / 60.0 + (seconds as f64) / 3600.0;
+
+    let declination = (-23.44_f64).to_radians()
+        * ((360.0 / 365.0) * (day_of_year as f64 + 10.0))
+            .to_radians()
+            .cos();
+    let hour_angle = (15.0 * (hours - 12.0)).to_radians();
+    let elevation = (lat.sin() * declination.sin()
+        + lat.cos() * declination.cos() * hour_angle.cos())
+    .asin()
+    .to_degrees();
+    // The sun sets at 0 degrees, and civil twilight ends at -6
+    (-elevation / 6.0).max(0.0).min(1.0)
+}
+
+// Tint everything on screen, going from dusk to night colors, then light it back up with windows,
+// headlights, and signal heads.
+pub fn draw_night(g: &mut GfxCtx, app: &App, source: &dyn GetDrawAgents, darkness: f64) {
+    let map = &app.primary.map;
+    let bounds = g.get_screen_bounds();
+    g.draw_polygon(
+        app.cs
+            .dusk_sky
+            .lerp(app.cs.night_sky, darkness)
+            .alpha(MAX_DARKNESS * (darkness as f32)),
+        &bounds.get_rectangle(),
+    );
+
+    let step = (darkness * DARKNESS_STEPS).round() as usize;
+    let hour = source.time().get_hours() % 24;
+    let mut cache = app.primary.draw_map.night.borrow_mut();
+    let fresh = match *cache {
+        Some((s, h, _)) => s != step || h != hour,
+        None => true,
+    };
+    if fresh {
+        *cache = Some((step, hour, draw_pavement_and_windows(g, app, step, hour)));
+    }
+    g.redraw(&cache.as_ref().unwrap().2);
+
+    let mut batch = GeomBatch::new();
+
+    // Headlights fade in with the dark
+    let beam = app.cs.headlight.alpha(0.5 * (darkness as f32));
+    for car in source.get_all_draw_cars(map) {
+        if car.status != CarStatus::Moving {
+            continue;
+        }
+        let front = car.body.last_pt();
+        if !bounds.contains(front) {
+            continue;
+        }
+        let angle = car.body.last_line().angle();
+        batch.push(
+            beam,
+            Polygon::new(&vec![
+                front,
+                front.project_away(HEADLIGHT_REACH, angle.rotate_degs(-HEADLIGHT_SPREAD_DEGS)),
+                front.project_away(HEADLIGHT_REACH, angle.rotate_degs(HEADLIGHT_SPREAD_DEGS)),
+            ]),
+        );
+    }
+
+    // One lit head per incoming road, showing the best movement it has right now
+    for i in map.all_intersections() {
+        if !i.is_traffic_signal() || !bounds.contains(i.polygon.center()) {
+            continue;
+        }
+        let signal = map.get_traffic_signal(i.id);
+        let (_, phase, _) = app.primary.sim.current_phase_and_remaining_time(i.id, map);
+        let mut heads: BTreeMap<DirectedRoadID, (TurnPriority, Pt2D)> = BTreeMap::new();
+        for (id, group) in &signal.turn_groups {
+            if id.crosswalk {
+                continue;
+            }
+            let pri = phase.get_priority_of_group(*id);
+            let pt = group.geom.first_pt();
+            let head = heads.entry(id.from).or_insert((pri, pt));
+            if pri > head.0 {
+                *head = (pri, pt);
+            }
+        }
+        for (_, (pri, pt)) in heads {
+            let color = match pri {
+                TurnPriority::Protected => app.cs.signal_protected_turn,
+                TurnPriority::Yield => app.cs.signal_permitted_turn,
+                TurnPriority::Banned => app.cs.signal_banned_turn,
+            };
+            batch.push(
+                color.alpha(0.3 * (darkness as f32)),
+                Circle::new(pt, SIGNAL_GLOW_RADIUS).to_polygon(),
+            );
+            batch.push(color, Circle::new(pt, SIGNAL_HEAD_RADIUS).to_polygon());
+        }
+    }
+
+    batch.draw(g);
+}
+
+// Pavement soaks up more of the dark than everything else, and some homes have their lights on.
+// Seen from above, a lit building just glows.
+fn draw_pavement_and_windows(g: &mut GfxCtx, app: &App, step: usize, hour: usize) -> Drawable {
+    let map = &app.primary.map;
+    let darkness = (step as f32) / (DARKNESS_STEPS as f32);
+    let mut batch = GeomBatch::new();
+
+    let pavement = app.cs.night_pavement.alpha(PAVEMENT_DARKNESS * darkness);
+    for r in map.all_roads() {
+        batch.push(pavement, r.get_thick_polygon(map).unwrap());
+    }
+    for i in map.all_intersections() {
+        batch.push(pavement, i.polygon.clone());
+    }
+
+    let window = app.cs.lit_window.alpha(WINDOW_GLOW * darkness);
+    for b in map.all_buildings() {
+        // The same homes stay lit as the night goes on and others turn their lights off
+        if b.occupancy.residents > 0 && (b.id.0 * 37) % 100 < pct_lit(hour) {
+            batch.push(window, b.polygon.clone());
+        }
+    }
+
+    g.upload(batch)
+}
+
+// What percent of homes have their lights on during some hour
+fn pct_lit(hour: usize) -> usize {
+    match hour {
+        17..=21 => 70,
+        22 => 50,
+        23 | 0 => 30,
+        1..=4 => 10,
+        5..=7 => 40,
+        _ => 20,
+    }
+}
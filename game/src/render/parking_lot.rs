@@ -1,7 +1,7 @@
 use crate::app::App;
 use crate::colors::ColorScheme;
 use crate::helpers::ID;
-use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
+use crate::render::{DrawOptions, MapIcons, Renderable, OUTLINE_THICKNESS};
 use ezgui::{Drawable, GeomBatch, GfxCtx, Prerender};
 use geom::{Distance, Line, PolyLine, Polygon, Pt2D};
 use map_model::{
@@ -9,6 +9,22 @@ use map_model::{
     SIDEWALK_THICKNESS,
 };
 
+// Split into two phases like this, because AlmostDrawParkingLot can be created on a background
+// thread, but GPU upload has to happen on the main one.
+pub struct AlmostDrawParkingLot {
+    id: ParkingLotID,
+    draw: GeomBatch,
+}
+
+impl AlmostDrawParkingLot {
+    pub fn finish(self, prerender: &Prerender) -> DrawParkingLot {
+        DrawParkingLot {
+            id: self.id,
+            draw: prerender.upload(self.draw),
+        }
+    }
+}
+
 pub struct DrawParkingLot {
     pub id: ParkingLotID,
     draw: Drawable,
@@ -19,21 +35,9 @@ impl DrawParkingLot {
         lot: &ParkingLot,
         cs: &ColorScheme,
         unzoomed_batch: &mut GeomBatch,
-        prerender: &Prerender,
-    ) -> DrawParkingLot {
-        unzoomed_batch.push(cs.parking_lot, lot.polygon.clone());
-        for aisle in &lot.aisles {
-            let aisle_thickness = NORMAL_LANE_THICKNESS / 2.0;
-            unzoomed_batch.push(
-                cs.unzoomed_residential,
-                PolyLine::unchecked_new(aisle.clone()).make_polygons(aisle_thickness),
-            );
-        }
-        unzoomed_batch.append(
-            GeomBatch::mapspace_svg(prerender, "../data/system/assets/map/parking.svg")
-                .scale(0.05)
-                .centered_on(lot.polygon.polylabel()),
-        );
+        icons: &MapIcons,
+    ) -> AlmostDrawParkingLot {
+        unzoomed_batch.append(DrawParkingLot::unzoomed(lot, cs, icons));
 
         // Trim the front path line away from the sidewalk's center line, so that it doesn't
         // overlap. For now, this cleanup is visual; it doesn't belong in the map_model layer.
@@ -80,11 +84,31 @@ impl DrawParkingLot {
             );
         }
 
-        DrawParkingLot {
+        AlmostDrawParkingLot {
             id: lot.id,
-            draw: prerender.upload(batch),
+            draw: batch,
         }
     }
+
+    pub fn unzoomed(lot: &ParkingLot, cs: &ColorScheme, icons: &MapIcons) -> GeomBatch {
+        let mut batch = GeomBatch::new();
+        batch.push(cs.parking_lot, lot.polygon.clone());
+        for aisle in &lot.aisles {
+            let aisle_thickness = NORMAL_LANE_THICKNESS / 2.0;
+            batch.push(
+                cs.unzoomed_residential,
+                PolyLine::unchecked_new(aisle.clone()).make_polygons(aisle_thickness),
+            );
+        }
+        batch.append(
+            icons
+                .parking
+                .clone()
+                .scale(0.05)
+                .centered_on(lot.polygon.polylabel()),
+        );
+        batch
+    }
 }
 
 impl Renderable for DrawParkingLot {
@@ -2,7 +2,7 @@ use crate::app::App;
 use crate::colors::ColorScheme;
 use crate::helpers::ID;
 use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
-use ezgui::{Color, Drawable, GeomBatch, GfxCtx, Line, Prerender, Text};
+use ezgui::{Drawable, GeomBatch, GfxCtx, Line, Prerender, Text};
 use geom::{ArrowCap, Circle, Distance, PolyLine, Polygon};
 use map_model::{Map, SIDEWALK_THICKNESS};
 use sim::{DrawPedCrowdInput, DrawPedestrianInput, PedCrowdLocation, PedestrianID};
@@ -228,7 +228,7 @@ impl DrawPedCrowd {
         let mut batch = GeomBatch::new();
         batch.push(cs.ped_crowd, blob.clone());
         batch.append(
-            Text::from(Line(format!("{}", input.members.len())).fg(Color::BLACK))
+            Text::from(Line(format!("{}", input.members.len())).fg(cs.map_label))
                 .render_to_batch(prerender)
                 .scale(0.02)
                 .centered_on(blob.center()),
@@ -2,27 +2,52 @@ use crate::app::App;
 use crate::colors::ColorScheme;
 use crate::helpers::ID;
 use crate::render::{DrawOptions, Renderable};
-use ezgui::{Drawable, GeomBatch, GfxCtx, Line, Prerender, Text};
-use geom::{Distance, Polygon, Pt2D};
-use map_model::{LaneType, Map, Road, RoadID};
-use std::cell::RefCell;
+use ezgui::{Drawable, GeomBatch, GfxCtx, Prerender};
+use geom::{Distance, Line, Polygon, Pt2D};
+use map_model::{LaneID, LaneType, Map, Road, RoadID};
+
+// Split into two phases like this, because AlmostDrawRoad can be created on a background thread,
+// but GPU upload has to happen on the main one.
+pub struct AlmostDrawRoad {
+    id: RoadID,
+    zorder: isize,
+    draw_center_line: GeomBatch,
+}
+
+impl AlmostDrawRoad {
+    pub fn finish(self, prerender: &Prerender) -> DrawRoad {
+        DrawRoad {
+            id: self.id,
+            zorder: self.zorder,
+            draw_center_line: prerender.upload(self.draw_center_line),
+        }
+    }
+}
 
 pub struct DrawRoad {
     pub id: RoadID,
     zorder: isize,
 
     draw_center_line: Drawable,
-    label: RefCell<Option<Drawable>>,
 }
 
 impl DrawRoad {
-    pub fn new(r: &Road, map: &Map, cs: &ColorScheme, prerender: &Prerender) -> DrawRoad {
+    pub fn new(r: &Road, map: &Map, cs: &ColorScheme) -> AlmostDrawRoad {
         let mut draw = GeomBatch::new();
         let center = r.get_current_center(map);
         let width = Distance::meters(0.25);
         // If the road is a one-way (only parking and sidewalk on the off-side), draw a solid line
         // No center line at all if there's a shared left turn lane
-        if r.children_backwards
+        // Roads with more than one lane in some direction get a solid double line, so nobody
+        // passes. Otherwise the line is dashed.
+        if let Some(median) = r.median {
+            // Raised like a sidewalk
+            draw.push(cs.sidewalk, center.make_polygons(median.width));
+            if let Some(curbs) = center.to_thick_boundary(median.width, width) {
+                draw.push(cs.road_center_line, curbs);
+            }
+        } else if r
+            .children_backwards
             .iter()
             .all(|(_, lt)| *lt == LaneType::Parking || *lt == LaneType::Sidewalk)
         {
@@ -30,17 +55,52 @@ impl DrawRoad {
         } else if r.children_forwards.is_empty()
             || r.children_forwards[0].1 != LaneType::SharedLeftTurn
         {
-            draw.extend(
-                cs.road_center_line,
-                center.dashed_lines(width, Distance::meters(2.0), Distance::meters(1.0)),
-            );
+            if num_through_lanes(&r.children_forwards) > 1
+                || num_through_lanes(&r.children_backwards) > 1
+            {
+                draw.push(
+                    cs.road_center_line,
+                    center.shift_left(width).unwrap().make_polygons(width / 2.0),
+                );
+                draw.push(
+                    cs.road_center_line,
+                    center
+                        .shift_right(width)
+                        .unwrap()
+                        .make_polygons(width / 2.0),
+                );
+            } else {
+                draw.extend(
+                    cs.road_center_line,
+                    center.dashed_lines(width, Distance::meters(2.0), Distance::meters(1.0)),
+                );
+            }
         }
 
-        DrawRoad {
+        // Bridges are drawn solid over the road. Underpasses are just dashed, since they're
+        // hidden beneath it.
+        for fb in &r.footbridges {
+            if let Some(line) = Line::maybe_new(fb.from.pt(map), fb.to.pt(map)) {
+                let width = map.get_l(fb.from.lane()).width;
+                let pl = line.to_polyline();
+                if fb.underpass {
+                    draw.extend(
+                        cs.sidewalk_lines,
+                        pl.dashed_lines(width / 4.0, Distance::meters(1.0), Distance::meters(0.5)),
+                    );
+                } else {
+                    draw.push(cs.sidewalk, pl.make_polygons(width));
+                    if let Some(rails) = pl.to_thick_boundary(width, width / 8.0) {
+                        draw.push(cs.sidewalk_lines, rails);
+                    }
+                }
+            }
+        }
+
+        AlmostDrawRoad {
             id: r.id,
             zorder: r.zorder,
-            draw_center_line: prerender.upload(draw),
-            label: RefCell::new(None),
+            draw_center_line: draw,
         }
     }
 }
@@ -50,54 +110,8 @@ impl Renderable for DrawRoad {
         ID::Road(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, app: &App, _: &DrawOptions) {
+    fn draw(&self, g: &mut GfxCtx, _: &App, _: &DrawOptions) {
         g.redraw(&self.draw_center_line);
-
-        if app.opts.label_roads {
-            // Lazily calculate
-            let mut label = self.label.borrow_mut();
-            if label.is_none() {
-                let mut batch = GeomBatch::new();
-                let r = app.primary.map.get_r(self.id);
-
-                if false {
-                    // Style 1: banner
-                    let mut txt = Text::new().with_bg();
-                    txt.add(Line(r.get_name()));
-                    batch.append(
-                        txt.render_to_batch(g.prerender)
-                            .scale(0.1)
-                            .centered_on(r.center_pts.middle()),
-                    );
-                } else {
-                    // Style 2: Yellow center-line
-                    let name = r.get_name();
-                    if r.center_pts.length() >= Distance::meters(30.0) && name != "???" {
-                        // TODO If it's definitely straddling bus/bike lanes, change the color? Or
-                        // even easier, just skip the center lines?
-                        let txt = Text::from(Line(name).fg(app.cs.road_center_line))
-                            .bg(app.cs.driving_lane);
-                        let (pt, mut angle) = r.center_pts.dist_along(r.center_pts.length() / 2.0);
-                        let theta = angle.normalized_degrees().rem_euclid(360.0);
-                        if theta > 90.0 {
-                            angle = angle.opposite();
-                        }
-                        if theta > 270.0 {
-                            angle = angle.opposite();
-                        }
-                        batch.append(
-                            txt.render_to_batch(g.prerender)
-                                .scale(0.1)
-                                .centered_on(pt)
-                                .rotate(angle),
-                        );
-                    }
-                }
-                *label = Some(g.prerender.upload(batch));
-            }
-            // TODO Covered up sometimes. We could fork and force a different z value...
-            g.redraw(label.as_ref().unwrap());
-        }
     }
 
     fn get_outline(&self, map: &Map) -> Polygon {
@@ -116,3 +130,10 @@ impl Renderable for DrawRoad {
         self.zorder
     }
 }
+
+fn num_through_lanes(lanes: &[(LaneID, LaneType)]) -> usize {
+    lanes
+        .iter()
+        .filter(|(_, lt)| *lt == LaneType::Driving || *lt == LaneType::Bus)
+        .count()
+}
@@ -0,0 +1,311 @@
+use crate::app::ShowLayers;
+use crate::colors::ColorScheme;
+use crate::helpers::ID;
+use crate::render::map::{osm_rank_to_color, unzoomed_intersection_color};
+use crate::render::parking_lot::DrawParkingLot;
+use crate::render::{DrawArea, DrawBuilding, MapIcons};
+use abstutil::Timer;
+use ezgui::{Drawable, GeomBatch, GfxCtx};
+use geom::{Bounds, Polygon};
+use map_model::Map;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+
+// The finest tiles split the map into 2^MAX_LEVEL pieces along each side
+const MAX_LEVEL: usize = 6;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BaseLayer {
+    Areas,
+    ParkingLots,
+    Intersections,
+    Roads,
+    Buildings,
+}
+
+// The static base map drawn while unzoomed, split into square tiles so only the part on screen is
+// drawn. The further out the camera is, the bigger and fewer the tiles, until one covers the whole
+// map; the caller just draws the whole-map batches then. Tiles only remember which objects they
+// contain; the geometry is generated from the map and uploaded the first time a tile is on screen,
+// so map edits just have to forget the affected tiles. Only tiles for the current level are kept,
+// so zooming in and out doesn't pile up copies of the map.
+pub struct BaseMapTiles {
+    bounds: Bounds,
+    cell_width: f64,
+    cell_height: f64,
+    // In drawing order
+    layers: Vec<TiledLayer>,
+    // The level and the uploaded tiles for it. Each tile has a drawable per layer, or none if the
+    // layer has nothing there.
+    tiles: RefCell<(usize, HashMap<(usize, usize), Vec<Option<Drawable>>>)>,
+}
+
+struct TiledLayer {
+    layer: BaseLayer,
+    // Objects are placed in the finest cell containing their center...
+    cells: HashMap<(usize, usize), Vec<ID>>,
+    // ... unless they're bigger than a cell, in which case they're always drawn. Uploaded when
+    // first needed.
+    big: Vec<ID>,
+    draw_big: RefCell<Option<Drawable>>,
+}
+
+impl BaseMapTiles {
+    pub fn new(bounds: Bounds) -> BaseMapTiles {
+        let num_cells = (1 << MAX_LEVEL) as f64;
+        BaseMapTiles {
+            cell_width: bounds.width() / num_cells,
+            cell_height: bounds.height() / num_cells,
+            bounds,
+            layers: Vec::new(),
+            tiles: RefCell::new((0, HashMap::new())),
+        }
+    }
+
+    // Layers are drawn in the order they're added. Separate groups of the same layer, like roads
+    // at different z-orders, should be added separately.
+    pub fn add(&mut self, layer: BaseLayer, ids: Vec<ID>, map: &Map) {
+        let mut tiled = TiledLayer {
+            layer,
+            cells: HashMap::new(),
+            big: Vec::new(),
+            draw_big: RefCell::new(None),
+        };
+        for id in ids {
+            let cell = self.cell_for(&id, map);
+            tiled.insert(id, cell);
+        }
+        self.layers.push(tiled);
+    }
+
+    // After map edits change these objects, move them to wherever they belong now, and forget
+    // the tiles they were or are in. Those'll be generated again the next time they're on screen.
+    pub fn invalidate(&mut self, ids: Vec<ID>, map: &Map) {
+        let mut dirty_cells = BTreeSet::new();
+        for id in ids {
+            let new_cell = self.cell_for(&id, map);
+            for tiled in &mut self.layers {
+                if let Some(old_cell) = tiled.remove(&id) {
+                    dirty_cells.extend(old_cell);
+                    dirty_cells.extend(new_cell);
+                    tiled.insert(id, new_cell);
+                    break;
+                }
+            }
+        }
+
+        let mut tiles = self.tiles.borrow_mut();
+        let shift = MAX_LEVEL - tiles.0;
+        for (cx, cy) in dirty_cells {
+            tiles.1.remove(&(cx >> shift, cy >> shift));
+        }
+    }
+
+    // The level where one tile is about as big as the screen. 0 means the whole map is one tile.
+    pub fn level(&self, screen: &Bounds) -> usize {
+        let ratio =
+            (self.bounds.width() / screen.width()).min(self.bounds.height() / screen.height());
+        if ratio <= 1.0 {
+            0
+        } else {
+            (ratio.log2().floor() as usize).min(MAX_LEVEL)
+        }
+    }
+
+    pub fn draw(
+        &self,
+        g: &mut GfxCtx,
+        level: usize,
+        layers: &ShowLayers,
+        map: &Map,
+        cs: &ColorScheme,
+        icons: &MapIcons,
+    ) {
+        let screen = g.get_screen_bounds();
+        let cells_per_tile = 1 << (MAX_LEVEL - level);
+        // Every object in a cell is smaller than the cell, so it can't stick out further than
+        // that
+        let (x1, y1) = self.tile(
+            level,
+            screen.min_x - self.cell_width,
+            screen.min_y - self.cell_height,
+        );
+        let (x2, y2) = self.tile(
+            level,
+            screen.max_x + self.cell_width,
+            screen.max_y + self.cell_height,
+        );
+
+        let mut tiles = self.tiles.borrow_mut();
+        if tiles.0 != level {
+            *tiles = (level, HashMap::new());
+        }
+        for x in x1..=x2 {
+            for y in y1..=y2 {
+                if !tiles.1.contains_key(&(x, y)) {
+                    let drawables = self
+                        .layers
+                        .iter()
+                        .map(|l| l.upload_tile(g, (x, y), cells_per_tile, map, cs, icons))
+                        .collect();
+                    tiles.1.insert((x, y), drawables);
+                }
+            }
+        }
+
+        for (idx, l) in self.layers.iter().enumerate() {
+            let show = match l.layer {
+                BaseLayer::Areas => layers.show_areas,
+                BaseLayer::ParkingLots => layers.show_parking_lots,
+                BaseLayer::Intersections => layers.show_intersections,
+                BaseLayer::Roads => layers.show_lanes,
+                BaseLayer::Buildings => layers.show_buildings,
+            };
+            if !show {
+                continue;
+            }
+            {
+                let mut draw_big = l.draw_big.borrow_mut();
+                if draw_big.is_none() {
+                    let mut batch = GeomBatch::new();
+                    for id in &l.big {
+                        batch.append(unzoomed_batch(id, map, cs, icons));
+                    }
+                    *draw_big = Some(g.upload(batch));
+                }
+                g.redraw(draw_big.as_ref().unwrap());
+            }
+            for x in x1..=x2 {
+                for y in y1..=y2 {
+                    if let Some(ref draw) = tiles.1[&(x, y)][idx] {
+                        g.redraw(draw);
+                    }
+                }
+            }
+        }
+    }
+
+    // None if the object is bigger than a cell
+    fn cell_for(&self, id: &ID, map: &Map) -> Option<(usize, usize)> {
+        let b = outline(id, map).get_bounds();
+        if b.width() > self.cell_width || b.height() > self.cell_height {
+            None
+        } else {
+            Some(self.cell(b.center().x(), b.center().y()))
+        }
+    }
+
+    fn cell(&self, x: f64, y: f64) -> (usize, usize) {
+        let max = (1 << MAX_LEVEL) - 1;
+        let cx = ((x - self.bounds.min_x) / self.cell_width).floor().max(0.0) as usize;
+        let cy = ((y - self.bounds.min_y) / self.cell_height)
+            .floor()
+            .max(0.0) as usize;
+        (cx.min(max), cy.min(max))
+    }
+
+    fn tile(&self, level: usize, x: f64, y: f64) -> (usize, usize) {
+        let (cx, cy) = self.cell(x, y);
+        (cx >> (MAX_LEVEL - level), cy >> (MAX_LEVEL - level))
+    }
+}
+
+impl TiledLayer {
+    fn insert(&mut self, id: ID, cell: Option<(usize, usize)>) {
+        if let Some(cell) = cell {
+            self.cells.entry(cell).or_insert_with(Vec::new).push(id);
+        } else {
+            self.big.push(id);
+            *self.draw_big.borrow_mut() = None;
+        }
+    }
+
+    // If the object is in this layer, returns where it was
+    fn remove(&mut self, id: &ID) -> Option<Option<(usize, usize)>> {
+        if let Some(idx) = self.big.iter().position(|x| x == id) {
+            self.big.remove(idx);
+            *self.draw_big.borrow_mut() = None;
+            return Some(None);
+        }
+        for (cell, list) in self.cells.iter_mut() {
+            if let Some(idx) = list.iter().position(|x| x == id) {
+                list.remove(idx);
+                return Some(Some(*cell));
+            }
+        }
+        None
+    }
+
+    fn upload_tile(
+        &self,
+        g: &mut GfxCtx,
+        (x, y): (usize, usize),
+        cells_per_tile: usize,
+        map: &Map,
+        cs: &ColorScheme,
+        icons: &MapIcons,
+    ) -> Option<Drawable> {
+        let mut batch = GeomBatch::new();
+        let mut empty = true;
+        for cx in x * cells_per_tile..(x + 1) * cells_per_tile {
+            for cy in y * cells_per_tile..(y + 1) * cells_per_tile {
+                if let Some(list) = self.cells.get(&(cx, cy)) {
+                    for id in list {
+                        batch.append(unzoomed_batch(id, map, cs, icons));
+                        empty = false;
+                    }
+                }
+            }
+        }
+        if empty {
+            None
+        } else {
+            Some(g.upload(batch))
+        }
+    }
+}
+
+// Has to match what DrawMap puts in the whole-map unzoomed batches
+fn unzoomed_batch(id: &ID, map: &Map, cs: &ColorScheme, icons: &MapIcons) -> GeomBatch {
+    match id {
+        ID::Area(a) => {
+            let area = map.get_a(*a);
+            GeomBatch::from(vec![(
+                DrawArea::color(area.area_type, cs),
+                area.polygon.clone(),
+            )])
+        }
+        ID::ParkingLot(pl) => DrawParkingLot::unzoomed(map.get_pl(*pl), cs, icons),
+        ID::Intersection(i) => {
+            let i = map.get_i(*i);
+            GeomBatch::from(vec![(
+                unzoomed_intersection_color(i, map, cs),
+                i.polygon.clone(),
+            )])
+        }
+        ID::Road(r) => {
+            let r = map.get_r(*r);
+            GeomBatch::from(vec![(
+                osm_rank_to_color(cs, r.get_rank()),
+                outline(id, map),
+            )])
+        }
+        ID::Building(b) => DrawBuilding::base(map.get_b(*b), cs, icons),
+        _ => unreachable!(),
+    }
+}
+
+fn outline(id: &ID, map: &Map) -> Polygon {
+    match id {
+        ID::Area(a) => map.get_a(*a).polygon.clone(),
+        ID::ParkingLot(pl) => map.get_pl(*pl).polygon.clone(),
+        ID::Intersection(i) => map.get_i(*i).polygon.clone(),
+        // Problems were already reported when the map was first drawn
+        ID::Road(r) => map
+            .get_r(*r)
+            .get_thick_polygon(map)
+            .get(&mut Timer::throwaway()),
+        ID::Building(b) => map.get_b(*b).polygon.clone(),
+        _ => unreachable!(),
+    }
+}
@@ -2,13 +2,16 @@ use crate::app::App;
 use crate::options::TrafficSignalStyle;
 use crate::render::intersection::make_crosswalk;
 use crate::render::{DrawTurnGroup, BIG_ARROW_THICKNESS};
+use abstutil::retain_btreeset;
 use ezgui::{
-    hotkey, Btn, Color, Composite, EventCtx, GeomBatch, HorizontalAlignment, Key, Line, Prerender,
+    hotkey, Btn, Composite, EventCtx, GeomBatch, HorizontalAlignment, Key, Line, Prerender,
     RewriteColor, Text, TextExt, VerticalAlignment, Widget,
 };
 use geom::{Angle, ArrowCap, Circle, Distance, Duration, Line, PolyLine, Polygon, Pt2D};
-use map_model::{IntersectionID, Phase, TurnPriority, SIDEWALK_THICKNESS};
-use std::collections::BTreeSet;
+use map_model::{DirectedRoadID, IntersectionID, Phase, TurnPriority, SIDEWALK_THICKNESS};
+use std::collections::{BTreeMap, BTreeSet};
+
+const SIGNAL_LAMP_RADIUS: Distance = Distance::const_meters(0.4);
 
 // Only draws a box when time_left is present
 pub fn draw_signal_phase(
@@ -21,6 +24,21 @@ pub fn draw_signal_phase(
     signal_style: TrafficSignalStyle,
 ) {
     let signal = app.primary.map.get_traffic_signal(i);
+    let phase_length = signal.max_phase_length(phase);
+    // During the leading pedestrian interval, only the crosswalks are on
+    let walk_only;
+    let phase = if time_left
+        .and_then(|t| signal.leading_pedestrian_interval_left(phase, t))
+        .is_some()
+    {
+        let mut p = phase.clone();
+        retain_btreeset(&mut p.protected_groups, |g| g.crosswalk);
+        p.yield_groups.clear();
+        walk_only = p;
+        &walk_only
+    } else {
+        phase
+    };
 
     match signal_style {
         TrafficSignalStyle::BAP => {
@@ -37,11 +55,14 @@ pub fn draw_signal_phase(
             }
 
             let (yellow_light, percent) = if let Some(t) = time_left {
-                (t <= Duration::seconds(5.0), (t / phase.duration) as f32)
+                (
+                    signal.is_yellow(phase, t),
+                    (t / phase_length).min(1.0) as f32,
+                )
             } else {
                 (false, 1.0)
             };
-            let yellow = Color::YELLOW;
+            let yellow = app.cs.signal_yellow;
             for g in &phase.protected_groups {
                 if !g.crosswalk {
                     let slice_start = if crossed_roads.contains(&(g.from.id, g.parent)) {
@@ -91,7 +112,7 @@ pub fn draw_signal_phase(
                 assert!(!g.crosswalk);
                 let pl = &signal.turn_groups[g].geom;
                 batch.extend(
-                    Color::BLACK,
+                    app.cs.signal_yield_outline,
                     pl.exact_slice(
                         SIDEWALK_THICKNESS - Distance::meters(0.1),
                         pl.length() - SIDEWALK_THICKNESS + Distance::meters(0.1),
@@ -261,7 +282,7 @@ pub fn draw_signal_phase(
 
     let radius = Distance::meters(2.0);
     let center = app.primary.map.get_i(i).polygon.center();
-    let percent = time_left.unwrap() / phase.duration;
+    let percent = (time_left.unwrap() / phase_length).min(1.0);
     batch.push(
         app.cs.signal_box,
         Circle::new(center, 1.2 * radius).to_polygon(),
@@ -276,6 +297,71 @@ pub fn draw_signal_phase(
     );
 }
 
+// A small signal head at the stop line of each approach, lighting red, yellow, or green for the
+// vehicles coming from that road. Red is closest to the intersection.
+pub fn draw_signal_heads(
+    phase: &Phase,
+    i: IntersectionID,
+    time_left: Duration,
+    batch: &mut GeomBatch,
+    app: &App,
+) {
+    let signal = app.primary.map.get_traffic_signal(i);
+    let held = signal
+        .leading_pedestrian_interval_left(phase, time_left)
+        .is_some();
+    let yellow = signal.is_yellow(phase, time_left);
+
+    // Can any vehicles from this road go, where do their movements start, and which way do they
+    // face?
+    let mut approaches: BTreeMap<DirectedRoadID, (bool, Vec<Pt2D>, Angle)> = BTreeMap::new();
+    for (id, group) in &signal.turn_groups {
+        if id.crosswalk {
+            continue;
+        }
+        let approach = approaches
+            .entry(id.from)
+            .or_insert_with(|| (false, Vec::new(), group.geom.first_line().angle()));
+        if phase.get_priority_of_group(*id) != TurnPriority::Banned {
+            approach.0 = true;
+        }
+        approach.1.push(group.geom.first_pt());
+    }
+
+    let lamps = vec![
+        app.cs.signal_lamp_red,
+        app.cs.signal_lamp_yellow,
+        app.cs.signal_lamp_green,
+    ];
+    for (_, (go, pts, angle)) in approaches {
+        let lit = if !go || held {
+            0
+        } else if yellow {
+            1
+        } else {
+            2
+        };
+        let back = angle.opposite();
+        let front = Pt2D::center(&pts).project_away(SIGNAL_LAMP_RADIUS / 2.0, back);
+        let spacing = SIGNAL_LAMP_RADIUS * 2.4;
+        batch.push(
+            app.cs.signal_housing,
+            Line::new(
+                front,
+                front.project_away(spacing * (lamps.len() as f64), back),
+            )
+            .make_polygons(spacing),
+        );
+        for (idx, color) in lamps.iter().enumerate() {
+            let center = front.project_away(spacing * (0.5 + idx as f64), back);
+            batch.push(
+                if idx == lit { *color } else { color.alpha(0.2) },
+                Circle::new(center, SIGNAL_LAMP_RADIUS).to_polygon(),
+            );
+        }
+    }
+}
+
 // TODO Kind of a hack to know that the second point is a better center.
 // Returns (center, angle)
 fn crosswalk_icon(geom: &PolyLine) -> (Pt2D, Angle) {
@@ -315,8 +401,12 @@ pub fn make_signal_diagram(
 
         txt.add(Line(""));
         txt.add(Line(format!("{} phases", signal.phases.len())).small_heading());
-        txt.add(Line(format!("Signal offset: {}", signal.offset)));
-        txt.add(Line(format!("One cycle lasts {}", signal.cycle_length())));
+        if signal.actuated {
+            txt.add(Line("Actuated by detectors"));
+        } else {
+            txt.add(Line(format!("Signal offset: {}", signal.offset)));
+            txt.add(Line(format!("One cycle lasts {}", signal.cycle_length())));
+        }
         txt.draw(ctx)
     };
     let mut col = if edit_mode {
@@ -339,7 +429,7 @@ pub fn make_signal_diagram(
             Widget::draw_batch(
                 ctx,
                 GeomBatch::from(vec![(
-                    Color::WHITE,
+                    app.cs.signal_diagram_separator,
                     Polygon::rectangle(0.2 * ctx.canvas.window_width / ctx.get_scale_factor(), 2.0),
                 )]),
             )
@@ -347,6 +437,23 @@ pub fn make_signal_diagram(
             .centered_horiz(),
         );
 
+        let mut phase_label = if signal.actuated {
+            format!(
+                "Phase {}: {} to {}",
+                idx + 1,
+                phase.min_green,
+                phase.max_green
+            )
+        } else {
+            format!("Phase {}: {}", idx + 1, phase.duration)
+        };
+        if phase.leading_pedestrian_interval > Duration::ZERO {
+            phase_label = format!(
+                "{} (walk {} before vehicles)",
+                phase_label, phase.leading_pedestrian_interval
+            );
+        }
+
         let phase_btn = {
             let mut orig_batch = GeomBatch::new();
             draw_signal_phase(
@@ -360,7 +467,7 @@ pub fn make_signal_diagram(
             );
 
             let mut normal = GeomBatch::new();
-            normal.push(Color::BLACK, bbox.clone());
+            normal.push(app.cs.signal_diagram_bg, bbox.clone());
             normal.append(
                 orig_batch
                     .translate(-bounds.min_x, -bounds.min_y)
@@ -369,7 +476,10 @@ pub fn make_signal_diagram(
 
             let mut hovered = GeomBatch::new();
             hovered.append(normal.clone());
-            hovered.push(Color::RED, bbox.to_outline(Distance::meters(5.0)));
+            hovered.push(
+                app.cs.signal_diagram_hovered,
+                bbox.to_outline(Distance::meters(5.0)),
+            );
 
             Btn::custom(normal, hovered, bbox.clone())
                 .build(ctx, format!("phase {}", idx + 1), None)
@@ -379,10 +489,7 @@ pub fn make_signal_diagram(
         let phase_col = if edit_mode {
             Widget::col(vec![
                 Widget::row(vec![
-                    Line(format!("Phase {}: {}", idx + 1, phase.duration))
-                        .small_heading()
-                        .draw(ctx)
-                        .margin_right(10),
+                    Line(phase_label).small_heading().draw(ctx).margin_right(10),
                     Btn::svg_def("../data/system/assets/tools/edit.svg").build(
                         ctx,
                         format!("change duration of phase {}", idx + 1),
@@ -425,15 +532,12 @@ pub fn make_signal_diagram(
                 ]),
             ])
         } else {
-            Widget::col(vec![
-                format!("Phase {}: {}", idx + 1, phase.duration).draw_text(ctx),
-                phase_btn,
-            ])
+            Widget::col(vec![phase_label.draw_text(ctx), phase_btn])
         }
         .padding(10);
 
         if idx == selected {
-            col.push(phase_col.bg(Color::hex("#2A2A2A")));
+            col.push(phase_col.bg(app.cs.signal_diagram_selected));
         } else {
             col.push(phase_col);
         }
@@ -445,7 +549,7 @@ pub fn make_signal_diagram(
             Widget::draw_batch(
                 ctx,
                 GeomBatch::from(vec![(
-                    Color::WHITE,
+                    app.cs.signal_diagram_separator,
                     Polygon::rectangle(0.2 * ctx.canvas.window_width / ctx.get_scale_factor(), 2.0),
                 )]),
             )
@@ -454,6 +558,9 @@ pub fn make_signal_diagram(
         );
 
         col.push(Btn::text_fg("Add new phase").build_def(ctx, None));
+        if signal.turn_groups.keys().any(|g| g.crosswalk) && !signal.has_ped_scramble() {
+            col.push(Btn::text_fg("Add pedestrian scramble").build_def(ctx, None));
+        }
     }
 
     Composite::new(Widget::col(col).bg(app.cs.panel_bg).padding(10))
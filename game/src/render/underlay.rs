@@ -0,0 +1,244 @@
+use abstutil::Timer;
+use ezgui::{Color, GeomBatch};
+use geom::{Bounds, GPSBounds, LonLat, Polygon, Pt2D};
+use image::RgbaImage;
+use std::f64::consts::PI;
+use std::path::Path;
+
+// Don't draw more than this many solid blocks; bigger imagery is averaged down to fit
+const MAX_BLOCKS: usize = 500_000;
+// Use the most detailed zoom level that covers the map with at most this many XYZ tiles
+const MAX_TILES: usize = 400;
+
+// Aerial imagery or any other raster drawn beneath the map, to check that imported geometry lines
+// up with reality. There's no texture support, so the pixels are averaged into small blocks of
+// solid color. Each block is projected from its own corners, so the imagery stays aligned even
+// where its projection and ours disagree.
+//
+// The path is either:
+// - a directory of XYZ tiles in Web Mercator, laid out as {zoom}/{x}/{y}.png
+// - a PNG georeferenced by a world file next to it (foo.pgw for foo.png), in degrees, not rotated
+pub fn load_underlay(path: &str, gps_bounds: &GPSBounds, timer: &mut Timer) -> GeomBatch {
+    let rasters = if Path::new(path).is_dir() {
+        load_xyz_tiles(path, gps_bounds, timer)
+    } else {
+        load_world_file(path, timer)
+    };
+    let map_bounds = gps_bounds.to_bounds();
+
+    let total_pixels: usize = rasters
+        .iter()
+        .map(|r| (r.img.width() * r.img.height()) as usize)
+        .sum();
+    let block = ((total_pixels as f64 / MAX_BLOCKS as f64).sqrt().ceil() as u32).max(1);
+
+    let mut batch = GeomBatch::new();
+    timer.start_iter("cut underlay into blocks", rasters.len());
+    for r in rasters {
+        timer.next();
+        let (width, height) = r.img.dimensions();
+        for y1 in (0..height).step_by(block as usize) {
+            for x1 in (0..width).step_by(block as usize) {
+                let (x2, y2) = ((x1 + block).min(width), (y1 + block).min(height));
+                let color = match average_color(&r.img, x1, y1, x2, y2) {
+                    Some(c) => c,
+                    None => {
+                        continue;
+                    }
+                };
+                let pts: Vec<Pt2D> = vec![(x1, y1), (x2, y1), (x2, y2), (x1, y2)]
+                    .into_iter()
+                    .map(|(x, y)| Pt2D::forcibly_from_gps(r.to_gps(x as f64, y as f64), gps_bounds))
+                    .collect();
+                if !overlaps(&Bounds::from(&pts), &map_bounds) {
+                    continue;
+                }
+                batch.push(color, Polygon::precomputed(pts, vec![0, 1, 2, 0, 2, 3]));
+            }
+        }
+    }
+    batch
+}
+
+enum Georef {
+    // Web Mercator tile coordinates
+    Tile {
+        zoom: u32,
+        x: u32,
+        y: u32,
+    },
+    // Longitude and latitude of the upper-left corner of the image, and the size of a pixel in
+    // degrees. The latitude size is usually negative.
+    WorldFile {
+        lon: f64,
+        lat: f64,
+        dlon: f64,
+        dlat: f64,
+    },
+}
+
+struct Raster {
+    img: RgbaImage,
+    georef: Georef,
+}
+
+impl Raster {
+    // Pixel coordinates are measured from the upper-left corner of the image
+    fn to_gps(&self, px: f64, py: f64) -> LonLat {
+        match self.georef {
+            Georef::Tile { zoom, x, y } => {
+                let n = (1u64 << zoom) as f64;
+                let tx = (x as f64) + px / (self.img.width() as f64);
+                let ty = (y as f64) + py / (self.img.height() as f64);
+                let lat = (PI * (1.0 - 2.0 * ty / n)).sinh().atan().to_degrees();
+                LonLat::new(tx / n * 360.0 - 180.0, lat)
+            }
+            Georef::WorldFile {
+                lon,
+                lat,
+                dlon,
+                dlat,
+            } => LonLat::new(lon + px * dlon, lat + py * dlat),
+        }
+    }
+}
+
+fn load_xyz_tiles(dir: &str, gps_bounds: &GPSBounds, timer: &mut Timer) -> Vec<Raster> {
+    let mut corners = Vec::new();
+    let b = gps_bounds.to_bounds();
+    for pt in vec![Pt2D::new(b.min_x, b.min_y), Pt2D::new(b.max_x, b.max_y)] {
+        corners.push(pt.forcibly_to_gps(gps_bounds));
+    }
+
+    let mut zooms: Vec<u32> = abstutil::list_dir(Path::new(dir))
+        .into_iter()
+        .filter_map(|path| {
+            Path::new(&path)
+                .file_name()
+                .and_then(|x| x.to_str())
+                .and_then(|x| x.parse::<u32>().ok())
+        })
+        .collect();
+    zooms.sort();
+    let mut picked = None;
+    for zoom in zooms.into_iter().rev() {
+        let (x1, y1) = tile_at(corners[0], zoom);
+        let (x2, y2) = tile_at(corners[1], zoom);
+        let (x1, x2) = (x1.min(x2), x1.max(x2));
+        let (y1, y2) = (y1.min(y2), y1.max(y2));
+        picked = Some((zoom, x1, y1, x2, y2));
+        if ((x2 - x1 + 1) * (y2 - y1 + 1)) as usize <= MAX_TILES {
+            break;
+        }
+    }
+    let (zoom, x1, y1, x2, y2) = match picked {
+        Some(x) => x,
+        None => {
+            timer.warn(format!("No zoom levels in {}, so no underlay", dir));
+            return Vec::new();
+        }
+    };
+
+    let mut rasters = Vec::new();
+    timer.start_iter(
+        format!("load zoom {} tiles from {}", zoom, dir),
+        ((x2 - x1 + 1) * (y2 - y1 + 1)) as usize,
+    );
+    for x in x1..=x2 {
+        for y in y1..=y2 {
+            timer.next();
+            let path = format!("{}/{}/{}/{}.png", dir, zoom, x, y);
+            if !abstutil::file_exists(path.clone()) {
+                continue;
+            }
+            match image::open(&path) {
+                Ok(img) => {
+                    rasters.push(Raster {
+                        img: img.to_rgba(),
+                        georef: Georef::Tile { zoom, x, y },
+                    });
+                }
+                Err(err) => {
+                    timer.warn(format!("Skipping underlay tile {}: {}", path, err));
+                }
+            }
+        }
+    }
+    rasters
+}
+
+fn load_world_file(path: &str, timer: &mut Timer) -> Vec<Raster> {
+    let world_path = Path::new(path).with_extension("pgw");
+    let params: Vec<f64> = match std::fs::read_to_string(&world_path) {
+        Ok(contents) => contents
+            .lines()
+            .filter_map(|line| line.trim().parse::<f64>().ok())
+            .collect(),
+        Err(err) => {
+            timer.warn(format!("No underlay, can't read {:?}: {}", world_path, err));
+            return Vec::new();
+        }
+    };
+    // The pixel size in x, two rotation terms, the pixel size in y, then the center of the
+    // upper-left pixel
+    if params.len() != 6 || params[1] != 0.0 || params[2] != 0.0 {
+        timer.warn(format!(
+            "No underlay, {:?} isn't an unrotated world file",
+            world_path
+        ));
+        return Vec::new();
+    }
+    let img = match image::open(path) {
+        Ok(img) => img.to_rgba(),
+        Err(err) => {
+            timer.warn(format!("No underlay, can't load {}: {}", path, err));
+            return Vec::new();
+        }
+    };
+    vec![Raster {
+        img,
+        georef: Georef::WorldFile {
+            lon: params[4] - params[0] / 2.0,
+            lat: params[5] - params[3] / 2.0,
+            dlon: params[0],
+            dlat: params[3],
+        },
+    }]
+}
+
+// The XYZ tile containing a point
+fn tile_at(pt: LonLat, zoom: u32) -> (u32, u32) {
+    let n = (1u64 << zoom) as f64;
+    let lat = pt.y().to_radians();
+    let x = (pt.x() + 180.0) / 360.0 * n;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * n;
+    let max = n - 1.0;
+    (x.max(0.0).min(max) as u32, y.max(0.0).min(max) as u32)
+}
+
+// None if the block is completely transparent
+fn average_color(img: &RgbaImage, x1: u32, y1: u32, x2: u32, y2: u32) -> Option<Color> {
+    let mut sum = [0u64; 4];
+    for y in y1..y2 {
+        for x in x1..x2 {
+            let px = img.get_pixel(x, y);
+            for (total, value) in sum.iter_mut().zip(px.0.iter()) {
+                *total += *value as u64;
+            }
+        }
+    }
+    if sum[3] == 0 {
+        return None;
+    }
+    let cnt = ((x2 - x1) * (y2 - y1)) as u64;
+    Some(Color::rgba(
+        (sum[0] / cnt) as usize,
+        (sum[1] / cnt) as usize,
+        (sum[2] / cnt) as usize,
+        (sum[3] as f32) / (cnt as f32) / 255.0,
+    ))
+}
+
+fn overlaps(b1: &Bounds, b2: &Bounds) -> bool {
+    b1.min_x <= b2.max_x && b1.max_x >= b2.min_x && b1.min_y <= b2.max_y && b1.max_y >= b2.min_y
+}
@@ -1,11 +1,16 @@
 use crate::app::App;
-use crate::common::Tab;
+use crate::common::{Tab, Warping};
 use crate::game::{msg, DrawBaselayer, State, Transition};
+use crate::helpers::ID;
 use crate::sandbox::dashboards::DashTab;
 use crate::sandbox::SandboxMode;
+use abstutil::prettyprint_usize;
 use ezgui::{
-    Btn, Composite, EventCtx, GfxCtx, Line, LinePlot, Outcome, PlotOptions, Series, Widget,
+    Btn, Composite, EventCtx, GfxCtx, Line, LinePlot, Outcome, PlotOptions, Series, Text, TextExt,
+    Widget,
 };
+use map_model::BikeFacility;
+use sim::{Gridlock, Measure};
 
 pub struct ActiveTraffic {
     composite: Composite,
@@ -32,17 +37,68 @@ impl ActiveTraffic {
             });
         }
 
+        let mut col = vec![
+            DashTab::ActiveTraffic.picker(ctx, app),
+            LinePlot::new(ctx, active_agents, PlotOptions::fixed()),
+        ];
+        let response_times = app
+            .primary
+            .sim
+            .get_analytics()
+            .emergency_response_times(app.primary.sim.time());
+        if response_times.count() > 0 {
+            col.push(
+                Line(format!(
+                    "Emergency response times: {}",
+                    response_times.describe()
+                ))
+                .draw(ctx)
+                .margin_above(10),
+            );
+        }
+
+        let analytics = app.primary.sim.get_analytics();
+        let door_zone_conflicts =
+            analytics.door_zone_conflicts_per_facility(app.primary.sim.time(), &app.primary.map);
+        for (facility, (dist, time)) in &analytics.bike_facility_usage {
+            col.push(
+                Line(format!(
+                    "Bikes in {} lanes: {} in {} ({} door zone conflicts)",
+                    match facility {
+                        BikeFacility::Shared => "shared",
+                        BikeFacility::Painted => "painted bike",
+                        BikeFacility::Protected => "protected bike",
+                    },
+                    dist,
+                    time,
+                    prettyprint_usize(door_zone_conflicts.get(*facility))
+                ))
+                .draw(ctx)
+                .margin_above(10),
+            );
+        }
+        let passed = analytics
+            .bike_passes
+            .iter()
+            .filter(|(_, _, _, _, passed)| *passed)
+            .count();
+        let stuck = analytics.bike_passes.len() - passed;
+        if passed + stuck > 0 {
+            col.push(
+                Line(format!(
+                    "Drivers passed bikes {} times and got stuck behind them {} times",
+                    prettyprint_usize(passed),
+                    prettyprint_usize(stuck)
+                ))
+                .draw(ctx)
+                .margin_above(10),
+            );
+        }
+
         Box::new(ActiveTraffic {
-            composite: Composite::new(
-                Widget::col(vec![
-                    DashTab::ActiveTraffic.picker(ctx, app),
-                    LinePlot::new(ctx, active_agents, PlotOptions::fixed()),
-                ])
-                .bg(app.cs.panel_bg)
-                .padding(10),
-            )
-            .exact_size_percent(90, 90)
-            .build(ctx),
+            composite: Composite::new(Widget::col(col).bg(app.cs.panel_bg).padding(10))
+                .exact_size_percent(90, 90)
+                .build(ctx),
         })
     }
 }
@@ -138,3 +194,280 @@ impl State for BusRoutes {
         self.composite.draw(g);
     }
 }
+
+pub struct CustomMetrics {
+    composite: Composite,
+}
+
+impl CustomMetrics {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State> {
+        let now = app.primary.sim.time();
+        let mut col = vec![
+            DashTab::CustomMetrics.picker(ctx, app),
+            Btn::text_fg("export to JSON").build_def(ctx, None),
+        ];
+        for m in &app.primary.sim.get_analytics().custom_metrics {
+            col.push(
+                Line(&m.metric.name)
+                    .small_heading()
+                    .draw(ctx)
+                    .margin_above(10),
+            );
+            let label = format!("{:?}", m.metric.event);
+            col.push(match m.metric.measure {
+                Measure::Count => LinePlot::new(
+                    ctx,
+                    vec![Series {
+                        label,
+                        color: app.cs.after_changes,
+                        pts: m.count_over_time(now),
+                    }],
+                    PlotOptions::fixed(),
+                ),
+                Measure::TotalDuration => LinePlot::new(
+                    ctx,
+                    vec![Series {
+                        label,
+                        color: app.cs.after_changes,
+                        pts: m.total_over_time(now),
+                    }],
+                    PlotOptions::fixed(),
+                ),
+            });
+        }
+
+        Box::new(CustomMetrics {
+            composite: Composite::new(Widget::col(col).bg(app.cs.panel_bg).padding(10))
+                .exact_size_percent(90, 90)
+                .build(ctx),
+        })
+    }
+}
+
+impl State for CustomMetrics {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "export to JSON" => {
+                    let path = format!(
+                        "../data/player/metrics_{}_{}.json",
+                        app.primary.map.get_name(),
+                        app.primary.sim.time().as_filename()
+                    );
+                    app.primary
+                        .sim
+                        .get_analytics()
+                        .export_custom_metrics(path.clone());
+                    Transition::Push(msg("Metrics exported", vec![format!("Saved to {}", path)]))
+                }
+                _ => DashTab::CustomMetrics.transition(ctx, app, &x),
+            },
+            None => Transition::Keep,
+        }
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.clear(app.cs.grass);
+        self.composite.draw(g);
+    }
+}
+
+pub struct DetectorReadings {
+    composite: Composite,
+}
+
+impl DetectorReadings {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State> {
+        let mut col = vec![
+            DashTab::Detectors.picker(ctx, app),
+            Btn::text_fg("export to CSV").build_def(ctx, None),
+        ];
+        let readings = &app.primary.sim.get_analytics().detector_readings;
+        for (id, d) in app.primary.sim.get_detectors().into_iter().enumerate() {
+            col.push(
+                Line(format!("Detector #{} on {}", id, d.lane))
+                    .small_heading()
+                    .draw(ctx)
+                    .margin_above(10),
+            );
+            let mut flow = Vec::new();
+            let mut occupancy = Vec::new();
+            for (time, _, _, count, occ) in readings.iter().filter(|r| r.1 == id) {
+                flow.push((*time, *count));
+                occupancy.push((*time, (occ * 100.0).round() as usize));
+            }
+            col.push(Widget::row(vec![
+                LinePlot::new(
+                    ctx,
+                    vec![Series {
+                        label: "vehicles per interval".to_string(),
+                        color: app.cs.after_changes,
+                        pts: flow,
+                    }],
+                    PlotOptions::fixed(),
+                ),
+                LinePlot::new(
+                    ctx,
+                    vec![Series {
+                        label: "percent occupied".to_string(),
+                        color: app.cs.after_changes,
+                        pts: occupancy,
+                    }],
+                    PlotOptions::fixed(),
+                ),
+            ]));
+        }
+
+        Box::new(DetectorReadings {
+            composite: Composite::new(Widget::col(col).bg(app.cs.panel_bg).padding(10))
+                .exact_size_percent(90, 90)
+                .build(ctx),
+        })
+    }
+}
+
+impl State for DetectorReadings {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "export to CSV" => {
+                    let path = format!(
+                        "../data/player/detectors_{}_{}.csv",
+                        app.primary.map.get_name(),
+                        app.primary.sim.time().as_filename()
+                    );
+                    app.primary
+                        .sim
+                        .get_analytics()
+                        .export_detector_readings(path.clone(), &app.primary.map);
+                    Transition::Push(msg(
+                        "Detector readings exported",
+                        vec![format!("Saved to {}", path)],
+                    ))
+                }
+                _ => DashTab::Detectors.transition(ctx, app, &x),
+            },
+            None => Transition::Keep,
+        }
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.clear(app.cs.grass);
+        self.composite.draw(g);
+    }
+}
+
+pub struct GridlockReport {
+    composite: Composite,
+}
+
+impl GridlockReport {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State> {
+        let map = &app.primary.map;
+        let mut col = vec![
+            DashTab::Gridlock.picker(ctx, app),
+            Btn::text_fg("export report").build_def(ctx, None),
+        ];
+        for (idx, gridlock) in app.primary.sim.get_gridlocks().iter().enumerate() {
+            col.push(
+                Line(format!("Gridlock #{}", idx))
+                    .small_heading()
+                    .draw(ctx)
+                    .margin_above(10),
+            );
+            col.push(gridlock.describe().draw_text(ctx));
+            col.push(Btn::text_fg(format!("jump to gridlock #{}", idx)).build_def(ctx, None));
+
+            let mut txt = Text::new();
+            for i in &gridlock.intersections {
+                txt.add(Line(format!("{} ({})", i, map.get_i(*i).name(map))));
+                for t in gridlock.turns.iter().filter(|t| t.parent == *i) {
+                    txt.add(Line(format!(
+                        "  - {:?} turn from {} to {}",
+                        map.get_t(*t).turn_type,
+                        map.get_parent(t.src).get_name(),
+                        map.get_parent(t.dst).get_name()
+                    )));
+                }
+            }
+            txt.add(Line(format!(
+                "Vehicles, each waiting on the next: {}",
+                gridlock
+                    .cars
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+            col.push(txt.draw(ctx));
+        }
+
+        Box::new(GridlockReport {
+            composite: Composite::new(Widget::col(col).bg(app.cs.panel_bg).padding(10))
+                .exact_size_percent(90, 90)
+                .build(ctx),
+        })
+    }
+}
+
+impl State for GridlockReport {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "export report" => {
+                    let path = format!(
+                        "../data/player/gridlock_{}_{}.json",
+                        app.primary.map.get_name(),
+                        app.primary.sim.time().as_filename()
+                    );
+                    abstutil::write_json(path.clone(), app.primary.sim.get_gridlocks());
+                    Transition::Push(msg(
+                        "Gridlock report exported",
+                        vec![format!("Saved to {}", path)],
+                    ))
+                }
+                x if x.starts_with("jump to gridlock #") => {
+                    let idx = x["jump to gridlock #".len()..].parse::<usize>().unwrap();
+                    let gridlock = app.primary.sim.get_gridlocks()[idx].clone();
+                    jump_to_gridlock(ctx, app, &gridlock)
+                }
+                _ => DashTab::Gridlock.transition(ctx, app, &x),
+            },
+            None => Transition::Keep,
+        }
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.clear(app.cs.grass);
+        self.composite.draw(g);
+    }
+}
+
+// Replaces the current state with a warp to the first intersection involved
+pub fn jump_to_gridlock(ctx: &mut EventCtx, app: &mut App, gridlock: &Gridlock) -> Transition {
+    match gridlock.intersections.iter().next() {
+        Some(i) => {
+            let id = ID::Intersection(*i);
+            Transition::Replace(Warping::new(
+                ctx,
+                id.canonical_point(&app.primary).unwrap(),
+                Some(10.0),
+                Some(id),
+                &mut app.primary,
+            ))
+        }
+        None => Transition::Pop,
+    }
+}
@@ -6,6 +6,7 @@ mod trip_table;
 use crate::app::App;
 use crate::game::Transition;
 use ezgui::{hotkey, Btn, Color, EventCtx, Key, Widget};
+pub use misc::{jump_to_gridlock, GridlockReport};
 pub use trip_table::TripTable;
 
 // Oh the dashboards melted, but we still had the radio
@@ -16,6 +17,9 @@ pub enum DashTab {
     ParkingOverhead,
     ActiveTraffic,
     BusRoutes,
+    CustomMetrics,
+    Detectors,
+    Gridlock,
 }
 
 impl DashTab {
@@ -27,10 +31,24 @@ impl DashTab {
             ("parking overhead", DashTab::ParkingOverhead),
             ("active traffic", DashTab::ActiveTraffic),
             ("bus routes", DashTab::BusRoutes),
+            ("custom metrics", DashTab::CustomMetrics),
+            ("detectors", DashTab::Detectors),
+            ("gridlock", DashTab::Gridlock),
         ] {
             if tab == DashTab::TripSummaries && app.has_prebaked().is_none() {
                 continue;
             }
+            if tab == DashTab::CustomMetrics
+                && app.primary.sim.get_analytics().custom_metrics.is_empty()
+            {
+                continue;
+            }
+            if tab == DashTab::Detectors && app.primary.sim.get_detectors().is_empty() {
+                continue;
+            }
+            if tab == DashTab::Gridlock && app.primary.sim.get_gridlocks().is_empty() {
+                continue;
+            }
             if self == tab {
                 row.push(Btn::text_bg2(name).inactive(ctx));
             } else {
@@ -61,6 +79,9 @@ impl DashTab {
             }
             "active traffic" => Transition::Replace(misc::ActiveTraffic::new(ctx, app)),
             "bus routes" => Transition::Replace(misc::BusRoutes::new(ctx, app)),
+            "custom metrics" => Transition::Replace(misc::CustomMetrics::new(ctx, app)),
+            "detectors" => Transition::Replace(misc::DetectorReadings::new(ctx, app)),
+            "gridlock" => Transition::Replace(misc::GridlockReport::new(ctx, app)),
             _ => unreachable!(),
         }
     }
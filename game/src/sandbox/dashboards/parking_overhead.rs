@@ -267,29 +267,74 @@ fn make(ctx: &mut EventCtx, app: &App, opts: &Options) -> Composite {
         btn(SortBy::PercentOverhead, "Percent overhead"),
     ];
 
+    let analytics = app.primary.sim.get_analytics();
+    let now = app.primary.sim.time();
+    let revenue = analytics.total_parking_revenue(now);
+    let cruising = analytics.parking_cruising_totals(now);
+    let drop_offs = analytics.drop_off_totals(now);
+    let deliveries = analytics.delivery_totals(now);
+    let ridehail = app.primary.sim.get_ridehail_status();
+
     let mut col = vec![DashTab::ParkingOverhead.picker(ctx, app)];
+    let mut summary = Text::from_multiline(vec![
+        Line(
+            "Trips taken by car also include time to walk between the building and \
+             parking spot, as well as the time to find parking.",
+        ),
+        Line("Overhead is 1 - driving time / total time"),
+        Line("Ideally, overhead is 0% -- the entire trip is just spent driving."),
+        Line(""),
+        Line("High overhead could mean:"),
+        Line("- the car burned more resources and caused more traffic looking for parking"),
+        Line("- somebody with impaired movement had to walk far to reach their vehicle"),
+        Line("- the person was inconvenienced"),
+        Line(""),
+        Line(
+            "Note: Trips beginning/ending outside the map have an artifically high \
+             overhead,",
+        ),
+        Line("since the time spent driving off-map isn't shown here."),
+        Line(""),
+        Line(format!(
+            "Average time spent cruising for parking: {}",
+            analytics
+                .average_cruising_time(now)
+                .map(|dt| dt.to_string())
+                .unwrap_or_else(|| "nobody has parked yet".to_string())
+        )),
+        Line(format!(
+            "{} drivers circled around looking for parking, wasting {} driving and {}",
+            prettyprint_usize(cruising.0),
+            cruising.1.describe_rounded(),
+            cruising.2
+        )),
+        Line(format!(
+            "{} drop-offs used a pickup / drop-off zone, {} stopped in the lane",
+            prettyprint_usize(drop_offs.0),
+            prettyprint_usize(drop_offs.1)
+        )),
+        Line(format!(
+            "{} deliveries used a loading zone, {} double-parked",
+            prettyprint_usize(deliveries.0),
+            prettyprint_usize(deliveries.1)
+        )),
+        Line(format!(
+            "On-street parking revenue so far: ${}.{:02}",
+            revenue / 100,
+            revenue % 100
+        )),
+    ]);
+    if let Some((idle, waiting)) = ridehail {
+        summary.add(Line(format!(
+            "Ride-hail vehicles drove {} without a passenger. {} idle, {} people waiting",
+            analytics.total_ridehail_deadheading(now).describe_rounded(),
+            prettyprint_usize(idle),
+            prettyprint_usize(waiting)
+        )));
+    }
     col.push(
         Widget::row(vec![
-            Text::from_multiline(vec![
-                Line(
-                    "Trips taken by car also include time to walk between the building and \
-                     parking spot, as well as the time to find parking.",
-                ),
-                Line("Overhead is 1 - driving time / total time"),
-                Line("Ideally, overhead is 0% -- the entire trip is just spent driving."),
-                Line(""),
-                Line("High overhead could mean:"),
-                Line("- the car burned more resources and caused more traffic looking for parking"),
-                Line("- somebody with impaired movement had to walk far to reach their vehicle"),
-                Line("- the person was inconvenienced"),
-                Line(""),
-                Line(
-                    "Note: Trips beginning/ending outside the map have an artifically high \
-                     overhead,",
-                ),
-                Line("since the time spent driving off-map isn't shown here."),
-            ])
-            .draw(ctx),
+            summary.draw(ctx),
             Filler::new(ScreenDims::new(
                 0.15 * ctx.canvas.window_width,
                 0.15 * ctx.canvas.window_width,
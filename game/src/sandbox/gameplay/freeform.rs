@@ -1,7 +1,7 @@
 use crate::app::{App, ShowEverything};
 use crate::common::{CityPicker, CommonState};
 use crate::edit::EditMode;
-use crate::game::{State, Transition, WizardState};
+use crate::game::{msg, State, Transition, WizardState};
 use crate::helpers::{nice_map_name, ID};
 use crate::sandbox::gameplay::{GameplayMode, GameplayState};
 use crate::sandbox::SandboxControls;
@@ -18,10 +18,13 @@ use map_model::{
 use rand::seq::SliceRandom;
 use rand::Rng;
 use sim::{
-    DontDrawAgents, DrivingGoal, IndividTrip, PersonID, PersonSpec, Scenario, SidewalkSpot,
-    SpawnTrip, TripEndpoint, TripMode, TripSpec,
+    closest_emergency_station, is_emergency_station, DontDrawAgents, DrivingGoal, IndividTrip,
+    PersonID, PersonSpec, Scenario, SidewalkSpot, SpawnTrip, TripEndpoint, TripMode, TripSpec,
 };
 
+// How many trips to send from a station when measuring response times
+const NUM_TEST_RESPONSES: usize = 10;
+
 // TODO Maybe remember what things were spawned, offer to replay this later
 pub struct Freeform {
     top_center: Composite,
@@ -576,9 +579,21 @@ pub fn spawn_agents_around(i: IntersectionID, app: &mut App) {
     sim.tiny_step(map, &mut app.primary.sim_cb);
 }
 
-pub fn actions(_: &App, id: ID) -> Vec<(Key, String)> {
+pub fn actions(app: &App, id: ID) -> Vec<(Key, String)> {
     match id {
-        ID::Building(_) => vec![(Key::Z, "start a trip here".to_string())],
+        ID::Building(b) => {
+            let mut actions = vec![
+                (Key::Z, "start a trip here".to_string()),
+                (Key::X, "send an emergency vehicle here".to_string()),
+            ];
+            if is_emergency_station(app.primary.map.get_b(b)) {
+                actions.push((
+                    Key::C,
+                    "send emergency vehicles to random places".to_string(),
+                ));
+            }
+            actions
+        }
         ID::Intersection(_) => vec![(Key::Z, "spawn agents here".to_string())],
         _ => Vec::new(),
     }
@@ -589,6 +604,41 @@ pub fn execute(ctx: &mut EventCtx, app: &mut App, id: ID, action: String) -> Tra
         (ID::Building(b), "start a trip here") => {
             Transition::Push(AgentSpawner::new(ctx, app, Some(b)))
         }
+        (ID::Building(b), "send an emergency vehicle here") => {
+            let result = match closest_emergency_station(&app.primary.map, b) {
+                Some(station) => {
+                    app.primary
+                        .sim
+                        .dispatch_emergency_vehicle(station, b, &app.primary.map)
+                }
+                None => Err("There are no fire or ambulance stations on this map".to_string()),
+            };
+            match result {
+                Ok(_) => {
+                    app.primary
+                        .sim
+                        .tiny_step(&app.primary.map, &mut app.primary.sim_cb);
+                    Transition::Keep
+                }
+                Err(err) => Transition::Push(msg("Can't send an emergency vehicle", vec![err])),
+            }
+        }
+        (ID::Building(b), "send emergency vehicles to random places") => {
+            let map = &app.primary.map;
+            let mut failures = Vec::new();
+            for _ in 0..NUM_TEST_RESPONSES {
+                let scene = map.all_buildings().choose(&mut app.primary.rng).unwrap().id;
+                if let Err(err) = app.primary.sim.dispatch_emergency_vehicle(b, scene, map) {
+                    failures.push(err);
+                }
+            }
+            app.primary.sim.tiny_step(map, &mut app.primary.sim_cb);
+            if failures.is_empty() {
+                Transition::Keep
+            } else {
+                Transition::Push(msg("Some emergency vehicles couldn't go", failures))
+            }
+        }
         (ID::Intersection(id), "spawn agents here") => {
             spawn_agents_around(id, app);
             Transition::Keep
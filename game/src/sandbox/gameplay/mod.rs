@@ -157,7 +157,17 @@ impl GameplayMode {
             match cmd {
                 EditCmd::ChangeLaneType { .. }
                 | EditCmd::ReverseLane { .. }
-                | EditCmd::ChangeSpeedLimit { .. } => {
+                | EditCmd::ChangeLaneWidth { .. }
+                | EditCmd::ChangeSpeedLimit { .. }
+                | EditCmd::ChangeTimedSpeedLimits { .. }
+                | EditCmd::ChangeSnowRoute { .. }
+                | EditCmd::ConsolidateJunction { .. }
+                | EditCmd::ChangeCurbRamps { .. }
+                | EditCmd::ChangeFootbridges { .. }
+                | EditCmd::ChangeMedian { .. }
+                | EditCmd::SignDetour { .. }
+                | EditCmd::ChangeParkingRestrictions { .. }
+                | EditCmd::ChangeScooterParking { .. } => {
                     if !self.can_edit_lanes() {
                         return false;
                     }
@@ -18,15 +18,18 @@ use ezgui::{
 };
 use geom::{ArrowCap, Distance, Duration, PolyLine, Polygon, Pt2D, Time};
 use map_model::raw::{OriginalIntersection, OriginalRoad};
-use map_model::{BuildingID, Map, OriginalLane, Position};
+use map_model::{BuildingID, Map, OriginalLane, Position, RoadID};
 use sim::{
-    AgentID, Analytics, BorderSpawnOverTime, CarID, DrivingGoal, IndividTrip, OriginDestination,
-    PersonID, PersonSpec, Scenario, ScenarioGenerator, SpawnOverTime, SpawnTrip, VehicleType,
+    AgentID, Analytics, BorderSpawnOverTime, CarID, DrivingGoal, Event, IndividTrip,
+    OriginDestination, PersonID, PersonSpec, Scenario, ScenarioGenerator, Sim, SimCallback,
+    SpawnOverTime, SpawnTrip, VehicleType,
 };
 use std::collections::BTreeSet;
 
 const ESCORT: CarID = CarID(0, VehicleType::Car);
 const CAR_BIKE_CONTENTION_GOAL: Duration = Duration::const_seconds(60.0);
+// How often to check triggers that depend on the state of the sim, not on events.
+const TRIGGER_POLL_FREQUENCY: Duration = Duration::const_seconds(5.0);
 
 pub struct Tutorial {
     top_center: Composite,
@@ -34,6 +37,8 @@ pub struct Tutorial {
 
     msg_panel: Option<Composite>,
     warped: bool,
+    // The stage's SimTrigger fired while this state was active
+    trigger_fired: bool,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -152,6 +157,43 @@ impl Tutorial {
             }
         }
 
+        // Wait on something happening in the sim
+        if tut.interaction() != Task::Nil {
+            if let Some(trigger) = tut.stage().trigger {
+                if app.primary.sim_cb.is_none() {
+                    app.primary.sim_cb = Some(Box::new(TriggerCallback {
+                        trigger,
+                        fired: false,
+                    }));
+                    app.primary
+                        .sim
+                        .set_periodic_callback(TRIGGER_POLL_FREQUENCY);
+                }
+                let fired = app
+                    .primary
+                    .sim_cb
+                    .as_ref()
+                    .and_then(|cb| cb.downcast_ref::<TriggerCallback>())
+                    .map(|cb| cb.fired)
+                    .unwrap_or(false);
+                if fired && !self.trigger_fired {
+                    self.trigger_fired = true;
+                    if let Some(ref mut speed) = controls.speed {
+                        speed.pause(ctx, app);
+                    }
+                    if tut.interaction() == Task::WatchBikes {
+                        return Some(Transition::Push(msg(
+                            "Traffic is backing up",
+                            vec![
+                                "Look at all the cars and bikes stuck on this road!",
+                                "Take a look around before moving on.",
+                            ],
+                        )));
+                    }
+                }
+            }
+        }
+
         // Interaction things
         if tut.interaction() == Task::Camera {
             if app.primary.current_selection == Some(ID::Building(tut.fire_station))
@@ -219,11 +261,8 @@ impl Tutorial {
         } else if tut.interaction() == Task::Escort {
             let following_car =
                 controls.common.as_ref().unwrap().info_panel_open(app) == Some(ID::Car(ESCORT));
-            let is_parked = app
-                .primary
-                .sim
-                .agent_to_trip(AgentID::Car(ESCORT))
-                .is_none();
+            // The trigger fires when the target parks
+            let is_parked = self.trigger_fired;
             if !tut.car_parked && is_parked && tut.following_car {
                 tut.car_parked = true;
                 self.top_center = tut.make_top_center(ctx, &app.cs, false);
@@ -246,7 +285,8 @@ impl Tutorial {
                 return Some(transition(ctx, app, tut));
             }
         } else if tut.interaction() == Task::WatchBikes {
-            if app.primary.sim.time() >= Time::START_OF_DAY + Duration::minutes(3) {
+            // The popup explaining the trigger has been dismissed by now
+            if self.trigger_fired || app.primary.sim.is_done() {
                 tut.next();
                 return Some(transition(ctx, app, tut));
             }
@@ -389,6 +429,19 @@ impl GameplayState for Tutorial {
         }
     }
 
+    fn on_destroy(&self, app: &mut App) {
+        if app
+            .primary
+            .sim_cb
+            .as_ref()
+            .map(|cb| cb.is::<TriggerCallback>())
+            .unwrap_or(false)
+        {
+            app.primary.sim_cb = None;
+            app.primary.sim.unset_periodic_callback();
+        }
+    }
+
     fn can_move_canvas(&self) -> bool {
         self.msg_panel.is_none()
     }
@@ -489,7 +542,7 @@ impl Task {
                 txt.append(Line(" to check the occupancy"));
                 return txt;
             }
-            Task::WatchBikes => "Watch for 3 minutes",
+            Task::WatchBikes => "Watch until traffic backs up",
             Task::FixBikes => {
                 return Text::from(Line(format!(
                     "[ ] Complete all trips {} faster",
@@ -526,6 +579,56 @@ struct Stage {
     task: Task,
     warp_to: Option<(ID, f64)>,
     spawn: Option<Box<dyn Fn(&mut App)>>,
+    // While the task is active, pause the sim the moment this happens
+    trigger: Option<SimTrigger>,
+}
+
+// Something happening in the simulation that a stage waits on, instead of a scripted time
+#[derive(Clone, Copy)]
+enum SimTrigger {
+    // At least this many vehicles are on the road at once
+    VehiclesOnRoad(RoadID, usize),
+    CarParks(CarID),
+}
+
+// Installed as the sim callback while a stage with a trigger is active. Halts the sim the moment
+// the trigger first fires.
+struct TriggerCallback {
+    trigger: SimTrigger,
+    fired: bool,
+}
+
+impl TriggerCallback {
+    fn fire(&mut self) -> bool {
+        if self.fired {
+            return false;
+        }
+        self.fired = true;
+        true
+    }
+}
+
+impl SimCallback for TriggerCallback {
+    fn run(&mut self, sim: &Sim, map: &Map) -> bool {
+        match self.trigger {
+            SimTrigger::VehiclesOnRoad(r, threshold) => {
+                if sim.num_vehicles_on_road(r, map) >= threshold {
+                    return self.fire();
+                }
+            }
+            SimTrigger::CarParks(_) => {}
+        }
+        false
+    }
+
+    fn event(&mut self, ev: &Event, _: Time, _: &Map) -> bool {
+        match (self.trigger, ev) {
+            (SimTrigger::CarParks(car), Event::CarReachedParkingSpot(c, _)) if car == *c => {
+                self.fire()
+            }
+            _ => false,
+        }
+    }
 }
 
 fn arrow(pt: ScreenPt) -> Option<Box<dyn Fn(&GfxCtx, &App) -> Pt2D>> {
@@ -539,6 +642,7 @@ impl Stage {
             task,
             warp_to: None,
             spawn: None,
+            trigger: None,
         }
     }
 
@@ -573,6 +677,12 @@ impl Stage {
         self
     }
 
+    fn pause_when(mut self, trigger: SimTrigger) -> Stage {
+        assert!(self.trigger.is_none());
+        self.trigger = Some(trigger);
+        self
+    }
+
     fn spawn(mut self, cb: Box<dyn Fn(&mut App)>) -> Stage {
         assert!(self.spawn.is_none());
         self.spawn = Some(cb);
@@ -639,7 +749,9 @@ fn make_bike_lane_scenario(map: &Map) -> ScenarioGenerator {
         num_peds: 0,
         num_cars: 10,
         num_bikes: 10,
+        num_trucks: 0,
         percent_use_transit: 0.0,
+        percent_heavy_trucks: 0.0,
         start_time: Time::START_OF_DAY,
         stop_time: Time::START_OF_DAY + Duration::seconds(10.0),
         start_from_border: map
@@ -883,6 +995,7 @@ impl TutorialState {
                 None
             },
             warped: false,
+            trigger_fired: false,
         })
     }
 
@@ -1038,6 +1151,7 @@ impl TutorialState {
 
         state.stages.push(
             Stage::new(Task::Escort)
+                .pause_when(SimTrigger::CarParks(ESCORT))
                 // Don't center on where the agents are, be a little offset
                 .warp_to(
                     ID::Building(map.find_b_by_osm_id(217699780).unwrap()),
@@ -1185,6 +1299,7 @@ impl TutorialState {
                             percent_driving: 1.0,
                             percent_biking: 0.0,
                             percent_use_transit: 0.0,
+                            percent_ridehail: 0.0,
                         }],
                         border_spawn_over_time: Vec::new(),
                     }
@@ -1232,11 +1347,15 @@ impl TutorialState {
 
         let bike_lane_scenario = make_bike_lane_scenario(map);
         let bike_lane_focus_pt = map.find_b_by_osm_id(217699496).unwrap();
+        let bike_lane_road = map
+            .find_r_by_osm_id(263665925, (2499826475, 53096959))
+            .unwrap();
 
         state.stages.push(
             Stage::new(Task::WatchBikes)
                 .warp_to(ID::Building(bike_lane_focus_pt), None)
                 .spawn_scenario(bike_lane_scenario.clone())
+                .pause_when(SimTrigger::VehiclesOnRoad(bike_lane_road, 10))
                 .msg(
                     vec![
                         "Well done!",
@@ -72,9 +72,8 @@ impl ShowTrafficSignal {
     pub fn new(ctx: &mut EventCtx, app: &App, i: IntersectionID) -> Box<dyn State> {
         let (idx, _, _) = app
             .primary
-            .map
-            .get_traffic_signal(i)
-            .current_phase_and_remaining_time(app.primary.sim.time());
+            .sim
+            .current_phase_and_remaining_time(i, &app.primary.map);
         return Box::new(ShowTrafficSignal {
             i,
             composite: make_signal_diagram(ctx, app, i, idx, false),
@@ -291,6 +290,16 @@ impl TurnExplorer {
                     color_turn_type(TurnType::SharedSidewalkCorner),
                     "sidewalk connection",
                 ));
+                col.push(ColorLegend::row(
+                    ctx,
+                    color_turn_type(TurnType::DiagonalCrosswalk),
+                    "diagonal crosswalk (only during all-walk)",
+                ));
+                col.push(ColorLegend::row(
+                    ctx,
+                    color_turn_type(TurnType::Footbridge),
+                    "footbridge or underpass",
+                ));
             } else {
                 col.push(ColorLegend::row(
                     ctx,
@@ -334,6 +343,8 @@ fn color_turn_type(t: TurnType) -> Color {
     match t {
         TurnType::SharedSidewalkCorner => Color::BLACK,
         TurnType::Crosswalk => Color::WHITE,
+        TurnType::DiagonalCrosswalk => Color::grey(0.7),
+        TurnType::Footbridge => Color::ORANGE,
         TurnType::Straight => Color::BLUE,
         TurnType::LaneChangeLeft => Color::CYAN,
         TurnType::LaneChangeRight => Color::PURPLE,
@@ -1,6 +1,7 @@
 mod dashboards;
 pub mod gameplay;
 mod misc_tools;
+mod replay;
 mod speed;
 mod uber_turns;
 
@@ -12,9 +13,9 @@ use crate::edit::{
     apply_map_edits, can_edit_lane, save_edits_as, EditMode, LaneEditor, StopSignEditor,
     TrafficSignalEditor,
 };
-use crate::game::{State, Transition, WizardState};
+use crate::game::{msg, State, Transition, WizardState};
 use crate::helpers::ID;
-use crate::layer::PickLayer;
+use crate::layer::{PickLayer, PinnedLayer};
 use crate::managed::{WrappedComposite, WrappedOutcome};
 use crate::pregame::MainMenu;
 use crate::render::AgentColorScheme;
@@ -23,11 +24,11 @@ use ezgui::{
     HorizontalAlignment, Key, Line, Outcome, Text, TextExt, VerticalAlignment, Widget, Wizard,
 };
 pub use gameplay::{spawn_agents_around, GameplayMode, TutorialPointer, TutorialState};
-use geom::{Polygon, Time};
-use map_model::MapEdits;
-use sim::{TripMode, VehicleType};
-pub use speed::TimeWarpScreen;
+use geom::{Duration, FindClosest, Polygon, Time};
+use map_model::{MapEdits, ObjectNote, PathConstraints};
+use sim::{Detector, Incident, TripMode, VehicleType};
 pub use speed::{SpeedControls, TimePanel};
+pub use speed::{TimeWarpScreen, WarpTrigger};
 
 pub struct SandboxMode {
     gameplay: Box<dyn gameplay::GameplayState>,
@@ -189,6 +190,7 @@ impl State for SandboxMode {
             .as_ref()
             .map(|s| s.is_paused())
             .unwrap_or(true)
+            && !app.layer.as_ref().map(|l| l.animated()).unwrap_or(false)
         {
             Transition::Keep
         } else {
@@ -197,6 +199,7 @@ impl State for SandboxMode {
     }
 
     fn draw(&self, g: &mut GfxCtx, app: &App) {
+        PinnedLayer::draw_all(g, app);
         if let Some(ref l) = app.layer {
             l.draw(g, app);
         }
@@ -230,6 +233,7 @@ impl State for SandboxMode {
 
     fn on_destroy(&mut self, _: &mut EventCtx, app: &mut App) {
         app.layer = None;
+        app.pinned_layers.clear();
         app.agent_cs = AgentColorScheme::new(&app.cs);
         self.gameplay.on_destroy(app);
     }
@@ -392,6 +396,17 @@ impl ContextualActions for Actions {
                     if app.opts.dev {
                         actions.push((Key::U, "explore uber-turns".to_string()));
                     }
+                    if !app.primary.map.get_i(i).is_border() {
+                        if app
+                            .primary
+                            .sim
+                            .enforces_dont_block_the_box(i, &app.primary.map)
+                        {
+                            actions.push((Key::B, "allow blocking the box".to_string()));
+                        } else {
+                            actions.push((Key::B, "enforce don't block the box".to_string()));
+                        }
+                    }
                 }
                 ID::Lane(l) => {
                     if !app.primary.map.get_turns_from_lane(l).is_empty() {
@@ -400,6 +415,10 @@ impl ContextualActions for Actions {
                     if can_edit_lane(&self.gameplay, l, app) {
                         actions.push((Key::E, "edit lane".to_string()));
                     }
+                    if app.primary.map.get_l(l).lane_type.is_for_moving_vehicles() {
+                        actions.push((Key::D, "place a detector here".to_string()));
+                        actions.push((Key::I, "close for an incident".to_string()));
+                    }
                 }
                 ID::Car(c) => {
                     if c.1 == VehicleType::Bus {
@@ -407,9 +426,18 @@ impl ContextualActions for Actions {
                         actions.push((Key::R, "show route".to_string()));
                     }
                 }
+                ID::Building(_) => {
+                    actions.push((Key::I, "show isochrones".to_string()));
+                }
+                ID::BusStop(_) => {
+                    actions.push((Key::A, "show arrivals board".to_string()));
+                }
                 _ => {}
             }
         }
+        if id.note_target(&app.primary.map).is_some() {
+            actions.push((Key::N, "write a note".to_string()));
+        }
         actions.extend(self.gameplay.actions(app, id));
         actions
     }
@@ -433,6 +461,18 @@ impl ContextualActions for Actions {
                 Box::new(EditMode::new(ctx, app, self.gameplay.clone())),
                 Box::new(StopSignEditor::new(ctx, app, i, self.gameplay.clone())),
             ),
+            (ID::Intersection(i), "allow blocking the box") => {
+                app.primary
+                    .sim
+                    .set_dont_block_the_box(i, false, &app.primary.map);
+                Transition::Keep
+            }
+            (ID::Intersection(i), "enforce don't block the box") => {
+                app.primary
+                    .sim
+                    .set_dont_block_the_box(i, true, &app.primary.map);
+                Transition::Keep
+            }
             (ID::Intersection(i), "explore uber-turns") => {
                 Transition::Push(uber_turns::UberTurnPicker::new(ctx, app, i))
             }
@@ -443,6 +483,106 @@ impl ContextualActions for Actions {
                 Box::new(EditMode::new(ctx, app, self.gameplay.clone())),
                 Box::new(LaneEditor::new(ctx, app, l, self.gameplay.clone())),
             ),
+            (ID::Lane(l), "place a detector here") => {
+                let lane = app.primary.map.get_l(l);
+                // Put the loop where the lane was clicked, or at the stop line otherwise
+                let mut closest = FindClosest::new(app.primary.map.get_bounds());
+                closest.add((), lane.lane_center_pts.points());
+                let dist = ctx
+                    .canvas
+                    .get_cursor_in_map_space()
+                    .and_then(|pt| closest.closest_pt(pt, lane.width * 2.0))
+                    .and_then(|(_, pt)| lane.lane_center_pts.dist_along_of_point(pt))
+                    .map(|(dist, _)| dist)
+                    .unwrap_or_else(|| lane.length());
+                let id = app.primary.sim.add_detector(Detector::new(l, dist));
+                Transition::Push(msg(
+                    "Detector placed",
+                    vec![format!(
+                        "Detector #{} is reporting. See its readings in the dashboards.",
+                        id
+                    )],
+                ))
+            }
+            (ID::Lane(l), "close for an incident") => {
+                Transition::Push(WizardState::new(Box::new(move |wiz, ctx, app| {
+                    let mut wizard = wiz.wrap(ctx);
+                    let (_, all_lanes) = wizard.choose("Close which lanes?", || {
+                        vec![
+                            Choice::new("just this lane", false),
+                            Choice::new("every lane going this way", true),
+                        ]
+                    })?;
+                    let (_, minutes) = wizard.choose("For how long?", || {
+                        vec![
+                            Choice::new("15 minutes", 15),
+                            Choice::new("30 minutes", 30),
+                            Choice::new("1 hour", 60),
+                            Choice::new("2 hours", 120),
+                        ]
+                    })?;
+
+                    let map = &app.primary.map;
+                    let lane = map.get_l(l);
+                    let lanes = if all_lanes {
+                        let parent = map.get_r(lane.parent);
+                        parent
+                            .lanes_on_side(parent.is_forwards(l))
+                            .into_iter()
+                            .filter(|l| map.get_l(*l).lane_type.is_for_moving_vehicles())
+                            .collect()
+                    } else {
+                        vec![l]
+                    };
+                    let start = app.primary.sim.time();
+                    let num_lanes = lanes.len();
+                    let id = app.primary.sim.add_incident(Incident {
+                        lanes,
+                        start,
+                        end: start + Duration::minutes(minutes),
+                    });
+                    Some(Transition::Replace(msg(
+                        "Incident started",
+                        vec![format!(
+                            "Incident #{} closes {} lanes until {}. See them in the incidents \
+                             layer.",
+                            id,
+                            num_lanes,
+                            start + Duration::minutes(minutes)
+                        )],
+                    )))
+                })))
+            }
+            (id, "write a note") => {
+                let target = id.note_target(&app.primary.map).unwrap();
+                Transition::Push(WizardState::new(Box::new(move |wiz, ctx, app| {
+                    let mut wizard = wiz.wrap(ctx);
+                    let old = app
+                        .primary
+                        .map
+                        .get_edits()
+                        .notes
+                        .get(&target)
+                        .cloned()
+                        .unwrap_or_else(ObjectNote::new);
+                    let note = wizard.input_string_prefilled(
+                        &format!("Note about {}", target.describe(&app.primary.map)),
+                        old.note.clone(),
+                    )?;
+                    let tags = wizard.input_string_prefilled(
+                        "Tags, separated by commas",
+                        old.tags.iter().cloned().collect::<Vec<_>>().join(", "),
+                    )?;
+                    app.primary.map.set_note(
+                        target,
+                        ObjectNote {
+                            note: note.trim().to_string(),
+                            tags: ObjectNote::parse_tags(&tags),
+                        },
+                    );
+                    Some(Transition::Pop)
+                })))
+            }
             (ID::Car(c), "show route") => {
                 *close_panel = false;
                 app.layer = Some(Box::new(crate::layer::bus::ShowBusRoute::new(
@@ -452,6 +592,23 @@ impl ContextualActions for Actions {
                 )));
                 Transition::Keep
             }
+            (ID::BusStop(bs), "show arrivals board") => {
+                *close_panel = false;
+                app.layer = Some(Box::new(crate::layer::bus::ArrivalsBoard::new(
+                    ctx, app, bs,
+                )));
+                Transition::Keep
+            }
+            (ID::Building(b), "show isochrones") => {
+                *close_panel = false;
+                app.layer = Some(Box::new(crate::layer::isochrone::Isochrone::new(
+                    ctx,
+                    app,
+                    b,
+                    PathConstraints::Pedestrian,
+                )));
+                Transition::Keep
+            }
             (_, "follow (run the simulation)") => {
                 *close_panel = false;
                 Transition::KeepWithData(Box::new(|state, ctx, app| {
@@ -0,0 +1,227 @@
+use crate::app::{App, ShowEverything};
+use crate::game::{msg, DrawBaselayer, State, Transition};
+use crate::render::DrawOptions;
+use ezgui::{
+    hotkey, Btn, Composite, EventCtx, EventLoopMode, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Slider, Text, VerticalAlignment, Widget, Wizard,
+};
+use geom::{Duration, Time};
+use sim::{GetDrawAgents, Replay};
+
+// How many times faster than realtime to play back
+const PLAYBACK_SPEED: f64 = 30.0;
+// Only list this many events from the current frame
+const MAX_EVENTS: usize = 10;
+// Events can be quite verbose
+const MAX_EVENT_LEN: usize = 100;
+
+// Plays back a recorded run without simulating anything, so it's possible to jump around or scrub
+// backwards freely. Also used to rewind through the live simulation's recent history.
+struct ReplayViewer {
+    composite: Composite,
+    replay: Replay,
+    time: Time,
+    playing: bool,
+}
+
+impl ReplayViewer {
+    // Starts from wherever the replay has been seeked to.
+    fn new(ctx: &mut EventCtx, app: &App, replay: Replay, title: String) -> Box<dyn State> {
+        let time = replay.time();
+        let composite = Composite::new(
+            Widget::col(vec![
+                Widget::row(vec![
+                    Line(title).small_heading().draw(ctx),
+                    Btn::text_fg("X")
+                        .build(ctx, "close", hotkey(Key::Escape))
+                        .align_right(),
+                ])
+                .margin_below(10),
+                Text::new().draw(ctx).named("info"),
+                Slider::horizontal(ctx, 0.25 * ctx.canvas.window_width, 25.0, 0.0)
+                    .named("time")
+                    .margin_above(10)
+                    .margin_below(10),
+                Widget::row(vec![
+                    Btn::text_fg("previous frame")
+                        .build_def(ctx, hotkey(Key::LeftArrow))
+                        .margin_right(10),
+                    play_button(ctx, false).margin_right(10),
+                    Btn::text_fg("next frame").build_def(ctx, hotkey(Key::RightArrow)),
+                ]),
+            ])
+            .bg(app.cs.panel_bg)
+            .padding(10),
+        )
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+        .build(ctx);
+
+        let mut viewer = ReplayViewer {
+            composite,
+            replay,
+            time,
+            playing: false,
+        };
+        viewer.seek(ctx, time);
+        Box::new(viewer)
+    }
+
+    fn seek(&mut self, ctx: &mut EventCtx, time: Time) {
+        self.time = time
+            .max(self.replay.start_time())
+            .min(self.replay.end_time());
+        self.replay.seek(self.time);
+        let pct = self.percent();
+        self.composite.slider_mut("time").set_percent(ctx, pct);
+        self.update_info(ctx);
+    }
+
+    fn percent(&self) -> f64 {
+        let total = self.replay.end_time() - self.replay.start_time();
+        if total == Duration::ZERO {
+            return 0.0;
+        }
+        ((self.time - self.replay.start_time()) / total)
+            .max(0.0)
+            .min(1.0)
+    }
+
+    fn update_info(&mut self, ctx: &mut EventCtx) {
+        let mut txt = Text::from(Line(self.replay.time().ampm_tostring()));
+        let events = self.replay.current_events();
+        for (t, ev) in events.iter().take(MAX_EVENTS) {
+            let mut line = format!("{}: {:?}", t, ev);
+            if line.len() > MAX_EVENT_LEN {
+                line = format!(
+                    "{}...",
+                    line.chars().take(MAX_EVENT_LEN).collect::<String>()
+                );
+            }
+            txt.add(Line(line).secondary());
+        }
+        if events.len() > MAX_EVENTS {
+            txt.add(Line(format!("... and {} more events", events.len() - MAX_EVENTS)).secondary());
+        }
+        self.composite
+            .replace(ctx, "info", txt.draw(ctx).named("info"));
+    }
+}
+
+impl State for ReplayViewer {
+    fn event(&mut self, ctx: &mut EventCtx, _: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "play" | "pause" => {
+                    self.playing = !self.playing;
+                    self.composite
+                        .replace(ctx, "play", play_button(ctx, self.playing));
+                }
+                "previous frame" => {
+                    let t = self.replay.time().clamped_sub(self.replay.frame_interval);
+                    self.seek(ctx, t);
+                }
+                "next frame" => {
+                    let t = self.replay.time() + self.replay.frame_interval;
+                    self.seek(ctx, t);
+                }
+                _ => unreachable!(),
+            },
+            None => {}
+        }
+
+        // Dragging the slider
+        let pct = self.composite.slider("time").get_percent();
+        if (pct - self.percent()).abs() > 0.0001 {
+            let t = self.replay.start_time()
+                + (self.replay.end_time() - self.replay.start_time()) * pct;
+            self.seek(ctx, t);
+        }
+
+        if self.playing {
+            if let Some(real_dt) = ctx.input.nonblocking_is_update_event() {
+                ctx.input.use_update_event();
+                let t = self.time + PLAYBACK_SPEED * real_dt;
+                if t >= self.replay.end_time() {
+                    self.playing = false;
+                    self.composite.replace(ctx, "play", play_button(ctx, false));
+                }
+                self.seek(ctx, t);
+            }
+            return Transition::KeepWithMode(EventLoopMode::Animation);
+        }
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        app.draw(g, DrawOptions::new(), &self.replay, &ShowEverything::new());
+        self.composite.draw(g);
+    }
+}
+
+fn play_button(ctx: &mut EventCtx, playing: bool) -> Widget {
+    if playing {
+        Btn::text_fg("pause").build_def(ctx, hotkey(Key::Space))
+    } else {
+        Btn::text_fg("play").build_def(ctx, hotkey(Key::Space))
+    }
+    .named("play")
+}
+
+// Scrub back through the last little while of the live simulation. Closing picks the simulation
+// back up where it was. The caller has to check the sim is keeping history.
+pub fn rewind(ctx: &mut EventCtx, app: &App) -> Box<dyn State> {
+    let history = app.primary.sim.rewind_history(&app.primary.map).unwrap();
+    let title = format!("Rewind (since {})", history.start_time().ampm_tostring());
+    ReplayViewer::new(ctx, app, history, title)
+}
+
+// Only replays recorded on the current map, with the same edits, can be watched.
+pub fn pick_replay(wiz: &mut Wizard, ctx: &mut EventCtx, app: &mut App) -> Option<Transition> {
+    let dir = abstutil::path_all_replays(app.primary.map.get_name());
+    if abstutil::list_all_objects(dir.clone()).is_empty() {
+        return Some(Transition::Replace(msg(
+            "No replays",
+            vec!["No replays have been saved on this map"],
+        )));
+    }
+    let name = wiz.wrap(ctx).choose_string("Watch which replay?", || {
+        abstutil::list_all_objects(dir.clone())
+    })?;
+    let path = format!("{}/{}.bin", dir, name);
+
+    let result = ctx.loading_screen("load replay", |_, mut timer| {
+        Replay::load(path.clone(), &mut timer)
+    });
+    match result {
+        Ok(replay) => {
+            let edits_name = &app.primary.map.get_edits().edits_name;
+            if &replay.edits_name != edits_name {
+                Some(Transition::Replace(msg(
+                    "Error",
+                    vec![format!(
+                        "This replay was recorded with the edits {}, but {} are loaded now",
+                        replay.edits_name, edits_name
+                    )],
+                )))
+            } else {
+                let title = format!("Replay of {}", replay.run_name);
+                Some(Transition::Replace(ReplayViewer::new(
+                    ctx, app, replay, title,
+                )))
+            }
+        }
+        Err(err) => Some(Transition::Replace(msg(
+            "Error",
+            vec![format!("Couldn't load replay {}: {}", path, err)],
+        ))),
+    }
+}
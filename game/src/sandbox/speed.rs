@@ -1,22 +1,28 @@
 use crate::app::{App, FindDelayedIntersections};
 use crate::common::Warping;
-use crate::game::{msg, State, Transition};
+use crate::edit::apply_map_edits;
+use crate::game::{msg, State, Transition, WizardState};
 use crate::helpers::ID;
+use crate::sandbox::dashboards::{jump_to_gridlock, GridlockReport};
+use crate::sandbox::replay::{pick_replay, rewind};
 use crate::sandbox::{GameplayMode, SandboxMode};
 use ezgui::{
     hotkey, AreaSlider, Btn, Choice, Color, Composite, EventCtx, EventLoopMode, GeomBatch, GfxCtx,
-    HorizontalAlignment, Key, Line, Outcome, PersistentSplit, RewriteColor, Text,
-    VerticalAlignment, Widget,
+    HorizontalAlignment, Key, Line, Outcome, PersistentSplit, RewriteColor, Text, TextExt,
+    VerticalAlignment, Widget, Wizard,
 };
 use geom::{Duration, Polygon, Pt2D, Time};
 use instant::Instant;
-use sim::AlertLocation;
+use map_model::Map;
+use sim::{AlertLocation, Event, Sim, SimCallback, TripID};
 
 pub struct SpeedControls {
     pub composite: Composite,
 
     paused: bool,
     setting: SpeedSetting,
+    // How many gridlocks the player has been told about
+    gridlocks_seen: usize,
 }
 
 #[derive(Clone, Copy, PartialEq, PartialOrd)]
@@ -100,6 +106,13 @@ impl SpeedControls {
 
         row.push(
             Widget::row(vec![
+                if app.primary.sim.is_keeping_history() {
+                    Btn::svg_def("../data/system/assets/speed/rewind.svg")
+                        .pad(9)
+                        .build(ctx, "rewind", hotkey(Key::Comma))
+                } else {
+                    Widget::nothing()
+                },
                 Btn::svg_def("../data/system/assets/speed/jump_to_time.svg")
                     .pad(9)
                     .build(ctx, "jump to specific time", hotkey(Key::B)),
@@ -124,6 +137,7 @@ impl SpeedControls {
             composite,
             paused: false,
             setting: SpeedSetting::Realtime,
+            gridlocks_seen: app.primary.sim.get_gridlocks().len(),
         }
     }
 
@@ -177,6 +191,10 @@ impl SpeedControls {
                         )));
                     }
                 }
+                "rewind" => {
+                    self.pause(ctx, app);
+                    return Some(Transition::Push(rewind(ctx, app)));
+                }
                 "jump to specific time" => {
                     return Some(Transition::Push(Box::new(JumpToTime::new(
                         ctx,
@@ -197,7 +215,7 @@ impl SpeedControls {
                         ctx,
                         app,
                         app.primary.sim.time() + dt,
-                        false,
+                        None,
                     )));
                 }
                 _ => unreachable!(),
@@ -273,6 +291,13 @@ impl SpeedControls {
 
         // TODO Need to do this anywhere that steps the sim, like TimeWarpScreen.
         let alerts = app.primary.sim.clear_alerts();
+        // Gridlock gets its own alert, with a way to dig in
+        if app.primary.sim.get_gridlocks().len() > self.gridlocks_seen {
+            let idx = self.gridlocks_seen;
+            self.gridlocks_seen = app.primary.sim.get_gridlocks().len();
+            self.pause(ctx, app);
+            return Some(Transition::Push(gridlock_alert(idx)));
+        }
         if !alerts.is_empty() {
             let popup = msg("Alerts", alerts.iter().map(|(_, _, msg)| msg).collect());
             let maybe_id = match alerts[0].1 {
@@ -334,6 +359,21 @@ impl SpeedControls {
     }
 }
 
+fn gridlock_alert(idx: usize) -> Box<dyn State> {
+    WizardState::new(Box::new(move |wiz, ctx, app| {
+        let gridlock = app.primary.sim.get_gridlocks()[idx].clone();
+        let choice = wiz.wrap(ctx).choose_string(
+            &format!("Gridlock #{}: {}", idx, gridlock.describe()),
+            || vec!["jump to gridlock", "see report", "keep going"],
+        )?;
+        match choice.as_ref() {
+            "jump to gridlock" => Some(jump_to_gridlock(ctx, app, &gridlock)),
+            "see report" => Some(Transition::Replace(GridlockReport::new(ctx, app))),
+            _ => Some(Transition::Pop),
+        }
+    }))
+}
+
 // TODO Text entry would be great
 struct JumpToTime {
     composite: Composite,
@@ -394,9 +434,69 @@ impl JumpToTime {
                     )
                     .margin_above(20)
                     .margin_below(20),
-                    Btn::text_bg2("Jump to the next delay over 5 minutes")
-                        .build_def(ctx, None)
-                        .centered_horiz(),
+                    Widget::row(vec![
+                        "Run at max speed until".draw_text(ctx).margin(5),
+                        Widget::dropdown(
+                            ctx,
+                            "trigger",
+                            Duration::minutes(5),
+                            vec![
+                                Choice::new("a delay over 1 minute", Duration::minutes(1)),
+                                Choice::new("a delay over 5 minutes", Duration::minutes(5)),
+                                Choice::new("a delay over 15 minutes", Duration::minutes(15)),
+                                Choice::new("a delay over 1 hour", Duration::hours(1)),
+                            ],
+                        )
+                        .margin(5),
+                    ])
+                    .centered_horiz(),
+                    Btn::text_bg2("Run until something interesting happens")
+                        .build(ctx, "run until trigger", None)
+                        .centered_horiz()
+                        .margin_above(10),
+                    Widget::draw_batch(
+                        ctx,
+                        GeomBatch::from(vec![(
+                            Color::WHITE,
+                            Polygon::rectangle(0.25 * ctx.canvas.window_width, 2.0),
+                        )]),
+                    )
+                    .margin_above(20)
+                    .margin_below(20),
+                    Widget::row(vec![
+                        Btn::text_bg2("Save a checkpoint here")
+                            .build(ctx, "save checkpoint", None)
+                            .margin_right(10),
+                        Btn::text_bg2("Restore a checkpoint").build(
+                            ctx,
+                            "restore checkpoint",
+                            None,
+                        ),
+                    ])
+                    .centered_horiz()
+                    .margin_below(10),
+                    Widget::row(vec![
+                        Btn::text_bg2("Mark an undo point here")
+                            .build(ctx, "mark undo point", None)
+                            .margin_right(10),
+                        if app.primary.undo_points.is_empty() {
+                            Btn::text_bg2("Undo to a marked point").inactive(ctx)
+                        } else {
+                            Btn::text_bg2("Undo to a marked point").build(ctx, "undo", None)
+                        },
+                    ])
+                    .centered_horiz()
+                    .margin_below(10),
+                    Widget::row(vec![
+                        if app.primary.sim.is_recording_replay() {
+                            Btn::text_bg2("Save the replay so far").build(ctx, "save replay", None)
+                        } else {
+                            Btn::text_bg2("Save the replay so far").inactive(ctx)
+                        }
+                        .margin_right(10),
+                        Btn::text_bg2("Watch a replay").build(ctx, "watch replay", None),
+                    ])
+                    .centered_horiz(),
                 ])
                 .bg(app.cs.panel_bg)
                 .padding(16),
@@ -418,7 +518,7 @@ impl State for JumpToTime {
                         if let Some(mode) = self.maybe_mode.take() {
                             return Transition::ReplaceThenPush(
                                 Box::new(SandboxMode::new(ctx, app, mode)),
-                                TimeWarpScreen::new(ctx, app, self.target, false),
+                                TimeWarpScreen::new(ctx, app, self.target, None),
                             );
                         } else {
                             return Transition::Replace(msg(
@@ -427,16 +527,61 @@ impl State for JumpToTime {
                             ));
                         }
                     }
-                    return Transition::Replace(TimeWarpScreen::new(ctx, app, self.target, false));
+                    return Transition::Replace(TimeWarpScreen::new(ctx, app, self.target, None));
                 }
-                "Jump to the next delay over 5 minutes" => {
+                "run until trigger" => {
                     return Transition::Replace(TimeWarpScreen::new(
                         ctx,
                         app,
                         app.primary.sim.get_end_of_day(),
-                        true,
+                        Some(WarpTrigger::Delay(self.composite.dropdown_value("trigger"))),
+                    ));
+                }
+                "save checkpoint" => {
+                    let edits = app.primary.map.get_edits();
+                    if edits.edits_name == "untitled edits" && !edits.commands.is_empty() {
+                        return Transition::Replace(msg(
+                            "Error",
+                            vec![
+                                "Save your map edits first,",
+                                "so the checkpoint can be restored later",
+                            ],
+                        ));
+                    }
+                    let name = app.primary.sim.time().as_filename();
+                    let path = ctx.loading_screen("save checkpoint", |_, _| {
+                        app.primary.sim.save_checkpoint(&app.primary.map, &name)
+                    });
+                    return Transition::Replace(msg(
+                        "Checkpoint saved",
+                        vec![format!("Saved {}", path)],
                     ));
                 }
+                "restore checkpoint" => {
+                    return Transition::Replace(WizardState::new(Box::new(restore_checkpoint)));
+                }
+                "mark undo point" => {
+                    let label = format!("marked at {}", app.primary.sim.time().ampm_tostring());
+                    ctx.loading_screen("mark undo point", |_, _| {
+                        app.primary.mark_undo_point(label);
+                    });
+                    return Transition::Pop;
+                }
+                "undo" => {
+                    return Transition::Replace(WizardState::new(Box::new(undo)));
+                }
+                "save replay" => {
+                    let path = ctx.loading_screen("save replay", |_, _| {
+                        app.primary.sim.save_replay(&app.primary.map).unwrap()
+                    });
+                    return Transition::Replace(msg(
+                        "Replay saved",
+                        vec![format!("Saved {}", path)],
+                    ));
+                }
+                "watch replay" => {
+                    return Transition::Replace(WizardState::new(Box::new(pick_replay)));
+                }
                 _ => unreachable!(),
             },
             None => {}
@@ -470,11 +615,115 @@ impl State for JumpToTime {
     }
 }
 
+fn restore_checkpoint(wiz: &mut Wizard, ctx: &mut EventCtx, app: &mut App) -> Option<Transition> {
+    // Only checkpoints made with the current edits can be restored
+    let dir = Sim::checkpoint_dir(&app.primary.map);
+    if abstutil::list_all_objects(dir.clone()).is_empty() {
+        return Some(Transition::Replace(msg(
+            "No checkpoints",
+            vec!["No checkpoints have been saved with the current map edits"],
+        )));
+    }
+    let name = wiz
+        .wrap(ctx)
+        .choose_string("Restore which checkpoint?", || {
+            abstutil::list_all_objects(dir.clone())
+        })?;
+    let path = format!("{}/{}.bin", dir, name);
+
+    ctx.loading_screen(
+        "restore checkpoint",
+        |ctx, mut timer| match Sim::load_checkpoint(path.clone(), &mut timer) {
+            Ok(sim) => {
+                app.primary.sim = sim;
+                app.recalculate_current_selection(ctx);
+                Some(Transition::Pop)
+            }
+            Err(err) => Some(Transition::Replace(msg(
+                "Error",
+                vec![format!("Couldn't restore checkpoint {}: {}", path, err)],
+            ))),
+        },
+    )
+}
+
+// Go back to an earlier moment, along with the map edits in effect then. Unlike rewinding, the
+// simulation really is back there, and can run forwards again differently.
+fn undo(wiz: &mut Wizard, ctx: &mut EventCtx, app: &mut App) -> Option<Transition> {
+    let (_, idx) = wiz.wrap(ctx).choose("Undo to which point?", || {
+        app.primary
+            .undo_points
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(idx, pt)| {
+                Choice::new(
+                    format!(
+                        "{} ({} bytes)",
+                        pt.label,
+                        abstutil::prettyprint_usize(pt.snapshot.size_bytes())
+                    ),
+                    idx,
+                )
+            })
+            .collect()
+    })?;
+
+    ctx.loading_screen("undo", |ctx, mut timer| {
+        let edits = app.primary.undo_points[idx].edits.clone();
+        if app.primary.map.get_edits() != &edits {
+            apply_map_edits(ctx, app, edits);
+            app.primary
+                .map
+                .recalculate_pathfinding_after_edits(&mut timer);
+        }
+        app.primary.sim.restore_snapshot(
+            &app.primary.undo_points[idx].snapshot,
+            &app.primary.map,
+            &mut timer,
+        );
+        app.recalculate_current_selection(ctx);
+    });
+    Some(Transition::Pop)
+}
+
+// Stops a TimeWarpScreen before the target time, when something interesting happens.
+#[derive(Clone, Copy, PartialEq)]
+pub enum WarpTrigger {
+    // Some intersection has had an agent waiting at least this long
+    Delay(Duration),
+    // The trip actually starts, which might be later than scheduled
+    TripStarts(TripID),
+}
+
+struct WaitForTrip {
+    trip: TripID,
+    started: bool,
+}
+
+impl SimCallback for WaitForTrip {
+    fn run(&mut self, _: &Sim, _: &Map) -> bool {
+        false
+    }
+
+    fn event(&mut self, ev: &Event, _: Time, _: &Map) -> bool {
+        if let Event::TripPhaseStarting(trip, _, _, _) = ev {
+            if *trip == self.trip && !self.started {
+                self.started = true;
+                return true;
+            }
+        }
+        false
+    }
+}
+
 // Display a nicer screen for jumping forwards in time, allowing cancellation.
 pub struct TimeWarpScreen {
     target: Time,
     started: Instant,
-    traffic_jams: bool,
+    trigger: Option<WarpTrigger>,
+    // Did we install the sim callback, and so need to clean it up?
+    owns_callback: bool,
     composite: Composite,
 }
 
@@ -483,26 +732,37 @@ impl TimeWarpScreen {
         ctx: &mut EventCtx,
         app: &mut App,
         target: Time,
-        mut traffic_jams: bool,
+        trigger: Option<WarpTrigger>,
     ) -> Box<dyn State> {
-        if traffic_jams {
-            if app.primary.sim_cb.is_none() {
-                app.primary.sim_cb = Some(Box::new(FindDelayedIntersections {
-                    halt_limit: Duration::minutes(5),
-                    report_limit: Duration::minutes(5),
-                    currently_delayed: Vec::new(),
-                }));
-                // TODO Can we get away with less frequently? Not sure about all the edge cases
-                app.primary.sim.set_periodic_callback(Duration::minutes(1));
-            } else {
-                traffic_jams = false;
+        // Only one callback at a time is supported. If something else already installed one, just
+        // warp without the trigger. Without its callback, TripStarts would run to the target
+        // time, so callers should check first.
+        let owns_callback = trigger.is_some() && app.primary.sim_cb.is_none();
+        if owns_callback {
+            match trigger.unwrap() {
+                WarpTrigger::Delay(limit) => {
+                    app.primary.sim_cb = Some(Box::new(FindDelayedIntersections {
+                        halt_limit: limit,
+                        report_limit: limit,
+                        currently_delayed: Vec::new(),
+                    }));
+                    // TODO Can we get away with less frequently? Not sure about all the edge cases
+                    app.primary.sim.set_periodic_callback(Duration::minutes(1));
+                }
+                WarpTrigger::TripStarts(trip) => {
+                    app.primary.sim_cb = Some(Box::new(WaitForTrip {
+                        trip,
+                        started: false,
+                    }));
+                }
             }
         }
 
         Box::new(TimeWarpScreen {
             target,
             started: Instant::now(),
-            traffic_jams,
+            trigger,
+            owns_callback,
             composite: Composite::new(
                 Widget::col(vec![
                     Text::new().draw(ctx).named("text"),
@@ -535,8 +795,23 @@ impl State for TimeWarpScreen {
                     vec![format!("At {}, near {:?}, {}", t, maybe_i, alert)],
                 ));
             }
-            if let Some(ref mut cb) = app.primary.sim_cb {
-                let di = cb.downcast_mut::<FindDelayedIntersections>().unwrap();
+            // Other callbacks, like the tutorial's, might be installed instead
+            if let Some(wait) = app
+                .primary
+                .sim_cb
+                .as_ref()
+                .and_then(|cb| cb.downcast_ref::<WaitForTrip>())
+            {
+                if wait.started {
+                    return Transition::Pop;
+                }
+            }
+            if let Some(di) = app
+                .primary
+                .sim_cb
+                .as_mut()
+                .and_then(|cb| cb.downcast_mut::<FindDelayedIntersections>())
+            {
                 if let Some((i, t)) = di.currently_delayed.get(0) {
                     if app.primary.sim.time() - *t > di.halt_limit {
                         let id = ID::Intersection(*i);
@@ -555,10 +830,23 @@ impl State for TimeWarpScreen {
 
             // I'm covered in shame for not doing this from the start.
             let mut txt = Text::from(Line("Let's do the time warp again!").small_heading());
-            txt.add(Line(format!(
-                "Simulating until it's {}",
-                self.target.ampm_tostring()
-            )));
+            match self.trigger {
+                Some(WarpTrigger::Delay(limit)) => {
+                    txt.add(Line(format!(
+                        "Simulating until some intersection has a delay over {}",
+                        limit
+                    )));
+                }
+                Some(WarpTrigger::TripStarts(trip)) => {
+                    txt.add(Line(format!("Simulating until {} starts", trip)));
+                }
+                None => {
+                    txt.add(Line(format!(
+                        "Simulating until it's {}",
+                        self.target.ampm_tostring()
+                    )));
+                }
+            }
             txt.add(Line(format!(
                 "It's currently {}",
                 app.primary.sim.time().ampm_tostring()
@@ -597,7 +885,7 @@ impl State for TimeWarpScreen {
     }
 
     fn on_destroy(&mut self, _: &mut EventCtx, app: &mut App) {
-        if self.traffic_jams {
+        if self.owns_callback {
             assert!(app.primary.sim_cb.is_some());
             app.primary.sim_cb = None;
             app.primary.sim.unset_periodic_callback();
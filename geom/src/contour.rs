@@ -0,0 +1,126 @@
+use crate::{Bounds, Distance, Polygon, Pt2D};
+
+// Values sampled evenly over some area, for turning scattered measurements (like travel times to
+// intersections) into smooth filled regions.
+pub struct Grid {
+    min_x: f64,
+    min_y: f64,
+    // Meters between samples
+    resolution: f64,
+    width: usize,
+    height: usize,
+    values: Vec<f64>,
+}
+
+impl Grid {
+    // Every sample starts with the initial value. Use something past any threshold of interest
+    // for places nothing reaches.
+    pub fn new(bounds: &Bounds, resolution: Distance, initial: f64) -> Grid {
+        let resolution = resolution.inner_meters();
+        assert!(resolution > 0.0);
+        let width = ((bounds.max_x - bounds.min_x) / resolution).ceil() as usize + 1;
+        let height = ((bounds.max_y - bounds.min_y) / resolution).ceil() as usize + 1;
+        Grid {
+            min_x: bounds.min_x,
+            min_y: bounds.min_y,
+            resolution,
+            width,
+            height,
+            values: vec![initial; width * height],
+        }
+    }
+
+    fn pt(&self, x: usize, y: usize) -> Pt2D {
+        Pt2D::new(
+            self.min_x + (x as f64) * self.resolution,
+            self.min_y + (y as f64) * self.resolution,
+        )
+    }
+
+    fn idx(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    // Every sample within the radius of a point becomes the value, plus some cost per meter away
+    // from the point, unless it's already lower.
+    pub fn splat_min(&mut self, center: Pt2D, radius: Distance, value: f64, per_meter: f64) {
+        let r = radius.inner_meters();
+        let x1 = ((center.x() - r - self.min_x) / self.resolution)
+            .floor()
+            .max(0.0) as usize;
+        let y1 = ((center.y() - r - self.min_y) / self.resolution)
+            .floor()
+            .max(0.0) as usize;
+        let x2 =
+            (((center.x() + r - self.min_x) / self.resolution).ceil() as usize).min(self.width - 1);
+        let y2 = (((center.y() + r - self.min_y) / self.resolution).ceil() as usize)
+            .min(self.height - 1);
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                let dist = self.pt(x, y).dist_to(center);
+                if dist > radius {
+                    continue;
+                }
+                let idx = self.idx(x, y);
+                self.values[idx] = self.values[idx].min(value + dist.inner_meters() * per_meter);
+            }
+        }
+    }
+
+    // One polygon covering everywhere the value is at most the threshold, interpolating between
+    // samples. This is marching squares, splitting each cell into two triangles so every piece is
+    // convex and there are no ambiguous saddle cases.
+    pub fn contour_below(&self, threshold: f64) -> Option<Polygon> {
+        let mut points = Vec::new();
+        let mut indices = Vec::new();
+        for y in 0..self.height.saturating_sub(1) {
+            for x in 0..self.width.saturating_sub(1) {
+                let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+                for tri in &[[0, 1, 2], [0, 2, 3]] {
+                    let samples: Vec<(Pt2D, f64)> = tri
+                        .iter()
+                        .map(|c| {
+                            let (cx, cy) = corners[*c];
+                            (self.pt(cx, cy), self.values[self.idx(cx, cy)])
+                        })
+                        .collect();
+                    let piece = clip_below(&samples, threshold);
+                    if piece.len() < 3 {
+                        continue;
+                    }
+                    // Each piece is convex, so a fan from the first point works
+                    let first = points.len();
+                    for i in 1..piece.len() - 1 {
+                        indices.extend(vec![first, first + i, first + i + 1]);
+                    }
+                    points.extend(piece);
+                }
+            }
+        }
+        if indices.is_empty() {
+            None
+        } else {
+            Some(Polygon::precomputed(points, indices))
+        }
+    }
+}
+
+// The part of a triangle where the linearly interpolated value is at most the threshold
+fn clip_below(samples: &Vec<(Pt2D, f64)>, threshold: f64) -> Vec<Pt2D> {
+    let mut result = Vec::new();
+    for i in 0..samples.len() {
+        let (pt1, v1) = samples[i];
+        let (pt2, v2) = samples[(i + 1) % samples.len()];
+        if v1 <= threshold {
+            result.push(pt1);
+        }
+        if (v1 <= threshold) != (v2 <= threshold) {
+            let pct = (threshold - v1) / (v2 - v1);
+            result.push(Pt2D::new(
+                pt1.x() + pct * (pt2.x() - pt1.x()),
+                pt1.y() + pct * (pt2.y() - pt1.y()),
+            ));
+        }
+    }
+    result
+}
@@ -1,6 +1,7 @@
 mod angle;
 mod bounds;
 mod circle;
+mod contour;
 mod distance;
 mod duration;
 mod find_closest;
@@ -17,6 +18,7 @@ mod time;
 pub use crate::angle::Angle;
 pub use crate::bounds::{Bounds, GPSBounds};
 pub use crate::circle::Circle;
+pub use crate::contour::Grid;
 pub use crate::distance::Distance;
 pub use crate::duration::Duration;
 pub use crate::find_closest::FindClosest;
@@ -1,4 +1,4 @@
-use geom::LonLat;
+use geom::{Duration, LonLat, Time};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeSet, HashMap};
@@ -7,7 +7,16 @@ use transitfeed::GTFSIterator;
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct Route {
     pub name: String,
+    // The route_id from GTFS, which outlives changes to the schedule
+    #[serde(default)]
+    pub route_id: String,
     pub stops: Vec<LonLat>,
+    // The stop_id for each of the stops. Empty for routes imported before these were kept.
+    #[serde(default)]
+    pub stop_ids: Vec<String>,
+    // When each scheduled trip leaves the first stop, sorted
+    #[serde(default)]
+    pub spawn_times: Vec<Time>,
 }
 
 pub fn load(dir_path: &str) -> Vec<Route> {
@@ -46,7 +55,8 @@ pub fn load(dir_path: &str) -> Vec<Route> {
     // Each (directed) route has many trips. Ignore all but the first and assume the list of stops
     // is the same. Also assume that records with the same trip are contiguous and that
     // stop_sequence is monotonic.
-    let mut directed_routes: HashMap<(String, bool), Vec<LonLat>> = HashMap::new();
+    let mut directed_routes: HashMap<(String, bool), Vec<String>> = HashMap::new();
+    let mut departures: HashMap<(String, bool), Vec<Time>> = HashMap::new();
     for (key, group) in
         GTFSIterator::<_, transitfeed::StopTime>::from_path(&format!("{}/stop_times.txt", dir_path))
             .unwrap()
@@ -56,12 +66,18 @@ pub fn load(dir_path: &str) -> Vec<Route> {
             .into_iter()
     {
         let (route_id, forwards) = trip_id_to_route_id_and_direction[&key].clone();
+        let records: Vec<transitfeed::StopTime> = group.collect();
+        // departure_time counts seconds since midnight, and may run past 24 hours.
+        departures
+            .entry((route_id.clone(), forwards))
+            .or_insert_with(Vec::new)
+            .push(Time::START_OF_DAY + Duration::seconds(records[0].departure_time as f64));
         if directed_routes.contains_key(&(route_id.clone(), forwards)) {
             continue;
         }
         directed_routes.insert(
             (route_id, forwards),
-            group.map(|rec| stop_id_to_pt[&rec.stop_id]).collect(),
+            records.into_iter().map(|rec| rec.stop_id).collect(),
         );
     }
 
@@ -72,16 +88,37 @@ pub fn load(dir_path: &str) -> Vec<Route> {
         .collect();
     let mut results = Vec::new();
     for route_id in route_ids {
-        let mut stops = directed_routes
+        let mut stop_ids = directed_routes
             .remove(&(route_id.clone(), true))
             .unwrap_or_else(Vec::new);
+        // The combined route starts with the forwards direction, so those trips determine when
+        // buses leave the first stop. Only fall back to the other direction if that's all there
+        // is.
+        let mut spawn_times = if stop_ids.is_empty() {
+            departures.remove(&(route_id.clone(), false))
+        } else {
+            departures.remove(&(route_id.clone(), true))
+        }
+        .unwrap_or_else(Vec::new);
+        // Trips after midnight belong to the previous service day and are written like 25:10.
+        // The same trip runs early in the morning of the day being simulated too.
+        let after_midnight: Vec<Time> = spawn_times
+            .iter()
+            .filter(|t| **t >= Time::START_OF_DAY + Duration::hours(24))
+            .map(|t| *t - Duration::hours(24))
+            .collect();
+        spawn_times.extend(after_midnight);
+        spawn_times.sort();
         if let Some(more_stops) = directed_routes.remove(&(route_id.clone(), false)) {
-            stops.extend(more_stops);
+            stop_ids.extend(more_stops);
         }
-        assert!(!stops.is_empty());
+        assert!(!stop_ids.is_empty());
         results.push(Route {
             name: route_id_to_name[&route_id].to_string(),
-            stops,
+            route_id,
+            stops: stop_ids.iter().map(|id| stop_id_to_pt[id]).collect(),
+            stop_ids,
+            spawn_times,
         });
     }
     assert!(directed_routes.is_empty());
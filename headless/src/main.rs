@@ -1,39 +1,72 @@
 use abstutil::{prettyprint_usize, CmdArgs, Timer};
+use geom::Time;
 use map_model::Map;
 use rand::SeedableRng;
 use rand_xorshift::XorShiftRng;
-use sim::{AlertHandler, Scenario, Sim, SimFlags};
+use sim::{AlertHandler, DepartureLearning, DepartureLearningConfig, Scenario, Sim, SimFlags};
 
 // This is specialized to experiment with running the pandemic model over long time periods.
-// Original functionality for profiling and debugging gridlock have been removed.
+// Original functionality for profiling and debugging gridlock have been removed. With
+// --learn_departures, it instead repeats one weekday, letting people shift their departure times
+// to avoid congestion. --checkpoint_at saves the full sim state partway through the pandemic run,
+// and --resume picks a later run back up from one of those checkpoints.
 
 fn main() {
     let mut args = CmdArgs::new();
     let num_days = args
         .optional_parse("--days", |s| s.parse::<usize>())
         .unwrap_or(1);
+    // The most days to run before giving up on converging
+    let learning_days = args.optional_parse("--learn_departures", |s| s.parse::<usize>());
+    let learning_config: DepartureLearningConfig = args
+        .optional("--learning")
+        .map(|path| abstutil::read_json(path, &mut Timer::throwaway()))
+        .unwrap_or_default();
+    let checkpoint_at = args.optional_parse("--checkpoint_at", Time::parse);
+    let resume = args.optional("--resume");
     args.done();
 
+    if let Some(max_days) = learning_days {
+        learn_departures(max_days, learning_config);
+        return;
+    }
+
     let mut sim_flags = SimFlags::synthetic_test("montlake", "pandemic");
     sim_flags.opts.enable_pandemic_model = Some(XorShiftRng::from_seed([sim_flags.rng_seed; 16]));
     // Less spam
     sim_flags.opts.alerts = AlertHandler::Silence;
     let mut timer = Timer::new("setup headless");
-    let (mut map, _, mut rng) = sim_flags.load(&mut timer);
-    map.hack_override_offstreet_spots(num_days);
-    // After the map changes, have to re-create the (empty) Sim, because things like
-    // ParkingSimState are out of sync.
-    let mut sim = Sim::new(&map, sim_flags.opts.clone(), &mut timer);
+    let (map, mut sim) = if let Some(path) = resume {
+        sim_flags.load = path;
+        let (mut map, sim, _) = sim_flags.load(&mut timer);
+        // The checkpoint has to come from a run with the same --days
+        map.hack_override_offstreet_spots(num_days);
+        (map, sim)
+    } else {
+        let (mut map, _, mut rng) = sim_flags.load(&mut timer);
+        map.hack_override_offstreet_spots(num_days);
+        // After the map changes, have to re-create the (empty) Sim, because things like
+        // ParkingSimState are out of sync.
+        let mut sim = Sim::new(&map, sim_flags.opts.clone(), &mut timer);
 
-    let base_scenario: Scenario = abstutil::read_binary(
-        abstutil::path_scenario(map.get_name(), "weekday"),
-        &mut timer,
-    );
-    base_scenario
-        .repeat_days(num_days)
-        .instantiate(&mut sim, &map, &mut rng, &mut timer);
+        let base_scenario: Scenario = abstutil::read_binary(
+            abstutil::path_scenario(map.get_name(), "weekday"),
+            &mut timer,
+        );
+        base_scenario
+            .repeat_days(num_days)
+            .instantiate(&mut sim, &map, &mut rng, &mut timer);
+        (map, sim)
+    };
     timer.done();
 
+    if let Some(t) = checkpoint_at {
+        if t > sim.time() {
+            sim.timed_step(&map, t - sim.time(), &mut None, &mut Timer::throwaway());
+        }
+        println!("Saved {}", sim.save_checkpoint(&map, &t.as_filename()));
+    }
+
     run_experiment(&map, &mut sim);
 }
 
@@ -42,7 +75,7 @@ fn run_experiment(map: &Map, sim: &mut Sim) {
     sim.run_until_done(
         &map,
         |sim, _map| {
-            // This'll run every 30 sim seconds
+            // This runs every opts.step_size, 30 sim seconds by default
             if false {
                 println!(
                     "At {}, {} infected",
@@ -56,3 +89,44 @@ fn run_experiment(map: &Map, sim: &mut Sim) {
     timer.done();
     println!("Done at {}", sim.time());
 }
+
+fn learn_departures(max_days: usize, config: DepartureLearningConfig) {
+    let mut sim_flags = SimFlags::synthetic_test("montlake", "learn_departures");
+    sim_flags.opts.alerts = AlertHandler::Silence;
+    let mut timer = Timer::new("setup headless");
+    let (map, _, mut rng) = sim_flags.load(&mut timer);
+    let mut scenario: Scenario = abstutil::read_binary(
+        abstutil::path_scenario(map.get_name(), "weekday"),
+        &mut timer,
+    );
+    timer.done();
+
+    let mut learning = DepartureLearning::new(config, &scenario);
+    let mut all_stats = Vec::new();
+    for _ in 0..max_days {
+        let mut sim = Sim::new(&map, sim_flags.opts.clone(), &mut Timer::throwaway());
+        // Make the same random choices every day, so only the departure times change
+        scenario.instantiate(
+            &mut sim,
+            &map,
+            &mut sim_flags.make_rng(),
+            &mut Timer::throwaway(),
+        );
+        sim.run_until_done(&map, |_, _| {}, None);
+
+        let stats = learning.adjust(&mut scenario, &sim, &mut rng);
+        println!("{}", stats.describe());
+        let converged = stats.converged;
+        all_stats.push(stats);
+        if converged {
+            break;
+        }
+    }
+
+    let path = format!("../data/player/departure_learning_{}.json", map.get_name());
+    abstutil::write_json(path.clone(), &all_stats);
+    println!("Wrote {}", path);
+    // The scenario with the learned departures can be loaded like any other
+    scenario.scenario_name = "weekday_learned".to_string();
+    scenario.save();
+}
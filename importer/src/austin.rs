@@ -26,6 +26,7 @@ pub fn osm_to_raw(name: &str) {
             public_offstreet_parking: None,
             private_offstreet_parking: convert_osm::PrivateOffstreetParking::FixedPerBldg(1),
             sidewalks: None,
+            curb_ramps: None,
             gtfs: None,
             elevation: None,
             clip: Some(format!("../data/input/austin/polygons/{}.poly", name)),
@@ -161,6 +161,7 @@ fn oneshot(osm_path: String, clip: Option<String>) {
             public_offstreet_parking: None,
             private_offstreet_parking: convert_osm::PrivateOffstreetParking::FixedPerBldg(1),
             sidewalks: None,
+            curb_ramps: None,
             gtfs: None,
             elevation: None,
             clip,
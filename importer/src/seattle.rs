@@ -65,6 +65,10 @@ pub fn osm_to_raw(name: &str) {
             ),
             // TODO These're buggy.
             sidewalks: None,
+            // Not downloaded automatically. Convert a curb ramp inventory with the kml crate and
+            // put it here to use it.
+            curb_ramps: Some("../data/input/seattle/curb_ramps.bin".to_string())
+                .filter(|path| abstutil::file_exists(path.clone())),
             gtfs: Some("../data/input/seattle/google_transit".to_string()),
             elevation: Some("../data/input/seattle/N47W122.hgt".to_string()),
             clip: Some(format!("../data/input/seattle/polygons/{}.poly", name)),
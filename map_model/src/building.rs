@@ -1,5 +1,5 @@
 use crate::{LaneID, Position};
-use geom::{Line, PolyLine, Polygon, Pt2D};
+use geom::{Distance, Line, PolyLine, Polygon, Pt2D};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::fmt;
@@ -51,6 +51,30 @@ pub struct Building {
     // Every building can't have OffstreetParking, because the nearest usable driving lane (not in
     // a parking blackhole) might be far away
     pub parking: Option<OffstreetParking>,
+    pub occupancy: Occupancy,
+    // From the height or building:levels tags in OSM, if either is there
+    pub height: Option<Distance>,
+}
+
+// Rough guesses from OSM tags and the building's footprint, used to weight where trips start and
+// end
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Occupancy {
+    pub residents: usize,
+    pub jobs: usize,
+}
+
+// Typical weekday rates. Each trip is counted at both ends, so these are about half of how many
+// trips a person makes.
+const TRIPS_PER_RESIDENT: f64 = 1.8;
+const TRIPS_PER_JOB: f64 = 1.5;
+
+impl Occupancy {
+    // How many trips per weekday start or end here
+    pub fn expected_trips_per_day(&self) -> usize {
+        ((self.residents as f64) * TRIPS_PER_RESIDENT + (self.jobs as f64) * TRIPS_PER_JOB).round()
+            as usize
+    }
 }
 
 impl Building {
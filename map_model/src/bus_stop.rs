@@ -1,4 +1,5 @@
 use crate::{LaneID, Position};
+use geom::Time;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -28,6 +29,8 @@ impl fmt::Display for BusRouteID {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct BusStop {
     pub id: BusStopID,
+    // The GTFS stop_id, which survives rebuilding the map, unlike the BusStopID
+    pub gtfs_id: Option<String>,
     // These might be on opposite sides of the road in the case of one-ways. Shouldn't matter
     // anywhere.
     pub driving_pos: Position,
@@ -38,5 +41,10 @@ pub struct BusStop {
 pub struct BusRoute {
     pub id: BusRouteID,
     pub name: String,
+    // The GTFS route_id
+    pub gtfs_id: Option<String>,
     pub stops: Vec<BusStopID>,
+    // When a bus should leave the first stop, according to the real schedule. If empty, the
+    // simulation just seeds one bus somewhere along the route.
+    pub spawn_times: Vec<Time>,
 }
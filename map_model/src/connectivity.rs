@@ -7,6 +7,16 @@ use std::collections::{HashSet, VecDeque};
 
 // Returns (relevant lanes in main component, disconnected relevant lanes)
 pub fn find_scc(map: &Map, constraints: PathConstraints) -> (HashSet<LaneID>, HashSet<LaneID>) {
+    find_scc_with_extra_turns(map, constraints, Vec::new())
+}
+
+// Like find_scc, but pretends there are also turns between these pairs of lanes. Useful to ask
+// what re-opening something would reconnect.
+pub fn find_scc_with_extra_turns(
+    map: &Map,
+    constraints: PathConstraints,
+    extra: Vec<(LaneID, LaneID)>,
+) -> (HashSet<LaneID>, HashSet<LaneID>) {
     let mut graph = DiGraphMap::new();
     for turn in map.all_turns().values() {
         if constraints.can_use(map.get_l(turn.id.src), map)
@@ -15,6 +25,9 @@ pub fn find_scc(map: &Map, constraints: PathConstraints) -> (HashSet<LaneID>, Ha
             graph.add_edge(turn.id.src, turn.id.dst, 1);
         }
     }
+    for (src, dst) in extra {
+        graph.add_edge(src, dst, 1);
+    }
     let components = petgraph::algo::kosaraju_scc(&graph);
     if components.is_empty() {
         return (HashSet::new(), HashSet::new());
@@ -0,0 +1,270 @@
+use crate::pathfind::cost;
+use crate::{
+    IntersectionID, LaneID, Map, Path, PathConstraints, PathRequest, PathStep, Position, RoadID,
+};
+use geom::Distance;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+// Arterials are where detour signs should send people; avoid sending traffic down residential
+// streets unless there's no other way.
+const RESIDENTIAL_PENALTY: f64 = 3.0;
+// Drivers following signs stray off the detour only when it doesn't connect to where they're
+// going.
+const OFF_DETOUR_PENALTY: usize = 100;
+
+// Finds a route around a closed road, from its src_i to its dst_i, preferring arterials. The roads
+// are in order from src_i. None if there's no way around.
+pub fn propose_detour(map: &Map, closed: RoadID) -> Option<Vec<RoadID>> {
+    cheapest_route(
+        map.get_r(closed).src_i,
+        map.get_r(closed).dst_i,
+        |current| {
+            let mut next = Vec::new();
+            for r in &map.get_i(current).roads {
+                let road = map.get_r(*r);
+                let dr = if road.src_i == current {
+                    road.id.forwards()
+                } else {
+                    road.id.backwards()
+                };
+                // This also skips the closed road
+                if dr.lanes(PathConstraints::Car, map).is_empty() {
+                    continue;
+                }
+                next.push((
+                    road.id,
+                    dr.dst_i(map),
+                    road_cost(road.center_pts.length(), road.get_rank()),
+                ));
+            }
+            next
+        },
+    )
+}
+
+fn road_cost(length: Distance, rank: usize) -> usize {
+    let mut cost = length.inner_meters();
+    if rank < 6 {
+        cost *= RESIDENTIAL_PENALTY;
+    }
+    cost.round() as usize
+}
+
+// Dijkstra's between two intersections. From each intersection, the neighbors are reached by some
+// road at some cost. The roads are in order from start.
+fn cheapest_route<F: Fn(IntersectionID) -> Vec<(RoadID, IntersectionID, usize)>>(
+    start: IntersectionID,
+    end: IntersectionID,
+    neighbors: F,
+) -> Option<Vec<RoadID>> {
+    let mut best_cost: HashMap<IntersectionID, usize> = HashMap::new();
+    // How each intersection was reached
+    let mut backrefs: HashMap<IntersectionID, (RoadID, IntersectionID)> = HashMap::new();
+    let mut queue: BinaryHeap<(Reverse<usize>, IntersectionID)> = BinaryHeap::new();
+    best_cost.insert(start, 0);
+    queue.push((Reverse(0), start));
+
+    while let Some((Reverse(cost_so_far), current)) = queue.pop() {
+        if current == end {
+            let mut roads = Vec::new();
+            let mut at = end;
+            while at != start {
+                let (r, prev) = backrefs[&at];
+                roads.push(r);
+                at = prev;
+            }
+            roads.reverse();
+            return Some(roads);
+        }
+        if cost_so_far > best_cost[&current] {
+            continue;
+        }
+
+        for (r, next, cost) in neighbors(current) {
+            let next_cost = cost_so_far + cost;
+            if best_cost.get(&next).map(|c| next_cost < *c).unwrap_or(true) {
+                best_cost.insert(next, next_cost);
+                backrefs.insert(next, (r, current));
+                queue.push((Reverse(next_cost), next));
+            }
+        }
+    }
+    None
+}
+
+// A driver whose current lane leads to one end of a closed road with a signed detour, and who
+// later needs to leave from the other end, follows the detour between the two. None if the path
+// doesn't change.
+pub fn follow_detour(path: &Path, map: &Map) -> Option<Path> {
+    let steps = path.get_steps();
+    let current = match steps[0] {
+        PathStep::Lane(l) => l,
+        _ => {
+            return None;
+        }
+    };
+    let i = map.get_l(current).dst_i;
+    for r in &map.get_i(i).roads {
+        let closed = map.get_r(*r);
+        if !closed.is_closed() || closed.detour.is_empty() {
+            continue;
+        }
+        let other = if closed.src_i == i {
+            closed.dst_i
+        } else {
+            closed.src_i
+        };
+        let rejoin = match rejoin_idx(steps, |l| map.get_l(l).src_i == other) {
+            Some(idx) => idx,
+            None => {
+                continue;
+            }
+        };
+        let rejoin_lane = steps[rejoin].as_lane();
+
+        let section = map.pathfind_with_costs(
+            PathRequest {
+                start: Position::new(current, Distance::ZERO),
+                end: Position::new(rejoin_lane, map.get_l(rejoin_lane).length()),
+                constraints: PathConstraints::Car,
+            },
+            |lane, turn| {
+                let base = cost(lane, turn, PathConstraints::Car, map);
+                if lane.id == current || closed.detour.contains(&lane.parent) {
+                    base
+                } else {
+                    base * OFF_DETOUR_PENALTY
+                }
+            },
+        )?;
+
+        return splice(steps, section.get_steps(), rejoin)
+            .map(|new_steps| Path::new(map, new_steps, path.end_dist()));
+    }
+    None
+}
+
+// The first lane after the current one that starts on the far side of the closed road
+fn rejoin_idx<F: Fn(LaneID) -> bool>(steps: &VecDeque<PathStep>, at_other_end: F) -> Option<usize> {
+    steps
+        .iter()
+        .skip(1)
+        .position(|step| match step {
+            PathStep::Lane(l) => at_other_end(*l),
+            _ => false,
+        })
+        .map(|idx| idx + 1)
+}
+
+// Replaces everything up to and including the rejoin step with the section. None if nothing
+// changes.
+fn splice(
+    steps: &VecDeque<PathStep>,
+    section: &VecDeque<PathStep>,
+    rejoin: usize,
+) -> Option<Vec<PathStep>> {
+    let mut new_steps: Vec<PathStep> = section.iter().cloned().collect();
+    new_steps.extend(steps.iter().skip(rejoin + 1).cloned());
+    if new_steps.iter().eq(steps.iter()) {
+        return None;
+    }
+    Some(new_steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TurnID;
+
+    fn i(x: usize) -> IntersectionID {
+        IntersectionID(x)
+    }
+
+    fn r(x: usize) -> RoadID {
+        RoadID(x)
+    }
+
+    fn turn(parent: usize, src: usize, dst: usize) -> PathStep {
+        PathStep::Turn(TurnID {
+            parent: i(parent),
+            src: LaneID(src),
+            dst: LaneID(dst),
+        })
+    }
+
+    #[test]
+    fn test_road_cost() {
+        // Primary
+        assert_eq!(road_cost(Distance::meters(100.0), 15), 100);
+        // Residential
+        assert_eq!(road_cost(Distance::meters(100.0), 3), 300);
+    }
+
+    #[test]
+    fn test_cheapest_route() {
+        // The closed road went 0 -> 3. Around one side is a short residential street through 1,
+        // around the other a longer arterial through 2. 4 is a dead-end.
+        let graph = |at: IntersectionID| match at.0 {
+            0 => vec![
+                (r(1), i(1), road_cost(Distance::meters(100.0), 3)),
+                (r(2), i(2), road_cost(Distance::meters(150.0), 15)),
+                (r(5), i(4), 10),
+            ],
+            1 => vec![(r(3), i(3), road_cost(Distance::meters(100.0), 3))],
+            2 => vec![(r(4), i(3), road_cost(Distance::meters(150.0), 15))],
+            _ => Vec::new(),
+        };
+        assert_eq!(cheapest_route(i(0), i(3), graph), Some(vec![r(2), r(4)]));
+        assert_eq!(cheapest_route(i(0), i(0), graph), Some(Vec::new()));
+        // No way back from the dead-end
+        assert_eq!(cheapest_route(i(4), i(3), graph), None);
+    }
+
+    #[test]
+    fn test_rejoin_and_splice() {
+        // Lane 1 leads to the closed road (lane 2). The path continues from lane 4 to lane 5.
+        let steps: VecDeque<PathStep> = vec![
+            PathStep::Lane(LaneID(1)),
+            turn(10, 1, 2),
+            PathStep::Lane(LaneID(2)),
+            turn(11, 2, 4),
+            PathStep::Lane(LaneID(4)),
+            turn(12, 4, 5),
+            PathStep::Lane(LaneID(5)),
+        ]
+        .into_iter()
+        .collect();
+        let rejoin = rejoin_idx(&steps, |l| l == LaneID(4)).unwrap();
+        assert_eq!(rejoin, 4);
+        // The current lane never counts
+        assert_eq!(rejoin_idx(&steps, |l| l == LaneID(1)), None);
+
+        // Around the block through lane 3 instead
+        let section: VecDeque<PathStep> = vec![
+            PathStep::Lane(LaneID(1)),
+            turn(10, 1, 3),
+            PathStep::Lane(LaneID(3)),
+            turn(11, 3, 4),
+            PathStep::Lane(LaneID(4)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            splice(&steps, &section, rejoin),
+            Some(vec![
+                PathStep::Lane(LaneID(1)),
+                turn(10, 1, 3),
+                PathStep::Lane(LaneID(3)),
+                turn(11, 3, 4),
+                PathStep::Lane(LaneID(4)),
+                turn(12, 4, 5),
+                PathStep::Lane(LaneID(5)),
+            ])
+        );
+
+        // The detour is what the path already did
+        let same: VecDeque<PathStep> = steps.iter().take(rejoin + 1).cloned().collect();
+        assert_eq!(splice(&steps, &same, rejoin), None);
+    }
+}
@@ -1,9 +1,12 @@
+use crate::notes::PermanentNoteTarget;
 use crate::raw::{OriginalIntersection, OriginalRoad};
 use crate::{
-    ControlStopSign, ControlTrafficSignal, IntersectionID, LaneID, LaneType, Map, RoadID, TurnID,
+    ControlStopSign, ControlTrafficSignal, Footbridge, IntersectionID, LaneID, LaneType, Map,
+    Median, NoteTarget, ObjectNote, ParkingRestrictions, PathConstraints, Position, RoadID,
+    ScooterParking, TimedSpeedLimit, TurnID,
 };
 use abstutil::{deserialize_btreemap, retain_btreemap, retain_btreeset, serialize_btreemap, Timer};
-use geom::Speed;
+use geom::{Distance, Duration, Speed};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 
@@ -15,13 +18,27 @@ pub struct MapEdits {
     // Derived from commands, kept up to date by update_derived
     pub original_lts: BTreeMap<LaneID, LaneType>,
     pub reversed_lanes: BTreeSet<LaneID>,
+    pub original_lane_widths: BTreeMap<LaneID, Distance>,
     pub original_intersections: BTreeMap<IntersectionID, EditIntersection>,
     pub changed_speed_limits: BTreeSet<RoadID>,
+    pub changed_timed_speed_limits: BTreeSet<RoadID>,
+    pub changed_snow_routes: BTreeSet<RoadID>,
+    pub consolidated_junctions: BTreeSet<RoadID>,
+    // Crossings of the road at the intersection whose curb ramps differ from the city data
+    pub changed_curb_ramps: BTreeSet<(RoadID, IntersectionID)>,
+    pub changed_footbridges: BTreeSet<RoadID>,
+    pub changed_medians: BTreeSet<RoadID>,
+    pub signed_detours: BTreeSet<RoadID>,
+    pub changed_parking_restrictions: BTreeSet<LaneID>,
+    pub changed_scooter_parking: BTreeSet<LaneID>,
 
     // Edits without these are player generated.
     pub proposal_description: Vec<String>,
     // The link is optional even for proposals
     pub proposal_link: Option<String>,
+
+    // Analysts' notes and tags about objects. Not commands; they don't change the map.
+    pub notes: BTreeMap<NoteTarget, ObjectNote>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -43,11 +60,68 @@ pub enum EditCmd {
         // New intended dst_i
         dst_i: IntersectionID,
     },
+    // The other lanes along the road shift over to make room
+    ChangeLaneWidth {
+        id: LaneID,
+        new: Distance,
+        old: Distance,
+    },
     ChangeSpeedLimit {
         id: RoadID,
         new: Speed,
         old: Speed,
     },
+    // Speed limits that only apply during part of every day
+    ChangeTimedSpeedLimits {
+        id: RoadID,
+        new: Vec<TimedSpeedLimit>,
+        old: Vec<TimedSpeedLimit>,
+    },
+    ChangeSnowRoute {
+        id: RoadID,
+        new: bool,
+        old: bool,
+    },
+    // Merge the intersections at both ends of a short road into one junction for the simulation
+    ConsolidateJunction {
+        id: RoadID,
+        new: bool,
+        old: bool,
+    },
+    // Whether the crosswalks over a road at one end have curb ramps on both sides
+    ChangeCurbRamps {
+        id: RoadID,
+        i: IntersectionID,
+        new: bool,
+        old: bool,
+    },
+    // Bridges and underpasses between the two sidewalks of a road
+    ChangeFootbridges {
+        id: RoadID,
+        new: Vec<Footbridge>,
+        old: Vec<Footbridge>,
+    },
+    ChangeMedian {
+        id: RoadID,
+        new: Option<Median>,
+        old: Option<Median>,
+    },
+    // The route around a closed road. Empty means no detour is signed.
+    SignDetour {
+        id: RoadID,
+        new: Vec<RoadID>,
+        old: Vec<RoadID>,
+    },
+    ChangeParkingRestrictions {
+        id: LaneID,
+        new: ParkingRestrictions,
+        old: ParkingRestrictions,
+    },
+    ChangeScooterParking {
+        id: LaneID,
+        new: ScooterParking,
+        old: ScooterParking,
+    },
     ChangeIntersection {
         i: IntersectionID,
         new: EditIntersection,
@@ -60,7 +134,59 @@ impl EditCmd {
         match self {
             EditCmd::ChangeLaneType { lt, id, .. } => format!("{} on #{}", lt.short_name(), id.0),
             EditCmd::ReverseLane { l, .. } => format!("reverse {}", l),
+            EditCmd::ChangeLaneWidth { id, new, .. } => format!("{} wide #{}", new, id.0),
             EditCmd::ChangeSpeedLimit { id, new, .. } => format!("limit {} for {}", new, id),
+            EditCmd::ChangeTimedSpeedLimits { id, new, .. } => {
+                if new.is_empty() {
+                    format!("no time-of-day speed limits for {}", id)
+                } else {
+                    format!("time-of-day speed limits for {}", id)
+                }
+            }
+            EditCmd::ChangeSnowRoute { id, new, .. } => {
+                if *new {
+                    format!("snow route {}", id)
+                } else {
+                    format!("not a snow route {}", id)
+                }
+            }
+            EditCmd::ConsolidateJunction { id, new, .. } => {
+                if *new {
+                    format!("one junction around {}", id)
+                } else {
+                    format!("separate junctions around {}", id)
+                }
+            }
+            EditCmd::ChangeCurbRamps { id, i, new, .. } => {
+                if *new {
+                    format!("curb ramps on {} at {}", id, i)
+                } else {
+                    format!("no curb ramps on {} at {}", id, i)
+                }
+            }
+            EditCmd::ChangeFootbridges { id, new, .. } => {
+                if new.is_empty() {
+                    format!("no footbridges over {}", id)
+                } else {
+                    format!("{} footbridges over {}", new.len(), id)
+                }
+            }
+            EditCmd::ChangeMedian { id, new, .. } => {
+                if new.is_some() {
+                    format!("median on {}", id)
+                } else {
+                    format!("remove median on {}", id)
+                }
+            }
+            EditCmd::SignDetour { id, new, .. } => {
+                if new.is_empty() {
+                    format!("remove detour around {}", id)
+                } else {
+                    format!("detour around {}", id)
+                }
+            }
+            EditCmd::ChangeParkingRestrictions { id, .. } => format!("parking rules for {}", id),
+            EditCmd::ChangeScooterParking { id, .. } => format!("scooter parking for {}", id),
             EditCmd::ChangeIntersection { i, new, .. } => match new {
                 EditIntersection::StopSign(_) => format!("stop sign #{}", i.0),
                 EditIntersection::TrafficSignal(_) => format!("traffic signal #{}", i.0),
@@ -68,6 +194,85 @@ impl EditCmd {
             },
         }
     }
+
+    // Which pathfinding graphs might this edit change? Moving lanes around changes every lane's
+    // length along the road, so that touches everybody.
+    pub(crate) fn changes_pathfinding(&self, map: &Map) -> Vec<PathConstraints> {
+        let everybody = vec![
+            PathConstraints::Pedestrian,
+            PathConstraints::Car,
+            PathConstraints::Bike,
+            PathConstraints::Bus,
+        ];
+        match self {
+            EditCmd::ChangeLaneType { lt, orig_lt, .. } => {
+                let mut list = lane_users(*lt);
+                for c in lane_users(*orig_lt) {
+                    if !list.contains(&c) {
+                        list.push(c);
+                    }
+                }
+                list
+            }
+            EditCmd::ReverseLane { l, .. } => lane_users(map.get_l(*l).lane_type),
+            EditCmd::ChangeLaneWidth { .. } | EditCmd::ChangeMedian { .. } => everybody,
+            EditCmd::ChangeSpeedLimit { .. } | EditCmd::ChangeTimedSpeedLimits { .. } => vec![
+                PathConstraints::Car,
+                PathConstraints::Bike,
+                PathConstraints::Bus,
+            ],
+            // Crossing costs for wheelchairs, and sidewalk turns
+            EditCmd::ChangeCurbRamps { .. } | EditCmd::ChangeFootbridges { .. } => {
+                vec![PathConstraints::Pedestrian]
+            }
+            // Only the simulation cares about these
+            EditCmd::ChangeSnowRoute { .. }
+            | EditCmd::ConsolidateJunction { .. }
+            | EditCmd::SignDetour { .. }
+            | EditCmd::ChangeParkingRestrictions { .. }
+            | EditCmd::ChangeScooterParking { .. } => Vec::new(),
+            EditCmd::ChangeIntersection { new, old, .. } => {
+                if *new == EditIntersection::Closed || *old == EditIntersection::Closed {
+                    everybody
+                } else {
+                    // Wheelchair users prefer crossing at signals
+                    vec![PathConstraints::Pedestrian]
+                }
+            }
+        }
+    }
+
+    // Can a running simulation pick up this edit without restarting the day? Speed limits, detour
+    // signs, snow routes, and signal or stop sign timing are just read by agents as they go.
+    // Closing a lane that vehicles move along only removes turns, so drivers can route around it.
+    // Anything else changes the structure of the map out from under agents.
+    pub fn can_apply_live(&self) -> bool {
+        match self {
+            EditCmd::ChangeSpeedLimit { .. }
+            | EditCmd::ChangeTimedSpeedLimits { .. }
+            | EditCmd::SignDetour { .. }
+            | EditCmd::ChangeSnowRoute { .. } => true,
+            EditCmd::ChangeLaneType { lt, orig_lt, .. } => {
+                *lt == LaneType::Construction
+                    && (*orig_lt == LaneType::Driving
+                        || *orig_lt == LaneType::Bus
+                        || *orig_lt == LaneType::Biking)
+            }
+            EditCmd::ChangeIntersection { new, old, .. } => match (new, old) {
+                (EditIntersection::StopSign(_), EditIntersection::StopSign(_))
+                | (EditIntersection::TrafficSignal(_), EditIntersection::TrafficSignal(_)) => true,
+                _ => false,
+            },
+            EditCmd::ReverseLane { .. }
+            | EditCmd::ChangeLaneWidth { .. }
+            | EditCmd::ConsolidateJunction { .. }
+            | EditCmd::ChangeCurbRamps { .. }
+            | EditCmd::ChangeFootbridges { .. }
+            | EditCmd::ChangeMedian { .. }
+            | EditCmd::ChangeParkingRestrictions { .. }
+            | EditCmd::ChangeScooterParking { .. } => false,
+        }
+    }
 }
 
 pub struct EditEffects {
@@ -85,11 +290,22 @@ impl MapEdits {
             proposal_description: Vec::new(),
             proposal_link: None,
             commands: Vec::new(),
+            notes: BTreeMap::new(),
 
             original_lts: BTreeMap::new(),
             reversed_lanes: BTreeSet::new(),
+            original_lane_widths: BTreeMap::new(),
             original_intersections: BTreeMap::new(),
             changed_speed_limits: BTreeSet::new(),
+            changed_timed_speed_limits: BTreeSet::new(),
+            changed_snow_routes: BTreeSet::new(),
+            consolidated_junctions: BTreeSet::new(),
+            changed_curb_ramps: BTreeSet::new(),
+            changed_footbridges: BTreeSet::new(),
+            changed_medians: BTreeSet::new(),
+            signed_detours: BTreeSet::new(),
+            changed_parking_restrictions: BTreeSet::new(),
+            changed_scooter_parking: BTreeSet::new(),
         }
     }
 
@@ -103,6 +319,52 @@ impl MapEdits {
         )
     }
 
+    pub fn all_tags(&self) -> BTreeSet<String> {
+        self.notes
+            .values()
+            .flat_map(|n| n.tags.iter().cloned())
+            .collect()
+    }
+
+    pub fn tagged(&self, tag: &str) -> Vec<NoteTarget> {
+        self.notes
+            .iter()
+            .filter(|(_, n)| n.tags.contains(tag))
+            .map(|(t, _)| *t)
+            .collect()
+    }
+
+    // One row per noted object, optionally just the ones with some tag. Notes and descriptions are
+    // quoted, since they're freeform.
+    pub fn export_notes(&self, path: String, tag: Option<&str>, map: &Map) {
+        let mut out = String::from("kind,id,osm_id,description,tags,note\n");
+        for (target, note) in &self.notes {
+            if tag.map(|t| !note.tags.contains(t)).unwrap_or(false) {
+                continue;
+            }
+            let id = match target {
+                NoteTarget::Road(r) => r.0,
+                NoteTarget::Intersection(i) => i.0,
+                NoteTarget::Building(b) => b.0,
+                NoteTarget::ParkingLot(pl) => pl.0,
+                NoteTarget::Area(a) => a.0,
+            };
+            out.push_str(&format!(
+                "{},{},{},\"{}\",{},\"{}\"\n",
+                target.kind(),
+                id,
+                target.osm_id(map),
+                target.describe(map).replace('"', "\"\""),
+                note.tags.iter().cloned().collect::<Vec<_>>().join(";"),
+                note.note.replace('"', "\"\"")
+            ));
+        }
+        if let Err(err) = std::fs::write(&path, out) {
+            panic!("Can't write {}: {}", path, err);
+        }
+        println!("Wrote {}", path);
+    }
+
     // TODO Version these? Or it's unnecessary, since we have a command stack.
     pub(crate) fn save(&self, map: &Map) {
         assert_ne!(self.edits_name, "untitled edits");
@@ -116,8 +378,18 @@ impl MapEdits {
     pub(crate) fn update_derived(&mut self, map: &Map) {
         let mut orig_lts = BTreeMap::new();
         let mut reversed_lanes = BTreeSet::new();
+        let mut orig_lane_widths = BTreeMap::new();
         let mut orig_intersections: BTreeMap<IntersectionID, EditIntersection> = BTreeMap::new();
         let mut changed_speed_limits = BTreeSet::new();
+        let mut changed_timed_speed_limits = BTreeSet::new();
+        let mut changed_snow_routes = BTreeSet::new();
+        let mut consolidated_junctions = BTreeSet::new();
+        let mut changed_curb_ramps = BTreeSet::new();
+        let mut changed_footbridges = BTreeSet::new();
+        let mut changed_medians = BTreeSet::new();
+        let mut signed_detours = BTreeSet::new();
+        let mut changed_parking_restrictions = BTreeSet::new();
+        let mut changed_scooter_parking = BTreeSet::new();
 
         for cmd in &self.commands {
             match cmd {
@@ -133,9 +405,41 @@ impl MapEdits {
                         reversed_lanes.insert(*l);
                     }
                 }
+                EditCmd::ChangeLaneWidth { id, old, .. } => {
+                    if !orig_lane_widths.contains_key(id) {
+                        orig_lane_widths.insert(*id, *old);
+                    }
+                }
                 EditCmd::ChangeSpeedLimit { id, .. } => {
                     changed_speed_limits.insert(*id);
                 }
+                EditCmd::ChangeTimedSpeedLimits { id, .. } => {
+                    changed_timed_speed_limits.insert(*id);
+                }
+                EditCmd::ChangeSnowRoute { id, .. } => {
+                    changed_snow_routes.insert(*id);
+                }
+                EditCmd::ConsolidateJunction { id, .. } => {
+                    consolidated_junctions.insert(*id);
+                }
+                EditCmd::ChangeCurbRamps { id, i, .. } => {
+                    changed_curb_ramps.insert((*id, *i));
+                }
+                EditCmd::ChangeFootbridges { id, .. } => {
+                    changed_footbridges.insert(*id);
+                }
+                EditCmd::ChangeMedian { id, .. } => {
+                    changed_medians.insert(*id);
+                }
+                EditCmd::SignDetour { id, .. } => {
+                    signed_detours.insert(*id);
+                }
+                EditCmd::ChangeParkingRestrictions { id, .. } => {
+                    changed_parking_restrictions.insert(*id);
+                }
+                EditCmd::ChangeScooterParking { id, .. } => {
+                    changed_scooter_parking.insert(*id);
+                }
                 EditCmd::ChangeIntersection { i, ref old, .. } => {
                     if !orig_intersections.contains_key(i) {
                         orig_intersections.insert(*i, old.clone());
@@ -145,17 +449,50 @@ impl MapEdits {
         }
 
         retain_btreemap(&mut orig_lts, |l, lt| map.get_l(*l).lane_type != *lt);
+        retain_btreemap(&mut orig_lane_widths, |l, w| map.get_l(*l).width != *w);
         retain_btreemap(&mut orig_intersections, |i, orig| {
             map.get_i_edit(*i) != orig.clone()
         });
         retain_btreeset(&mut changed_speed_limits, |r| {
             map.get_r(*r).speed_limit != map.get_r(*r).speed_limit_from_osm()
         });
+        retain_btreeset(&mut changed_timed_speed_limits, |r| {
+            map.get_r(*r).timed_speed_limits != map.get_r(*r).timed_speed_limits_from_osm()
+        });
+        retain_btreeset(&mut changed_snow_routes, |r| map.get_r(*r).snow_route);
+        retain_btreeset(&mut consolidated_junctions, |r| {
+            map.get_r(*r).consolidated_junction
+        });
+        retain_btreeset(&mut changed_curb_ramps, |(r, i)| {
+            let road = map.get_r(*r);
+            road.missing_curb_ramps.contains(i) != road.missing_curb_ramps_from_osm().contains(i)
+        });
+        retain_btreeset(&mut changed_footbridges, |r| {
+            !map.get_r(*r).footbridges.is_empty()
+        });
+        retain_btreeset(&mut changed_medians, |r| map.get_r(*r).median.is_some());
+        retain_btreeset(&mut signed_detours, |r| !map.get_r(*r).detour.is_empty());
+        retain_btreeset(&mut changed_parking_restrictions, |l| {
+            map.get_l(*l).parking_restrictions != ParkingRestrictions::unrestricted()
+        });
+        retain_btreeset(&mut changed_scooter_parking, |l| {
+            map.get_l(*l).scooter_parking != ScooterParking::Anywhere
+        });
 
         self.original_lts = orig_lts;
         self.reversed_lanes = reversed_lanes;
+        self.original_lane_widths = orig_lane_widths;
         self.original_intersections = orig_intersections;
         self.changed_speed_limits = changed_speed_limits;
+        self.changed_timed_speed_limits = changed_timed_speed_limits;
+        self.changed_snow_routes = changed_snow_routes;
+        self.consolidated_junctions = consolidated_junctions;
+        self.changed_curb_ramps = changed_curb_ramps;
+        self.changed_footbridges = changed_footbridges;
+        self.changed_medians = changed_medians;
+        self.signed_detours = signed_detours;
+        self.changed_parking_restrictions = changed_parking_restrictions;
+        self.changed_scooter_parking = changed_scooter_parking;
     }
 
     // Assumes update_derived has been called.
@@ -173,6 +510,13 @@ impl MapEdits {
                 dst_i: map.get_l(*l).dst_i,
             });
         }
+        for (l, old) in &self.original_lane_widths {
+            self.commands.push(EditCmd::ChangeLaneWidth {
+                id: *l,
+                new: map.get_l(*l).width,
+                old: *old,
+            });
+        }
         for (i, old) in &self.original_intersections {
             self.commands.push(EditCmd::ChangeIntersection {
                 i: *i,
@@ -187,6 +531,71 @@ impl MapEdits {
                 old: map.get_r(*r).speed_limit_from_osm(),
             });
         }
+        for r in &self.changed_timed_speed_limits {
+            self.commands.push(EditCmd::ChangeTimedSpeedLimits {
+                id: *r,
+                new: map.get_r(*r).timed_speed_limits.clone(),
+                old: map.get_r(*r).timed_speed_limits_from_osm(),
+            });
+        }
+        for r in &self.changed_snow_routes {
+            self.commands.push(EditCmd::ChangeSnowRoute {
+                id: *r,
+                new: true,
+                old: false,
+            });
+        }
+        for r in &self.consolidated_junctions {
+            self.commands.push(EditCmd::ConsolidateJunction {
+                id: *r,
+                new: true,
+                old: false,
+            });
+        }
+        for (r, i) in &self.changed_curb_ramps {
+            let has_ramps = !map.get_r(*r).missing_curb_ramps.contains(i);
+            self.commands.push(EditCmd::ChangeCurbRamps {
+                id: *r,
+                i: *i,
+                new: has_ramps,
+                old: !has_ramps,
+            });
+        }
+        for r in &self.changed_footbridges {
+            self.commands.push(EditCmd::ChangeFootbridges {
+                id: *r,
+                new: map.get_r(*r).footbridges.clone(),
+                old: Vec::new(),
+            });
+        }
+        for r in &self.changed_medians {
+            self.commands.push(EditCmd::ChangeMedian {
+                id: *r,
+                new: map.get_r(*r).median,
+                old: None,
+            });
+        }
+        for r in &self.signed_detours {
+            self.commands.push(EditCmd::SignDetour {
+                id: *r,
+                new: map.get_r(*r).detour.clone(),
+                old: Vec::new(),
+            });
+        }
+        for l in &self.changed_parking_restrictions {
+            self.commands.push(EditCmd::ChangeParkingRestrictions {
+                id: *l,
+                new: map.get_l(*l).parking_restrictions.clone(),
+                old: ParkingRestrictions::unrestricted(),
+            });
+        }
+        for l in &self.changed_scooter_parking {
+            self.commands.push(EditCmd::ChangeScooterParking {
+                id: *l,
+                new: map.get_l(*l).scooter_parking,
+                old: ScooterParking::Anywhere,
+            });
+        }
     }
 }
 
@@ -220,6 +629,9 @@ pub struct PermanentMapEdits {
     pub proposal_description: Vec<String>,
     // The link is optional even for proposals
     pub proposal_link: Option<String>,
+
+    #[serde(default)]
+    notes: Vec<(PermanentNoteTarget, ObjectNote)>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -232,9 +644,37 @@ enum PermanentEditIntersection {
         must_stop: BTreeMap<OriginalRoad, bool>,
     },
     TrafficSignal(seattle_traffic_signals::TrafficSignal),
+    // The external traffic signal format doesn't express offsets, actuation, or leading
+    // pedestrian intervals, so only use this variant when they're needed. Older edits keep loading
+    // as-is. It used to be called OffsetTrafficSignal and only had the offset; the rest is missing
+    // from those edits.
+    #[serde(alias = "OffsetTrafficSignal")]
+    ExtendedTrafficSignal {
+        signal: seattle_traffic_signals::TrafficSignal,
+        offset_seconds: usize,
+        // If present, the signal is actuated, and this has (min green, max green) in seconds for
+        // every phase.
+        #[serde(default)]
+        actuated_phases: Option<Vec<(usize, usize)>>,
+        // If present, the leading pedestrian interval in seconds for every phase
+        #[serde(default)]
+        leading_pedestrian_intervals: Option<Vec<usize>>,
+        // If present, the yellow interval in seconds for every phase
+        #[serde(default)]
+        yellows: Option<Vec<usize>>,
+    },
     Closed,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+struct PermanentFootbridge {
+    from: OriginalLane,
+    from_dist: Distance,
+    to: OriginalLane,
+    to_dist: Distance,
+    underpass: bool,
+}
+
 // Enough data to notice when lanes along a road have changed
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct OriginalLane {
@@ -257,11 +697,62 @@ enum PermanentEditCmd {
         // New intended dst_i
         dst_i: OriginalIntersection,
     },
+    ChangeLaneWidth {
+        id: OriginalLane,
+        new: Distance,
+        old: Distance,
+    },
     ChangeSpeedLimit {
         id: OriginalRoad,
         new: Speed,
         old: Speed,
     },
+    ChangeTimedSpeedLimits {
+        id: OriginalRoad,
+        new: Vec<TimedSpeedLimit>,
+        old: Vec<TimedSpeedLimit>,
+    },
+    ChangeSnowRoute {
+        id: OriginalRoad,
+        new: bool,
+        old: bool,
+    },
+    ConsolidateJunction {
+        id: OriginalRoad,
+        new: bool,
+        old: bool,
+    },
+    ChangeCurbRamps {
+        id: OriginalRoad,
+        i: OriginalIntersection,
+        new: bool,
+        old: bool,
+    },
+    ChangeFootbridges {
+        id: OriginalRoad,
+        new: Vec<PermanentFootbridge>,
+        old: Vec<PermanentFootbridge>,
+    },
+    ChangeMedian {
+        id: OriginalRoad,
+        new: Option<Median>,
+        old: Option<Median>,
+    },
+    SignDetour {
+        id: OriginalRoad,
+        new: Vec<OriginalRoad>,
+        old: Vec<OriginalRoad>,
+    },
+    ChangeParkingRestrictions {
+        id: OriginalLane,
+        new: ParkingRestrictions,
+        old: ParkingRestrictions,
+    },
+    ChangeScooterParking {
+        id: OriginalLane,
+        new: ScooterParking,
+        old: ScooterParking,
+    },
     ChangeIntersection {
         i: OriginalIntersection,
         new: PermanentEditIntersection,
@@ -276,6 +767,11 @@ impl PermanentMapEdits {
             edits_name: edits.edits_name.clone(),
             proposal_description: edits.proposal_description.clone(),
             proposal_link: edits.proposal_link.clone(),
+            notes: edits
+                .notes
+                .iter()
+                .map(|(t, n)| (PermanentNoteTarget::to_permanent(*t, map), n.clone()))
+                .collect(),
             commands: edits
                 .commands
                 .iter()
@@ -291,6 +787,13 @@ impl PermanentMapEdits {
                         l: OriginalLane::to_permanent(*l, map),
                         dst_i: map.get_i(*dst_i).orig_id,
                     },
+                    EditCmd::ChangeLaneWidth { id, new, old } => {
+                        PermanentEditCmd::ChangeLaneWidth {
+                            id: OriginalLane::to_permanent(*id, map),
+                            new: *new,
+                            old: *old,
+                        }
+                    }
                     EditCmd::ChangeSpeedLimit { id, new, old } => {
                         PermanentEditCmd::ChangeSpeedLimit {
                             id: map.get_r(*id).orig_id,
@@ -298,6 +801,66 @@ impl PermanentMapEdits {
                             old: *old,
                         }
                     }
+                    EditCmd::ChangeTimedSpeedLimits { id, new, old } => {
+                        PermanentEditCmd::ChangeTimedSpeedLimits {
+                            id: map.get_r(*id).orig_id,
+                            new: new.clone(),
+                            old: old.clone(),
+                        }
+                    }
+                    EditCmd::ChangeSnowRoute { id, new, old } => {
+                        PermanentEditCmd::ChangeSnowRoute {
+                            id: map.get_r(*id).orig_id,
+                            new: *new,
+                            old: *old,
+                        }
+                    }
+                    EditCmd::ConsolidateJunction { id, new, old } => {
+                        PermanentEditCmd::ConsolidateJunction {
+                            id: map.get_r(*id).orig_id,
+                            new: *new,
+                            old: *old,
+                        }
+                    }
+                    EditCmd::ChangeCurbRamps { id, i, new, old } => {
+                        PermanentEditCmd::ChangeCurbRamps {
+                            id: map.get_r(*id).orig_id,
+                            i: map.get_i(*i).orig_id,
+                            new: *new,
+                            old: *old,
+                        }
+                    }
+                    EditCmd::ChangeFootbridges { id, new, old } => {
+                        PermanentEditCmd::ChangeFootbridges {
+                            id: map.get_r(*id).orig_id,
+                            new: new.iter().map(|fb| fb.to_permanent(map)).collect(),
+                            old: old.iter().map(|fb| fb.to_permanent(map)).collect(),
+                        }
+                    }
+                    EditCmd::ChangeMedian { id, new, old } => PermanentEditCmd::ChangeMedian {
+                        id: map.get_r(*id).orig_id,
+                        new: *new,
+                        old: *old,
+                    },
+                    EditCmd::SignDetour { id, new, old } => PermanentEditCmd::SignDetour {
+                        id: map.get_r(*id).orig_id,
+                        new: new.iter().map(|r| map.get_r(*r).orig_id).collect(),
+                        old: old.iter().map(|r| map.get_r(*r).orig_id).collect(),
+                    },
+                    EditCmd::ChangeParkingRestrictions { id, new, old } => {
+                        PermanentEditCmd::ChangeParkingRestrictions {
+                            id: OriginalLane::to_permanent(*id, map),
+                            new: new.clone(),
+                            old: old.clone(),
+                        }
+                    }
+                    EditCmd::ChangeScooterParking { id, new, old } => {
+                        PermanentEditCmd::ChangeScooterParking {
+                            id: OriginalLane::to_permanent(*id, map),
+                            new: *new,
+                            old: *old,
+                        }
+                    }
                     EditCmd::ChangeIntersection { i, new, old } => {
                         PermanentEditCmd::ChangeIntersection {
                             i: map.get_i(*i).orig_id,
@@ -315,6 +878,11 @@ impl PermanentMapEdits {
             edits_name: perma.edits_name,
             proposal_description: perma.proposal_description,
             proposal_link: perma.proposal_link,
+            notes: perma
+                .notes
+                .into_iter()
+                .filter_map(|(t, n)| t.from_permanent(map).map(|t| (t, n)))
+                .collect(),
             commands: perma
                 .commands
                 .into_iter()
@@ -331,6 +899,10 @@ impl PermanentMapEdits {
                         let dst_i = map.find_i_by_osm_id(dst_i.osm_node_id)?;
                         Ok(EditCmd::ReverseLane { l, dst_i })
                     }
+                    PermanentEditCmd::ChangeLaneWidth { id, new, old } => {
+                        let id = id.from_permanent(map)?;
+                        Ok(EditCmd::ChangeLaneWidth { id, new, old })
+                    }
                     PermanentEditCmd::ChangeSpeedLimit { id, new, old } => {
                         let id = map.find_r_by_osm_id(
                             id.osm_way_id,
@@ -338,6 +910,80 @@ impl PermanentMapEdits {
                         )?;
                         Ok(EditCmd::ChangeSpeedLimit { id, new, old })
                     }
+                    PermanentEditCmd::ChangeTimedSpeedLimits { id, new, old } => {
+                        let id = map.find_r_by_osm_id(
+                            id.osm_way_id,
+                            (id.i1.osm_node_id, id.i2.osm_node_id),
+                        )?;
+                        Ok(EditCmd::ChangeTimedSpeedLimits { id, new, old })
+                    }
+                    PermanentEditCmd::ChangeSnowRoute { id, new, old } => {
+                        let id = map.find_r_by_osm_id(
+                            id.osm_way_id,
+                            (id.i1.osm_node_id, id.i2.osm_node_id),
+                        )?;
+                        Ok(EditCmd::ChangeSnowRoute { id, new, old })
+                    }
+                    PermanentEditCmd::ConsolidateJunction { id, new, old } => {
+                        let id = map.find_r_by_osm_id(
+                            id.osm_way_id,
+                            (id.i1.osm_node_id, id.i2.osm_node_id),
+                        )?;
+                        Ok(EditCmd::ConsolidateJunction { id, new, old })
+                    }
+                    PermanentEditCmd::ChangeCurbRamps { id, i, new, old } => {
+                        let id = map.find_r_by_osm_id(
+                            id.osm_way_id,
+                            (id.i1.osm_node_id, id.i2.osm_node_id),
+                        )?;
+                        let i = map.find_i_by_osm_id(i.osm_node_id)?;
+                        Ok(EditCmd::ChangeCurbRamps { id, i, new, old })
+                    }
+                    PermanentEditCmd::ChangeFootbridges { id, new, old } => {
+                        let id = map.find_r_by_osm_id(
+                            id.osm_way_id,
+                            (id.i1.osm_node_id, id.i2.osm_node_id),
+                        )?;
+                        Ok(EditCmd::ChangeFootbridges {
+                            id,
+                            new: new
+                                .into_iter()
+                                .map(|fb| fb.from_permanent(map))
+                                .collect::<Result<_, _>>()?,
+                            old: old
+                                .into_iter()
+                                .map(|fb| fb.from_permanent(map))
+                                .collect::<Result<_, _>>()?,
+                        })
+                    }
+                    PermanentEditCmd::ChangeMedian { id, new, old } => {
+                        let id = map.find_r_by_osm_id(
+                            id.osm_way_id,
+                            (id.i1.osm_node_id, id.i2.osm_node_id),
+                        )?;
+                        Ok(EditCmd::ChangeMedian { id, new, old })
+                    }
+                    PermanentEditCmd::SignDetour { id, new, old } => {
+                        let find = |id: OriginalRoad| {
+                            map.find_r_by_osm_id(
+                                id.osm_way_id,
+                                (id.i1.osm_node_id, id.i2.osm_node_id),
+                            )
+                        };
+                        Ok(EditCmd::SignDetour {
+                            id: find(id)?,
+                            new: new.into_iter().map(find).collect::<Result<_, _>>()?,
+                            old: old.into_iter().map(find).collect::<Result<_, _>>()?,
+                        })
+                    }
+                    PermanentEditCmd::ChangeParkingRestrictions { id, new, old } => {
+                        let id = id.from_permanent(map)?;
+                        Ok(EditCmd::ChangeParkingRestrictions { id, new, old })
+                    }
+                    PermanentEditCmd::ChangeScooterParking { id, new, old } => {
+                        let id = id.from_permanent(map)?;
+                        Ok(EditCmd::ChangeScooterParking { id, new, old })
+                    }
                     PermanentEditCmd::ChangeIntersection { i, new, old } => {
                         let id = map.find_i_by_osm_id(i.osm_node_id)?;
                         Ok(EditCmd::ChangeIntersection {
@@ -355,14 +1001,54 @@ impl PermanentMapEdits {
 
             original_lts: BTreeMap::new(),
             reversed_lanes: BTreeSet::new(),
+            original_lane_widths: BTreeMap::new(),
             original_intersections: BTreeMap::new(),
             changed_speed_limits: BTreeSet::new(),
+            changed_timed_speed_limits: BTreeSet::new(),
+            changed_snow_routes: BTreeSet::new(),
+            consolidated_junctions: BTreeSet::new(),
+            changed_curb_ramps: BTreeSet::new(),
+            changed_footbridges: BTreeSet::new(),
+            changed_medians: BTreeSet::new(),
+            signed_detours: BTreeSet::new(),
+            changed_parking_restrictions: BTreeSet::new(),
+            changed_scooter_parking: BTreeSet::new(),
         };
         edits.update_derived(map);
         Ok(edits)
     }
 }
 
+impl Footbridge {
+    fn to_permanent(&self, map: &Map) -> PermanentFootbridge {
+        PermanentFootbridge {
+            from: OriginalLane::to_permanent(self.from.lane(), map),
+            from_dist: self.from.dist_along(),
+            to: OriginalLane::to_permanent(self.to.lane(), map),
+            to_dist: self.to.dist_along(),
+            underpass: self.underpass,
+        }
+    }
+}
+
+impl PermanentFootbridge {
+    fn from_permanent(self, map: &Map) -> Result<Footbridge, String> {
+        let from = self.from.from_permanent(map)?;
+        let to = self.to.from_permanent(map)?;
+        if self.from_dist > map.get_l(from).length() || self.to_dist > map.get_l(to).length() {
+            return Err(format!(
+                "footbridge between {} and {} is off the sidewalk",
+                from, to
+            ));
+        }
+        Ok(Footbridge {
+            from: Position::new(from, self.from_dist),
+            to: Position::new(to, self.to_dist),
+            underpass: self.underpass,
+        })
+    }
+}
+
 impl EditIntersection {
     fn to_permanent(&self, map: &Map) -> PermanentEditIntersection {
         match self {
@@ -374,7 +1060,54 @@ impl EditIntersection {
                     .collect(),
             },
             EditIntersection::TrafficSignal(ref ts) => {
-                PermanentEditIntersection::TrafficSignal(ts.export(map))
+                let has_lpi = ts
+                    .phases
+                    .iter()
+                    .any(|p| p.leading_pedestrian_interval > Duration::ZERO);
+                let has_yellow = ts.phases.iter().any(|p| p.yellow != Phase::new().yellow);
+                if ts.offset == Duration::ZERO && !ts.actuated && !has_lpi && !has_yellow {
+                    PermanentEditIntersection::TrafficSignal(ts.export(map))
+                } else {
+                    PermanentEditIntersection::ExtendedTrafficSignal {
+                        signal: ts.export(map),
+                        offset_seconds: ts.offset.inner_seconds() as usize,
+                        actuated_phases: if ts.actuated {
+                            Some(
+                                ts.phases
+                                    .iter()
+                                    .map(|p| {
+                                        (
+                                            p.min_green.inner_seconds() as usize,
+                                            p.max_green.inner_seconds() as usize,
+                                        )
+                                    })
+                                    .collect(),
+                            )
+                        } else {
+                            None
+                        },
+                        leading_pedestrian_intervals: if has_lpi {
+                            Some(
+                                ts.phases
+                                    .iter()
+                                    .map(|p| p.leading_pedestrian_interval.inner_seconds() as usize)
+                                    .collect(),
+                            )
+                        } else {
+                            None
+                        },
+                        yellows: if has_yellow {
+                            Some(
+                                ts.phases
+                                    .iter()
+                                    .map(|p| p.yellow.inner_seconds() as usize)
+                                    .collect(),
+                            )
+                        } else {
+                            None
+                        },
+                    }
+                }
             }
             EditIntersection::Closed => PermanentEditIntersection::Closed,
         }
@@ -408,6 +1141,43 @@ impl PermanentEditIntersection {
             PermanentEditIntersection::TrafficSignal(ts) => Some(EditIntersection::TrafficSignal(
                 ControlTrafficSignal::import(ts, i, map)?,
             )),
+            PermanentEditIntersection::ExtendedTrafficSignal {
+                signal,
+                offset_seconds,
+                actuated_phases,
+                leading_pedestrian_intervals,
+                yellows,
+            } => {
+                let mut ts = ControlTrafficSignal::import(signal, i, map)?;
+                ts.offset = Duration::seconds(offset_seconds as f64);
+                if let Some(greens) = actuated_phases {
+                    if greens.len() != ts.phases.len() {
+                        return None;
+                    }
+                    ts.actuated = true;
+                    for (phase, (min, max)) in ts.phases.iter_mut().zip(greens) {
+                        phase.min_green = Duration::seconds(min as f64);
+                        phase.max_green = Duration::seconds(max as f64);
+                    }
+                }
+                if let Some(lpis) = leading_pedestrian_intervals {
+                    if lpis.len() != ts.phases.len() {
+                        return None;
+                    }
+                    for (phase, lpi) in ts.phases.iter_mut().zip(lpis) {
+                        phase.leading_pedestrian_interval = Duration::seconds(lpi as f64);
+                    }
+                }
+                if let Some(yellows) = yellows {
+                    if yellows.len() != ts.phases.len() {
+                        return None;
+                    }
+                    for (phase, yellow) in ts.phases.iter_mut().zip(yellows) {
+                        phase.yellow = Duration::seconds(yellow as f64);
+                    }
+                }
+                Some(EditIntersection::TrafficSignal(ts))
+            }
             PermanentEditIntersection::Closed => Some(EditIntersection::Closed),
         }
     }
@@ -442,3 +1212,18 @@ impl OriginalLane {
         }
     }
 }
+
+// Whose pathfinding graph includes lanes of this type?
+fn lane_users(lt: LaneType) -> Vec<PathConstraints> {
+    match lt {
+        LaneType::Driving => vec![
+            PathConstraints::Car,
+            PathConstraints::Bike,
+            PathConstraints::Bus,
+        ],
+        LaneType::Bus => vec![PathConstraints::Bike, PathConstraints::Bus],
+        LaneType::Biking => vec![PathConstraints::Bike],
+        LaneType::Sidewalk => vec![PathConstraints::Pedestrian],
+        LaneType::Parking | LaneType::SharedLeftTurn | LaneType::Construction => Vec::new(),
+    }
+}
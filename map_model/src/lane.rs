@@ -1,9 +1,9 @@
 use crate::pathfind;
 use crate::{
     osm, BuildingID, BusStopID, DirectedRoadID, IntersectionID, Map, PathConstraints, Road, RoadID,
-    TurnType,
+    TurnType, NORMAL_LANE_THICKNESS,
 };
-use geom::{Angle, Distance, Line, PolyLine, Pt2D};
+use geom::{Angle, Distance, Duration, Line, PolyLine, Pt2D, Time};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::fmt;
@@ -103,6 +103,113 @@ pub struct Lane {
     // If set, cars trying to park near here should actually start their search at this other lane.
     // Only populated for driving lanes inevitably leading to borders.
     pub parking_blackhole: Option<LaneID>,
+    // Only meaningful for parking lanes
+    pub parking_restrictions: ParkingRestrictions,
+    // Only meaningful for sidewalks
+    pub scooter_parking: ScooterParking,
+}
+
+// Rules for parking along a lane. Nobody gets towed; drivers just take these into account when
+// choosing where to park.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParkingRestrictions {
+    // 0 means free
+    pub cents_per_hour: usize,
+    // None means no limit
+    pub max_duration: Option<Duration>,
+    // No parking from the first hour until the second, every day. If the first is bigger, the ban
+    // lasts overnight.
+    pub banned_hours: Option<(usize, usize)>,
+    // The whole blockface is reserved for vehicles briefly stopping to pick up or drop off
+    // passengers. Nobody can park here.
+    pub pudo_zone: bool,
+    // The whole blockface is reserved for trucks loading and unloading deliveries. Nobody else can
+    // park here.
+    pub loading_zone: bool,
+}
+
+impl ParkingRestrictions {
+    pub fn unrestricted() -> ParkingRestrictions {
+        ParkingRestrictions {
+            cents_per_hour: 0,
+            max_duration: None,
+            banned_hours: None,
+            pudo_zone: false,
+            loading_zone: false,
+        }
+    }
+
+    pub fn is_banned(&self, time: Time) -> bool {
+        if self.pudo_zone || self.loading_zone {
+            return true;
+        }
+        if let Some((start, end)) = self.banned_hours {
+            let hour = time.get_parts().0 % 24;
+            if start <= end {
+                hour >= start && hour < end
+            } else {
+                hour >= start || hour < end
+            }
+        } else {
+            false
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        if self.pudo_zone {
+            return "pickup / drop-off zone, no parking".to_string();
+        }
+        if self.loading_zone {
+            return "loading zone, no parking".to_string();
+        }
+        let mut parts = Vec::new();
+        if self.cents_per_hour > 0 {
+            parts.push(format!(
+                "${}.{:02} / hour",
+                self.cents_per_hour / 100,
+                self.cents_per_hour % 100
+            ));
+        }
+        if let Some(d) = self.max_duration {
+            parts.push(format!("{} max", d));
+        }
+        if let Some((start, end)) = self.banned_hours {
+            parts.push(format!("no parking {}:00 - {}:00", start, end));
+        }
+        if parts.is_empty() {
+            "free, no limits".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+// Where riders may leave dockless scooters along a sidewalk
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ScooterParking {
+    Anywhere,
+    // Only in a corral holding this many devices
+    Corral(usize),
+    Banned,
+}
+
+impl ScooterParking {
+    // How many devices can be left here?
+    pub fn capacity(self) -> Option<usize> {
+        match self {
+            ScooterParking::Anywhere => None,
+            ScooterParking::Corral(n) => Some(n),
+            ScooterParking::Banned => Some(0),
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            ScooterParking::Anywhere => "anywhere".to_string(),
+            ScooterParking::Corral(n) => format!("in a corral for {}", n),
+            ScooterParking::Banned => "banned".to_string(),
+        }
+    }
 }
 
 impl Lane {
@@ -159,6 +266,29 @@ impl Lane {
         self.lane_center_pts.length()
     }
 
+    // Like Road::percent_grade, but in the direction of travel along this lane.
+    pub fn percent_grade(&self, map: &Map) -> f64 {
+        let road = map.get_r(self.parent);
+        let grade = road.percent_grade(map);
+        if road.src_i == self.src_i {
+            grade
+        } else {
+            -grade
+        }
+    }
+
+    // Vehicles slow down in lanes narrower than they're comfortable with. 1.0 means no slowdown.
+    pub fn narrow_lane_speed_factor(&self) -> f64 {
+        let comfortable = match self.lane_type {
+            LaneType::Driving | LaneType::Bus => NORMAL_LANE_THICKNESS,
+            LaneType::Biking => Distance::meters(1.5),
+            _ => {
+                return 1.0;
+            }
+        };
+        (self.width / comfortable).max(0.5).min(1.0)
+    }
+
     pub fn intersections(&self) -> Vec<IntersectionID> {
         // TODO I think we're assuming there are no loop lanes
         vec![self.src_i, self.dst_i]
@@ -3,11 +3,13 @@ mod building;
 mod bus_stop;
 mod city;
 pub mod connectivity;
+mod detour;
 mod edits;
 mod intersection;
 mod lane;
 mod make;
 mod map;
+mod notes;
 pub mod osm;
 mod parking_lot;
 mod pathfind;
@@ -19,22 +21,30 @@ mod traversable;
 mod turn;
 
 pub use crate::area::{Area, AreaID, AreaType};
-pub use crate::building::{Building, BuildingID, FrontPath, OffstreetParking};
+pub use crate::building::{Building, BuildingID, FrontPath, Occupancy, OffstreetParking};
 pub use crate::bus_stop::{BusRoute, BusRouteID, BusStop, BusStopID};
 pub use crate::city::City;
+pub use crate::detour::{follow_detour, propose_detour};
 pub use crate::edits::{
     EditCmd, EditEffects, EditIntersection, MapEdits, OriginalLane, PermanentMapEdits,
 };
 pub use crate::intersection::{Intersection, IntersectionID, IntersectionType};
-pub use crate::lane::{Lane, LaneID, LaneType, PARKING_LOT_SPOT_LENGTH, PARKING_SPOT_LENGTH};
+pub use crate::lane::{
+    Lane, LaneID, LaneType, ParkingRestrictions, ScooterParking, PARKING_LOT_SPOT_LENGTH,
+    PARKING_SPOT_LENGTH,
+};
 pub use crate::make::initial::lane_specs::RoadSpec;
 pub use crate::map::Map;
+pub use crate::notes::{NoteTarget, ObjectNote};
 pub use crate::parking_lot::{ParkingLot, ParkingLotID};
+pub use crate::pathfind::dijkstra::{BIKING_SPEED, WALKING_SPEED};
 pub use crate::pathfind::uber_turns::{IntersectionCluster, UberTurn, UberTurnGroup};
 pub use crate::pathfind::{Path, PathConstraints, PathRequest, PathStep};
-pub use crate::road::{DirectedRoadID, Road, RoadID};
+pub use crate::road::{
+    BikeFacility, DirectedRoadID, Footbridge, Median, Road, RoadID, TimedSpeedLimit,
+};
 pub use crate::stop_signs::{ControlStopSign, RoadWithStopSign};
-pub use crate::traffic_signals::{ControlTrafficSignal, Phase};
+pub use crate::traffic_signals::{ControlTrafficSignal, Phase, PlanPhase, SignalTimingPlan};
 pub use crate::traversable::{Position, Traversable};
 pub use crate::turn::{Turn, TurnGroup, TurnGroupID, TurnID, TurnPriority, TurnType};
 use abstutil::Cloneable;
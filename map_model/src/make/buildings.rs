@@ -1,12 +1,16 @@
 use crate::make::sidewalk_finder::find_sidewalk_points;
 use crate::raw::{OriginalBuilding, RawBuilding, RawParkingLot};
 use crate::{
-    osm, Building, BuildingID, FrontPath, LaneID, LaneType, Map, OffstreetParking, ParkingLot,
-    ParkingLotID, Position, NORMAL_LANE_THICKNESS, PARKING_LOT_SPOT_LENGTH,
+    osm, Building, BuildingID, FrontPath, LaneID, LaneType, Map, Occupancy, OffstreetParking,
+    ParkingLot, ParkingLotID, Position, NORMAL_LANE_THICKNESS, PARKING_LOT_SPOT_LENGTH,
 };
 use abstutil::Timer;
 use geom::{Angle, Distance, HashablePt2D, Line, PolyLine, Polygon, Pt2D, Ring};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+// Rough size of one stall plus its share of the aisles, used to guess the capacity of lots without
+// any mapped aisles
+const AREA_PER_LOT_SPOT: f64 = 30.0;
 
 pub fn make_all_buildings(
     input: &BTreeMap<OriginalBuilding, RawBuilding>,
@@ -63,6 +67,8 @@ pub fn make_all_buildings(
                 },
                 amenities: b.amenities.clone(),
                 parking: None,
+                occupancy: estimate_occupancy(&b.osm_tags, &b.amenities, &b.polygon),
+                height: get_height(&b.osm_tags),
                 label_center: b.polygon.polylabel(),
             };
 
@@ -135,6 +141,8 @@ pub fn make_all_parking_lots(
     );
 
     let mut results = Vec::new();
+    // Per result, from OSM
+    let mut capacities = Vec::new();
     timer.start_iter("create parking lot driveways", center_per_lot.len());
     for (lot_center, orig) in center_per_lot.into_iter().zip(input.iter()) {
         timer.next();
@@ -182,12 +190,14 @@ pub fn make_all_parking_lots(
                     aisles: Vec::new(),
                     osm_id: orig.osm_id,
                     spots: Vec::new(),
+                    extra_spots: 0,
 
                     driveway_line,
                     driving_pos,
                     sidewalk_line,
                     sidewalk_pos: *sidewalk_pos,
                 });
+                capacities.push(orig.capacity);
             } else {
                 timer.warn(format!(
                     "Parking lot from OSM way {} can't have a driveway.",
@@ -225,9 +235,19 @@ pub fn make_all_parking_lots(
     }
 
     timer.start_iter("generate parking lot spots", results.len());
-    for lot in results.iter_mut() {
+    for (lot, capacity) in results.iter_mut().zip(capacities) {
         timer.next();
         lot.spots = infer_spots(&lot.polygon, &lot.aisles);
+
+        // Trust OSM's capacity over the spots laid out along aisles. If neither exists, guess.
+        let capacity = capacity.unwrap_or_else(|| {
+            if lot.spots.is_empty() {
+                (lot.polygon.area() / AREA_PER_LOT_SPOT) as usize
+            } else {
+                lot.spots.len()
+            }
+        });
+        lot.extra_spots = capacity.saturating_sub(lot.spots.len());
     }
 
     timer.stop("convert parking lots");
@@ -235,6 +255,75 @@ pub fn make_all_parking_lots(
     results
 }
 
+// Rough height of one story, for buildings only tagged with the number of levels
+const HEIGHT_PER_LEVEL: Distance = Distance::const_meters(3.0);
+
+fn get_height(tags: &BTreeMap<String, String>) -> Option<Distance> {
+    // Usually plain meters, but sometimes with units attached
+    if let Some(m) = tags
+        .get("height")
+        .and_then(|x| x.trim_end_matches('m').trim().parse::<f64>().ok())
+        .filter(|x| *x > 0.0)
+    {
+        return Some(Distance::meters(m));
+    }
+    tags.get("building:levels")
+        .and_then(|x| x.parse::<f64>().ok())
+        .filter(|x| *x > 0.0)
+        .map(|levels| levels * HEIGHT_PER_LEVEL)
+}
+
+// Floor area in square meters per person living or working somewhere
+const AREA_PER_RESIDENT: f64 = 50.0;
+const AREA_PER_JOB: f64 = 30.0;
+
+fn estimate_occupancy(
+    tags: &BTreeMap<String, String>,
+    amenities: &BTreeSet<(String, String)>,
+    polygon: &Polygon,
+) -> Occupancy {
+    let levels = tags
+        .get("building:levels")
+        .and_then(|x| x.parse::<f64>().ok())
+        .filter(|x| *x >= 1.0)
+        .unwrap_or(1.0);
+    let floor_area = polygon.area() * levels;
+
+    let residential = match tags.get("building").map(|x| x.as_str()) {
+        Some("house")
+        | Some("detached")
+        | Some("semidetached_house")
+        | Some("terrace")
+        | Some("bungalow")
+        | Some("cabin") => {
+            // A single household, no matter how big the house is
+            return Occupancy {
+                residents: 3,
+                jobs: 0,
+            };
+        }
+        Some("residential") | Some("apartments") | Some("dormitory") => true,
+        Some("commercial") | Some("office") | Some("retail") | Some("industrial")
+        | Some("warehouse") | Some("school") | Some("university") | Some("hospital")
+        | Some("church") | Some("civic") | Some("public") | Some("supermarket") => false,
+        // Plain building=yes. Shops and restaurants are usually tagged as amenities; otherwise
+        // guess it's somewhere people live.
+        _ => amenities.is_empty(),
+    };
+    if residential {
+        Occupancy {
+            residents: ((floor_area / AREA_PER_RESIDENT) as usize).max(1),
+            // Ground-floor shops in apartment buildings
+            jobs: amenities.len() * 5,
+        }
+    } else {
+        Occupancy {
+            residents: 0,
+            jobs: ((floor_area / AREA_PER_JOB) as usize).max(1),
+        }
+    }
+}
+
 // Adjust the path to start on the building's border, not center
 fn trim_path(poly: &Polygon, path: Line) -> Line {
     for bldg_line in poly.points().windows(2) {
@@ -18,11 +18,15 @@ pub fn make_bus_stops(
     timer.start("make bus stops");
     let mut bus_stop_pts: HashSet<HashablePt2D> = HashSet::new();
     let mut route_lookups: HashMap<String, Vec<HashablePt2D>> = HashMap::new();
+    let mut gtfs_ids: HashMap<HashablePt2D, String> = HashMap::new();
     for route in bus_routes {
-        for gps in &route.stops {
+        for (idx, gps) in route.stops.iter().enumerate() {
             if let Some(pt) = Pt2D::from_gps(*gps, gps_bounds) {
                 let hash_pt = pt.to_hashable();
                 bus_stop_pts.insert(hash_pt);
+                if let Some(id) = route.stop_ids.get(idx) {
+                    gtfs_ids.entry(hash_pt).or_insert_with(|| id.clone());
+                }
                 route_lookups
                     .entry(route.name.clone())
                     .or_insert_with(Vec::new)
@@ -65,6 +69,7 @@ pub fn make_bus_stops(
                     stop_id,
                     BusStop {
                         id: stop_id,
+                        gtfs_id: gtfs_ids.get(&orig_pt).cloned(),
                         sidewalk_pos,
                         driving_pos,
                     },
@@ -92,7 +97,13 @@ pub fn make_bus_stops(
         routes.push(BusRoute {
             id,
             name: route_name.to_string(),
+            gtfs_id: if route.route_id.is_empty() {
+                None
+            } else {
+                Some(route.route_id.clone())
+            },
             stops,
+            spawn_times: route.spawn_times.clone(),
         });
     }
     timer.stop("make bus stops");
@@ -1,4 +1,5 @@
-use crate::{osm, LaneType};
+use crate::{osm, LaneType, NORMAL_LANE_THICKNESS, SIDEWALK_THICKNESS};
+use geom::Distance;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::{fmt, iter};
@@ -155,6 +156,116 @@ pub fn get_lane_types(osm_tags: &BTreeMap<String, String>) -> (Vec<LaneType>, Ve
     (fwd_side, back_side)
 }
 
+// The width of every lane from get_lane_types, in the same order. Most ways aren't tagged with
+// widths, so this falls back to the defaults.
+pub fn get_lane_widths(
+    osm_tags: &BTreeMap<String, String>,
+    fwd_side: &[LaneType],
+    back_side: &[LaneType],
+) -> (Vec<Distance>, Vec<Distance>) {
+    let oneway = back_side.is_empty();
+    (
+        side_widths(osm_tags, fwd_side, true, oneway),
+        side_widths(osm_tags, back_side, false, oneway),
+    )
+}
+
+fn side_widths(
+    osm_tags: &BTreeMap<String, String>,
+    lane_types: &[LaneType],
+    fwd: bool,
+    oneway: bool,
+) -> Vec<Distance> {
+    let (side, dir) = if fwd {
+        ("right", "forward")
+    } else {
+        ("left", "backward")
+    };
+    // Only the first tag that's present and parses is used.
+    let find = |keys: Vec<String>| -> Option<Distance> {
+        keys.into_iter()
+            .filter_map(|k| osm_tags.get(&k))
+            .filter_map(|v| parse_width(v))
+            .next()
+    };
+
+    // width:lanes lists the lanes for motor vehicles from left to right, which is also the order
+    // here, starting from the center of the road.
+    // TODO This is backwards when driving on the left.
+    let mut motor_widths: Vec<Option<Distance>> = osm_tags
+        .get(&format!("width:lanes:{}", dir))
+        .or_else(|| {
+            if oneway {
+                osm_tags.get("width:lanes")
+            } else {
+                None
+            }
+        })
+        .map(|v| v.split('|').map(parse_width).collect::<Vec<_>>())
+        .unwrap_or_else(Vec::new)
+        .into_iter()
+        .rev()
+        .collect();
+
+    lane_types
+        .iter()
+        .map(|lt| match lt {
+            LaneType::Driving | LaneType::Bus => motor_widths
+                .pop()
+                .flatten()
+                .unwrap_or(NORMAL_LANE_THICKNESS),
+            LaneType::Biking => find(vec![
+                format!("cycleway:{}:width", side),
+                "cycleway:both:width".to_string(),
+                "cycleway:width".to_string(),
+            ])
+            .unwrap_or(NORMAL_LANE_THICKNESS),
+            LaneType::Parking => find(vec![
+                format!(
+                    "{}:width",
+                    if fwd {
+                        osm::PARKING_RIGHT
+                    } else {
+                        osm::PARKING_LEFT
+                    }
+                ),
+                format!("{}:width", osm::PARKING_BOTH),
+            ])
+            .unwrap_or(NORMAL_LANE_THICKNESS),
+            LaneType::Sidewalk => find(vec![
+                format!("sidewalk:{}:width", side),
+                "sidewalk:both:width".to_string(),
+                "sidewalk:width".to_string(),
+            ])
+            .unwrap_or(SIDEWALK_THICKNESS),
+            LaneType::SharedLeftTurn | LaneType::Construction => NORMAL_LANE_THICKNESS,
+        })
+        .collect()
+}
+
+// Understands meters ("3.5" or "3.5 m") and feet ("11'" or "11 ft"). Anything silly is ignored.
+fn parse_width(value: &str) -> Option<Distance> {
+    let value = value.trim();
+    let (num, feet) = if value.ends_with("ft") {
+        (&value[0..value.len() - 2], true)
+    } else if value.ends_with('\'') {
+        (&value[0..value.len() - 1], true)
+    } else if value.ends_with('m') {
+        (&value[0..value.len() - 1], false)
+    } else {
+        (value, false)
+    };
+    let mut meters = num.trim().parse::<f64>().ok()?;
+    if feet {
+        meters *= 0.3048;
+    }
+    if (0.5..=10.0).contains(&meters) {
+        Some(Distance::meters(meters))
+    } else {
+        None
+    }
+}
+
 // This is a convenient way for map_editor to plumb instructions here.
 #[derive(Serialize, Deserialize)]
 pub struct RoadSpec {
@@ -225,3 +336,32 @@ impl RoadSpec {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_width() {
+        assert_eq!(parse_width("3.5"), Some(Distance::meters(3.5)));
+        assert_eq!(parse_width(" 3.5 m "), Some(Distance::meters(3.5)));
+        assert_eq!(parse_width("3m"), Some(Distance::meters(3.0)));
+        assert_eq!(parse_width("11'"), Some(Distance::meters(11.0 * 0.3048)));
+        assert_eq!(parse_width("11 ft"), Some(Distance::meters(11.0 * 0.3048)));
+
+        for bad in vec![
+            "",
+            "m",
+            "ft",
+            "wide",
+            "3.5 meters",
+            "3,5",
+            "NaN",
+            "-3",
+            "0.1",
+            "100",
+        ] {
+            assert_eq!(parse_width(bad), None, "{} parsed", bad);
+        }
+    }
+}
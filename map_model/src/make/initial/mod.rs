@@ -3,7 +3,7 @@ pub mod lane_specs;
 
 pub use self::geometry::intersection_polygon;
 use crate::raw::{OriginalIntersection, OriginalRoad, RawMap, RawRoad};
-use crate::{IntersectionType, LaneType, NORMAL_LANE_THICKNESS, SIDEWALK_THICKNESS};
+use crate::{IntersectionType, LaneType};
 use abstutil::Timer;
 use geom::{Bounds, Distance, PolyLine, Pt2D};
 use std::collections::{BTreeMap, BTreeSet};
@@ -33,15 +33,10 @@ impl Road {
         let mut fwd_width = Distance::ZERO;
         let mut back_width = Distance::ZERO;
         for l in &lane_specs {
-            let w = if l.lane_type == LaneType::Sidewalk {
-                SIDEWALK_THICKNESS
-            } else {
-                NORMAL_LANE_THICKNESS
-            };
             if l.reverse_pts {
-                back_width += w;
+                back_width += l.width;
             } else {
-                fwd_width += w;
+                fwd_width += l.width;
             }
         }
 
@@ -114,22 +109,27 @@ impl InitialMap {
 pub struct LaneSpec {
     pub lane_type: LaneType,
     pub reverse_pts: bool,
+    pub width: Distance,
 }
 
 pub fn get_lane_specs(osm_tags: &BTreeMap<String, String>) -> Vec<LaneSpec> {
     let (side1_types, side2_types) = lane_specs::get_lane_types(osm_tags);
+    let (side1_widths, side2_widths) =
+        lane_specs::get_lane_widths(osm_tags, &side1_types, &side2_types);
 
     let mut specs: Vec<LaneSpec> = Vec::new();
-    for lane_type in side1_types {
+    for (lane_type, width) in side1_types.into_iter().zip(side1_widths) {
         specs.push(LaneSpec {
             lane_type,
             reverse_pts: false,
+            width,
         });
     }
-    for lane_type in side2_types {
+    for (lane_type, width) in side2_types.into_iter().zip(side2_widths) {
         specs.push(LaneSpec {
             lane_type,
             reverse_pts: true,
+            width,
         });
     }
     if specs.is_empty() {
@@ -95,6 +95,7 @@ fn greedy_assignment(map: &Map, intersection: IntersectionID) -> ControlTrafficS
         id: intersection,
         phases,
         offset: Duration::ZERO,
+        actuated: false,
         turn_groups,
     };
     // This must succeed
@@ -123,6 +124,7 @@ fn degenerate(map: &Map, i: IntersectionID) -> Option<ControlTrafficSignal> {
         id: i,
         phases,
         offset: Duration::ZERO,
+        actuated: false,
         turn_groups: TurnGroup::for_i(i, map),
     };
     ts.validate().ok()
@@ -170,6 +172,7 @@ fn three_way(map: &Map, i: IntersectionID) -> Option<ControlTrafficSignal> {
         id: i,
         phases,
         offset: Duration::ZERO,
+        actuated: false,
         turn_groups,
     };
     ts.validate().ok()
@@ -213,6 +216,7 @@ fn four_way_four_phase(map: &Map, i: IntersectionID) -> Option<ControlTrafficSig
         id: i,
         phases,
         offset: Duration::ZERO,
+        actuated: false,
         turn_groups: TurnGroup::for_i(i, map),
     };
     ts.validate().ok()
@@ -255,6 +259,7 @@ fn four_way_two_phase(map: &Map, i: IntersectionID) -> Option<ControlTrafficSign
         id: i,
         phases,
         offset: Duration::ZERO,
+        actuated: false,
         turn_groups: TurnGroup::for_i(i, map),
     };
     ts.validate().ok()
@@ -308,6 +313,7 @@ fn four_oneways(map: &Map, i: IntersectionID) -> Option<ControlTrafficSignal> {
         id: i,
         phases,
         offset: Duration::ZERO,
+        actuated: false,
         turn_groups: TurnGroup::for_i(i, map),
     };
     ts.validate().ok()
@@ -334,6 +340,7 @@ fn all_walk_all_yield(map: &Map, i: IntersectionID) -> ControlTrafficSignal {
         id: i,
         phases: vec![all_walk, all_yield],
         offset: Duration::ZERO,
+        actuated: false,
         turn_groups,
     };
     // This must succeed
@@ -371,6 +378,7 @@ fn phase_per_road(map: &Map, i: IntersectionID) -> Option<ControlTrafficSignal>
         id: i,
         phases,
         offset: Duration::ZERO,
+        actuated: false,
         turn_groups,
     };
     ts.validate().ok()
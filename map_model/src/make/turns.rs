@@ -1,6 +1,7 @@
 use crate::raw::{DrivingSide, RestrictionType};
 use crate::{
-    Intersection, IntersectionID, Lane, LaneID, LaneType, Road, RoadID, Turn, TurnID, TurnType,
+    Footbridge, Intersection, IntersectionID, Lane, LaneID, LaneType, Road, RoadID, Turn, TurnID,
+    TurnType,
 };
 use abstutil::{wraparound_get, Timer, Warn};
 use geom::{Distance, Line, PolyLine, Pt2D, Ring};
@@ -21,6 +22,14 @@ pub fn make_all_turns(
     let mut raw_turns: Vec<Turn> = Vec::new();
     raw_turns.extend(make_vehicle_turns(i, roads, lanes, timer));
     raw_turns.extend(make_walking_turns(driving_side, i, roads, lanes, timer));
+    // Footbridges replace the crosswalk between the same sidewalks
+    let footbridges = make_footbridges(i, roads, lanes);
+    let replaced: BTreeSet<TurnID> = footbridges.iter().map(|t| t.id).collect();
+    raw_turns.retain(|t| !replaced.contains(&t.id));
+    for t in &mut raw_turns {
+        t.other_crosswalk_ids.retain(|id| !replaced.contains(id));
+    }
+    raw_turns.extend(footbridges);
     let unique_turns = ensure_unique(raw_turns);
 
     let mut final_turns: Vec<Turn> = Vec::new();
@@ -431,9 +440,118 @@ fn make_walking_turns(
         }
     }
 
+    if roads.len() == 4 {
+        let diagonals = make_diagonal_crosswalks(i.id, lanes, &roads, idx_offset);
+        for turn in diagonals {
+            if result.iter().all(|t| t.id != turn.id) {
+                result.push(turn);
+            }
+        }
+    }
+
     result
 }
 
+// Cut straight across a 4-way intersection from each corner to the opposite one. Corners are
+// found the same way as SharedSidewalkCorners.
+fn make_diagonal_crosswalks(
+    i: IntersectionID,
+    lanes: &Vec<Lane>,
+    roads: &Vec<&Road>,
+    idx_offset: isize,
+) -> Vec<Turn> {
+    let mut result = Vec::new();
+    for idx1 in 0..roads.len() {
+        if let Some(l1) = get_sidewalk(lanes, roads[idx1].incoming_lanes(i)) {
+            if let Some(l2) = get_sidewalk(
+                lanes,
+                wraparound_get(roads, (idx1 as isize) + 3 * idx_offset).outgoing_lanes(i),
+            ) {
+                if let Some(line) = Line::maybe_new(l1.endpoint(i), l2.endpoint(i)) {
+                    result.push(Turn {
+                        id: turn_id(i, l1.id, l2.id),
+                        turn_type: TurnType::DiagonalCrosswalk,
+                        other_crosswalk_ids: BTreeSet::new(),
+                        geom: line.to_polyline(),
+                    });
+                }
+            }
+        }
+    }
+    result
+}
+
+fn make_footbridges(i: &Intersection, roads: &Vec<Road>, lanes: &Vec<Lane>) -> Vec<Turn> {
+    let mut result = Vec::new();
+    for r in &i.roads {
+        let road = &roads[r.0];
+        for fb in &road.footbridges {
+            if footbridge_end(road, fb, lanes) != i.id {
+                continue;
+            }
+            let l1 = &lanes[fb.from.lane().0];
+            let l2 = &lanes[fb.to.lane().0];
+            // Walk along the first sidewalk to the stairs, cross, then walk back along the other
+            let mut pts = walk_to_footbridge(i.id, l1, fb.from.dist_along());
+            let mut back = walk_to_footbridge(i.id, l2, fb.to.dist_along());
+            back.reverse();
+            pts.extend(back);
+            pts.dedup();
+            if let Some(geom) = PolyLine::maybe_new(pts) {
+                result.push(Turn {
+                    id: turn_id(i.id, l1.id, l2.id),
+                    turn_type: TurnType::Footbridge,
+                    other_crosswalk_ids: BTreeSet::new(),
+                    geom: geom.clone(),
+                });
+                result.push(Turn {
+                    id: turn_id(i.id, l2.id, l1.id),
+                    turn_type: TurnType::Footbridge,
+                    other_crosswalk_ids: BTreeSet::new(),
+                    geom: geom.reversed(),
+                });
+            }
+        }
+    }
+    result
+}
+
+// Whichever end of the road is closer to the middle of the footbridge
+fn footbridge_end(road: &Road, fb: &Footbridge, lanes: &Vec<Lane>) -> IntersectionID {
+    let mut from_src = Distance::ZERO;
+    let mut total = Distance::ZERO;
+    for pos in vec![fb.from, fb.to] {
+        let l = &lanes[pos.lane().0];
+        from_src += if l.src_i == road.src_i {
+            pos.dist_along()
+        } else {
+            l.length() - pos.dist_along()
+        };
+        total += l.length();
+    }
+    if from_src <= total / 2.0 {
+        road.src_i
+    } else {
+        road.dst_i
+    }
+}
+
+// From where the sidewalk meets the intersection to some spot along it
+fn walk_to_footbridge(i: IntersectionID, l: &Lane, dist: Distance) -> Vec<Pt2D> {
+    let slice = if l.dst_i == i {
+        l.lane_center_pts
+            .slice(dist, l.length())
+            .map(|(pl, _)| pl.reversed())
+    } else {
+        l.lane_center_pts
+            .slice(Distance::ZERO, dist)
+            .map(|(pl, _)| pl)
+    };
+    slice
+        .map(|pl| pl.into_points())
+        .unwrap_or_else(|| vec![l.endpoint(i)])
+}
+
 fn make_crosswalks(i: IntersectionID, l1: &Lane, l2: &Lane) -> Vec<Turn> {
     let l1_pt = l1.endpoint(i);
     let l2_pt = l2.endpoint(i);
@@ -713,6 +831,14 @@ fn from_pt(pt: Point2d<f64>) -> Pt2D {
 fn is_turn_allowed(turn: &Turn, roads: &Vec<Road>, lanes: &Vec<Lane>) -> bool {
     let l = &lanes[turn.id.src.0];
     let r = &roads[l.parent.0];
+    // Medians can stop vehicles from turning left onto or off of a road
+    if turn.turn_type == TurnType::Left
+        && vec![r, &roads[lanes[turn.id.dst.0].parent.0]]
+            .into_iter()
+            .any(|r| r.median.map(|m| m.no_left_turns).unwrap_or(false))
+    {
+        return false;
+    }
     if let Some(types) = l.get_turn_restrictions(r) {
         types.contains(&turn.turn_type)
     } else {
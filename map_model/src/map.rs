@@ -1,19 +1,26 @@
-use crate::pathfind::Pathfinder;
+use crate::pathfind::{dijkstra, Pathfinder};
 use crate::raw::{DrivingSide, OriginalIntersection, OriginalRoad, RawMap};
 use crate::{
     connectivity, make, osm, Area, AreaID, Building, BuildingID, BusRoute, BusRouteID, BusStop,
     BusStopID, ControlStopSign, ControlTrafficSignal, EditCmd, EditEffects, EditIntersection,
-    Intersection, IntersectionID, IntersectionType, Lane, LaneID, LaneType, MapEdits, ParkingLot,
-    ParkingLotID, Path, PathConstraints, PathRequest, Position, Road, RoadID, Turn, TurnGroupID,
-    TurnID, TurnType, NORMAL_LANE_THICKNESS, SIDEWALK_THICKNESS,
+    Intersection, IntersectionID, IntersectionType, Lane, LaneID, LaneType, MapEdits, NoteTarget,
+    ObjectNote, ParkingLot, ParkingLotID, ParkingRestrictions, Path, PathConstraints, PathRequest,
+    Position, Road, RoadID, ScooterParking, Turn, TurnGroupID, TurnID, TurnType,
 };
 use abstutil::{deserialize_btreemap, serialize_btreemap, Error, Timer, Warn};
-use geom::{Angle, Bounds, Distance, GPSBounds, Line, PolyLine, Polygon, Pt2D, Speed};
+use geom::{Angle, Bounds, Distance, Duration, GPSBounds, Line, PolyLine, Polygon, Pt2D, Speed};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+// Bump this whenever the serialized form of Map or anything inside it changes. Maps are bincode,
+// so older files can't be read and have to be imported again.
+const MAP_FORMAT_VERSION: usize = 3;
 
 #[derive(Serialize, Deserialize)]
 pub struct Map {
+    // Always MAP_FORMAT_VERSION for maps that load. First, so that older files usually fail to
+    // deserialize right away.
+    format_version: usize,
     roads: Vec<Road>,
     lanes: Vec<Lane>,
     intersections: Vec<Intersection>,
@@ -44,6 +51,9 @@ pub struct Map {
     // TODO Argh, hack, initialization order is hard!
     pathfinder: Option<Pathfinder>,
     pathfinder_dirty: bool,
+    // Which graphs need updating once pathfinding is recalculated
+    #[serde(skip_serializing, skip_deserializing)]
+    dirty_pathfinding: BTreeSet<PathConstraints>,
 
     city_name: String,
     name: String,
@@ -57,6 +67,13 @@ impl Map {
             match abstutil::maybe_read_binary(path.clone(), timer) {
                 Ok(map) => {
                     let map: Map = map;
+                    if map.format_version != MAP_FORMAT_VERSION {
+                        println!(
+                            "\n\n{} was imported by an older version and has to be imported again",
+                            path
+                        );
+                        std::process::exit(1);
+                    }
 
                     if false {
                         use abstutil::{prettyprint_usize, serialized_size_bytes};
@@ -99,6 +116,7 @@ impl Map {
                 Err(err) => {
                     println!("\n\n{} is missing or corrupt. Check https://github.com/dabreegster/abstreet/blob/master/docs/dev.md and file an issue if you have trouble.", path);
                     println!("\n{}", err);
+                    println!("\nIf it was imported by an older version, import it again.");
                     std::process::exit(1);
                 }
             }
@@ -116,6 +134,7 @@ impl Map {
     // Just for temporary std::mem::replace tricks.
     pub fn blank() -> Map {
         Map {
+            format_version: MAP_FORMAT_VERSION,
             roads: Vec::new(),
             lanes: Vec::new(),
             intersections: Vec::new(),
@@ -137,6 +156,7 @@ impl Map {
             driving_side: DrivingSide::Right,
             pathfinder: None,
             pathfinder_dirty: false,
+            dirty_pathfinding: BTreeSet::new(),
             city_name: "blank city".to_string(),
             name: "blank".to_string(),
             edits: MapEdits::new(),
@@ -334,6 +354,29 @@ impl Map {
         &self.intersections[id.0]
     }
 
+    // All of the intersections acting as one junction with this one, including itself. Edits can
+    // consolidate intersections joined by short roads.
+    pub fn get_consolidated_junction(&self, i: IntersectionID) -> BTreeSet<IntersectionID> {
+        let mut members = BTreeSet::new();
+        let mut queue = vec![i];
+        while let Some(i) = queue.pop() {
+            if !members.insert(i) {
+                continue;
+            }
+            for r in &self.get_i(i).roads {
+                let road = self.get_r(*r);
+                if road.consolidated_junction {
+                    queue.push(if road.src_i == i {
+                        road.dst_i
+                    } else {
+                        road.src_i
+                    });
+                }
+            }
+        }
+        members
+    }
+
     pub fn get_t(&self, id: TurnID) -> &Turn {
         // When pathfinding breaks, seeing this TurnID is useful.
         if let Some(ref t) = self.turns.get(&id) {
@@ -548,7 +591,8 @@ impl Map {
     }
 
     pub fn unsaved_edits(&self) -> bool {
-        self.edits.edits_name == "untitled edits" && !self.edits.commands.is_empty()
+        self.edits.edits_name == "untitled edits"
+            && (!self.edits.commands.is_empty() || !self.edits.notes.is_empty())
     }
 
     pub fn save(&self) {
@@ -669,6 +713,36 @@ impl Map {
         self.pathfinder.as_ref().unwrap().pathfind(req, self)
     }
 
+    // Like pathfind, but with costs that can change between calls. Only for vehicles. Slow!
+    pub fn pathfind_with_costs<F: Fn(&Lane, &Turn) -> usize>(
+        &self,
+        req: PathRequest,
+        cost: F,
+    ) -> Option<Path> {
+        dijkstra::pathfind(&req, self, cost)
+    }
+
+    // How long it takes to get from a building to every intersection within the limit, walking
+    // or driving. See dijkstra::travel_times.
+    pub fn travel_times(
+        &self,
+        start: BuildingID,
+        constraints: PathConstraints,
+        limit: Duration,
+    ) -> HashMap<IntersectionID, Duration> {
+        dijkstra::travel_times(self, start, constraints, limit)
+    }
+
+    // Like pathfind for pedestrians, but for people using wheelchairs.
+    pub fn pathfind_wheelchair(&self, req: PathRequest) -> Option<Path> {
+        assert_eq!(req.constraints, PathConstraints::Pedestrian);
+        assert!(!self.pathfinder_dirty);
+        self.pathfinder
+            .as_ref()
+            .unwrap()
+            .pathfind_wheelchair(req, self)
+    }
+
     pub fn should_use_transit(
         &self,
         start: Position,
@@ -680,10 +754,30 @@ impl Map {
             .should_use_transit(self, start, end)
     }
 
-    // None for SharedSidewalkCorners
+    // Can somebody in a wheelchair get on and off the curb at both ends of this turn? Only
+    // crosswalks ever lack ramps; diagonal ones need them on both roads they cross.
+    pub fn crossing_has_curb_ramps(&self, t: TurnID) -> bool {
+        match self.get_t(t).turn_type {
+            TurnType::Crosswalk | TurnType::DiagonalCrosswalk => vec![t.src, t.dst]
+                .into_iter()
+                .all(|l| !self.get_parent(l).missing_curb_ramps.contains(&t.parent)),
+            _ => true,
+        }
+    }
+
+    // Can pedestrians wait on a median halfway along this crosswalk?
+    pub fn crossing_has_refuge(&self, t: TurnID) -> bool {
+        self.get_t(t).turn_type == TurnType::Crosswalk && self.get_parent(t.src).median.is_some()
+    }
+
+    // None for SharedSidewalkCorners, DiagonalCrosswalks, and Footbridges
     pub fn get_turn_group(&self, t: TurnID) -> Option<TurnGroupID> {
         if let Some(ref ts) = self.maybe_get_traffic_signal(t.parent) {
-            if self.get_t(t).turn_type == TurnType::SharedSidewalkCorner {
+            let turn_type = self.get_t(t).turn_type;
+            if turn_type == TurnType::SharedSidewalkCorner
+                || turn_type == TurnType::DiagonalCrosswalk
+                || turn_type == TurnType::Footbridge
+            {
                 return None;
             }
             for tg in ts.turn_groups.values() {
@@ -733,6 +827,32 @@ impl Map {
         None
     }
 
+    pub fn find_pl_by_osm_id(&self, osm_id: i64) -> Option<ParkingLotID> {
+        self.parking_lots
+            .iter()
+            .find(|pl| pl.osm_id == osm_id)
+            .map(|pl| pl.id)
+    }
+
+    pub fn find_a_by_osm_id(&self, osm_id: i64) -> Option<AreaID> {
+        self.areas.iter().find(|a| a.osm_id == osm_id).map(|a| a.id)
+    }
+
+    // Stops are matched to the nearest sidewalk, so a few GTFS stops might share one BusStop
+    pub fn find_bs_by_gtfs_id(&self, gtfs_id: &str) -> Option<BusStopID> {
+        self.bus_stops
+            .values()
+            .find(|bs| bs.gtfs_id.as_ref().map(|id| id == gtfs_id).unwrap_or(false))
+            .map(|bs| bs.id)
+    }
+
+    pub fn find_br_by_gtfs_id(&self, gtfs_id: &str) -> Option<BusRouteID> {
+        self.bus_routes
+            .iter()
+            .find(|br| br.gtfs_id.as_ref().map(|id| id == gtfs_id).unwrap_or(false))
+            .map(|br| br.id)
+    }
+
     pub fn right_shift(&self, pl: PolyLine, width: Distance) -> Warn<PolyLine> {
         self.driving_side.right_shift(pl, width)
     }
@@ -790,6 +910,19 @@ impl Map {
         }
     }
 
+    // Empty notes are removed. Notes don't touch the map, so unlike commands, they're saved right
+    // away for named edits.
+    pub fn set_note(&mut self, target: NoteTarget, note: ObjectNote) {
+        if note.is_empty() {
+            self.edits.notes.remove(&target);
+        } else {
+            self.edits.notes.insert(target, note);
+        }
+        if self.edits.edits_name != "untitled edits" {
+            self.edits.save(self);
+        }
+    }
+
     pub fn save_edits(&mut self) {
         let mut edits = self.edits.clone();
         edits.commands.clear();
@@ -815,6 +948,24 @@ impl Map {
         // Simplest strategy: Remove common prefix.
         let mut effects = EditEffects::new();
 
+        // Everything gets undone and applied again, but only the commands that differ can change
+        // what pathfinding sees.
+        let same_prefix = self
+            .edits
+            .commands
+            .iter()
+            .zip(new_edits.commands.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut dirty = Vec::new();
+        for cmd in self.edits.commands[same_prefix..]
+            .iter()
+            .chain(new_edits.commands[same_prefix..].iter())
+        {
+            dirty.extend(cmd.changes_pathfinding(self));
+        }
+        self.dirty_pathfinding.extend(dirty);
+
         // First undo all existing edits.
         let mut undo = std::mem::replace(&mut self.edits.commands, Vec::new());
         undo.reverse();
@@ -877,7 +1028,8 @@ impl Map {
         }
 
         let mut pathfinder = self.pathfinder.take().unwrap();
-        pathfinder.apply_edits(self, timer);
+        let dirty = std::mem::replace(&mut self.dirty_pathfinding, BTreeSet::new());
+        pathfinder.apply_edits(self, &dirty, timer);
         self.pathfinder = Some(pathfinder);
 
         // Also recompute parking blackholes. This is cheap enough to do from scratch.
@@ -902,6 +1054,7 @@ fn make_half_map(
     timer: &mut Timer,
 ) -> Map {
     let mut map = Map {
+        format_version: MAP_FORMAT_VERSION,
         roads: Vec::new(),
         lanes: Vec::new(),
         intersections: Vec::new(),
@@ -919,6 +1072,7 @@ fn make_half_map(
         driving_side: raw.driving_side,
         pathfinder: None,
         pathfinder_dirty: false,
+        dirty_pathfinding: BTreeSet::new(),
         city_name: raw.city_name.clone(),
         name: raw.name.clone(),
         edits: MapEdits::new(),
@@ -1006,8 +1160,17 @@ fn make_half_map(
             } else {
                 0
             },
+            detour: Vec::new(),
+            timed_speed_limits: Vec::new(),
+            snow_route: false,
+            missing_curb_ramps: BTreeSet::new(),
+            footbridges: Vec::new(),
+            median: None,
+            consolidated_junction: false,
         };
         road.speed_limit = road.speed_limit_from_osm();
+        road.timed_speed_limits = road.timed_speed_limits_from_osm();
+        road.missing_curb_ramps = road.missing_curb_ramps_from_osm();
 
         for lane in &r.lane_specs {
             let id = LaneID(map.lanes.len());
@@ -1027,11 +1190,7 @@ fn make_half_map(
             };
             // TODO probably different behavior for oneways
             // TODO need to factor in yellow center lines (but what's the right thing to even do?
-            let width = if lane.lane_type == LaneType::Sidewalk {
-                SIDEWALK_THICKNESS
-            } else {
-                NORMAL_LANE_THICKNESS
-            };
+            let width = lane.width;
             let lane_center_pts = map
                 .right_shift(unshifted_pts, other_lanes_width + width / 2.0)
                 .with_context(timer, format!("shift for {}", id));
@@ -1047,6 +1206,8 @@ fn make_half_map(
                 building_paths: Vec::new(),
                 bus_stops: Vec::new(),
                 parking_blackhole: None,
+                parking_restrictions: ParkingRestrictions::unrestricted(),
+                scooter_parking: ScooterParking::Anywhere,
             });
         }
         if road.get_name() == "???" {
@@ -1236,6 +1397,50 @@ impl EditCmd {
                 recalculate_turns(dst_i, map, effects, timer);
                 true
             }
+            EditCmd::ChangeLaneWidth { id, new, .. } => {
+                if map.lanes[id.0].width == *new {
+                    return false;
+                }
+
+                // Find the center before any lane moves
+                let r = map.lanes[id.0].parent;
+                let road = &map.roads[r.0];
+                let mut center = road.get_current_center(map);
+                if road.children_forwards.is_empty() {
+                    center = center.reversed();
+                }
+                map.lanes[id.0].width = *new;
+                recalculate_lane_geometry(r, center, map, timer);
+
+                let (src_i, dst_i) = (map.roads[r.0].src_i, map.roads[r.0].dst_i);
+                effects.changed_roads.insert(r);
+                effects.changed_intersections.insert(src_i);
+                effects.changed_intersections.insert(dst_i);
+                recalculate_turns(src_i, map, effects, timer);
+                recalculate_turns(dst_i, map, effects, timer);
+                true
+            }
+            EditCmd::ChangeMedian { id, new, .. } => {
+                if map.roads[id.0].median == *new {
+                    return false;
+                }
+
+                let road = &map.roads[id.0];
+                let mut center = road.get_current_center(map);
+                if road.children_forwards.is_empty() {
+                    center = center.reversed();
+                }
+                map.roads[id.0].median = *new;
+                recalculate_lane_geometry(*id, center, map, timer);
+
+                let (src_i, dst_i) = (map.roads[id.0].src_i, map.roads[id.0].dst_i);
+                effects.changed_roads.insert(*id);
+                effects.changed_intersections.insert(src_i);
+                effects.changed_intersections.insert(dst_i);
+                recalculate_turns(src_i, map, effects, timer);
+                recalculate_turns(dst_i, map, effects, timer);
+                true
+            }
             EditCmd::ChangeSpeedLimit { id, new, .. } => {
                 if map.roads[id.0].speed_limit != *new {
                     map.roads[id.0].speed_limit = *new;
@@ -1245,6 +1450,98 @@ impl EditCmd {
                     false
                 }
             }
+            EditCmd::ChangeTimedSpeedLimits { id, new, .. } => {
+                if map.roads[id.0].timed_speed_limits != *new {
+                    map.roads[id.0].timed_speed_limits = new.clone();
+                    effects.changed_roads.insert(*id);
+                    true
+                } else {
+                    false
+                }
+            }
+            EditCmd::ChangeSnowRoute { id, new, .. } => {
+                if map.roads[id.0].snow_route != *new {
+                    map.roads[id.0].snow_route = *new;
+                    effects.changed_roads.insert(*id);
+                    true
+                } else {
+                    false
+                }
+            }
+            EditCmd::ConsolidateJunction { id, new, .. } => {
+                // Edits loaded from a file might not have gone through the UI's check
+                if *new && !map.roads[id.0].can_consolidate_junction(map) {
+                    timer.warn(format!(
+                        "Not consolidating {} into one junction; it's too long or touches a \
+                         border",
+                        id
+                    ));
+                    return false;
+                }
+                if map.roads[id.0].consolidated_junction != *new {
+                    map.roads[id.0].consolidated_junction = *new;
+                    effects.changed_roads.insert(*id);
+                    effects.changed_intersections.insert(map.roads[id.0].src_i);
+                    effects.changed_intersections.insert(map.roads[id.0].dst_i);
+                    true
+                } else {
+                    false
+                }
+            }
+            EditCmd::ChangeCurbRamps { id, i, new, .. } => {
+                let road = &mut map.roads[id.0];
+                // new means the crossing has ramps
+                let changed = if *new {
+                    road.missing_curb_ramps.remove(i)
+                } else {
+                    road.missing_curb_ramps.insert(*i)
+                };
+                if changed {
+                    effects.changed_roads.insert(*id);
+                }
+                changed
+            }
+            EditCmd::ChangeFootbridges { id, new, .. } => {
+                let r = &mut map.roads[id.0];
+                if r.footbridges == *new {
+                    return false;
+                }
+                r.footbridges = new.clone();
+                effects.changed_roads.insert(r.id);
+                effects.changed_intersections.insert(r.src_i);
+                effects.changed_intersections.insert(r.dst_i);
+                let (src_i, dst_i) = (r.src_i, r.dst_i);
+                recalculate_turns(src_i, map, effects, timer);
+                recalculate_turns(dst_i, map, effects, timer);
+                true
+            }
+            EditCmd::SignDetour { id, new, .. } => {
+                if map.roads[id.0].detour != *new {
+                    map.roads[id.0].detour = new.clone();
+                    effects.changed_roads.insert(*id);
+                    true
+                } else {
+                    false
+                }
+            }
+            EditCmd::ChangeParkingRestrictions { id, new, .. } => {
+                if map.lanes[id.0].parking_restrictions != *new {
+                    map.lanes[id.0].parking_restrictions = new.clone();
+                    effects.changed_roads.insert(map.lanes[id.0].parent);
+                    true
+                } else {
+                    false
+                }
+            }
+            EditCmd::ChangeScooterParking { id, new, .. } => {
+                if map.lanes[id.0].scooter_parking != *new {
+                    map.lanes[id.0].scooter_parking = *new;
+                    effects.changed_roads.insert(map.lanes[id.0].parent);
+                    true
+                } else {
+                    false
+                }
+            }
             EditCmd::ChangeIntersection {
                 i,
                 ref new,
@@ -1301,6 +1598,18 @@ impl EditCmd {
                 }
                 .apply(effects, map, timer)
             }
+            EditCmd::ChangeLaneWidth { id, new, old } => EditCmd::ChangeLaneWidth {
+                id: *id,
+                new: *old,
+                old: *new,
+            }
+            .apply(effects, map, timer),
+            EditCmd::ChangeMedian { id, new, old } => EditCmd::ChangeMedian {
+                id: *id,
+                new: *old,
+                old: *new,
+            }
+            .apply(effects, map, timer),
             EditCmd::ChangeSpeedLimit { id, old, .. } => {
                 if map.roads[id.0].speed_limit != *old {
                     map.roads[id.0].speed_limit = *old;
@@ -1310,6 +1619,57 @@ impl EditCmd {
                     false
                 }
             }
+            EditCmd::ChangeTimedSpeedLimits { id, new, old } => EditCmd::ChangeTimedSpeedLimits {
+                id: *id,
+                new: old.clone(),
+                old: new.clone(),
+            }
+            .apply(effects, map, timer),
+            EditCmd::ChangeSnowRoute { id, new, old } => EditCmd::ChangeSnowRoute {
+                id: *id,
+                new: *old,
+                old: *new,
+            }
+            .apply(effects, map, timer),
+            EditCmd::ConsolidateJunction { id, new, old } => EditCmd::ConsolidateJunction {
+                id: *id,
+                new: *old,
+                old: *new,
+            }
+            .apply(effects, map, timer),
+            EditCmd::ChangeCurbRamps { id, i, new, old } => EditCmd::ChangeCurbRamps {
+                id: *id,
+                i: *i,
+                new: *old,
+                old: *new,
+            }
+            .apply(effects, map, timer),
+            EditCmd::ChangeFootbridges { id, new, old } => EditCmd::ChangeFootbridges {
+                id: *id,
+                new: old.clone(),
+                old: new.clone(),
+            }
+            .apply(effects, map, timer),
+            EditCmd::SignDetour { id, new, old } => EditCmd::SignDetour {
+                id: *id,
+                new: old.clone(),
+                old: new.clone(),
+            }
+            .apply(effects, map, timer),
+            EditCmd::ChangeParkingRestrictions { id, new, old } => {
+                EditCmd::ChangeParkingRestrictions {
+                    id: *id,
+                    new: old.clone(),
+                    old: new.clone(),
+                }
+                .apply(effects, map, timer)
+            }
+            EditCmd::ChangeScooterParking { id, new, old } => EditCmd::ChangeScooterParking {
+                id: *id,
+                new: *old,
+                old: *new,
+            }
+            .apply(effects, map, timer),
             EditCmd::ChangeIntersection {
                 i,
                 ref old,
@@ -1324,6 +1684,27 @@ impl EditCmd {
     }
 }
 
+// Lay out every lane of a road outwards from the center again, using their current widths and
+// leaving room for any median. The intersection polygons at either end aren't recalculated, so a
+// much wider road might poke out of them a bit.
+fn recalculate_lane_geometry(r: RoadID, center: PolyLine, map: &mut Map, timer: &mut Timer) {
+    let road = &map.roads[r.0];
+    let median = road.median_width();
+    for (lanes, pl) in vec![
+        (road.children_forwards.clone(), center.clone()),
+        (road.children_backwards.clone(), center.reversed()),
+    ] {
+        let mut width_so_far = median / 2.0;
+        for (l, _) in lanes {
+            let width = map.lanes[l.0].width;
+            map.lanes[l.0].lane_center_pts = map
+                .right_shift(pl.clone(), width_so_far + width / 2.0)
+                .with_context(timer, format!("shift for {}", l));
+            width_so_far += width;
+        }
+    }
+}
+
 // This clobbers previously set traffic signal overrides.
 // TODO Step 1: Detect and warn about that
 // TODO Step 2: Avoid when possible
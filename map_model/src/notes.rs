@@ -0,0 +1,135 @@
+use crate::raw::{OriginalIntersection, OriginalRoad};
+use crate::{AreaID, BuildingID, IntersectionID, Map, ParkingLotID, RoadID};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Something on the map an analyst can write about. Lanes don't get their own notes; they share
+// their road's.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NoteTarget {
+    Road(RoadID),
+    Intersection(IntersectionID),
+    Building(BuildingID),
+    ParkingLot(ParkingLotID),
+    Area(AreaID),
+}
+
+// Freeform notes and tags don't change the map at all, but they live alongside a proposal's
+// edits, so the study that produced them travels with it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjectNote {
+    pub note: String,
+    pub tags: BTreeSet<String>,
+}
+
+impl ObjectNote {
+    pub fn new() -> ObjectNote {
+        ObjectNote {
+            note: String::new(),
+            tags: BTreeSet::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.note.is_empty() && self.tags.is_empty()
+    }
+
+    // Comma-separated, ignoring case and extra whitespace
+    pub fn parse_tags(raw: &str) -> BTreeSet<String> {
+        raw.split(',')
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect()
+    }
+}
+
+impl NoteTarget {
+    pub fn kind(self) -> &'static str {
+        match self {
+            NoteTarget::Road(_) => "road",
+            NoteTarget::Intersection(_) => "intersection",
+            NoteTarget::Building(_) => "building",
+            NoteTarget::ParkingLot(_) => "parking lot",
+            NoteTarget::Area(_) => "area",
+        }
+    }
+
+    // The OSM way or node, for matching up with other data
+    pub fn osm_id(self, map: &Map) -> i64 {
+        match self {
+            NoteTarget::Road(r) => map.get_r(r).orig_id.osm_way_id,
+            NoteTarget::Intersection(i) => map.get_i(i).orig_id.osm_node_id,
+            NoteTarget::Building(b) => map.get_b(b).osm_way_id,
+            NoteTarget::ParkingLot(pl) => map.get_pl(pl).osm_id,
+            NoteTarget::Area(a) => map.get_a(a).osm_id,
+        }
+    }
+
+    pub fn describe(self, map: &Map) -> String {
+        match self {
+            NoteTarget::Road(r) => format!("{} ({})", r, map.get_r(r).get_name()),
+            NoteTarget::Intersection(i) => format!(
+                "{} ({})",
+                i,
+                map.get_i(i)
+                    .roads
+                    .iter()
+                    .map(|r| map.get_r(*r).get_name())
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect::<Vec<_>>()
+                    .join(" & ")
+            ),
+            NoteTarget::Building(b) => format!("{} ({})", b, map.get_b(b).address),
+            NoteTarget::ParkingLot(pl) => format!("{}", pl),
+            NoteTarget::Area(a) => format!("{}", a),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) enum PermanentNoteTarget {
+    Road(OriginalRoad),
+    Intersection(OriginalIntersection),
+    Building(i64),
+    ParkingLot(i64),
+    Area(i64),
+}
+
+impl PermanentNoteTarget {
+    pub(crate) fn to_permanent(target: NoteTarget, map: &Map) -> PermanentNoteTarget {
+        match target {
+            NoteTarget::Road(r) => PermanentNoteTarget::Road(map.get_r(r).orig_id),
+            NoteTarget::Intersection(i) => PermanentNoteTarget::Intersection(map.get_i(i).orig_id),
+            NoteTarget::Building(b) => PermanentNoteTarget::Building(map.get_b(b).osm_way_id),
+            NoteTarget::ParkingLot(pl) => PermanentNoteTarget::ParkingLot(map.get_pl(pl).osm_id),
+            NoteTarget::Area(a) => PermanentNoteTarget::Area(map.get_a(a).osm_id),
+        }
+    }
+
+    // Unlike edit commands, a note on something that's vanished from the basemap isn't worth
+    // failing to load the whole proposal over, so this just returns None.
+    pub(crate) fn from_permanent(self, map: &Map) -> Option<NoteTarget> {
+        match self {
+            PermanentNoteTarget::Road(id) => map
+                .find_r_by_osm_id(id.osm_way_id, (id.i1.osm_node_id, id.i2.osm_node_id))
+                .ok()
+                .map(NoteTarget::Road),
+            PermanentNoteTarget::Intersection(id) => map
+                .find_i_by_osm_id(id.osm_node_id)
+                .ok()
+                .map(NoteTarget::Intersection),
+            PermanentNoteTarget::Building(id) => map.find_b_by_osm_id(id).map(NoteTarget::Building),
+            PermanentNoteTarget::ParkingLot(id) => map
+                .all_parking_lots()
+                .iter()
+                .find(|pl| pl.osm_id == id)
+                .map(|pl| NoteTarget::ParkingLot(pl.id)),
+            PermanentNoteTarget::Area(id) => map
+                .all_areas()
+                .iter()
+                .find(|a| a.osm_id == id)
+                .map(|a| NoteTarget::Area(a.id)),
+        }
+    }
+}
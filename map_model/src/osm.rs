@@ -5,6 +5,8 @@
 pub const NAME: &str = "name";
 pub const HIGHWAY: &str = "highway";
 pub const MAXSPEED: &str = "maxspeed";
+pub const MAXSPEED_CONDITIONAL: &str = "maxspeed:conditional";
+pub const MAXSPEED_HGV_CONDITIONAL: &str = "maxspeed:hgv:conditional";
 pub const PARKING_RIGHT: &str = "parking:lane:right";
 pub const PARKING_LEFT: &str = "parking:lane:left";
 pub const PARKING_BOTH: &str = "parking:lane:both";
@@ -30,3 +32,6 @@ pub const SYNTHETIC_LANES: &str = "abst:synthetic_lanes";
 // Any roads might have these.
 pub const INFERRED_PARKING: &str = "abst:parking_inferred";
 pub const INFERRED_SIDEWALKS: &str = "abst:sidewalks_inferred";
+// From city data, which ends of a road are crossed without curb ramps on both sides: "start",
+// "end", or "both"
+pub const MISSING_CURB_RAMPS: &str = "abst:missing_curb_ramps";
@@ -29,6 +29,10 @@ pub struct ParkingLot {
     pub osm_id: i64,
     // The middle of the "T", pointing towards the parking aisle
     pub spots: Vec<(Pt2D, Angle)>,
+    // Spots that should exist (per OSM's capacity tag, or guessed from the lot's area), but that
+    // couldn't be laid out along any mapped aisle. They have no geometry, so cars parked there
+    // aren't drawn.
+    pub extra_spots: usize,
 
     // Goes from the lot to the driving lane
     pub driveway_line: PolyLine,
@@ -40,3 +44,9 @@ pub struct ParkingLot {
     pub sidewalk_line: Line,
     pub sidewalk_pos: Position,
 }
+
+impl ParkingLot {
+    pub fn capacity(&self) -> usize {
+        self.spots.len() + self.extra_spots
+    }
+}
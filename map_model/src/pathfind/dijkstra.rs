@@ -0,0 +1,153 @@
+use crate::{
+    BuildingID, IntersectionID, Lane, LaneID, Map, Path, PathConstraints, PathRequest, PathStep,
+    Turn, TurnID,
+};
+use geom::{Distance, Duration, Speed};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// Typical, not the fastest
+pub const WALKING_SPEED: Speed = Speed::const_meters_per_second(1.34);
+pub const BIKING_SPEED: Speed = Speed::const_meters_per_second(4.5);
+
+// Plain Dijkstra's over lanes for vehicles. Much slower than the contraction hierarchy, but the
+// caller can supply costs that change all the time. The cost of a lane and the turn leaving it
+// are combined, like driving::cost.
+pub fn pathfind<F: Fn(&Lane, &Turn) -> usize>(
+    req: &PathRequest,
+    map: &Map,
+    cost: F,
+) -> Option<Path> {
+    let start = req.start.lane();
+    let end = req.end.lane();
+    if start == end && req.start.dist_along() <= req.end.dist_along() {
+        return Some(Path::new(
+            map,
+            vec![PathStep::Lane(start)],
+            req.end.dist_along(),
+        ));
+    }
+
+    // The start lane isn't in best_cost, so a path looping back to it can still be found when it's
+    // also the end.
+    let mut best_cost: HashMap<LaneID, usize> = HashMap::new();
+    let mut backrefs: HashMap<LaneID, TurnID> = HashMap::new();
+    let mut queue: BinaryHeap<(Reverse<usize>, LaneID)> = BinaryHeap::new();
+    queue.push((Reverse(0), start));
+    let mut expanded_start = false;
+
+    while let Some((Reverse(cost_so_far), current)) = queue.pop() {
+        if current == end && backrefs.contains_key(&end) {
+            let mut steps = vec![PathStep::Lane(end)];
+            let mut at = end;
+            loop {
+                let t = backrefs[&at];
+                steps.push(PathStep::Turn(t));
+                steps.push(PathStep::Lane(t.src));
+                if t.src == start {
+                    break;
+                }
+                at = t.src;
+            }
+            steps.reverse();
+            return Some(Path::new(map, steps, req.end.dist_along()));
+        }
+        if current == start {
+            if expanded_start {
+                continue;
+            }
+            expanded_start = true;
+        } else if cost_so_far > best_cost[&current] {
+            continue;
+        }
+
+        let lane = map.get_l(current);
+        for turn in map.get_turns_for(current, req.constraints) {
+            let next = turn.id.dst;
+            let next_cost = cost_so_far + cost(lane, turn);
+            if best_cost.get(&next).map(|c| next_cost < *c).unwrap_or(true) {
+                best_cost.insert(next, next_cost);
+                backrefs.insert(next, turn.id);
+                queue.push((Reverse(next_cost), next));
+            }
+        }
+    }
+    None
+}
+
+// How long it takes to walk or drive from a building to every intersection, up to some limit.
+// This is multi-source Dijkstra's over intersections, starting from both ends of the building's
+// sidewalk, or the far end of its driving lane. People on foot can use sidewalks either way;
+// vehicles follow the direction of each lane at the speed limit, with bikes going no faster than
+// BIKING_SPEED. Turn restrictions and delays at intersections are ignored, so this is optimistic
+// for driving.
+pub fn travel_times(
+    map: &Map,
+    start: BuildingID,
+    constraints: PathConstraints,
+    limit: Duration,
+) -> HashMap<IntersectionID, Duration> {
+    let walking = constraints == PathConstraints::Pedestrian;
+    let speed = |l: &Lane| {
+        if walking {
+            WALKING_SPEED
+        } else if constraints == PathConstraints::Bike {
+            map.get_parent(l.id).speed_limit.min(BIKING_SPEED)
+        } else {
+            map.get_parent(l.id).speed_limit
+        }
+    };
+
+    let mut queue: BinaryHeap<(Reverse<Duration>, IntersectionID)> = BinaryHeap::new();
+    let sidewalk = map.get_b(start).front_path.sidewalk;
+    if walking {
+        let lane = map.get_l(sidewalk.lane());
+        queue.push((Reverse(sidewalk.dist_along() / WALKING_SPEED), lane.src_i));
+        queue.push((
+            Reverse((lane.length() - sidewalk.dist_along()) / WALKING_SPEED),
+            lane.dst_i,
+        ));
+    } else {
+        let lane = map.get_l(map.find_driving_lane_near_building(start));
+        // The driving lane might be on a different road, if the closest one was a dead-end.
+        let dist = if lane.parent == map.get_l(sidewalk.lane()).parent {
+            sidewalk
+                .equiv_pos(lane.id, Distance::ZERO, map)
+                .dist_along()
+        } else {
+            Distance::ZERO
+        };
+        queue.push((Reverse((lane.length() - dist) / speed(lane)), lane.dst_i));
+    }
+
+    let mut best: HashMap<IntersectionID, Duration> = HashMap::new();
+    while let Some((Reverse(time), i)) = queue.pop() {
+        if time > limit || best.contains_key(&i) {
+            continue;
+        }
+        best.insert(i, time);
+
+        let intersection = map.get_i(i);
+        let mut next = Vec::new();
+        for l in &intersection.outgoing_lanes {
+            let lane = map.get_l(*l);
+            if constraints.can_use(lane, map) {
+                next.push((lane, lane.dst_i));
+            }
+        }
+        if walking {
+            for l in &intersection.incoming_lanes {
+                let lane = map.get_l(*l);
+                if constraints.can_use(lane, map) {
+                    next.push((lane, lane.src_i));
+                }
+            }
+        }
+        for (lane, to) in next {
+            if !best.contains_key(&to) {
+                queue.push((Reverse(time + lane.length() / speed(lane)), to));
+            }
+        }
+    }
+    best
+}
@@ -1,4 +1,4 @@
-use crate::pathfind::node_map::{deserialize_nodemap, NodeMap};
+use crate::pathfind::node_map::{deserialize_nodemap, fingerprint, NodeMap};
 use crate::pathfind::uber_turns::{IntersectionCluster, UberTurn};
 use crate::{Lane, LaneID, Map, Path, PathConstraints, PathRequest, PathStep, Turn, TurnID};
 use abstutil::MultiMap;
@@ -15,6 +15,8 @@ pub struct VehiclePathfinder {
     nodes: NodeMap<Node>,
     uber_turns: Vec<UberTurn>,
     constraints: PathConstraints,
+    // Of the input graph, to skip contracting again when edits don't change it
+    input_fingerprint: u64,
 
     #[serde(skip_serializing, skip_deserializing)]
     path_calc: ThreadLocal<RefCell<PathCalculator>>,
@@ -50,6 +52,7 @@ impl VehiclePathfinder {
         }
 
         let input_graph = make_input_graph(map, &nodes, &uber_turns, constraints);
+        let input_fingerprint = fingerprint(&input_graph);
 
         // All VehiclePathfinders have the same nodes (lanes), so if we're not the first being
         // built, seed from the node ordering.
@@ -65,6 +68,7 @@ impl VehiclePathfinder {
             nodes,
             uber_turns,
             constraints,
+            input_fingerprint,
             path_calc: ThreadLocal::new(),
         }
     }
@@ -113,14 +117,21 @@ impl VehiclePathfinder {
         ))
     }
 
-    pub fn apply_edits(&mut self, map: &Map) {
+    // Returns false if the edits didn't change anything this graph cares about.
+    pub fn apply_edits(&mut self, map: &Map) -> bool {
         // The NodeMap is just all lanes and uber-turns -- it won't change. So we can also reuse
         // the node ordering.
         // TODO Make sure the result of this is deterministic and equivalent to computing from
         // scratch.
         let input_graph = make_input_graph(map, &self.nodes, &self.uber_turns, self.constraints);
+        let input_fingerprint = fingerprint(&input_graph);
+        if input_fingerprint == self.input_fingerprint {
+            return false;
+        }
         let node_ordering = self.graph.get_node_ordering();
         self.graph = fast_paths::prepare_with_order(&input_graph, &node_ordering).unwrap();
+        self.input_fingerprint = input_fingerprint;
+        true
     }
 }
 
@@ -1,3 +1,4 @@
+pub(crate) mod dijkstra;
 mod driving;
 mod node_map;
 // TODO tmp
@@ -13,7 +14,7 @@ use crate::{
 use abstutil::Timer;
 use geom::{Distance, PolyLine, EPSILON_DIST};
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, VecDeque};
 use std::fmt;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -158,6 +159,10 @@ impl Path {
         self.crossed_so_far
     }
 
+    pub fn end_dist(&self) -> Distance {
+        self.end_dist
+    }
+
     pub fn total_length(&self) -> Distance {
         self.total_length
     }
@@ -193,9 +198,13 @@ impl Path {
         self.steps.push_back(step);
     }
 
-    // Trusting the caller to do this in valid ways.
+    // Trusting the caller to do this in valid ways. The current step can only be swapped for
+    // another lane on the same road, when a vehicle changes lanes partway along it.
     pub fn modify_step(&mut self, idx: usize, step: PathStep, map: &Map) {
-        assert!(idx != 0);
+        assert!(
+            idx != 0
+                || map.get_l(self.steps[0].as_lane()).parent == map.get_l(step.as_lane()).parent
+        );
         self.total_length -= self.steps[idx].as_traversable().length(map);
         self.steps[idx] = step;
         self.total_length += self.steps[idx].as_traversable().length(map);
@@ -220,6 +229,16 @@ impl Path {
         self.steps[self.steps.len() - 1]
     }
 
+    // Live map edits might close a lane or remove a turn that's still ahead.
+    pub fn is_still_valid(&self, constraints: PathConstraints, map: &Map) -> bool {
+        self.steps.iter().all(|step| match step {
+            PathStep::Lane(l) | PathStep::ContraflowLane(l) => {
+                constraints.can_use(map.get_l(*l), map)
+            }
+            PathStep::Turn(t) => map.maybe_get_t(*t).is_some(),
+        })
+    }
+
     // dist_ahead is unlimited when None.
     pub fn trace(
         &self,
@@ -321,7 +340,7 @@ impl Path {
 
 // Who's asking for a path?
 // TODO This is an awful name.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PathConstraints {
     Pedestrian,
     Car,
@@ -474,6 +493,7 @@ pub struct Pathfinder {
     bike_graph: VehiclePathfinder,
     bus_graph: VehiclePathfinder,
     walking_graph: SidewalkPathfinder,
+    wheelchair_graph: SidewalkPathfinder,
     // TODO Option just during initialization! Ewww.
     walking_with_transit_graph: Option<SidewalkPathfinder>,
 }
@@ -495,20 +515,26 @@ impl Pathfinder {
         timer.stop("prepare pathfinding for buses");
 
         timer.start("prepare pathfinding for pedestrians");
-        let walking_graph = SidewalkPathfinder::new(map, false, &bus_graph);
+        let walking_graph = SidewalkPathfinder::new(map, false, false, &bus_graph);
         timer.stop("prepare pathfinding for pedestrians");
 
+        timer.start("prepare pathfinding for wheelchairs");
+        let wheelchair_graph = SidewalkPathfinder::new(map, false, true, &bus_graph);
+        timer.stop("prepare pathfinding for wheelchairs");
+
         Pathfinder {
             car_graph,
             bike_graph,
             bus_graph,
             walking_graph,
+            wheelchair_graph,
             walking_with_transit_graph: None,
         }
     }
 
     pub fn setup_walking_with_transit(&mut self, map: &Map) {
-        self.walking_with_transit_graph = Some(SidewalkPathfinder::new(map, true, &self.bus_graph));
+        self.walking_with_transit_graph =
+            Some(SidewalkPathfinder::new(map, true, false, &self.bus_graph));
     }
 
     pub fn pathfind(&self, req: PathRequest, map: &Map) -> Option<Path> {
@@ -520,6 +546,10 @@ impl Pathfinder {
         }
     }
 
+    pub fn pathfind_wheelchair(&self, req: PathRequest, map: &Map) -> Option<Path> {
+        self.wheelchair_graph.pathfind(&req, map)
+    }
+
     pub fn should_use_transit(
         &self,
         map: &Map,
@@ -532,28 +562,89 @@ impl Pathfinder {
             .should_use_transit(map, start, end)
     }
 
-    pub fn apply_edits(&mut self, map: &Map, timer: &mut Timer) {
-        timer.start("apply edits to car pathfinding");
-        self.car_graph.apply_edits(map);
-        timer.stop("apply edits to car pathfinding");
+    // This doesn't rebuild just the region around the edits; fast_paths can only contract a whole
+    // graph. Instead, graphs for constraints the edits can't affect are skipped, and the rest skip
+    // contracting if their edges and weights came out the same. Changing a parking lane doesn't
+    // touch anything, for example.
+    pub fn apply_edits(
+        &mut self,
+        map: &Map,
+        changed: &BTreeSet<PathConstraints>,
+        timer: &mut Timer,
+    ) {
+        let mut unchanged = Vec::new();
+
+        if changed.contains(&PathConstraints::Car) {
+            timer.start("apply edits to car pathfinding");
+            if !self.car_graph.apply_edits(map) {
+                unchanged.push("car");
+            }
+            timer.stop("apply edits to car pathfinding");
+        } else {
+            unchanged.push("car");
+        }
 
-        timer.start("apply edits to bike pathfinding");
-        self.bike_graph.apply_edits(map);
-        timer.stop("apply edits to bike pathfinding");
+        if changed.contains(&PathConstraints::Bike) {
+            timer.start("apply edits to bike pathfinding");
+            if !self.bike_graph.apply_edits(map) {
+                unchanged.push("bike");
+            }
+            timer.stop("apply edits to bike pathfinding");
+        } else {
+            unchanged.push("bike");
+        }
 
-        timer.start("apply edits to bus pathfinding");
-        self.bus_graph.apply_edits(map);
-        timer.stop("apply edits to bus pathfinding");
+        if changed.contains(&PathConstraints::Bus) {
+            timer.start("apply edits to bus pathfinding");
+            if !self.bus_graph.apply_edits(map) {
+                unchanged.push("bus");
+            }
+            timer.stop("apply edits to bus pathfinding");
+        } else {
+            unchanged.push("bus");
+        }
 
-        timer.start("apply edits to pedestrian pathfinding");
-        self.walking_graph.apply_edits(map, &self.bus_graph);
-        timer.stop("apply edits to pedestrian pathfinding");
+        if changed.contains(&PathConstraints::Pedestrian) {
+            timer.start("apply edits to pedestrian pathfinding");
+            if !self.walking_graph.apply_edits(map, &self.bus_graph) {
+                unchanged.push("pedestrian");
+            }
+            timer.stop("apply edits to pedestrian pathfinding");
 
-        timer.start("apply edits to pedestrian using transit pathfinding");
-        self.walking_with_transit_graph
-            .as_mut()
-            .unwrap()
-            .apply_edits(map, &self.bus_graph);
-        timer.stop("apply edits to pedestrian using transit pathfinding");
+            // Curb ramps and signals only change turn costs, so this is just re-contracting with
+            // the same node ordering.
+            timer.start("apply edits to wheelchair pathfinding");
+            if !self.wheelchair_graph.apply_edits(map, &self.bus_graph) {
+                unchanged.push("wheelchair");
+            }
+            timer.stop("apply edits to wheelchair pathfinding");
+        } else {
+            unchanged.push("pedestrian");
+            unchanged.push("wheelchair");
+        }
+
+        // Riding the bus costs however long the bus takes
+        if changed.contains(&PathConstraints::Pedestrian) || changed.contains(&PathConstraints::Bus)
+        {
+            timer.start("apply edits to pedestrian using transit pathfinding");
+            if !self
+                .walking_with_transit_graph
+                .as_mut()
+                .unwrap()
+                .apply_edits(map, &self.bus_graph)
+            {
+                unchanged.push("pedestrian using transit");
+            }
+            timer.stop("apply edits to pedestrian using transit pathfinding");
+        } else {
+            unchanged.push("pedestrian using transit");
+        }
+
+        if !unchanged.is_empty() {
+            timer.note(format!(
+                "Edits didn't affect {} pathfinding",
+                unchanged.join(", ")
+            ));
+        }
     }
 }
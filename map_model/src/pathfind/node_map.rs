@@ -1,7 +1,9 @@
-use fast_paths::{NodeId, ShortestPath};
+use fast_paths::{InputGraph, NodeId, ShortestPath};
 use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::BTreeMap;
 use std::fmt::Debug;
+use std::hash::Hasher;
 
 // TODO Upstream this in fast_paths when this is more solid.
 #[derive(Serialize)]
@@ -66,3 +68,16 @@ pub fn deserialize_nodemap<
         id_to_node,
     })
 }
+
+// Summarizes every edge and weight, so an edit that doesn't touch a graph can skip contracting it
+// again. A stale fingerprint (like 0 from an old file) just means contracting once more.
+pub fn fingerprint(graph: &InputGraph) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_usize(graph.get_num_nodes());
+    for edge in graph.get_edges() {
+        hasher.write_usize(edge.from);
+        hasher.write_usize(edge.to);
+        hasher.write_usize(edge.weight);
+    }
+    hasher.finish()
+}
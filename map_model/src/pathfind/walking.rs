@@ -1,7 +1,8 @@
 use crate::pathfind::driving::VehiclePathfinder;
-use crate::pathfind::node_map::{deserialize_nodemap, NodeMap};
+use crate::pathfind::node_map::{deserialize_nodemap, fingerprint, NodeMap};
 use crate::{
-    BusRouteID, BusStopID, LaneID, Map, Path, PathConstraints, PathRequest, PathStep, Position,
+    BusRoute, BusRouteID, BusStopID, LaneID, Map, Path, PathConstraints, PathRequest, PathStep,
+    Position, Turn, TurnType,
 };
 use fast_paths::{deserialize_32, serialize_32, FastGraph, InputGraph, PathCalculator};
 use geom::{Distance, Speed};
@@ -9,6 +10,12 @@ use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use thread_local::ThreadLocal;
 
+// In seconds. Wheelchair users only cross without curb ramps when there's no other way.
+const NO_CURB_RAMPS_PENALTY: usize = 30 * 60;
+// In seconds. Signals give a protected time to cross, so wheelchair users go a bit out of their
+// way for them.
+const UNSIGNALIZED_CROSSING_PENALTY: usize = 60;
+
 #[derive(Serialize, Deserialize)]
 pub struct SidewalkPathfinder {
     #[serde(serialize_with = "serialize_32", deserialize_with = "deserialize_32")]
@@ -16,6 +23,11 @@ pub struct SidewalkPathfinder {
     #[serde(deserialize_with = "deserialize_nodemap")]
     nodes: NodeMap<Node>,
     use_transit: bool,
+    // Avoid crosswalks without curb ramps and prefer crossing at signals. Never combined with
+    // transit.
+    wheelchair: bool,
+    // Of the input graph, to skip contracting again when edits don't change it
+    input_fingerprint: u64,
 
     #[serde(skip_serializing, skip_deserializing)]
     path_calc: ThreadLocal<RefCell<PathCalculator>>,
@@ -25,11 +37,19 @@ pub struct SidewalkPathfinder {
 enum Node {
     // false is src_i, true is dst_i
     SidewalkEndpoint(LaneID, bool),
-    RideBus(BusStopID),
+    // On a bus of this route, at this stop. Each route gets its own nodes, so that changing
+    // routes means getting off and waiting again.
+    RideBus(BusStopID, BusRouteID),
 }
 
 impl SidewalkPathfinder {
-    pub fn new(map: &Map, use_transit: bool, bus_graph: &VehiclePathfinder) -> SidewalkPathfinder {
+    pub fn new(
+        map: &Map,
+        use_transit: bool,
+        wheelchair: bool,
+        bus_graph: &VehiclePathfinder,
+    ) -> SidewalkPathfinder {
+        assert!(!(use_transit && wheelchair));
         let mut nodes = NodeMap::new();
         // We're assuming that to start with, no sidewalks are closed for construction!
         for l in map.all_lanes() {
@@ -39,54 +59,51 @@ impl SidewalkPathfinder {
             }
         }
         if use_transit {
-            // Add a node for each bus stop.
-            for stop in map.all_bus_stops().values() {
-                nodes.get_or_insert(Node::RideBus(stop.id));
+            // Add a node for each stop along each route.
+            for route in map.get_all_bus_routes() {
+                for stop in &route.stops {
+                    nodes.get_or_insert(Node::RideBus(*stop, route.id));
+                }
             }
         }
 
-        let graph = fast_paths::prepare(&make_input_graph(map, &nodes, use_transit, bus_graph));
+        let input_graph = make_input_graph(map, &nodes, use_transit, wheelchair, bus_graph);
+        let input_fingerprint = fingerprint(&input_graph);
+        let graph = fast_paths::prepare(&input_graph);
         SidewalkPathfinder {
             graph,
             nodes,
             use_transit,
+            wheelchair,
+            input_fingerprint,
             path_calc: ThreadLocal::new(),
         }
     }
 
-    pub fn apply_edits(&mut self, map: &Map, bus_graph: &VehiclePathfinder) {
+    // Returns false if the edits didn't change anything this graph cares about.
+    pub fn apply_edits(&mut self, map: &Map, bus_graph: &VehiclePathfinder) -> bool {
         // The NodeMap is all sidewalks and bus stops -- it won't change. So we can also reuse the
         // node ordering.
-        let input_graph = make_input_graph(map, &self.nodes, self.use_transit, bus_graph);
+        let input_graph = make_input_graph(
+            map,
+            &self.nodes,
+            self.use_transit,
+            self.wheelchair,
+            bus_graph,
+        );
+        let input_fingerprint = fingerprint(&input_graph);
+        if input_fingerprint == self.input_fingerprint {
+            return false;
+        }
         let node_ordering = self.graph.get_node_ordering();
         self.graph = fast_paths::prepare_with_order(&input_graph, &node_ordering).unwrap();
+        self.input_fingerprint = input_fingerprint;
+        true
     }
 
     pub fn pathfind(&self, req: &PathRequest, map: &Map) -> Option<Path> {
-        // Special-case one-step paths.
-        // TODO Maybe we don't need these special cases anymore.
-        if req.start.lane() == req.end.lane() {
-            // Weird case, but it can happen for walking from a building path to a bus stop that're
-            // actually at the same spot.
-            if req.start.dist_along() == req.end.dist_along() {
-                return Some(Path::new(
-                    map,
-                    vec![PathStep::Lane(req.start.lane())],
-                    req.start.dist_along(),
-                ));
-            } else if req.start.dist_along() < req.end.dist_along() {
-                return Some(Path::new(
-                    map,
-                    vec![PathStep::Lane(req.start.lane())],
-                    req.end.dist_along(),
-                ));
-            } else {
-                return Some(Path::new(
-                    map,
-                    vec![PathStep::ContraflowLane(req.start.lane())],
-                    req.end.dist_along(),
-                ));
-            }
+        if let Some(path) = one_step_path(req, map) {
+            return Some(path);
         }
 
         let mut calc = self
@@ -98,63 +115,11 @@ impl SidewalkPathfinder {
             self.nodes.get(closest_node(req.start, map)),
             self.nodes.get(closest_node(req.end, map)),
         )?;
-        let path = self.nodes.translate(&raw_path);
-
-        let mut steps: Vec<PathStep> = Vec::new();
-
-        for pair in path.windows(2) {
-            let (l1, l1_endpt) = match pair[0] {
-                Node::SidewalkEndpoint(l, endpt) => (l, endpt),
-                Node::RideBus(_) => unreachable!(),
-            };
-            let l2 = match pair[1] {
-                Node::SidewalkEndpoint(l, _) => l,
-                Node::RideBus(_) => unreachable!(),
-            };
-
-            if l1 == l2 {
-                if l1_endpt {
-                    steps.push(PathStep::ContraflowLane(l1));
-                } else {
-                    steps.push(PathStep::Lane(l1));
-                }
-            } else {
-                let i = {
-                    let l = map.get_l(l1);
-                    if l1_endpt {
-                        l.dst_i
-                    } else {
-                        l.src_i
-                    }
-                };
-                // Could assert the intersection matches (l2, l2_endpt).
-                let turn = map.get_turn_between(l1, l2, i).unwrap();
-                steps.push(PathStep::Turn(turn));
-            }
-        }
-
-        // Don't start or end a path in a turn; sim layer breaks.
-        if let PathStep::Turn(t) = steps[0] {
-            let lane = map.get_l(t.src);
-            if lane.src_i == t.parent {
-                steps.insert(0, PathStep::ContraflowLane(lane.id));
-            } else {
-                steps.insert(0, PathStep::Lane(lane.id));
-            }
-        }
-        if let PathStep::Turn(t) = steps.last().unwrap() {
-            let lane = map.get_l(t.dst);
-            if lane.src_i == t.parent {
-                steps.push(PathStep::Lane(lane.id));
-            } else {
-                steps.push(PathStep::ContraflowLane(lane.id));
-            }
-        }
-
-        Some(Path::new(map, steps, req.end.dist_along()))
+        Some(nodes_to_path(self.nodes.translate(&raw_path), req, map))
     }
 
-    // Attempt the pathfinding and see if we should ride a bus.
+    // Attempt the pathfinding and see if we should ride a bus. If the best path transfers between
+    // routes, only the first ride is returned; the rest should be planned again after getting off.
     pub fn should_use_transit(
         &self,
         map: &Map,
@@ -167,28 +132,140 @@ impl SidewalkPathfinder {
             self.nodes.get(closest_node(end, map)),
         )?;
 
-        let mut nodes = self.nodes.translate(&raw_path);
-        let mut first_stop = None;
-        for n in &nodes {
-            if let Node::RideBus(stop) = n {
-                first_stop = Some(*stop);
-                break;
+        let mut ride: Option<(BusStopID, BusStopID, BusRouteID)> = None;
+        for n in self.nodes.translate(&raw_path) {
+            match (n, ride) {
+                (Node::RideBus(stop, route), None) => {
+                    ride = Some((stop, stop, route));
+                }
+                (Node::RideBus(stop, route), Some((stop1, _, r))) if route == r => {
+                    ride = Some((stop1, stop, route));
+                }
+                (Node::SidewalkEndpoint(_, _), None) => {}
+                // Got off the first bus
+                _ => {
+                    break;
+                }
             }
         }
-        let first_stop = first_stop?;
-        let possible_routes = map.get_routes_serving_stop(first_stop);
+        let (stop1, stop2, route) = ride?;
+        assert_ne!(stop1, stop2);
+        Some((stop1, stop2, route))
+    }
+}
 
-        nodes.reverse();
-        for n in nodes {
-            if let Node::RideBus(stop2) = n {
-                if let Some(route) = possible_routes.iter().find(|r| r.stops.contains(&stop2)) {
-                    assert_ne!(first_stop, stop2);
-                    return Some((first_stop, stop2, route.id));
-                }
+// Special-case one-step paths.
+// TODO Maybe we don't need these special cases anymore.
+fn one_step_path(req: &PathRequest, map: &Map) -> Option<Path> {
+    if req.start.lane() != req.end.lane() {
+        return None;
+    }
+    // Weird case, but it can happen for walking from a building path to a bus stop that're
+    // actually at the same spot.
+    if req.start.dist_along() == req.end.dist_along() {
+        Some(Path::new(
+            map,
+            vec![PathStep::Lane(req.start.lane())],
+            req.start.dist_along(),
+        ))
+    } else if req.start.dist_along() < req.end.dist_along() {
+        Some(Path::new(
+            map,
+            vec![PathStep::Lane(req.start.lane())],
+            req.end.dist_along(),
+        ))
+    } else {
+        Some(Path::new(
+            map,
+            vec![PathStep::ContraflowLane(req.start.lane())],
+            req.end.dist_along(),
+        ))
+    }
+}
+
+// The nodes must all be sidewalk endpoints.
+fn nodes_to_path(path: Vec<Node>, req: &PathRequest, map: &Map) -> Path {
+    let mut steps: Vec<PathStep> = Vec::new();
+
+    for pair in path.windows(2) {
+        let (l1, l1_endpt) = match pair[0] {
+            Node::SidewalkEndpoint(l, endpt) => (l, endpt),
+            Node::RideBus(_, _) => unreachable!(),
+        };
+        let l2 = match pair[1] {
+            Node::SidewalkEndpoint(l, _) => l,
+            Node::RideBus(_, _) => unreachable!(),
+        };
+
+        if l1 == l2 {
+            if l1_endpt {
+                steps.push(PathStep::ContraflowLane(l1));
+            } else {
+                steps.push(PathStep::Lane(l1));
             }
+        } else {
+            let i = {
+                let l = map.get_l(l1);
+                if l1_endpt {
+                    l.dst_i
+                } else {
+                    l.src_i
+                }
+            };
+            // Could assert the intersection matches (l2, l2_endpt).
+            let turn = map.get_turn_between(l1, l2, i).unwrap();
+            steps.push(PathStep::Turn(turn));
         }
-        unreachable!()
     }
+
+    // Don't start or end a path in a turn; sim layer breaks.
+    if let PathStep::Turn(t) = steps[0] {
+        let lane = map.get_l(t.src);
+        if lane.src_i == t.parent {
+            steps.insert(0, PathStep::ContraflowLane(lane.id));
+        } else {
+            steps.insert(0, PathStep::Lane(lane.id));
+        }
+    }
+    if let PathStep::Turn(t) = steps.last().unwrap() {
+        let lane = map.get_l(t.dst);
+        if lane.src_i == t.parent {
+            steps.push(PathStep::Lane(lane.id));
+        } else {
+            steps.push(PathStep::ContraflowLane(lane.id));
+        }
+    }
+
+    Path::new(map, steps, req.end.dist_along())
+}
+
+// What it costs to use a walkable turn, in seconds.
+fn turn_cost(t: &Turn, map: &Map, wheelchair: bool) -> usize {
+    let mut cost = to_s(t.geom.length());
+    if wheelchair {
+        if !map.crossing_has_curb_ramps(t.id) {
+            cost += NO_CURB_RAMPS_PENALTY;
+        } else if t.turn_type != TurnType::SharedSidewalkCorner
+            && t.turn_type != TurnType::Footbridge
+            && map.maybe_get_traffic_signal(t.id.parent).is_none()
+        {
+            cost += UNSIGNALIZED_CROSSING_PENALTY;
+        }
+    }
+    cost
+}
+
+// Diagonal crosswalks only exist during a pedestrian scramble.
+fn walkable(t: &Turn, map: &Map) -> bool {
+    if t.turn_type == TurnType::DiagonalCrosswalk
+        && !map
+            .maybe_get_traffic_signal(t.id.parent)
+            .map(|ts| ts.has_ped_scramble())
+            .unwrap_or(false)
+    {
+        return false;
+    }
+    t.between_sidewalks()
 }
 
 fn closest_node(pos: Position, map: &Map) -> Node {
@@ -200,6 +277,7 @@ fn make_input_graph(
     map: &Map,
     nodes: &NodeMap<Node>,
     use_transit: bool,
+    wheelchair: bool,
     bus_graph: &VehiclePathfinder,
 ) -> InputGraph {
     let mut input_graph = InputGraph::new();
@@ -215,35 +293,44 @@ fn make_input_graph(
     }
 
     for t in map.all_turns().values() {
-        if t.between_sidewalks() {
+        if walkable(t, map) {
             let from = Node::SidewalkEndpoint(t.id.src, map.get_l(t.id.src).dst_i == t.id.parent);
             let to = Node::SidewalkEndpoint(t.id.dst, map.get_l(t.id.dst).dst_i == t.id.parent);
-            input_graph.add_edge(nodes.get(from), nodes.get(to), to_s(t.geom.length()));
+            input_graph.add_edge(
+                nodes.get(from),
+                nodes.get(to),
+                turn_cost(t, map, wheelchair),
+            );
         }
     }
 
     if use_transit {
-        // Connect bus stops with both sidewalk endpoints, using the appropriate distance.
-        for stop in map.all_bus_stops().values() {
-            let ride_bus = nodes.get(Node::RideBus(stop.id));
-            let lane = map.get_l(stop.sidewalk_pos.lane());
-            for endpt in &[true, false] {
-                let cost = if *endpt {
-                    to_s(lane.length() - stop.sidewalk_pos.dist_along())
-                } else {
-                    to_s(stop.sidewalk_pos.dist_along())
-                };
-                // Add some extra penalty (equivalent to 1m) to using a bus stop. Otherwise a path
-                // might try to pass through it uselessly.
-                let penalty = 100;
-                let sidewalk = nodes.get(Node::SidewalkEndpoint(lane.id, *endpt));
-                input_graph.add_edge(sidewalk, ride_bus, cost + penalty);
-                input_graph.add_edge(ride_bus, sidewalk, cost + penalty);
+        // Connect bus stops with both sidewalk endpoints, using the appropriate distance. Boarding
+        // costs the expected wait for that route.
+        for route in map.get_all_bus_routes() {
+            let wait = expected_wait(route);
+            for stop_id in &route.stops {
+                let stop = map.get_bs(*stop_id);
+                let ride_bus = nodes.get(Node::RideBus(stop.id, route.id));
+                let lane = map.get_l(stop.sidewalk_pos.lane());
+                for endpt in &[true, false] {
+                    let cost = if *endpt {
+                        to_s(lane.length() - stop.sidewalk_pos.dist_along())
+                    } else {
+                        to_s(stop.sidewalk_pos.dist_along())
+                    };
+                    // Add some extra penalty (equivalent to 1m) to using a bus stop. Otherwise a
+                    // path might try to pass through it uselessly.
+                    let penalty = 100;
+                    let sidewalk = nodes.get(Node::SidewalkEndpoint(lane.id, *endpt));
+                    input_graph.add_edge(sidewalk, ride_bus, cost + penalty + wait);
+                    input_graph.add_edge(ride_bus, sidewalk, cost + penalty);
+                }
             }
         }
 
         // Connect each adjacent stop along a route, with the cost based on how long it'll take a
-        // bus to drive between the stops. Optimistically assume no waiting time at a stop.
+        // bus to drive between the stops.
         for route in map.get_all_bus_routes() {
             for (stop1, stop2) in
                 route
@@ -264,8 +351,8 @@ fn make_input_graph(
                     map,
                 ) {
                     input_graph.add_edge(
-                        nodes.get(Node::RideBus(*stop1)),
-                        nodes.get(Node::RideBus(*stop2)),
+                        nodes.get(Node::RideBus(*stop1, route.id)),
+                        nodes.get(Node::RideBus(*stop2, route.id)),
                         driving_cost,
                     );
                 } else {
@@ -282,6 +369,20 @@ fn make_input_graph(
     input_graph
 }
 
+// How long somebody showing up at a random time should expect to wait for a bus of this route, in
+// seconds
+fn expected_wait(route: &BusRoute) -> usize {
+    // Without a schedule, optimistically assume no waiting time, but still charge a bit for each
+    // boarding, so that paths don't switch routes for no reason.
+    let boarding = 60;
+    if route.spawn_times.len() < 2 {
+        return boarding;
+    }
+    let span = *route.spawn_times.last().unwrap() - route.spawn_times[0];
+    let headway = span / ((route.spawn_times.len() - 1) as f64);
+    boarding + (headway / 2.0).inner_seconds().round() as usize
+}
+
 fn to_s(dist: Distance) -> usize {
     let walking_speed = Speed::meters_per_second(1.34);
     let time = dist / walking_speed;
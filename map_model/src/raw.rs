@@ -337,6 +337,8 @@ pub struct RawArea {
 pub struct RawParkingLot {
     pub polygon: Polygon,
     pub osm_id: i64,
+    // From the OSM capacity tag
+    pub capacity: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
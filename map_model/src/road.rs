@@ -1,11 +1,14 @@
 use crate::raw::{OriginalRoad, RestrictionType};
-use crate::{osm, BusStopID, IntersectionID, LaneID, LaneType, Map, PathConstraints};
+use crate::{osm, BusStopID, IntersectionID, LaneID, LaneType, Map, PathConstraints, Position};
 use abstutil::{Error, Warn};
-use geom::{Distance, PolyLine, Polygon, Speed};
+use geom::{Distance, PolyLine, Polygon, Speed, Time};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
 
+// Dual carriageways in the US are usually split by less than this
+const MAX_CONSOLIDATED_ROAD_LENGTH: Distance = Distance::const_meters(50.0);
+
 // TODO reconsider pub usize. maybe outside world shouldnt know.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct RoadID(pub usize);
@@ -98,7 +101,25 @@ pub struct Road {
     pub complicated_turn_restrictions: Vec<(RoadID, RoadID)>,
     pub orig_id: OriginalRoad,
     pub speed_limit: Speed,
+    // Overrides speed_limit during part of the day, like in school zones. If several apply at once,
+    // the lowest wins.
+    pub timed_speed_limits: Vec<TimedSpeedLimit>,
     pub zorder: isize,
+    // Only meaningful when the road is closed. The signed route around it, from src_i to dst_i.
+    pub detour: Vec<RoadID>,
+    // Plowed first and kept clear during winter storms. Only matters when a sim is running with
+    // snow conditions.
+    pub snow_route: bool,
+    // The crosswalks over this road at these ends are missing a curb ramp on at least one side.
+    pub missing_curb_ramps: BTreeSet<IntersectionID>,
+    // Grade-separated crossings between the two sidewalks of this road
+    pub footbridges: Vec<Footbridge>,
+    // A raised strip between the two directions of travel
+    pub median: Option<Median>,
+    // Folded into one junction with the intersections at both ends, like the short crossing
+    // between the two halves of a dual carriageway. Vehicles already inside the junction don't
+    // stop again for its second stop sign or signal. The geometry doesn't change.
+    pub consolidated_junction: bool,
 
     // Invariant: A road must contain at least one child
     // These are ordered from closest to center lane (left-most when driving on the right) to
@@ -113,6 +134,69 @@ pub struct Road {
     pub dst_i: IntersectionID,
 }
 
+// A bridge or underpass across a road, from a spot on one sidewalk to a spot on the other. It's
+// reached from the end of the road closer to it, replacing the crosswalk there, so pedestrians never
+// wait for traffic to cross.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Footbridge {
+    pub from: Position,
+    pub to: Position,
+    pub underpass: bool,
+}
+
+// Pedestrians using the crosswalks at either end of the road can wait on the median halfway across,
+// so they only have to clear one direction of traffic at a time. It might also stop vehicles from
+// turning left onto or off of the road.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Median {
+    pub width: Distance,
+    pub no_left_turns: bool,
+}
+
+// How much a lane that bikes can use keeps them apart from other vehicles
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BikeFacility {
+    // Bikes mix with everybody else in a general purpose or bus lane
+    Shared,
+    // A bike lane right next to moving traffic
+    Painted,
+    // A bike lane without moving traffic right next to it, like one running between parking and
+    // the sidewalk
+    Protected,
+}
+
+// A speed limit that only applies during part of every day
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimedSpeedLimit {
+    pub limit: Speed,
+    // From the first hour until the second. If the first is bigger, the limit lasts overnight.
+    pub hours: (usize, usize),
+    // Only for trucks and buses, like a nighttime truck limit
+    pub trucks_only: bool,
+}
+
+impl TimedSpeedLimit {
+    pub fn is_active(&self, time: Time) -> bool {
+        let hour = time.get_parts().0 % 24;
+        let (start, end) = self.hours;
+        if start <= end {
+            hour >= start && hour < end
+        } else {
+            hour >= start || hour < end
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} {}:00 - {}:00{}",
+            self.limit,
+            self.hours.0,
+            self.hours.1,
+            if self.trucks_only { " for trucks" } else { "" }
+        )
+    }
+}
+
 impl Road {
     pub fn get_lane_types(&self) -> (Vec<LaneType>, Vec<LaneType>) {
         (
@@ -207,6 +291,64 @@ impl Road {
         }
     }
 
+    // The lanes immediately to either side of this one. The centermost lane on each side is next to
+    // the centermost lane going the other way.
+    pub fn neighbors(&self, lane: LaneID) -> Vec<(LaneID, LaneType)> {
+        let (fwds, idx) = self.dir_and_offset(lane);
+        let (same, other) = if fwds {
+            (&self.children_forwards, &self.children_backwards)
+        } else {
+            (&self.children_backwards, &self.children_forwards)
+        };
+        let mut result = Vec::new();
+        if idx == 0 {
+            result.extend(other.first().cloned());
+        } else {
+            result.push(same[idx - 1]);
+        }
+        result.extend(same.get(idx + 1).cloned());
+        result
+    }
+
+    // None if bikes can't ride in the lane
+    pub fn bike_facility(&self, lane: LaneID) -> Option<BikeFacility> {
+        let lt = self
+            .children_forwards
+            .iter()
+            .chain(self.children_backwards.iter())
+            .find(|(l, _)| *l == lane)?
+            .1;
+        match lt {
+            LaneType::Driving | LaneType::Bus => Some(BikeFacility::Shared),
+            LaneType::Biking => {
+                if self
+                    .neighbors(lane)
+                    .into_iter()
+                    .any(|(_, lt)| lt.is_for_moving_vehicles() && lt != LaneType::Biking)
+                {
+                    Some(BikeFacility::Painted)
+                } else {
+                    Some(BikeFacility::Protected)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Only short connectors between two real intersections can be folded into one junction
+    pub fn can_consolidate_junction(&self, map: &Map) -> bool {
+        self.center_pts.length() <= MAX_CONSOLIDATED_ROAD_LENGTH
+            && !map.get_i(self.src_i).is_border()
+            && !map.get_i(self.dst_i).is_border()
+    }
+
+    // Is this lane next to parked cars, where somebody could open a door into a passing bike?
+    pub fn in_door_zone(&self, lane: LaneID) -> bool {
+        self.neighbors(lane)
+            .into_iter()
+            .any(|(_, lt)| lt == LaneType::Parking)
+    }
+
     pub(crate) fn speed_limit_from_osm(&self) -> Speed {
         if let Some(limit) = self.osm_tags.get(osm::MAXSPEED) {
             // TODO handle other units
@@ -225,6 +367,87 @@ impl Road {
         Speed::miles_per_hour(20.0)
     }
 
+    // The speed limit in effect at some time, for trucks and buses or everybody else
+    pub fn speed_limit_at(&self, time: Time, truck: bool) -> Speed {
+        self.timed_speed_limits
+            .iter()
+            .filter(|x| x.is_active(time) && (truck || !x.trucks_only))
+            .map(|x| x.limit)
+            .fold(None, |min: Option<Speed>, limit| {
+                Some(min.map(|s| s.min(limit)).unwrap_or(limit))
+            })
+            .unwrap_or(self.speed_limit)
+    }
+
+    // Understands the simplest conditional limits, like "20 mph @ (Mo-Fr 07:00-09:00,
+    // 14:00-16:00)". Days are ignored, and times are rounded to the hour.
+    pub(crate) fn timed_speed_limits_from_osm(&self) -> Vec<TimedSpeedLimit> {
+        let mut results = Vec::new();
+        for (key, trucks_only) in vec![
+            (osm::MAXSPEED_CONDITIONAL, false),
+            (osm::MAXSPEED_HGV_CONDITIONAL, true),
+        ] {
+            let value = match self.osm_tags.get(key) {
+                Some(x) => x,
+                None => {
+                    continue;
+                }
+            };
+            for rule in value.split(';') {
+                let parts: Vec<&str> = rule.split('@').map(|x| x.trim()).collect();
+                if parts.len() != 2 || !parts[0].ends_with(" mph") {
+                    continue;
+                }
+                let limit = match parts[0][0..parts[0].len() - 4].parse::<f64>() {
+                    Ok(mph) => Speed::miles_per_hour(mph),
+                    Err(_) => {
+                        continue;
+                    }
+                };
+                for range in parts[1]
+                    .trim_matches(|c| c == '(' || c == ')')
+                    .split(|c| c == ',' || c == ' ')
+                {
+                    let times: Vec<&str> = range.split('-').collect();
+                    if times.len() != 2 || !times[0].contains(':') || !times[1].contains(':') {
+                        continue;
+                    }
+                    let hour = |x: &str| x.split(':').next().unwrap().parse::<usize>().ok();
+                    if let (Some(start), Some(end)) = (hour(times[0]), hour(times[1])) {
+                        results.push(TimedSpeedLimit {
+                            limit,
+                            hours: (start % 24, end % 24),
+                            trucks_only,
+                        });
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    pub(crate) fn missing_curb_ramps_from_osm(&self) -> BTreeSet<IntersectionID> {
+        let mut ends = BTreeSet::new();
+        match self
+            .osm_tags
+            .get(osm::MISSING_CURB_RAMPS)
+            .map(|x| x.as_str())
+        {
+            Some("start") => {
+                ends.insert(self.src_i);
+            }
+            Some("end") => {
+                ends.insert(self.dst_i);
+            }
+            Some("both") => {
+                ends.insert(self.src_i);
+                ends.insert(self.dst_i);
+            }
+            _ => {}
+        }
+        ends
+    }
+
     pub fn incoming_lanes(&self, i: IntersectionID) -> &Vec<(LaneID, LaneType)> {
         if self.src_i == i {
             &self.children_backwards
@@ -285,6 +508,24 @@ impl Road {
         }
     }
 
+    // Closed for construction, with no lanes left for any kind of vehicle
+    pub fn is_closed(&self) -> bool {
+        let mut any_construction = false;
+        for (_, lt) in self
+            .children_forwards
+            .iter()
+            .chain(&self.children_backwards)
+        {
+            if lt.is_for_moving_vehicles() {
+                return false;
+            }
+            if *lt == LaneType::Construction {
+                any_construction = true;
+            }
+        }
+        any_construction
+    }
+
     pub fn all_lanes(&self) -> Vec<LaneID> {
         self.children_forwards
             .iter()
@@ -293,6 +534,13 @@ impl Road {
             .collect()
     }
 
+    pub fn has_sidewalks(&self) -> bool {
+        self.children_forwards
+            .iter()
+            .chain(self.children_backwards.iter())
+            .any(|(_, lt)| *lt == LaneType::Sidewalk)
+    }
+
     pub fn lanes_on_side(&self, dir: bool) -> Vec<LaneID> {
         if dir {
             &self.children_forwards
@@ -311,8 +559,15 @@ impl Road {
         } else {
             self.children_backwards[0].0
         });
-        map.left_shift(lane.lane_center_pts.clone(), lane.width / 2.0)
-            .unwrap()
+        map.left_shift(
+            lane.lane_center_pts.clone(),
+            lane.width / 2.0 + self.median_width() / 2.0,
+        )
+        .unwrap()
+    }
+
+    pub fn median_width(&self) -> Distance {
+        self.median.map(|m| m.width).unwrap_or(Distance::ZERO)
     }
 
     pub fn any_on_other_side(&self, l: LaneID, lt: LaneType) -> Option<LaneID> {
@@ -340,13 +595,14 @@ impl Road {
     pub fn get_thick_polyline(&self, map: &Map) -> Warn<(PolyLine, Distance)> {
         let fwd = self.width_fwd(map);
         let back = self.width_back(map);
+        let total = fwd + back + self.median_width();
 
         if fwd >= back {
             map.right_shift(self.center_pts.clone(), (fwd - back) / 2.0)
-                .map(|pl| (pl, fwd + back))
+                .map(|pl| (pl, total))
         } else {
             map.left_shift(self.center_pts.clone(), (back - fwd) / 2.0)
-                .map(|pl| (pl, fwd + back))
+                .map(|pl| (pl, total))
         }
     }
 
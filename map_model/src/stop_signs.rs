@@ -102,9 +102,11 @@ impl ControlStopSign {
     // TODO Or cache
     pub fn get_priority(&self, turn: TurnID, map: &Map) -> TurnPriority {
         match map.get_t(turn).turn_type {
-            TurnType::SharedSidewalkCorner => TurnPriority::Protected,
+            TurnType::SharedSidewalkCorner | TurnType::Footbridge => TurnPriority::Protected,
             // TODO This actually feels like a policy bit that should be flippable.
             TurnType::Crosswalk => TurnPriority::Protected,
+            // Nothing stops the cars for long enough
+            TurnType::DiagonalCrosswalk => TurnPriority::Banned,
             _ => {
                 if self.roads[&map.get_l(turn.src).parent].must_stop {
                     TurnPriority::Yield
@@ -1,17 +1,24 @@
 use crate::make::traffic_signals::{brute_force, get_possible_policies};
 use crate::{
-    DirectedRoadID, IntersectionID, Map, TurnGroup, TurnGroupID, TurnID, TurnPriority, TurnType,
+    DirectedRoadID, IntersectionID, Map, RoadID, TurnGroup, TurnGroupID, TurnID, TurnPriority,
+    TurnType,
 };
 use abstutil::{deserialize_btreemap, retain_btreeset, serialize_btreemap, Timer};
-use geom::{Duration, Time};
+use geom::{Angle, Distance, Duration, Speed, Time};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 
+// The slowest people in generated scenarios walk about this fast
+const SLOWEST_WALKING_SPEED: Speed = Speed::const_meters_per_second(0.894);
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ControlTrafficSignal {
     pub id: IntersectionID,
     pub phases: Vec<Phase>,
     pub offset: Duration,
+    // Fixed-time signals always run each phase for its duration. Actuated signals use detectors to
+    // hold each phase between its min and max green, moving on early when nobody's waiting for it.
+    pub actuated: bool,
 
     #[serde(
         serialize_with = "serialize_btreemap",
@@ -25,6 +32,46 @@ pub struct Phase {
     pub protected_groups: BTreeSet<TurnGroupID>,
     pub yield_groups: BTreeSet<TurnGroupID>,
     pub duration: Duration,
+    // Only used by actuated signals
+    pub min_green: Duration,
+    pub max_green: Duration,
+    // For this long at the start of the phase, only the crosswalks go, giving pedestrians a head
+    // start before any vehicles.
+    pub leading_pedestrian_interval: Duration,
+    // For this long at the end of the phase, the vehicle movements are yellow. Vehicles already in
+    // the intersection finish, but no new ones start. Only fixed-time signals have this; actuated
+    // ones can end a phase at any check.
+    pub yellow: Duration,
+}
+
+// A complete timing plan for one signal, meant for sharing with other people and traffic
+// engineering tools. Roads and intersections are referred to by OpenStreetMap IDs, so a plan
+// still applies after the map is rebuilt. See docs/signal_timing_plans.md for the format.
+#[derive(Serialize, Deserialize)]
+pub struct SignalTimingPlan {
+    pub intersection_osm_node_id: i64,
+    #[serde(default)]
+    pub offset_seconds: usize,
+    #[serde(default)]
+    pub actuated: bool,
+    pub phases: Vec<PlanPhase>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlanPhase {
+    pub protected_turns: Vec<seattle_traffic_signals::Turn>,
+    #[serde(default)]
+    pub permitted_turns: Vec<seattle_traffic_signals::Turn>,
+    pub duration_seconds: usize,
+    // Only for actuated signals
+    #[serde(default)]
+    pub min_green_seconds: Option<usize>,
+    #[serde(default)]
+    pub max_green_seconds: Option<usize>,
+    #[serde(default)]
+    pub leading_pedestrian_interval_seconds: usize,
+    #[serde(default)]
+    pub yellow_seconds: Option<usize>,
 }
 
 impl ControlTrafficSignal {
@@ -68,6 +115,188 @@ impl ControlTrafficSignal {
         unreachable!()
     }
 
+    // The longest a phase could last. Actuated signals might end it earlier.
+    pub fn max_phase_length(&self, phase: &Phase) -> Duration {
+        if self.actuated {
+            phase.max_green
+        } else {
+            phase.duration
+        }
+    }
+
+    // How long the slowest pedestrians need to get across every crosswalk protected during this
+    // phase, or just to the median when there's a refuge island. Shorter phases strand people in
+    // the road.
+    pub fn min_crossing_time(&self, idx: usize, map: &Map) -> Duration {
+        let mut longest = Distance::ZERO;
+        for g in &self.phases[idx].protected_groups {
+            if !g.crosswalk {
+                continue;
+            }
+            for t in &self.turn_groups[g].members {
+                let mut dist = map.get_t(*t).geom.length();
+                if map.crossing_has_refuge(*t) {
+                    dist = dist / 2.0;
+                }
+                if dist > longest {
+                    longest = dist;
+                }
+            }
+        }
+        longest / SLOWEST_WALKING_SPEED
+    }
+
+    // If a phase with this much time remaining is still in its leading pedestrian interval,
+    // returns how much longer vehicles have to wait.
+    pub fn leading_pedestrian_interval_left(
+        &self,
+        phase: &Phase,
+        remaining: Duration,
+    ) -> Option<Duration> {
+        let elapsed = self.max_phase_length(phase) - remaining;
+        if elapsed < phase.leading_pedestrian_interval {
+            Some(phase.leading_pedestrian_interval - elapsed)
+        } else {
+            None
+        }
+    }
+
+    // Is a phase with this much time remaining in its yellow interval?
+    pub fn is_yellow(&self, phase: &Phase, remaining: Duration) -> bool {
+        !self.actuated && remaining <= phase.yellow
+    }
+
+    // How far into the cycle (ignoring the offset) does the first phase protecting some movement
+    // start? Leave from or to unspecified to match anything.
+    pub fn start_of_protected_phase(
+        &self,
+        from: Option<DirectedRoadID>,
+        to: Option<DirectedRoadID>,
+    ) -> Option<Duration> {
+        let mut start = Duration::ZERO;
+        for p in &self.phases {
+            if p.protected_groups.iter().any(|g| {
+                !g.crosswalk
+                    && from.map(|r| g.from == r).unwrap_or(true)
+                    && to.map(|r| g.to == r).unwrap_or(true)
+            }) {
+                return Some(start);
+            }
+            start += p.duration;
+        }
+        None
+    }
+
+    // Copy the phases and timing onto another intersection with the same number of roads. Roads
+    // are matched up by the direction they leave each intersection. Movements that don't exist
+    // there are dropped, protected movements that conflict there are downgraded to yield, and
+    // movements that only exist there go in the first phase they fit. The result has to pass
+    // validate.
+    pub fn transfer(&self, to: IntersectionID, map: &Map) -> Result<ControlTrafficSignal, String> {
+        let src_roads = roads_by_angle(self.id, map);
+        let dst_roads = roads_by_angle(to, map);
+        if src_roads.len() != dst_roads.len() {
+            return Err(format!(
+                "{} has {} roads, but {} has {}",
+                self.id,
+                src_roads.len(),
+                to,
+                dst_roads.len()
+            ));
+        }
+
+        // Both lists are sorted by angle, so just find the rotation that lines up best.
+        let rotation = (0..dst_roads.len())
+            .min_by_key(|shift| {
+                let total: f64 = src_roads
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, (_, angle))| {
+                        let other = dst_roads[(idx + shift) % dst_roads.len()].1;
+                        angle
+                            .shortest_rotation_towards(other)
+                            .normalized_degrees()
+                            .min(other.shortest_rotation_towards(*angle).normalized_degrees())
+                    })
+                    .sum();
+                (total * 100.0) as usize
+            })
+            .unwrap_or(0);
+        let road_mapping: BTreeMap<RoadID, RoadID> = src_roads
+            .iter()
+            .enumerate()
+            .map(|(idx, (r, _))| (*r, dst_roads[(idx + rotation) % dst_roads.len()].0))
+            .collect();
+        let turn_groups = TurnGroup::for_i(to, map);
+        let remap = |g: &TurnGroupID| -> Option<TurnGroupID> {
+            let id = TurnGroupID {
+                from: remap_directed_road(g.from, self.id, road_mapping[&g.from.id], to, map),
+                to: remap_directed_road(g.to, self.id, road_mapping[&g.to.id], to, map),
+                parent: to,
+                crosswalk: g.crosswalk,
+            };
+            if turn_groups.contains_key(&id) {
+                Some(id)
+            } else {
+                None
+            }
+        };
+
+        let mut phases = Vec::new();
+        for orig in &self.phases {
+            let mut phase = Phase {
+                protected_groups: BTreeSet::new(),
+                yield_groups: BTreeSet::new(),
+                ..orig.clone()
+            };
+            for g in orig.protected_groups.iter().filter_map(&remap) {
+                if phase.could_be_protected(g, &turn_groups) {
+                    phase.protected_groups.insert(g);
+                } else if !g.crosswalk {
+                    phase.yield_groups.insert(g);
+                }
+            }
+            for g in orig.yield_groups.iter().filter_map(&remap) {
+                if !phase.protected_groups.contains(&g) {
+                    phase.yield_groups.insert(g);
+                }
+            }
+            phases.push(phase);
+        }
+
+        for g in turn_groups.keys() {
+            if phases
+                .iter()
+                .any(|p| p.protected_groups.contains(g) || p.yield_groups.contains(g))
+            {
+                continue;
+            }
+            if let Some(phase) = phases
+                .iter_mut()
+                .find(|p| p.could_be_protected(*g, &turn_groups))
+            {
+                phase.protected_groups.insert(*g);
+            } else if !g.crosswalk && !phases.is_empty() {
+                phases[0].yield_groups.insert(*g);
+            }
+        }
+
+        ControlTrafficSignal {
+            id: to,
+            phases,
+            offset: self.offset,
+            actuated: self.actuated,
+            turn_groups,
+        }
+        .validate()
+        .map_err(|err| {
+            format!(
+                "The timing from {} doesn't work at {}: {}",
+                self.id, to, err
+            )
+        })
+    }
+
     pub fn validate(self) -> Result<ControlTrafficSignal, String> {
         // Does the assignment cover the correct set of groups?
         let expected_groups: BTreeSet<TurnGroupID> = self.turn_groups.keys().cloned().collect();
@@ -105,6 +334,36 @@ impl ControlTrafficSignal {
                 }
             }
 
+            if self.actuated
+                && (phase.min_green == Duration::ZERO || phase.min_green > phase.max_green)
+            {
+                return Err(format!(
+                    "Actuated traffic signal {} has a phase with bad min green {} and max green {}",
+                    self.id, phase.min_green, phase.max_green
+                ));
+            }
+
+            // Vehicles have to get some time after the pedestrians' head start
+            let shortest = if self.actuated {
+                phase.min_green
+            } else {
+                phase.duration
+            };
+            if phase.leading_pedestrian_interval >= shortest {
+                return Err(format!(
+                    "Traffic signal {} has a phase with a leading pedestrian interval of {}, but \
+                     it only lasts {}",
+                    self.id, phase.leading_pedestrian_interval, shortest
+                ));
+            }
+            if !self.actuated && phase.leading_pedestrian_interval + phase.yellow >= shortest {
+                return Err(format!(
+                    "Traffic signal {} has a phase with a leading pedestrian interval of {} and a \
+                     yellow of {}, but it only lasts {}",
+                    self.id, phase.leading_pedestrian_interval, phase.yellow, shortest
+                ));
+            }
+
             // Do any of the crosswalks yield?
             for g in phase.yield_groups.iter().map(|g| &self.turn_groups[g]) {
                 assert!(g.turn_type != TurnType::Crosswalk);
@@ -114,6 +373,11 @@ impl ControlTrafficSignal {
         Ok(self)
     }
 
+    // Does some phase let pedestrians cross every way at once, including diagonally?
+    pub fn has_ped_scramble(&self) -> bool {
+        self.phases.iter().any(|p| p.is_all_walk(self))
+    }
+
     // Returns true if this did anything
     pub fn convert_to_ped_scramble(&mut self) -> bool {
         let orig = self.clone();
@@ -166,6 +430,10 @@ impl Phase {
             protected_groups: BTreeSet::new(),
             yield_groups: BTreeSet::new(),
             duration: Duration::seconds(30.0),
+            min_green: Duration::seconds(10.0),
+            max_green: Duration::seconds(60.0),
+            leading_pedestrian_interval: Duration::ZERO,
+            yellow: Duration::seconds(3.0),
         }
     }
 
@@ -183,15 +451,37 @@ impl Phase {
         true
     }
 
+    // Every crosswalk is protected and every vehicle movement is banned
+    pub fn is_all_walk(&self, parent: &ControlTrafficSignal) -> bool {
+        let crosswalks: BTreeSet<TurnGroupID> = parent
+            .turn_groups
+            .keys()
+            .filter(|g| g.crosswalk)
+            .cloned()
+            .collect();
+        !crosswalks.is_empty()
+            && self.protected_groups == crosswalks
+            && self.yield_groups.is_empty()
+    }
+
     pub fn get_priority_of_turn(&self, t: TurnID, parent: &ControlTrafficSignal) -> TurnPriority {
         // TODO Cache this?
-        let g = parent
+        match parent
             .turn_groups
             .values()
             .find(|g| g.members.contains(&t))
             .map(|g| g.id)
-            .unwrap();
-        self.get_priority_of_group(g)
+        {
+            Some(g) => self.get_priority_of_group(g),
+            // Only diagonal crosswalks don't belong to a group
+            None => {
+                if self.is_all_walk(parent) {
+                    TurnPriority::Protected
+                } else {
+                    TurnPriority::Banned
+                }
+            }
+        }
     }
 
     pub fn get_priority_of_group(&self, g: TurnGroupID) -> TurnPriority {
@@ -274,6 +564,7 @@ impl ControlTrafficSignal {
                     protected_groups,
                     yield_groups,
                     duration: Duration::seconds(p.duration_seconds as f64),
+                    ..Phase::new()
                 });
             } else {
                 return None;
@@ -283,6 +574,7 @@ impl ControlTrafficSignal {
             id,
             phases,
             offset: Duration::ZERO,
+            actuated: false,
             turn_groups: TurnGroup::for_i(id, map),
         }
         .validate()
@@ -290,6 +582,133 @@ impl ControlTrafficSignal {
     }
 }
 
+impl ControlTrafficSignal {
+    pub fn export_plan(&self, map: &Map) -> SignalTimingPlan {
+        SignalTimingPlan {
+            intersection_osm_node_id: map.get_i(self.id).orig_id.osm_node_id,
+            offset_seconds: self.offset.inner_seconds() as usize,
+            actuated: self.actuated,
+            phases: self
+                .phases
+                .iter()
+                .map(|p| PlanPhase {
+                    protected_turns: p
+                        .protected_groups
+                        .iter()
+                        .map(|t| export_turn_group(t, map))
+                        .collect(),
+                    permitted_turns: p
+                        .yield_groups
+                        .iter()
+                        .map(|t| export_turn_group(t, map))
+                        .collect(),
+                    duration_seconds: p.duration.inner_seconds() as usize,
+                    min_green_seconds: if self.actuated {
+                        Some(p.min_green.inner_seconds() as usize)
+                    } else {
+                        None
+                    },
+                    max_green_seconds: if self.actuated {
+                        Some(p.max_green.inner_seconds() as usize)
+                    } else {
+                        None
+                    },
+                    leading_pedestrian_interval_seconds: p
+                        .leading_pedestrian_interval
+                        .inner_seconds()
+                        as usize,
+                    yellow_seconds: if self.actuated {
+                        None
+                    } else {
+                        Some(p.yellow.inner_seconds() as usize)
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    // The plan applies to the intersection it names. Unlike import, this explains what's wrong
+    // with plans that don't fit this map.
+    pub fn import_plan(plan: SignalTimingPlan, map: &Map) -> Result<ControlTrafficSignal, String> {
+        let id = map.find_i_by_osm_id(plan.intersection_osm_node_id)?;
+        let mut phases = Vec::new();
+        for (idx, p) in plan.phases.into_iter().enumerate() {
+            let mut phase = Phase::new();
+            for (turns, groups) in vec![
+                (p.protected_turns, &mut phase.protected_groups),
+                (p.permitted_turns, &mut phase.yield_groups),
+            ] {
+                for t in turns {
+                    groups.insert(import_turn_group(t, map).ok_or_else(|| {
+                        format!(
+                            "Phase {} has a movement between roads that don't exist in this map",
+                            idx + 1
+                        )
+                    })?);
+                }
+            }
+            phase.duration = Duration::seconds(p.duration_seconds as f64);
+            if let Some(min) = p.min_green_seconds {
+                phase.min_green = Duration::seconds(min as f64);
+            }
+            if let Some(max) = p.max_green_seconds {
+                phase.max_green = Duration::seconds(max as f64);
+            }
+            phase.leading_pedestrian_interval =
+                Duration::seconds(p.leading_pedestrian_interval_seconds as f64);
+            if let Some(yellow) = p.yellow_seconds {
+                phase.yellow = Duration::seconds(yellow as f64);
+            }
+            phases.push(phase);
+        }
+        ControlTrafficSignal {
+            id,
+            phases,
+            offset: Duration::seconds(plan.offset_seconds as f64),
+            actuated: plan.actuated,
+            turn_groups: TurnGroup::for_i(id, map),
+        }
+        .validate()
+    }
+}
+
+// Every road of an intersection, along with the direction it points away from it, sorted by that
+// angle.
+fn roads_by_angle(i: IntersectionID, map: &Map) -> Vec<(RoadID, Angle)> {
+    let mut roads: Vec<(RoadID, Angle)> = map
+        .get_i(i)
+        .roads
+        .iter()
+        .map(|r| {
+            let road = map.get_r(*r);
+            let angle = if road.src_i == i {
+                road.center_pts.first_line().angle()
+            } else {
+                road.center_pts.last_line().angle().opposite()
+            };
+            (*r, angle)
+        })
+        .collect();
+    roads.sort_by_key(|(_, angle)| (angle.normalized_degrees() * 100.0) as usize);
+    roads
+}
+
+// Keep the same sense of direction (into or out of the intersection) on the new road.
+fn remap_directed_road(
+    dr: DirectedRoadID,
+    from_i: IntersectionID,
+    r: RoadID,
+    to_i: IntersectionID,
+    map: &Map,
+) -> DirectedRoadID {
+    let incoming = dr.dst_i(map) == from_i;
+    if (map.get_r(r).dst_i == to_i) == incoming {
+        r.forwards()
+    } else {
+        r.backwards()
+    }
+}
+
 fn export_turn_group(id: &TurnGroupID, map: &Map) -> seattle_traffic_signals::Turn {
     let from = map.get_r(id.from.id).orig_id;
     let to = map.get_r(id.to.id).orig_id;
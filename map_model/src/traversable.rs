@@ -1,5 +1,5 @@
 use crate::{LaneID, Map, TurnID};
-use geom::{Angle, Distance, PolyLine, Pt2D, Speed};
+use geom::{Angle, Distance, PolyLine, Pt2D, Speed, Time};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -108,6 +108,13 @@ impl Traversable {
     }
 
     // TODO Just expose the PolyLine instead of all these layers of helpers
+    pub fn get_polyline<'a>(&self, map: &'a Map) -> &'a PolyLine {
+        match *self {
+            Traversable::Lane(id) => &map.get_l(id).lane_center_pts,
+            Traversable::Turn(id) => &map.get_t(id).geom,
+        }
+    }
+
     pub fn length(&self, map: &Map) -> Distance {
         match *self {
             Traversable::Lane(id) => map.get_l(id).length(),
@@ -143,6 +150,23 @@ impl Traversable {
         }
     }
 
+    // Including limits that only apply at certain times of day, for trucks and buses or everybody
+    // else
+    pub fn speed_limit_at(&self, map: &Map, time: Time, truck: bool) -> Speed {
+        match *self {
+            Traversable::Lane(id) => map.get_parent(id).speed_limit_at(time, truck),
+            Traversable::Turn(id) => map.get_parent(id.dst).speed_limit_at(time, truck),
+        }
+    }
+
+    // Turns are treated as flat.
+    pub fn percent_grade(&self, map: &Map) -> f64 {
+        match *self {
+            Traversable::Lane(id) => map.get_l(id).percent_grade(map),
+            Traversable::Turn(_) => 0.0,
+        }
+    }
+
     pub fn get_zorder(&self, map: &Map) -> isize {
         match *self {
             Traversable::Lane(id) => map.get_parent(id).zorder,
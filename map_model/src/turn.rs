@@ -26,6 +26,11 @@ impl fmt::Display for TurnID {
 pub enum TurnType {
     Crosswalk,
     SharedSidewalkCorner,
+    // Straight across the middle of a 4-way intersection, between opposite corners. Only usable
+    // during a traffic signal's all-walk phase.
+    DiagonalCrosswalk,
+    // Over or under a road, between the two sidewalks. Never conflicts with traffic.
+    Footbridge,
     // These are for vehicle turns
     Straight,
     LaneChangeLeft,
@@ -81,6 +86,8 @@ impl Turn {
     pub fn conflicts_with(&self, other: &Turn) -> bool {
         if self.turn_type == TurnType::SharedSidewalkCorner
             || other.turn_type == TurnType::SharedSidewalkCorner
+            || self.turn_type == TurnType::Footbridge
+            || other.turn_type == TurnType::Footbridge
         {
             return false;
         }
@@ -107,7 +114,10 @@ impl Turn {
     }
 
     pub fn between_sidewalks(&self) -> bool {
-        self.turn_type == TurnType::SharedSidewalkCorner || self.turn_type == TurnType::Crosswalk
+        self.turn_type == TurnType::SharedSidewalkCorner
+            || self.turn_type == TurnType::Crosswalk
+            || self.turn_type == TurnType::DiagonalCrosswalk
+            || self.turn_type == TurnType::Footbridge
     }
 }
 
@@ -141,7 +151,9 @@ impl TurnGroup {
             let from = map.get_l(turn.id.src).get_directed_parent(map);
             let to = map.get_l(turn.id.dst).get_directed_parent(map);
             match turn.turn_type {
-                TurnType::SharedSidewalkCorner => {}
+                TurnType::SharedSidewalkCorner
+                | TurnType::DiagonalCrosswalk
+                | TurnType::Footbridge => {}
                 TurnType::Crosswalk => {
                     let id = TurnGroupID {
                         from,
@@ -174,7 +186,10 @@ impl TurnGroup {
             let turn_types: BTreeSet<TurnType> = members
                 .iter()
                 .map(|t| match map.get_t(*t).turn_type {
-                    TurnType::Crosswalk | TurnType::SharedSidewalkCorner => unreachable!(),
+                    TurnType::Crosswalk
+                    | TurnType::SharedSidewalkCorner
+                    | TurnType::DiagonalCrosswalk
+                    | TurnType::Footbridge => unreachable!(),
                     TurnType::Straight | TurnType::LaneChangeLeft | TurnType::LaneChangeRight => {
                         TurnType::Straight
                     }
@@ -1,22 +1,61 @@
-use crate::{AlertLocation, CarID, Event, ParkingSpot, TripID, TripMode, TripPhaseType};
-use abstutil::Counter;
+use crate::metrics::MetricInput;
+use crate::{
+    AgentID, AlertLocation, CarID, CustomMetric, Event, MetricSeries, ParkingSpot, PedestrianID,
+    TripID, TripMode, TripPhaseType, VehicleType,
+};
+use abstutil::{deserialize_btreemap, serialize_btreemap, Counter, Timer};
 use geom::{Distance, Duration, Histogram, Time};
 use map_model::{
-    BusRouteID, BusStopID, IntersectionID, LaneID, Map, ParkingLotID, Path, PathRequest, RoadID,
-    Traversable, TurnGroupID,
+    BikeFacility, BuildingID, BusRouteID, BusStopID, DirectedRoadID, IntersectionID, LaneID, Map,
+    OriginalLane, ParkingLotID, Path, PathRequest, RoadID, Traversable, TurnGroupID, TurnID,
+    TurnType,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, VecDeque};
 
+// Individual intersection delays, bus arrivals, and raw throughput are only kept this long. After
+// that, they're folded into buckets or dropped, so memory stays bounded over a long run.
+const RAW_WINDOW: Duration = Duration::const_seconds(3600.0);
+const BUCKET_SIZE: Duration = Duration::const_seconds(15.0 * 60.0);
+// With a page directory, summaries older than this are written to disk and only read back while
+// answering a question about that time.
+const PAGE_WINDOW: Duration = Duration::const_seconds(3.0 * 3600.0);
+// Don't bother writing less than this much time at once
+const PAGE_SIZE: Duration = Duration::const_seconds(3600.0);
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Analytics {
     pub road_thruput: TimeSeriesCount<RoadID>,
+    // Just box trucks and tractor-trailers. They're counted in road_thruput too.
+    pub truck_road_thruput: TimeSeriesCount<RoadID>,
+    // Vehicles only, split by the direction they travel along the road. Sidewalks don't have a
+    // direction.
+    pub directed_road_thruput: TimeSeriesCount<DirectedRoadID>,
     pub intersection_thruput: TimeSeriesCount<IntersectionID>,
 
     // Unlike everything else in Analytics, this is just for a moment in time.
     pub demand: BTreeMap<TurnGroupID, usize>,
+    // How many walking trips planned to use each crosswalk on their shortest path, including
+    // people in wheelchairs who wound up going around
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub crossings: BTreeMap<TurnID, usize>,
     pub bus_arrivals: Vec<(Time, CarID, BusRouteID, BusStopID)>,
+    // The last compacted arrival of each bus, to measure the time to its next stop
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    last_compacted_bus_arrival: BTreeMap<CarID, (Time, BusRouteID, BusStopID)>,
+    // Once compacted, only the people who hadn't boarded yet are kept
     pub bus_passengers_waiting: Vec<(Time, BusStopID, BusRouteID)>,
+    // Somebody got on a bus. Only used to work out who's still waiting, so these're dropped once
+    // compacted.
+    pub bus_boardings: Vec<(Time, BusStopID, BusRouteID)>,
+    // Somebody couldn't board a full bus
+    pub denied_boardings: Vec<(Time, CarID, BusRouteID, BusStopID)>,
     pub started_trips: BTreeMap<TripID, Time>,
     // TODO Hack: No TripMode means aborted
     // Finish time, ID, mode (or None as aborted), trip duration
@@ -24,10 +63,67 @@ pub struct Analytics {
     // TODO This subsumes finished_trips
     pub trip_log: Vec<(Time, TripID, Option<PathRequest>, TripPhaseType)>,
     pub intersection_delays: BTreeMap<IntersectionID, Vec<(Time, Duration, TripMode)>>,
+    // Summaries of older intersection delays and bus activity still in memory
+    pub compacted: CompactedAnalytics,
+    // Everything in intersection_delays, bus arrivals, and raw throughput before this has been
+    // compacted
+    compacted_until: Time,
+    // If set, old summaries are moved out of compacted into files in this directory
+    page_dir: Option<String>,
+    // The start time and file of every page written so far, in order
+    pages: Vec<(Time, String)>,
+    // Everything in compacted before this has been paged out
+    paged_until: Time,
     // Per parking lane or lot, when does a spot become filled (true) or free (false)
     pub parking_lane_changes: BTreeMap<LaneID, Vec<(Time, bool)>>,
     pub parking_lot_changes: BTreeMap<ParkingLotID, Vec<(Time, bool)>>,
+    // When did each car currently parked on-street start paying?
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    parking_started: BTreeMap<CarID, Time>,
+    // When a car left a spot, what lane was it on, and how many cents did it pay?
+    pub parking_revenue: Vec<(Time, LaneID, usize)>,
+    // When a car finally parked after circling around, how far did it drive and how long did it
+    // take?
+    pub parking_cruising: Vec<(Time, CarID, Distance, Duration)>,
+    // How many scooters each rebalancing tour moved, and how far the van drove
+    pub scooter_rebalancing: Vec<(Time, usize, Distance)>,
+    // Where cars stopped to let somebody out, and if they used a pickup / drop-off zone
+    pub drop_offs: Vec<(Time, LaneID, bool)>,
+    // Where trucks stopped to make deliveries, and if they used a loading zone
+    pub deliveries: Vec<(Time, LaneID, bool)>,
+    // How far ride-hail vehicles drove without a passenger
+    pub ridehail_deadheading: Vec<(Time, CarID, Distance)>,
+    // When an emergency vehicle arrived, where it came from and went, and how long it took
+    pub emergency_responses: Vec<(Time, BuildingID, BuildingID, Duration)>,
+    // At the end of each reporting interval, per detector: the lane it's on, how many vehicles
+    // reached it, and its occupancy
+    pub detector_readings: Vec<(Time, usize, LaneID, usize, f64)>,
+    // Where people crossed roads mid-block
+    pub jaywalks: Vec<(Time, PedestrianID, RoadID)>,
+    // Where drivers had to brake for them
+    pub jaywalking_conflicts: Vec<(Time, PedestrianID, CarID, RoadID)>,
+    // Where drivers didn't yield to somebody waiting to cross at a stop sign
+    pub yield_failures: Vec<(Time, CarID, PedestrianID, IntersectionID)>,
+    // Where bikes (the first car) had to dodge a parked car's door
+    pub door_zone_conflicts: Vec<(Time, CarID, CarID, LaneID)>,
+    // Where drivers caught up to a bike sharing their lane, and if they passed it (true) or got
+    // stuck behind it (false)
+    pub bike_passes: Vec<(Time, CarID, CarID, RoadID, bool)>,
+    // How far bikes rode along each kind of facility, and how long it took. Only lanes that bikes
+    // went all the way along count.
+    pub bike_facility_usage: BTreeMap<BikeFacility, (Distance, Duration)>,
+    // The lane each bike is riding along right now, and when it started
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    bikes_on_lanes: BTreeMap<CarID, (Time, LaneID)>,
     pub(crate) alerts: Vec<(Time, AlertLocation, String)>,
+    // User-defined, from SimOptions
+    pub custom_metrics: Vec<MetricSeries>,
 
     // After we restore from a savestate, don't record anything. This is only going to make sense
     // if savestates are only used for quickly previewing against prebaked results, where we have
@@ -39,32 +135,92 @@ impl Analytics {
     pub fn new() -> Analytics {
         Analytics {
             road_thruput: TimeSeriesCount::new(),
+            truck_road_thruput: TimeSeriesCount::new(),
+            directed_road_thruput: TimeSeriesCount::new(),
             intersection_thruput: TimeSeriesCount::new(),
             demand: BTreeMap::new(),
+            crossings: BTreeMap::new(),
             bus_arrivals: Vec::new(),
+            last_compacted_bus_arrival: BTreeMap::new(),
             bus_passengers_waiting: Vec::new(),
+            bus_boardings: Vec::new(),
+            denied_boardings: Vec::new(),
             started_trips: BTreeMap::new(),
             finished_trips: Vec::new(),
             trip_log: Vec::new(),
             intersection_delays: BTreeMap::new(),
+            compacted: CompactedAnalytics::new(),
+            compacted_until: Time::START_OF_DAY,
+            page_dir: None,
+            pages: Vec::new(),
+            paged_until: Time::START_OF_DAY,
             parking_lane_changes: BTreeMap::new(),
             parking_lot_changes: BTreeMap::new(),
+            parking_started: BTreeMap::new(),
+            parking_revenue: Vec::new(),
+            parking_cruising: Vec::new(),
+            scooter_rebalancing: Vec::new(),
+            drop_offs: Vec::new(),
+            deliveries: Vec::new(),
+            ridehail_deadheading: Vec::new(),
+            emergency_responses: Vec::new(),
+            detector_readings: Vec::new(),
+            jaywalks: Vec::new(),
+            jaywalking_conflicts: Vec::new(),
+            yield_failures: Vec::new(),
+            door_zone_conflicts: Vec::new(),
+            bike_passes: Vec::new(),
+            bike_facility_usage: BTreeMap::new(),
+            bikes_on_lanes: BTreeMap::new(),
             alerts: Vec::new(),
+            custom_metrics: Vec::new(),
             record_anything: true,
         }
     }
 
+    pub fn with_custom_metrics(metrics: Vec<CustomMetric>) -> Analytics {
+        let mut a = Analytics::new();
+        a.custom_metrics = metrics.into_iter().map(MetricSeries::new).collect();
+        a
+    }
+
+    // Keep old summaries in files here instead of memory. One run per directory.
+    pub fn page_old_data_to(&mut self, dir: String) {
+        self.page_dir = Some(dir);
+    }
+
     pub fn event(&mut self, ev: Event, time: Time, map: &Map) {
         if !self.record_anything {
             return;
         }
 
+        // Custom metrics. Do this first, to know if a trip is just starting.
+        if !self.custom_metrics.is_empty() {
+            let new_trip = match ev {
+                Event::TripPhaseStarting(id, _, _, _) => !self.started_trips.contains_key(&id),
+                _ => false,
+            };
+            if let Some(input) = MetricInput::from_event(&ev, new_trip, map) {
+                for m in &mut self.custom_metrics {
+                    m.record(time, &input);
+                }
+            }
+        }
+
         // Throughput
         if let Event::AgentEntersTraversable(a, to) = ev {
             let mode = TripMode::from_agent(a);
             match to {
                 Traversable::Lane(l) => {
-                    self.road_thruput.record(time, map.get_l(l).parent, mode);
+                    let lane = map.get_l(l);
+                    self.road_thruput.record(time, lane.parent, mode);
+                    if !lane.is_sidewalk() {
+                        let id = DirectedRoadID {
+                            id: lane.parent,
+                            forwards: map.get_r(lane.parent).is_forwards(l),
+                        };
+                        self.directed_road_thruput.record(time, id, mode);
+                    }
                 }
                 Traversable::Turn(t) => {
                     self.intersection_thruput.record(time, t.parent, mode);
@@ -79,6 +235,9 @@ impl Analytics {
             Event::PersonLeavesMap(_, mode, i, _) | Event::PersonEntersMap(_, mode, i, _) => {
                 self.intersection_thruput.record(time, i, mode);
             }
+            Event::TruckEntersRoad(_, r) => {
+                self.truck_road_thruput.record(time, r, TripMode::Drive);
+            }
             _ => {}
         }
 
@@ -87,11 +246,19 @@ impl Analytics {
             self.bus_arrivals.push((time, bus, route, stop));
         }
 
+        // Full buses
+        if let Event::PassengerDeniedBoarding(_, bus, route, stop) = ev {
+            self.denied_boardings.push((time, bus, route, stop));
+        }
+
         // Bus passengers
         if let Event::TripPhaseStarting(_, _, _, ref tpt) = ev {
             if let TripPhaseType::WaitingForBus(route, stop) = tpt {
                 self.bus_passengers_waiting.push((time, *stop, *route));
             }
+            if let TripPhaseType::RidingBus(route, stop, _) = tpt {
+                self.bus_boardings.push((time, *stop, *route));
+            }
         }
 
         // Started trips
@@ -121,10 +288,82 @@ impl Analytics {
                 .or_insert_with(Vec::new)
                 .push((time, delay, mode));
         }
+        if time - self.compacted_until >= RAW_WINDOW + BUCKET_SIZE {
+            self.compact(time);
+        }
+
+        if let Event::CruisedForParking(car, dist, dt) = ev {
+            self.parking_cruising.push((time, car, dist, dt));
+        }
+        if let Event::ScootersRebalanced(moved, dist) = ev {
+            self.scooter_rebalancing.push((time, moved, dist));
+        }
+        if let Event::PassengerDroppedOff(_, l, in_zone) = ev {
+            self.drop_offs.push((time, l, in_zone));
+        }
+        if let Event::DeliveryStarted(_, l, in_zone) = ev {
+            self.deliveries.push((time, l, in_zone));
+        }
+        if let Event::RideHailDeadhead(car, dist) = ev {
+            self.ridehail_deadheading.push((time, car, dist));
+        }
+        if let Event::EmergencyResponse(_, from, to, dt) = ev {
+            self.emergency_responses.push((time, from, to, dt));
+        }
+        if let Event::DetectorReading(id, l, count, occupancy) = ev {
+            self.detector_readings.push((time, id, l, count, occupancy));
+        }
+        if let Event::PedJaywalked(ped, r) = ev {
+            self.jaywalks.push((time, ped, r));
+        }
+        if let Event::JaywalkingConflict(ped, car, r) = ev {
+            self.jaywalking_conflicts.push((time, ped, car, r));
+        }
+        if let Event::FailedToYield(car, ped, i) = ev {
+            self.yield_failures.push((time, car, ped, i));
+        }
+
+        // Bikes
+        if let Event::DoorZoneConflict(bike, car, l) = ev {
+            self.door_zone_conflicts.push((time, bike, car, l));
+        }
+        if let Event::PassedBike(car, bike, r) = ev {
+            self.bike_passes.push((time, car, bike, r, true));
+        }
+        if let Event::StuckBehindBike(car, bike, r) = ev {
+            self.bike_passes.push((time, car, bike, r, false));
+        }
+        if let Event::BikeStoppedAtSidewalk(bike, _) = ev {
+            self.bikes_on_lanes.remove(&bike);
+        }
+        if let Event::AgentEntersTraversable(AgentID::Car(bike), to) = ev {
+            if bike.1 == VehicleType::Bike {
+                match to {
+                    Traversable::Lane(l) => {
+                        self.bikes_on_lanes.insert(bike, (time, l));
+                    }
+                    Traversable::Turn(t) => {
+                        if let Some((started, l)) = self.bikes_on_lanes.remove(&bike) {
+                            if l == t.src {
+                                if let Some(facility) = map.get_parent(l).bike_facility(l) {
+                                    let entry = self
+                                        .bike_facility_usage
+                                        .entry(facility)
+                                        .or_insert((Distance::ZERO, Duration::ZERO));
+                                    entry.0 += map.get_l(l).length();
+                                    entry.1 += time - started;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
 
         // Parking spot changes
-        if let Event::CarReachedParkingSpot(_, spot) = ev {
+        if let Event::CarReachedParkingSpot(car, spot) = ev {
             if let ParkingSpot::Onstreet(l, _) = spot {
+                self.parking_started.insert(car, time);
                 self.parking_lane_changes
                     .entry(l)
                     .or_insert_with(Vec::new)
@@ -136,8 +375,17 @@ impl Analytics {
                     .push((time, true));
             }
         }
-        if let Event::CarLeftParkingSpot(_, spot) = ev {
+        if let Event::CarLeftParkingSpot(car, spot) = ev {
             if let ParkingSpot::Onstreet(l, _) = spot {
+                // Cars parked before the simulation started don't pay
+                if let Some(started) = self.parking_started.remove(&car) {
+                    let hours = (time - started).inner_seconds() / 3600.0;
+                    let cost = (hours * (map.get_l(l).parking_restrictions.cents_per_hour as f64))
+                        .round() as usize;
+                    if cost > 0 {
+                        self.parking_revenue.push((time, l, cost));
+                    }
+                }
                 self.parking_lane_changes
                     .entry(l)
                     .or_insert_with(Vec::new)
@@ -172,6 +420,444 @@ impl Analytics {
         }
     }
 
+    // Fold everything from buckets that ended more than RAW_WINDOW ago into summaries.
+    fn compact(&mut self, now: Time) {
+        let cutoff = bucket_start(now - RAW_WINDOW);
+        if cutoff <= self.compacted_until {
+            return;
+        }
+        self.compact_intersection_delays(cutoff);
+        self.compact_bus_arrivals(cutoff);
+        self.road_thruput.compact(cutoff);
+        self.truck_road_thruput.compact(cutoff);
+        self.directed_road_thruput.compact(cutoff);
+        self.intersection_thruput.compact(cutoff);
+        self.compacted_until = cutoff;
+        self.page_out(now);
+    }
+
+    // Write summaries older than PAGE_WINDOW to a new page, once there's enough of them
+    fn page_out(&mut self, now: Time) {
+        let dir = match self.page_dir {
+            Some(ref dir) => dir.clone(),
+            None => {
+                return;
+            }
+        };
+        let cutoff = bucket_start(now - PAGE_WINDOW).min(self.compacted_until);
+        if cutoff - self.paged_until < PAGE_SIZE {
+            return;
+        }
+        let page = self.compacted.split_off_before(cutoff);
+        let path = format!("{}/{}.bin", dir, self.paged_until.inner_seconds() as usize);
+        abstutil::write_binary(path.clone(), &page);
+        self.pages.push((self.paged_until, path));
+        self.paged_until = cutoff;
+    }
+
+    // Everything compacted up to some time, oldest first. Pages are read back one at a time, just
+    // for the caller to look at.
+    fn for_each_compacted<F: FnMut(&CompactedAnalytics)>(&self, now: Time, mut f: F) {
+        for (start, path) in &self.pages {
+            if *start > now {
+                return;
+            }
+            match abstutil::maybe_read_binary::<CompactedAnalytics>(
+                path.clone(),
+                &mut Timer::throwaway(),
+            ) {
+                Ok(page) => f(&page),
+                Err(err) => panic!("Couldn't read old analytics from {}: {}", path, err),
+            }
+        }
+        f(&self.compacted);
+    }
+
+    fn compact_intersection_delays(&mut self, cutoff: Time) {
+        for (i, list) in self.intersection_delays.iter_mut() {
+            let num_old = list.iter().take_while(|(t, _, _)| *t < cutoff).count();
+            if num_old == 0 {
+                continue;
+            }
+            let buckets = self
+                .compacted
+                .intersection_delays
+                .entry(*i)
+                .or_insert_with(Vec::new);
+            let mut current: BTreeMap<TripMode, DelayBucket> = BTreeMap::new();
+            for (t, dt, mode) in list.drain(0..num_old) {
+                let start = bucket_start(t);
+                if current.values().next().map(|b| b.start != start) == Some(true) {
+                    buckets.extend(
+                        std::mem::replace(&mut current, BTreeMap::new())
+                            .into_iter()
+                            .map(|(_, b)| b),
+                    );
+                }
+                let bucket = current.entry(mode).or_insert_with(|| DelayBucket {
+                    start,
+                    mode,
+                    count: 0,
+                    total: Duration::ZERO,
+                    max: Duration::ZERO,
+                });
+                bucket.count += 1;
+                bucket.total += dt;
+                bucket.max = bucket.max.max(dt);
+            }
+            buckets.extend(current.into_iter().map(|(_, b)| b));
+        }
+    }
+
+    // Arrivals are summarized as the time from each bus's previous stop, like bus_arrivals()
+    // reports. Boarding is replayed up to the cutoff, so only the people still waiting are kept.
+    fn compact_bus_arrivals(&mut self, cutoff: Time) {
+        let num_denied = self
+            .denied_boardings
+            .iter()
+            .take_while(|(t, _, _, _)| *t < cutoff)
+            .count();
+        for (t, _, route, stop) in self.denied_boardings.drain(0..num_denied) {
+            *self
+                .compacted
+                .denied_boardings
+                .entry((route, bucket_start(t), stop))
+                .or_insert(0) += 1;
+        }
+
+        let num_waiting = self
+            .bus_passengers_waiting
+            .iter()
+            .take_while(|(t, _, _)| *t < cutoff)
+            .count();
+        let mut waiting: BTreeMap<(BusRouteID, BusStopID), Vec<Time>> = BTreeMap::new();
+        for (t, stop, route) in self.bus_passengers_waiting.drain(0..num_waiting) {
+            waiting
+                .entry((route, stop))
+                .or_insert_with(Vec::new)
+                .push(t);
+        }
+        let num_boardings = self
+            .bus_boardings
+            .iter()
+            .take_while(|(t, _, _)| *t < cutoff)
+            .count();
+        for (t, stop, route) in self.bus_boardings.drain(0..num_boardings) {
+            if let Some(times) = waiting.get_mut(&(route, stop)) {
+                board_bus(times, t);
+            }
+        }
+
+        let num_arrivals = self
+            .bus_arrivals
+            .iter()
+            .take_while(|(t, _, _, _)| *t < cutoff)
+            .count();
+        let mut buckets: BTreeMap<(BusRouteID, Time, BusStopID), BusDelayBucket> = BTreeMap::new();
+        for (t, car, route, stop) in self.bus_arrivals.drain(0..num_arrivals) {
+            let last = self
+                .compacted
+                .last_bus_arrivals
+                .entry((route, stop))
+                .or_insert_with(Vec::new);
+            if last.last().map(|t0| bucket_start(*t0)) == Some(bucket_start(t)) {
+                last.pop();
+            }
+            last.push(t);
+
+            if let Some((t0, _, _)) = self
+                .last_compacted_bus_arrival
+                .insert(car, (t, route, stop))
+            {
+                let start = bucket_start(t);
+                let bucket =
+                    buckets
+                        .entry((route, start, stop))
+                        .or_insert_with(|| BusDelayBucket {
+                            start,
+                            stop,
+                            count: 0,
+                            total: Duration::ZERO,
+                            max: Duration::ZERO,
+                        });
+                bucket.count += 1;
+                bucket.total += t - t0;
+                bucket.max = bucket.max.max(t - t0);
+            }
+        }
+        for ((route, _, _), bucket) in buckets {
+            self.compacted
+                .bus_arrivals
+                .entry(route)
+                .or_insert_with(Vec::new)
+                .push(bucket);
+        }
+
+        let mut still_waiting: Vec<(Time, BusStopID, BusRouteID)> = waiting
+            .into_iter()
+            .flat_map(|((route, stop), times)| times.into_iter().map(move |t| (t, stop, route)))
+            .collect();
+        still_waiting.sort();
+        still_waiting.extend(self.bus_passengers_waiting.drain(..));
+        self.bus_passengers_waiting = still_waiting;
+    }
+
+    // Dump the value of every custom metric over time
+    pub fn export_custom_metrics(&self, path: String) {
+        abstutil::write_json(path, &self.custom_metrics);
+    }
+
+    // A CSV file laid out like the raw feeds from real detector stations: one row per detector per
+    // reporting interval, with the vehicle count and percent occupancy. Lanes are identified by
+    // their OSM way and nodes, their direction, and their offset from the middle of the road, so
+    // readings can be matched up after the map is rebuilt.
+    pub fn export_detector_readings(&self, path: String, map: &Map) {
+        let mut out = String::from(
+            "timestamp,detector,lane,osm_way_id,osm_node_id1,osm_node_id2,direction,offset,flow,\
+             occupancy\n",
+        );
+        for (time, id, l, count, occupancy) in &self.detector_readings {
+            let orig = OriginalLane::to_permanent(*l, map);
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{:.1}\n",
+                time,
+                id,
+                l.0,
+                orig.parent.osm_way_id,
+                orig.parent.i1.osm_node_id,
+                orig.parent.i2.osm_node_id,
+                if orig.fwd { "forwards" } else { "backwards" },
+                orig.idx,
+                count,
+                occupancy * 100.0
+            ));
+        }
+        if let Err(err) = std::fs::write(&path, out) {
+            panic!("Can't write {}: {}", path, err);
+        }
+        println!("Wrote {}", path);
+    }
+
+    // A CSV file with the number of vehicles starting along each road per hour, with trucks broken
+    // out separately. Pedestrians aren't counted.
+    pub fn export_road_volumes(&self, path: String, map: &Map) {
+        let mut counts: BTreeMap<(RoadID, usize), (usize, usize)> = BTreeMap::new();
+        for ((r, mode, hour), count) in &self.road_thruput.counts {
+            if *mode != TripMode::Walk {
+                counts.entry((*r, *hour)).or_insert((0, 0)).0 += count;
+            }
+        }
+        for ((r, _, hour), count) in &self.truck_road_thruput.counts {
+            counts.entry((*r, *hour)).or_insert((0, 0)).1 += count;
+        }
+
+        let mut out = String::from(
+            "road,osm_way_id,osm_node_id1,osm_node_id2,hour,vehicles,trucks,percent_trucks\n",
+        );
+        for ((r, hour), (vehicles, trucks)) in counts {
+            let orig = map.get_r(r).orig_id;
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{:.1}\n",
+                r.0,
+                orig.osm_way_id,
+                orig.i1.osm_node_id,
+                orig.i2.osm_node_id,
+                hour,
+                vehicles,
+                trucks,
+                if vehicles == 0 {
+                    0.0
+                } else {
+                    100.0 * (trucks as f64) / (vehicles as f64)
+                }
+            ));
+        }
+        if let Err(err) = std::fs::write(&path, out) {
+            panic!("Can't write {}: {}", path, err);
+        }
+        println!("Wrote {}", path);
+    }
+
+    // A tidy CSV file with one row per road, direction, mode, and hour in [from_hour, to_hour),
+    // counting agents starting along the road. Pedestrians are counted in both directions
+    // together.
+    pub fn export_flow_map(&self, path: String, map: &Map, from_hour: usize, to_hour: usize) {
+        let mut out =
+            String::from("road,osm_way_id,osm_node_id1,osm_node_id2,direction,mode,hour,count\n");
+        let mut rows: BTreeMap<(RoadID, &'static str, TripMode, usize), usize> = BTreeMap::new();
+        for ((dr, mode, hour), count) in &self.directed_road_thruput.counts {
+            if (from_hour..to_hour).contains(hour) {
+                let dir = if dr.forwards { "forwards" } else { "backwards" };
+                rows.insert((dr.id, dir, *mode, *hour), *count);
+            }
+        }
+        for ((r, mode, hour), count) in &self.road_thruput.counts {
+            if *mode == TripMode::Walk && (from_hour..to_hour).contains(hour) {
+                rows.insert((*r, "both", *mode, *hour), *count);
+            }
+        }
+        for ((r, dir, mode, hour), count) in rows {
+            let orig = map.get_r(r).orig_id;
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{}\n",
+                r.0,
+                orig.osm_way_id,
+                orig.i1.osm_node_id,
+                orig.i2.osm_node_id,
+                dir,
+                mode.noun().to_lowercase(),
+                hour,
+                count
+            ));
+        }
+        if let Err(err) = std::fs::write(&path, out) {
+            panic!("Can't write {}: {}", path, err);
+        }
+        println!("Wrote {}", path);
+    }
+
+    // In cents. Only counts cars that have already left their spot.
+    pub fn total_parking_revenue(&self, now: Time) -> usize {
+        self.parking_revenue
+            .iter()
+            .take_while(|(t, _, _)| *t <= now)
+            .map(|(_, _, cost)| *cost)
+            .sum()
+    }
+
+    // How long do drivers spend looking for parking, from when they first start searching until
+    // they've parked? Only counts searches that finished before now. None if nobody has parked yet.
+    pub fn average_cruising_time(&self, now: Time) -> Option<Duration> {
+        let mut searching_since: BTreeMap<TripID, Time> = BTreeMap::new();
+        let mut total = Duration::ZERO;
+        let mut cnt = 0;
+        for (t, id, _, phase) in &self.trip_log {
+            if *t > now {
+                break;
+            }
+            if *phase == TripPhaseType::Parking {
+                searching_since.entry(*id).or_insert(*t);
+            } else if *phase == TripPhaseType::Aborted {
+                searching_since.remove(id);
+            } else if let Some(start) = searching_since.remove(id) {
+                total += *t - start;
+                cnt += 1;
+            }
+        }
+        if cnt == 0 {
+            None
+        } else {
+            Some(total / (cnt as f64))
+        }
+    }
+
+    // How many drivers had to circle around to find parking, and the total distance and time they
+    // wasted doing so
+    pub fn parking_cruising_totals(&self, now: Time) -> (usize, Distance, Duration) {
+        let mut cnt = 0;
+        let mut dist = Distance::ZERO;
+        let mut dt = Duration::ZERO;
+        for (t, _, d, duration) in &self.parking_cruising {
+            if *t > now {
+                break;
+            }
+            cnt += 1;
+            dist += *d;
+            dt += *duration;
+        }
+        (cnt, dist, dt)
+    }
+
+    // How many drop-offs used a pickup / drop-off zone, and how many blocked the lane instead
+    pub fn drop_off_totals(&self, now: Time) -> (usize, usize) {
+        let mut in_zone = 0;
+        let mut in_lane = 0;
+        for (t, _, zone) in &self.drop_offs {
+            if *t > now {
+                break;
+            }
+            if *zone {
+                in_zone += 1;
+            } else {
+                in_lane += 1;
+            }
+        }
+        (in_zone, in_lane)
+    }
+
+    // How many deliveries used a loading zone, and how many double-parked instead
+    pub fn delivery_totals(&self, now: Time) -> (usize, usize) {
+        let mut in_zone = 0;
+        let mut double_parked = 0;
+        for (t, _, zone) in &self.deliveries {
+            if *t > now {
+                break;
+            }
+            if *zone {
+                in_zone += 1;
+            } else {
+                double_parked += 1;
+            }
+        }
+        (in_zone, double_parked)
+    }
+
+    pub fn total_ridehail_deadheading(&self, now: Time) -> Distance {
+        let mut dist = Distance::ZERO;
+        for (t, _, d) in &self.ridehail_deadheading {
+            if *t > now {
+                break;
+            }
+            dist += *d;
+        }
+        dist
+    }
+
+    pub fn emergency_response_times(&self, now: Time) -> Histogram<Duration> {
+        let mut times = Histogram::new();
+        for (t, _, _, dt) in &self.emergency_responses {
+            if *t > now {
+                break;
+            }
+            times.add(*dt);
+        }
+        times
+    }
+
+    // Per road, how many people crossed mid-block, and how many times a driver had to brake for
+    // them
+    pub fn jaywalking_per_road(&self, now: Time) -> (Counter<RoadID>, Counter<RoadID>) {
+        let mut jaywalks = Counter::new();
+        for (t, _, r) in &self.jaywalks {
+            if *t > now {
+                break;
+            }
+            jaywalks.inc(*r);
+        }
+        let mut conflicts = Counter::new();
+        for (t, _, _, r) in &self.jaywalking_conflicts {
+            if *t > now {
+                break;
+            }
+            conflicts.inc(*r);
+        }
+        (jaywalks, conflicts)
+    }
+
+    // Per kind of bike facility, how many times bikes had to dodge a door
+    pub fn door_zone_conflicts_per_facility(&self, now: Time, map: &Map) -> Counter<BikeFacility> {
+        let mut cnt = Counter::new();
+        for (t, _, _, l) in &self.door_zone_conflicts {
+            if *t > now {
+                break;
+            }
+            if let Some(facility) = map.get_parent(*l).bike_facility(*l) {
+                cnt.inc(facility);
+            }
+        }
+        cnt
+    }
+
     pub fn record_demand(&mut self, path: &Path, map: &Map) {
         for step in path.get_steps() {
             if let Traversable::Turn(t) = step.as_traversable() {
@@ -182,6 +868,19 @@ impl Analytics {
         }
     }
 
+    pub fn record_crossings(&mut self, path: &Path, map: &Map) {
+        for step in path.get_steps() {
+            if let Traversable::Turn(t) = step.as_traversable() {
+                match map.get_t(t).turn_type {
+                    TurnType::Crosswalk | TurnType::DiagonalCrosswalk => {
+                        *self.crossings.entry(t).or_insert(0) += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
     // TODO If these ever need to be speeded up, just cache the histogram and index in the events
     // list.
 
@@ -233,69 +932,182 @@ impl Analytics {
     // Find intersections where the cumulative sum of delay has changed. Negative means faster.
     pub fn compare_delay(&self, now: Time, before: &Analytics) -> Vec<(IntersectionID, Duration)> {
         let mut results = Vec::new();
-        for (i, list1) in &self.intersection_delays {
-            if let Some(list2) = before.intersection_delays.get(i) {
-                let mut sum1 = Duration::ZERO;
-                for (t, dt, _) in list1 {
-                    if *t > now {
+        for i in self.intersection_delays.keys() {
+            if before.intersection_delays.contains_key(i) {
+                let sum1 = self.total_delay(*i, now);
+                let sum2 = before.total_delay(*i, now);
+                if sum1 != sum2 {
+                    results.push((*i, sum1 - sum2));
+                }
+            }
+        }
+        results
+    }
+
+    fn total_delay(&self, i: IntersectionID, now: Time) -> Duration {
+        let mut sum = Duration::ZERO;
+        self.for_each_compacted(now, |c| {
+            if let Some(buckets) = c.intersection_delays.get(&i) {
+                for b in buckets {
+                    if b.start > now {
+                        break;
+                    }
+                    sum += b.total;
+                }
+            }
+        });
+        for (t, dt, _) in &self.intersection_delays[&i] {
+            if *t > now {
+                break;
+            }
+            sum += *dt;
+        }
+        sum
+    }
+
+    // Every delay through an intersection up to some time. Compacted delays are summarized as one
+    // (middle of the bucket, average delay) point per mode.
+    pub fn delays_through(&self, i: IntersectionID, now: Time) -> Vec<(Time, Duration, TripMode)> {
+        let mut results = Vec::new();
+        self.for_each_compacted(now, |c| {
+            if let Some(buckets) = c.intersection_delays.get(&i) {
+                for b in buckets {
+                    if b.start > now {
                         break;
                     }
-                    sum1 += *dt;
+                    results.push((
+                        b.start + BUCKET_SIZE / 2.0,
+                        b.total / (b.count as f64),
+                        b.mode,
+                    ));
+                }
+            }
+        });
+        if let Some(list) = self.intersection_delays.get(&i) {
+            for (t, dt, mode) in list {
+                if *t > now {
+                    break;
                 }
+                results.push((*t, *dt, *mode));
+            }
+        }
+        results
+    }
 
-                let mut sum2 = Duration::ZERO;
-                for (t, dt, _) in list2 {
-                    if *t > now {
+    // Per intersection, the average delay of one mode up to some time, and how many delays that
+    // averages. Intersections nobody of that mode waited at are skipped.
+    pub fn average_delays(
+        &self,
+        mode: TripMode,
+        now: Time,
+    ) -> Vec<(IntersectionID, Duration, usize)> {
+        // Go through each page once, not once per intersection
+        let mut per_intersection: BTreeMap<IntersectionID, (usize, Duration)> = BTreeMap::new();
+        self.for_each_compacted(now, |c| {
+            for (i, buckets) in &c.intersection_delays {
+                let (count, sum) = per_intersection.entry(*i).or_insert((0, Duration::ZERO));
+                for b in buckets {
+                    if b.start > now {
                         break;
                     }
-                    sum2 += *dt;
+                    if b.mode == mode {
+                        *count += b.count;
+                        *sum += b.total;
+                    }
                 }
+            }
+        });
 
-                if sum1 != sum2 {
-                    results.push((*i, sum1 - sum2));
+        let mut results = Vec::new();
+        for (i, list) in &self.intersection_delays {
+            let (mut count, mut sum) = per_intersection
+                .get(i)
+                .cloned()
+                .unwrap_or((0, Duration::ZERO));
+            for (t, dt, m) in list {
+                if *t > now {
+                    break;
+                }
+                if *m == mode {
+                    count += 1;
+                    sum += *dt;
                 }
             }
+            if count > 0 {
+                results.push((*i, sum / (count as f64), count));
+            }
         }
         results
     }
 
+    // Compacted arrivals only remember the average time from the previous stop
     pub fn bus_arrivals(
         &self,
         now: Time,
         r: BusRouteID,
     ) -> BTreeMap<BusStopID, Histogram<Duration>> {
-        let mut per_bus: BTreeMap<CarID, Vec<(Time, BusStopID)>> = BTreeMap::new();
-        for (t, car, route, stop) in &self.bus_arrivals {
-            if *t > now {
-                break;
-            }
-            if *route == r {
-                per_bus
-                    .entry(*car)
-                    .or_insert_with(Vec::new)
-                    .push((*t, *stop));
-            }
-        }
         let mut delay_to_stop: BTreeMap<BusStopID, Histogram<Duration>> = BTreeMap::new();
-        for events in per_bus.values() {
-            for pair in events.windows(2) {
-                delay_to_stop
-                    .entry(pair[1].1)
-                    .or_insert_with(Histogram::new)
-                    .add(pair[1].0 - pair[0].0);
+        self.for_each_compacted(now, |c| {
+            if let Some(buckets) = c.bus_arrivals.get(&r) {
+                for b in buckets {
+                    if b.start > now {
+                        break;
+                    }
+                    let hgram = delay_to_stop.entry(b.stop).or_insert_with(Histogram::new);
+                    for _ in 0..b.count {
+                        hgram.add(b.total / (b.count as f64));
+                    }
+                }
             }
+        });
+        for (_, stop, dt) in self.bus_delays(now, r) {
+            delay_to_stop
+                .entry(stop)
+                .or_insert_with(Histogram::new)
+                .add(dt);
         }
         delay_to_stop
     }
 
-    // TODO Refactor!
-    // For each stop, a list of (time, delay)
+    // For each stop, a list of (time, delay). Compacted arrivals are summarized as one (middle of
+    // the bucket, average delay) point.
     pub fn bus_arrivals_over_time(
         &self,
         now: Time,
         r: BusRouteID,
     ) -> BTreeMap<BusStopID, Vec<(Time, Duration)>> {
+        let mut delays_to_stop: BTreeMap<BusStopID, Vec<(Time, Duration)>> = BTreeMap::new();
+        self.for_each_compacted(now, |c| {
+            if let Some(buckets) = c.bus_arrivals.get(&r) {
+                for b in buckets {
+                    if b.start > now {
+                        break;
+                    }
+                    delays_to_stop
+                        .entry(b.stop)
+                        .or_insert_with(Vec::new)
+                        .push((b.start + BUCKET_SIZE / 2.0, b.total / (b.count as f64)));
+                }
+            }
+        });
+        for (t, stop, dt) in self.bus_delays(now, r) {
+            delays_to_stop
+                .entry(stop)
+                .or_insert_with(Vec::new)
+                .push((t, dt));
+        }
+        delays_to_stop
+    }
+
+    // Every arrival not yet compacted of a bus on this route, with the time since its previous
+    // stop
+    fn bus_delays(&self, now: Time, r: BusRouteID) -> Vec<(Time, BusStopID, Duration)> {
         let mut per_bus: BTreeMap<CarID, Vec<(Time, BusStopID)>> = BTreeMap::new();
+        for (car, (t, route, stop)) in &self.last_compacted_bus_arrival {
+            if *route == r {
+                per_bus.insert(*car, vec![(*t, *stop)]);
+            }
+        }
         for (t, car, route, stop) in &self.bus_arrivals {
             if *t > now {
                 break;
@@ -307,16 +1119,35 @@ impl Analytics {
                     .push((*t, *stop));
             }
         }
-        let mut delays_to_stop: BTreeMap<BusStopID, Vec<(Time, Duration)>> = BTreeMap::new();
+        let mut results = Vec::new();
         for events in per_bus.values() {
             for pair in events.windows(2) {
-                delays_to_stop
-                    .entry(pair[1].1)
-                    .or_insert_with(Vec::new)
-                    .push((pair[1].0, pair[1].0 - pair[0].0));
+                results.push((pair[1].0, pair[1].1, pair[1].0 - pair[0].0));
             }
         }
-        delays_to_stop
+        results
+    }
+
+    // Compacted arrivals only remember the last one during each bucket, so a time partway through
+    // a compacted bucket might find an earlier arrival.
+    pub fn last_bus_arrival(&self, now: Time, r: BusRouteID, stop: BusStopID) -> Option<Time> {
+        let raw = self
+            .bus_arrivals
+            .iter()
+            .rev()
+            .find(|(t, _, route, s)| *t <= now && *route == r && *s == stop);
+        if let Some((t, _, _, _)) = raw {
+            return Some(*t);
+        }
+        let mut last = None;
+        self.for_each_compacted(now, |c| {
+            if let Some(times) = c.last_bus_arrivals.get(&(r, stop)) {
+                if let Some(t) = times.iter().rev().find(|t| **t <= now) {
+                    last = Some(*t);
+                }
+            }
+        });
+        last
     }
 
     // At some moment in time, what's the distribution of passengers waiting for a route like?
@@ -338,13 +1169,13 @@ impl Analytics {
             }
         }
 
-        for (t, _, route, stop) in &self.bus_arrivals {
+        for (t, stop, route) in &self.bus_boardings {
             if *t > now {
                 break;
             }
             if *route == r {
-                if let Some(ref mut times) = waiting_per_stop.get_mut(stop) {
-                    times.retain(|time| *time > *t);
+                if let Some(times) = waiting_per_stop.get_mut(stop) {
+                    board_bus(times, *t);
                 }
             }
         }
@@ -365,6 +1196,27 @@ impl Analytics {
             .collect()
     }
 
+    // Up to some moment in time, how many people couldn't board a full bus, per stop?
+    pub fn denied_boardings(&self, now: Time, r: BusRouteID) -> Counter<BusStopID> {
+        let mut per_stop = Counter::new();
+        self.for_each_compacted(now, |c| {
+            for ((route, start, stop), count) in &c.denied_boardings {
+                if *route == r && *start <= now {
+                    per_stop.add(*stop, *count);
+                }
+            }
+        });
+        for (t, _, route, stop) in &self.denied_boardings {
+            if *t > now {
+                break;
+            }
+            if *route == r {
+                per_stop.inc(*stop);
+            }
+        }
+        per_stop
+    }
+
     pub fn get_trip_phases(&self, trip: TripID, map: &Map) -> Vec<TripPhase> {
         let mut phases: Vec<TripPhase> = Vec::new();
         for (t, id, maybe_req, phase_type) in &self.trip_log {
@@ -541,6 +1393,106 @@ pub struct TripPhase {
     pub phase_type: TripPhaseType,
 }
 
+// Older intersection delays and bus activity, summarized per BUCKET_SIZE. Each list is in order.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CompactedAnalytics {
+    pub intersection_delays: BTreeMap<IntersectionID, Vec<DelayBucket>>,
+    // The time between consecutive stops
+    pub bus_arrivals: BTreeMap<BusRouteID, Vec<BusDelayBucket>>,
+    // (route, bucket start, stop) to how many people couldn't board a full bus
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub denied_boardings: BTreeMap<(BusRouteID, Time, BusStopID), usize>,
+    // The last arrival at each stop during each bucket
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub last_bus_arrivals: BTreeMap<(BusRouteID, BusStopID), Vec<Time>>,
+}
+
+impl CompactedAnalytics {
+    fn new() -> CompactedAnalytics {
+        CompactedAnalytics {
+            intersection_delays: BTreeMap::new(),
+            bus_arrivals: BTreeMap::new(),
+            denied_boardings: BTreeMap::new(),
+            last_bus_arrivals: BTreeMap::new(),
+        }
+    }
+
+    // Moves everything from buckets starting before the cutoff into a new CompactedAnalytics.
+    // The cutoff must be the start of a bucket.
+    fn split_off_before(&mut self, cutoff: Time) -> CompactedAnalytics {
+        let mut old = CompactedAnalytics::new();
+        for (i, buckets) in self.intersection_delays.iter_mut() {
+            let num_old = buckets.iter().take_while(|b| b.start < cutoff).count();
+            if num_old > 0 {
+                old.intersection_delays
+                    .insert(*i, buckets.drain(0..num_old).collect());
+            }
+        }
+        for (r, buckets) in self.bus_arrivals.iter_mut() {
+            let num_old = buckets.iter().take_while(|b| b.start < cutoff).count();
+            if num_old > 0 {
+                old.bus_arrivals
+                    .insert(*r, buckets.drain(0..num_old).collect());
+            }
+        }
+        let (before, after): (BTreeMap<_, _>, BTreeMap<_, _>) =
+            std::mem::replace(&mut self.denied_boardings, BTreeMap::new())
+                .into_iter()
+                .partition(|((_, start, _), _)| *start < cutoff);
+        old.denied_boardings = before;
+        self.denied_boardings = after;
+        for (key, times) in self.last_bus_arrivals.iter_mut() {
+            let num_old = times.iter().take_while(|t| **t < cutoff).count();
+            if num_old > 0 {
+                old.last_bus_arrivals
+                    .insert(*key, times.drain(0..num_old).collect());
+            }
+        }
+        old
+    }
+}
+
+// A summary of all delays through one intersection for one mode, over BUCKET_SIZE
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DelayBucket {
+    pub start: Time,
+    pub mode: TripMode,
+    pub count: usize,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+// A summary of the time buses on one route took to reach one stop from their previous stop, over
+// BUCKET_SIZE
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BusDelayBucket {
+    pub start: Time,
+    pub stop: BusStopID,
+    pub count: usize,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+// Somebody boarded, so they're not waiting anymore. Who exactly isn't recorded, but people board
+// in the order they started waiting. Anybody refused by a full bus keeps waiting, since they never
+// board.
+fn board_bus(waiting: &mut Vec<Time>, boarded_at: Time) {
+    if let Some(idx) = waiting.iter().position(|t| *t <= boarded_at) {
+        waiting.remove(idx);
+    }
+}
+
+fn bucket_start(t: Time) -> Time {
+    let idx = (t.inner_seconds() / BUCKET_SIZE.inner_seconds()).floor();
+    Time::START_OF_DAY + BUCKET_SIZE * idx
+}
+
 // Slightly misleading -- TripMode::Transit means buses, not pedestrians taking transit
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TimeSeriesCount<X: Ord + Clone> {
@@ -548,8 +1500,10 @@ pub struct TimeSeriesCount<X: Ord + Clone> {
     pub counts: BTreeMap<(X, TripMode, usize), usize>,
 
     // Very expensive to store, so it's optional. But useful to flag on to experiment with
-    // representations better than the hour count above.
+    // representations better than the hour count above. Only the last few hours are kept.
     pub raw: Vec<(Time, TripMode, X)>,
+    // Everything in raw before this was dropped
+    raw_starts: Time,
 }
 
 impl<X: Ord + Clone> TimeSeriesCount<X> {
@@ -557,9 +1511,17 @@ impl<X: Ord + Clone> TimeSeriesCount<X> {
         TimeSeriesCount {
             counts: BTreeMap::new(),
             raw: Vec::new(),
+            raw_starts: Time::START_OF_DAY,
         }
     }
 
+    // The hourly counts already cover everything dropped
+    fn compact(&mut self, cutoff: Time) {
+        let num_old = self.raw.iter().take_while(|(t, _, _)| *t < cutoff).count();
+        self.raw.drain(0..num_old);
+        self.raw_starts = cutoff;
+    }
+
     fn record(&mut self, time: Time, id: X, mode: TripMode) {
         // TODO Manually change flag
         if false {
@@ -593,6 +1555,19 @@ impl<X: Ord + Clone> TimeSeriesCount<X> {
         cnt
     }
 
+    // Like all_total_counts, but only over the last few hour blocks, including the current one
+    pub fn recent_counts(&self, now: Time, hours: usize) -> Counter<X> {
+        let end = now.get_hours();
+        let start = (end + 1).saturating_sub(hours);
+        let mut cnt = Counter::new();
+        for ((id, _, hour), value) in &self.counts {
+            if *hour >= start && *hour <= end {
+                cnt.add(id.clone(), *value);
+            }
+        }
+        cnt
+    }
+
     pub fn count_per_hour(&self, id: X, time: Time) -> Vec<(TripMode, Vec<(Time, usize)>)> {
         let hour = time.get_hours();
         let mut results = Vec::new();
@@ -618,9 +1593,15 @@ impl<X: Ord + Clone> TimeSeriesCount<X> {
         let mut pts_per_mode: BTreeMap<TripMode, Vec<(Time, usize)>> = BTreeMap::new();
         let mut windows_per_mode: BTreeMap<TripMode, Window> = BTreeMap::new();
         for mode in TripMode::all() {
-            pts_per_mode.insert(mode, vec![(Time::START_OF_DAY, 0)]);
+            pts_per_mode.insert(mode, Vec::new());
             windows_per_mode.insert(mode, Window::new(window_size));
         }
+        // Right after raw_starts, the window would be missing what was dropped
+        let start = if self.raw_starts == Time::START_OF_DAY {
+            Time::START_OF_DAY
+        } else {
+            (self.raw_starts + window_size).min(now)
+        };
 
         for (t, m, x) in &self.raw {
             if *x != id {
@@ -630,12 +1611,22 @@ impl<X: Ord + Clone> TimeSeriesCount<X> {
                 break;
             }
 
-            let count = windows_per_mode.get_mut(m).unwrap().add(*t);
-            pts_per_mode.get_mut(m).unwrap().push((*t, count));
+            let window = windows_per_mode.get_mut(m).unwrap();
+            let pts = pts_per_mode.get_mut(m).unwrap();
+            if *t >= start && pts.is_empty() {
+                pts.push((start, window.count(start)));
+            }
+            let count = window.add(*t);
+            if *t >= start {
+                pts.push((*t, count));
+            }
         }
 
         for (m, pts) in pts_per_mode.iter_mut() {
             let mut window = windows_per_mode.remove(m).unwrap();
+            if pts.is_empty() {
+                pts.push((start, window.count(start)));
+            }
 
             // Add a drop-off after window_size (+ a little epsilon!)
             let t = (pts.last().unwrap().0 + window_size + Duration::seconds(0.1)).min(now);
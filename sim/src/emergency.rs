@@ -0,0 +1,42 @@
+use crate::router::curb_stop;
+use geom::Distance;
+use map_model::{Building, BuildingID, Map, Position};
+
+// Somewhere between an ambulance and a fire engine
+pub(crate) const EMERGENCY_VEHICLE_LENGTH: Distance = Distance::const_meters(8.0);
+
+// Fire and ambulance stations, and hospitals
+pub fn is_emergency_station(b: &Building) -> bool {
+    b.amenities
+        .iter()
+        .any(|(_, a)| a == "fire_station" || a == "ambulance_station" || a == "hospital")
+}
+
+pub fn emergency_stations(map: &Map) -> Vec<BuildingID> {
+    map.all_buildings()
+        .iter()
+        .filter(|b| is_emergency_station(b))
+        .map(|b| b.id)
+        .collect()
+}
+
+// The station closest in a straight line, which isn't necessarily the quickest to respond
+pub fn closest_emergency_station(map: &Map, to: BuildingID) -> Option<BuildingID> {
+    let pt = map.get_b(to).polygon.center();
+    emergency_stations(map)
+        .into_iter()
+        .min_by_key(|b| map.get_b(*b).polygon.center().dist_to(pt))
+}
+
+// Where an emergency vehicle leaves from or stops at a building. It doesn't look for anywhere to
+// pull over.
+pub(crate) fn emergency_curb(b: BuildingID, map: &Map) -> Option<Position> {
+    curb_stop(
+        b,
+        map.find_driving_lane_near_building(b),
+        EMERGENCY_VEHICLE_LENGTH,
+        map,
+        |_| false,
+    )
+    .map(|(pos, _)| pos)
+}
@@ -1,9 +1,10 @@
 use crate::{
     AgentID, CarID, OffMapLocation, ParkingSpot, PedestrianID, PersonID, TripID, TripMode,
 };
-use geom::Duration;
+use geom::{Distance, Duration};
 use map_model::{
-    BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, Map, Path, PathRequest, Traversable,
+    BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, Map, Path, PathRequest, RoadID,
+    Traversable,
 };
 use serde::{Deserialize, Serialize};
 
@@ -14,9 +15,35 @@ use serde::{Deserialize, Serialize};
 pub enum Event {
     CarReachedParkingSpot(CarID, ParkingSpot),
     CarLeftParkingSpot(CarID, ParkingSpot),
+    // A driver had to circle around to find a free spot. How far did they drive, and how long did
+    // it take?
+    CruisedForParking(CarID, Distance, Duration),
+    // How many scooters were moved, and how far the van drove
+    ScootersRebalanced(usize, Distance),
+    // A car stopped along this lane to let somebody out. True if it used a pickup / drop-off zone,
+    // false if it blocked the lane.
+    PassengerDroppedOff(CarID, LaneID, bool),
+    // A truck stopped along this lane to make a delivery. True if it used a loading zone, false if
+    // it double-parked.
+    DeliveryStarted(CarID, LaneID, bool),
+    // A box truck or tractor-trailer started along some road
+    TruckEntersRoad(CarID, RoadID),
+    // A ride-hail vehicle is about to drive this far without a passenger
+    RideHailDeadhead(CarID, Distance),
+    // An emergency vehicle sent from the first building reached the second. How long did it
+    // take?
+    EmergencyResponse(CarID, BuildingID, BuildingID, Duration),
+    // Over the last reporting interval, how many vehicles reached a detector, and what fraction of
+    // the time was somebody over it?
+    DetectorReading(usize, LaneID, usize, f64),
 
     BusArrivedAtStop(CarID, BusRouteID, BusStopID),
     BusDepartedFromStop(CarID, BusRouteID, BusStopID),
+    // A bus reached its terminal and rests this long before its next run. True if it used a
+    // layover space, false if it blocked the lane.
+    BusLaidOver(CarID, BusRouteID, BusStopID, Duration, bool),
+    // The bus was full, so the pedestrian has to keep waiting
+    PassengerDeniedBoarding(PedestrianID, CarID, BusRouteID, BusStopID),
 
     PersonEntersBuilding(PersonID, BuildingID),
     PersonLeavesBuilding(PersonID, BuildingID),
@@ -26,8 +53,22 @@ pub enum Event {
     PersonLeavesRemoteBuilding(PersonID, OffMapLocation),
 
     PedReachedParkingSpot(PedestrianID, ParkingSpot),
+    // Somebody started crossing this road mid-block, away from any crosswalk
+    PedJaywalked(PedestrianID, RoadID),
+    // A driver had to brake for somebody crossing this road mid-block
+    JaywalkingConflict(PedestrianID, CarID, RoadID),
+    // A driver went through a stop sign intersection ahead of somebody waiting to use a crosswalk
+    FailedToYield(CarID, PedestrianID, IntersectionID),
 
     BikeStoppedAtSidewalk(CarID, LaneID),
+    // Somebody opened the door of a parked car just as a bike rode by in this lane. The first car
+    // is the bike.
+    DoorZoneConflict(CarID, CarID, LaneID),
+    // A driver changed lanes to get around a bike sharing their lane. The second car is the bike.
+    PassedBike(CarID, CarID, RoadID),
+    // A driver caught up to a bike sharing their lane and couldn't safely pass, so they're stuck
+    // behind it. The second car is the bike.
+    StuckBehindBike(CarID, CarID, RoadID),
 
     AgentEntersTraversable(AgentID, Traversable),
     IntersectionDelayMeasured(IntersectionID, Duration, TripMode),
@@ -69,6 +110,8 @@ pub enum TripPhaseType {
     Finished,
     DelayedStart,
     Remote,
+    WaitingForRideHail,
+    Delivering,
 }
 
 impl TripPhaseType {
@@ -84,6 +127,8 @@ impl TripPhaseType {
             TripPhaseType::Finished => "trip finished".to_string(),
             TripPhaseType::DelayedStart => "delayed by previous trip taking too long".to_string(),
             TripPhaseType::Remote => "remote trip outside the map boundaries".to_string(),
+            TripPhaseType::WaitingForRideHail => "waiting for a ride-hail pickup".to_string(),
+            TripPhaseType::Delivering => "making a delivery".to_string(),
         }
     }
 }
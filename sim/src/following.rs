@@ -0,0 +1,190 @@
+use crate::{Vehicle, VehicleType, FOLLOWING_DISTANCE};
+use geom::{Distance, Duration, Speed};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Trucks get going slowly no matter the model, in meters per second squared
+const HEAVY_VEHICLE_MAX_ACCEL: f64 = 0.6;
+
+// How vehicles follow each other. Loaded from a JSON file with --car_following, or from
+// data/system/car_following/<map>/<scenario>.json when a scenario is instantiated, like:
+//
+// {
+//   "Calibrated": {
+//     "Car": { "desired_gap": 2.0, "max_accel": 1.5, "max_decel": 3.0, "reaction_time": 1.0 },
+//     "Bus": { "desired_gap": 3.0, "max_accel": 1.0 }
+//   }
+// }
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CarFollowingModel {
+    // Vehicles keep a fixed gap and go at full speed the instant there's room to move
+    Simple,
+    // Parameters in the spirit of the Intelligent Driver Model for each vehicle type. Types (and
+    // fields) that're missing get the defaults.
+    Calibrated(BTreeMap<VehicleType, FollowingParams>),
+}
+
+impl Default for CarFollowingModel {
+    fn default() -> CarFollowingModel {
+        CarFollowingModel::Simple
+    }
+}
+
+impl CarFollowingModel {
+    pub(crate) fn params(&self, vehicle: &Vehicle) -> FollowingParams {
+        let mut params = match self {
+            CarFollowingModel::Simple => FollowingParams::simple(),
+            CarFollowingModel::Calibrated(ref per_type) => per_type
+                .get(&vehicle.vehicle_type)
+                .cloned()
+                .unwrap_or_else(FollowingParams::default),
+        };
+        if vehicle.is_heavy() {
+            params.max_accel = params.max_accel.min(HEAVY_VEHICLE_MAX_ACCEL);
+        }
+        params
+    }
+
+    // Every vehicle has to be able to speed up, slow down, and react eventually
+    pub fn validate(&self) -> Result<(), String> {
+        if let CarFollowingModel::Calibrated(ref per_type) = self {
+            for (vehicle_type, params) in per_type {
+                if params.max_accel <= 0.0
+                    || params.max_decel <= 0.0
+                    || params.reaction_time <= Duration::ZERO
+                {
+                    return Err(format!(
+                        "{:?} max_accel, max_decel, and reaction_time must be positive: {:?}",
+                        vehicle_type, params
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FollowingParams {
+    // How much room a driver leaves behind the vehicle in front of them when stopped
+    pub desired_gap: Distance,
+    // In meters per second squared
+    pub max_accel: f64,
+    // How hard drivers comfortably brake, in meters per second squared
+    pub max_decel: f64,
+    // How long it takes to start moving once there's room to
+    pub reaction_time: Duration,
+}
+
+impl Default for FollowingParams {
+    fn default() -> FollowingParams {
+        FollowingParams {
+            desired_gap: Distance::meters(2.0),
+            max_accel: 1.5,
+            max_decel: 3.0,
+            reaction_time: Duration::seconds(1.0),
+        }
+    }
+}
+
+impl FollowingParams {
+    fn simple() -> FollowingParams {
+        FollowingParams {
+            desired_gap: FOLLOWING_DISTANCE,
+            max_accel: std::f64::INFINITY,
+            max_decel: std::f64::INFINITY,
+            reaction_time: Duration::ZERO,
+        }
+    }
+
+    // How long it takes to cover some distance, going no faster than the speed. Vehicles still
+    // move from one event to the next at a constant rate, so starting from a stop just takes
+    // longer overall. That also covers the time lost slowing down to that stop, since nobody knew
+    // they'd have to stop until they got there.
+    pub(crate) fn crossing_time(&self, dist: Distance, speed: Speed, from_stop: bool) -> Duration {
+        let cruising = dist / speed;
+        if !from_stop || dist == Distance::ZERO {
+            return cruising;
+        }
+        let d = dist.inner_meters();
+        let v = speed.inner_meters_per_second();
+        let accel_dist = v * v / (2.0 * self.max_accel);
+        // Compared to cruising the whole way
+        let lost_speeding_up = if d >= accel_dist {
+            v / (2.0 * self.max_accel)
+        } else {
+            (2.0 * d / self.max_accel).sqrt() - d / v
+        };
+        let lost_slowing_down = v / (2.0 * self.max_decel);
+        cruising + self.reaction_time + Duration::seconds(lost_speeding_up + lost_slowing_down)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Duration, expected: f64) {
+        assert!(
+            (actual.inner_seconds() - expected).abs() < 0.01,
+            "got {}, expected {}s",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_crossing_time() {
+        let params = FollowingParams::default();
+        let dist = Distance::meters(100.0);
+        let speed = Speed::meters_per_second(10.0);
+
+        // Already moving
+        assert_close(params.crossing_time(dist, speed, false), 10.0);
+        assert_close(params.crossing_time(Distance::ZERO, speed, true), 0.0);
+        // The simple model doesn't lose any time starting up
+        assert_close(
+            FollowingParams::simple().crossing_time(dist, speed, true),
+            10.0,
+        );
+
+        // Reaction time, half the time to reach full speed, and half the time it took to stop
+        assert_close(
+            params.crossing_time(dist, speed, true),
+            10.0 + 1.0 + 10.0 / 3.0 + 10.0 / 6.0,
+        );
+        // Too short to ever reach full speed
+        assert_close(
+            params.crossing_time(Distance::meters(3.0), speed, true),
+            1.0 + 2.0 + 10.0 / 6.0,
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let model = |params| {
+            let mut per_type = BTreeMap::new();
+            per_type.insert(VehicleType::Car, params);
+            CarFollowingModel::Calibrated(per_type)
+        };
+        assert!(CarFollowingModel::Simple.validate().is_ok());
+        assert!(model(FollowingParams::default()).validate().is_ok());
+        for bad in vec![
+            FollowingParams {
+                max_accel: 0.0,
+                ..FollowingParams::default()
+            },
+            FollowingParams {
+                max_decel: -1.0,
+                ..FollowingParams::default()
+            },
+            FollowingParams {
+                reaction_time: Duration::ZERO,
+                ..FollowingParams::default()
+            },
+        ] {
+            assert!(model(bad).validate().is_err());
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::CarID;
+use geom::{Duration, Time};
+use map_model::{IntersectionID, TurnID};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// How often to look for gridlock
+pub(crate) const GRIDLOCK_CHECK_EVERY: Duration = Duration::const_seconds(60.0);
+// Vehicles have to be stuck at least this long to count. Anything shorter is probably just
+// somebody slow to clear an intersection.
+pub(crate) const GRIDLOCK_MIN_WAIT: Duration = Duration::const_seconds(60.0);
+
+// A set of vehicles each waiting on the next, in a cycle. Nobody can move until something breaks
+// the cycle.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gridlock {
+    pub detected_at: Time,
+    // In order; each is waiting on the next, and the last on the first
+    pub cars: Vec<CarID>,
+    // The turns each car is waiting to make (or stuck in the middle of)
+    pub turns: BTreeSet<TurnID>,
+    pub intersections: BTreeSet<IntersectionID>,
+}
+
+impl Gridlock {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} vehicles stuck in a cycle through {} intersections since {}",
+            self.cars.len(),
+            self.intersections.len(),
+            self.detected_at.ampm_tostring()
+        )
+    }
+}
+
+// Returns every cycle in the waits-for graph, each once. A car waiting on more than one other car
+// (like a turn conflicting with a few accepted turns) can be part of a few cycles; just report the
+// first found.
+pub(crate) fn find_cycles(waiting_on: &BTreeMap<CarID, BTreeSet<CarID>>) -> Vec<Vec<CarID>> {
+    let mut cycles = Vec::new();
+    let mut done: BTreeSet<CarID> = BTreeSet::new();
+    for start in waiting_on.keys() {
+        if done.contains(start) {
+            continue;
+        }
+        // Depth-first search, remembering the current path
+        let mut path: Vec<CarID> = Vec::new();
+        let mut on_path: BTreeSet<CarID> = BTreeSet::new();
+        let mut stack: Vec<(CarID, usize)> = vec![(*start, 0)];
+        while let Some((car, depth)) = stack.pop() {
+            while path.len() > depth {
+                on_path.remove(&path.pop().unwrap());
+            }
+            if on_path.contains(&car) {
+                let idx = path.iter().position(|c| *c == car).unwrap();
+                cycles.push(path[idx..].to_vec());
+                continue;
+            }
+            if done.contains(&car) {
+                continue;
+            }
+            done.insert(car);
+            path.push(car);
+            on_path.insert(car);
+            if let Some(next) = waiting_on.get(&car) {
+                for c in next {
+                    stack.push((*c, depth + 1));
+                }
+            }
+        }
+    }
+    cycles
+}
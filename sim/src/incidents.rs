@@ -0,0 +1,87 @@
+use crate::{Command, Scheduler};
+use geom::Time;
+use map_model::{LaneID, Map};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// A crash, roadwork, or anything else that closes some lanes for a while. Usually loaded from a
+// JSON file with --incidents, like:
+//
+// [
+//   { "lanes": [123, 124], "start": 28800.0, "end": 32400.0 }
+// ]
+//
+// Times are seconds since midnight. Sidewalks and parking lanes can't be blocked.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Incident {
+    pub lanes: Vec<LaneID>,
+    pub start: Time,
+    pub end: Time,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct IncidentSimState {
+    incidents: Vec<Incident>,
+    // Indices into incidents
+    active: BTreeSet<usize>,
+}
+
+impl IncidentSimState {
+    pub fn new(incidents: Vec<Incident>, scheduler: &mut Scheduler) -> IncidentSimState {
+        let mut sim = IncidentSimState {
+            incidents: Vec::new(),
+            active: BTreeSet::new(),
+        };
+        for incident in incidents {
+            sim.add(incident, Time::START_OF_DAY, scheduler);
+        }
+        sim
+    }
+
+    // Returns the ID of the new incident. If it should've already started, it starts now.
+    pub fn add(&mut self, incident: Incident, now: Time, scheduler: &mut Scheduler) -> usize {
+        let id = self.incidents.len();
+        if incident.end > now {
+            scheduler.push(incident.start.max(now), Command::StartIncident(id));
+            scheduler.push(incident.end, Command::EndIncident(id));
+        }
+        self.incidents.push(incident);
+        id
+    }
+
+    pub fn get_all(&self) -> Vec<&Incident> {
+        self.incidents.iter().collect()
+    }
+
+    // Lanes blocked by some incident happening right now
+    pub fn blocked_lanes(&self) -> BTreeSet<LaneID> {
+        self.active
+            .iter()
+            .flat_map(|id| self.incidents[*id].lanes.clone())
+            .collect()
+    }
+
+    // Returns the lanes this newly closes.
+    pub fn start(&mut self, id: usize, map: &Map) -> Vec<LaneID> {
+        let before = self.blocked_lanes();
+        self.active.insert(id);
+        self.incidents[id]
+            .lanes
+            .iter()
+            .filter(|l| !before.contains(l) && map.get_l(**l).lane_type.is_for_moving_vehicles())
+            .cloned()
+            .collect()
+    }
+
+    // Returns the lanes this reopens. Another incident might still be blocking some of them.
+    pub fn end(&mut self, id: usize, map: &Map) -> Vec<LaneID> {
+        self.active.remove(&id);
+        let after = self.blocked_lanes();
+        self.incidents[id]
+            .lanes
+            .iter()
+            .filter(|l| !after.contains(l) && map.get_l(**l).lane_type.is_for_moving_vehicles())
+            .cloned()
+            .collect()
+    }
+}
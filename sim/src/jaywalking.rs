@@ -0,0 +1,81 @@
+use crate::PedestrianID;
+use geom::{Distance, Duration};
+use map_model::{osm, Map, Path, PathStep, Position, Road, TurnType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// When pedestrians cross the middle of a road instead of walking to the corner. Usually loaded
+// from a JSON file with --jaywalking; anything missing gets the default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JaywalkingConfig {
+    // Per OSM highway type, what fraction of pedestrians always use the crosswalk
+    pub compliance: BTreeMap<String, f64>,
+    // For highway types not listed above
+    pub default_compliance: f64,
+    // Nobody in a vehicle can reach the crossing point until this long after the pedestrian is
+    // across
+    pub min_gap: Duration,
+    // After waiting this long for a gap, give up and walk to the crosswalk
+    pub max_wait: Duration,
+    // How often somebody waiting for a gap checks again
+    pub retry_every: Duration,
+}
+
+impl Default for JaywalkingConfig {
+    fn default() -> JaywalkingConfig {
+        let mut compliance = BTreeMap::new();
+        compliance.insert("motorway".to_string(), 1.0);
+        compliance.insert("trunk".to_string(), 0.95);
+        compliance.insert("primary".to_string(), 0.9);
+        compliance.insert("secondary".to_string(), 0.8);
+        compliance.insert("tertiary".to_string(), 0.6);
+        compliance.insert("residential".to_string(), 0.3);
+        compliance.insert("service".to_string(), 0.1);
+        JaywalkingConfig {
+            compliance,
+            default_compliance: 0.5,
+            min_gap: Duration::seconds(3.0),
+            max_wait: Duration::seconds(30.0),
+            retry_every: Duration::seconds(2.0),
+        }
+    }
+}
+
+impl JaywalkingConfig {
+    // Deterministic per pedestrian and road, like detour compliance, so the same person makes the
+    // same choice in every run.
+    pub(crate) fn would_jaywalk(&self, ped: PedestrianID, r: &Road) -> bool {
+        let compliance = r
+            .osm_tags
+            .get(osm::HIGHWAY)
+            .and_then(|h| self.compliance.get(h))
+            .cloned()
+            .unwrap_or(self.default_compliance);
+        (((ped.0 + r.id.0) % 100) as f64) >= compliance * 100.0
+    }
+}
+
+// Somebody standing on a sidewalk whose path goes to the next corner, crosses this same road, and
+// comes back along the other side could cut straight across instead. Where would they wind up?
+pub(crate) fn midblock_crossing(path: &Path, pos: Position, map: &Map) -> Option<Position> {
+    let steps = path.get_steps();
+    if steps.len() < 3 || steps[0].as_lane() != pos.lane() {
+        return None;
+    }
+    let t = match steps[1] {
+        PathStep::Turn(t) => t,
+        _ => return None,
+    };
+    if map.get_t(t).turn_type != TurnType::Crosswalk {
+        return None;
+    }
+    let other = match steps[2] {
+        PathStep::Lane(l) | PathStep::ContraflowLane(l) => l,
+        _ => return None,
+    };
+    if other == pos.lane() || map.get_l(other).parent != map.get_l(pos.lane()).parent {
+        return None;
+    }
+    Some(pos.equiv_pos(other, Distance::ZERO, map))
+}
@@ -0,0 +1,87 @@
+use geom::{Distance, Duration, Speed};
+use serde::{Deserialize, Serialize};
+
+// How drivers decide to change lanes partway along a road. Usually loaded from a JSON file with
+// --lane_changing; anything missing gets the default.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaneChangeConfig {
+    // Extra room a driver wants in front of and behind them in the new lane, beyond the gaps
+    // everybody already keeps, before changing lanes just to get ahead
+    pub min_gap: Distance,
+    // Whoever would wind up behind in the new lane has to be at least this far away in time,
+    // going at their current speed
+    pub min_headway: Duration,
+    // Nobody starts changing lanes this close to the end of the lane
+    pub min_room_left: Distance,
+}
+
+impl Default for LaneChangeConfig {
+    fn default() -> LaneChangeConfig {
+        LaneChangeConfig {
+            min_gap: Distance::meters(3.0),
+            min_headway: Duration::seconds(1.5),
+            min_room_left: Distance::meters(15.0),
+        }
+    }
+}
+
+impl LaneChangeConfig {
+    // Gap acceptance. The gaps are how much room is left over after both drivers keep their usual
+    // distance. When the change is mandatory, drivers squeeze into smaller gaps ahead, but still
+    // won't cut somebody off.
+    pub(crate) fn accept_gap(
+        &self,
+        gap_ahead: Option<Distance>,
+        gap_behind: Option<(Distance, Speed)>,
+        mandatory: bool,
+    ) -> bool {
+        let extra = if mandatory {
+            Distance::ZERO
+        } else {
+            self.min_gap
+        };
+        if let Some(gap) = gap_ahead {
+            if gap < extra {
+                return false;
+            }
+        }
+        if let Some((gap, speed)) = gap_behind {
+            if gap < extra.max(speed * self.min_headway) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_gap() {
+        let cfg = LaneChangeConfig::default();
+        let m = Distance::meters;
+        let speed = Speed::meters_per_second;
+
+        // An empty lane
+        assert!(cfg.accept_gap(None, None, false));
+        assert!(cfg.accept_gap(None, None, true));
+
+        // A tight gap ahead is only fine when there's no choice
+        assert!(!cfg.accept_gap(Some(m(2.0)), None, false));
+        assert!(cfg.accept_gap(Some(m(2.0)), None, true));
+        assert!(cfg.accept_gap(Some(m(3.0)), None, false));
+
+        // Somebody stopped close behind
+        assert!(!cfg.accept_gap(None, Some((m(2.0), speed(0.0))), false));
+        assert!(cfg.accept_gap(None, Some((m(2.0), speed(0.0))), true));
+
+        // Somebody fast behind needs 1.5s of room, even for a mandatory change
+        assert!(!cfg.accept_gap(None, Some((m(14.0), speed(10.0))), false));
+        assert!(!cfg.accept_gap(None, Some((m(14.0), speed(10.0))), true));
+        assert!(cfg.accept_gap(None, Some((m(16.0), speed(10.0))), true));
+        assert!(cfg.accept_gap(Some(m(5.0)), Some((m(16.0), speed(10.0))), false));
+    }
+}
@@ -1,33 +1,73 @@
 mod analytics;
+mod emergency;
 mod events;
+mod following;
+mod gridlock;
+mod incidents;
+mod jaywalking;
+mod lanechange;
 mod make;
 mod mechanics;
+mod metrics;
 mod pandemic;
 mod render;
+mod replay;
+mod reroute;
+mod ridehail;
 mod router;
 mod scheduler;
+mod scooters;
+mod script;
 mod sim;
+mod snow;
 mod transit;
 mod trips;
+mod weather;
 
 pub use self::analytics::{Analytics, TripPhase};
-pub(crate) use self::events::Event;
-pub use self::events::{AlertLocation, TripPhaseType};
+pub use self::emergency::{closest_emergency_station, emergency_stations, is_emergency_station};
+pub(crate) use self::emergency::{emergency_curb, EMERGENCY_VEHICLE_LENGTH};
+pub use self::events::{AlertLocation, Event, TripPhaseType};
+pub use self::following::{CarFollowingModel, FollowingParams};
+pub use self::gridlock::Gridlock;
+pub(crate) use self::gridlock::{find_cycles, GRIDLOCK_CHECK_EVERY, GRIDLOCK_MIN_WAIT};
+pub use self::incidents::Incident;
+pub(crate) use self::incidents::IncidentSimState;
+pub(crate) use self::jaywalking::midblock_crossing;
+pub use self::jaywalking::JaywalkingConfig;
+pub use self::lanechange::LaneChangeConfig;
 pub use self::make::{
-    BorderSpawnOverTime, IndividTrip, OffMapLocation, OriginDestination, PersonSpec, Scenario,
-    ScenarioGenerator, SimFlags, SpawnOverTime, SpawnTrip, TripSpawner, TripSpec,
+    BorderSpawnOverTime, DepartureLearning, DepartureLearningConfig, IndividTrip, LearningStats,
+    OffMapLocation, OriginDestination, PersonSpec, Scenario, ScenarioGenerator, SimFlags,
+    SpawnOverTime, SpawnTrip, TripSpawner, TripSpec,
 };
+pub use self::mechanics::Detector;
 pub(crate) use self::mechanics::{
-    DrivingSimState, IntersectionSimState, ParkingSimState, WalkingSimState,
+    DetectorSimState, DrivingSimState, IntersectionSimState, ParkingSimState, WalkingSimState,
 };
+pub use self::metrics::{CustomMetric, Measure, MetricEvent, MetricSeries};
 pub(crate) use self::pandemic::PandemicModel;
+pub use self::replay::Replay;
+pub use self::reroute::RerouteConfig;
+pub(crate) use self::reroute::Rerouter;
+pub use self::ridehail::RideHailConfig;
+pub(crate) use self::ridehail::{
+    can_reach, deadhead_dist, ridehail_curb, RideHailSimState, RideRequest,
+};
 pub(crate) use self::router::{ActionAtEnd, Router};
 pub(crate) use self::scheduler::{Command, Scheduler};
-pub use self::sim::{AgentProperties, AlertHandler, Sim, SimCallback, SimOptions};
-pub(crate) use self::transit::TransitSimState;
+pub use self::scooters::ScooterConfig;
+pub(crate) use self::scooters::ScooterSimState;
+pub use self::script::check_script;
+pub(crate) use self::script::{ScriptAction, SimScript, SCRIPT_TICK_EVERY};
+pub use self::sim::{AgentProperties, AlertHandler, Sim, SimCallback, SimOptions, Snapshot};
+pub use self::snow::SnowConditions;
+pub use self::transit::{BusCapacityConfig, LayoverConfig};
+pub(crate) use self::transit::{BusStopAction, TransitSimState};
 pub use self::trips::{Person, PersonState, TripResult};
 pub use self::trips::{TripEndpoint, TripMode};
 pub(crate) use self::trips::{TripLeg, TripManager};
+pub use self::weather::{Weather, WeatherConditions, WeatherSpell};
 pub use crate::render::{
     CarStatus, DontDrawAgents, DrawCarInput, DrawPedCrowdInput, DrawPedestrianInput, GetDrawAgents,
     PedCrowdLocation, UnzoomedAgent,
@@ -36,7 +76,7 @@ use abstutil::Cloneable;
 use geom::{Distance, Pt2D, Speed, Time};
 use map_model::{
     BuildingID, BusStopID, DirectedRoadID, IntersectionID, LaneID, Map, ParkingLotID, Path,
-    PathConstraints, PathRequest, Position,
+    PathConstraints, PathRequest, Position, Traversable,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -49,6 +89,13 @@ pub const MIN_CAR_LENGTH: Distance = Distance::const_meters(4.5);
 pub const MAX_CAR_LENGTH: Distance = Distance::const_meters(6.5);
 // Note this is more than MAX_CAR_LENGTH
 pub const BUS_LENGTH: Distance = Distance::const_meters(12.5);
+// A box truck making deliveries. These never park, so they can be longer than a parking spot.
+pub const TRUCK_LENGTH: Distance = Distance::const_meters(10.0);
+// A tractor-trailer passing through from one border to another
+pub const HEAVY_TRUCK_LENGTH: Distance = Distance::const_meters(18.0);
+// Seated and standing passengers on a standard 40-foot bus. Routes with other buses can override
+// this; see BusCapacityConfig.
+pub const DEFAULT_BUS_CAPACITY: usize = 60;
 
 // At all speeds (including at rest), cars must be at least this far apart, measured from front of
 // one car to the back of the other.
@@ -162,6 +209,51 @@ pub struct Vehicle {
     pub max_speed: Option<Speed>,
 }
 
+impl Vehicle {
+    // How fast this vehicle can go along something at some time, ignoring other agents.
+    pub fn speed_on(&self, on: Traversable, map: &Map, now: Time) -> Speed {
+        let limit = on.speed_limit_at(map, now, self.is_truck());
+        let mut speed = match self.max_speed {
+            Some(s) => s.min(limit),
+            None => limit,
+        };
+        if self.vehicle_type == VehicleType::Bike {
+            speed = (speed * bike_grade_factor(on.percent_grade(map))).min(limit);
+        }
+        if let Traversable::Lane(l) = on {
+            speed = speed * map.get_l(l).narrow_lane_speed_factor();
+            // Cyclists riding next to parked cars watch out for opening doors
+            if self.vehicle_type == VehicleType::Bike && map.get_parent(l).in_door_zone(l) {
+                speed = speed * DOOR_ZONE_BIKE_SPEED_FACTOR;
+            }
+        }
+        speed
+    }
+
+    // Buses and box trucks follow truck speed limits
+    pub fn is_truck(&self) -> bool {
+        self.vehicle_type == VehicleType::Bus || self.length > MAX_CAR_LENGTH
+    }
+
+    // Box trucks and tractor-trailers, but not buses. These're counted separately from other
+    // traffic and pull away slowly.
+    pub fn is_heavy(&self) -> bool {
+        self.vehicle_type == VehicleType::Car && self.length > MAX_CAR_LENGTH
+    }
+}
+
+const DOOR_ZONE_BIKE_SPEED_FACTOR: f64 = 0.85;
+
+// Cyclists slow down a lot going uphill and speed up a bit going downhill. On very steep hills,
+// they're barely faster than walking.
+fn bike_grade_factor(grade: f64) -> f64 {
+    if grade >= 0.0 {
+        (1.0 - 8.0 * grade).max(0.3)
+    } else {
+        (1.0 - 4.0 * grade).min(1.5)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct VehicleSpec {
     pub vehicle_type: VehicleType,
@@ -495,7 +587,7 @@ pub struct CreateCar {
     pub req: PathRequest,
     pub start_dist: Distance,
     pub maybe_parked_car: Option<ParkedCar>,
-    // None for buses
+    // None for buses, and for ride-hail vehicles without a passenger
     pub trip_and_person: Option<(TripID, PersonID)>,
 }
 
@@ -2,12 +2,17 @@ use crate::{DrivingGoal, IndividTrip, PersonID, PersonSpec, Scenario, SidewalkSp
 use abstutil::Timer;
 use geom::{Duration, Time};
 use map_model::{BuildingID, DirectedRoadID, Map, PathConstraints};
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::seq::SliceRandom;
 use rand::Rng;
 use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 
+// How long delivery trucks stop at a business
+const MIN_DELIVERY_DWELL: Duration = Duration::const_seconds(5.0 * 60.0);
+const MAX_DELIVERY_DWELL: Duration = Duration::const_seconds(20.0 * 60.0);
+
 // A way to generate Scenarios
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ScenarioGenerator {
@@ -31,6 +36,9 @@ pub struct SpawnOverTime {
     pub percent_driving: f64,
     pub percent_biking: f64,
     pub percent_use_transit: f64,
+    // Only trips between two buildings can use ride-hail
+    #[serde(default)]
+    pub percent_ridehail: f64,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -38,7 +46,13 @@ pub struct BorderSpawnOverTime {
     pub num_peds: usize,
     pub num_cars: usize,
     pub num_bikes: usize,
+    // Trucks deliver to a business, then leave the map somewhere
+    #[serde(default)]
+    pub num_trucks: usize,
     pub percent_use_transit: f64,
+    // Of the cars passing through to another border, how many are tractor-trailers instead
+    #[serde(default)]
+    pub percent_heavy_trucks: f64,
     // TODO use https://docs.rs/rand/0.5.5/rand/distributions/struct.Normal.html
     pub start_time: Time,
     pub stop_time: Time,
@@ -53,24 +67,26 @@ impl ScenarioGenerator {
         scenario.only_seed_buses = self.only_seed_buses.clone();
 
         timer.start(format!("Generating scenario {}", self.scenario_name));
+        let bldgs = BuildingChoices::new(map);
 
         for s in &self.spawn_over_time {
             timer.start_iter("SpawnOverTime each agent", s.num_agents);
             for _ in 0..s.num_agents {
                 timer.next();
-                s.spawn_agent(rng, &mut scenario, map, timer);
+                s.spawn_agent(rng, &mut scenario, &bldgs, map, timer);
             }
         }
 
         timer.start_iter("BorderSpawnOverTime", self.border_spawn_over_time.len());
         for s in &self.border_spawn_over_time {
             timer.next();
-            s.spawn_peds(rng, &mut scenario, map, timer);
+            s.spawn_peds(rng, &mut scenario, &bldgs, map, timer);
             s.spawn_vehicles(
                 s.num_cars,
                 PathConstraints::Car,
                 rng,
                 &mut scenario,
+                &bldgs,
                 map,
                 timer,
             );
@@ -79,9 +95,11 @@ impl ScenarioGenerator {
                 PathConstraints::Bike,
                 rng,
                 &mut scenario,
+                &bldgs,
                 map,
                 timer,
             );
+            s.spawn_trucks(rng, &mut scenario, &bldgs, map, timer);
         }
 
         timer.stop(format!("Generating scenario {}", self.scenario_name));
@@ -100,6 +118,7 @@ impl ScenarioGenerator {
                 percent_driving: 0.5,
                 percent_biking: 0.5,
                 percent_use_transit: 0.5,
+                percent_ridehail: 0.0,
             }],
             // If there are no sidewalks/driving lanes at a border, scenario instantiation will
             // just warn and skip them.
@@ -110,11 +129,13 @@ impl ScenarioGenerator {
                     num_peds: 10,
                     num_cars: 10,
                     num_bikes: 10,
+                    num_trucks: 0,
                     start_time: Time::START_OF_DAY,
                     stop_time: Time::START_OF_DAY + Duration::seconds(5.0),
                     start_from_border: i.some_outgoing_road(map).unwrap(),
                     goal: OriginDestination::Anywhere,
                     percent_use_transit: 0.5,
+                    percent_heavy_trucks: 0.0,
                 })
                 .collect(),
         };
@@ -127,6 +148,7 @@ impl ScenarioGenerator {
                 percent_driving: 0.5,
                 percent_biking: 0.5,
                 percent_use_transit: 0.5,
+                percent_ridehail: 0.0,
             });
         }
         s
@@ -154,6 +176,7 @@ impl ScenarioGenerator {
                 percent_driving: 0.5,
                 percent_biking: 0.5,
                 percent_use_transit: 0.5,
+                percent_ridehail: 0.0,
             }],
             border_spawn_over_time: Vec::new(),
         }
@@ -165,19 +188,40 @@ impl SpawnOverTime {
         &self,
         rng: &mut XorShiftRng,
         scenario: &mut Scenario,
+        bldgs: &BuildingChoices,
         map: &Map,
         timer: &mut Timer,
     ) {
         let depart = rand_time(rng, self.start_time, self.stop_time);
-        // Note that it's fine for agents to start/end at the same building. Later we might
-        // want a better assignment of people per household, or workers per office building.
-        let from_bldg = map.all_buildings().choose(rng).unwrap().id;
+        // Note that it's fine for agents to start/end at the same building. Trips tend to start
+        // where people live.
+        let from_bldg = bldgs.pick_origin(rng);
         let id = PersonID(scenario.people.len());
 
+        // Check first, so scenarios without ride-hail use the RNG the same way as before
+        if self.percent_ridehail > 0.0 && rng.gen_bool(self.percent_ridehail) {
+            if let Some(DrivingGoal::ParkNear(to_bldg)) =
+                self.goal
+                    .pick_driving_goal(PathConstraints::Car, bldgs, map, rng, timer)
+            {
+                if to_bldg != from_bldg {
+                    scenario.people.push(PersonSpec {
+                        id,
+                        orig_id: None,
+                        trips: vec![IndividTrip {
+                            depart,
+                            trip: SpawnTrip::UsingRideHail(from_bldg, to_bldg),
+                        }],
+                    });
+                    return;
+                }
+            }
+        }
+
         if rng.gen_bool(self.percent_driving) {
-            if let Some(goal) = self
-                .goal
-                .pick_driving_goal(PathConstraints::Car, map, rng, timer)
+            if let Some(goal) =
+                self.goal
+                    .pick_driving_goal(PathConstraints::Car, bldgs, map, rng, timer)
             {
                 scenario.people.push(PersonSpec {
                     id,
@@ -194,9 +238,9 @@ impl SpawnOverTime {
         let start_spot = SidewalkSpot::building(from_bldg, map);
 
         if rng.gen_bool(self.percent_biking) {
-            if let Some(goal) = self
-                .goal
-                .pick_driving_goal(PathConstraints::Bike, map, rng, timer)
+            if let Some(goal) =
+                self.goal
+                    .pick_driving_goal(PathConstraints::Bike, bldgs, map, rng, timer)
             {
                 scenario.people.push(PersonSpec {
                     id,
@@ -210,7 +254,7 @@ impl SpawnOverTime {
             }
         }
 
-        if let Some(goal) = self.goal.pick_walking_goal(map, rng, timer) {
+        if let Some(goal) = self.goal.pick_walking_goal(bldgs, map, rng, timer) {
             if start_spot == goal {
                 timer.warn("Skipping walking trip between same two buildings".to_string());
                 return;
@@ -254,6 +298,7 @@ impl BorderSpawnOverTime {
         &self,
         rng: &mut XorShiftRng,
         scenario: &mut Scenario,
+        bldgs: &BuildingChoices,
         map: &Map,
         timer: &mut Timer,
     ) {
@@ -276,7 +321,7 @@ impl BorderSpawnOverTime {
         for _ in 0..self.num_peds {
             let depart = rand_time(rng, self.start_time, self.stop_time);
             let id = PersonID(scenario.people.len());
-            if let Some(goal) = self.goal.pick_walking_goal(map, rng, timer) {
+            if let Some(goal) = self.goal.pick_walking_goal(bldgs, map, rng, timer) {
                 if rng.gen_bool(self.percent_use_transit) {
                     // TODO This throws away some work. It also sequentially does expensive
                     // work right here.
@@ -319,23 +364,86 @@ impl BorderSpawnOverTime {
         constraints: PathConstraints,
         rng: &mut XorShiftRng,
         scenario: &mut Scenario,
+        bldgs: &BuildingChoices,
         map: &Map,
         timer: &mut Timer,
     ) {
         for _ in 0..num {
             let depart = rand_time(rng, self.start_time, self.stop_time);
-            if let Some(goal) = self.goal.pick_driving_goal(constraints, map, rng, timer) {
+            if let Some(goal) = self
+                .goal
+                .pick_driving_goal(constraints, bldgs, map, rng, timer)
+            {
+                // Only roll the dice when trucks are configured, so existing scenarios don't change
+                let heavy = constraints == PathConstraints::Car
+                    && self.percent_heavy_trucks > 0.0
+                    && match goal {
+                        DrivingGoal::Border(_, _, _) => true,
+                        DrivingGoal::ParkNear(_) => false,
+                    }
+                    && rng.gen_bool(self.percent_heavy_trucks);
+                let id = PersonID(scenario.people.len());
+                scenario.people.push(PersonSpec {
+                    id,
+                    orig_id: None,
+                    trips: vec![IndividTrip {
+                        depart,
+                        trip: if heavy {
+                            SpawnTrip::HeavyTruck {
+                                dr: self.start_from_border,
+                                goal,
+                            }
+                        } else {
+                            SpawnTrip::FromBorder {
+                                dr: self.start_from_border,
+                                goal,
+                                is_bike: constraints == PathConstraints::Bike,
+                                origin: None,
+                            }
+                        },
+                    }],
+                });
+            }
+        }
+    }
+
+    fn spawn_trucks(
+        &self,
+        rng: &mut XorShiftRng,
+        scenario: &mut Scenario,
+        bldgs: &BuildingChoices,
+        map: &Map,
+        timer: &mut Timer,
+    ) {
+        let borders = map.all_outgoing_borders();
+        for _ in 0..self.num_trucks {
+            let depart = rand_time(rng, self.start_time, self.stop_time);
+            let bldg = if let Some(b) = bldgs.pick_business(rng) {
+                b
+            } else {
+                timer.warn("No businesses for trucks to deliver to".to_string());
+                return;
+            };
+            let dwell = Duration::seconds(rng.gen_range(
+                MIN_DELIVERY_DWELL.inner_seconds(),
+                MAX_DELIVERY_DWELL.inner_seconds(),
+            ));
+            if let Some(goal) = borders
+                .choose(rng)
+                .and_then(|i| i.some_incoming_road(map))
+                .and_then(|dr| DrivingGoal::end_at_border(dr, PathConstraints::Car, None, map))
+            {
                 let id = PersonID(scenario.people.len());
                 scenario.people.push(PersonSpec {
                     id,
                     orig_id: None,
                     trips: vec![IndividTrip {
                         depart,
-                        trip: SpawnTrip::FromBorder {
+                        trip: SpawnTrip::Delivery {
                             dr: self.start_from_border,
+                            bldg,
+                            dwell,
                             goal,
-                            is_bike: constraints == PathConstraints::Bike,
-                            origin: None,
                         },
                     }],
                 });
@@ -355,14 +463,13 @@ impl OriginDestination {
     fn pick_driving_goal(
         &self,
         constraints: PathConstraints,
+        bldgs: &BuildingChoices,
         map: &Map,
         rng: &mut XorShiftRng,
         timer: &mut Timer,
     ) -> Option<DrivingGoal> {
         match self {
-            OriginDestination::Anywhere => Some(DrivingGoal::ParkNear(
-                map.all_buildings().choose(rng).unwrap().id,
-            )),
+            OriginDestination::Anywhere => Some(DrivingGoal::ParkNear(bldgs.pick_destination(rng))),
             OriginDestination::GotoBldg(b) => Some(DrivingGoal::ParkNear(*b)),
             OriginDestination::EndOfRoad(dr) => {
                 let goal = DrivingGoal::end_at_border(*dr, constraints, None, map);
@@ -379,15 +486,15 @@ impl OriginDestination {
 
     fn pick_walking_goal(
         &self,
+        bldgs: &BuildingChoices,
         map: &Map,
         rng: &mut XorShiftRng,
         timer: &mut Timer,
     ) -> Option<SidewalkSpot> {
         match self {
-            OriginDestination::Anywhere => Some(SidewalkSpot::building(
-                map.all_buildings().choose(rng).unwrap().id,
-                map,
-            )),
+            OriginDestination::Anywhere => {
+                Some(SidewalkSpot::building(bldgs.pick_destination(rng), map))
+            }
             OriginDestination::EndOfRoad(dr) => {
                 let goal = SidewalkSpot::end_at_border(dr.dst_i(map), None, map);
                 if goal.is_none() {
@@ -400,6 +507,56 @@ impl OriginDestination {
     }
 }
 
+// Buildings weighted by their estimated Occupancy. Every building has some chance of being picked.
+// None if the map has no buildings.
+struct BuildingChoices {
+    origins: Option<WeightedIndex<usize>>,
+    destinations: Option<WeightedIndex<usize>>,
+    // Only buildings with jobs. None if there aren't any.
+    businesses: Option<WeightedIndex<usize>>,
+}
+
+impl BuildingChoices {
+    fn new(map: &Map) -> BuildingChoices {
+        let mut origins = Vec::new();
+        let mut destinations = Vec::new();
+        let mut businesses = Vec::new();
+        for b in map.all_buildings() {
+            origins.push(b.occupancy.residents + 1);
+            // People mostly go to work, shop, or eat, but sometimes visit other homes
+            destinations.push(b.occupancy.jobs + b.occupancy.residents / 4 + 1);
+            businesses.push(b.occupancy.jobs);
+        }
+        BuildingChoices {
+            origins: WeightedIndex::new(origins).ok(),
+            destinations: WeightedIndex::new(destinations).ok(),
+            businesses: WeightedIndex::new(businesses).ok(),
+        }
+    }
+
+    fn pick_origin(&self, rng: &mut XorShiftRng) -> BuildingID {
+        BuildingID(
+            self.origins
+                .as_ref()
+                .expect("map has no buildings")
+                .sample(rng),
+        )
+    }
+
+    fn pick_destination(&self, rng: &mut XorShiftRng) -> BuildingID {
+        BuildingID(
+            self.destinations
+                .as_ref()
+                .expect("map has no buildings")
+                .sample(rng),
+        )
+    }
+
+    fn pick_business(&self, rng: &mut XorShiftRng) -> Option<BuildingID> {
+        self.businesses.as_ref().map(|w| BuildingID(w.sample(rng)))
+    }
+}
+
 fn rand_time(rng: &mut XorShiftRng, low: Time, high: Time) -> Time {
     assert!(high > low);
     Time::START_OF_DAY + Duration::seconds(rng.gen_range(low.inner_seconds(), high.inner_seconds()))
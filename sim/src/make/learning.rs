@@ -0,0 +1,193 @@
+use crate::{PersonID, Scenario, Sim};
+use geom::{Duration, Time};
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// How people change when they leave over many simulated days, after getting stuck in congestion.
+// Usually loaded from a JSON file; anything missing gets the default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DepartureLearningConfig {
+    // Only trips that spent longer than this waiting in traffic consider leaving at a different
+    // time
+    pub min_delay: Duration,
+    // Each day, only this fraction of the delayed trips actually change. Letting everybody react
+    // at once just moves the peak around.
+    pub adjust_fraction: f64,
+    // Delayed trips move this fraction of the time they were stuck, randomly earlier or later.
+    // Trips that weren't delayed drift back towards their original departure by the same fraction.
+    pub step: f64,
+    // Nobody moves further than this from their original departure
+    pub max_shift: Duration,
+    // Consider the departures settled once fewer than this fraction of trips change in a day
+    pub converged_fraction: f64,
+}
+
+impl Default for DepartureLearningConfig {
+    fn default() -> DepartureLearningConfig {
+        DepartureLearningConfig {
+            min_delay: Duration::minutes(2),
+            adjust_fraction: 0.3,
+            step: 0.5,
+            max_shift: Duration::minutes(60),
+            converged_fraction: 0.01,
+        }
+    }
+}
+
+// How one day of departure learning went
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LearningStats {
+    // Starting at 1
+    pub iteration: usize,
+    pub trips_finished: usize,
+    pub trips_unfinished: usize,
+    // Summed over finished trips
+    pub total_trip_time: Duration,
+    pub total_delay: Duration,
+    // How many departures changed for the next day, and by how much on average
+    pub trips_shifted: usize,
+    pub avg_shift: Duration,
+    // The fraction of trips that changed is under DepartureLearningConfig::converged_fraction
+    pub converged: bool,
+}
+
+impl LearningStats {
+    pub fn describe(&self) -> String {
+        let finished = self.trips_finished.max(1) as f64;
+        format!(
+            "Day {}: {} trips finished, {} unfinished, average trip {}, average delay {}, {} \
+             departures shifted by {} on average{}",
+            self.iteration,
+            abstutil::prettyprint_usize(self.trips_finished),
+            abstutil::prettyprint_usize(self.trips_unfinished),
+            self.total_trip_time / finished,
+            self.total_delay / finished,
+            abstutil::prettyprint_usize(self.trips_shifted),
+            self.avg_shift,
+            if self.converged { " (converged)" } else { "" }
+        )
+    }
+}
+
+// Day-to-day learning: simulate a day, then nudge each person's departure times away from the
+// congestion they ran into, and repeat until hardly anybody wants to change.
+pub struct DepartureLearning {
+    config: DepartureLearningConfig,
+    // Where each trip originally departed, by person and index into their schedule
+    preferred: BTreeMap<(PersonID, usize), Time>,
+    iteration: usize,
+}
+
+impl DepartureLearning {
+    pub fn new(config: DepartureLearningConfig, scenario: &Scenario) -> DepartureLearning {
+        let mut preferred = BTreeMap::new();
+        for person in &scenario.people {
+            for (idx, trip) in person.trips.iter().enumerate() {
+                preferred.insert((person.id, idx), trip.depart);
+            }
+        }
+        DepartureLearning {
+            config,
+            preferred,
+            iteration: 0,
+        }
+    }
+
+    // The sim has to have finished running the scenario as it is now. Changes departures in the
+    // scenario for the next day.
+    pub fn adjust(
+        &mut self,
+        scenario: &mut Scenario,
+        sim: &Sim,
+        rng: &mut XorShiftRng,
+    ) -> LearningStats {
+        self.iteration += 1;
+        let mut stats = LearningStats {
+            iteration: self.iteration,
+            trips_finished: 0,
+            trips_unfinished: 0,
+            total_trip_time: Duration::ZERO,
+            total_delay: Duration::ZERO,
+            trips_shifted: 0,
+            avg_shift: Duration::ZERO,
+            converged: false,
+        };
+        let mut total_trips = 0;
+        let mut total_shift = Duration::ZERO;
+
+        for spec in &mut scenario.people {
+            total_trips += spec.trips.len();
+            let person = match sim.lookup_person(spec.id) {
+                Some(p) => p,
+                None => {
+                    continue;
+                }
+            };
+            // If some trips couldn't be scheduled, there's no way to match the rest up
+            if person.trips.len() != spec.trips.len() {
+                continue;
+            }
+
+            let mut prev_depart: Option<Time> = None;
+            for (idx, (trip, id)) in spec.trips.iter_mut().zip(person.trips.iter()).enumerate() {
+                let preferred = self.preferred[&(spec.id, idx)];
+                let mut depart = trip.depart;
+
+                if let Some((total, delay)) = sim.finished_trip_time(*id) {
+                    stats.trips_finished += 1;
+                    stats.total_trip_time += total;
+                    stats.total_delay += delay;
+
+                    if delay > self.config.min_delay {
+                        if rng.gen_bool(self.config.adjust_fraction) {
+                            let dt = delay * self.config.step;
+                            depart = if rng.gen_bool(0.5) {
+                                depart.clamped_sub(dt)
+                            } else {
+                                depart + dt
+                            };
+                        }
+                    } else if depart != preferred {
+                        let dt = (preferred - depart) * self.config.step;
+                        depart = if dt < Duration::ZERO {
+                            depart.clamped_sub(Duration::ZERO - dt)
+                        } else {
+                            depart + dt
+                        };
+                    }
+                } else {
+                    stats.trips_unfinished += 1;
+                }
+
+                // Stay near the original time, and keep the schedule in order
+                depart = depart
+                    .max(preferred.clamped_sub(self.config.max_shift))
+                    .min(preferred + self.config.max_shift);
+                if let Some(prev) = prev_depart {
+                    depart = depart.max(prev + Duration::seconds(1.0));
+                }
+                prev_depart = Some(depart);
+
+                if depart != trip.depart {
+                    stats.trips_shifted += 1;
+                    total_shift += if depart > trip.depart {
+                        depart - trip.depart
+                    } else {
+                        trip.depart - depart
+                    };
+                    trip.depart = depart;
+                }
+            }
+        }
+
+        if stats.trips_shifted > 0 {
+            stats.avg_shift = total_shift / (stats.trips_shifted as f64);
+        }
+        stats.converged =
+            (stats.trips_shifted as f64) < self.config.converged_fraction * (total_trips as f64);
+        stats
+    }
+}
@@ -1,6 +1,11 @@
-use crate::{AlertHandler, Scenario, Sim, SimOptions};
-use abstutil::CmdArgs;
-use map_model::{Map, MapEdits};
+use crate::sim::DEFAULT_STEP_SIZE;
+use crate::{
+    check_script, AlertHandler, BusCapacityConfig, CarFollowingModel, JaywalkingConfig,
+    RideHailConfig, Scenario, ScooterConfig, Sim, SimOptions, SnowConditions,
+};
+use abstutil::{CmdArgs, Timer};
+use geom::Duration;
+use map_model::{IntersectionID, Map, MapEdits};
 use rand::SeedableRng;
 use rand_xorshift::XorShiftRng;
 
@@ -30,6 +35,17 @@ impl SimFlags {
                     .unwrap_or_else(|| "unnamed".to_string()),
                 use_freeform_policy_everywhere: args.enabled("--freeform_policy"),
                 dont_block_the_box: !args.enabled("--disable_block_the_box"),
+                block_the_box_overrides: parse_intersections(
+                    args.optional("--dont_block_the_box_at"),
+                )
+                .into_iter()
+                .map(|i| (i, true))
+                .chain(
+                    parse_intersections(args.optional("--allow_block_the_box_at"))
+                        .into_iter()
+                        .map(|i| (i, false)),
+                )
+                .collect(),
                 recalc_lanechanging: !args.enabled("--disable_recalc_lc"),
                 break_turn_conflict_cycles: !args.enabled("--disable_break_turn_conflict_cycles"),
                 enable_pandemic_model: if args.enabled("--pandemic") {
@@ -47,6 +63,130 @@ impl SimFlags {
                     })
                     .unwrap_or(AlertHandler::Print),
                 pathfinding_upfront: args.enabled("--pathfinding_upfront"),
+                step_size: args
+                    .optional("--step_size")
+                    .map(|x| {
+                        SimOptions::parse_step_size(&x)
+                            .unwrap_or_else(|err| panic!("Bad --step_size={}: {}", x, err))
+                    })
+                    .unwrap_or(DEFAULT_STEP_SIZE),
+                custom_metrics: args
+                    .optional("--metrics")
+                    .map(|path| abstutil::read_json(path, &mut Timer::throwaway()))
+                    .unwrap_or_else(Vec::new),
+                scooters: args.optional("--scooters").map(|path| {
+                    let cfg: ScooterConfig = abstutil::read_json(path, &mut Timer::throwaway());
+                    if cfg.rebalance_every <= Duration::ZERO {
+                        panic!(
+                            "--scooters rebalance_every must be positive, but got {:?}",
+                            cfg
+                        );
+                    }
+                    cfg
+                }),
+                drop_off_share: args
+                    .optional_parse("--drop_offs", |s| s.parse())
+                    .unwrap_or(0.0),
+                ridehail: args.optional("--ridehail").map(|path| {
+                    let cfg: RideHailConfig = abstutil::read_json(path, &mut Timer::throwaway());
+                    if cfg.reposition_every <= Duration::ZERO {
+                        panic!(
+                            "--ridehail reposition_every must be positive, but got {:?}",
+                            cfg
+                        );
+                    }
+                    cfg
+                }),
+                snow: args.optional("--snow").map(|path| {
+                    let snow: SnowConditions = abstutil::read_json(path, &mut Timer::throwaway());
+                    if snow.speed_factor <= 0.0 || snow.capacity_factor <= 0.0 {
+                        panic!("--snow factors must be positive, but got {:?}", snow);
+                    }
+                    snow
+                }),
+                reroute: args
+                    .optional("--reroute")
+                    .map(|path| abstutil::read_json(path, &mut Timer::throwaway())),
+                detour_compliance: args
+                    .optional_parse("--detour_compliance", |s| s.parse())
+                    .unwrap_or(0.0),
+                lane_changing: args
+                    .optional("--lane_changing")
+                    .map(|path| abstutil::read_json(path, &mut Timer::throwaway())),
+                car_following: args
+                    .optional("--car_following")
+                    .map(|path| {
+                        let model: CarFollowingModel =
+                            abstutil::read_json(path, &mut Timer::throwaway());
+                        if let Err(err) = model.validate() {
+                            panic!("--car_following: {}", err);
+                        }
+                        model
+                    })
+                    .unwrap_or_default(),
+                detectors: args
+                    .optional("--detectors")
+                    .map(|path| abstutil::read_json(path, &mut Timer::throwaway()))
+                    .unwrap_or_else(Vec::new),
+                incidents: args
+                    .optional("--incidents")
+                    .map(|path| abstutil::read_json(path, &mut Timer::throwaway()))
+                    .unwrap_or_else(Vec::new),
+                wheelchair_share: args
+                    .optional_parse("--wheelchair_share", |s| s.parse())
+                    .unwrap_or(0.0),
+                jaywalking: args.optional("--jaywalking").map(|path| {
+                    let cfg: JaywalkingConfig = abstutil::read_json(path, &mut Timer::throwaway());
+                    if cfg.retry_every <= Duration::ZERO {
+                        panic!(
+                            "--jaywalking retry_every must be positive, but got {:?}",
+                            cfg
+                        );
+                    }
+                    cfg
+                }),
+                detect_gridlock: !args.enabled("--disable_gridlock_detection"),
+                record_replay: args.optional_parse("--record_replay", Duration::parse).map(
+                    |every| {
+                        if every <= Duration::ZERO {
+                            panic!("--record_replay={} must be positive", every);
+                        }
+                        every
+                    },
+                ),
+                rewind_history: args.optional_parse("--rewind_history", Duration::parse),
+                crosswalk_yield: args.optional_parse("--crosswalk_yield", |s| s.parse()).map(
+                    |compliance: f64| {
+                        if compliance < 0.0 || compliance > 1.0 {
+                            panic!("--crosswalk_yield={} must be between 0 and 1", compliance);
+                        }
+                        compliance
+                    },
+                ),
+                layovers: args
+                    .optional("--layovers")
+                    .map(|path| abstutil::read_json(path, &mut Timer::throwaway())),
+                bus_capacity: args
+                    .optional("--bus_capacity")
+                    .map(|path| {
+                        let config: BusCapacityConfig =
+                            abstutil::read_json(path, &mut Timer::throwaway());
+                        if config.default == 0 || config.per_route.values().any(|c| *c == 0) {
+                            panic!("--bus_capacity can't have a route with 0 capacity");
+                        }
+                        config
+                    })
+                    .unwrap_or_default(),
+                script: args.optional("--script").map(|path| {
+                    if let Err(err) = check_script(&path) {
+                        panic!("Bad --script: {}", err);
+                    }
+                    (path, XorShiftRng::from_seed([rng_seed; 16]))
+                }),
+                weather: args
+                    .optional("--weather")
+                    .map(|path| abstutil::read_json(path, &mut Timer::throwaway())),
+                page_analytics: args.optional("--page_analytics"),
             },
         }
     }
@@ -86,6 +226,20 @@ impl SimFlags {
             }
             sim.restore_paths(&map, timer);
 
+            (map, sim, rng)
+        } else if self.load.starts_with("../data/player/checkpoints/") {
+            timer.note(format!("Resuming from checkpoint {}", self.load));
+
+            let sim = Sim::load_checkpoint(self.load.clone(), timer)
+                .unwrap_or_else(|err| panic!("Couldn't load checkpoint {}: {}", self.load, err));
+
+            let mut map = Map::new(abstutil::path_map(&sim.map_name), timer);
+            if sim.edits_name != "untitled edits" {
+                map.apply_edits(MapEdits::load(&map, &sim.edits_name, timer).unwrap(), timer);
+                map.recalculate_pathfinding_after_edits(timer);
+            }
+            // Unlike savestates, the paths are all still there
+
             (map, sim, rng)
         } else if self.load.starts_with("../data/system/scenarios/") {
             timer.note(format!(
@@ -122,3 +276,18 @@ impl SimFlags {
         }
     }
 }
+
+// A comma-separated list of intersection IDs
+fn parse_intersections(arg: Option<String>) -> Vec<IntersectionID> {
+    arg.map(|list| {
+        list.split(',')
+            .map(|x| {
+                IntersectionID(
+                    x.parse()
+                        .unwrap_or_else(|_| panic!("Bad intersection ID {}", x)),
+                )
+            })
+            .collect()
+    })
+    .unwrap_or_else(Vec::new)
+}
@@ -1,4 +1,5 @@
 mod generator;
+mod learning;
 mod load;
 mod scenario;
 mod spawner;
@@ -6,6 +7,7 @@ mod spawner;
 pub use self::generator::{
     BorderSpawnOverTime, OriginDestination, ScenarioGenerator, SpawnOverTime,
 };
+pub use self::learning::{DepartureLearning, DepartureLearningConfig, LearningStats};
 pub use self::load::SimFlags;
 pub use self::scenario::{IndividTrip, OffMapLocation, PersonSpec, Scenario, SpawnTrip};
 pub use self::spawner::{TripSpawner, TripSpec};
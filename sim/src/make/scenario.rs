@@ -1,7 +1,7 @@
 use crate::{
-    CarID, DrivingGoal, OrigPersonID, ParkingSpot, PersonID, SidewalkPOI, SidewalkSpot, Sim,
-    TripEndpoint, TripMode, TripSpec, Vehicle, VehicleSpec, VehicleType, BIKE_LENGTH,
-    MAX_CAR_LENGTH, MIN_CAR_LENGTH,
+    CarFollowingModel, CarID, DrivingGoal, OrigPersonID, ParkingSpot, PersonID, SidewalkPOI,
+    SidewalkSpot, Sim, TripEndpoint, TripMode, TripSpec, Vehicle, VehicleSpec, VehicleType,
+    BIKE_LENGTH, HEAVY_TRUCK_LENGTH, MAX_CAR_LENGTH, MIN_CAR_LENGTH, TRUCK_LENGTH,
 };
 use abstutil::{prettyprint_usize, Counter, Timer};
 use geom::{Distance, Duration, LonLat, Speed, Time};
@@ -58,6 +58,20 @@ pub enum SpawnTrip {
     UsingBike(SidewalkSpot, DrivingGoal),
     JustWalking(SidewalkSpot, SidewalkSpot),
     UsingTransit(SidewalkSpot, SidewalkSpot, BusRouteID, BusStopID, BusStopID),
+    // Between two buildings, in a ride-hail vehicle
+    UsingRideHail(BuildingID, BuildingID),
+    // A truck enters the map from a border, stops at a building, then leaves
+    Delivery {
+        dr: DirectedRoadID,
+        bldg: BuildingID,
+        dwell: Duration,
+        goal: DrivingGoal,
+    },
+    // A tractor-trailer passing through, from one border to another
+    HeavyTruck {
+        dr: DirectedRoadID,
+        goal: DrivingGoal,
+    },
     // Completely off-map trip. Don't really simulate much of it.
     Remote {
         from: OffMapLocation,
@@ -78,6 +92,26 @@ impl Scenario {
     pub fn instantiate(&self, sim: &mut Sim, map: &Map, rng: &mut XorShiftRng, timer: &mut Timer) {
         sim.set_name(self.scenario_name.clone());
 
+        if sim.get_weather().spells.is_empty() {
+            let path = abstutil::path_weather(&self.map_name, &self.scenario_name);
+            if abstutil::file_exists(path.clone()) {
+                sim.set_weather(abstutil::read_json(path, timer));
+            }
+        }
+        let weather = sim.get_weather().clone();
+
+        // Like the weather, --car_following wins over the scenario's own model
+        if *sim.get_car_following() == CarFollowingModel::Simple {
+            let path = abstutil::path_car_following(&self.map_name, &self.scenario_name);
+            if abstutil::file_exists(path.clone()) {
+                let model: CarFollowingModel = abstutil::read_json(path.clone(), timer);
+                if let Err(err) = model.validate() {
+                    panic!("{}: {}", path, err);
+                }
+                sim.set_car_following(model);
+            }
+        }
+
         timer.start(format!("Instantiating {}", self.scenario_name));
 
         if let Some(ref routes) = self.only_seed_buses {
@@ -98,6 +132,7 @@ impl Scenario {
         let mut parked_cars: Vec<(Vehicle, BuildingID)> = Vec::new();
         for p in &self.people {
             timer.next();
+            let p = &weather.adjust_schedule(p, map);
 
             if let Err(err) = p.check_schedule(map) {
                 panic!("{}", err);
@@ -161,6 +196,22 @@ impl Scenario {
         }
     }
 
+    pub fn truck() -> VehicleSpec {
+        VehicleSpec {
+            vehicle_type: VehicleType::Car,
+            length: TRUCK_LENGTH,
+            max_speed: None,
+        }
+    }
+
+    pub fn heavy_truck() -> VehicleSpec {
+        VehicleSpec {
+            vehicle_type: VehicleType::Car,
+            length: HEAVY_TRUCK_LENGTH,
+            max_speed: None,
+        }
+    }
+
     pub fn rand_bike(rng: &mut XorShiftRng) -> VehicleSpec {
         let max_speed = Some(Scenario::rand_speed(
             rng,
@@ -446,6 +497,62 @@ impl SpawnTrip {
                 trip_time,
                 mode,
             },
+            SpawnTrip::UsingRideHail(start, goal) => TripSpec::UsingRideHail { start, goal },
+            SpawnTrip::Delivery {
+                dr,
+                bldg,
+                dwell,
+                goal,
+            } => {
+                if let Some(start_pos) = dr
+                    .lanes(PathConstraints::Car, map)
+                    .into_iter()
+                    .filter(|l| map.get_l(*l).length() > TRUCK_LENGTH)
+                    .collect::<Vec<_>>()
+                    .choose(rng)
+                    .map(|l| Position::new(*l, TRUCK_LENGTH))
+                {
+                    TripSpec::Delivery {
+                        start_pos,
+                        truck: use_vehicle.unwrap(),
+                        bldg,
+                        dwell,
+                        goal,
+                    }
+                } else {
+                    TripSpec::NoRoomToSpawn {
+                        i: dr.src_i(map),
+                        goal,
+                        use_vehicle: use_vehicle.unwrap(),
+                        origin: None,
+                    }
+                }
+            }
+            SpawnTrip::HeavyTruck { dr, goal } => {
+                if let Some(start_pos) = dr
+                    .lanes(PathConstraints::Car, map)
+                    .into_iter()
+                    .filter(|l| map.get_l(*l).length() > HEAVY_TRUCK_LENGTH)
+                    .collect::<Vec<_>>()
+                    .choose(rng)
+                    .map(|l| Position::new(*l, HEAVY_TRUCK_LENGTH))
+                {
+                    TripSpec::VehicleAppearing {
+                        start_pos,
+                        goal,
+                        use_vehicle: use_vehicle.unwrap(),
+                        retry_if_no_room: true,
+                        origin: None,
+                    }
+                } else {
+                    TripSpec::NoRoomToSpawn {
+                        i: dr.src_i(map),
+                        goal,
+                        use_vehicle: use_vehicle.unwrap(),
+                        origin: None,
+                    }
+                }
+            }
         }
     }
 
@@ -457,7 +564,12 @@ impl SpawnTrip {
             SpawnTrip::FromBorder { dr, ref origin, .. } => {
                 TripEndpoint::Border(dr.src_i(map), origin.clone())
             }
-            SpawnTrip::UsingParkedCar(b, _) => TripEndpoint::Bldg(*b),
+            SpawnTrip::Delivery { dr, .. } | SpawnTrip::HeavyTruck { dr, .. } => {
+                TripEndpoint::Border(dr.src_i(map), None)
+            }
+            SpawnTrip::UsingParkedCar(b, _) | SpawnTrip::UsingRideHail(b, _) => {
+                TripEndpoint::Bldg(*b)
+            }
             SpawnTrip::UsingBike(ref spot, _)
             | SpawnTrip::JustWalking(ref spot, _)
             | SpawnTrip::UsingTransit(ref spot, _, _, _, _) => match spot.connection {
@@ -480,7 +592,9 @@ impl SpawnTrip {
             SpawnTrip::VehicleAppearing { ref goal, .. }
             | SpawnTrip::FromBorder { ref goal, .. }
             | SpawnTrip::UsingParkedCar(_, ref goal)
-            | SpawnTrip::UsingBike(_, ref goal) => match goal {
+            | SpawnTrip::UsingBike(_, ref goal)
+            | SpawnTrip::Delivery { ref goal, .. }
+            | SpawnTrip::HeavyTruck { ref goal, .. } => match goal {
                 DrivingGoal::ParkNear(b) => TripEndpoint::Bldg(*b),
                 DrivingGoal::Border(i, _, ref loc) => TripEndpoint::Border(*i, loc.clone()),
             },
@@ -491,6 +605,7 @@ impl SpawnTrip {
                     _ => unreachable!(),
                 }
             }
+            SpawnTrip::UsingRideHail(_, b) => TripEndpoint::Bldg(*b),
             // Pick an arbitrary border
             SpawnTrip::Remote { ref to, .. } => {
                 TripEndpoint::Border(map.all_incoming_borders()[0].id, Some(to.clone()))
@@ -683,6 +798,17 @@ impl PersonSpec {
                 }
                 SpawnTrip::JustWalking(_, _) | SpawnTrip::UsingTransit(_, _, _, _, _) => None,
                 SpawnTrip::Remote { .. } => None,
+                SpawnTrip::UsingRideHail(_, _) => None,
+                SpawnTrip::Delivery { .. } => {
+                    // Every delivery uses its own truck, which disappears at the end
+                    vehicle_specs.push(Scenario::truck());
+                    Some(vehicle_specs.len() - 1)
+                }
+                SpawnTrip::HeavyTruck { .. } => {
+                    // Heavy trucks just pass through and never park, so they aren't reused
+                    vehicle_specs.push(Scenario::heavy_truck());
+                    Some(vehicle_specs.len() - 1)
+                }
             };
             vehicle_foreach_trip.push(use_for_trip);
         }
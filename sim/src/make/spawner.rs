@@ -1,6 +1,8 @@
+use crate::router::delivery_stop;
 use crate::{
-    CarID, Command, DrivingGoal, OffMapLocation, Person, PersonID, Scheduler, SidewalkSpot,
-    TripEndpoint, TripLeg, TripManager, TripMode, VehicleType, BIKE_LENGTH, MAX_CAR_LENGTH,
+    can_reach, CarID, Command, DrivingGoal, OffMapLocation, Person, PersonID, Scheduler,
+    SidewalkSpot, TripEndpoint, TripLeg, TripManager, TripMode, VehicleType, BIKE_LENGTH,
+    MAX_CAR_LENGTH, TRUCK_LENGTH,
 };
 use abstutil::Timer;
 use geom::{Duration, Time, EPSILON_DIST};
@@ -58,6 +60,19 @@ pub enum TripSpec {
         trip_time: Duration,
         mode: TripMode,
     },
+    // Wait for a ride-hail vehicle to come pick you up
+    UsingRideHail {
+        start: BuildingID,
+        goal: BuildingID,
+    },
+    // A truck appears, stops in front of a building for a while, then continues to the goal
+    Delivery {
+        start_pos: Position,
+        truck: CarID,
+        bldg: BuildingID,
+        dwell: Duration,
+        goal: DrivingGoal,
+    },
 }
 
 // This structure is created temporarily by a Scenario or to interactively spawn agents.
@@ -171,6 +186,28 @@ impl TripSpawner {
             }
             TripSpec::UsingTransit { .. } => {}
             TripSpec::Remote { .. } => {}
+            TripSpec::UsingRideHail { .. } => {}
+            TripSpec::Delivery {
+                start_pos, bldg, ..
+            } => {
+                if start_pos.dist_along() < TRUCK_LENGTH {
+                    panic!(
+                        "Can't spawn a truck at {}; too close to the start",
+                        start_pos.dist_along()
+                    );
+                }
+                match delivery_stop(*bldg, map) {
+                    Some((stop, _)) if can_reach(*start_pos, stop) => {}
+                    _ => {
+                        println!(
+                            "Can't deliver to {} from {}; there's nowhere to stop",
+                            bldg,
+                            start_pos.lane()
+                        );
+                        return;
+                    }
+                }
+            }
         };
 
         self.trips.push((person.id, start_time, spec, trip_start));
@@ -330,6 +367,25 @@ impl TripSpawner {
                     vec![TripLeg::Remote(to)],
                     map,
                 ),
+                TripSpec::Delivery { truck, goal, .. } => trips.new_trip(
+                    person.id,
+                    start_time,
+                    trip_start,
+                    TripMode::Drive,
+                    vec![TripLeg::Drive(truck, goal)],
+                    map,
+                ),
+                TripSpec::UsingRideHail { goal, .. } => trips.new_trip(
+                    person.id,
+                    start_time,
+                    trip_start,
+                    TripMode::Drive,
+                    vec![
+                        TripLeg::RideHail(goal),
+                        TripLeg::Walk(SidewalkSpot::building(goal, map)),
+                    ],
+                    map,
+                ),
             };
             scheduler.push(
                 start_time,
@@ -398,6 +454,15 @@ impl TripSpec {
                 constraints: PathConstraints::Pedestrian,
             }),
             TripSpec::Remote { .. } => None,
+            // Nobody knows where the vehicle will come from yet
+            TripSpec::UsingRideHail { .. } => None,
+            TripSpec::Delivery {
+                start_pos, bldg, ..
+            } => delivery_stop(*bldg, map).map(|(end, _)| PathRequest {
+                start: *start_pos,
+                end,
+                constraints: PathConstraints::Car,
+            }),
         }
     }
 }
@@ -1,12 +1,16 @@
 use crate::{
-    CarStatus, DistanceInterval, DrawCarInput, ParkingSpot, PersonID, Router, TimeInterval,
-    TransitSimState, TripID, Vehicle, VehicleType,
+    CarStatus, DistanceInterval, DrawCarInput, FollowingParams, ParkingSpot, PersonID, Router,
+    SnowConditions, TimeInterval, TransitSimState, TripID, Vehicle, VehicleType, Weather,
 };
-use geom::{Distance, Duration, PolyLine, Time};
-use map_model::{Map, Traversable};
+use geom::{Distance, Duration, PolyLine, Speed, Time};
+use map_model::{LaneID, Map, Traversable};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
+// How long a car takes to drift over into the next lane. Only affects drawing; the car belongs to
+// the new lane as soon as it starts changing.
+const TIME_TO_CHANGE_LANES: Duration = Duration::const_seconds(3.0);
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Car {
     pub vehicle: Vehicle,
@@ -21,11 +25,29 @@ pub struct Car {
     // In reverse order -- most recently left is first. The sum length of these must be >=
     // vehicle.length.
     pub last_steps: VecDeque<Traversable>,
+    // The most recent lane change partway along a road
+    pub lane_change: Option<LaneChange>,
+    // How this driver follows whoever's in front of them
+    pub following: FollowingParams,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct LaneChange {
+    pub from: LaneID,
+    pub to: LaneID,
+    pub started: Time,
 }
 
 impl Car {
     // Assumes the current head of the path is the thing to cross.
-    pub fn crossing_state(&self, start_dist: Distance, start_time: Time, map: &Map) -> CarState {
+    pub fn crossing_state(
+        &self,
+        start_dist: Distance,
+        start_time: Time,
+        map: &Map,
+        snow: Option<SnowConditions>,
+        weather: &Weather,
+    ) -> CarState {
         let dist_int = DistanceInterval::new_driving(
             start_dist,
             if self.router.last_step() {
@@ -34,7 +56,7 @@ impl Car {
                 self.router.head().length(map)
             },
         );
-        self.crossing_state_with_end_dist(dist_int, start_time, map)
+        self.crossing_state_with_end_dist(dist_int, start_time, map, snow, weather)
     }
 
     pub fn crossing_state_with_end_dist(
@@ -42,16 +64,40 @@ impl Car {
         dist_int: DistanceInterval,
         start_time: Time,
         map: &Map,
+        snow: Option<SnowConditions>,
+        weather: &Weather,
     ) -> CarState {
-        let on = self.router.head();
-        let mut speed = on.speed_limit(map);
-        if let Some(s) = self.vehicle.max_speed {
-            speed = speed.min(s);
-        }
-        let dt = (dist_int.end - dist_int.start) / speed;
+        let speed = self.speed_on(self.router.head(), map, start_time, snow, weather);
+        // Cars that had to wait take a while to get going again
+        let from_stop = match self.state {
+            CarState::Queued { blocked_since } | CarState::WaitingToAdvance { blocked_since } => {
+                blocked_since < start_time
+            }
+            CarState::Unparking(_, _, _) | CarState::Idling(_, _) => true,
+            CarState::Crossing(_, _) | CarState::Parking(_, _, _) => false,
+        };
+        let dt = self
+            .following
+            .crossing_time(dist_int.end - dist_int.start, speed, from_stop);
         CarState::Crossing(TimeInterval::new(start_time, start_time + dt), dist_int)
     }
 
+    // How fast this car can go along something in the current conditions, ignoring other agents.
+    pub fn speed_on(
+        &self,
+        on: Traversable,
+        map: &Map,
+        now: Time,
+        snow: Option<SnowConditions>,
+        weather: &Weather,
+    ) -> Speed {
+        let speed = self.vehicle.speed_on(on, map, now) * weather.driving_factor(on, map, now);
+        match snow {
+            Some(s) => s.speed_on(speed, on, map),
+            None => speed,
+        }
+    }
+
     pub fn get_draw_car(
         &self,
         front: Distance,
@@ -158,6 +204,21 @@ impl Car {
             }
             _ => raw_body,
         };
+        let body = match self.lane_change {
+            Some(ref lc)
+                if self.router.head() == Traversable::Lane(lc.to)
+                    && now < lc.started + TIME_TO_CHANGE_LANES =>
+            {
+                // Start out over the old lane and smoothly move across
+                let parent = map.get_parent(lc.to);
+                let lanes_over =
+                    parent.dir_and_offset(lc.from).1 as f64 - parent.dir_and_offset(lc.to).1 as f64;
+                let percent_left = 1.0 - (now - lc.started) / TIME_TO_CHANGE_LANES;
+                body.shift_right(map.get_l(lc.to).width * lanes_over * percent_left)
+                    .unwrap()
+            }
+            _ => body,
+        };
 
         DrawCarInput {
             id: self.vehicle.id,
@@ -0,0 +1,195 @@
+use crate::mechanics::DrivingSimState;
+use crate::{CarID, Command, Event, Scheduler};
+use geom::{Distance, Duration, Time};
+use map_model::{ControlTrafficSignal, IntersectionID, LaneID, Map, Phase, TurnID, TurnPriority};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// How far back from the stop line the default loop reaches
+const DETECTOR_LENGTH: Distance = Distance::const_meters(30.0);
+// A standard loop placed by the user
+const LOOP_LENGTH: Distance = Distance::const_meters(2.0);
+// How often placed detectors check what's over them
+const SAMPLE_RATE: Duration = Duration::const_seconds(0.5);
+// Like real detector feeds, counts and occupancy are reported over fixed intervals
+const REPORT_INTERVAL: Duration = Duration::const_seconds(30.0);
+
+// A virtual induction loop on a lane. It notices any vehicle sitting on or passing over it.
+// Detectors placed by the user are usually loaded from a JSON file with --detectors, like:
+//
+// [
+//   { "lane": 123, "dist": 80.0, "length": 2.0 },
+//   { "lane": 456, "dist": 40.0, "length": 10.0, "turns": [...] }
+// ]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Detector {
+    pub lane: LaneID,
+    // How far along the lane the front edge of the loop is
+    pub dist: Distance,
+    pub length: Distance,
+    // Per-turn detectors only notice vehicles about to make one of these turns. Empty means every
+    // vehicle on the lane.
+    #[serde(default)]
+    pub turns: BTreeSet<TurnID>,
+}
+
+impl Detector {
+    // A standard loop with its front edge this far along the lane
+    pub fn new(lane: LaneID, dist: Distance) -> Detector {
+        Detector {
+            lane,
+            dist: dist.max(LOOP_LENGTH),
+            length: LOOP_LENGTH,
+            turns: BTreeSet::new(),
+        }
+    }
+
+    // One loop at the stop line for every lane that gets to move during this phase. Actuated
+    // signals use these for any phase that placed detectors don't cover.
+    pub fn for_phase(phase: &Phase, signal: &ControlTrafficSignal, map: &Map) -> Vec<Detector> {
+        let mut lanes = BTreeSet::new();
+        for g in phase
+            .protected_groups
+            .iter()
+            .chain(phase.yield_groups.iter())
+        {
+            if g.crosswalk {
+                continue;
+            }
+            for t in &signal.turn_groups[g].members {
+                lanes.insert(t.src);
+            }
+        }
+        lanes
+            .into_iter()
+            .map(|lane| Detector {
+                lane,
+                dist: map.get_l(lane).length(),
+                length: DETECTOR_LENGTH,
+                turns: BTreeSet::new(),
+            })
+            .collect()
+    }
+
+    // Does some vehicle this detector notices get to go during the phase?
+    pub fn serves_phase(&self, phase: &Phase, signal: &ControlTrafficSignal, map: &Map) -> bool {
+        map.get_turns_from_lane(self.lane).into_iter().any(|t| {
+            (self.turns.is_empty() || self.turns.contains(&t.id))
+                && phase.get_priority_of_turn(t.id, signal) != TurnPriority::Banned
+        })
+    }
+
+    pub fn vehicles_over(&self, now: Time, driving: &DrivingSimState) -> Vec<CarID> {
+        driving
+            .vehicles_over(now, self.lane, self.dist - self.length, self.dist)
+            .into_iter()
+            .filter(|(_, turn)| {
+                self.turns.is_empty() || turn.map(|t| self.turns.contains(&t)).unwrap_or(false)
+            })
+            .map(|(car, _)| car)
+            .collect()
+    }
+
+    pub fn is_occupied(&self, now: Time, driving: &DrivingSimState) -> bool {
+        !self.vehicles_over(now, driving).is_empty()
+    }
+}
+
+// Detectors placed by the user, with the readings they're building up.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectorSimState {
+    detectors: Vec<PlacedDetector>,
+    // When the current reporting interval started
+    interval_start: Time,
+    // How many samples have been taken during the current interval
+    samples: usize,
+    events: Vec<Event>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct PlacedDetector {
+    detector: Detector,
+    // Who was over the loop at the last sample
+    over: BTreeSet<CarID>,
+    // During the current interval, how many vehicles have reached the loop, and in how many
+    // samples was somebody over it
+    count: usize,
+    occupied_samples: usize,
+}
+
+impl DetectorSimState {
+    pub fn new(detectors: Vec<Detector>, scheduler: &mut Scheduler) -> DetectorSimState {
+        let mut sim = DetectorSimState {
+            detectors: Vec::new(),
+            interval_start: Time::START_OF_DAY,
+            samples: 0,
+            events: Vec::new(),
+        };
+        for d in detectors {
+            sim.add(d, Time::START_OF_DAY, scheduler);
+        }
+        sim
+    }
+
+    // Returns the ID of the new detector, used in readings.
+    pub fn add(&mut self, detector: Detector, now: Time, scheduler: &mut Scheduler) -> usize {
+        if self.detectors.is_empty() {
+            self.interval_start = now;
+            self.samples = 0;
+            scheduler.push(now + SAMPLE_RATE, Command::SampleDetectors);
+        }
+        self.detectors.push(PlacedDetector {
+            detector,
+            over: BTreeSet::new(),
+            count: 0,
+            occupied_samples: 0,
+        });
+        self.detectors.len() - 1
+    }
+
+    pub fn get_all(&self) -> Vec<&Detector> {
+        self.detectors.iter().map(|d| &d.detector).collect()
+    }
+
+    // Placed detectors on lanes leading to this intersection
+    pub fn approaching(&self, i: IntersectionID, map: &Map) -> Vec<&Detector> {
+        self.detectors
+            .iter()
+            .map(|d| &d.detector)
+            .filter(|d| map.get_l(d.lane).dst_i == i)
+            .collect()
+    }
+
+    pub fn sample(&mut self, now: Time, driving: &DrivingSimState, scheduler: &mut Scheduler) {
+        self.samples += 1;
+        for d in &mut self.detectors {
+            let over: BTreeSet<CarID> =
+                d.detector.vehicles_over(now, driving).into_iter().collect();
+            d.count += over.difference(&d.over).count();
+            if !over.is_empty() {
+                d.occupied_samples += 1;
+            }
+            d.over = over;
+        }
+
+        if now - self.interval_start >= REPORT_INTERVAL {
+            for (id, d) in self.detectors.iter_mut().enumerate() {
+                self.events.push(Event::DetectorReading(
+                    id,
+                    d.detector.lane,
+                    d.count,
+                    (d.occupied_samples as f64) / (self.samples as f64),
+                ));
+                d.count = 0;
+                d.occupied_samples = 0;
+            }
+            self.interval_start = now;
+            self.samples = 0;
+        }
+        scheduler.push(now + SAMPLE_RATE, Command::SampleDetectors);
+    }
+
+    pub fn collect_events(&mut self) -> Vec<Event> {
+        std::mem::replace(&mut self.events, Vec::new())
+    }
+}
@@ -1,20 +1,41 @@
-use crate::mechanics::car::{Car, CarState};
+use crate::mechanics::car::{Car, CarState, LaneChange};
 use crate::mechanics::Queue;
 use crate::{
-    ActionAtEnd, AgentID, AgentProperties, CarID, Command, CreateCar, DistanceInterval,
-    DrawCarInput, Event, IntersectionSimState, ParkedCar, ParkingSimState, PersonID, Scheduler,
-    TimeInterval, TransitSimState, TripManager, TripPositions, UnzoomedAgent, Vehicle,
-    WalkingSimState, FOLLOWING_DISTANCE,
+    ActionAtEnd, AgentID, AgentProperties, AlertLocation, BusStopAction, CarFollowingModel, CarID,
+    Command, CreateCar, DistanceInterval, DrawCarInput, Event, IntersectionSimState,
+    LaneChangeConfig, ParkedCar, ParkingSimState, ParkingSpot, PedestrianID, PersonID,
+    RerouteConfig, Rerouter, Router, Scheduler, SnowConditions, TimeInterval, TransitSimState,
+    TripManager, TripPhaseType, TripPositions, UnzoomedAgent, Vehicle, VehicleType,
+    WalkingSimState, Weather,
 };
 use abstutil::{deserialize_btreemap, serialize_btreemap};
-use geom::{Distance, Duration, PolyLine, Time};
-use map_model::{LaneID, Map, Path, PathStep, Traversable};
+use geom::{Distance, Duration, PolyLine, Speed, Time};
+use map_model::{
+    follow_detour, BikeFacility, LaneID, Map, Path, PathConstraints, PathRequest, PathStep,
+    Position, RoadID, Traversable, TurnID, TurnType,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
 
 const TIME_TO_UNPARK: Duration = Duration::const_seconds(10.0);
 const TIME_TO_PARK: Duration = Duration::const_seconds(15.0);
 const TIME_TO_WAIT_AT_STOP: Duration = Duration::const_seconds(10.0);
+// Letting somebody in or out while stopped in the lane. In a pickup / drop-off zone, the car only
+// blocks the lane while pulling in and out.
+const TIME_TO_DROP_OFF: Duration = Duration::const_seconds(45.0);
+const TIME_TO_PULL_OVER: Duration = Duration::const_seconds(10.0);
+// How far drivers move towards the curb to make way for an emergency vehicle
+const PULL_OVER_SHIFT: Distance = Distance::const_meters(1.0);
+// How often somebody stuck at the end of a lane closed by an incident tries to merge out
+const TIME_TO_RETRY_MERGE: Duration = Duration::const_seconds(5.0);
+// A bike this close to somebody opening the door of a parked car has to swerve or stop
+const DOOR_ZONE_REACH: Distance = Distance::const_meters(3.0);
+// Drivers only route through a lane closed by an incident if there's no other way, hoping it
+// reopens by the time they get there.
+const BLOCKED_LANE_PENALTY: Duration = Duration::const_seconds(3600.0);
+// When predicting arrival times, stopped traffic is assumed to crawl along at least this fast,
+// since the queue will clear eventually.
+const MIN_PREDICTED_SPEED: Speed = Speed::const_meters_per_second(1.0);
 
 // TODO Do something else.
 pub(crate) const BLIND_RETRY_TO_CREEP_FORWARDS: Duration = Duration::const_seconds(0.1);
@@ -35,26 +56,55 @@ pub struct DrivingSimState {
     events: Vec<Event>,
 
     recalc_lanechanging: bool,
+    snow: Option<SnowConditions>,
+    #[serde(default)]
+    weather: Weather,
+    // None means drivers stick to their original route
+    rerouter: Option<Rerouter>,
+    // What fraction of drivers follow signed detours around closed roads
+    detour_compliance: f64,
+    // None means vehicles stay in one lane for the whole length of a road
+    lane_changing: Option<LaneChangeConfig>,
+    car_following: CarFollowingModel,
+    // Pedestrians crossing lanes mid-block: how far along the lane, when they'll be clear of it,
+    // and who
+    #[serde(default)]
+    midblock_crossings: BTreeMap<LaneID, Vec<(Distance, Time, PedestrianID)>>,
 }
 
 impl DrivingSimState {
-    pub fn new(map: &Map, recalc_lanechanging: bool) -> DrivingSimState {
+    pub fn new(
+        map: &Map,
+        recalc_lanechanging: bool,
+        snow: Option<SnowConditions>,
+        reroute: Option<RerouteConfig>,
+        detour_compliance: f64,
+        lane_changing: Option<LaneChangeConfig>,
+        car_following: CarFollowingModel,
+    ) -> DrivingSimState {
         let mut sim = DrivingSimState {
             cars: BTreeMap::new(),
             queues: BTreeMap::new(),
             events: Vec::new(),
             recalc_lanechanging,
+            snow,
+            weather: Weather::default(),
+            rerouter: reroute.map(Rerouter::new),
+            detour_compliance,
+            lane_changing,
+            car_following,
+            midblock_crossings: BTreeMap::new(),
         };
 
         for l in map.all_lanes() {
             if l.lane_type.is_for_moving_vehicles() {
-                let q = Queue::new(Traversable::Lane(l.id), map);
+                let q = Queue::new(Traversable::Lane(l.id), map, snow);
                 sim.queues.insert(q.id, q);
             }
         }
         for t in map.all_turns().values() {
             if !t.between_sidewalks() {
-                let q = Queue::new(Traversable::Turn(t.id), map);
+                let q = Queue::new(Traversable::Turn(t.id), map, snow);
                 sim.queues.insert(q.id, q);
             }
         }
@@ -62,6 +112,21 @@ impl DrivingSimState {
         sim
     }
 
+    // Vehicles already partway along something keep their old speed until they reach the next
+    // lane or turn.
+    pub fn set_weather(&mut self, weather: Weather) {
+        self.weather = weather;
+    }
+
+    // Only affects vehicles created from now on
+    pub fn set_car_following(&mut self, model: CarFollowingModel) {
+        self.car_following = model;
+    }
+
+    pub fn get_car_following(&self) -> &CarFollowingModel {
+        &self.car_following
+    }
+
     // True if it worked
     pub fn start_car_on_lane(
         &mut self,
@@ -77,9 +142,11 @@ impl DrivingSimState {
         if !intersections.nobody_headed_towards(first_lane, map.get_l(first_lane).src_i) {
             return false;
         }
+        let following = self.car_following.params(&params.vehicle);
         if let Some(idx) = self.queues[&Traversable::Lane(first_lane)].get_idx_to_insert_car(
             params.start_dist,
             params.vehicle.length,
+            following.desired_gap,
             now,
             &self.cars,
             &self.queues,
@@ -93,8 +160,17 @@ impl DrivingSimState {
                 started_at: now,
                 total_blocked_time: Duration::ZERO,
                 trip_and_person: params.trip_and_person,
+                lane_change: None,
+                following,
             };
             if let Some(p) = params.maybe_parked_car {
+                self.check_door_zone(
+                    car.vehicle.id,
+                    p.spot,
+                    Position::new(first_lane, params.start_dist),
+                    now,
+                    map,
+                );
                 car.state = CarState::Unparking(
                     params.start_dist,
                     p.spot,
@@ -105,6 +181,7 @@ impl DrivingSimState {
                 if car.router.last_step() {
                     match car.router.maybe_handle_end(
                         params.start_dist,
+                        now,
                         &car.vehicle,
                         parking,
                         map,
@@ -131,7 +208,8 @@ impl DrivingSimState {
                     }
                 }
 
-                car.state = car.crossing_state(params.start_dist, now, map);
+                car.state =
+                    car.crossing_state(params.start_dist, now, map, self.snow, &self.weather);
             }
             scheduler.push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
             {
@@ -139,7 +217,7 @@ impl DrivingSimState {
                 queue.cars.insert(idx, car.vehicle.id);
                 // Don't use try_to_reserve_entry -- it's overly conservative.
                 // get_idx_to_insert_car does a more detailed check of the current space usage.
-                queue.reserved_length += car.vehicle.length + FOLLOWING_DISTANCE;
+                queue.reserved_length += car.vehicle.length + car.following.desired_gap;
             }
             self.cars.insert(car.vehicle.id, car);
             return true;
@@ -199,13 +277,22 @@ impl DrivingSimState {
                 map,
                 parking,
                 intersections,
+                trips,
                 transit,
                 scheduler,
             );
             self.cars.insert(id, car);
+            if !need_distances {
+                self.maybe_change_lanes(id, now, map, intersections, scheduler);
+            }
         }
 
         if need_distances {
+            if let CarState::Parking(front, spot, _) = self.cars[&id].state {
+                let lane = self.cars[&id].router.head().as_lane();
+                self.check_door_zone(id, spot, Position::new(lane, front), now, map);
+            }
+
             // Do this before removing the car!
             let dists = self.queues[&self.cars[&id].router.head()].get_car_positions(
                 now,
@@ -238,6 +325,7 @@ impl DrivingSimState {
         map: &Map,
         parking: &mut ParkingSimState,
         intersections: &mut IntersectionSimState,
+        trips: &mut TripManager,
         transit: &mut TransitSimState,
         scheduler: &mut Scheduler,
     ) -> bool {
@@ -266,6 +354,7 @@ impl DrivingSimState {
                     // side effect of choosing an end_dist.
                     car.router.maybe_handle_end(
                         front,
+                        now,
                         &car.vehicle,
                         parking,
                         map,
@@ -273,14 +362,64 @@ impl DrivingSimState {
                         &mut self.events,
                     );
                 }
-                car.state = car.crossing_state(front, now, map);
+                car.state = car.crossing_state(front, now, map, self.snow, &self.weather);
                 scheduler.push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
             }
             CarState::Idling(dist, _) => {
-                car.router = transit.bus_departed_from_stop(car.vehicle.id);
-                self.events
-                    .push(Event::PathAmended(car.router.get_path().clone()));
-                car.state = car.crossing_state(dist, now, map);
+                if car.vehicle.vehicle_type == VehicleType::Bus {
+                    car.router = transit.bus_departed_from_stop(car.vehicle.id);
+                    self.events
+                        .push(Event::PathAmended(car.router.get_path().clone()));
+                } else if car.router.is_ridehail() {
+                    let pos = Position::new(car.router.head().as_lane(), dist);
+                    if let Some((router, trip_and_person)) = trips.ridehail_vehicle_ready(
+                        now,
+                        car.vehicle.id,
+                        pos,
+                        map,
+                        parking,
+                        scheduler,
+                    ) {
+                        car.router = router;
+                        car.trip_and_person = trip_and_person;
+                        self.events
+                            .push(Event::PathAmended(car.router.get_path().clone()));
+                    } else {
+                        // Nothing else to do. The router will say to go idle right here, and that
+                        // needs distances to remove the car.
+                        car.state = CarState::Queued { blocked_since: now };
+                        return true;
+                    }
+                } else if car.router.is_delivering() {
+                    // Already checked that this works when the truck stopped
+                    let (router, req) = car
+                        .router
+                        .leave_after_delivery(Position::new(car.router.head().as_lane(), dist), map)
+                        .unwrap();
+                    car.router = router;
+                    self.events
+                        .push(Event::PathAmended(car.router.get_path().clone()));
+                    let (trip, person) = car.trip_and_person.unwrap();
+                    self.events.push(Event::TripPhaseStarting(
+                        trip,
+                        person,
+                        Some(req),
+                        TripPhaseType::Driving,
+                    ));
+                } else {
+                    // Done dropping somebody off. Like unparking, ignore the answer; this just
+                    // picks a parking spot, and the next loop handles anything else.
+                    car.router.maybe_handle_end(
+                        dist,
+                        now,
+                        &car.vehicle,
+                        parking,
+                        map,
+                        car.trip_and_person,
+                        &mut self.events,
+                    );
+                }
+                car.state = car.crossing_state(dist, now, map, self.snow, &self.weather);
                 scheduler.push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
 
                 // Update our follower, so they know we stopped idling.
@@ -300,9 +439,11 @@ impl DrivingSimState {
                                 follower.total_blocked_time += now - blocked_since;
                                 follower.state = follower.crossing_state(
                                     // Since the follower was Queued, this must be where they are.
-                                    dist - car.vehicle.length - FOLLOWING_DISTANCE,
+                                    dist - car.vehicle.length - follower.following.desired_gap,
                                     now,
                                     map,
+                                    self.snow,
+                                    &self.weather,
                                 );
                                 scheduler.update(
                                     follower.state.get_end_time(),
@@ -328,11 +469,17 @@ impl DrivingSimState {
                 let goto = car.router.next();
                 assert!(from != goto);
 
+                if self.queues[&from].blocked {
+                    // The only way out is to merge into another lane
+                    scheduler.push(
+                        now + TIME_TO_RETRY_MERGE,
+                        Command::UpdateCar(car.vehicle.id),
+                    );
+                    return false;
+                }
+
                 if let Traversable::Turn(t) = goto {
-                    let mut speed = goto.speed_limit(map);
-                    if let Some(s) = car.vehicle.max_speed {
-                        speed = speed.min(s);
-                    }
+                    let speed = car.speed_on(goto, map, now, self.snow, &self.weather);
                     if !intersections.maybe_start_turn(
                         AgentID::Car(car.vehicle.id),
                         t,
@@ -357,6 +504,7 @@ impl DrivingSimState {
                 // way, until laggy_head is None.
 
                 let last_step = car.router.advance(
+                    now,
                     &car.vehicle,
                     parking,
                     map,
@@ -364,12 +512,21 @@ impl DrivingSimState {
                     &mut self.events,
                 );
                 car.total_blocked_time += now - blocked_since;
-                car.state = car.crossing_state(Distance::ZERO, now, map);
+                car.state = car.crossing_state(Distance::ZERO, now, map, self.snow, &self.weather);
+                if let Traversable::Lane(l) = goto {
+                    self.yield_to_jaywalkers(car, l, now, map);
+                }
                 scheduler.push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                 self.events.push(Event::AgentEntersTraversable(
                     AgentID::Car(car.vehicle.id),
                     goto,
                 ));
+                if let Traversable::Lane(l) = goto {
+                    if car.vehicle.is_heavy() {
+                        self.events
+                            .push(Event::TruckEntersRoad(car.vehicle.id, map.get_l(l).parent));
+                    }
+                }
 
                 // Don't mark turn_finished until our back is out of the turn.
                 car.last_steps.push_front(last_step);
@@ -382,10 +539,12 @@ impl DrivingSimState {
                     car.crossing_state_with_end_dist(
                         DistanceInterval::new_driving(
                             Distance::ZERO,
-                            car.vehicle.length + FOLLOWING_DISTANCE,
+                            car.vehicle.length + car.following.desired_gap,
                         ),
                         now,
                         map,
+                        self.snow,
+                        &self.weather,
                     )
                     .get_end_time(),
                     Command::UpdateLaggyHead(car.vehicle.id),
@@ -396,12 +555,490 @@ impl DrivingSimState {
                     .unwrap()
                     .cars
                     .push_back(car.vehicle.id);
+
+                if let Traversable::Lane(l) = goto {
+                    // Bikes and buses would skew the live times for cars, and they don't follow
+                    // detours or re-plan.
+                    if car.vehicle.vehicle_type == VehicleType::Car {
+                        self.maybe_change_route(car, l, now, map);
+                    }
+                }
+
+                // Get the next traffic signal ready before arriving
+                if car.router.is_responding() {
+                    if let (Traversable::Lane(_), Some(Traversable::Turn(t))) =
+                        (goto, car.router.maybe_next())
+                    {
+                        intersections.preempt_signal(now, car.vehicle.id, t, map, scheduler);
+                    }
+                }
             }
             CarState::Parking(_, _, _) => unreachable!(),
         }
         false
     }
 
+    // Called when a car starts a new lane. Drivers go around incidents ahead, might follow the
+    // signs around a closed road, or look for a faster way when their route is backed up.
+    fn maybe_change_route(&mut self, car: &mut Car, l: LaneID, now: Time, map: &Map) {
+        if let Some(ref mut rerouter) = self.rerouter {
+            rerouter.car_entered_lane(car.vehicle.id, l, now, map);
+        }
+        if car.router.last_step() || !car.router.can_reroute() {
+            return;
+        }
+
+        // Somebody who was partway through a turn when live edits closed a lane ahead finds a new
+        // route now.
+        let constraints = car.vehicle.vehicle_type.to_constraints();
+        let mut new_path = if car.router.get_path().is_still_valid(constraints, map) {
+            None
+        } else {
+            route_around_closures(car.router.get_path(), l, constraints, map).ok()
+        };
+        if new_path.is_none() {
+            new_path = self.avoid_blocked_lanes(car, map);
+        }
+        // Each driver either always or never follows detour signs
+        if new_path.is_none() && ((car.vehicle.id.0 % 100) as f64) < self.detour_compliance * 100.0
+        {
+            new_path = follow_detour(car.router.get_path(), map);
+        }
+        if new_path.is_none() {
+            if let Some(ref mut rerouter) = self.rerouter {
+                new_path = rerouter.maybe_reroute(
+                    car.vehicle.id,
+                    car.router.get_path(),
+                    PathConstraints::Car,
+                    now,
+                    map,
+                );
+            }
+        }
+        if let Some(path) = new_path {
+            car.router.reroute(path);
+            self.events
+                .push(Event::PathAmended(car.router.get_path().clone()));
+        }
+    }
+
+    // If the rest of the route after the current lane goes through a lane closed by an incident,
+    // returns a path around it, starting from the beginning of the current lane.
+    fn avoid_blocked_lanes(&self, car: &Car, map: &Map) -> Option<Path> {
+        let path = car.router.get_path();
+        let start = match path.current_step() {
+            PathStep::Lane(l) => l,
+            _ => {
+                return None;
+            }
+        };
+        let end = match path.last_step() {
+            PathStep::Lane(l) => l,
+            _ => {
+                return None;
+            }
+        };
+        if !path.get_steps().iter().skip(1).any(|step| match step {
+            PathStep::Lane(l) => self.queues[&Traversable::Lane(*l)].blocked,
+            _ => false,
+        }) {
+            return None;
+        }
+
+        let req = PathRequest {
+            start: Position::new(start, Distance::ZERO),
+            end: Position::new(end, path.end_dist()),
+            constraints: PathConstraints::Car,
+        };
+        let new_path = map.pathfind_with_costs(req, |lane, turn| {
+            let mut cost = (lane.length() / map.get_r(lane.parent).speed_limit)
+                + (turn.geom.length() / map.get_parent(turn.id.dst).speed_limit);
+            if self.queues[&Traversable::Lane(turn.id.dst)].blocked {
+                cost += BLOCKED_LANE_PENALTY;
+            }
+            cost.inner_seconds().round() as usize
+        })?;
+        if new_path.get_steps() == path.get_steps() {
+            return None;
+        }
+        Some(new_path)
+    }
+
+    // Called when an incident starts. Drivers on their way find a route around the closed lanes,
+    // and anybody already waiting at the end of one starts trying to merge out.
+    pub fn block_lanes(
+        &mut self,
+        lanes: Vec<LaneID>,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) {
+        if lanes.is_empty() {
+            return;
+        }
+        for l in lanes {
+            let queue = self.queues.get_mut(&Traversable::Lane(l)).unwrap();
+            queue.blocked = true;
+            if let Some(id) = queue.cars.get(0) {
+                if let CarState::WaitingToAdvance { .. } = self.cars[id].state {
+                    scheduler.update(now, Command::UpdateCar(*id));
+                }
+            }
+        }
+
+        // Only cars that're partway along a lane can switch routes cleanly. Everybody else will
+        // check again when they reach their next lane.
+        let mut new_paths = Vec::new();
+        for car in self.cars.values() {
+            if car.vehicle.vehicle_type != VehicleType::Car
+                || car.router.last_step()
+                || !car.router.can_reroute()
+            {
+                continue;
+            }
+            if let (Traversable::Lane(_), CarState::Crossing(_, _)) =
+                (car.router.head(), &car.state)
+            {
+                if let Some(path) = self.avoid_blocked_lanes(car, map) {
+                    new_paths.push((car.vehicle.id, path));
+                }
+            }
+        }
+        for (id, path) in new_paths {
+            let car = self.cars.get_mut(&id).unwrap();
+            car.router.reroute(path);
+            self.events
+                .push(Event::PathAmended(car.router.get_path().clone()));
+        }
+    }
+
+    // Called after live map edits. Drivers headed through a closed lane find a way around, and any
+    // new turns get a queue. Fails without changing anything if somebody is already on a closed
+    // lane or turn, or can't get around.
+    pub fn handle_live_edits(
+        &mut self,
+        now: Time,
+        map: &Map,
+        intersections: &mut IntersectionSimState,
+        scheduler: &mut Scheduler,
+    ) -> Result<(), String> {
+        let mut new_paths = Vec::new();
+        for car in self.cars.values() {
+            let constraints = car.vehicle.vehicle_type.to_constraints();
+            if car.router.get_path().is_still_valid(constraints, map) {
+                continue;
+            }
+            if !car.router.can_reroute() {
+                return Err(format!(
+                    "{} can't change its route around the closures",
+                    car.vehicle.id
+                ));
+            }
+            match car.router.head() {
+                Traversable::Lane(l) => {
+                    if !constraints.can_use(map.get_l(l), map) {
+                        return Err(format!(
+                            "{} is already on {}, which is closed",
+                            car.vehicle.id, l
+                        ));
+                    }
+                    let path = route_around_closures(car.router.get_path(), l, constraints, map)
+                        .map_err(|err| format!("{} {}", car.vehicle.id, err))?;
+                    new_paths.push((car.vehicle.id, path));
+                }
+                Traversable::Turn(t) => {
+                    if map.maybe_get_t(t).is_none() {
+                        return Err(format!(
+                            "{} is already doing {}, which is gone",
+                            car.vehicle.id, t
+                        ));
+                    }
+                    // Make sure there's a way around, but don't switch routes until the next lane
+                    route_around_closures(car.router.get_path(), t.dst, constraints, map)
+                        .map_err(|err| format!("{} {}", car.vehicle.id, err))?;
+                }
+            }
+        }
+
+        for t in map.all_turns().values() {
+            let id = Traversable::Turn(t.id);
+            if !t.between_sidewalks() && !self.queues.contains_key(&id) {
+                self.queues.insert(id, Queue::new(id, map, self.snow));
+            }
+        }
+        // Roads might've become or stopped being snow routes
+        if let Some(snow) = self.snow {
+            for q in self.queues.values_mut() {
+                q.capacity = snow.capacity_of(q.id, map);
+            }
+        }
+        for (id, path) in new_paths {
+            let car = self.cars.get_mut(&id).unwrap();
+            // Anybody already waiting on their old turn has to ask again
+            if let CarState::WaitingToAdvance { .. } = car.state {
+                if let Traversable::Turn(t) = car.router.next() {
+                    intersections.cancel_request(AgentID::Car(id), t);
+                }
+                scheduler.update(now, Command::UpdateCar(id));
+            }
+            car.router.reroute(path);
+            self.events
+                .push(Event::PathAmended(car.router.get_path().clone()));
+        }
+        Ok(())
+    }
+
+    // Called when an incident clears.
+    pub fn unblock_lanes(
+        &mut self,
+        lanes: Vec<LaneID>,
+        now: Time,
+        map: &Map,
+        intersections: &mut IntersectionSimState,
+        scheduler: &mut Scheduler,
+    ) {
+        for l in lanes {
+            let queue = self.queues.get_mut(&Traversable::Lane(l)).unwrap();
+            queue.blocked = false;
+            if let Some(id) = queue.cars.get(0) {
+                if let CarState::WaitingToAdvance { .. } = self.cars[id].state {
+                    scheduler.update(now, Command::UpdateCar(*id));
+                }
+            }
+            // Whoever's waiting to turn into the lane can try again
+            intersections.space_freed(now, map.get_l(l).src_i, scheduler, map);
+        }
+    }
+
+    // Called when a car winds up stuck behind somebody partway along a lane, at the end of a lane
+    // closed by an incident, or when it stops moving in a lane that only reaches its next turn by
+    // swerving across the intersection. Getting around a vehicle stopped in the lane, out of a
+    // closed lane, or into the lane that makes the upcoming turn is mandatory; otherwise drivers
+    // only move over if there are fewer vehicles ahead in the next lane. Either way, they need to
+    // still be able to make their upcoming turn and find a big enough gap.
+    fn maybe_change_lanes(
+        &mut self,
+        id: CarID,
+        now: Time,
+        map: &Map,
+        intersections: &mut IntersectionSimState,
+        scheduler: &mut Scheduler,
+    ) {
+        let blocked = match self.cars[&id].router.head() {
+            Traversable::Lane(l) => self.queues[&Traversable::Lane(l)].blocked,
+            Traversable::Turn(_) => false,
+        };
+        // Drivers caught behind a bike in a lane they share try to pass it
+        let passing_bike = match self.cars[&id].router.head() {
+            Traversable::Lane(l)
+                if !blocked && map.get_parent(l).bike_facility(l) == Some(BikeFacility::Shared) =>
+            {
+                let queue = &self.queues[&Traversable::Lane(l)];
+                match queue.cars.iter().position(|c| *c == id) {
+                    Some(idx) if idx > 0 && queue.cars[idx - 1].1 == VehicleType::Bike => {
+                        Some(queue.cars[idx - 1])
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let for_turn = match self.cars[&id].router.head() {
+            Traversable::Lane(_) => next_turn_changes_lanes(&self.cars[&id].router, map),
+            Traversable::Turn(_) => false,
+        };
+        // Everybody knows how to merge out of a closed lane, around a bike, or into the lane for
+        // their turn, even if they don't change lanes otherwise.
+        let config = match self.lane_changing {
+            Some(c) => c,
+            None if blocked || for_turn || passing_bike.is_some() => LaneChangeConfig::default(),
+            None => {
+                return;
+            }
+        };
+        let current = {
+            let car = &self.cars[&id];
+            let stuck = match car.state {
+                CarState::Queued { .. } => true,
+                CarState::WaitingToAdvance { .. } => blocked || for_turn,
+                _ => false,
+            };
+            match car.router.head() {
+                Traversable::Lane(l)
+                    if stuck
+                        && (car.vehicle.vehicle_type == VehicleType::Car
+                            || blocked
+                            || for_turn)
+                        && !car.router.last_step()
+                        && car.last_steps.is_empty() =>
+                {
+                    l
+                }
+                _ => {
+                    return;
+                }
+            }
+        };
+        let dists = self.queues[&Traversable::Lane(current)].get_car_positions(
+            now,
+            &self.cars,
+            &self.queues,
+        );
+        let idx = dists.iter().position(|(c, _)| *c == id).unwrap();
+        if idx == 0 && !blocked && !for_turn {
+            return;
+        }
+        let our_dist = dists[idx].1;
+        if map.get_l(current).length() - our_dist < config.min_room_left && !blocked && !for_turn {
+            return;
+        }
+        let mandatory = blocked
+            || for_turn
+            || match self.cars[&dists[idx - 1].0].state {
+                CarState::Idling(_, _) => true,
+                _ => false,
+            };
+
+        let car = &self.cars[&id];
+        let parent = map.get_parent(current);
+        let (fwds, offset) = parent.dir_and_offset(current);
+        let siblings = if fwds {
+            &parent.children_forwards
+        } else {
+            &parent.children_backwards
+        };
+        let current_lt = map.get_l(current).lane_type;
+        // The number of vehicles ahead in the new lane, the lane, and the route from there
+        let mut best: Option<(usize, LaneID, Router)> = None;
+        for (other_offset, (l, lt)) in siblings.iter().enumerate() {
+            if *lt != current_lt || (other_offset as isize - offset as isize).abs() != 1 {
+                continue;
+            }
+            let queue = &self.queues[&Traversable::Lane(*l)];
+            if !queue.room_for_car(car) {
+                continue;
+            }
+            let mut router = car.router.clone();
+            if !router.change_lanes(*l, map) {
+                continue;
+            }
+            // Don't move over into a lane that'd need to swerve across the intersection, unless
+            // the current one is closed
+            if !blocked && next_turn_changes_lanes(&router, map) {
+                continue;
+            }
+
+            let others = queue.get_car_positions(now, &self.cars, &self.queues);
+            // Slot in behind anybody right alongside, like somebody already at the stop line
+            let new_idx = others
+                .iter()
+                .position(|(_, dist)| our_dist > *dist)
+                .unwrap_or_else(|| others.len());
+            // Passing a bike is worth a longer queue, but not getting stuck behind another bike
+            if passing_bike.is_some() {
+                if new_idx > 0 && (others[new_idx - 1].0).1 == VehicleType::Bike {
+                    continue;
+                }
+            } else if !mandatory && new_idx >= idx {
+                continue;
+            }
+            // Too complicated to figure out where the laggy head's back is
+            if new_idx == 0 && queue.laggy_head.is_some() {
+                continue;
+            }
+            // How much room there'd be beyond what everybody wants to keep
+            let gap_ahead = if new_idx == 0 {
+                None
+            } else {
+                let (leader, leader_dist) = others[new_idx - 1];
+                Some(
+                    leader_dist
+                        - self.cars[&leader].vehicle.length
+                        - our_dist
+                        - car.following.desired_gap,
+                )
+            };
+            let gap_behind = others.get(new_idx).map(|(follower, follower_dist)| {
+                let speed = match self.cars[follower].state {
+                    CarState::Crossing(ref time_int, ref dist_int) => Speed::from_dist_time(
+                        dist_int.end - dist_int.start,
+                        time_int.end - time_int.start,
+                    ),
+                    _ => Speed::ZERO,
+                };
+                (
+                    our_dist
+                        - car.vehicle.length
+                        - *follower_dist
+                        - self.cars[follower].following.desired_gap,
+                    speed,
+                )
+            });
+            if !config.accept_gap(gap_ahead, gap_behind, mandatory) {
+                continue;
+            }
+            if best.as_ref().map(|(n, _, _)| new_idx < *n).unwrap_or(true) {
+                best = Some((new_idx, *l, router));
+            }
+        }
+        let (new_idx, to, router) = match best {
+            Some(x) => x,
+            None => {
+                if let Some(bike) = passing_bike {
+                    self.events
+                        .push(Event::StuckBehindBike(id, bike, parent.id));
+                }
+                return;
+            }
+        };
+        if let Some(bike) = passing_bike {
+            self.events.push(Event::PassedBike(id, bike, parent.id));
+        }
+
+        let mut car = self.cars.remove(&id).unwrap();
+        {
+            let queue = self.queues.get_mut(&Traversable::Lane(current)).unwrap();
+            assert_eq!(queue.cars.remove(idx).unwrap(), id);
+            queue.free_reserved_space(&car);
+        }
+        intersections.space_freed(now, map.get_l(current).src_i, scheduler, map);
+        {
+            let queue = self.queues.get_mut(&Traversable::Lane(to)).unwrap();
+            queue.cars.insert(new_idx, id);
+            queue.reserved_length += car.vehicle.length + car.following.desired_gap;
+        }
+
+        match car.state {
+            CarState::Queued { blocked_since } => {
+                car.total_blocked_time += now - blocked_since;
+            }
+            CarState::WaitingToAdvance { blocked_since } => {
+                car.total_blocked_time += now - blocked_since;
+                // They might've asked to turn before the lane was closed
+                if let Traversable::Turn(t) = car.router.next() {
+                    intersections.cancel_request(AgentID::Car(id), t);
+                }
+            }
+            _ => {}
+        }
+        car.router = router;
+        car.lane_change = Some(LaneChange {
+            from: current,
+            to,
+            started: now,
+        });
+        car.state = car.crossing_state(our_dist, now, map, self.snow, &self.weather);
+        scheduler.update(car.state.get_end_time(), Command::UpdateCar(id));
+        self.events
+            .push(Event::PathAmended(car.router.get_path().clone()));
+        self.cars.insert(id, car);
+
+        if idx != dists.len() - 1 {
+            let (follower_id, follower_dist) = dists[idx + 1];
+            self.leader_left(follower_id, follower_dist, now, map, scheduler);
+        }
+    }
+
     // Returns true if the car survives.
     fn update_car_with_distances(
         &mut self,
@@ -426,6 +1063,7 @@ impl DrivingSimState {
             CarState::Queued { blocked_since } => {
                 match car.router.maybe_handle_end(
                     our_dist,
+                    now,
                     &car.vehicle,
                     parking,
                     map,
@@ -476,7 +1114,8 @@ impl DrivingSimState {
                     }
                     Some(ActionAtEnd::GotoLaneEnd) => {
                         car.total_blocked_time += now - blocked_since;
-                        car.state = car.crossing_state(our_dist, now, map);
+                        car.state =
+                            car.crossing_state(our_dist, now, map, self.snow, &self.weather);
                         scheduler
                             .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                         true
@@ -494,7 +1133,7 @@ impl DrivingSimState {
                         false
                     }
                     Some(ActionAtEnd::BusAtStop) => {
-                        transit.bus_arrived_at_stop(
+                        let action = transit.bus_arrived_at_stop(
                             now,
                             car.vehicle.id,
                             trips,
@@ -503,9 +1142,154 @@ impl DrivingSimState {
                             map,
                         );
                         car.total_blocked_time += now - blocked_since;
+                        match action {
+                            BusStopAction::Retire => false,
+                            BusStopAction::LayOver(recovery, true) => {
+                                // Get out of the way, and pull back out when the layover's done
+                                transit.bus_pulled_into_layover_space(
+                                    car.vehicle.clone(),
+                                    Position::new(car.router.head().as_lane(), our_dist),
+                                );
+                                scheduler.push(
+                                    now + recovery.max(TIME_TO_WAIT_AT_STOP),
+                                    Command::EndLayover(car.vehicle.id),
+                                );
+                                false
+                            }
+                            _ => {
+                                // With no free layover space, the bus rests in the lane
+                                let wait = match action {
+                                    BusStopAction::LayOver(recovery, _) => {
+                                        recovery.max(TIME_TO_WAIT_AT_STOP)
+                                    }
+                                    _ => TIME_TO_WAIT_AT_STOP,
+                                };
+                                car.state =
+                                    CarState::Idling(our_dist, TimeInterval::new(now, now + wait));
+                                scheduler.push(
+                                    car.state.get_end_time(),
+                                    Command::UpdateCar(car.vehicle.id),
+                                );
+                                true
+                            }
+                        }
+                    }
+                    Some(ActionAtEnd::RideHailStop(in_zone)) => {
+                        car.total_blocked_time += now - blocked_since;
+                        car.state = CarState::Idling(
+                            our_dist,
+                            TimeInterval::new(
+                                now,
+                                now + if in_zone {
+                                    TIME_TO_PULL_OVER
+                                } else {
+                                    TIME_TO_DROP_OFF
+                                },
+                            ),
+                        );
+                        scheduler
+                            .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                        true
+                    }
+                    Some(ActionAtEnd::RideHailIdle) => {
+                        trips.ridehail_vehicle_idle(
+                            now,
+                            car.vehicle.id,
+                            Position::new(car.router.head().as_lane(), our_dist),
+                            map,
+                            parking,
+                            scheduler,
+                        );
+                        false
+                    }
+                    Some(ActionAtEnd::Deliver(in_zone, dwell)) => {
+                        car.total_blocked_time += now - blocked_since;
+                        let (trip, person) = car.trip_and_person.unwrap();
+                        let pos = Position::new(car.router.head().as_lane(), our_dist);
+                        let (router, req) = match car.router.leave_after_delivery(pos, map) {
+                            Some(pair) => pair,
+                            None => {
+                                self.events.push(Event::Alert(
+                                    AlertLocation::Person(person),
+                                    format!(
+                                        "{} can't go anywhere after delivering on {}",
+                                        car.vehicle.id,
+                                        pos.lane()
+                                    ),
+                                ));
+                                trips.abort_trip(now, trip, None, parking, scheduler, map);
+                                return false;
+                            }
+                        };
+                        self.events.push(Event::DeliveryStarted(
+                            car.vehicle.id,
+                            pos.lane(),
+                            in_zone,
+                        ));
+                        self.events.push(Event::TripPhaseStarting(
+                            trip,
+                            person,
+                            None,
+                            TripPhaseType::Delivering,
+                        ));
+                        if in_zone {
+                            // Get out of the way, and come back out of the loading zone when done
+                            trips.truck_pulled_into_loading_zone(
+                                car.vehicle.id,
+                                car.total_blocked_time,
+                            );
+                            scheduler.push(
+                                now + dwell,
+                                Command::SpawnCar(
+                                    CreateCar {
+                                        vehicle: car.vehicle.clone(),
+                                        router,
+                                        req,
+                                        start_dist: our_dist,
+                                        maybe_parked_car: None,
+                                        trip_and_person: car.trip_and_person,
+                                    },
+                                    true,
+                                ),
+                            );
+                            false
+                        } else {
+                            // Double-park, blocking the lane the whole time. The router still
+                            // knows where to go once the truck's done.
+                            car.state =
+                                CarState::Idling(our_dist, TimeInterval::new(now, now + dwell));
+                            scheduler
+                                .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                            true
+                        }
+                    }
+                    Some(ActionAtEnd::EmergencyArrived(station, scene, response_time)) => {
+                        self.events.push(Event::EmergencyResponse(
+                            car.vehicle.id,
+                            station,
+                            scene,
+                            response_time,
+                        ));
+                        // Stay on the scene, off the road
+                        false
+                    }
+                    Some(ActionAtEnd::DropOff(in_zone)) => {
+                        car.total_blocked_time += now - blocked_since;
+                        self.events.push(Event::PassengerDroppedOff(
+                            car.vehicle.id,
+                            car.router.head().as_lane(),
+                            in_zone,
+                        ));
                         car.state = CarState::Idling(
                             our_dist,
-                            TimeInterval::new(now, now + TIME_TO_WAIT_AT_STOP),
+                            TimeInterval::new(
+                                now,
+                                now + if in_zone {
+                                    TIME_TO_PULL_OVER
+                                } else {
+                                    TIME_TO_DROP_OFF
+                                },
+                            ),
                         );
                         scheduler
                             .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -578,7 +1362,7 @@ impl DrivingSimState {
         // Hacks to delete cars that're mid-turn
         if let Traversable::Turn(_) = car.router.head() {
             let queue = self.queues.get_mut(&car.router.head()).unwrap();
-            queue.reserved_length += car.vehicle.length + FOLLOWING_DISTANCE;
+            queue.reserved_length += car.vehicle.length + car.following.desired_gap;
         }
         if let Some(Traversable::Turn(t)) = car.router.maybe_next() {
             intersections.cancel_request(AgentID::Car(c), t);
@@ -613,6 +1397,9 @@ impl DrivingSimState {
         }
 
         intersections.vehicle_gone(car.vehicle.id);
+        if let Some(ref mut rerouter) = self.rerouter {
+            rerouter.car_gone(car.vehicle.id);
+        }
 
         // We might be vanishing while partly clipping into other stuff.
         self.trim_last_steps(
@@ -629,37 +1416,48 @@ impl DrivingSimState {
 
         // Update the follower so that they don't suddenly jump forwards.
         if idx != dists.len() - 1 {
-            let (follower_id, follower_dist) = dists[idx + 1];
-            let mut follower = self.cars.get_mut(&follower_id).unwrap();
             // TODO If the leader vanished at a border node, this still jumps a bit -- the
             // lead car's back is still sticking out. Need to still be bound by them, even
             // though they don't exist! If the leader just parked, then we're fine.
-            match follower.state {
-                CarState::Queued { blocked_since } => {
-                    // Prevent them from jumping forwards.
-                    follower.total_blocked_time += now - blocked_since;
-                    follower.state = follower.crossing_state(follower_dist, now, map);
-                    scheduler.update(
-                        follower.state.get_end_time(),
-                        Command::UpdateCar(follower_id),
-                    );
-                }
-                CarState::Crossing(_, _) => {
-                    // If the follower was still Crossing, they might not've been blocked
-                    // by leader yet. In that case, recalculating their Crossing state is a
-                    // no-op.
-                    follower.state = follower.crossing_state(follower_dist, now, map);
-                    scheduler.update(
-                        follower.state.get_end_time(),
-                        Command::UpdateCar(follower_id),
-                    );
-                }
-                // They weren't blocked
-                CarState::Unparking(_, _, _)
-                | CarState::Parking(_, _, _)
-                | CarState::Idling(_, _) => {}
-                CarState::WaitingToAdvance { .. } => unreachable!(),
+            let (follower_id, follower_dist) = dists[idx + 1];
+            self.leader_left(follower_id, follower_dist, now, map, scheduler);
+        }
+    }
+
+    // The car ahead of this one disappeared from the queue.
+    fn leader_left(
+        &mut self,
+        follower_id: CarID,
+        follower_dist: Distance,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) {
+        let mut follower = self.cars.get_mut(&follower_id).unwrap();
+        match follower.state {
+            CarState::Queued { blocked_since } => {
+                // Prevent them from jumping forwards.
+                follower.total_blocked_time += now - blocked_since;
+                follower.state =
+                    follower.crossing_state(follower_dist, now, map, self.snow, &self.weather);
+                scheduler.update(
+                    follower.state.get_end_time(),
+                    Command::UpdateCar(follower_id),
+                );
+            }
+            CarState::Crossing(_, _) => {
+                // If the follower was still Crossing, they might not've been blocked by leader
+                // yet. In that case, recalculating their Crossing state is a no-op.
+                follower.state =
+                    follower.crossing_state(follower_dist, now, map, self.snow, &self.weather);
+                scheduler.update(
+                    follower.state.get_end_time(),
+                    Command::UpdateCar(follower_id),
+                );
             }
+            // They weren't blocked
+            CarState::Unparking(_, _, _) | CarState::Parking(_, _, _) | CarState::Idling(_, _) => {}
+            CarState::WaitingToAdvance { .. } => unreachable!(),
         }
     }
 
@@ -687,7 +1485,8 @@ impl DrivingSimState {
         };
 
         // Trim off as many of the oldest last_steps as we've made distance.
-        let mut dist_left_to_cleanup = self.cars[&id].vehicle.length + FOLLOWING_DISTANCE;
+        let mut dist_left_to_cleanup =
+            self.cars[&id].vehicle.length + self.cars[&id].following.desired_gap;
         dist_left_to_cleanup -= dist_along_last;
         let mut num_to_trim = None;
         for (idx, step) in self.cars[&id].last_steps.iter().enumerate() {
@@ -713,10 +1512,12 @@ impl DrivingSimState {
                     // fine for correctness.
                     DistanceInterval::new_driving(
                         dist_along_last,
-                        self.cars[&id].vehicle.length + FOLLOWING_DISTANCE,
+                        self.cars[&id].vehicle.length + self.cars[&id].following.desired_gap,
                     ),
                     now,
                     map,
+                    self.snow,
+                    &self.weather,
                 )
                 .get_end_time();
             // Sometimes due to rounding, retry_at will be exactly time, but we really need to
@@ -822,6 +1623,7 @@ impl DrivingSimState {
                     vehicle_type: Some(car.vehicle.vehicle_type),
                     pos: queue.id.dist_along(dist, map).0,
                     person: car.trip_and_person.map(|(_, p)| p),
+                    on: queue.id,
                 });
             }
         }
@@ -829,6 +1631,175 @@ impl DrivingSimState {
         result
     }
 
+    // Per road, the average speed of every vehicle along it right now, as a fraction of the
+    // fastest that vehicle could go there. That's the speed limit for most cars; bikes and slow
+    // vehicles aren't counted as congestion. Stuck vehicles count as stopped; parking and idling
+    // ones aren't part of traffic. Vehicles in the middle of a turn don't count either.
+    pub fn road_speed_ratios(&self, now: Time, map: &Map) -> BTreeMap<RoadID, f64> {
+        let mut sums: BTreeMap<RoadID, (f64, usize)> = BTreeMap::new();
+        for queue in self.queues.values() {
+            let l = match queue.id {
+                Traversable::Lane(l) => l,
+                Traversable::Turn(_) => {
+                    continue;
+                }
+            };
+            for id in &queue.cars {
+                let car = &self.cars[id];
+                let speed = match car.state {
+                    CarState::Crossing(ref time_int, ref dist_int) => {
+                        let dt = time_int.end - time_int.start;
+                        if dt == Duration::ZERO {
+                            continue;
+                        }
+                        Speed::meters_per_second(
+                            dist_int.length().inner_meters() / dt.inner_seconds(),
+                        )
+                    }
+                    CarState::Queued { .. } | CarState::WaitingToAdvance { .. } => Speed::ZERO,
+                    CarState::Unparking(_, _, _)
+                    | CarState::Parking(_, _, _)
+                    | CarState::Idling(_, _) => {
+                        continue;
+                    }
+                };
+                let limit = car.vehicle.speed_on(queue.id, map, now);
+                let entry = sums.entry(map.get_l(l).parent).or_insert((0.0, 0));
+                entry.0 +=
+                    (speed.inner_meters_per_second() / limit.inner_meters_per_second()).min(1.0);
+                entry.1 += 1;
+            }
+        }
+        sums.into_iter()
+            .map(|(r, (sum, cnt))| (r, sum / (cnt as f64)))
+            .collect()
+    }
+
+    // For every vehicle that's been stuck at least min_wait, who it's waiting on
+    pub fn waiting_on(
+        &self,
+        now: Time,
+        min_wait: Duration,
+        intersections: &IntersectionSimState,
+    ) -> BTreeMap<CarID, BTreeSet<CarID>> {
+        let mut results = BTreeMap::new();
+        for queue in self.queues.values() {
+            for (idx, id) in queue.cars.iter().enumerate() {
+                let car = &self.cars[id];
+                let (blocked_since, waiting_to_advance) = match car.state {
+                    CarState::Queued { blocked_since } => (blocked_since, false),
+                    CarState::WaitingToAdvance { blocked_since } => (blocked_since, true),
+                    _ => {
+                        continue;
+                    }
+                };
+                if now - blocked_since < min_wait {
+                    continue;
+                }
+                let others: BTreeSet<CarID> = if idx > 0 {
+                    vec![queue.cars[idx - 1]].into_iter().collect()
+                } else if waiting_to_advance {
+                    intersections
+                        .get_blocked_by(AgentID::Car(*id))
+                        .into_iter()
+                        .map(|a| a.as_car())
+                        .collect()
+                } else {
+                    queue.laggy_head.into_iter().collect()
+                };
+                if !others.is_empty() {
+                    results.insert(*id, others);
+                }
+            }
+        }
+        results
+    }
+
+    // The turn a vehicle is in the middle of, or headed towards next
+    pub fn get_next_turn(&self, id: CarID) -> Option<TurnID> {
+        let router = &self.cars.get(&id)?.router;
+        match router.head() {
+            Traversable::Turn(t) => Some(t),
+            Traversable::Lane(_) => match router.maybe_next() {
+                Some(Traversable::Turn(t)) => Some(t),
+                _ => None,
+            },
+        }
+    }
+
+    // The average speed of every vehicle moving along or stuck on each lane right now
+    pub fn average_speeds(&self) -> BTreeMap<LaneID, Speed> {
+        let mut results = BTreeMap::new();
+        for queue in self.queues.values() {
+            let l = match queue.id {
+                Traversable::Lane(l) => l,
+                Traversable::Turn(_) => {
+                    continue;
+                }
+            };
+            let mut total = Speed::ZERO;
+            let mut count = 0;
+            for c in &queue.cars {
+                match self.cars[c].state {
+                    CarState::Crossing(ref time_int, ref dist_int) => {
+                        if time_int.end > time_int.start {
+                            total = total
+                                + Speed::from_dist_time(
+                                    dist_int.end - dist_int.start,
+                                    time_int.end - time_int.start,
+                                );
+                        }
+                        count += 1;
+                    }
+                    CarState::Queued { .. } | CarState::WaitingToAdvance { .. } => {
+                        count += 1;
+                    }
+                    CarState::Unparking(_, _, _)
+                    | CarState::Parking(_, _, _)
+                    | CarState::Idling(_, _) => {}
+                }
+            }
+            if count > 0 {
+                results.insert(l, total * (1.0 / (count as f64)));
+            }
+        }
+        results
+    }
+
+    pub fn count_vehicles_on(&self, on: Traversable) -> usize {
+        self.queues.get(&on).map(|q| q.cars.len()).unwrap_or(0)
+    }
+
+    // Is any vehicle within this distance of the end of the lane?
+    // Vehicles with some part of their body between start and end along a lane, and the turn
+    // each one will make next
+    pub fn vehicles_over(
+        &self,
+        now: Time,
+        l: LaneID,
+        start: Distance,
+        end: Distance,
+    ) -> Vec<(CarID, Option<TurnID>)> {
+        let queue = match self.queues.get(&Traversable::Lane(l)) {
+            Some(q) => q,
+            None => {
+                return Vec::new();
+            }
+        };
+        queue
+            .get_car_positions(now, &self.cars, &self.queues)
+            .into_iter()
+            .filter(|(id, front)| *front >= start && *front - self.cars[id].vehicle.length <= end)
+            .map(|(id, _)| {
+                let turn = match self.cars[&id].router.maybe_next() {
+                    Some(Traversable::Turn(t)) => Some(t),
+                    _ => None,
+                };
+                (id, turn)
+            })
+            .collect()
+    }
+
     pub fn does_car_exist(&self, id: CarID) -> bool {
         self.cars.contains_key(&id)
     }
@@ -859,16 +1830,43 @@ impl DrivingSimState {
     ) -> Vec<DrawCarInput> {
         let mut result = Vec::new();
         for queue in self.queues.values() {
-            result.extend(
-                queue
-                    .get_car_positions(now, &self.cars, &self.queues)
-                    .into_iter()
-                    .map(|(id, dist)| self.cars[&id].get_draw_car(dist, now, map, transit)),
-            );
+            result.extend(self.draw_queue(queue, now, map, transit));
         }
         result
     }
 
+    fn draw_queue(
+        &self,
+        queue: &Queue,
+        now: Time,
+        map: &Map,
+        transit: &TransitSimState,
+    ) -> Vec<DrawCarInput> {
+        let positions = queue.get_car_positions(now, &self.cars, &self.queues);
+        // Everybody else on a lane with an emergency vehicle pulls towards the curb
+        let make_way = match queue.id {
+            Traversable::Lane(_) => positions
+                .iter()
+                .any(|(id, _)| self.cars[id].router.is_responding()),
+            Traversable::Turn(_) => false,
+        };
+        positions
+            .into_iter()
+            .map(|(id, dist)| {
+                let car = &self.cars[&id];
+                let mut draw = car.get_draw_car(dist, now, map, transit);
+                if make_way && !car.router.is_responding() {
+                    // This runs every frame, so don't spam warnings about weird geometry
+                    if let Some(body) = draw.body.shift_right(PULL_OVER_SHIFT).ok_without_warnings()
+                    {
+                        draw.body = body;
+                    }
+                }
+                draw
+            })
+            .collect()
+    }
+
     // This is about as expensive as get_draw_cars_on.
     pub fn get_single_draw_car(
         &self,
@@ -891,11 +1889,7 @@ impl DrivingSimState {
         transit: &TransitSimState,
     ) -> Vec<DrawCarInput> {
         match self.queues.get(&on) {
-            Some(q) => q
-                .get_car_positions(now, &self.cars, &self.queues)
-                .into_iter()
-                .map(|(id, dist)| self.cars[&id].get_draw_car(dist, now, map, transit))
-                .collect(),
+            Some(q) => self.draw_queue(q, now, map, transit),
             None => Vec::new(),
         }
     }
@@ -937,6 +1931,63 @@ impl DrivingSimState {
         }
     }
 
+    // Estimates when a car reaches the end of its current path, using how fast the other cars
+    // along each remaining lane are moving right now. Empty lanes and turns are crossed at the
+    // car's free-flow speed. Time spent waiting at intersections isn't counted.
+    pub fn predict_arrival(&self, id: CarID, now: Time, map: &Map) -> Option<Time> {
+        let car = self.cars.get(&id)?;
+        let path = car.router.get_path();
+        let speeds = self.average_speeds();
+
+        let steps = path.get_steps();
+        let mut total = Duration::ZERO;
+        for (idx, step) in steps.iter().enumerate() {
+            let on = step.as_traversable();
+            if idx == 0 {
+                total += match car.state {
+                    // Already at the end of the current step
+                    CarState::Queued { .. } | CarState::WaitingToAdvance { .. } => Duration::ZERO,
+                    // Crossing always goes to the end of the current step
+                    _ => (car.state.get_end_time() - now).max(Duration::ZERO),
+                };
+            } else if idx == steps.len() - 1 {
+                total += path.end_dist() / predicted_speed(&car.vehicle, on, &speeds, now, map);
+            } else {
+                total += on.length(map) / predicted_speed(&car.vehicle, on, &speeds, now, map);
+            }
+        }
+        Some(now + total)
+    }
+
+    // When a bus reaches a stop further along its route. legs are the paths between the stop it's
+    // headed to now and the one in question; the bus waits at each stop in between.
+    pub fn predict_bus_arrival(
+        &self,
+        id: CarID,
+        legs: Vec<&Path>,
+        now: Time,
+        map: &Map,
+    ) -> Option<Time> {
+        let car = self.cars.get(&id)?;
+        let speeds = self.average_speeds();
+
+        let mut time = self.predict_arrival(id, now, map)?;
+        for path in legs {
+            time += TIME_TO_WAIT_AT_STOP;
+            let steps = path.get_steps();
+            for (idx, step) in steps.iter().enumerate() {
+                let on = step.as_traversable();
+                let dist = if idx == steps.len() - 1 {
+                    path.end_dist()
+                } else {
+                    on.length(map)
+                };
+                time += dist / predicted_speed(&car.vehicle, on, &speeds, now, map);
+            }
+        }
+        Some(time)
+    }
+
     pub fn get_path(&self, id: CarID) -> Option<&Path> {
         let car = self.cars.get(&id)?;
         Some(car.router.get_path())
@@ -974,6 +2025,13 @@ impl DrivingSimState {
         car.vehicle.owner
     }
 
+    pub fn is_responding(&self, id: CarID) -> bool {
+        self.cars
+            .get(&id)
+            .map(|car| car.router.is_responding())
+            .unwrap_or(false)
+    }
+
     // TODO Clean this up
     pub fn find_blockage_front(
         &self,
@@ -1036,7 +2094,198 @@ impl DrivingSimState {
         }
     }
 
+    // Will every vehicle along this road stay clear of the point somebody wants to cross at until
+    // clear_until? Vehicles entering the road later will yield instead.
+    pub fn gap_for_midblock_crossing(
+        &self,
+        pos: Position,
+        clear_until: Time,
+        now: Time,
+        map: &Map,
+    ) -> bool {
+        for l in map.get_parent(pos.lane()).all_lanes() {
+            if !map.get_l(l).lane_type.is_for_moving_vehicles() {
+                continue;
+            }
+            let queue = &self.queues[&Traversable::Lane(l)];
+            let dist = pos.equiv_pos(l, Distance::ZERO, map).dist_along();
+            for (id, front) in queue.get_car_positions(now, &self.cars, &self.queues) {
+                let car = &self.cars[&id];
+                let arrive = if front >= dist {
+                    // Already past
+                    if front - car.vehicle.length >= dist {
+                        continue;
+                    }
+                    now
+                } else if let CarState::Crossing(ref time_int, ref dist_int) = car.state {
+                    match arrival_time(time_int, dist_int, dist) {
+                        Some(t) => t,
+                        None => continue,
+                    }
+                } else {
+                    // Stopped short of the crossing
+                    continue;
+                };
+                if arrive < clear_until {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    // Until they're across, vehicles entering the road slow down to let them finish.
+    pub fn ped_crossing_midblock(
+        &mut self,
+        ped: PedestrianID,
+        pos: Position,
+        until: Time,
+        map: &Map,
+    ) {
+        for l in map.get_parent(pos.lane()).all_lanes() {
+            if map.get_l(l).lane_type.is_for_moving_vehicles() {
+                let dist = pos.equiv_pos(l, Distance::ZERO, map).dist_along();
+                self.midblock_crossings
+                    .entry(l)
+                    .or_insert_with(Vec::new)
+                    .push((dist, until, ped));
+            }
+        }
+    }
+
+    // The car just started along this lane. If it'd reach somebody crossing mid-block before
+    // they're clear, it brakes to arrive just as they finish.
+    fn yield_to_jaywalkers(&mut self, car: &mut Car, l: LaneID, now: Time, map: &Map) {
+        let crossings = match self.midblock_crossings.get_mut(&l) {
+            Some(c) => c,
+            None => {
+                return;
+            }
+        };
+        crossings.retain(|(_, until, _)| *until > now);
+        if let CarState::Crossing(ref mut time_int, ref dist_int) = car.state {
+            for (dist, until, ped) in crossings.iter() {
+                if let Some(arrive) = arrival_time(time_int, dist_int, *dist) {
+                    if arrive < *until {
+                        *time_int =
+                            TimeInterval::new(time_int.start, time_int.end + (*until - arrive));
+                        self.events.push(Event::JaywalkingConflict(
+                            *ped,
+                            car.vehicle.id,
+                            map.get_l(l).parent,
+                        ));
+                    }
+                }
+            }
+        }
+        if crossings.is_empty() {
+            self.midblock_crossings.remove(&l);
+        }
+    }
+
+    // Somebody's getting into or out of a car parked on-street. The position is where the car
+    // stops alongside the spot. Any bike riding right by in a lane next to the spot has to dodge
+    // the door.
+    fn check_door_zone(
+        &mut self,
+        car: CarID,
+        spot: ParkingSpot,
+        pos: Position,
+        now: Time,
+        map: &Map,
+    ) {
+        let parking_lane = match spot {
+            ParkingSpot::Onstreet(l, _) => l,
+            ParkingSpot::Offstreet(_, _) | ParkingSpot::Lot(_, _) => {
+                return;
+            }
+        };
+        for (l, _) in map.get_parent(parking_lane).neighbors(parking_lane) {
+            let dists = match self.queues.get(&Traversable::Lane(l)) {
+                Some(queue) => queue.get_car_positions(now, &self.cars, &self.queues),
+                None => {
+                    continue;
+                }
+            };
+            let door = pos.equiv_pos(l, Distance::ZERO, map).dist_along();
+            for (bike, dist) in dists {
+                if bike.1 == VehicleType::Bike && (dist - door).abs() <= DOOR_ZONE_REACH {
+                    self.events.push(Event::DoorZoneConflict(bike, car, l));
+                }
+            }
+        }
+    }
+
     pub fn collect_events(&mut self) -> Vec<Event> {
         std::mem::replace(&mut self.events, Vec::new())
     }
 }
+
+// When a vehicle crossing this interval at a steady pace reaches dist, if it does at all
+// Does the route's next turn only reach the lane after it by swerving across the intersection?
+// Then the driver should've been in a different lane for it.
+fn next_turn_changes_lanes(router: &Router, map: &Map) -> bool {
+    match router.get_path().get_steps().get(1) {
+        Some(PathStep::Turn(t)) => match map.get_t(*t).turn_type {
+            TurnType::LaneChangeLeft | TurnType::LaneChangeRight => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn arrival_time(
+    time_int: &TimeInterval,
+    dist_int: &DistanceInterval,
+    dist: Distance,
+) -> Option<Time> {
+    if dist < dist_int.start || dist > dist_int.end {
+        return None;
+    }
+    let len = dist_int.end - dist_int.start;
+    if len == Distance::ZERO {
+        return Some(time_int.start);
+    }
+    Some(time_int.start + (time_int.end - time_int.start) * ((dist - dist_int.start) / len))
+}
+
+// Live map edits closed something on this path. Find a new way to the same end, starting from the
+// beginning of this lane.
+fn route_around_closures(
+    path: &Path,
+    start: LaneID,
+    constraints: PathConstraints,
+    map: &Map,
+) -> Result<Path, String> {
+    let end = match path.last_step() {
+        PathStep::Lane(l) => l,
+        _ => {
+            return Err("doesn't end at a lane".to_string());
+        }
+    };
+    let req = PathRequest {
+        start: Position::new(start, Distance::ZERO),
+        end: Position::new(end, path.end_dist()),
+        constraints,
+    };
+    map.pathfind(req.clone())
+        .ok_or_else(|| format!("can't get around the closures: {}", req))
+}
+
+// Current traffic along a lane if there is any, otherwise the vehicle's free-flow speed
+fn predicted_speed(
+    vehicle: &Vehicle,
+    on: Traversable,
+    speeds: &BTreeMap<LaneID, Speed>,
+    now: Time,
+    map: &Map,
+) -> Speed {
+    let free_flow = vehicle.speed_on(on, map, now);
+    match on {
+        Traversable::Lane(l) => speeds
+            .get(&l)
+            .map(|s| s.min(free_flow).max(MIN_PREDICTED_SPEED))
+            .unwrap_or(free_flow),
+        Traversable::Turn(_) => free_flow,
+    }
+}
@@ -1,10 +1,12 @@
 use crate::mechanics::car::Car;
-use crate::mechanics::Queue;
-use crate::{AgentID, AlertLocation, CarID, Command, Event, Scheduler, Speed, TripMode};
-use abstutil::{deserialize_btreemap, retain_btreeset, serialize_btreemap};
+use crate::mechanics::{Detector, DetectorSimState, DrivingSimState, Queue};
+use crate::{
+    AgentID, AlertLocation, CarID, Command, Event, PedestrianID, Scheduler, Speed, TripMode,
+};
+use abstutil::{deserialize_btreemap, retain_btreemap, retain_btreeset, serialize_btreemap};
 use geom::{Duration, Time};
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, IntersectionID, LaneID, Map, RoadID, Traversable,
+    ControlStopSign, ControlTrafficSignal, IntersectionID, LaneID, Map, Phase, RoadID, Traversable,
     TurnID, TurnPriority, TurnType,
 };
 use serde::{Deserialize, Serialize};
@@ -12,17 +14,48 @@ use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 const WAIT_AT_STOP_SIGN: Duration = Duration::const_seconds(0.5);
 const WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL: Duration = Duration::const_seconds(0.2);
+// After the min green, how often does an actuated signal check its detectors?
+const ACTUATED_EXTENSION: Duration = Duration::const_seconds(2.0);
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub struct IntersectionSimState {
     state: BTreeMap<IntersectionID, State>,
     use_freeform_policy_everywhere: bool,
     dont_block_the_box: bool,
+    // Intersections that don't follow the dont_block_the_box default. True means enforce.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    block_the_box_overrides: BTreeMap<IntersectionID, bool>,
     break_turn_conflict_cycles: bool,
+    // At stop signs, what fraction of drivers yield to pedestrians waiting to cross. None means
+    // drivers only wait for people already in the crosswalk.
+    #[serde(default)]
+    crosswalk_yield: Option<f64>,
     // (x, y) means x is blocked by y. It's a many-to-many relationship. TODO Better data
     // structure.
     blocked_by: BTreeSet<(CarID, CarID)>,
     events: Vec<Event>,
+    // Only for actuated traffic signals. Fixed-time signals just depend on the time.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    actuated: BTreeMap<IntersectionID, ActuatedState>,
+    // Traffic signals held in one phase until an emergency vehicle gets through
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    preempted: BTreeMap<IntersectionID, Preemption>,
+    // Traffic signals a script has pinned to one phase for a while
+    #[serde(
+        default,
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    held: BTreeMap<IntersectionID, Hold>,
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
@@ -37,6 +70,24 @@ struct State {
     waiting: BTreeMap<Request, Time>,
 }
 
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct ActuatedState {
+    current_phase: usize,
+    phase_started: Time,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Preemption {
+    car: CarID,
+    phase: usize,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Hold {
+    phase: usize,
+    until: Time,
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Debug)]
 struct Request {
     agent: AgentID,
@@ -49,15 +100,22 @@ impl IntersectionSimState {
         scheduler: &mut Scheduler,
         use_freeform_policy_everywhere: bool,
         dont_block_the_box: bool,
+        block_the_box_overrides: BTreeMap<IntersectionID, bool>,
         break_turn_conflict_cycles: bool,
+        crosswalk_yield: Option<f64>,
     ) -> IntersectionSimState {
         let mut sim = IntersectionSimState {
             state: BTreeMap::new(),
             use_freeform_policy_everywhere,
             dont_block_the_box,
+            block_the_box_overrides,
             break_turn_conflict_cycles,
+            crosswalk_yield,
             blocked_by: BTreeSet::new(),
             events: Vec::new(),
+            actuated: BTreeMap::new(),
+            preempted: BTreeMap::new(),
+            held: BTreeMap::new(),
         };
         for i in map.all_intersections() {
             sim.state.insert(
@@ -69,7 +127,18 @@ impl IntersectionSimState {
                 },
             );
             if i.is_traffic_signal() && !use_freeform_policy_everywhere {
-                sim.update_intersection(Time::START_OF_DAY, i.id, map, scheduler);
+                let signal = map.get_traffic_signal(i.id);
+                if signal.actuated {
+                    sim.actuated.insert(
+                        i.id,
+                        ActuatedState {
+                            current_phase: 0,
+                            phase_started: Time::START_OF_DAY,
+                        },
+                    );
+                }
+                // The first update figures out when the next one should happen
+                scheduler.push(Time::START_OF_DAY, Command::UpdateIntersection(i.id));
             }
         }
         sim
@@ -92,7 +161,8 @@ impl IntersectionSimState {
     ) {
         let state = self.state.get_mut(&turn.parent).unwrap();
         assert!(state.accepted.remove(&Request { agent, turn }));
-        if map.get_t(turn).turn_type != TurnType::SharedSidewalkCorner {
+        let turn_type = map.get_t(turn).turn_type;
+        if turn_type != TurnType::SharedSidewalkCorner && turn_type != TurnType::Footbridge {
             self.wakeup_waiting(now, turn.parent, scheduler, map);
         }
         if self.break_turn_conflict_cycles {
@@ -100,6 +170,16 @@ impl IntersectionSimState {
                 retain_btreeset(&mut self.blocked_by, |(_, c)| *c != car);
             }
         }
+        if let AgentID::Car(car) = agent {
+            if self
+                .preempted
+                .get(&turn.parent)
+                .map(|p| p.car == car)
+                .unwrap_or(false)
+            {
+                self.end_preemption(now, turn.parent, scheduler, map);
+            }
+        }
     }
 
     // For deleting cars
@@ -111,6 +191,9 @@ impl IntersectionSimState {
                 retain_btreeset(&mut self.blocked_by, |(c1, c2)| *c1 != car && *c2 != car);
             }
         }
+        if let AgentID::Car(car) = agent {
+            retain_btreemap(&mut self.preempted, |_, p| p.car != car);
+        }
     }
 
     pub fn space_freed(
@@ -127,6 +210,153 @@ impl IntersectionSimState {
     // turn.
     pub fn vehicle_gone(&mut self, car: CarID) {
         retain_btreeset(&mut self.blocked_by, |(c1, c2)| *c1 != car && *c2 != car);
+        // The signal resumes its normal cycle at the next update
+        retain_btreemap(&mut self.preempted, |_, p| p.car != car);
+    }
+
+    // Called after live map edits, which might've retimed traffic signals. Actuated signals that
+    // lost the phase they were in start over, and every signal figures out its next change again.
+    pub fn handle_live_edits(&mut self, now: Time, map: &Map, scheduler: &mut Scheduler) {
+        if self.use_freeform_policy_everywhere {
+            return;
+        }
+        for i in map.all_intersections() {
+            if !i.is_traffic_signal() {
+                continue;
+            }
+            let signal = map.get_traffic_signal(i.id);
+            if signal.actuated {
+                let reset = self
+                    .actuated
+                    .get(&i.id)
+                    .map(|state| state.current_phase >= signal.phases.len())
+                    .unwrap_or(true);
+                if reset {
+                    self.actuated.insert(
+                        i.id,
+                        ActuatedState {
+                            current_phase: 0,
+                            phase_started: now,
+                        },
+                    );
+                }
+            } else {
+                self.actuated.remove(&i.id);
+            }
+            if self
+                .preempted
+                .get(&i.id)
+                .map(|p| p.phase >= signal.phases.len())
+                .unwrap_or(false)
+            {
+                self.preempted.remove(&i.id);
+            }
+            if self
+                .held
+                .get(&i.id)
+                .map(|h| h.phase >= signal.phases.len())
+                .unwrap_or(false)
+            {
+                self.held.remove(&i.id);
+            }
+            scheduler.update(now, Command::UpdateIntersection(i.id));
+        }
+    }
+
+    // An emergency vehicle is approaching a traffic signal, planning to do this turn. Jump to a
+    // phase that lets it through, and hold it there until the vehicle's done with the turn.
+    pub fn preempt_signal(
+        &mut self,
+        now: Time,
+        car: CarID,
+        turn: TurnID,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) {
+        if self.use_freeform_policy_everywhere || self.preempted.contains_key(&turn.parent) {
+            return;
+        }
+        let signal = if let Some(s) = map.maybe_get_traffic_signal(turn.parent) {
+            s
+        } else {
+            return;
+        };
+        // Prefer protecting the turn, and staying in the current phase if it's good enough
+        let current = self.current_phase_and_remaining_time(now, signal).0;
+        let candidates: Vec<usize> = std::iter::once(current)
+            .chain(0..signal.phases.len())
+            .collect();
+        let best = vec![TurnPriority::Protected, TurnPriority::Yield]
+            .into_iter()
+            .find_map(|pri| {
+                candidates
+                    .iter()
+                    .find(|idx| signal.phases[**idx].get_priority_of_turn(turn, signal) == pri)
+                    .cloned()
+            });
+        if let Some(phase) = best {
+            self.preempted.insert(signal.id, Preemption { car, phase });
+            self.wakeup_waiting(now, signal.id, scheduler, map);
+        }
+    }
+
+    // Pin a traffic signal to one phase until some time, overriding its usual timing. Emergency
+    // vehicles can still preempt it.
+    pub fn hold_signal(
+        &mut self,
+        now: Time,
+        i: IntersectionID,
+        phase: usize,
+        until: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> Result<(), String> {
+        if self.use_freeform_policy_everywhere {
+            return Err(format!("can't hold {}; traffic signals are disabled", i));
+        }
+        let signal = map
+            .maybe_get_traffic_signal(i)
+            .ok_or_else(|| format!("{} isn't a traffic signal", i))?;
+        if phase >= signal.phases.len() {
+            return Err(format!(
+                "{} only has {} phases, can't hold phase {}",
+                i,
+                signal.phases.len(),
+                phase
+            ));
+        }
+        if until <= now {
+            return Err(format!(
+                "can't hold {} until {}, it's already {}",
+                i, until, now
+            ));
+        }
+        self.held.insert(i, Hold { phase, until });
+        self.wakeup_waiting(now, i, scheduler, map);
+        scheduler.update(now, Command::UpdateIntersection(i));
+        Ok(())
+    }
+
+    fn end_preemption(
+        &mut self,
+        now: Time,
+        i: IntersectionID,
+        scheduler: &mut Scheduler,
+        map: &Map,
+    ) {
+        let preemption = self.preempted.remove(&i).unwrap();
+        // Actuated signals carry on from the held phase. Fixed-time signals just go back to their
+        // schedule.
+        if self.actuated.contains_key(&i) {
+            self.actuated.insert(
+                i,
+                ActuatedState {
+                    current_phase: preemption.phase,
+                    phase_started: now,
+                },
+            );
+        }
+        self.wakeup_waiting(now, i, scheduler, map);
     }
 
     fn wakeup_waiting(&self, now: Time, i: IntersectionID, scheduler: &mut Scheduler, map: &Map) {
@@ -152,9 +382,8 @@ impl IntersectionSimState {
                 protected.push(req);
             }
         } else if let Some(ref signal) = map.maybe_get_traffic_signal(i) {
-            let (_, phase, _) = signal.current_phase_and_remaining_time(now);
             for (req, _) in all {
-                match phase.get_priority_of_turn(req.turn, signal) {
+                match self.signal_priority(now, signal, req.turn, map) {
                     TurnPriority::Protected => {
                         protected.push(req);
                     }
@@ -195,17 +424,193 @@ impl IntersectionSimState {
 
     // This is only triggered for traffic signals.
     pub fn update_intersection(
-        &self,
+        &mut self,
         now: Time,
         id: IntersectionID,
         map: &Map,
         scheduler: &mut Scheduler,
+        driving: &DrivingSimState,
+        detectors: &DetectorSimState,
     ) {
+        let signal = map.get_traffic_signal(id);
+        if self.held.get(&id).map(|h| now >= h.until).unwrap_or(false) {
+            let hold = self.held.remove(&id).unwrap();
+            // Like after a preemption, actuated signals carry on from the held phase
+            if self.actuated.contains_key(&id) {
+                self.actuated.insert(
+                    id,
+                    ActuatedState {
+                        current_phase: hold.phase,
+                        phase_started: now,
+                    },
+                );
+            }
+        }
+        let mut next_update = if self.preempted.contains_key(&id) {
+            // Keep checking, in case the emergency vehicle vanishes without finishing its turn
+            now + ACTUATED_EXTENSION
+        } else if let Some(hold) = self.held.get(&id) {
+            hold.until
+        } else if self.actuated.contains_key(&id) {
+            self.update_actuated_signal(now, signal, map, driving, detectors)
+        } else {
+            let (_, _, remaining) = signal.current_phase_and_remaining_time(now);
+            now + remaining
+        };
+        // Let the vehicles go once the pedestrians' head start is over
+        let (_, phase, remaining) = self.current_phase_and_remaining_time(now, signal);
+        if let Some(left) = signal.leading_pedestrian_interval_left(phase, remaining) {
+            if now + left < next_update {
+                next_update = now + left;
+            }
+        }
         self.wakeup_waiting(now, id, scheduler, map);
-        let (_, _, remaining) = map
-            .get_traffic_signal(id)
-            .current_phase_and_remaining_time(now);
-        scheduler.push(now + remaining, Command::UpdateIntersection(id));
+        scheduler.push(next_update, Command::UpdateIntersection(id));
+    }
+
+    // Decide if an actuated signal should move on from its current phase. Returns when to check
+    // again.
+    fn update_actuated_signal(
+        &mut self,
+        now: Time,
+        signal: &ControlTrafficSignal,
+        map: &Map,
+        driving: &DrivingSimState,
+        detectors: &DetectorSimState,
+    ) -> Time {
+        let current_phase = self.actuated[&signal.id].current_phase;
+        let phase_started = self.actuated[&signal.id].phase_started;
+        let phase = &signal.phases[current_phase];
+        if now < phase_started + phase.min_green {
+            return phase_started + phase.min_green;
+        }
+        let max_end = phase_started + phase.max_green;
+
+        // Skip over phases that nobody's waiting for
+        let num_phases = signal.phases.len();
+        let next_phase = (1..num_phases)
+            .map(|x| (current_phase + x) % num_phases)
+            .find(|idx| self.has_demand(now, signal, *idx, map, driving, detectors));
+        if let Some(idx) = next_phase {
+            if now >= max_end
+                || !self.has_demand(now, signal, current_phase, map, driving, detectors)
+            {
+                self.actuated.insert(
+                    signal.id,
+                    ActuatedState {
+                        current_phase: idx,
+                        phase_started: now,
+                    },
+                );
+                return now + signal.phases[idx].min_green;
+            }
+        }
+
+        // Extend the green, up to the max. If nobody else is waiting, just rest here.
+        if now < max_end && now + ACTUATED_EXTENSION > max_end {
+            max_end
+        } else {
+            now + ACTUATED_EXTENSION
+        }
+    }
+
+    fn has_demand(
+        &self,
+        now: Time,
+        signal: &ControlTrafficSignal,
+        idx: usize,
+        map: &Map,
+        driving: &DrivingSimState,
+        detectors: &DetectorSimState,
+    ) -> bool {
+        let phase = &signal.phases[idx];
+        // Placed detectors take over from the default ones for any phase they serve
+        let placed: Vec<&Detector> = detectors
+            .approaching(signal.id, map)
+            .into_iter()
+            .filter(|d| d.serves_phase(phase, signal, map))
+            .collect();
+        let occupied = if placed.is_empty() {
+            Detector::for_phase(phase, signal, map)
+                .into_iter()
+                .any(|d| d.is_occupied(now, driving))
+        } else {
+            placed.into_iter().any(|d| d.is_occupied(now, driving))
+        };
+        if occupied {
+            return true;
+        }
+        // Pedestrians waiting to cross act like they've pressed the button
+        self.state[&signal.id]
+            .waiting
+            .keys()
+            .any(|req| match req.agent {
+                AgentID::Pedestrian(_) => {
+                    phase.get_priority_of_turn(req.turn, signal) != TurnPriority::Banned
+                }
+                _ => false,
+            })
+    }
+
+    // Like the current phase's priority for the turn, except vehicles are held during a leading
+    // pedestrian interval and can't start once the light turns yellow
+    fn signal_priority(
+        &self,
+        now: Time,
+        signal: &ControlTrafficSignal,
+        turn: TurnID,
+        map: &Map,
+    ) -> TurnPriority {
+        let (_, phase, remaining) = self.current_phase_and_remaining_time(now, signal);
+        if !map.get_t(turn).between_sidewalks()
+            && (signal
+                .leading_pedestrian_interval_left(phase, remaining)
+                .is_some()
+                || signal.is_yellow(phase, remaining))
+        {
+            return TurnPriority::Banned;
+        }
+        phase.get_priority_of_turn(turn, signal)
+    }
+
+    pub fn current_phase_and_remaining_time<'a>(
+        &self,
+        now: Time,
+        signal: &'a ControlTrafficSignal,
+    ) -> (usize, &'a Phase, Duration) {
+        if let Some(p) = self.preempted.get(&signal.id) {
+            let phase = &signal.phases[p.phase];
+            // Pretend the phase just got past any leading pedestrian interval, so vehicles can go
+            // right away and have plenty of time left
+            return (
+                p.phase,
+                phase,
+                signal.max_phase_length(phase) - phase.leading_pedestrian_interval,
+            );
+        }
+        if let Some(hold) = self.held.get(&signal.id) {
+            let phase = &signal.phases[hold.phase];
+            return (
+                hold.phase,
+                phase,
+                (signal.max_phase_length(phase) - phase.leading_pedestrian_interval)
+                    .min(hold.until - now),
+            );
+        }
+        if let Some(state) = self.actuated.get(&signal.id) {
+            let phase = &signal.phases[state.current_phase];
+            // The phase could end at any check after the min green, but it's guaranteed to go
+            // until then. Past the max green, it's resting until somebody else shows up.
+            let max_end = state.phase_started + phase.max_green;
+            let remaining = if now < max_end {
+                max_end - now
+            } else {
+                ACTUATED_EXTENSION
+            };
+            (state.current_phase, phase, remaining)
+        } else {
+            signal.current_phase_and_remaining_time(now)
+        }
     }
 
     // For cars: The head car calls this when they're at the end of the lane WaitingToAdvance. If
@@ -238,11 +643,19 @@ impl IntersectionSimState {
             .or_insert(now);
 
         let readonly_pair = maybe_cars_and_queues.as_ref().map(|(_, c, q)| (*c, &**q));
+        let mut at_stop_sign = false;
         let allowed = if self.use_freeform_policy_everywhere {
             self.freeform_policy(&req, map, readonly_pair)
+        } else if map.get_parent(turn.src).consolidated_junction
+            && !map.get_t(turn).between_sidewalks()
+        {
+            // Vehicles on a road inside a consolidated junction already got through its stop sign
+            // or signal on the way in, so they just have to avoid conflicts.
+            self.freeform_policy(&req, map, readonly_pair)
         } else if let Some(ref signal) = map.maybe_get_traffic_signal(turn.parent) {
             self.traffic_signal_policy(&req, map, signal, speed, now, scheduler, readonly_pair)
         } else if let Some(ref sign) = map.maybe_get_stop_sign(turn.parent) {
+            at_stop_sign = true;
             self.stop_sign_policy(&req, map, sign, now, scheduler, readonly_pair)
         } else {
             unreachable!()
@@ -255,12 +668,10 @@ impl IntersectionSimState {
         if let Some((car, _, queues)) = maybe_cars_and_queues {
             assert_eq!(agent, AgentID::Car(car.vehicle.id));
             let queue = queues.get_mut(&Traversable::Lane(turn.dst)).unwrap();
-            if !queue.try_to_reserve_entry(
-                car,
-                !self.dont_block_the_box
-                    || allow_block_the_box(map.get_i(turn.parent).orig_id.osm_node_id),
-            ) {
-                if self.break_turn_conflict_cycles {
+            if !queue.try_to_reserve_entry(car, !self.enforces_dont_block_the_box(turn.parent, map))
+            {
+                // Nobody in particular is to blame for a lane closed by an incident
+                if self.break_turn_conflict_cycles && !queue.blocked {
                     // TODO Should we run the detector here?
                     if let Some(c) = queue.laggy_head {
                         self.blocked_by.insert((car.vehicle.id, c));
@@ -286,11 +697,25 @@ impl IntersectionSimState {
             }
         }
 
+        // The driver is really going now, so if somebody's still waiting to cross, they didn't
+        // yield.
+        if let (true, Some(_), AgentID::Car(car)) = (at_stop_sign, self.crosswalk_yield, agent) {
+            if let Some(ped) = self.waiting_to_cross(&req, map) {
+                self.events
+                    .push(Event::FailedToYield(car, ped, req.turn.parent));
+            }
+        }
+
         // TODO For now, we're only interested in signals, and there's too much raw data to store
-        // for stop signs too.
+        // for stop signs too. The exception is pedestrians crossing when drivers are modeled
+        // yielding to them, to see how long they wait.
         let state = self.state.get_mut(&turn.parent).unwrap();
         let delay = now - state.waiting.remove(&req).unwrap();
-        if map.maybe_get_traffic_signal(state.id).is_some() {
+        let crossing = match map.get_t(turn).turn_type {
+            TurnType::Crosswalk | TurnType::DiagonalCrosswalk => self.crosswalk_yield.is_some(),
+            _ => false,
+        };
+        if map.maybe_get_traffic_signal(state.id).is_some() || crossing {
             self.events.push(Event::IntersectionDelayMeasured(
                 turn.parent,
                 delay,
@@ -307,6 +732,27 @@ impl IntersectionSimState {
         true
     }
 
+    // Do vehicles wait for room in their target lane before entering this intersection?
+    pub fn enforces_dont_block_the_box(&self, i: IntersectionID, map: &Map) -> bool {
+        if let Some(enforce) = self.block_the_box_overrides.get(&i) {
+            return *enforce;
+        }
+        self.dont_block_the_box && !allow_block_the_box(map.get_i(i).orig_id.osm_node_id)
+    }
+
+    pub fn set_dont_block_the_box(
+        &mut self,
+        now: Time,
+        i: IntersectionID,
+        enforce: bool,
+        scheduler: &mut Scheduler,
+        map: &Map,
+    ) {
+        self.block_the_box_overrides.insert(i, enforce);
+        // Anybody stuck waiting for room might be able to go now.
+        self.wakeup_waiting(now, i, scheduler, map);
+    }
+
     pub fn debug(&self, id: IntersectionID, map: &Map) {
         println!("{}", abstutil::to_json(&self.state[&id]));
         if let Some(ref sign) = map.maybe_get_stop_sign(id) {
@@ -449,9 +895,32 @@ impl IntersectionSimState {
         // TODO Make sure we can optimistically finish this turn before an approaching
         // higher-priority vehicle wants to begin.
 
+        if let (Some(compliance), AgentID::Car(car)) = (self.crosswalk_yield, req.agent) {
+            // Like the wheelchair share, the same drivers always yield. The ones who don't are
+            // caught in maybe_start_turn, once it's certain they're going.
+            if ((car.0 % 100) as f64) < compliance * 100.0
+                && self.waiting_to_cross(req, map).is_some()
+            {
+                // Once the pedestrian finishes crossing, everybody waiting gets woken up.
+                return false;
+            }
+        }
+
         true
     }
 
+    // Somebody waiting to use a crosswalk that this turn would cut across
+    fn waiting_to_cross(&self, req: &Request, map: &Map) -> Option<PedestrianID> {
+        let turn = map.get_t(req.turn);
+        self.state[&req.turn.parent]
+            .waiting
+            .keys()
+            .find_map(|r| match r.agent {
+                AgentID::Pedestrian(p) if map.get_t(r.turn).conflicts_with(turn) => Some(p),
+                _ => None,
+            })
+    }
+
     fn traffic_signal_policy(
         &mut self,
         req: &Request,
@@ -464,15 +933,17 @@ impl IntersectionSimState {
     ) -> bool {
         let turn = map.get_t(req.turn);
 
-        // SharedSidewalkCorner doesn't conflict with anything -- fastpath!
-        if turn.turn_type == TurnType::SharedSidewalkCorner {
+        // SharedSidewalkCorner and Footbridge don't conflict with anything -- fastpath!
+        if turn.turn_type == TurnType::SharedSidewalkCorner
+            || turn.turn_type == TurnType::Footbridge
+        {
             return true;
         }
 
-        let (_, phase, remaining_phase_time) = signal.current_phase_and_remaining_time(now);
+        let (_, phase, remaining_phase_time) = self.current_phase_and_remaining_time(now, signal);
 
         // Can't go at all this phase.
-        let our_priority = phase.get_priority_of_turn(req.turn, signal);
+        let our_priority = self.signal_priority(now, signal, req.turn, map);
         if our_priority == TurnPriority::Banned {
             return false;
         }
@@ -506,7 +977,12 @@ impl IntersectionSimState {
         // Optimistically if nobody else is in the way, this is how long it'll take to finish the
         // turn. Don't start the turn if we won't finish by the time the light changes. If we get
         // it wrong, that's fine -- block the box a bit.
-        let time_to_cross = turn.geom.length() / speed;
+        // Pedestrians only have to make it to a refuge island, if there is one.
+        let time_to_cross = if map.crossing_has_refuge(turn.id) {
+            turn.geom.length() / 2.0 / speed
+        } else {
+            turn.geom.length() / speed
+        };
         if time_to_cross > remaining_phase_time {
             // Actually, we might have bigger problems...
             if time_to_cross > phase.duration {
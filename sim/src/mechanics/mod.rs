@@ -1,10 +1,12 @@
 mod car;
+mod detector;
 mod driving;
 mod intersection;
 mod parking;
 mod queue;
 mod walking;
 
+pub use self::detector::{Detector, DetectorSimState};
 pub use self::driving::DrivingSimState;
 pub use self::intersection::IntersectionSimState;
 pub use self::parking::ParkingSimState;
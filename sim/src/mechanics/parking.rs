@@ -3,13 +3,20 @@ use abstutil::{
     deserialize_btreemap, deserialize_multimap, serialize_btreemap, serialize_multimap, MultiMap,
     Timer,
 };
-use geom::{Distance, PolyLine, Pt2D};
+use geom::{Distance, Duration, PolyLine, Pt2D, Time};
 use map_model::{
     BuildingID, Lane, LaneID, LaneType, Map, ParkingLotID, PathConstraints, PathStep, Position,
     Traversable, TurnID,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet};
+
+// How drivers trade off parking rules against distance. Every dollar per hour is worth driving (and
+// later walking) another 50m.
+const DISTANCE_PER_CENT_PER_HOUR: Distance = Distance::const_meters(0.5);
+// Drivers don't know how long they'll stay, so any limit this short is a nuisance.
+const SHORT_TIME_LIMIT: Duration = Duration::const_seconds(2.0 * 3600.0);
+const SHORT_TIME_LIMIT_PENALTY: Distance = Distance::const_meters(100.0);
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub struct ParkingSimState {
@@ -87,11 +94,11 @@ impl ParkingSimState {
         }
         for pl in map.all_parking_lots() {
             // TODO Parking lots without any spots shouldn't be possible
-            if pl.spots.is_empty() {
+            if pl.capacity() == 0 {
                 continue;
             }
             if map.get_l(pl.driving_pos.lane()).parking_blackhole.is_none() {
-                sim.num_spots_per_lot.insert(pl.id, pl.spots.len());
+                sim.num_spots_per_lot.insert(pl.id, pl.capacity());
                 sim.driving_to_lots.insert(pl.driving_pos.lane(), pl.id);
             }
         }
@@ -191,7 +198,7 @@ impl ParkingSimState {
         for pl in self.driving_to_lots.get(id) {
             for idx in 0..self.num_spots_per_lot[&pl] {
                 if let Some(car) = self.occupants.get(&ParkingSpot::Lot(*pl, idx)) {
-                    cars.push(self.get_draw_car(*car, map).unwrap());
+                    cars.extend(self.get_draw_car(*car, map));
                 }
             }
         }
@@ -219,7 +226,8 @@ impl ParkingSimState {
             ParkingSpot::Offstreet(_, _) => None,
             ParkingSpot::Lot(pl, idx) => {
                 let pl = map.get_pl(pl);
-                let (pt, angle) = pl.spots[idx];
+                // Extra spots aren't drawn
+                let (pt, angle) = *pl.spots.get(idx)?;
                 let buffer = Distance::meters(0.5);
                 Some(DrawCarInput {
                     id: p.vehicle.id,
@@ -242,9 +250,12 @@ impl ParkingSimState {
     pub fn canonical_pt(&self, id: CarID, map: &Map) -> Option<Pt2D> {
         let p = self.parked_cars.get(&id)?;
         match p.spot {
-            ParkingSpot::Onstreet(_, _) | ParkingSpot::Lot(_, _) => {
-                self.get_draw_car(id, map).map(|c| c.body.last_pt())
-            }
+            ParkingSpot::Onstreet(_, _) => self.get_draw_car(id, map).map(|c| c.body.last_pt()),
+            ParkingSpot::Lot(pl, _) => Some(
+                self.get_draw_car(id, map)
+                    .map(|c| c.body.last_pt())
+                    .unwrap_or_else(|| map.get_pl(pl).polygon.center()),
+            ),
             ParkingSpot::Offstreet(b, _) => Some(map.get_b(b).label_center),
         }
     }
@@ -274,11 +285,15 @@ impl ParkingSimState {
         // Either the building where a seeded car starts or the target of a trip. For filtering
         // private spots.
         target: BuildingID,
+        now: Time,
         map: &Map,
     ) -> Vec<(ParkingSpot, Position)> {
         let mut candidates = Vec::new();
 
         for l in self.driving_to_parking_lanes.get(driving_pos.lane()) {
+            if map.get_l(*l).parking_restrictions.is_banned(now) {
+                continue;
+            }
             let parking_dist = driving_pos
                 .equiv_pos(*l, driving_pos.dist_along(), map)
                 .dist_along();
@@ -325,6 +340,30 @@ impl ParkingSimState {
             .collect()
     }
 
+    // Is there any public parking reachable from this driving lane?
+    pub fn has_parking_along(&self, driving_lane: LaneID) -> bool {
+        !self.driving_to_parking_lanes.get(driving_lane).is_empty()
+            || !self.driving_to_lots.get(driving_lane).is_empty()
+    }
+
+    // How much farther a driver would go to avoid the rules on this spot. Only onstreet parking
+    // has rules.
+    pub fn spot_penalty(&self, spot: ParkingSpot, map: &Map) -> Distance {
+        match spot {
+            ParkingSpot::Onstreet(l, _) => {
+                let rules = &map.get_l(l).parking_restrictions;
+                let mut penalty = DISTANCE_PER_CENT_PER_HOUR * (rules.cents_per_hour as f64);
+                if let Some(max) = rules.max_duration {
+                    if max < SHORT_TIME_LIMIT {
+                        penalty += SHORT_TIME_LIMIT_PENALTY;
+                    }
+                }
+                penalty
+            }
+            ParkingSpot::Offstreet(_, _) | ParkingSpot::Lot(_, _) => Distance::ZERO,
+        }
+    }
+
     pub fn spot_to_driving_pos(&self, spot: ParkingSpot, vehicle: &Vehicle, map: &Map) -> Position {
         match spot {
             ParkingSpot::Onstreet(l, idx) => {
@@ -401,9 +440,13 @@ impl ParkingSimState {
         start: LaneID,
         vehicle: &Vehicle,
         target: BuildingID,
+        now: Time,
         map: &Map,
     ) -> Option<(Vec<PathStep>, ParkingSpot, Position)> {
         let mut backrefs: HashMap<LaneID, TurnID> = HashMap::new();
+        // Lanes whose best spot has a penalty get pushed back into the queue once, as if they were
+        // that much farther away.
+        let mut deferred: HashSet<LaneID> = HashSet::new();
         // Don't travel far.
         // This is a max-heap, so negate all distances. Tie breaker is lane ID, arbitrary but
         // deterministic.
@@ -422,27 +465,35 @@ impl ParkingSimState {
                         Position::new(current, Distance::ZERO),
                         vehicle,
                         target,
+                        now,
                         map,
                     )
                     .into_iter()
-                    .min_by_key(|(_, pos)| pos.dist_along())
+                    .min_by_key(|(spot, pos)| pos.dist_along() + self.spot_penalty(*spot, map))
                 {
-                    let mut steps = vec![PathStep::Lane(current)];
-                    let mut current = current;
-                    loop {
-                        if current == start {
-                            // Don't include PathStep::Lane(start)
-                            steps.pop();
-                            steps.reverse();
-                            return Some((steps, spot, pos));
+                    let penalty = self.spot_penalty(spot, map);
+                    // insert() is false if this lane was already deferred
+                    if penalty > Distance::ZERO && deferred.insert(current) {
+                        queue.push((dist_so_far - penalty, current));
+                    } else {
+                        let mut steps = vec![PathStep::Lane(current)];
+                        let mut current = current;
+                        loop {
+                            if current == start {
+                                // Don't include PathStep::Lane(start)
+                                steps.pop();
+                                steps.reverse();
+                                return Some((steps, spot, pos));
+                            }
+                            let turn = backrefs[&current];
+                            steps.push(PathStep::Turn(turn));
+                            steps.push(PathStep::Lane(turn.src));
+                            current = turn.src;
                         }
-                        let turn = backrefs[&current];
-                        steps.push(PathStep::Turn(turn));
-                        steps.push(PathStep::Lane(turn.src));
-                        current = turn.src;
                     }
                 }
             }
+            // When a deferred lane comes back around, all of these turns have already been explored
             for turn in map.get_turns_for(current, PathConstraints::Car) {
                 if !backrefs.contains_key(&turn.id.dst) {
                     let dist_this_step = turn.geom.length() + map.get_l(current).length();
@@ -1,5 +1,5 @@
 use crate::mechanics::car::{Car, CarState};
-use crate::{CarID, FOLLOWING_DISTANCE};
+use crate::{CarID, SnowConditions};
 use geom::{Distance, Time};
 use map_model::{Map, Traversable};
 use serde::{Deserialize, Serialize};
@@ -13,22 +13,32 @@ pub struct Queue {
     pub laggy_head: Option<CarID>,
 
     pub geom_len: Distance,
-    // When a car's turn is accepted, reserve the vehicle length + FOLLOWING_DISTANCE for the
+    // How much of geom_len vehicles can actually use. Less than geom_len when snow narrows the
+    // road.
+    pub capacity: Distance,
+    // When a car's turn is accepted, reserve the vehicle length + its desired gap for the
     // target lane. When the car completely leaves (stops being the laggy_head), free up that
     // space. To prevent blocking the box for possibly scary amounts of time, allocate some of this
     // length first. This is unused for turns themselves. This value can exceed geom_len (for the
     // edge case of ONE long car on a short queue).
     pub reserved_length: Distance,
+    // Closed by an incident. Nobody new can enter, and vehicles already here can't leave through
+    // the end.
+    pub blocked: bool,
 }
 
 impl Queue {
-    pub fn new(id: Traversable, map: &Map) -> Queue {
+    pub fn new(id: Traversable, map: &Map, snow: Option<SnowConditions>) -> Queue {
         Queue {
             id,
             cars: VecDeque::new(),
             laggy_head: None,
             geom_len: id.length(map),
+            capacity: snow
+                .map(|s| s.capacity_of(id, map))
+                .unwrap_or_else(|| id.length(map)),
             reserved_length: Distance::ZERO,
+            blocked: false,
         }
     }
 
@@ -56,14 +66,14 @@ impl Queue {
         let mut result: Vec<(CarID, Distance)> = Vec::new();
 
         for id in &self.cars {
+            // Everybody keeps their own distance from whoever's in front of them
+            let gap = cars[id].following.desired_gap;
             let bound = match result.last() {
-                Some((leader, last_dist)) => {
-                    *last_dist - cars[leader].vehicle.length - FOLLOWING_DISTANCE
-                }
+                Some((leader, last_dist)) => *last_dist - cars[leader].vehicle.length - gap,
                 None => match self.laggy_head {
                     Some(id) => {
                         // The simple but broken version:
-                        //self.geom_len - cars[&id].vehicle.length - FOLLOWING_DISTANCE
+                        //self.geom_len - cars[&id].vehicle.length - gap
 
                         // The expensive case. We need to figure out exactly where the laggy head
                         // is on their queue.
@@ -79,7 +89,7 @@ impl Queue {
                             // 1) Hope that the last person in this queue isn't bounded by the
                             //    agent in front of them yet. geom_len
                             // 2) Assume the leader has advanced minimally into the next lane.
-                            //    geom_len - laggy head's length - FOLLOWING_DISTANCE.
+                            //    geom_len - laggy head's length - gap.
                             //
                             // For now, optimistically assume 1. If we're wrong, consequences could
                             // be queue spillover (we're too optimistic about the number of
@@ -104,12 +114,10 @@ impl Queue {
                             }
                             // They might actually be out of the way, but laggy_head hasn't been
                             // updated yet.
-                            if dist_away_from_this_queue
-                                < leader.vehicle.length + FOLLOWING_DISTANCE
-                            {
+                            if dist_away_from_this_queue < leader.vehicle.length + gap {
                                 self.geom_len
                                     - (cars[&id].vehicle.length - dist_away_from_this_queue)
-                                    - FOLLOWING_DISTANCE
+                                    - gap
                             } else {
                                 self.geom_len
                             }
@@ -162,6 +170,7 @@ impl Queue {
         &self,
         start_dist: Distance,
         vehicle_len: Distance,
+        gap: Distance,
         now: Time,
         cars: &BTreeMap<CarID, Car>,
         queues: &BTreeMap<Traversable, Queue>,
@@ -183,14 +192,14 @@ impl Queue {
         }
 
         // Are we too close to the leader?
-        if idx != 0
-            && dists[idx - 1].1 - cars[&dists[idx - 1].0].vehicle.length - FOLLOWING_DISTANCE
-                < start_dist
+        if idx != 0 && dists[idx - 1].1 - cars[&dists[idx - 1].0].vehicle.length - gap < start_dist
         {
             return None;
         }
         // Or the follower?
-        if idx != dists.len() && start_dist - vehicle_len - FOLLOWING_DISTANCE < dists[idx].1 {
+        if idx != dists.len()
+            && start_dist - vehicle_len - cars[&dists[idx].0].following.desired_gap < dists[idx].1
+        {
             return None;
         }
 
@@ -200,12 +209,15 @@ impl Queue {
     // If true, there's room and the car must actually start the turn (because the space is
     // reserved).
     pub fn try_to_reserve_entry(&mut self, car: &Car, force_entry: bool) -> bool {
-        // Sometimes a car + FOLLOWING_DISTANCE might be longer than the geom_len entirely. In that
+        // Sometimes a car + its gap might be longer than the geom_len entirely. In that
         // case, it just means the car won't totally fit on the queue at once, which is fine.
         // Reserve the normal amount of space; the next car trying to enter will get rejected.
         // Also allow this don't-block-the-box prevention to be disabled.
-        let dist = car.vehicle.length + FOLLOWING_DISTANCE;
-        if self.reserved_length + dist < self.geom_len
+        if self.blocked {
+            return false;
+        }
+        let dist = car.vehicle.length + car.following.desired_gap;
+        if self.reserved_length + dist < self.capacity
             || self.reserved_length == Distance::ZERO
             || force_entry
         {
@@ -217,12 +229,15 @@ impl Queue {
 
     // TODO Refactor
     pub fn room_for_car(&self, car: &Car) -> bool {
+        if self.blocked {
+            return false;
+        }
         self.reserved_length == Distance::ZERO
-            || self.reserved_length + car.vehicle.length + FOLLOWING_DISTANCE < self.geom_len
+            || self.reserved_length + car.vehicle.length + car.following.desired_gap < self.capacity
     }
 
     pub fn free_reserved_space(&mut self, car: &Car) {
-        self.reserved_length -= car.vehicle.length + FOLLOWING_DISTANCE;
+        self.reserved_length -= car.vehicle.length + car.following.desired_gap;
         assert!(self.reserved_length >= Distance::ZERO);
     }
 }
@@ -234,7 +249,9 @@ fn validate_positions(
     id: Traversable,
 ) -> Vec<(CarID, Distance)> {
     for pair in dists.windows(2) {
-        if pair[0].1 - cars[&pair[0].0].vehicle.length - FOLLOWING_DISTANCE < pair[1].1 {
+        if pair[0].1 - cars[&pair[0].0].vehicle.length - cars[&pair[1].0].following.desired_gap
+            < pair[1].1
+        {
             dump_cars(&dists, cars, id, now);
             panic!(
                 "get_car_positions wound up with bad positioning: {} then {}\n{:?}",
@@ -1,13 +1,15 @@
 use crate::{
-    AgentID, AgentProperties, Command, CreatePedestrian, DistanceInterval, DrawPedCrowdInput,
-    DrawPedestrianInput, Event, IntersectionSimState, ParkingSimState, ParkingSpot,
-    PedCrowdLocation, PedestrianID, PersonID, Scheduler, SidewalkPOI, SidewalkSpot, TimeInterval,
-    TransitSimState, TripID, TripManager, TripPositions, UnzoomedAgent,
+    midblock_crossing, AgentID, AgentProperties, Command, CreatePedestrian, DistanceInterval,
+    DrawPedCrowdInput, DrawPedestrianInput, DrivingSimState, Event, IntersectionSimState,
+    JaywalkingConfig, ParkingSimState, ParkingSpot, PedCrowdLocation, PedestrianID, PersonID,
+    Scheduler, SidewalkPOI, SidewalkSpot, TimeInterval, TransitSimState, TripID, TripManager,
+    TripPositions, UnzoomedAgent, Weather,
 };
 use abstutil::{deserialize_multimap, serialize_multimap, MultiMap};
 use geom::{Distance, Duration, Line, PolyLine, Speed, Time};
 use map_model::{
-    BuildingID, BusRouteID, Map, ParkingLotID, Path, PathStep, Traversable, SIDEWALK_THICKNESS,
+    BuildingID, BusRouteID, Map, ParkingLotID, Path, PathStep, Position, Traversable,
+    SIDEWALK_THICKNESS,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -25,17 +27,34 @@ pub struct WalkingSimState {
     )]
     peds_per_traversable: MultiMap<Traversable, PedestrianID>,
     events: Vec<Event>,
+    #[serde(default)]
+    weather: Weather,
+    // None means pedestrians only cross roads at crosswalks
+    #[serde(default)]
+    jaywalking: Option<JaywalkingConfig>,
 }
 
 impl WalkingSimState {
-    pub fn new() -> WalkingSimState {
+    pub fn new(jaywalking: Option<JaywalkingConfig>) -> WalkingSimState {
         WalkingSimState {
             peds: BTreeMap::new(),
             peds_per_traversable: MultiMap::new(),
             events: Vec::new(),
+            weather: Weather::default(),
+            jaywalking,
         }
     }
 
+    // Pedestrians already partway along something keep their old speed until they reach the next
+    // sidewalk or crosswalk.
+    pub fn set_weather(&mut self, weather: Weather) {
+        self.weather = weather;
+    }
+
+    pub fn get_weather(&self) -> &Weather {
+        &self.weather
+    }
+
     pub fn spawn_ped(
         &mut self,
         now: Time,
@@ -84,7 +103,12 @@ impl WalkingSimState {
                 Line::new(driving_pos.pt(map), params.start.sidewalk_pos.pt(map)),
                 TimeInterval::new(now, now + TIME_TO_FINISH_BIKING),
             ),
-            _ => ped.crossing_state(params.start.sidewalk_pos.dist_along(), now, map),
+            _ => ped.crossing_state(
+                params.start.sidewalk_pos.dist_along(),
+                now,
+                map,
+                &self.weather,
+            ),
         };
 
         scheduler.push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
@@ -121,6 +145,7 @@ impl WalkingSimState {
         scheduler: &mut Scheduler,
         trips: &mut TripManager,
         transit: &mut TransitSimState,
+        driving: &mut DrivingSimState,
     ) {
         let mut ped = self.peds.get_mut(&id).unwrap();
         match ped.state {
@@ -225,6 +250,8 @@ impl WalkingSimState {
                         &mut self.peds_per_traversable,
                         &mut self.events,
                         scheduler,
+                        &self.weather,
+                        trips,
                     ) {
                         scheduler.push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
                     } else {
@@ -241,15 +268,24 @@ impl WalkingSimState {
                     &mut self.peds_per_traversable,
                     &mut self.events,
                     scheduler,
+                    &self.weather,
+                    trips,
                 ) {
                     scheduler.push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
                     ped.total_blocked_time += now - blocked_since;
                 }
             }
             PedState::LeavingBuilding(b, _) => {
-                ped.state =
-                    ped.crossing_state(map.get_b(b).front_path.sidewalk.dist_along(), now, map);
-                scheduler.push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
+                ped.start_along_sidewalk(
+                    map.get_b(b).front_path.sidewalk.dist_along(),
+                    now,
+                    map,
+                    &self.weather,
+                    &self.jaywalking,
+                    driving,
+                    &mut self.events,
+                    scheduler,
+                );
             }
             PedState::EnteringBuilding(bldg, _) => {
                 self.peds_per_traversable
@@ -266,8 +302,16 @@ impl WalkingSimState {
                 self.peds.remove(&id);
             }
             PedState::LeavingParkingLot(pl, _) => {
-                ped.state = ped.crossing_state(map.get_pl(pl).sidewalk_pos.dist_along(), now, map);
-                scheduler.push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
+                ped.start_along_sidewalk(
+                    map.get_pl(pl).sidewalk_pos.dist_along(),
+                    now,
+                    map,
+                    &self.weather,
+                    &self.jaywalking,
+                    driving,
+                    &mut self.events,
+                    scheduler,
+                );
             }
             PedState::EnteringParkingLot(_, _) => {
                 self.peds_per_traversable
@@ -301,10 +345,45 @@ impl WalkingSimState {
                 self.peds.remove(&id);
             }
             PedState::FinishingBiking(ref spot, _, _) => {
-                ped.state = ped.crossing_state(spot.sidewalk_pos.dist_along(), now, map);
-                scheduler.push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
+                let dist = spot.sidewalk_pos.dist_along();
+                ped.start_along_sidewalk(
+                    dist,
+                    now,
+                    map,
+                    &self.weather,
+                    &self.jaywalking,
+                    driving,
+                    &mut self.events,
+                    scheduler,
+                );
             }
             PedState::WaitingForBus(_, _) => unreachable!(),
+            PedState::WaitingToJaywalk(_, _) => {
+                ped.maybe_jaywalk(
+                    now,
+                    map,
+                    &self.weather,
+                    self.jaywalking.as_ref().unwrap(),
+                    driving,
+                    &mut self.events,
+                    scheduler,
+                );
+            }
+            PedState::Jaywalking(_, to, _, _) => {
+                // Skip the crosswalk and land on the other side of the road
+                self.peds_per_traversable
+                    .remove(ped.path.current_step().as_traversable(), ped.id);
+                ped.path.shift(map);
+                ped.path.shift(map);
+                self.peds_per_traversable
+                    .insert(ped.path.current_step().as_traversable(), ped.id);
+                self.events.push(Event::AgentEntersTraversable(
+                    AgentID::Pedestrian(ped.id),
+                    ped.path.current_step().as_traversable(),
+                ));
+                ped.state = ped.crossing_state(to, now, map, &self.weather);
+                scheduler.push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
+            }
         }
     }
 
@@ -332,7 +411,8 @@ impl WalkingSimState {
         let p = &self.peds[&id];
         let time_spent_waiting = match p.state {
             PedState::WaitingToTurn(_, blocked_since)
-            | PedState::WaitingForBus(_, blocked_since) => now - blocked_since,
+            | PedState::WaitingForBus(_, blocked_since)
+            | PedState::WaitingToJaywalk(_, blocked_since) => now - blocked_since,
             _ => Duration::ZERO,
         };
 
@@ -366,6 +446,34 @@ impl WalkingSimState {
         p.path.trace(map, dist, dist_ahead)
     }
 
+    // Estimates when a pedestrian reaches the end of their current path at their own walking
+    // speed. Time spent waiting to cross isn't counted.
+    pub fn predict_arrival(&self, id: PedestrianID, now: Time, map: &Map) -> Option<Time> {
+        let ped = self.peds.get(&id)?;
+        let steps = ped.path.get_steps();
+        let mut total = Duration::ZERO;
+        for (idx, step) in steps.iter().enumerate() {
+            if idx == 0 {
+                total += match ped.state {
+                    PedState::WaitingToTurn(_, _)
+                    | PedState::WaitingForBus(_, _)
+                    | PedState::WaitingToJaywalk(_, _) => Duration::ZERO,
+                    // Crossing always goes to the end of the current step
+                    _ => (ped.state.get_end_time() - now).max(Duration::ZERO),
+                };
+            } else if idx == steps.len() - 1 {
+                let dist = match step {
+                    PathStep::ContraflowLane(l) => map.get_l(*l).length() - ped.path.end_dist(),
+                    _ => ped.path.end_dist(),
+                };
+                total += dist / ped.speed;
+            } else {
+                total += step.as_traversable().length(map) / ped.speed;
+            }
+        }
+        Some(now + total)
+    }
+
     pub fn get_path(&self, id: PedestrianID) -> Option<&Path> {
         let p = self.peds.get(&id)?;
         Some(&p.path)
@@ -375,10 +483,12 @@ impl WalkingSimState {
         let mut peds = Vec::new();
 
         for ped in self.peds.values() {
+            let draw = ped.get_draw_ped(now, map);
             peds.push(UnzoomedAgent {
                 vehicle_type: None,
-                pos: ped.get_draw_ped(now, map).pos,
+                pos: draw.pos,
                 person: Some(ped.person),
+                on: draw.on,
             });
         }
 
@@ -408,12 +518,17 @@ impl WalkingSimState {
         let mut backwards: Vec<(PedestrianID, Distance)> = Vec::new();
         let mut bldg_front_path: MultiMap<BuildingID, (PedestrianID, Distance)> = MultiMap::new();
         let mut lot_front_path: MultiMap<ParkingLotID, (PedestrianID, Distance)> = MultiMap::new();
+        let mut loners: Vec<DrawPedestrianInput> = Vec::new();
 
         for id in self.peds_per_traversable.get(on) {
             let ped = &self.peds[id];
             let dist = ped.get_dist_along(now, map);
 
             match ped.state {
+                PedState::Jaywalking(_, _, _, _) => {
+                    // Out in the road, not in any group on the sidewalk
+                    loners.push(ped.get_draw_ped(now, map));
+                }
                 PedState::Crossing(ref dist_int, _) => {
                     if dist_int.start < dist_int.end {
                         forwards.push((*id, dist));
@@ -446,7 +561,8 @@ impl WalkingSimState {
                 }
                 PedState::StartingToBike(_, _, _)
                 | PedState::FinishingBiking(_, _, _)
-                | PedState::WaitingForBus(_, _) => {
+                | PedState::WaitingForBus(_, _)
+                | PedState::WaitingToJaywalk(_, _) => {
                     // The backwards half of the sidewalk is closer to the road.
                     backwards.push((*id, dist));
                 }
@@ -454,7 +570,6 @@ impl WalkingSimState {
         }
 
         let mut crowds: Vec<DrawPedCrowdInput> = Vec::new();
-        let mut loners: Vec<DrawPedestrianInput> = Vec::new();
 
         // For each group, sort by distance along. Attempt to bundle into intervals.
         for (mut group, location, on_len) in vec![
@@ -528,7 +643,13 @@ struct Pedestrian {
 }
 
 impl Pedestrian {
-    fn crossing_state(&self, start_dist: Distance, start_time: Time, map: &Map) -> PedState {
+    fn crossing_state(
+        &self,
+        start_dist: Distance,
+        start_time: Time,
+        map: &Map,
+        weather: &Weather,
+    ) -> PedState {
         let end_dist = if self.path.is_last_step() {
             self.goal.sidewalk_pos.dist_along()
         } else {
@@ -540,7 +661,9 @@ impl Pedestrian {
             }
         };
         let dist_int = DistanceInterval::new_walking(start_dist, end_dist);
-        let time_int = TimeInterval::new(start_time, start_time + dist_int.length() / self.speed);
+        let speed = self.speed
+            * weather.walking_factor(self.path.current_step().as_traversable(), map, start_time);
+        let time_int = TimeInterval::new(start_time, start_time + dist_int.length() / speed);
         PedState::Crossing(dist_int, time_int)
     }
 
@@ -557,6 +680,7 @@ impl Pedestrian {
             PedState::StartingToBike(ref spot, _, _) => spot.sidewalk_pos.dist_along(),
             PedState::FinishingBiking(ref spot, _, _) => spot.sidewalk_pos.dist_along(),
             PedState::WaitingForBus(_, _) => self.goal.sidewalk_pos.dist_along(),
+            PedState::WaitingToJaywalk(dist, _) | PedState::Jaywalking(dist, _, _, _) => dist,
         }
     }
 
@@ -634,6 +758,20 @@ impl Pedestrian {
             PedState::FinishingBiking(_, ref line, ref time_int) => {
                 (line.percent_along(time_int.percent(now)), line.angle())
             }
+            PedState::WaitingToJaywalk(dist, _) => {
+                // Stand at the curb, facing the road
+                let (pt, angle) = on.dist_along(dist, map);
+                (
+                    pt.project_away(
+                        SIDEWALK_THICKNESS / 4.0,
+                        map.driving_side_angle(angle.rotate_degs(-90.0)),
+                    ),
+                    map.driving_side_angle(angle.rotate_degs(-90.0)),
+                )
+            }
+            PedState::Jaywalking(_, _, ref line, ref time_int) => {
+                (line.percent_along(time_int.percent(now)), line.angle())
+            }
             PedState::WaitingForBus(_, _) => {
                 let (pt, angle) = self.goal.sidewalk_pos.pt_and_angle(map);
                 // Stand on the far side of the sidewalk (by the bus stop), facing the road
@@ -661,6 +799,77 @@ impl Pedestrian {
         }
     }
 
+    // Right after stepping onto the sidewalk, somebody who's about to walk to the corner just to
+    // cross this road and double back might cut straight across instead.
+    fn start_along_sidewalk(
+        &mut self,
+        dist: Distance,
+        now: Time,
+        map: &Map,
+        weather: &Weather,
+        jaywalking: &Option<JaywalkingConfig>,
+        driving: &mut DrivingSimState,
+        events: &mut Vec<Event>,
+        scheduler: &mut Scheduler,
+    ) {
+        if let Some(ref cfg) = jaywalking {
+            let pos = Position::new(self.path.current_step().as_lane(), dist);
+            if midblock_crossing(&self.path, pos, map).is_some()
+                && cfg.would_jaywalk(self.id, map.get_parent(pos.lane()))
+            {
+                self.state = PedState::WaitingToJaywalk(dist, now);
+                self.maybe_jaywalk(now, map, weather, cfg, driving, events, scheduler);
+                return;
+            }
+        }
+        self.state = self.crossing_state(dist, now, map, weather);
+        scheduler.push(self.state.get_end_time(), Command::UpdatePed(self.id));
+    }
+
+    // Cross if there's a gap, keep waiting, or give up and walk to the crosswalk.
+    fn maybe_jaywalk(
+        &mut self,
+        now: Time,
+        map: &Map,
+        weather: &Weather,
+        cfg: &JaywalkingConfig,
+        driving: &mut DrivingSimState,
+        events: &mut Vec<Event>,
+        scheduler: &mut Scheduler,
+    ) {
+        let (dist, blocked_since) = match self.state {
+            PedState::WaitingToJaywalk(dist, blocked_since) => (dist, blocked_since),
+            _ => unreachable!(),
+        };
+        let from = Position::new(self.path.current_step().as_lane(), dist);
+        let to = midblock_crossing(&self.path, from, map).unwrap();
+        let line = match Line::maybe_new(from.pt(map), to.pt(map)) {
+            Some(l) => l,
+            None => {
+                self.state = self.crossing_state(dist, now, map, weather);
+                scheduler.push(self.state.get_end_time(), Command::UpdatePed(self.id));
+                return;
+            }
+        };
+        let speed = self.speed * weather.walking_factor(Traversable::Lane(from.lane()), map, now);
+        let until = now + line.length() / speed;
+
+        if driving.gap_for_midblock_crossing(from, until + cfg.min_gap, now, map) {
+            driving.ped_crossing_midblock(self.id, from, until, map);
+            events.push(Event::PedJaywalked(self.id, map.get_l(from.lane()).parent));
+            self.total_blocked_time += now - blocked_since;
+            self.state =
+                PedState::Jaywalking(dist, to.dist_along(), line, TimeInterval::new(now, until));
+            scheduler.push(until, Command::UpdatePed(self.id));
+        } else if now - blocked_since >= cfg.max_wait {
+            self.total_blocked_time += now - blocked_since;
+            self.state = self.crossing_state(dist, now, map, weather);
+            scheduler.push(self.state.get_end_time(), Command::UpdatePed(self.id));
+        } else {
+            scheduler.push(now + cfg.retry_every, Command::UpdatePed(self.id));
+        }
+    }
+
     // True if we successfully continued to the next step of our path
     fn maybe_transition(
         &mut self,
@@ -670,6 +879,8 @@ impl Pedestrian {
         peds_per_traversable: &mut MultiMap<Traversable, PedestrianID>,
         events: &mut Vec<Event>,
         scheduler: &mut Scheduler,
+        weather: &Weather,
+        trips: &mut TripManager,
     ) -> bool {
         if let PathStep::Turn(t) = self.path.next_step() {
             if !intersections.maybe_start_turn(
@@ -692,7 +903,19 @@ impl Pedestrian {
             PathStep::ContraflowLane(l) => map.get_l(l).length(),
             PathStep::Turn(_) => Distance::ZERO,
         };
-        self.state = self.crossing_state(start_dist, now, map);
+        // Reaching a new sidewalk is a chance to notice a free scooter nearby
+        match self.path.current_step() {
+            PathStep::Lane(l) | PathStep::ContraflowLane(l) => {
+                if let Some((goal, path)) =
+                    trips.ped_maybe_switch_to_scooter(self.id, Position::new(l, start_dist), map)
+                {
+                    self.goal = goal;
+                    self.path = path;
+                }
+            }
+            PathStep::Turn(_) => {}
+        }
+        self.state = self.crossing_state(start_dist, now, map, weather);
         peds_per_traversable.insert(self.path.current_step().as_traversable(), self.id);
         events.push(Event::AgentEntersTraversable(
             AgentID::Pedestrian(self.id),
@@ -714,6 +937,11 @@ enum PedState {
     StartingToBike(SidewalkSpot, Line, TimeInterval),
     FinishingBiking(SidewalkSpot, Line, TimeInterval),
     WaitingForBus(BusRouteID, Time),
+    // At this distance along the sidewalk, waiting since this time for a gap to cross mid-block
+    WaitingToJaywalk(Distance, Time),
+    // Crossing the road mid-block, from this distance along the current sidewalk to this distance
+    // along the one on the other side
+    Jaywalking(Distance, Distance, Line, TimeInterval),
 }
 
 impl PedState {
@@ -728,6 +956,8 @@ impl PedState {
             PedState::StartingToBike(_, _, ref time_int) => time_int.end,
             PedState::FinishingBiking(_, _, ref time_int) => time_int.end,
             PedState::WaitingForBus(_, _) => unreachable!(),
+            PedState::WaitingToJaywalk(_, _) => unreachable!(),
+            PedState::Jaywalking(_, _, _, ref time_int) => time_int.end,
         }
     }
 }
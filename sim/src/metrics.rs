@@ -0,0 +1,266 @@
+use crate::{AgentID, Event, ParkingSpot, TripMode};
+use geom::{Duration, Time};
+use map_model::{IntersectionID, Map, RoadID, Traversable};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// How finely a MetricSeries remembers its history
+const RESOLUTION: Duration = Duration::const_seconds(60.0);
+
+// A user-defined metric, computed over the event stream while the sim runs. These're usually
+// loaded from a JSON file with --metrics, like:
+//
+// [
+//   {
+//     "name": "cars parking along Boyer",
+//     "event": "CarParked",
+//     "roads": [123, 456]
+//   },
+//   {
+//     "name": "time bikes spend waiting at Montlake",
+//     "event": "IntersectionDelay",
+//     "modes": ["Bike"],
+//     "intersections": [42],
+//     "measure": "TotalDuration"
+//   }
+// ]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CustomMetric {
+    pub name: String,
+    pub event: MetricEvent,
+    // Each of these filters is ignored when it's empty. Events that don't happen at an
+    // intersection or road (like trips starting) never match a non-empty filter of that kind.
+    #[serde(default)]
+    pub modes: BTreeSet<TripMode>,
+    #[serde(default)]
+    pub intersections: BTreeSet<IntersectionID>,
+    #[serde(default)]
+    pub roads: BTreeSet<RoadID>,
+    #[serde(default)]
+    pub measure: Measure,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MetricEvent {
+    TripStarted,
+    TripFinished,
+    TripAborted,
+    // An agent starts a turn
+    EnteredIntersection,
+    // An agent starts a lane
+    EnteredRoad,
+    // A box truck or tractor-trailer starts a lane
+    TruckEnteredRoad,
+    CarParked,
+    CarUnparked,
+    BusArrived,
+    PassengerDeniedBoarding,
+    IntersectionDelay,
+    // A bike had to dodge the door of a parked car
+    DoorZoneConflict,
+    // A driver changed lanes to get around a bike
+    PassedBike,
+    // A driver couldn't safely pass a bike and is stuck behind it
+    StuckBehindBike,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Measure {
+    // How many matching events happened
+    Count,
+    // The sum of the duration attached to each event: total trip time for TripFinished, the delay
+    // for IntersectionDelay. Other events have no duration.
+    TotalDuration,
+}
+
+impl Default for Measure {
+    fn default() -> Measure {
+        Measure::Count
+    }
+}
+
+// The facts about one event that a CustomMetric can filter on
+pub(crate) struct MetricInput {
+    event: MetricEvent,
+    mode: Option<TripMode>,
+    intersection: Option<IntersectionID>,
+    road: Option<RoadID>,
+    duration: Duration,
+}
+
+impl MetricInput {
+    // Not every Event is interesting to metrics. new_trip says if TripPhaseStarting is the first
+    // phase of a trip.
+    pub fn from_event(ev: &Event, new_trip: bool, map: &Map) -> Option<MetricInput> {
+        let mut input = MetricInput {
+            event: MetricEvent::TripStarted,
+            mode: None,
+            intersection: None,
+            road: None,
+            duration: Duration::ZERO,
+        };
+        match ev {
+            Event::TripPhaseStarting(_, _, _, _) => {
+                if !new_trip {
+                    return None;
+                }
+            }
+            Event::TripFinished {
+                mode, total_time, ..
+            } => {
+                input.event = MetricEvent::TripFinished;
+                input.mode = Some(*mode);
+                input.duration = *total_time;
+            }
+            Event::TripAborted(_) => {
+                input.event = MetricEvent::TripAborted;
+            }
+            Event::AgentEntersTraversable(a, on) => {
+                input.mode = Some(TripMode::from_agent(*a));
+                match on {
+                    Traversable::Lane(l) => {
+                        input.event = MetricEvent::EnteredRoad;
+                        input.road = Some(map.get_l(*l).parent);
+                    }
+                    Traversable::Turn(t) => {
+                        input.event = MetricEvent::EnteredIntersection;
+                        input.intersection = Some(t.parent);
+                    }
+                }
+            }
+            Event::TruckEntersRoad(_, r) => {
+                input.event = MetricEvent::TruckEnteredRoad;
+                input.mode = Some(TripMode::Drive);
+                input.road = Some(*r);
+            }
+            Event::CarReachedParkingSpot(car, spot) | Event::CarLeftParkingSpot(car, spot) => {
+                input.event = if let Event::CarReachedParkingSpot(_, _) = ev {
+                    MetricEvent::CarParked
+                } else {
+                    MetricEvent::CarUnparked
+                };
+                input.mode = Some(TripMode::from_agent(AgentID::Car(*car)));
+                if let ParkingSpot::Onstreet(l, _) = spot {
+                    input.road = Some(map.get_l(*l).parent);
+                }
+            }
+            Event::BusArrivedAtStop(_, _, stop) => {
+                input.event = MetricEvent::BusArrived;
+                input.mode = Some(TripMode::Transit);
+                input.road = Some(map.get_parent(map.get_bs(*stop).driving_pos.lane()).id);
+            }
+            Event::PassengerDeniedBoarding(_, _, _, stop) => {
+                input.event = MetricEvent::PassengerDeniedBoarding;
+                input.mode = Some(TripMode::Transit);
+                input.road = Some(map.get_parent(map.get_bs(*stop).driving_pos.lane()).id);
+            }
+            Event::IntersectionDelayMeasured(i, delay, mode) => {
+                input.event = MetricEvent::IntersectionDelay;
+                input.mode = Some(*mode);
+                input.intersection = Some(*i);
+                input.duration = *delay;
+            }
+            Event::DoorZoneConflict(_, _, l) => {
+                input.event = MetricEvent::DoorZoneConflict;
+                input.mode = Some(TripMode::Bike);
+                input.road = Some(map.get_l(*l).parent);
+            }
+            Event::PassedBike(_, _, r) | Event::StuckBehindBike(_, _, r) => {
+                input.event = if let Event::PassedBike(_, _, _) = ev {
+                    MetricEvent::PassedBike
+                } else {
+                    MetricEvent::StuckBehindBike
+                };
+                input.mode = Some(TripMode::Drive);
+                input.road = Some(*r);
+            }
+            _ => {
+                return None;
+            }
+        }
+        Some(input)
+    }
+}
+
+impl CustomMetric {
+    fn matches(&self, input: &MetricInput) -> bool {
+        fn allowed<T: Ord>(filter: &BTreeSet<T>, x: &Option<T>) -> bool {
+            filter.is_empty() || x.as_ref().map(|x| filter.contains(x)).unwrap_or(false)
+        }
+
+        self.event == input.event
+            && allowed(&self.modes, &input.mode)
+            && allowed(&self.intersections, &input.intersection)
+            && allowed(&self.roads, &input.road)
+    }
+}
+
+// A CustomMetric and its value over time
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MetricSeries {
+    pub metric: CustomMetric,
+    pub count: usize,
+    pub total: Duration,
+    // The running count and total after the last matching event, at most once per RESOLUTION
+    pub pts: Vec<(Time, usize, Duration)>,
+}
+
+impl MetricSeries {
+    pub fn new(metric: CustomMetric) -> MetricSeries {
+        MetricSeries {
+            metric,
+            count: 0,
+            total: Duration::ZERO,
+            pts: Vec::new(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, time: Time, input: &MetricInput) {
+        if !self.metric.matches(input) {
+            return;
+        }
+        self.count += 1;
+        self.total += input.duration;
+        // Over a long run, one point per event adds up, so only keep the last in each interval
+        if let Some(last) = self.pts.last_mut() {
+            if resolution_idx(last.0) == resolution_idx(time) {
+                *last = (time, self.count, self.total);
+                return;
+            }
+        }
+        self.pts.push((time, self.count, self.total));
+    }
+
+    // Step functions, ending at now. Use whichever matches metric.measure.
+    pub fn count_over_time(&self, now: Time) -> Vec<(Time, usize)> {
+        self.step_function(now, |(_, cnt, _)| *cnt, 0)
+    }
+
+    pub fn total_over_time(&self, now: Time) -> Vec<(Time, Duration)> {
+        self.step_function(now, |(_, _, total)| *total, Duration::ZERO)
+    }
+
+    fn step_function<T: Copy, F: Fn(&(Time, usize, Duration)) -> T>(
+        &self,
+        now: Time,
+        value: F,
+        zero: T,
+    ) -> Vec<(Time, T)> {
+        let mut pts = vec![(Time::START_OF_DAY, zero)];
+        let mut last = zero;
+        for pt in &self.pts {
+            if pt.0 > now {
+                break;
+            }
+            pts.push((pt.0, last));
+            last = value(pt);
+            pts.push((pt.0, last));
+        }
+        pts.push((now, last));
+        pts
+    }
+}
+
+fn resolution_idx(t: Time) -> usize {
+    (t.inner_seconds() / RESOLUTION.inner_seconds()) as usize
+}
@@ -5,9 +5,10 @@ pub use pandemic::{Cmd, PandemicModel};
 use rand::Rng;
 use rand_distr::{Distribution, Exp, Normal};
 use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
 use std::ops;
 
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct AnyTime(f64);
 
 impl AnyTime {
@@ -68,7 +69,7 @@ impl From<f64> for AnyTime {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StateEvent {
     Exposition,
     Incubation,
@@ -77,7 +78,7 @@ pub enum StateEvent {
     Death,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
     s: StateEvent,
     p_hosp: f64,  // probability of people being hospitalized after infection
@@ -149,7 +150,7 @@ impl Event {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum State {
     Sane((Event, Time)),
     Exposed((Event, Time)),
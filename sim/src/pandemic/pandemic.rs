@@ -11,7 +11,7 @@ use std::collections::BTreeMap;
 // TODO If two people are in the same shared space indefinitely and neither leaves, we don't model
 // transmission. It only occurs when people leave a space.
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PandemicModel {
     pop: BTreeMap<PersonID, State>,
 
@@ -352,7 +352,7 @@ impl PandemicModel {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct SharedSpace<T: Ord> {
     // Since when has a person been in some shared space?
     // TODO This is an awkward data structure; abstutil::MultiMap is also bad, because key removal
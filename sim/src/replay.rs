@@ -0,0 +1,299 @@
+use crate::{
+    CarID, CarStatus, DrawCarInput, DrawPedCrowdInput, DrawPedestrianInput, Event, GetDrawAgents,
+    PedestrianID, UnzoomedAgent,
+};
+use abstutil::Timer;
+use geom::{Angle, Distance, Duration, Pt2D, Time};
+use map_model::{Map, Traversable};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+// A recording of a run: every event, plus where every moving agent was at regular intervals. It's
+// compact enough to share, and can be played back -- forwards or backwards, from any time --
+// without simulating anything. Parked cars aren't included. The same thing also keeps a rolling
+// window of recent history, so the UI can rewind; to stay cheap, that only has the frames.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub map_name: String,
+    pub edits_name: String,
+    pub run_name: String,
+    pub frame_interval: Duration,
+    // Every event, in order
+    pub events: Vec<(Time, Event)>,
+    frames: Vec<Frame>,
+    // If set, anything older than this is forgotten as new frames are recorded
+    #[serde(default)]
+    keep_only: Option<Duration>,
+
+    // What's being played back right now, as an index into frames
+    #[serde(skip_serializing, skip_deserializing)]
+    current: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Frame {
+    time: Time,
+    cars: Vec<CarPosition>,
+    peds: Vec<PedPosition>,
+}
+
+// Just enough to rebuild the car's body from the map
+#[derive(Clone, Serialize, Deserialize)]
+struct CarPosition {
+    id: CarID,
+    on: Traversable,
+    front: Distance,
+    length: Distance,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct PedPosition {
+    id: PedestrianID,
+    on: Traversable,
+    pos: Pt2D,
+    facing: Angle,
+}
+
+impl Replay {
+    pub(crate) fn new(map_name: String, run_name: String, frame_interval: Duration) -> Replay {
+        Replay {
+            map_name,
+            edits_name: "untitled edits".to_string(),
+            run_name,
+            frame_interval,
+            events: Vec::new(),
+            frames: Vec::new(),
+            keep_only: None,
+            current: 0,
+        }
+    }
+
+    pub(crate) fn rolling(
+        map_name: String,
+        run_name: String,
+        frame_interval: Duration,
+        keep_only: Duration,
+    ) -> Replay {
+        let mut replay = Replay::new(map_name, run_name, frame_interval);
+        replay.keep_only = Some(keep_only);
+        replay
+    }
+
+    pub(crate) fn record_frame(
+        &mut self,
+        time: Time,
+        cars: Vec<DrawCarInput>,
+        peds: Vec<DrawPedestrianInput>,
+        map: &Map,
+    ) {
+        self.frames.push(Frame {
+            time,
+            cars: cars
+                .into_iter()
+                .map(|c| {
+                    let pl = c.on.get_polyline(map);
+                    // The body might stick out behind the current lane or turn; only the front
+                    // matters.
+                    let front = pl
+                        .dist_along_of_point(c.body.last_pt())
+                        .map(|(dist, _)| dist)
+                        .unwrap_or_else(|| pl.length());
+                    CarPosition {
+                        id: c.id,
+                        on: c.on,
+                        front,
+                        length: c.body.length(),
+                    }
+                })
+                .collect(),
+            peds: peds
+                .into_iter()
+                .map(|p| PedPosition {
+                    id: p.id,
+                    on: p.on,
+                    pos: p.pos,
+                    facing: p.facing,
+                })
+                .collect(),
+        });
+
+        if let Some(keep) = self.keep_only {
+            let cutoff = time.clamped_sub(keep);
+            let old_frames = self.frames.iter().take_while(|f| f.time < cutoff).count();
+            self.frames.drain(0..old_frames);
+            let old_events = first_event_at(&self.events, cutoff);
+            self.events.drain(0..old_events);
+        }
+    }
+
+    // Going back to an earlier moment and simulating again changes what happens afterwards.
+    pub(crate) fn forget_after(&mut self, time: Time) {
+        let keep_frames = self.frames.iter().take_while(|f| f.time <= time).count();
+        self.frames.truncate(keep_frames);
+        let keep_events = self.events.iter().take_while(|(t, _)| *t <= time).count();
+        self.events.truncate(keep_events);
+    }
+
+    pub fn load(path: String, timer: &mut Timer) -> Result<Replay, std::io::Error> {
+        abstutil::maybe_read_binary(path, timer)
+    }
+
+    pub fn start_time(&self) -> Time {
+        self.frames
+            .first()
+            .map(|f| f.time)
+            .unwrap_or(Time::START_OF_DAY)
+    }
+
+    pub fn end_time(&self) -> Time {
+        self.frames
+            .last()
+            .map(|f| f.time)
+            .unwrap_or(Time::START_OF_DAY)
+    }
+
+    // Play back the last frame at or before this time.
+    pub fn seek(&mut self, time: Time) {
+        self.current = match self.frames.binary_search_by_key(&time, |f| f.time) {
+            Ok(idx) => idx,
+            Err(idx) => idx.max(1) - 1,
+        };
+    }
+
+    // Every event from the frame being played back, up until the next frame
+    pub fn current_events(&self) -> &[(Time, Event)] {
+        let start = self.time();
+        let end = start + self.frame_interval;
+        &self.events[first_event_at(&self.events, start)..first_event_at(&self.events, end)]
+    }
+
+    fn frame(&self) -> Option<&Frame> {
+        self.frames.get(self.current)
+    }
+
+    fn draw_car(&self, car: &CarPosition, map: &Map) -> DrawCarInput {
+        let front = car.front.min(car.on.length(map));
+        DrawCarInput {
+            id: car.id,
+            waiting_for_turn: None,
+            status: CarStatus::Moving,
+            on: car.on,
+            label: None,
+            body: car
+                .on
+                .exact_slice((front - car.length).max(Distance::ZERO), front, map),
+        }
+    }
+
+    fn draw_ped(&self, ped: &PedPosition) -> DrawPedestrianInput {
+        DrawPedestrianInput {
+            id: ped.id,
+            pos: ped.pos,
+            facing: ped.facing,
+            waiting_for_turn: None,
+            preparing_bike: false,
+            waiting_for_bus: false,
+            on: ped.on,
+        }
+    }
+}
+
+// The index of the first event at or after this time
+fn first_event_at(events: &[(Time, Event)], time: Time) -> usize {
+    match events.binary_search_by(|(t, _)| {
+        if *t < time {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        }
+    }) {
+        Ok(idx) | Err(idx) => idx,
+    }
+}
+
+impl GetDrawAgents for Replay {
+    fn time(&self) -> Time {
+        self.frame().map(|f| f.time).unwrap_or(Time::START_OF_DAY)
+    }
+
+    fn step_count(&self) -> usize {
+        self.current
+    }
+
+    fn get_draw_car(&self, id: CarID, map: &Map) -> Option<DrawCarInput> {
+        let car = self.frame()?.cars.iter().find(|c| c.id == id)?;
+        Some(self.draw_car(car, map))
+    }
+
+    fn get_draw_ped(&self, id: PedestrianID, _: &Map) -> Option<DrawPedestrianInput> {
+        let ped = self.frame()?.peds.iter().find(|p| p.id == id)?;
+        Some(self.draw_ped(ped))
+    }
+
+    fn get_draw_cars(&self, on: Traversable, map: &Map) -> Vec<DrawCarInput> {
+        self.frame()
+            .map(|f| {
+                f.cars
+                    .iter()
+                    .filter(|c| c.on == on)
+                    .map(|c| self.draw_car(c, map))
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new)
+    }
+
+    fn get_draw_peds(
+        &self,
+        on: Traversable,
+        _: &Map,
+    ) -> (Vec<DrawPedestrianInput>, Vec<DrawPedCrowdInput>) {
+        let peds = self
+            .frame()
+            .map(|f| {
+                f.peds
+                    .iter()
+                    .filter(|p| p.on == on)
+                    .map(|p| self.draw_ped(p))
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new);
+        (peds, Vec::new())
+    }
+
+    fn get_all_draw_cars(&self, map: &Map) -> Vec<DrawCarInput> {
+        self.frame()
+            .map(|f| f.cars.iter().map(|c| self.draw_car(c, map)).collect())
+            .unwrap_or_else(Vec::new)
+    }
+
+    fn get_all_draw_peds(&self, _: &Map) -> Vec<DrawPedestrianInput> {
+        self.frame()
+            .map(|f| f.peds.iter().map(|p| self.draw_ped(p)).collect())
+            .unwrap_or_else(Vec::new)
+    }
+
+    fn get_unzoomed_agents(&self, map: &Map) -> Vec<UnzoomedAgent> {
+        let mut result = Vec::new();
+        if let Some(f) = self.frame() {
+            for c in &f.cars {
+                let front = c.front.min(c.on.length(map));
+                result.push(UnzoomedAgent {
+                    vehicle_type: Some(c.id.1),
+                    pos: c.on.dist_along(front, map).0,
+                    // The person isn't recorded
+                    person: None,
+                    on: c.on,
+                });
+            }
+            for p in &f.peds {
+                result.push(UnzoomedAgent {
+                    vehicle_type: None,
+                    pos: p.pos,
+                    person: None,
+                    on: p.on,
+                });
+            }
+        }
+        result
+    }
+}
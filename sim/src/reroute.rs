@@ -0,0 +1,233 @@
+use crate::CarID;
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Distance, Duration, Time};
+use map_model::{
+    DirectedRoadID, LaneID, Map, Path, PathConstraints, PathRequest, PathStep, Position,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// How much the newest observation of a road counts towards its live travel time
+const SMOOTHING: f64 = 0.3;
+
+// When drivers give up on their route and look for a faster one. Usually loaded from a JSON file
+// with --reroute; anything missing gets the default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RerouteConfig {
+    // Re-plan when the live estimate for the rest of the route is this many times the original
+    // estimate
+    pub threshold: f64,
+    // Pathfinding with live times is slow, so each driver waits at least this long before
+    // re-planning again
+    pub min_interval: Duration,
+}
+
+impl Default for RerouteConfig {
+    fn default() -> RerouteConfig {
+        RerouteConfig {
+            threshold: 1.5,
+            min_interval: Duration::minutes(5),
+        }
+    }
+}
+
+// Tracks how long vehicles are actually taking to get through each road, including waiting at the
+// intersection at the end, and uses that to re-plan routes for drivers stuck in congestion.
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub(crate) struct Rerouter {
+    config: RerouteConfig,
+    // Smoothed over recent vehicles. Roads nobody has finished crossing yet are missing.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    live_times: BTreeMap<DirectedRoadID, Duration>,
+    // When each car entered the road it's on now
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    entered: BTreeMap<CarID, (DirectedRoadID, Time)>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    last_reroute: BTreeMap<CarID, Time>,
+}
+
+impl Rerouter {
+    pub fn new(config: RerouteConfig) -> Rerouter {
+        Rerouter {
+            config,
+            live_times: BTreeMap::new(),
+            entered: BTreeMap::new(),
+            last_reroute: BTreeMap::new(),
+        }
+    }
+
+    // Only called when a car moves from a turn onto a lane, so the time always covers a full
+    // road. Changing lanes partway along a road doesn't count.
+    pub fn car_entered_lane(&mut self, car: CarID, l: LaneID, now: Time, map: &Map) {
+        self.car_entered_road(car, map.get_l(l).get_directed_parent(map), now);
+    }
+
+    fn car_entered_road(&mut self, car: CarID, dr: DirectedRoadID, now: Time) {
+        if let Some((prev, since)) = self.entered.insert(car, (dr, now)) {
+            let observed = now - since;
+            let smoothed = match self.live_times.get(&prev) {
+                Some(old) => *old * (1.0 - SMOOTHING) + observed * SMOOTHING,
+                None => observed,
+            };
+            self.live_times.insert(prev, smoothed);
+        }
+    }
+
+    pub fn car_gone(&mut self, car: CarID) {
+        self.entered.remove(&car);
+        self.last_reroute.remove(&car);
+    }
+
+    pub fn live_time(&self, dr: DirectedRoadID) -> Option<Duration> {
+        self.live_times.get(&dr).cloned()
+    }
+
+    // If the rest of the path looks much slower than originally expected, returns a new path
+    // starting from the beginning of the current lane.
+    pub fn maybe_reroute(
+        &mut self,
+        car: CarID,
+        path: &Path,
+        constraints: PathConstraints,
+        now: Time,
+        map: &Map,
+    ) -> Option<Path> {
+        if !self.can_reroute(car, now) {
+            return None;
+        }
+        let start = match path.current_step() {
+            PathStep::Lane(l) => l,
+            _ => {
+                return None;
+            }
+        };
+        let end = match path.last_step() {
+            PathStep::Lane(l) => l,
+            _ => {
+                return None;
+            }
+        };
+        if start == end {
+            return None;
+        }
+
+        let mut original = Duration::ZERO;
+        let mut live = Duration::ZERO;
+        for step in path.get_steps() {
+            if let PathStep::Lane(l) = step {
+                let lane = map.get_l(*l);
+                let free_flow = lane.length() / map.get_r(lane.parent).speed_limit;
+                original += free_flow;
+                live += self
+                    .live_time(lane.get_directed_parent(map))
+                    .unwrap_or(free_flow);
+            }
+        }
+        if !self.too_slow(original, live) {
+            return None;
+        }
+
+        self.last_reroute.insert(car, now);
+        let req = PathRequest {
+            start: Position::new(start, Distance::ZERO),
+            end: Position::new(end, path.end_dist()),
+            constraints,
+        };
+        let new_path = map.pathfind_with_costs(req, |lane, turn| {
+            let free_flow = (lane.length() / map.get_r(lane.parent).speed_limit)
+                + (turn.geom.length() / map.get_parent(turn.id.dst).speed_limit);
+            // Turns are already included in the live time for the road before them
+            self.live_time(lane.get_directed_parent(map))
+                .unwrap_or(free_flow)
+                .inner_seconds()
+                .round() as usize
+        })?;
+        if new_path.get_steps() == path.get_steps() {
+            return None;
+        }
+        Some(new_path)
+    }
+
+    fn can_reroute(&self, car: CarID, now: Time) -> bool {
+        self.last_reroute
+            .get(&car)
+            .map(|t| now - *t >= self.config.min_interval)
+            .unwrap_or(true)
+    }
+
+    fn too_slow(&self, original: Duration, live: Duration) -> bool {
+        live > original * self.config.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VehicleType;
+    use map_model::RoadID;
+
+    #[test]
+    fn test_smoothing() {
+        let mut rerouter = Rerouter::new(RerouteConfig::default());
+        let road1 = DirectedRoadID {
+            id: RoadID(1),
+            forwards: true,
+        };
+        let road2 = DirectedRoadID {
+            id: RoadID(2),
+            forwards: true,
+        };
+        let car1 = CarID(1, VehicleType::Car);
+        let car2 = CarID(2, VehicleType::Car);
+        let t = |secs| Time::START_OF_DAY + Duration::seconds(secs);
+
+        // Nobody's finished crossing yet
+        rerouter.car_entered_road(car1, road1, t(0.0));
+        assert_eq!(rerouter.live_time(road1), None);
+
+        // The first observation is taken as is
+        rerouter.car_entered_road(car1, road2, t(100.0));
+        assert_eq!(rerouter.live_time(road1), Some(Duration::seconds(100.0)));
+        assert_eq!(rerouter.live_time(road2), None);
+
+        // Later ones only nudge the estimate
+        rerouter.car_entered_road(car2, road1, t(100.0));
+        rerouter.car_entered_road(car2, road2, t(300.0));
+        assert_eq!(
+            rerouter.live_time(road1),
+            Some(Duration::seconds(0.7 * 100.0 + 0.3 * 200.0))
+        );
+
+        // A car leaving the map mid-road doesn't count
+        rerouter.car_gone(car1);
+        assert_eq!(rerouter.live_time(road2), None);
+    }
+
+    #[test]
+    fn test_threshold() {
+        let mut rerouter = Rerouter::new(RerouteConfig::default());
+        let minutes = Duration::minutes;
+        assert!(!rerouter.too_slow(minutes(10), minutes(10)));
+        assert!(!rerouter.too_slow(minutes(10), minutes(15)));
+        assert!(rerouter.too_slow(minutes(10), minutes(16)));
+
+        let car = CarID(1, VehicleType::Car);
+        let t = |mins| Time::START_OF_DAY + Duration::minutes(mins);
+        assert!(rerouter.can_reroute(car, t(0)));
+        rerouter.last_reroute.insert(car, t(10));
+        assert!(!rerouter.can_reroute(car, t(14)));
+        assert!(rerouter.can_reroute(car, t(15)));
+        // Others aren't held back
+        assert!(rerouter.can_reroute(CarID(2, VehicleType::Car), t(14)));
+    }
+}
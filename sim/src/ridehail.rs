@@ -0,0 +1,293 @@
+use crate::router::curb_stop;
+use crate::{CarID, PersonID, TripID, Vehicle, VehicleType, MIN_CAR_LENGTH};
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Distance, Duration};
+use map_model::{BuildingID, Map, Path, Position};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+pub(crate) const RIDEHAIL_VEHICLE_LENGTH: Distance = MIN_CAR_LENGTH;
+
+// How a ride-hail service operates. Usually loaded from a JSON file with --ridehail; anything
+// missing gets the default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RideHailConfig {
+    // How many vehicles drive for the service
+    pub fleet_size: usize,
+    // How often idle vehicles move towards where recent requests came from
+    pub reposition_every: Duration,
+    // Idle vehicles already this close (in a straight line) to recent demand stay put
+    pub reposition_threshold: Distance,
+}
+
+impl Default for RideHailConfig {
+    fn default() -> RideHailConfig {
+        RideHailConfig {
+            fleet_size: 20,
+            reposition_every: Duration::minutes(15),
+            reposition_threshold: Distance::meters(1000.0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub(crate) struct RideRequest {
+    pub trip: TripID,
+    pub person: PersonID,
+    pub pickup: BuildingID,
+    pub goal: BuildingID,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+enum Status {
+    // Waiting off the road, with the front of the car here
+    Idle(Position),
+    Repositioning,
+    // Heading to pick somebody up
+    EnRoute(RideRequest),
+    // Carrying somebody to their destination
+    Occupied(RideRequest),
+}
+
+// A fleet of ride-hail vehicles. They don't belong to anybody, so they drive around without a
+// trip until they pick somebody up.
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub struct RideHailSimState {
+    // None means the service doesn't exist
+    config: Option<RideHailConfig>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    vehicles: BTreeMap<CarID, Status>,
+    // Requests that no vehicle was free to serve yet, oldest first
+    waiting: VecDeque<RideRequest>,
+    // Where requests came from since the last round of repositioning
+    recent_pickups: Vec<Position>,
+}
+
+impl RideHailSimState {
+    // Vehicles get the first CarIDs, so the caller shouldn't hand those out again.
+    pub fn new(map: &Map, config: Option<RideHailConfig>) -> RideHailSimState {
+        let mut state = RideHailSimState {
+            config,
+            vehicles: BTreeMap::new(),
+            waiting: VecDeque::new(),
+            recent_pickups: Vec::new(),
+        };
+        let fleet_size = match state.config {
+            Some(ref c) => c.fleet_size,
+            None => {
+                return state;
+            }
+        };
+
+        // Spread the fleet out evenly to start
+        let lanes: Vec<_> = map
+            .all_lanes()
+            .iter()
+            .filter(|l| l.is_driving() && l.length() > 2.0 * RIDEHAIL_VEHICLE_LENGTH)
+            .collect();
+        if lanes.is_empty() {
+            return state;
+        }
+        for idx in 0..fleet_size {
+            let l = lanes[idx * lanes.len() / fleet_size];
+            state.vehicles.insert(
+                CarID(idx, VehicleType::Car),
+                Status::Idle(Position::new(l.id, l.length() / 2.0)),
+            );
+        }
+        state
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    pub fn fleet_size(&self) -> usize {
+        self.vehicles.len()
+    }
+
+    pub fn is_ridehail(&self, id: CarID) -> bool {
+        self.vehicles.contains_key(&id)
+    }
+
+    pub fn num_idle(&self) -> usize {
+        self.vehicles
+            .values()
+            .filter(|s| match s {
+                Status::Idle(_) => true,
+                _ => false,
+            })
+            .count()
+    }
+
+    pub fn num_waiting(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub(crate) fn make_vehicle(&self, id: CarID) -> Vehicle {
+        assert!(self.is_ridehail(id));
+        Vehicle {
+            id,
+            owner: None,
+            vehicle_type: VehicleType::Car,
+            length: RIDEHAIL_VEHICLE_LENGTH,
+            max_speed: None,
+        }
+    }
+
+    pub(crate) fn reposition_every(&self) -> Option<Duration> {
+        self.config.as_ref().map(|c| c.reposition_every)
+    }
+
+    // The vehicle currently carrying somebody on this trip
+    pub(crate) fn vehicle_for(&self, trip: TripID) -> Option<CarID> {
+        self.vehicles.iter().find_map(|(id, s)| match s {
+            Status::Occupied(ref r) if r.trip == trip => Some(*id),
+            _ => None,
+        })
+    }
+
+    // Dispatches the closest idle vehicle, returning it and where it's waiting. If nobody's free,
+    // the request waits for the next vehicle to finish a ride.
+    pub(crate) fn request(
+        &mut self,
+        req: RideRequest,
+        pickup: Position,
+        map: &Map,
+    ) -> Option<(CarID, Position)> {
+        self.recent_pickups.push(pickup);
+        let pt = pickup.pt(map);
+        let best = self
+            .vehicles
+            .iter()
+            .filter_map(|(id, s)| match s {
+                Status::Idle(pos) if can_reach(*pos, pickup) => Some((*id, *pos)),
+                _ => None,
+            })
+            .min_by_key(|(_, pos)| pos.pt(map).dist_to(pt));
+        if let Some((id, pos)) = best {
+            self.vehicles.insert(id, Status::EnRoute(req));
+            Some((id, pos))
+        } else {
+            self.waiting.push_back(req);
+            None
+        }
+    }
+
+    // When a vehicle is free, it serves the oldest request it can reach. curb_pos finds where to
+    // pick up at a building.
+    pub(crate) fn next_job<F: Fn(BuildingID) -> Position>(
+        &mut self,
+        id: CarID,
+        at: Position,
+        curb_pos: F,
+    ) -> Option<(RideRequest, Position)> {
+        let idx = self
+            .waiting
+            .iter()
+            .position(|r| can_reach(at, curb_pos(r.pickup)))?;
+        let req = self.waiting.remove(idx).unwrap();
+        let pickup = curb_pos(req.pickup);
+        self.vehicles.insert(id, Status::EnRoute(req.clone()));
+        Some((req, pickup))
+    }
+
+    // Returns the request this vehicle was heading to serve, or carrying somebody for
+    pub(crate) fn current_job(&self, id: CarID) -> Option<(RideRequest, bool)> {
+        match self.vehicles[&id] {
+            Status::EnRoute(ref r) => Some((r.clone(), false)),
+            Status::Occupied(ref r) => Some((r.clone(), true)),
+            Status::Idle(_) | Status::Repositioning => None,
+        }
+    }
+
+    pub(crate) fn picked_up(&mut self, id: CarID) {
+        let req = match self.vehicles[&id] {
+            Status::EnRoute(ref r) => r.clone(),
+            _ => unreachable!(),
+        };
+        self.vehicles.insert(id, Status::Occupied(req));
+    }
+
+    pub(crate) fn finished_job(&mut self, id: CarID) {
+        self.vehicles.insert(id, Status::Repositioning);
+    }
+
+    pub(crate) fn go_idle(&mut self, id: CarID, at: Position) {
+        self.vehicles.insert(id, Status::Idle(at));
+    }
+
+    // If the trip is aborted while waiting, forget about it. A vehicle already heading there will
+    // notice when it arrives.
+    pub(crate) fn cancel(&mut self, trip: TripID) {
+        self.waiting.retain(|r| r.trip != trip);
+    }
+
+    // Sends idle vehicles towards places where people recently asked for rides, if nobody's
+    // already waiting nearby. Returns each vehicle moved, where it's been waiting, and where it
+    // should go.
+    pub(crate) fn reposition(&mut self, map: &Map) -> Vec<(CarID, Position, Position)> {
+        let threshold = match self.config {
+            Some(ref c) => c.reposition_threshold,
+            None => {
+                return Vec::new();
+            }
+        };
+        let mut moves = Vec::new();
+        let mut covered_lanes = BTreeSet::new();
+        for target in std::mem::replace(&mut self.recent_pickups, Vec::new()) {
+            if !covered_lanes.insert(target.lane()) {
+                continue;
+            }
+            let pt = target.pt(map);
+            let idle: Vec<(CarID, Position)> = self
+                .vehicles
+                .iter()
+                .filter_map(|(id, s)| match s {
+                    Status::Idle(pos) => Some((*id, *pos)),
+                    _ => None,
+                })
+                .collect();
+            if idle
+                .iter()
+                .any(|(_, pos)| pos.pt(map).dist_to(pt) <= threshold)
+            {
+                continue;
+            }
+            if let Some((id, pos)) = idle
+                .into_iter()
+                .filter(|(_, pos)| can_reach(*pos, target))
+                .min_by_key(|(_, pos)| pos.pt(map).dist_to(pt))
+            {
+                self.vehicles.insert(id, Status::Repositioning);
+                moves.push((id, pos, target));
+            }
+        }
+        moves
+    }
+}
+
+// Where a ride-hail vehicle stops for a building, and whether that's in a pickup / drop-off zone
+pub(crate) fn ridehail_curb(b: BuildingID, map: &Map) -> Option<(Position, bool)> {
+    curb_stop(
+        b,
+        map.find_driving_lane_near_building(b),
+        RIDEHAIL_VEHICLE_LENGTH,
+        map,
+        |r| r.pudo_zone,
+    )
+}
+
+// Driving along a single lane only works going forwards.
+pub(crate) fn can_reach(from: Position, to: Position) -> bool {
+    from.lane() != to.lane() || from.dist_along() < to.dist_along()
+}
+
+// How far a vehicle drives along a path, starting and ending partway along lanes
+pub(crate) fn deadhead_dist(path: &Path, from: Position, to: Position, map: &Map) -> Distance {
+    path.total_length() - from.dist_along() - (map.get_l(to.lane()).length() - to.dist_along())
+}
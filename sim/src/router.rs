@@ -1,14 +1,19 @@
 use crate::mechanics::Queue;
 use crate::{
-    Event, ParkingSimState, ParkingSpot, PersonID, SidewalkSpot, TripID, TripPhaseType, Vehicle,
+    DrivingGoal, Event, ParkingSimState, ParkingSpot, PersonID, SidewalkSpot, TripID,
+    TripPhaseType, Vehicle, VehicleType, TRUCK_LENGTH,
 };
-use geom::Distance;
+use geom::{Distance, Duration, Time};
 use map_model::{
-    BuildingID, IntersectionID, Map, Path, PathConstraints, PathRequest, PathStep, Position,
-    Traversable, TurnID,
+    BuildingID, IntersectionID, LaneID, Map, ParkingRestrictions, Path, PathConstraints,
+    PathRequest, PathStep, Position, Traversable, TurnID,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+// After circling this far without finding a spot, drivers give up and head straight for a spot
+// they know is free, even if it's far away.
+const MAX_CRUISING_DISTANCE: Distance = Distance::const_meters(2000.0);
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Router {
@@ -25,6 +30,20 @@ pub enum ActionAtEnd {
     StopBiking(SidewalkSpot),
     BusAtStop,
     GiveUpOnParking,
+    // Stop briefly to let somebody out. True if the car pulled into a pickup / drop-off zone,
+    // false if it's blocking the lane.
+    DropOff(bool),
+    // A ride-hail vehicle stops at the curb to pick up or drop off its passenger. Like DropOff,
+    // true if it's in a pickup / drop-off zone.
+    RideHailStop(bool),
+    // A ride-hail vehicle has nothing to do, so it leaves the road to wait for a request
+    RideHailIdle,
+    // A delivery truck stops for this long. True if it pulled into a loading zone, false if it's
+    // double-parked in the lane.
+    Deliver(bool, Duration),
+    // An emergency vehicle reached the scene, coming from the first building to the second. How
+    // long did it take since being dispatched?
+    EmergencyArrived(BuildingID, BuildingID, Duration),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -36,6 +55,10 @@ enum Goal {
         spot: Option<(ParkingSpot, Distance)>,
         // No parking available at all!
         stuck_end_dist: Option<Distance>,
+        // Set once the driver starts circling around, looking for a free spot
+        cruising: Option<Cruising>,
+        // Somebody to let out along the last lane, before looking for parking
+        drop_off: Option<DropOff>,
     },
     EndAtBorder {
         end_dist: Distance,
@@ -47,6 +70,41 @@ enum Goal {
     FollowBusRoute {
         end_dist: Distance,
     },
+    RideHail {
+        end_dist: Distance,
+        in_zone: bool,
+        // Already picked up or dropped off here, or just repositioning, so it's time to go idle
+        stopped: bool,
+    },
+    Deliver {
+        end_dist: Distance,
+        in_zone: bool,
+        dwell: Duration,
+        // Where to go afterwards
+        exit: DrivingGoal,
+    },
+    Respond {
+        end_dist: Distance,
+        station: BuildingID,
+        scene: BuildingID,
+        dispatched: Time,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Cruising {
+    started: Time,
+    // Driven so far while searching
+    dist: Distance,
+    // Lanes already checked for spots
+    visited: BTreeSet<LaneID>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct DropOff {
+    // Where the front of the car stops
+    dist: Distance,
+    in_zone: bool,
 }
 
 impl Router {
@@ -64,10 +122,36 @@ impl Router {
                 target: bldg,
                 spot: None,
                 stuck_end_dist: None,
+                cruising: None,
+                drop_off: None,
             },
         }
     }
 
+    // Before parking, stop along the target building's curb to let a passenger out. If the curb
+    // has a pickup / drop-off zone, pull into it; otherwise stop in the lane.
+    pub fn drop_off_first(&mut self, vehicle_len: Distance, map: &Map) {
+        if let Goal::ParkNearBuilding {
+            target,
+            ref mut drop_off,
+            ..
+        } = self.goal
+        {
+            // Not worth it when the car starts on the same lane
+            if self.path.get_steps().len() == 1 {
+                return;
+            }
+            let lane = self.path.last_step().as_lane();
+            if let Some((pos, in_zone)) = curb_stop(target, lane, vehicle_len, map, |r| r.pudo_zone)
+            {
+                *drop_off = Some(DropOff {
+                    dist: pos.dist_along(),
+                    in_zone,
+                });
+            }
+        }
+    }
+
     pub fn bike_then_stop(path: Path, end_dist: Distance, map: &Map) -> Option<Router> {
         let last_lane = path.get_steps().iter().last().unwrap().as_lane();
         if map
@@ -92,6 +176,121 @@ impl Router {
         }
     }
 
+    // A ride-hail vehicle heading to the curb to pick up or drop off
+    pub fn ridehail_stop(path: Path, end_dist: Distance, in_zone: bool) -> Router {
+        Router {
+            path,
+            goal: Goal::RideHail {
+                end_dist,
+                in_zone,
+                stopped: false,
+            },
+        }
+    }
+
+    // An empty ride-hail vehicle moving somewhere else to wait
+    pub fn ridehail_reposition(path: Path, end_dist: Distance) -> Router {
+        Router {
+            path,
+            goal: Goal::RideHail {
+                end_dist,
+                in_zone: false,
+                stopped: true,
+            },
+        }
+    }
+
+    // A delivery truck heading to the curb in front of a building
+    pub fn deliver(
+        path: Path,
+        end_dist: Distance,
+        in_zone: bool,
+        dwell: Duration,
+        exit: DrivingGoal,
+    ) -> Router {
+        Router {
+            path,
+            goal: Goal::Deliver {
+                end_dist,
+                in_zone,
+                dwell,
+                exit,
+            },
+        }
+    }
+
+    pub fn is_delivering(&self) -> bool {
+        match self.goal {
+            Goal::Deliver { .. } => true,
+            _ => false,
+        }
+    }
+
+    // Once a delivery truck is done at the curb, how it continues on from there
+    pub fn leave_after_delivery(&self, from: Position, map: &Map) -> Option<(Router, PathRequest)> {
+        let exit = match self.goal {
+            Goal::Deliver { ref exit, .. } => exit,
+            _ => unreachable!(),
+        };
+        let req = PathRequest {
+            start: from,
+            end: exit.goal_pos(PathConstraints::Car, map),
+            constraints: PathConstraints::Car,
+        };
+        let path = map.pathfind(req.clone())?;
+        let router = exit.make_router(path, map, VehicleType::Car)?;
+        Some((router, req))
+    }
+
+    pub fn respond(
+        path: Path,
+        end_dist: Distance,
+        station: BuildingID,
+        scene: BuildingID,
+        dispatched: Time,
+    ) -> Router {
+        Router {
+            path,
+            goal: Goal::Respond {
+                end_dist,
+                station,
+                scene,
+                dispatched,
+            },
+        }
+    }
+
+    // Emergency vehicles get priority at traffic signals, and other drivers make way for them.
+    pub fn is_responding(&self) -> bool {
+        match self.goal {
+            Goal::Respond { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_ridehail(&self) -> bool {
+        match self.goal {
+            Goal::RideHail { .. } => true,
+            _ => false,
+        }
+    }
+
+    // Buses have to follow their route, and drivers circling for parking are already improvising.
+    pub fn can_reroute(&self) -> bool {
+        match self.goal {
+            Goal::FollowBusRoute { .. } => false,
+            Goal::ParkNearBuilding { ref cruising, .. } => cruising.is_none(),
+            _ => true,
+        }
+    }
+
+    // The new path has to start at the current step and end in the same place.
+    pub fn reroute(&mut self, path: Path) {
+        assert_eq!(path.current_step(), self.path.current_step());
+        assert_eq!(path.last_step(), self.path.last_step());
+        self.path = path;
+    }
+
     pub fn head(&self) -> Traversable {
         self.path.current_step().as_traversable()
     }
@@ -120,10 +319,20 @@ impl Router {
             Goal::ParkNearBuilding {
                 spot,
                 stuck_end_dist,
+                ref drop_off,
                 ..
-            } => stuck_end_dist.unwrap_or_else(|| spot.unwrap().1),
+            } => {
+                if let Some(ref d) = drop_off {
+                    d.dist
+                } else {
+                    stuck_end_dist.unwrap_or_else(|| spot.unwrap().1)
+                }
+            }
             Goal::BikeThenStop { end_dist } => end_dist,
             Goal::FollowBusRoute { end_dist } => end_dist,
+            Goal::RideHail { end_dist, .. } => end_dist,
+            Goal::Deliver { end_dist, .. } => end_dist,
+            Goal::Respond { end_dist, .. } => end_dist,
         }
     }
 
@@ -134,6 +343,7 @@ impl Router {
     // Returns the step just finished
     pub fn advance(
         &mut self,
+        now: Time,
         vehicle: &Vehicle,
         parking: &ParkingSimState,
         map: &Map,
@@ -145,6 +355,7 @@ impl Router {
             // Do this to trigger the side-effect of looking for parking.
             self.maybe_handle_end(
                 Distance::ZERO,
+                now,
                 vehicle,
                 parking,
                 map,
@@ -172,6 +383,7 @@ impl Router {
     pub fn maybe_handle_end(
         &mut self,
         front: Distance,
+        now: Time,
         vehicle: &Vehicle,
         parking: &ParkingSimState,
         map: &Map,
@@ -190,8 +402,21 @@ impl Router {
             Goal::ParkNearBuilding {
                 ref mut spot,
                 ref mut stuck_end_dist,
+                ref mut cruising,
+                ref mut drop_off,
                 target,
             } => {
+                if let Some(d) = drop_off.take() {
+                    if front < d.dist {
+                        *drop_off = Some(d);
+                        return None;
+                    }
+                    if front == d.dist {
+                        return Some(ActionAtEnd::DropOff(d.in_zone));
+                    }
+                    // Already past the building, like when the car started on this lane
+                }
+
                 if let Some(d) = stuck_end_dist {
                     if *d == front {
                         return Some(ActionAtEnd::GiveUpOnParking);
@@ -210,26 +435,29 @@ impl Router {
                         Position::new(current_lane, front),
                         vehicle,
                         target,
+                        now,
                         map,
                     );
+                    // Paying more or having to move the car soon is worth some extra distance
                     let best = if let Some(ref p) = map.get_b(target).parking {
                         if p.driving_pos.lane() == current_lane {
                             let target_dist = p.driving_pos.dist_along();
                             // Closest to the building
-                            candidates
-                                .into_iter()
-                                .min_by_key(|(_, pos)| (pos.dist_along() - target_dist).abs())
+                            candidates.into_iter().min_by_key(|(spot, pos)| {
+                                (pos.dist_along() - target_dist).abs()
+                                    + parking.spot_penalty(*spot, map)
+                            })
                         } else {
                             // Closest to the road endpoint, I guess
-                            candidates
-                                .into_iter()
-                                .min_by_key(|(_, pos)| pos.dist_along())
+                            candidates.into_iter().min_by_key(|(spot, pos)| {
+                                pos.dist_along() + parking.spot_penalty(*spot, map)
+                            })
                         }
                     } else {
                         // Closest to the road endpoint, I guess
-                        candidates
-                            .into_iter()
-                            .min_by_key(|(_, pos)| pos.dist_along())
+                        candidates.into_iter().min_by_key(|(spot, pos)| {
+                            pos.dist_along() + parking.spot_penalty(*spot, map)
+                        })
                     };
                     if let Some((new_spot, new_pos)) = best {
                         if let Some((t, p)) = trip_and_person {
@@ -246,8 +474,49 @@ impl Router {
                         }
                         *spot = Some((new_spot, new_pos.dist_along()));
                     } else {
-                        if let Some((new_path_steps, new_spot, new_pos)) =
-                            parking.path_to_free_parking_spot(current_lane, vehicle, target, map)
+                        // Drivers don't know where the free spots are, so circle around nearby
+                        // lanes and take the first one they see.
+                        let next_turn = if cruising
+                            .as_ref()
+                            .map(|c| c.dist < MAX_CRUISING_DISTANCE)
+                            .unwrap_or(true)
+                        {
+                            pick_cruising_turn(current_lane, target, cruising, parking, map)
+                        } else {
+                            None
+                        };
+                        if let Some(turn) = next_turn {
+                            if cruising.is_none() {
+                                *cruising = Some(Cruising {
+                                    started: now,
+                                    dist: Distance::ZERO,
+                                    visited: BTreeSet::new(),
+                                });
+                                if let Some((t, p)) = trip_and_person {
+                                    events.push(Event::TripPhaseStarting(
+                                        t,
+                                        p,
+                                        Some(PathRequest {
+                                            start: Position::new(current_lane, front),
+                                            end: Position::new(
+                                                turn.dst,
+                                                map.get_l(turn.dst).length(),
+                                            ),
+                                            constraints: PathConstraints::Car,
+                                        }),
+                                        TripPhaseType::Parking,
+                                    ));
+                                }
+                            }
+                            let c = cruising.as_mut().unwrap();
+                            c.visited.insert(current_lane);
+                            c.dist += map.get_l(current_lane).length() - front
+                                + map.get_t(turn).geom.length();
+                            self.path.add(PathStep::Turn(turn), map);
+                            self.path.add(PathStep::Lane(turn.dst), map);
+                            events.push(Event::PathAmended(self.path.clone()));
+                        } else if let Some((new_path_steps, new_spot, new_pos)) = parking
+                            .path_to_free_parking_spot(current_lane, vehicle, target, now, map)
                         {
                             *spot = Some((new_spot, new_pos.dist_along()));
                             for step in new_path_steps {
@@ -280,6 +549,13 @@ impl Router {
                 }
 
                 if spot.unwrap().1 == front {
+                    if let Some(c) = cruising.take() {
+                        events.push(Event::CruisedForParking(
+                            vehicle.id,
+                            c.dist + front,
+                            now - c.started,
+                        ));
+                    }
                     Some(ActionAtEnd::StartParking(spot.unwrap().0))
                 } else {
                     None
@@ -307,6 +583,48 @@ impl Router {
                     None
                 }
             }
+            Goal::RideHail {
+                end_dist,
+                in_zone,
+                ref mut stopped,
+            } => {
+                if end_dist != front {
+                    None
+                } else if *stopped {
+                    Some(ActionAtEnd::RideHailIdle)
+                } else {
+                    *stopped = true;
+                    Some(ActionAtEnd::RideHailStop(in_zone))
+                }
+            }
+            Goal::Deliver {
+                end_dist,
+                in_zone,
+                dwell,
+                ..
+            } => {
+                if end_dist == front {
+                    Some(ActionAtEnd::Deliver(in_zone, dwell))
+                } else {
+                    None
+                }
+            }
+            Goal::Respond {
+                end_dist,
+                station,
+                scene,
+                dispatched,
+            } => {
+                if end_dist == front {
+                    Some(ActionAtEnd::EmergencyArrived(
+                        station,
+                        scene,
+                        now - dispatched,
+                    ))
+                } else {
+                    None
+                }
+            }
         }
     }
 
@@ -357,7 +675,9 @@ impl Router {
                         dst: next_lane,
                     };
                     if map.maybe_get_t(turn2).is_some() {
-                        Some((queues[&Traversable::Lane(*l)].cars.len(), turn1, *l, turn2))
+                        // Stay out of lanes closed by an incident
+                        let queue = &queues[&Traversable::Lane(*l)];
+                        Some(((queue.blocked, queue.cars.len()), turn1, *l, turn2))
                     } else {
                         None
                     }
@@ -379,7 +699,136 @@ impl Router {
         self.path.modify_step(3, PathStep::Turn(turn2), map);
     }
 
+    // Switch to another lane on the same road partway along the current lane. If the new lane
+    // can't make the same turn, it can lead somewhere else on the next road, as long as that still
+    // reaches the lane after. False if there's no way to continue from the new lane.
+    pub fn change_lanes(&mut self, to: LaneID, map: &Map) -> bool {
+        let steps = self.path.get_steps();
+        let current_turn = match steps[1] {
+            PathStep::Turn(t) => t,
+            _ => {
+                return false;
+            }
+        };
+
+        let direct = TurnID {
+            parent: current_turn.parent,
+            src: to,
+            dst: current_turn.dst,
+        };
+        if map.maybe_get_t(direct).is_some() {
+            self.path.modify_step(0, PathStep::Lane(to), map);
+            self.path.modify_step(1, PathStep::Turn(direct), map);
+            return true;
+        }
+
+        let after = match steps.get(4) {
+            Some(PathStep::Lane(l)) => *l,
+            _ => {
+                return false;
+            }
+        };
+        let orig_next_lane = current_turn.dst;
+        let parent = map.get_parent(orig_next_lane);
+        let orig_lt = map.get_l(orig_next_lane).lane_type;
+        let siblings = if parent.is_forwards(orig_next_lane) {
+            &parent.children_forwards
+        } else {
+            &parent.children_backwards
+        };
+        for (l, lt) in siblings {
+            let turn1 = TurnID {
+                parent: current_turn.parent,
+                src: to,
+                dst: *l,
+            };
+            let turn2 = TurnID {
+                parent: map.get_l(after).src_i,
+                src: *l,
+                dst: after,
+            };
+            if orig_lt == *lt
+                && map.maybe_get_t(turn1).is_some()
+                && map.maybe_get_t(turn2).is_some()
+            {
+                self.path.modify_step(0, PathStep::Lane(to), map);
+                self.path.modify_step(1, PathStep::Turn(turn1), map);
+                self.path.modify_step(2, PathStep::Lane(*l), map);
+                self.path.modify_step(3, PathStep::Turn(turn2), map);
+                return true;
+            }
+        }
+        false
+    }
+
     pub fn replace_path_for_serialization(&mut self, path: Path) -> Path {
         std::mem::replace(&mut self.path, path)
     }
 }
+
+// Where should a driver circling for parking go next? Prefer lanes not checked yet, with parking
+// along them, that lead closer to the destination.
+fn pick_cruising_turn(
+    current: LaneID,
+    target: BuildingID,
+    cruising: &Option<Cruising>,
+    parking: &ParkingSimState,
+    map: &Map,
+) -> Option<TurnID> {
+    let goal_pt = map.get_b(target).polygon.center();
+    map.get_turns_for(current, PathConstraints::Car)
+        .into_iter()
+        .min_by_key(|turn| {
+            let dst = map.get_l(turn.id.dst);
+            (
+                cruising
+                    .as_ref()
+                    .map(|c| c.visited.contains(&dst.id))
+                    .unwrap_or(false),
+                // Don't wind up heading off the map
+                dst.parking_blackhole.is_some(),
+                !parking.has_parking_along(dst.id),
+                dst.last_pt().dist_to(goal_pt),
+            )
+        })
+        .map(|turn| turn.id)
+}
+
+// Where along a driving lane should a vehicle stop in front of a building, and is there a zone it
+// can pull into, according to is_zone? None if the vehicle doesn't fit on the lane.
+pub(crate) fn curb_stop(
+    b: BuildingID,
+    lane: LaneID,
+    vehicle_len: Distance,
+    map: &Map,
+    is_zone: fn(&ParkingRestrictions) -> bool,
+) -> Option<(Position, bool)> {
+    let lane_len = map.get_l(lane).length();
+    if lane_len < vehicle_len {
+        return None;
+    }
+    let road = map.get_parent(lane);
+    let sidewalk = map.get_b(b).front_path.sidewalk;
+    let dist = if map.get_l(sidewalk.lane()).parent == road.id {
+        sidewalk.equiv_pos(lane, vehicle_len, map).dist_along()
+    } else {
+        // The building's lane was a blackhole, so just stop somewhere along the way
+        lane_len / 2.0
+    };
+    let in_zone = road
+        .lanes_on_side(road.is_forwards(lane))
+        .into_iter()
+        .any(|l| is_zone(&map.get_l(l).parking_restrictions));
+    Some((Position::new(lane, dist.max(vehicle_len)), in_zone))
+}
+
+// Where a delivery truck stops in front of a building, and whether there's a loading zone
+pub(crate) fn delivery_stop(b: BuildingID, map: &Map) -> Option<(Position, bool)> {
+    curb_stop(
+        b,
+        map.find_driving_lane_near_building(b),
+        TRUCK_LENGTH,
+        map,
+        |r| r.loading_zone,
+    )
+}
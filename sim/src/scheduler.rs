@@ -1,19 +1,28 @@
 use crate::{
     pandemic, AgentID, CarID, CreateCar, CreatePedestrian, PedestrianID, TripID, TripSpec,
 };
+use abstutil::Timer;
 use derivative::Derivative;
 use geom::{Duration, Histogram, Time};
-use map_model::{IntersectionID, Path, PathRequest};
+use map_model::{BusRouteID, IntersectionID, Map, Path, PathRequest};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, BinaryHeap};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+// When trips don't calculate paths upfront, look this far ahead for trips about to start and
+// calculate their paths in parallel.
+const PATH_PREFETCH_WINDOW: Duration = Duration::const_seconds(60.0);
+// Below this many requests, spinning up threads isn't worth it; start_trip will just pathfind.
+const MIN_PARALLEL_PATHS: usize = 8;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub enum Command {
     // If true, retry when there's no room to spawn somewhere
     SpawnCar(CreateCar, bool),
     SpawnPed(CreatePedestrian),
+    // Start the next scheduled bus for a route. The index points into the route's spawn_times.
+    SpawnBus(BusRouteID, usize),
     StartTrip(TripID, TripSpec, Option<PathRequest>, Option<Path>),
     UpdateCar(CarID),
     // Distinguish this from UpdateCar to avoid confusing things
@@ -23,6 +32,16 @@ pub enum Command {
     Callback(Duration),
     Pandemic(pandemic::Cmd),
     FinishRemoteTrip(TripID),
+    RebalanceScooters,
+    RepositionRideHail,
+    SampleDetectors,
+    StartIncident(usize),
+    EndIncident(usize),
+    DetectGridlock,
+    RecordReplayFrame,
+    RecordRewindFrame,
+    RunScript,
+    EndLayover(CarID),
 }
 
 impl Command {
@@ -38,6 +57,7 @@ impl Command {
         match self {
             Command::SpawnCar(ref create, _) => CommandType::Car(create.vehicle.id),
             Command::SpawnPed(ref create) => CommandType::Ped(create.id),
+            Command::SpawnBus(route, _) => CommandType::SpawnBus(*route),
             Command::StartTrip(id, _, _, _) => CommandType::StartTrip(*id),
             Command::UpdateCar(id) => CommandType::Car(*id),
             Command::UpdateLaggyHead(id) => CommandType::CarLaggyHead(*id),
@@ -46,6 +66,16 @@ impl Command {
             Command::Callback(_) => CommandType::Callback,
             Command::Pandemic(ref p) => CommandType::Pandemic(p.clone()),
             Command::FinishRemoteTrip(t) => CommandType::FinishRemoteTrip(*t),
+            Command::RebalanceScooters => CommandType::RebalanceScooters,
+            Command::RepositionRideHail => CommandType::RepositionRideHail,
+            Command::SampleDetectors => CommandType::SampleDetectors,
+            Command::StartIncident(id) => CommandType::StartIncident(*id),
+            Command::EndIncident(id) => CommandType::EndIncident(*id),
+            Command::DetectGridlock => CommandType::DetectGridlock,
+            Command::RecordReplayFrame => CommandType::RecordReplayFrame,
+            Command::RecordRewindFrame => CommandType::RecordRewindFrame,
+            Command::RunScript => CommandType::RunScript,
+            Command::EndLayover(id) => CommandType::EndLayover(*id),
         }
     }
 }
@@ -58,10 +88,21 @@ pub enum CommandType {
     Car(CarID),
     CarLaggyHead(CarID),
     Ped(PedestrianID),
+    SpawnBus(BusRouteID),
     Intersection(IntersectionID),
     Callback,
     Pandemic(pandemic::Cmd),
     FinishRemoteTrip(TripID),
+    RebalanceScooters,
+    RepositionRideHail,
+    SampleDetectors,
+    StartIncident(usize),
+    EndIncident(usize),
+    DetectGridlock,
+    RecordReplayFrame,
+    RecordRewindFrame,
+    RunScript,
+    EndLayover(CarID),
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -101,6 +142,16 @@ pub struct Scheduler {
     #[derivative(PartialEq = "ignore")]
     #[serde(skip_serializing, skip_deserializing)]
     delta_times: Histogram<Duration>,
+
+    // StartTrip commands that still need a path calculated. Entries may be stale; the queued
+    // command is the source of truth. Losing this in a savestate just means those trips pathfind
+    // lazily, with the same result.
+    #[derivative(PartialEq = "ignore")]
+    #[serde(skip_serializing, skip_deserializing)]
+    unpathed_trips: BTreeSet<(Time, TripID)>,
+    #[derivative(PartialEq = "ignore")]
+    #[serde(skip_serializing, skip_deserializing)]
+    prefetched_until: Option<Time>,
 }
 
 impl Scheduler {
@@ -111,6 +162,8 @@ impl Scheduler {
             latest_time: Time::START_OF_DAY,
             last_time: Time::START_OF_DAY,
             delta_times: Histogram::new(),
+            unpathed_trips: BTreeSet::new(),
+            prefetched_until: None,
         }
     }
 
@@ -124,6 +177,9 @@ impl Scheduler {
         self.last_time = self.last_time.max(time);
         self.delta_times.add(time - self.latest_time);
 
+        if let Command::StartTrip(id, _, Some(_), None) = cmd {
+            self.unpathed_trips.insert((time, id));
+        }
         let cmd_type = cmd.to_type();
 
         match self.queued_commands.entry(cmd_type.clone()) {
@@ -214,6 +270,95 @@ impl Scheduler {
         }
     }
 
+    // Live map edits might close something along a path that was calculated before the vehicle
+    // spawned. Find new paths for those, failing without changing anything if there's no way
+    // around. Trips not started yet just look for a path again, and fail later like any other
+    // trip if there isn't one.
+    pub fn handle_live_edits(&mut self, map: &Map) -> Result<(), String> {
+        let mut new_paths = Vec::new();
+        for (cmd_type, (cmd, _)) in &self.queued_commands {
+            match cmd {
+                Command::SpawnCar(ref create_car, _) => {
+                    let constraints = create_car.vehicle.vehicle_type.to_constraints();
+                    if !create_car
+                        .router
+                        .get_path()
+                        .is_still_valid(constraints, map)
+                    {
+                        if let Some(path) = map.pathfind(create_car.req.clone()) {
+                            new_paths.push((cmd_type.clone(), Some(path)));
+                        } else {
+                            return Err(format!(
+                                "{} is about to spawn, but can't get around the closures",
+                                create_car.vehicle.id
+                            ));
+                        }
+                    }
+                }
+                Command::StartTrip(_, _, Some(ref req), Some(ref path)) => {
+                    if !path.is_still_valid(req.constraints, map) {
+                        new_paths.push((cmd_type.clone(), map.pathfind(req.clone())));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (cmd_type, maybe_path) in new_paths {
+            match self.queued_commands.get_mut(&cmd_type).unwrap().0 {
+                Command::SpawnCar(ref mut create_car, _) => {
+                    create_car.router.reroute(maybe_path.unwrap());
+                }
+                Command::StartTrip(_, _, _, ref mut path) => {
+                    *path = maybe_path;
+                }
+                _ => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    // Pathfinding is the most expensive part of starting a trip, and it only reads the map. Before
+    // stepping up to the given time, calculate paths for all trips starting soon in parallel and
+    // stash them in the queued commands. Commands still run one at a time in the usual order, so
+    // results don't depend on the number of threads.
+    pub fn prefetch_trip_paths(&mut self, up_to: Time, map: &Map) {
+        if self.prefetched_until.map(|t| up_to <= t).unwrap_or(false) {
+            return;
+        }
+        let until = up_to + PATH_PREFETCH_WINDOW;
+        self.prefetched_until = Some(until);
+        let later = self.unpathed_trips.split_off(&(until, TripID(usize::MAX)));
+        let due = std::mem::replace(&mut self.unpathed_trips, later);
+
+        let mut requests = Vec::new();
+        for (_, id) in due {
+            if let Some((Command::StartTrip(_, _, Some(ref req), None), _)) =
+                self.queued_commands.get(&CommandType::StartTrip(id))
+            {
+                requests.push((id, req.clone()));
+            }
+        }
+        if requests.len() < MIN_PARALLEL_PATHS {
+            return;
+        }
+
+        let results =
+            Timer::throwaway().parallelize("prefetch trip paths", requests, |(id, req)| {
+                (id, map.pathfind(req))
+            });
+        for (id, maybe_path) in results {
+            // If there's no path, leave it to start_trip to try again and fail normally.
+            if let Some(path) = maybe_path {
+                if let Some((Command::StartTrip(_, _, _, ref mut p), _)) =
+                    self.queued_commands.get_mut(&CommandType::StartTrip(id))
+                {
+                    *p = Some(path);
+                }
+            }
+        }
+    }
+
     pub fn describe_stats(&self) -> String {
         format!("delta times for events: {}", self.delta_times.describe())
     }
@@ -0,0 +1,263 @@
+use crate::{CarID, PersonID, SidewalkSpot, Vehicle, VehicleType, BIKE_LENGTH};
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Distance, Duration, Pt2D, Speed};
+use map_model::{BuildingID, LaneID, Map, Position};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// 15mph, the usual cap for rentals
+const SCOOTER_SPEED: Speed = Speed::const_meters_per_second(6.7);
+
+// How shared scooters are deployed. Usually loaded from a JSON file with --scooters; anything
+// missing gets the default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScooterConfig {
+    // How many devices to scatter per kilometer of sidewalk
+    pub devices_per_km: f64,
+    // Walking trips at least this far (in a straight line) will look for a scooter
+    pub min_trip_distance: Distance,
+    // How far people will walk (in a straight line) to reach a scooter, or from where they leave
+    // it to their destination
+    pub max_walk_to_scooter: Distance,
+    // How often the operator's van collects devices from crowded sidewalks and returns them to
+    // empty ones
+    pub rebalance_every: Duration,
+    // How many devices the van can carry on each tour
+    pub rebalance_capacity: usize,
+}
+
+impl Default for ScooterConfig {
+    fn default() -> ScooterConfig {
+        ScooterConfig {
+            devices_per_km: 5.0,
+            min_trip_distance: Distance::meters(800.0),
+            max_walk_to_scooter: Distance::meters(200.0),
+            rebalance_every: Duration::hours(1),
+            rebalance_capacity: 20,
+        }
+    }
+}
+
+// Dockless scooters that anybody walking far enough can rent. Devices are left at the bike rack
+// spot of a sidewalk, so the sidewalk is the finest location tracked.
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub struct ScooterSimState {
+    // None means the model is disabled
+    config: Option<ScooterConfig>,
+    // How many devices are free on each sidewalk
+    available: BTreeMap<LaneID, usize>,
+    // Where devices were at the start of the day. Rebalancing tries to restore this.
+    deployed: BTreeMap<LaneID, usize>,
+    // Devices being ridden, and the sidewalk they were picked up from
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    rented: BTreeMap<CarID, LaneID>,
+}
+
+impl ScooterSimState {
+    pub fn new(map: &Map, config: Option<ScooterConfig>) -> ScooterSimState {
+        let mut state = ScooterSimState {
+            config,
+            available: BTreeMap::new(),
+            deployed: BTreeMap::new(),
+            rented: BTreeMap::new(),
+        };
+        let density = match state.config {
+            Some(ref c) => c.devices_per_km,
+            None => {
+                return state;
+            }
+        };
+
+        // Spread devices evenly, carrying the fractional part between sidewalks
+        let mut leftover = 0.0;
+        for l in map.all_lanes() {
+            if !l.is_sidewalk() || SidewalkSpot::bike_rack(l.id, map).is_none() {
+                continue;
+            }
+            leftover += l.length().inner_meters() / 1000.0 * density;
+            let mut cnt = leftover.floor() as usize;
+            leftover -= cnt as f64;
+            if let Some(cap) = l.scooter_parking.capacity() {
+                cnt = cnt.min(cap);
+            }
+            if cnt > 0 {
+                state.available.insert(l.id, cnt);
+                state.deployed.insert(l.id, cnt);
+            }
+        }
+        state
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    pub fn is_scooter(&self, id: CarID) -> bool {
+        self.rented.contains_key(&id)
+    }
+
+    pub fn get_available(&self) -> &BTreeMap<LaneID, usize> {
+        &self.available
+    }
+
+    pub fn num_rented(&self) -> usize {
+        self.rented.len()
+    }
+
+    // Should a walking trip between these points try to use a scooter?
+    pub(crate) fn worth_riding(&self, from: Pt2D, to: Pt2D) -> bool {
+        match self.config {
+            Some(ref c) => from.dist_to(to) >= c.min_trip_distance,
+            None => false,
+        }
+    }
+
+    // The closest sidewalk with a free device, if it's close enough to walk to
+    pub(crate) fn find_nearby(&self, pt: Pt2D, map: &Map) -> Option<LaneID> {
+        let max_dist = self.config.as_ref()?.max_walk_to_scooter;
+        self.available
+            .iter()
+            .filter(|(_, cnt)| **cnt > 0)
+            .map(|(l, _)| (*l, rack_pt(*l, map).dist_to(pt)))
+            .filter(|(_, dist)| *dist <= max_dist)
+            .min_by_key(|(_, dist)| *dist)
+            .map(|(l, _)| l)
+    }
+
+    // Where should somebody heading to this building leave their device? If the building's own
+    // sidewalk doesn't allow it (or the corral is full), pick the closest building along a
+    // sidewalk that does, as long as it's within walking distance.
+    pub(crate) fn find_drop_off(&self, goal: BuildingID, map: &Map) -> Option<BuildingID> {
+        let max_dist = self.config.as_ref()?.max_walk_to_scooter;
+        if self.has_room(map.get_b(goal).sidewalk(), map) {
+            return Some(goal);
+        }
+        let goal_pt = map.get_b(goal).polygon.center();
+        map.all_lanes()
+            .iter()
+            .filter(|l| l.is_sidewalk() && !l.building_paths.is_empty() && self.has_room(l.id, map))
+            .map(|l| (l, rack_pt(l.id, map).dist_to(goal_pt)))
+            .filter(|(_, dist)| *dist <= max_dist)
+            .min_by_key(|(_, dist)| *dist)
+            .map(|(l, _)| l.building_paths[0])
+    }
+
+    fn has_room(&self, sidewalk: LaneID, map: &Map) -> bool {
+        if SidewalkSpot::bike_rack(sidewalk, map).is_none() {
+            return false;
+        }
+        match map.get_l(sidewalk).scooter_parking.capacity() {
+            Some(cap) => self.available.get(&sidewalk).cloned().unwrap_or(0) < cap,
+            None => true,
+        }
+    }
+
+    pub(crate) fn rent(&mut self, id: CarID, sidewalk: LaneID) {
+        let cnt = self.available.get_mut(&sidewalk).unwrap();
+        *cnt -= 1;
+        self.rented.insert(id, sidewalk);
+    }
+
+    pub(crate) fn make_vehicle(&self, id: CarID, rider: PersonID) -> Vehicle {
+        assert!(self.is_scooter(id));
+        Vehicle {
+            id,
+            owner: Some(rider),
+            vehicle_type: VehicleType::Bike,
+            length: BIKE_LENGTH,
+            max_speed: Some(SCOOTER_SPEED),
+        }
+    }
+
+    pub(crate) fn leave(&mut self, id: CarID, sidewalk: LaneID) {
+        self.rented.remove(&id).unwrap();
+        *self.available.entry(sidewalk).or_insert(0) += 1;
+    }
+
+    // If the trip is aborted, the device magically goes back where it was picked up.
+    pub(crate) fn abandon(&mut self, id: CarID) {
+        let sidewalk = self.rented[&id];
+        self.leave(id, sidewalk);
+    }
+
+    pub(crate) fn rebalance_every(&self) -> Option<Duration> {
+        self.config.as_ref().map(|c| c.rebalance_every)
+    }
+
+    // One tour of the operator's van: pick up devices from the most crowded sidewalks, then drop
+    // them off at the emptiest ones, compared to where they started the day. Sidewalks where
+    // parking is now banned or over capacity count as crowded. Returns how many devices were
+    // moved and roughly how far the van drove.
+    pub(crate) fn rebalance(&mut self, map: &Map) -> (usize, Distance) {
+        let capacity = match self.config {
+            Some(ref c) => c.rebalance_capacity,
+            None => {
+                return (0, Distance::ZERO);
+            }
+        };
+
+        let target = |l: LaneID| {
+            let want = self.deployed.get(&l).cloned().unwrap_or(0);
+            match map.get_l(l).scooter_parking.capacity() {
+                Some(cap) => want.min(cap),
+                None => want,
+            }
+        };
+        let mut surplus: Vec<(usize, LaneID)> = Vec::new();
+        let mut deficit: Vec<(usize, LaneID)> = Vec::new();
+        for (l, have) in &self.available {
+            let want = target(*l);
+            if *have > want {
+                surplus.push((*have - want, *l));
+            }
+        }
+        for l in self.deployed.keys() {
+            let want = target(*l);
+            let have = self.available.get(l).cloned().unwrap_or(0);
+            if want > have {
+                deficit.push((want - have, *l));
+            }
+        }
+        // Biggest imbalance first, ties broken by lane for determinism
+        surplus.sort_by(|a, b| b.cmp(a));
+        deficit.sort_by(|a, b| b.cmp(a));
+
+        // Only pick up what can be dropped off somewhere
+        let capacity = capacity.min(deficit.iter().map(|(need, _)| *need).sum());
+        let mut stops: Vec<Pt2D> = Vec::new();
+        let mut carrying = 0;
+        for (extra, l) in surplus {
+            if carrying == capacity {
+                break;
+            }
+            let take = extra.min(capacity - carrying);
+            *self.available.get_mut(&l).unwrap() -= take;
+            carrying += take;
+            stops.push(rack_pt(l, map));
+        }
+        let moved = carrying;
+        for (need, l) in deficit {
+            if carrying == 0 {
+                break;
+            }
+            let give = need.min(carrying);
+            *self.available.entry(l).or_insert(0) += give;
+            carrying -= give;
+            stops.push(rack_pt(l, map));
+        }
+        let dist = stops
+            .windows(2)
+            .map(|pair| pair[0].dist_to(pair[1]))
+            .fold(Distance::ZERO, |a, b| a + b);
+        (moved, dist)
+    }
+}
+
+// Where devices are left along a sidewalk
+fn rack_pt(sidewalk: LaneID, map: &Map) -> Pt2D {
+    Position::new(sidewalk, map.get_l(sidewalk).length() / 2.0).pt(map)
+}
@@ -0,0 +1,274 @@
+use crate::{Event, TripMode};
+use geom::{Duration, Time};
+use map_model::{BuildingID, IntersectionID, LaneID, Map};
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use rhai::{Dynamic, Engine, EvalAltResult, ImmutableString, Scope, AST, INT};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+// How often on_tick runs
+pub(crate) const SCRIPT_TICK_EVERY: Duration = Duration::const_seconds(60.0);
+
+// Lets people write their own scenarios, like "random daily incidents", without forking this
+// crate. A Rhai script passed with --script can define any of these hooks:
+//
+// - on_tick(seconds): called every minute, starting at midnight
+// - on_event(kind, description, seconds): called for every sim event. kind is the name of the
+//   Event variant, like "TripFinished", and description is the whole thing, debug-printed.
+//
+// Times are seconds since midnight. Hooks act on the sim by calling:
+//
+// - close_lane(lane, minutes)
+// - spawn_trip(from_building, to_building, mode), where mode is walk, bike, transit, or drive
+// - hold_signal(intersection, phase, seconds)
+// - alert(message)
+//
+// and can look around with random(lo, hi), random_driving_lane(), random_building(),
+// random_traffic_signal(), and num_phases(intersection). IDs are plain integers; the random_*
+// functions return -1 when the map has nothing to pick. The randomness is seeded from --rng_seed,
+// so runs are still reproducible. For example:
+//
+// fn on_tick(seconds) {
+//     if seconds % 3600 == 0 && random(0, 4) == 0 {
+//         let l = random_driving_lane();
+//         close_lane(l, 30);
+//         alert("A crash closed lane " + l);
+//     }
+// }
+//
+// Actions take effect right after the hook returns. Mistakes, like closing a sidewalk, are
+// reported as alerts instead of stopping the simulation.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct SimScript {
+    path: String,
+    // Saved along with the rest of the sim, so a restored or cloned sim makes the same choices
+    rng: XorShiftRng,
+    // Compiled when first needed, so savestates only have to remember the path
+    #[serde(skip_serializing, skip_deserializing)]
+    runtime: Option<Runtime>,
+}
+
+// Clones of the sim share this, but nothing in it outlives a single hook call
+#[derive(Clone)]
+struct Runtime {
+    engine: Arc<Engine>,
+    ast: AST,
+    actions: Arc<Mutex<Vec<ScriptAction>>>,
+    // The script's RNG is lent here while a hook runs
+    rng: Arc<Mutex<XorShiftRng>>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum ScriptAction {
+    CloseLane(LaneID, Duration),
+    SpawnTrip(BuildingID, BuildingID, TripMode),
+    HoldSignal(IntersectionID, usize, Duration),
+    Alert(String),
+}
+
+// Just checks that the script parses, without a map to run it on
+pub fn check_script(path: &str) -> Result<(), String> {
+    Engine::new()
+        .compile_file(path.into())
+        .map(|_| ())
+        .map_err(|err| format!("couldn't load script {}: {}", path, err))
+}
+
+impl SimScript {
+    pub fn load(path: &str, map: &Map, rng: XorShiftRng) -> Result<SimScript, String> {
+        let runtime = Runtime::new(path, map, rng.clone())?;
+        Ok(SimScript {
+            path: path.to_string(),
+            rng,
+            runtime: Some(runtime),
+        })
+    }
+
+    pub fn on_tick(&mut self, now: Time, map: &Map) -> Vec<ScriptAction> {
+        self.call("on_tick", (now.inner_seconds() as INT,), map)
+    }
+
+    pub fn on_event(&mut self, ev: &Event, now: Time, map: &Map) -> Vec<ScriptAction> {
+        let description = format!("{:?}", ev);
+        let kind = description
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .unwrap()
+            .to_string();
+        self.call(
+            "on_event",
+            (kind, description, now.inner_seconds() as INT),
+            map,
+        )
+    }
+
+    // Returns everything the hook asked to do
+    fn call(&mut self, hook: &str, args: impl rhai::FuncArgs, map: &Map) -> Vec<ScriptAction> {
+        if self.runtime.is_none() {
+            // After loading a savestate
+            match Runtime::new(&self.path, map, self.rng.clone()) {
+                Ok(runtime) => {
+                    self.runtime = Some(runtime);
+                }
+                // Probably the script changed since the savestate. Don't spam the same alert.
+                Err(err) => {
+                    return if hook == "on_tick" {
+                        vec![ScriptAction::Alert(err)]
+                    } else {
+                        Vec::new()
+                    };
+                }
+            }
+        }
+        let runtime = self.runtime.as_ref().unwrap();
+
+        *runtime.rng.lock().unwrap() = self.rng.clone();
+        let result: Result<Dynamic, Box<EvalAltResult>> =
+            runtime
+                .engine
+                .call_fn(&mut Scope::new(), &runtime.ast, hook, args);
+        self.rng = runtime.rng.lock().unwrap().clone();
+
+        let mut actions: Vec<ScriptAction> = runtime.actions.lock().unwrap().drain(..).collect();
+        match result {
+            Ok(_) => {}
+            // Scripts don't have to define every hook
+            Err(err) => match *err {
+                EvalAltResult::ErrorFunctionNotFound(ref name, _) if name.starts_with(hook) => {}
+                _ => {
+                    actions.push(ScriptAction::Alert(format!(
+                        "{} failed in {}: {}",
+                        self.path, hook, err
+                    )));
+                }
+            },
+        }
+        actions
+    }
+
+    // A fresh RNG for anything the script's actions need to randomize, like vehicle lengths
+    pub fn fork_rng(&mut self) -> XorShiftRng {
+        abstutil::fork_rng(&mut self.rng)
+    }
+}
+
+impl Runtime {
+    fn new(path: &str, map: &Map, rng: XorShiftRng) -> Result<Runtime, String> {
+        let mut engine = Engine::new();
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        let rng = Arc::new(Mutex::new(rng));
+
+        {
+            let actions = actions.clone();
+            engine.register_fn("close_lane", move |l: INT, minutes: INT| {
+                actions.lock().unwrap().push(ScriptAction::CloseLane(
+                    LaneID(l as usize),
+                    Duration::minutes(minutes.max(0) as usize),
+                ));
+            });
+        }
+        {
+            let actions = actions.clone();
+            engine.register_fn(
+                "spawn_trip",
+                move |from: INT, to: INT, mode: ImmutableString| {
+                    let mut actions = actions.lock().unwrap();
+                    match parse_mode(&mode) {
+                        Some(mode) => actions.push(ScriptAction::SpawnTrip(
+                            BuildingID(from as usize),
+                            BuildingID(to as usize),
+                            mode,
+                        )),
+                        None => actions.push(ScriptAction::Alert(format!(
+                            "spawn_trip: unknown mode {}",
+                            mode
+                        ))),
+                    }
+                },
+            );
+        }
+        {
+            let actions = actions.clone();
+            engine.register_fn("hold_signal", move |i: INT, phase: INT, seconds: INT| {
+                actions.lock().unwrap().push(ScriptAction::HoldSignal(
+                    IntersectionID(i as usize),
+                    phase.max(0) as usize,
+                    Duration::seconds(seconds as f64),
+                ));
+            });
+        }
+        {
+            let actions = actions.clone();
+            engine.register_fn("alert", move |msg: ImmutableString| {
+                actions
+                    .lock()
+                    .unwrap()
+                    .push(ScriptAction::Alert(msg.to_string()));
+            });
+        }
+
+        {
+            let rng = rng.clone();
+            engine.register_fn("random", move |lo: INT, hi: INT| -> INT {
+                if lo >= hi {
+                    lo
+                } else {
+                    rng.lock().unwrap().gen_range(lo, hi)
+                }
+            });
+        }
+        let driving_lanes: Vec<INT> = map
+            .all_lanes()
+            .iter()
+            .filter(|l| l.is_driving())
+            .map(|l| l.id.0 as INT)
+            .collect();
+        let buildings: Vec<INT> = map.all_buildings().iter().map(|b| b.id.0 as INT).collect();
+        let mut signals: Vec<INT> = Vec::new();
+        let mut num_phases: Vec<INT> = vec![0; map.all_intersections().len()];
+        for i in map.all_intersections() {
+            if let Some(signal) = map.maybe_get_traffic_signal(i.id) {
+                signals.push(i.id.0 as INT);
+                num_phases[i.id.0] = signal.phases.len() as INT;
+            }
+        }
+        for (name, choices) in vec![
+            ("random_driving_lane", driving_lanes),
+            ("random_building", buildings),
+            ("random_traffic_signal", signals),
+        ] {
+            let rng = rng.clone();
+            engine.register_fn(name, move || -> INT {
+                if choices.is_empty() {
+                    -1
+                } else {
+                    choices[rng.lock().unwrap().gen_range(0, choices.len())]
+                }
+            });
+        }
+        engine.register_fn("num_phases", move |i: INT| -> INT {
+            num_phases.get(i as usize).cloned().unwrap_or(0)
+        });
+
+        let ast = engine
+            .compile_file(path.into())
+            .map_err(|err| format!("couldn't load script {}: {}", path, err))?;
+        Ok(Runtime {
+            engine: Arc::new(engine),
+            ast,
+            actions,
+            rng,
+        })
+    }
+}
+
+fn parse_mode(x: &str) -> Option<TripMode> {
+    match x {
+        "walk" => Some(TripMode::Walk),
+        "bike" => Some(TripMode::Bike),
+        "transit" => Some(TripMode::Transit),
+        "drive" => Some(TripMode::Drive),
+        _ => None,
+    }
+}
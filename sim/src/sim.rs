@@ -1,26 +1,36 @@
 use crate::{
-    AgentID, AlertLocation, Analytics, CarID, Command, CreateCar, DrawCarInput, DrawPedCrowdInput,
-    DrawPedestrianInput, DrivingSimState, Event, GetDrawAgents, IntersectionSimState, OrigPersonID,
-    PandemicModel, ParkedCar, ParkingSimState, ParkingSpot, PedestrianID, Person, PersonID,
-    PersonState, Router, Scheduler, SidewalkPOI, SidewalkSpot, TransitSimState, TripEndpoint,
-    TripID, TripManager, TripMode, TripPhaseType, TripPositions, TripResult, TripSpawner,
-    UnzoomedAgent, Vehicle, VehicleSpec, VehicleType, WalkingSimState, BUS_LENGTH, MIN_CAR_LENGTH,
+    emergency_curb, find_cycles, AgentID, AlertLocation, Analytics, BusCapacityConfig,
+    CarFollowingModel, CarID, Command, CreateCar, CustomMetric, Detector, DetectorSimState,
+    DrawCarInput, DrawPedCrowdInput, DrawPedestrianInput, DrivingSimState, Event, GetDrawAgents,
+    Gridlock, Incident, IncidentSimState, IndividTrip, IntersectionSimState, JaywalkingConfig,
+    LaneChangeConfig, LayoverConfig, OrigPersonID, PandemicModel, ParkedCar, ParkingSimState,
+    ParkingSpot, PedestrianID, Person, PersonID, PersonSpec, PersonState, Replay, RerouteConfig,
+    RideHailConfig, RideHailSimState, Router, Scenario, Scheduler, ScooterConfig, ScooterSimState,
+    ScriptAction, SidewalkPOI, SidewalkSpot, SimScript, SnowConditions, SpawnTrip, TransitSimState,
+    TripEndpoint, TripID, TripManager, TripMode, TripPhaseType, TripPositions, TripResult,
+    TripSpawner, UnzoomedAgent, Vehicle, VehicleSpec, VehicleType, WalkingSimState, Weather,
+    BUS_LENGTH, EMERGENCY_VEHICLE_LENGTH, GRIDLOCK_CHECK_EVERY, GRIDLOCK_MIN_WAIT, MIN_CAR_LENGTH,
+    SCRIPT_TICK_EVERY,
 };
 use abstutil::Timer;
 use derivative::Derivative;
 use geom::{Distance, Duration, PolyLine, Pt2D, Speed, Time};
 use instant::Instant;
 use map_model::{
-    BuildingID, BusRoute, BusRouteID, IntersectionID, LaneID, Map, ParkingLotID, Path,
-    PathConstraints, PathRequest, PathStep, Position, RoadID, Traversable,
+    BuildingID, BusRoute, BusRouteID, BusStopID, IntersectionID, LaneID, Map, ParkingLotID, Path,
+    PathConstraints, PathRequest, PathStep, Phase, Position, RoadID, Traversable, TurnID,
 };
 use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::panic;
 
 // TODO Do something else.
 const BLIND_RETRY_TO_SPAWN: Duration = Duration::const_seconds(5.0);
+// How often to remember where everybody is, for rewinding
+const REWIND_FRAME_INTERVAL: Duration = Duration::const_seconds(5.0);
+// A scheduled bus run that left longer ago than this is probably over
+const LATE_BUS_RUN: Duration = Duration::const_seconds(3600.0);
 
 #[derive(Serialize, Deserialize, Clone, Derivative)]
 #[derivative(PartialEq)]
@@ -29,11 +39,21 @@ pub struct Sim {
     parking: ParkingSimState,
     walking: WalkingSimState,
     intersections: IntersectionSimState,
+    detectors: DetectorSimState,
+    incidents: IncidentSimState,
     transit: TransitSimState,
     trips: TripManager,
     #[derivative(PartialEq = "ignore")]
     #[serde(skip_serializing, skip_deserializing)]
     pandemic: Option<PandemicModel>,
+    #[derivative(PartialEq = "ignore")]
+    #[serde(skip_serializing, skip_deserializing)]
+    replay: Option<Replay>,
+    #[derivative(PartialEq = "ignore")]
+    #[serde(skip_serializing, skip_deserializing)]
+    rewind: Option<Replay>,
+    #[derivative(PartialEq = "ignore")]
+    script: Option<SimScript>,
     scheduler: Scheduler,
     time: Time,
 
@@ -60,6 +80,21 @@ pub struct Sim {
     #[derivative(PartialEq = "ignore")]
     #[serde(skip_serializing, skip_deserializing)]
     alerts: AlertHandler,
+    #[derivative(PartialEq = "ignore")]
+    #[serde(skip_serializing, skip_deserializing, default = "default_step_size")]
+    step_size: Duration,
+    #[serde(default)]
+    wheelchair_share: f64,
+    // Every gridlock found so far
+    #[serde(default)]
+    gridlocks: Vec<Gridlock>,
+}
+
+pub(crate) const DEFAULT_STEP_SIZE: Duration = Duration::const_seconds(30.0);
+const MAX_STEP_SIZE: Duration = Duration::const_seconds(3600.0);
+
+fn default_step_size() -> Duration {
+    DEFAULT_STEP_SIZE
 }
 
 #[derive(Clone)]
@@ -67,14 +102,67 @@ pub struct SimOptions {
     pub run_name: String,
     pub use_freeform_policy_everywhere: bool,
     pub dont_block_the_box: bool,
+    // Intersections that don't follow dont_block_the_box. True means enforce it there.
+    pub block_the_box_overrides: BTreeMap<IntersectionID, bool>,
     pub recalc_lanechanging: bool,
     pub break_turn_conflict_cycles: bool,
     pub enable_pandemic_model: Option<XorShiftRng>,
     pub alerts: AlertHandler,
     pub pathfinding_upfront: bool,
+    // How far run_until_done advances between callbacks. Agents move in continuous time and each
+    // event happens exactly when it's scheduled, so this doesn't affect the results, only how often
+    // the caller gets to look at the sim.
+    pub step_size: Duration,
+    pub custom_metrics: Vec<CustomMetric>,
+    // None means no shared scooters
+    pub scooters: Option<ScooterConfig>,
+    // What fraction of car trips ending at a building first stop at the curb to let a passenger
+    // out, before parking
+    pub drop_off_share: f64,
+    // None means no ride-hail service
+    pub ridehail: Option<RideHailConfig>,
+    // None means clear roads
+    pub snow: Option<SnowConditions>,
+    // None means drivers never re-plan their route in congestion
+    pub reroute: Option<RerouteConfig>,
+    // What fraction of drivers follow signed detours around closed roads, instead of their own
+    // shortest path
+    pub detour_compliance: f64,
+    // None means vehicles stay in one lane for the whole length of a road
+    pub lane_changing: Option<LaneChangeConfig>,
+    pub car_following: CarFollowingModel,
+    pub detectors: Vec<Detector>,
+    // Lanes closed for a while during the day
+    pub incidents: Vec<Incident>,
+    // What fraction of people walk using a wheelchair, avoiding crossings without curb ramps
+    pub wheelchair_share: f64,
+    // None means pedestrians only cross roads at crosswalks
+    pub jaywalking: Option<JaywalkingConfig>,
+    // None means use the scenario's own weather, if it has any
+    pub weather: Option<Weather>,
+    // Periodically look for vehicles stuck waiting on each other in a cycle, and raise an alert
+    pub detect_gridlock: bool,
+    // Record every event and, this often, the position of every moving agent, so the run can be
+    // saved and played back later. None means don't record anything.
+    pub record_replay: Option<Duration>,
+    // Remember where every moving agent was over this much recent time, so the UI can rewind.
+    // None means don't keep any history. It's not free, so it's off unless asked for.
+    pub rewind_history: Option<Duration>,
+    // A Rhai script that watches the simulation and acts on it; see script.rs. The RNG seeds the
+    // script's randomness.
+    pub script: Option<(String, XorShiftRng)>,
+    // None means buses don't rest at the end of their route
+    pub layovers: Option<LayoverConfig>,
+    pub bus_capacity: BusCapacityConfig,
+    // At stop signs, what fraction of drivers yield to pedestrians waiting to cross. None means
+    // drivers only wait for people already in the crosswalk.
+    pub crosswalk_yield: Option<f64>,
+    // Write old analytics summaries to files in this directory, and only read them back when
+    // asked about that time. None keeps everything in memory.
+    pub page_analytics: Option<String>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum AlertHandler {
     // Just print the alert to STDOUT
     Print,
@@ -96,37 +184,138 @@ impl SimOptions {
             run_name: run_name.to_string(),
             use_freeform_policy_everywhere: false,
             dont_block_the_box: true,
+            block_the_box_overrides: BTreeMap::new(),
             recalc_lanechanging: true,
             break_turn_conflict_cycles: true,
             enable_pandemic_model: None,
             alerts: AlertHandler::Print,
             pathfinding_upfront: false,
+            step_size: DEFAULT_STEP_SIZE,
+            custom_metrics: Vec::new(),
+            scooters: None,
+            drop_off_share: 0.0,
+            ridehail: None,
+            snow: None,
+            reroute: None,
+            detour_compliance: 0.0,
+            lane_changing: None,
+            car_following: CarFollowingModel::Simple,
+            detectors: Vec::new(),
+            incidents: Vec::new(),
+            wheelchair_share: 0.0,
+            jaywalking: None,
+            weather: None,
+            detect_gridlock: true,
+            record_replay: None,
+            rewind_history: None,
+            script: None,
+            layovers: None,
+            bus_capacity: BusCapacityConfig::default(),
+            crosswalk_yield: None,
+            page_analytics: None,
         }
     }
+
+    pub fn parse_step_size(x: &str) -> Result<Duration, String> {
+        let step_size = Duration::parse(x).map_err(|err| err.to_string())?;
+        if step_size <= Duration::ZERO {
+            return Err(format!("step size {} must be positive", step_size));
+        }
+        if step_size > MAX_STEP_SIZE {
+            return Err(format!(
+                "step size {} can't be more than {}",
+                step_size, MAX_STEP_SIZE
+            ));
+        }
+        Ok(step_size)
+    }
 }
 
 // Setup
 impl Sim {
     pub fn new(map: &Map, opts: SimOptions, timer: &mut Timer) -> Sim {
         let mut scheduler = Scheduler::new();
-        Sim {
-            driving: DrivingSimState::new(map, opts.recalc_lanechanging),
+        let scooters = ScooterSimState::new(map, opts.scooters);
+        if let Some(every) = scooters.rebalance_every() {
+            scheduler.push(Time::START_OF_DAY + every, Command::RebalanceScooters);
+        }
+        let ridehail = RideHailSimState::new(map, opts.ridehail);
+        if let Some(every) = ridehail.reposition_every() {
+            scheduler.push(Time::START_OF_DAY + every, Command::RepositionRideHail);
+        }
+        let detectors = DetectorSimState::new(opts.detectors, &mut scheduler);
+        let incidents = IncidentSimState::new(opts.incidents, &mut scheduler);
+        if opts.detect_gridlock {
+            scheduler.push(
+                Time::START_OF_DAY + GRIDLOCK_CHECK_EVERY,
+                Command::DetectGridlock,
+            );
+        }
+        let replay = opts.record_replay.map(|every| {
+            scheduler.push(Time::START_OF_DAY, Command::RecordReplayFrame);
+            Replay::new(map.get_name().to_string(), opts.run_name.clone(), every)
+        });
+        let rewind = opts.rewind_history.map(|keep| {
+            scheduler.push(Time::START_OF_DAY, Command::RecordRewindFrame);
+            Replay::rolling(
+                map.get_name().to_string(),
+                opts.run_name.clone(),
+                REWIND_FRAME_INTERVAL,
+                keep,
+            )
+        });
+        // A broken script shouldn't stop the whole simulation; just run without it
+        let mut script_err = None;
+        let script = opts
+            .script
+            .and_then(|(path, rng)| match SimScript::load(&path, map, rng) {
+                Ok(s) => {
+                    scheduler.push(Time::START_OF_DAY, Command::RunScript);
+                    Some(s)
+                }
+                Err(err) => {
+                    script_err = Some(err);
+                    None
+                }
+            });
+        let mut sim = Sim {
+            driving: DrivingSimState::new(
+                map,
+                opts.recalc_lanechanging,
+                opts.snow,
+                opts.reroute,
+                opts.detour_compliance,
+                opts.lane_changing,
+                opts.car_following,
+            ),
             parking: ParkingSimState::new(map, timer),
-            walking: WalkingSimState::new(),
+            walking: WalkingSimState::new(opts.jaywalking),
             intersections: IntersectionSimState::new(
                 map,
                 &mut scheduler,
                 opts.use_freeform_policy_everywhere,
                 opts.dont_block_the_box,
+                opts.block_the_box_overrides,
                 opts.break_turn_conflict_cycles,
+                opts.crosswalk_yield,
+            ),
+            detectors,
+            incidents,
+            transit: TransitSimState::new(opts.layovers, opts.bus_capacity),
+            trips: TripManager::new(
+                opts.pathfinding_upfront,
+                opts.drop_off_share,
+                scooters,
+                ridehail,
             ),
-            transit: TransitSimState::new(),
-            trips: TripManager::new(opts.pathfinding_upfront),
             pandemic: if let Some(rng) = opts.enable_pandemic_model {
                 Some(PandemicModel::new(rng))
             } else {
                 None
             },
+            replay,
+            rewind,
+            script,
             scheduler,
             time: Time::START_OF_DAY,
 
@@ -137,9 +326,26 @@ impl Sim {
             step_count: 0,
             trip_positions: None,
             alerts: opts.alerts,
+            step_size: opts.step_size,
+            wheelchair_share: opts.wheelchair_share,
+            gridlocks: Vec::new(),
 
-            analytics: Analytics::new(),
+            analytics: Analytics::with_custom_metrics(opts.custom_metrics),
+        };
+        if let Some(dir) = opts.page_analytics {
+            sim.analytics.page_old_data_to(dir);
         }
+        if let Some(weather) = opts.weather {
+            sim.set_weather(weather);
+        }
+        if let Some(err) = script_err {
+            sim.analytics.event(
+                Event::Alert(AlertLocation::Nil, err),
+                Time::START_OF_DAY,
+                map,
+            );
+        }
+        sim
     }
 
     pub fn make_spawner(&self) -> TripSpawner {
@@ -152,13 +358,29 @@ impl Sim {
             m.initialize(self.trips.get_all_people(), &mut self.scheduler);
         }
 
-        self.dispatch_events(Vec::new(), map);
+        self.dispatch_events(Vec::new(), map, &mut None);
     }
 
     pub fn get_free_onstreet_spots(&self, l: LaneID) -> Vec<ParkingSpot> {
         self.parking.get_free_onstreet_spots(l)
     }
 
+    // How many scooters are free along each sidewalk. Empty if there are no scooters.
+    pub fn get_available_scooters(&self) -> &BTreeMap<LaneID, usize> {
+        self.trips.scooters.get_available()
+    }
+
+    // How many ride-hail vehicles are idle, and how many people are waiting for one. None if
+    // there's no ride-hail service.
+    pub fn get_ridehail_status(&self) -> Option<(usize, usize)> {
+        let ridehail = &self.trips.ridehail;
+        if ridehail.is_enabled() {
+            Some((ridehail.num_idle(), ridehail.num_waiting()))
+        } else {
+            None
+        }
+    }
+
     pub fn get_free_offstreet_spots(&self, b: BuildingID) -> Vec<ParkingSpot> {
         self.parking.get_free_offstreet_spots(b)
     }
@@ -196,15 +418,20 @@ impl Sim {
                 Position::new(driving_lane, Distance::ZERO),
                 &vehicle,
                 b,
+                self.time,
                 map,
             )
             .get(0)
         {
             spot.clone()
         } else {
-            let (_, spot, _) =
-                self.parking
-                    .path_to_free_parking_spot(driving_lane, &vehicle, b, map)?;
+            let (_, spot, _) = self.parking.path_to_free_parking_spot(
+                driving_lane,
+                &vehicle,
+                b,
+                self.time,
+                map,
+            )?;
             spot
         };
 
@@ -237,74 +464,160 @@ impl Sim {
     }
 
     pub fn seed_bus_route(&mut self, route: &BusRoute, map: &Map, timer: &mut Timer) -> Vec<CarID> {
-        let mut results: Vec<CarID> = Vec::new();
+        let legs = self.transit.create_empty_route(route, map);
+
+        // If the route has a real schedule, follow it. Each bus starts at the first stop and
+        // leaves the map once it's back there. Only one SpawnBus per route is queued at a time;
+        // handling it queues the next.
+        if !route.spawn_times.is_empty() {
+            let next = route
+                .spawn_times
+                .iter()
+                .position(|t| *t >= self.time)
+                .unwrap_or_else(|| route.spawn_times.len());
+            // When starting partway through the day, the latest run that already left would be
+            // somewhere along the route. Start it from the beginning right away, so the route
+            // isn't empty until the next departure.
+            if next > 0 && self.time - route.spawn_times[next - 1] < LATE_BUS_RUN {
+                self.scheduler
+                    .push(self.time, Command::SpawnBus(route.id, next - 1));
+            } else if next < route.spawn_times.len() {
+                self.scheduler
+                    .push(route.spawn_times[next], Command::SpawnBus(route.id, next));
+            }
+            return Vec::new();
+        }
 
-        // Try to spawn just ONE bus anywhere.
+        // Otherwise, try to spawn just ONE bus anywhere.
         // TODO Be more realistic. One bus per stop is too much, one is too little.
-        for (next_stop_idx, req, mut path, end_dist) in
-            self.transit.create_empty_route(route, map).into_iter()
-        {
-            // For now, no desire for randomness. Caller can pass in list of specs if that ever
-            // changes.
-            let vehicle = VehicleSpec {
-                vehicle_type: VehicleType::Bus,
-                length: BUS_LENGTH,
-                max_speed: None,
+        for (next_stop_idx, req, path, end_dist) in legs {
+            if let Some(id) = self.spawn_bus(route, next_stop_idx, req, path, end_dist, map) {
+                return vec![id];
             }
-            .make(CarID(self.trips.new_car_id(), VehicleType::Bus), None);
-            let id = vehicle.id;
-
-            loop {
-                if path.is_last_step() {
-                    timer.warn(format!(
-                        "Giving up on seeding a bus headed towards stop {} of {} ({})",
-                        next_stop_idx, route.name, route.id
-                    ));
-                    break;
-                }
-                let start_lane = if let PathStep::Lane(l) = path.current_step() {
-                    l
-                } else {
-                    path.shift(map);
-                    // TODO Technically should update request, but it shouldn't matter
-                    continue;
-                };
-                if map.get_l(start_lane).length() < vehicle.length {
-                    path.shift(map);
-                    // TODO Technically should update request, but it shouldn't matter
-                    continue;
-                }
+            timer.warn(format!(
+                "Giving up on seeding a bus headed towards stop {} of {} ({})",
+                next_stop_idx, route.name, route.id
+            ));
+        }
+        // TODO Bigger failure
+        timer.warn(format!("Failed to make ANY buses for {}!", route.name));
+        Vec::new()
+    }
 
-                // Bypass some layers of abstraction that don't make sense for buses.
-                if self.driving.start_car_on_lane(
-                    self.time,
-                    CreateCar {
-                        start_dist: vehicle.length,
-                        vehicle: vehicle.clone(),
-                        req: req.clone(),
-                        router: Router::follow_bus_route(path.clone(), end_dist),
-                        maybe_parked_car: None,
-                        trip_and_person: None,
-                    },
-                    map,
-                    &self.intersections,
-                    &self.parking,
-                    &mut self.scheduler,
-                ) {
-                    self.transit.bus_created(id, route.id, next_stop_idx);
-                    self.analytics.record_demand(&path, map);
-                    results.push(id);
-                    return results;
-                } else {
-                    path.shift(map);
-                }
+    // Starts a bus somewhere along the path to the next stop, as close to the beginning as
+    // possible.
+    fn spawn_bus(
+        &mut self,
+        route: &BusRoute,
+        next_stop_idx: usize,
+        req: PathRequest,
+        mut path: Path,
+        end_dist: Distance,
+        map: &Map,
+    ) -> Option<CarID> {
+        // For now, no desire for randomness. Caller can pass in list of specs if that ever
+        // changes.
+        let vehicle = VehicleSpec {
+            vehicle_type: VehicleType::Bus,
+            length: BUS_LENGTH,
+            max_speed: None,
+        }
+        .make(CarID(self.trips.new_car_id(), VehicleType::Bus), None);
+        let id = vehicle.id;
+
+        loop {
+            if path.is_last_step() {
+                return None;
+            }
+            let start_lane = if let PathStep::Lane(l) = path.current_step() {
+                l
+            } else {
+                path.shift(map);
+                // TODO Technically should update request, but it shouldn't matter
+                continue;
+            };
+            if map.get_l(start_lane).length() < vehicle.length {
+                path.shift(map);
+                // TODO Technically should update request, but it shouldn't matter
+                continue;
+            }
+
+            // Bypass some layers of abstraction that don't make sense for buses.
+            if self.driving.start_car_on_lane(
+                self.time,
+                CreateCar {
+                    start_dist: vehicle.length,
+                    vehicle: vehicle.clone(),
+                    req: req.clone(),
+                    router: Router::follow_bus_route(path.clone(), end_dist),
+                    maybe_parked_car: None,
+                    trip_and_person: None,
+                },
+                map,
+                &self.intersections,
+                &self.parking,
+                &mut self.scheduler,
+            ) {
+                self.transit.bus_created(
+                    id,
+                    route.id,
+                    next_stop_idx,
+                    !route.spawn_times.is_empty(),
+                );
+                self.analytics.record_demand(&path, map);
+                return Some(id);
+            } else {
+                path.shift(map);
             }
         }
-        if results.is_empty() {
-            // TODO Bigger failure
-            timer.warn(format!("Failed to make ANY buses for {}!", route.name));
+    }
+
+    // Sends an emergency vehicle from a station to some building, leaving right away. The response
+    // time is recorded when it arrives.
+    pub fn dispatch_emergency_vehicle(
+        &mut self,
+        station: BuildingID,
+        scene: BuildingID,
+        map: &Map,
+    ) -> Result<CarID, String> {
+        let start = emergency_curb(station, map)
+            .ok_or_else(|| format!("{} has nowhere to leave from", station))?;
+        let end =
+            emergency_curb(scene, map).ok_or_else(|| format!("nowhere to stop at {}", scene))?;
+        if start == end {
+            return Err(format!("{} is already at {}", station, scene));
         }
-        results
+        let req = PathRequest {
+            start,
+            end,
+            constraints: PathConstraints::Car,
+        };
+        let path = map
+            .pathfind(req.clone())
+            .ok_or_else(|| format!("no route from {} to {}", station, scene))?;
+
+        let vehicle = VehicleSpec {
+            vehicle_type: VehicleType::Car,
+            length: EMERGENCY_VEHICLE_LENGTH,
+            max_speed: None,
+        }
+        .make(CarID(self.trips.new_car_id(), VehicleType::Car), None);
+        let id = vehicle.id;
+        self.scheduler.push(
+            self.time,
+            Command::SpawnCar(
+                CreateCar {
+                    start_dist: start.dist_along(),
+                    vehicle,
+                    router: Router::respond(path, end.dist_along(), station, scene, self.time),
+                    req,
+                    maybe_parked_car: None,
+                    trip_and_person: None,
+                },
+                true,
+            ),
+        );
+        Ok(id)
     }
 
     pub fn set_name(&mut self, name: String) {
@@ -400,6 +713,12 @@ impl Sim {
             return false;
         };
 
+        self.scheduler.prefetch_trip_paths(max_time, map);
+
+        // TODO Commands still run one at a time; only trip pathfinding above is parallel. Stepping
+        // independent regions at once needs the driving queues, intersections, and parking to stop
+        // sharing mutable state through the scheduler, and a benchmark on a big map to show it
+        // pays off.
         let mut halt = false;
         while let Some(time) = self.scheduler.peek_next_time() {
             if time > max_time {
@@ -418,6 +737,49 @@ impl Sim {
     }
 
     // If true, halt simulation because the callback said so.
+    // Raises an alert for every new gridlock
+    fn detect_gridlock(&mut self) -> Vec<Event> {
+        let waiting_on = self
+            .driving
+            .waiting_on(self.time, GRIDLOCK_MIN_WAIT, &self.intersections);
+        let mut events = Vec::new();
+        for cars in find_cycles(&waiting_on) {
+            // Still stuck in one we already know about?
+            let set: BTreeSet<CarID> = cars.iter().cloned().collect();
+            if self
+                .gridlocks
+                .iter()
+                .any(|g| g.cars.iter().cloned().collect::<BTreeSet<_>>() == set)
+            {
+                continue;
+            }
+
+            let turns: BTreeSet<TurnID> = cars
+                .iter()
+                .filter_map(|c| self.driving.get_next_turn(*c))
+                .collect();
+            let gridlock = Gridlock {
+                detected_at: self.time,
+                cars,
+                intersections: turns.iter().map(|t| t.parent).collect(),
+                turns,
+            };
+            events.push(Event::Alert(
+                match gridlock.intersections.iter().next() {
+                    Some(i) => AlertLocation::Intersection(*i),
+                    None => AlertLocation::Nil,
+                },
+                format!(
+                    "Gridlock #{}: {}",
+                    self.gridlocks.len(),
+                    gridlock.describe()
+                ),
+            ));
+            self.gridlocks.push(gridlock);
+        }
+        events
+    }
+
     fn do_step(
         &mut self,
         map: &Map,
@@ -478,6 +840,17 @@ impl Sim {
                     }
                     self.analytics
                         .record_demand(create_car.router.get_path(), map);
+                    if create_car.router.is_responding() {
+                        if let Some(Traversable::Turn(t)) = create_car.router.maybe_next() {
+                            self.intersections.preempt_signal(
+                                self.time,
+                                create_car.vehicle.id,
+                                t,
+                                map,
+                                &mut self.scheduler,
+                            );
+                        }
+                    }
                 } else if retry_if_no_room {
                     // TODO Record this in the trip log
                     self.scheduler.push(
@@ -501,7 +874,25 @@ impl Sim {
                     );
                 }
             }
-            Command::SpawnPed(create_ped) => {
+            Command::SpawnBus(r, idx) => {
+                let route = map.get_br(r);
+                let (next_stop_idx, req, path, end_dist) = self.transit.first_leg(r);
+                if self
+                    .spawn_bus(route, next_stop_idx, req, path, end_dist, map)
+                    .is_none()
+                {
+                    println!(
+                        "No room to spawn the {} bus for {}. Skipping it!",
+                        self.time.ampm_tostring(),
+                        route.name
+                    );
+                }
+                if let Some(t) = route.spawn_times.get(idx + 1) {
+                    self.scheduler
+                        .push((*t).max(self.time), Command::SpawnBus(r, idx + 1));
+                }
+            }
+            Command::SpawnPed(mut create_ped) => {
                 // Do the order a bit backwards so we don't have to clone the
                 // CreatePedestrian. spawn_ped can't fail.
                 self.trips
@@ -513,6 +904,13 @@ impl Sim {
                     TripPhaseType::Walking,
                 ));
                 self.analytics.record_demand(&create_ped.path, map);
+                // Crossings on the shortest path, whether or not this person can use them
+                self.analytics.record_crossings(&create_ped.path, map);
+                if ((create_ped.person.0 % 100) as f64) < self.wheelchair_share * 100.0 {
+                    if let Some(path) = map.pathfind_wheelchair(create_ped.req.clone()) {
+                        create_ped.path = path;
+                    }
+                }
 
                 // Maybe there's actually no work to do!
                 match (&create_ped.start.connection, &create_ped.goal.connection) {
@@ -572,11 +970,18 @@ impl Sim {
                     &mut self.scheduler,
                     &mut self.trips,
                     &mut self.transit,
+                    &mut self.driving,
                 );
             }
             Command::UpdateIntersection(i) => {
-                self.intersections
-                    .update_intersection(self.time, i, map, &mut self.scheduler);
+                self.intersections.update_intersection(
+                    self.time,
+                    i,
+                    map,
+                    &mut self.scheduler,
+                    &self.driving,
+                    &self.detectors,
+                );
             }
             Command::Callback(frequency) => {
                 self.scheduler
@@ -600,28 +1005,230 @@ impl Sim {
                     &mut self.scheduler,
                 );
             }
+            Command::RebalanceScooters => {
+                let every = self.trips.rebalance_scooters(map);
+                self.scheduler
+                    .push(self.time + every, Command::RebalanceScooters);
+            }
+            Command::RepositionRideHail => {
+                let every = self
+                    .trips
+                    .reposition_ridehail(self.time, map, &mut self.scheduler);
+                self.scheduler
+                    .push(self.time + every, Command::RepositionRideHail);
+            }
+            Command::RecordReplayFrame => {
+                // Cars in parking spots just sit there, so leave them out. Anything on the road,
+                // even idling or in the middle of parking, is kept.
+                let cars = self
+                    .driving
+                    .get_all_draw_cars(self.time, map, &self.transit);
+                let peds = self.get_all_draw_peds(map);
+                let replay = self.replay.as_mut().unwrap();
+                replay.record_frame(self.time, cars, peds, map);
+                self.scheduler.push(
+                    self.time + replay.frame_interval,
+                    Command::RecordReplayFrame,
+                );
+            }
+            Command::RecordRewindFrame => {
+                // Plain savestates don't keep history, so it just stops after loading one.
+                if self.rewind.is_some() {
+                    let cars = self
+                        .driving
+                        .get_all_draw_cars(self.time, map, &self.transit);
+                    let peds = self.get_all_draw_peds(map);
+                    let time = self.time;
+                    self.rewind
+                        .as_mut()
+                        .unwrap()
+                        .record_frame(time, cars, peds, map);
+                    self.scheduler.push(
+                        self.time + REWIND_FRAME_INTERVAL,
+                        Command::RecordRewindFrame,
+                    );
+                }
+            }
+            Command::SampleDetectors => {
+                self.detectors
+                    .sample(self.time, &self.driving, &mut self.scheduler);
+            }
+            Command::StartIncident(id) => {
+                let lanes = self.incidents.start(id, map);
+                self.driving
+                    .block_lanes(lanes, self.time, map, &mut self.scheduler);
+            }
+            Command::EndIncident(id) => {
+                let lanes = self.incidents.end(id, map);
+                self.driving.unblock_lanes(
+                    lanes,
+                    self.time,
+                    map,
+                    &mut self.intersections,
+                    &mut self.scheduler,
+                );
+            }
+            Command::DetectGridlock => {
+                events.extend(self.detect_gridlock());
+                self.scheduler
+                    .push(self.time + GRIDLOCK_CHECK_EVERY, Command::DetectGridlock);
+            }
+            Command::EndLayover(bus) => {
+                let create_car = self.transit.bus_ending_layover(bus);
+                let path = create_car.router.get_path().clone();
+                if self.driving.start_car_on_lane(
+                    self.time,
+                    create_car,
+                    map,
+                    &self.intersections,
+                    &self.parking,
+                    &mut self.scheduler,
+                ) {
+                    self.transit.bus_left_layover_space(bus);
+                    events.push(Event::PathAmended(path));
+                } else {
+                    // Wait for a gap in traffic
+                    self.scheduler
+                        .push(self.time + BLIND_RETRY_TO_SPAWN, Command::EndLayover(bus));
+                }
+            }
+            Command::RunScript => {
+                if let Some(ref mut script) = self.script {
+                    let actions = script.on_tick(self.time, map);
+                    events.extend(self.apply_script_actions(actions, map));
+                    self.scheduler
+                        .push(self.time + SCRIPT_TICK_EVERY, Command::RunScript);
+                }
+            }
         }
 
         // Record events at precisely the time they occur.
-        self.dispatch_events(events, map);
+        if self.dispatch_events(events, map, maybe_cb) {
+            halt = true;
+        }
 
         halt
     }
 
-    fn dispatch_events(&mut self, mut events: Vec<Event>, map: &Map) {
+    // If true, halt simulation because the callback said so.
+    fn dispatch_events(
+        &mut self,
+        mut events: Vec<Event>,
+        map: &Map,
+        maybe_cb: &mut Option<Box<dyn SimCallback>>,
+    ) -> bool {
+        let mut halt = false;
+        let mut script_actions = Vec::new();
         events.extend(self.trips.collect_events());
         events.extend(self.transit.collect_events());
         events.extend(self.driving.collect_events());
         events.extend(self.walking.collect_events());
         events.extend(self.intersections.collect_events());
         events.extend(self.parking.collect_events());
+        events.extend(self.detectors.collect_events());
         for ev in events {
             if let Some(ref mut m) = self.pandemic {
                 m.handle_event(self.time, &ev, &mut self.scheduler);
             }
 
+            if let Some(ref mut cb) = maybe_cb {
+                if cb.event(&ev, self.time, map) {
+                    halt = true;
+                }
+            }
+            if let Some(ref mut replay) = self.replay {
+                replay.events.push((self.time, ev.clone()));
+            }
+            if let Some(ref mut script) = self.script {
+                script_actions.extend(script.on_event(&ev, self.time, map));
+            }
+
             self.analytics.event(ev, self.time, map);
         }
+        // Don't show the script its own alerts, or it could keep reacting to them forever
+        for ev in self.apply_script_actions(script_actions, map) {
+            self.analytics.event(ev, self.time, map);
+        }
+        halt
+    }
+
+    // Returns alerts about anything that couldn't be done
+    fn apply_script_actions(&mut self, actions: Vec<ScriptAction>, map: &Map) -> Vec<Event> {
+        let mut alerts = Vec::new();
+        for action in actions {
+            let result = match action {
+                ScriptAction::CloseLane(l, duration) => self.script_close_lane(l, duration, map),
+                ScriptAction::SpawnTrip(from, to, mode) => {
+                    self.script_spawn_trip(from, to, mode, map)
+                }
+                ScriptAction::HoldSignal(i, phase, duration) => {
+                    if map.maybe_get_i(i).is_none() {
+                        Err(format!("{} doesn't exist", i))
+                    } else {
+                        self.intersections.hold_signal(
+                            self.time,
+                            i,
+                            phase,
+                            self.time + duration,
+                            map,
+                            &mut self.scheduler,
+                        )
+                    }
+                }
+                ScriptAction::Alert(msg) => Err(msg),
+            };
+            if let Err(msg) = result {
+                alerts.push(Event::Alert(AlertLocation::Nil, msg));
+            }
+        }
+        alerts
+    }
+
+    fn script_close_lane(
+        &mut self,
+        l: LaneID,
+        duration: Duration,
+        map: &Map,
+    ) -> Result<(), String> {
+        match map.maybe_get_l(l) {
+            Some(lane) if lane.is_sidewalk() || lane.is_parking() => {
+                Err(format!("{} can't be closed", l))
+            }
+            Some(_) => {
+                self.add_incident(Incident {
+                    lanes: vec![l],
+                    start: self.time,
+                    end: self.time + duration,
+                });
+                Ok(())
+            }
+            None => Err(format!("{} doesn't exist", l)),
+        }
+    }
+
+    fn script_spawn_trip(
+        &mut self,
+        from: BuildingID,
+        to: BuildingID,
+        mode: TripMode,
+        map: &Map,
+    ) -> Result<(), String> {
+        if map.maybe_get_b(from).is_none() || map.maybe_get_b(to).is_none() || from == to {
+            return Err(format!("can't spawn a trip from {} to {}", from, to));
+        }
+        // Like spawning trips from the UI. Keep the run name; instantiate overwrites it.
+        let mut scenario = Scenario::empty(map, &self.run_name);
+        scenario.people.push(PersonSpec {
+            id: PersonID(self.trips.get_all_people().len()),
+            orig_id: None,
+            trips: vec![IndividTrip {
+                depart: self.time,
+                trip: SpawnTrip::new(TripEndpoint::Bldg(from), TripEndpoint::Bldg(to), mode, map),
+            }],
+        });
+        let mut rng = self.script.as_mut().unwrap().fork_rng();
+        scenario.instantiate(self, map, &mut rng, &mut Timer::throwaway());
+        Ok(())
     }
 
     pub fn timed_step(
@@ -740,7 +1347,7 @@ impl Sim {
 
         loop {
             // TODO Regular printing doesn't happen if we use a time_limit :\
-            let dt = time_limit.unwrap_or_else(|| Duration::seconds(30.0));
+            let dt = time_limit.unwrap_or(self.step_size);
 
             match panic::catch_unwind(panic::AssertUnwindSafe(|| {
                 self.timed_step(map, dt, &mut None, &mut Timer::throwaway());
@@ -867,6 +1474,105 @@ impl Sim {
         Ok(sim)
     }
 
+    pub fn checkpoint_dir(map: &Map) -> String {
+        abstutil::path_all_checkpoints(map.get_name(), &map.get_edits().edits_name)
+    }
+
+    // Unlike a savestate, a checkpoint keeps everything: the analytics so far, the pandemic model,
+    // and the exact path of every agent and queued command. Resuming from one behaves exactly like
+    // never having stopped, so it's possible to branch different experiments from the same moment.
+    // The map's edits must be saved under a name, so the same map can be rebuilt later.
+    pub fn save_checkpoint(&mut self, map: &Map, name: &str) -> String {
+        self.edits_name = map.get_edits().edits_name.clone();
+        let path = abstutil::path_checkpoint(&self.map_name, &self.edits_name, name);
+        abstutil::write_binary(
+            path.clone(),
+            &CheckpointRef {
+                sim: self,
+                analytics: &self.analytics,
+                pandemic: &self.pandemic,
+                replay: &self.replay,
+                rewind: &self.rewind,
+                alerts: &self.alerts,
+                step_size: self.step_size,
+            },
+        );
+        path
+    }
+
+    // The caller has to make sure the map matches the checkpoint.
+    pub fn load_checkpoint(path: String, timer: &mut Timer) -> Result<Sim, std::io::Error> {
+        let checkpoint: Checkpoint = abstutil::maybe_read_binary(path, timer)?;
+        Ok(checkpoint.into_sim())
+    }
+
+    // An in-memory checkpoint. It's a full copy of the Sim, except trips that haven't started yet
+    // don't keep their paths, just like in a savestate, and any replay or rewind history being
+    // recorded is left out.
+    pub fn snapshot(&mut self) -> Snapshot {
+        let restore = self.scheduler.before_savestate();
+        let bytes = abstutil::to_binary(&CheckpointRef {
+            sim: self,
+            analytics: &self.analytics,
+            pandemic: &self.pandemic,
+            replay: &None,
+            rewind: &None,
+            alerts: &self.alerts,
+            step_size: self.step_size,
+        });
+        self.scheduler.after_savestate(restore);
+        Snapshot {
+            time: self.time,
+            bytes,
+        }
+    }
+
+    // The caller has to make sure the map has the same edits as when the snapshot was taken.
+    // Recordings weren't saved in the snapshot, so the current ones carry on from the earlier time.
+    pub fn restore_snapshot(&mut self, snapshot: &Snapshot, map: &Map, timer: &mut Timer) {
+        let checkpoint: Checkpoint = abstutil::from_binary(&snapshot.bytes).unwrap();
+        let mut sim = checkpoint.into_sim();
+        sim.replay = self.replay.take().map(|mut r| {
+            r.forget_after(snapshot.time);
+            r
+        });
+        sim.rewind = self.rewind.take().map(|mut r| {
+            r.forget_after(snapshot.time);
+            r
+        });
+        sim.restore_paths(map, timer);
+        *self = sim;
+    }
+
+    pub fn is_recording_replay(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    // Saves everything recorded so far, returning the path. None if the sim isn't recording.
+    pub fn save_replay(&mut self, map: &Map) -> Option<String> {
+        let replay = self.replay.as_mut()?;
+        replay.edits_name = map.get_edits().edits_name.clone();
+        let path = abstutil::path_replay(
+            &self.map_name,
+            &format!("{}_{}", self.run_name, self.time.as_filename()),
+        );
+        abstutil::write_binary(path.clone(), replay);
+        Some(path)
+    }
+
+    pub fn is_keeping_history(&self) -> bool {
+        self.rewind.is_some()
+    }
+
+    // Where everybody was over the last little while, ready to be played back from the most recent
+    // moment. None if the sim isn't keeping history.
+    pub fn rewind_history(&self, map: &Map) -> Option<Replay> {
+        let mut history = self.rewind.clone()?;
+        history.edits_name = map.get_edits().edits_name.clone();
+        history.seek(self.time);
+        Some(history)
+    }
+
     pub fn restore_paths(&mut self, map: &Map, timer: &mut Timer) {
         let paths = timer.parallelize(
             "calculate paths",
@@ -877,12 +1583,69 @@ impl Sim {
     }
 }
 
+// Everything that a plain savestate skips gets written alongside the Sim. These two have to have
+// the same fields in the same order.
+#[derive(Serialize)]
+struct CheckpointRef<'a> {
+    sim: &'a Sim,
+    analytics: &'a Analytics,
+    pandemic: &'a Option<PandemicModel>,
+    replay: &'a Option<Replay>,
+    rewind: &'a Option<Replay>,
+    alerts: &'a AlertHandler,
+    step_size: Duration,
+}
+
+#[derive(Deserialize)]
+struct Checkpoint {
+    sim: Sim,
+    analytics: Analytics,
+    pandemic: Option<PandemicModel>,
+    replay: Option<Replay>,
+    rewind: Option<Replay>,
+    alerts: AlertHandler,
+    step_size: Duration,
+}
+
+impl Checkpoint {
+    fn into_sim(self) -> Sim {
+        let mut sim = self.sim;
+        sim.analytics = self.analytics;
+        sim.pandemic = self.pandemic;
+        sim.replay = self.replay;
+        sim.rewind = self.rewind;
+        sim.alerts = self.alerts;
+        sim.step_size = self.step_size;
+        sim
+    }
+}
+
+// A moment of the simulation to undo back to. See Sim::snapshot.
+pub struct Snapshot {
+    pub time: Time,
+    bytes: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn size_bytes(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
 // Queries of all sorts
 impl Sim {
     pub fn time(&self) -> Time {
         self.time
     }
 
+    // What this is running on, as names to load
+    pub fn get_map_name(&self) -> &str {
+        &self.map_name
+    }
+    pub fn get_edits_name(&self) -> &str {
+        &self.edits_name
+    }
+
     pub fn is_done(&self) -> bool {
         self.trips.is_done()
     }
@@ -936,15 +1699,58 @@ impl Sim {
         }
     }
 
+    // When an active agent is expected to reach the end of the current leg of its trip, based on
+    // the rest of its route and current traffic. Updates as conditions change. None for bus
+    // passengers, since the bus decides when they get off.
+    pub fn predict_arrival(&self, id: AgentID, map: &Map) -> Option<Time> {
+        match id {
+            AgentID::Pedestrian(id) => self.walking.predict_arrival(id, self.time, map),
+            AgentID::Car(id) => self.driving.predict_arrival(id, self.time, map),
+            AgentID::BusPassenger(_, _) => None,
+        }
+    }
+
+    // Every bus expected to reach this stop, soonest first, with the route it's running. Buses
+    // laying over off the road aren't included until they pull back out.
+    pub fn predict_bus_arrivals(
+        &self,
+        stop: BusStopID,
+        map: &Map,
+    ) -> Vec<(Time, CarID, BusRouteID)> {
+        let mut results = Vec::new();
+        for route in map.get_routes_serving_stop(stop) {
+            for (bus, _) in self.transit.buses_for_route(route.id) {
+                if let Some(time) = self
+                    .transit
+                    .legs_until(bus, stop)
+                    .and_then(|legs| self.driving.predict_bus_arrival(bus, legs, self.time, map))
+                {
+                    results.push((time, bus, route.id));
+                }
+            }
+        }
+        results.sort_by_key(|(t, _, _)| *t);
+        results
+    }
+
     // TODO Temporary until we figure out all the info to expose
     pub fn bus_properties(&self, car: CarID, map: &Map) -> Vec<(String, String)> {
         let passengers = self.transit.get_passengers(car);
+        let capacity = self.transit.bus_capacity(car);
         vec![
             (
                 "Route".to_string(),
                 map.get_br(self.transit.bus_route(car)).name.clone(),
             ),
-            ("Passengers".to_string(), passengers.len().to_string()),
+            (
+                "Passengers".to_string(),
+                format!(
+                    "{} / {} ({}% full)",
+                    passengers.len(),
+                    capacity,
+                    100 * passengers.len() / capacity.max(1)
+                ),
+            ),
         ]
     }
 
@@ -990,6 +1796,9 @@ impl Sim {
             .get_owner_of_car(id)
             .or_else(|| self.parking.get_owner_of_car(id))
     }
+    pub fn is_emergency_vehicle(&self, id: CarID) -> bool {
+        self.driving.is_responding(id)
+    }
     pub fn lookup_parked_car(&self, id: CarID) -> Option<&ParkedCar> {
         self.parking.lookup_parked_car(id)
     }
@@ -1040,6 +1849,11 @@ impl Sim {
         self.driving.get_all_driving_paths()
     }
 
+    // Roads with no vehicles along them are missing.
+    pub fn road_speed_ratios(&self, map: &Map) -> BTreeMap<RoadID, f64> {
+        self.driving.road_speed_ratios(self.time, map)
+    }
+
     pub fn trace_route(
         &self,
         id: AgentID,
@@ -1105,10 +1919,21 @@ impl Sim {
     pub fn get_blocked_by(&self, a: AgentID) -> HashSet<AgentID> {
         self.intersections.get_blocked_by(a)
     }
+    pub fn enforces_dont_block_the_box(&self, i: IntersectionID, map: &Map) -> bool {
+        self.intersections.enforces_dont_block_the_box(i, map)
+    }
+
+    pub fn average_speeds(&self) -> BTreeMap<LaneID, Speed> {
+        self.driving.average_speeds()
+    }
 
     pub fn location_of_buses(&self, route: BusRouteID, map: &Map) -> Vec<(CarID, Pt2D)> {
         let mut results = Vec::new();
         for (car, _) in self.transit.buses_for_route(route) {
+            if let Some(pos) = self.transit.get_layover_space(car) {
+                results.push((car, pos.pt(map)));
+                continue;
+            }
             // TODO This is a slow, indirect method!
             results.push((
                 car,
@@ -1122,7 +1947,13 @@ impl Sim {
     pub fn status_of_buses(&self, route: BusRouteID) -> Vec<(CarID, usize, f64)> {
         let mut results = Vec::new();
         for (bus, stop_idx) in self.transit.buses_for_route(route) {
-            results.push((bus, stop_idx, self.driving.percent_along_route(bus)));
+            // Laying over off the road, at the very start of the route
+            let percent = if self.transit.get_layover_space(bus).is_some() {
+                0.0
+            } else {
+                self.driving.percent_along_route(bus)
+            };
+            results.push((bus, stop_idx, percent));
         }
         results
     }
@@ -1136,6 +1967,15 @@ impl Sim {
             .find_blockage_front(car, map, &self.intersections)
     }
 
+    // Includes cars and bikes on every lane of the road, whether they're moving or queued.
+    pub fn num_vehicles_on_road(&self, r: RoadID, map: &Map) -> usize {
+        map.get_r(r)
+            .all_lanes()
+            .into_iter()
+            .map(|l| self.driving.count_vehicles_on(Traversable::Lane(l)))
+            .sum()
+    }
+
     // For intersections with an agent waiting beyond some threshold, return when they started
     // waiting. Sorted by earliest waiting (likely the root cause of gridlock).
     pub fn delayed_intersections(&self, threshold: Duration) -> Vec<(IntersectionID, Time)> {
@@ -1143,6 +1983,16 @@ impl Sim {
             .delayed_intersections(self.time, threshold)
     }
 
+    // Actuated signals don't just depend on the time, so use this instead of asking the map.
+    pub fn current_phase_and_remaining_time<'a>(
+        &self,
+        i: IntersectionID,
+        map: &'a Map,
+    ) -> (usize, &'a Phase, Duration) {
+        self.intersections
+            .current_phase_and_remaining_time(self.time, map.get_traffic_signal(i))
+    }
+
     pub fn bldg_to_people(&self, b: BuildingID) -> Vec<PersonID> {
         self.trips.bldg_to_people(b)
     }
@@ -1194,6 +2044,75 @@ impl Sim {
         }
     }
 
+    // Takes effect immediately, so enforcement can be compared at one intersection mid-run.
+    pub fn set_dont_block_the_box(&mut self, i: IntersectionID, enforce: bool, map: &Map) {
+        self.intersections
+            .set_dont_block_the_box(self.time, i, enforce, &mut self.scheduler, map);
+    }
+
+    // Starts reporting right away. Returns the ID used in the detector's readings.
+    pub fn add_detector(&mut self, detector: Detector) -> usize {
+        self.detectors.add(detector, self.time, &mut self.scheduler)
+    }
+
+    pub fn get_detectors(&self) -> Vec<&Detector> {
+        self.detectors.get_all()
+    }
+
+    // Returns the incident's ID. If it should've already started, it starts right away.
+    pub fn add_incident(&mut self, incident: Incident) -> usize {
+        self.incidents.add(incident, self.time, &mut self.scheduler)
+    }
+
+    pub fn get_incidents(&self) -> Vec<&Incident> {
+        self.incidents.get_all()
+    }
+
+    pub fn get_blocked_lanes(&self) -> BTreeSet<LaneID> {
+        self.incidents.blocked_lanes()
+    }
+
+    // Replaces the weather for the whole day. Who takes transit instead of walking or biking is
+    // decided when a scenario is instantiated, so changing the weather later only affects speeds.
+    pub fn set_weather(&mut self, weather: Weather) {
+        self.driving.set_weather(weather.clone());
+        self.walking.set_weather(weather);
+    }
+
+    pub fn get_weather(&self) -> &Weather {
+        self.walking.get_weather()
+    }
+
+    // Only affects vehicles created from now on
+    pub fn set_car_following(&mut self, model: CarFollowingModel) {
+        self.driving.set_car_following(model);
+    }
+
+    pub fn get_car_following(&self) -> &CarFollowingModel {
+        self.driving.get_car_following()
+    }
+
+    // Picks up map edits that can be applied live (see EditCmd::can_apply_live) without restarting
+    // the day. The map's pathfinding must already be updated. If this fails, somebody is stranded
+    // by the edits, and the simulation has to start over.
+    pub fn handle_live_edits(&mut self, map: &Map) -> Result<(), String> {
+        self.driving.handle_live_edits(
+            self.time,
+            map,
+            &mut self.intersections,
+            &mut self.scheduler,
+        )?;
+        self.scheduler.handle_live_edits(map)?;
+        self.intersections
+            .handle_live_edits(self.time, map, &mut self.scheduler);
+        self.edits_name = map.get_edits().edits_name.clone();
+        Ok(())
+    }
+
+    pub fn get_gridlocks(&self) -> &Vec<Gridlock> {
+        &self.gridlocks
+    }
+
     pub fn clear_alerts(&mut self) -> Vec<(Time, AlertLocation, String)> {
         std::mem::replace(&mut self.analytics.alerts, Vec::new())
     }
@@ -1203,6 +2122,11 @@ impl Sim {
 pub trait SimCallback: downcast_rs::Downcast {
     // Run at some scheduled time. If this returns true, halt simulation.
     fn run(&mut self, sim: &Sim, map: &Map) -> bool;
+    // Run for every event at the moment it happens, regardless of set_periodic_callback. If this
+    // returns true, halt simulation.
+    fn event(&mut self, _: &Event, _: Time, _: &Map) -> bool {
+        false
+    }
 }
 downcast_rs::impl_downcast!(SimCallback);
 
@@ -0,0 +1,52 @@
+use geom::{Distance, Speed};
+use map_model::{Map, Traversable};
+use serde::{Deserialize, Serialize};
+
+// Winter storm conditions. Roads designated as snow routes (a map edit) get plowed and stay at
+// full speed; everything else is slower and fits fewer vehicles. Usually loaded from a JSON file
+// with --snow; anything missing gets the default.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnowConditions {
+    // Multiplies the speed of every vehicle off of snow routes
+    pub speed_factor: f64,
+    // Multiplies how much of each lane off of snow routes vehicles can queue along
+    pub capacity_factor: f64,
+}
+
+impl Default for SnowConditions {
+    fn default() -> SnowConditions {
+        SnowConditions {
+            speed_factor: 0.5,
+            capacity_factor: 0.5,
+        }
+    }
+}
+
+impl SnowConditions {
+    pub(crate) fn speed_on(&self, speed: Speed, on: Traversable, map: &Map) -> Speed {
+        if on_snow_route(on, map) {
+            speed
+        } else {
+            speed * self.speed_factor
+        }
+    }
+
+    // Turns aren't limited; only lanes have room for vehicles to queue up.
+    pub(crate) fn capacity_of(&self, on: Traversable, map: &Map) -> Distance {
+        let len = on.length(map);
+        match on {
+            Traversable::Lane(_) if !on_snow_route(on, map) => len * self.capacity_factor,
+            _ => len,
+        }
+    }
+}
+
+// A turn counts as part of the road it leads to.
+fn on_snow_route(on: Traversable, map: &Map) -> bool {
+    let r = match on {
+        Traversable::Lane(l) => map.get_l(l).parent,
+        Traversable::Turn(t) => map.get_l(t.dst).parent,
+    };
+    map.get_r(r).snow_route
+}
@@ -1,9 +1,9 @@
 use crate::{
-    CarID, Event, PedestrianID, PersonID, Router, Scheduler, TripID, TripManager, TripPhaseType,
-    WalkingSimState,
+    CarID, CreateCar, Event, PedestrianID, PersonID, Router, Scheduler, TripID, TripManager,
+    TripPhaseType, Vehicle, WalkingSimState, DEFAULT_BUS_CAPACITY,
 };
 use abstutil::{deserialize_btreemap, serialize_btreemap};
-use geom::{Distance, Time};
+use geom::{Distance, Duration, Time};
 use map_model::{
     BusRoute, BusRouteID, BusStopID, Map, Path, PathConstraints, PathRequest, Position,
 };
@@ -13,6 +13,70 @@ use std::collections::BTreeMap;
 // These index stops along a route, not stops along a single sidewalk.
 type StopIdx = usize;
 
+// Buses rest at the first stop of their route, the terminal, before starting the next run, to
+// recover from delays. Usually loaded from a JSON file with --layovers, like:
+//
+// {
+//   "recovery_time": 300.0,
+//   "spaces": [[{ "sidewalk": 123, "idx": 0 }, 2]]
+// }
+//
+// spaces says how many buses fit in the layover bay at a terminal stop, off the road. Buses at a
+// terminal without a free space lay over in the lane, blocking it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoverConfig {
+    pub recovery_time: Duration,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub spaces: BTreeMap<BusStopID, usize>,
+}
+
+impl Default for LayoverConfig {
+    fn default() -> LayoverConfig {
+        LayoverConfig {
+            recovery_time: Duration::minutes(5),
+            spaces: BTreeMap::new(),
+        }
+    }
+}
+
+// How many passengers fit on the buses of each route. Usually loaded from a JSON file with
+// --bus_capacity, like:
+//
+// {
+//   "default": 60,
+//   "per_route": [[12, 120]]
+// }
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BusCapacityConfig {
+    // For routes not listed
+    pub default: usize,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub per_route: BTreeMap<BusRouteID, usize>,
+}
+
+impl Default for BusCapacityConfig {
+    fn default() -> BusCapacityConfig {
+        BusCapacityConfig {
+            default: DEFAULT_BUS_CAPACITY,
+            per_route: BTreeMap::new(),
+        }
+    }
+}
+
+impl BusCapacityConfig {
+    fn for_route(&self, route: BusRouteID) -> usize {
+        self.per_route.get(&route).cloned().unwrap_or(self.default)
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 struct StopForRoute {
     id: BusStopID,
@@ -34,6 +98,11 @@ struct Bus {
     route: BusRouteID,
     // Where does each passenger want to deboard?
     passengers: Vec<(PersonID, BusStopID)>,
+    // Nobody else can board once there are this many passengers
+    capacity: usize,
+    // Buses following a real schedule make one run, from the first stop back around to it, then
+    // leave. Otherwise they loop forever.
+    scheduled: bool,
     state: BusState,
 }
 
@@ -43,6 +112,15 @@ enum BusState {
     AtStop(StopIdx),
 }
 
+// What a bus does after passengers get on and off at a stop
+pub enum BusStopAction {
+    Depart,
+    // How long to lay over at the terminal, and whether there's a free layover space to do it in
+    LayOver(Duration, bool),
+    // The bus finished its scheduled run and leaves the map
+    Retire,
+}
+
 // This kind of acts like TripManager, managing transitions... but a bit more statefully.
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub struct TransitSimState {
@@ -62,16 +140,30 @@ pub struct TransitSimState {
         deserialize_with = "deserialize_btreemap"
     )]
     peds_waiting: BTreeMap<BusStopID, Vec<(PedestrianID, BusRouteID, BusStopID, Time)>>,
+    // None means buses turn around at the terminal like at any other stop
+    #[serde(default)]
+    layovers: Option<LayoverConfig>,
+    // Buses off the road in a layover bay, and where they'll pull back out
+    #[serde(
+        default,
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    in_layover_space: BTreeMap<CarID, (Vehicle, Position)>,
+    capacities: BusCapacityConfig,
 
     events: Vec<Event>,
 }
 
 impl TransitSimState {
-    pub fn new() -> TransitSimState {
+    pub fn new(layovers: Option<LayoverConfig>, capacities: BusCapacityConfig) -> TransitSimState {
         TransitSimState {
             buses: BTreeMap::new(),
             routes: BTreeMap::new(),
             peds_waiting: BTreeMap::new(),
+            layovers,
+            in_layover_space: BTreeMap::new(),
+            capacities,
             events: Vec::new(),
         }
     }
@@ -134,7 +226,26 @@ impl TransitSimState {
         stops
     }
 
-    pub fn bus_created(&mut self, bus: CarID, route: BusRouteID, next_stop_idx: StopIdx) {
+    // Like create_empty_route, but just for the leg starting at the first stop of an existing
+    // route.
+    pub fn first_leg(&self, route: BusRouteID) -> (StopIdx, PathRequest, Path, Distance) {
+        let route = &self.routes[&route];
+        let stop = &route.stops[0];
+        (
+            stop.next_stop_idx,
+            stop.req.clone(),
+            stop.path_to_next_stop.clone(),
+            route.stops[stop.next_stop_idx].driving_pos.dist_along(),
+        )
+    }
+
+    pub fn bus_created(
+        &mut self,
+        bus: CarID,
+        route: BusRouteID,
+        next_stop_idx: StopIdx,
+        scheduled: bool,
+    ) {
         self.routes.get_mut(&route).unwrap().buses.push(bus);
         self.buses.insert(
             bus,
@@ -142,6 +253,8 @@ impl TransitSimState {
                 car: bus,
                 route,
                 passengers: Vec::new(),
+                capacity: self.capacities.for_route(route),
+                scheduled,
                 state: BusState::DrivingToStop(next_stop_idx),
             },
         );
@@ -155,7 +268,7 @@ impl TransitSimState {
         walking: &mut WalkingSimState,
         scheduler: &mut Scheduler,
         map: &Map,
-    ) {
+    ) -> BusStopAction {
         let mut bus = self.buses.get_mut(&id).unwrap();
         match bus.state {
             BusState::DrivingToStop(stop_idx) => {
@@ -174,13 +287,22 @@ impl TransitSimState {
                     }
                 }
                 bus.passengers = still_riding;
+                // Scheduled buses only arrive at the first stop at the end of their run
+                let retiring = bus.scheduled && stop_idx == 0;
 
                 // Board new passengers.
                 let mut still_waiting = Vec::new();
                 for (ped, route, stop2, started_waiting) in
                     self.peds_waiting.remove(&stop1).unwrap_or_else(Vec::new)
                 {
-                    if bus.route == route {
+                    if bus.route != route
+                        || retiring
+                        || !reaches_before_end_of_run(bus, &self.routes[&route], stop_idx, stop2)
+                    {
+                        still_waiting.push((ped, route, stop2, started_waiting));
+                    } else if refuse_if_full(bus, ped, stop1, &mut self.events) {
+                        still_waiting.push((ped, route, stop2, started_waiting));
+                    } else {
                         let (trip, person) = trips.ped_boarded_bus(
                             now,
                             ped,
@@ -199,14 +321,88 @@ impl TransitSimState {
                             TripPhaseType::RidingBus(route, stop1, bus.car),
                         ));
                         bus.passengers.push((person, stop2));
-                    } else {
-                        still_waiting.push((ped, route, stop2, started_waiting));
                     }
                 }
                 self.peds_waiting.insert(stop1, still_waiting);
+
+                if retiring {
+                    // Everybody got off here; nobody rides past the end of a run
+                    assert!(bus.passengers.is_empty());
+                    let route = bus.route;
+                    self.buses.remove(&id);
+                    self.routes
+                        .get_mut(&route)
+                        .unwrap()
+                        .buses
+                        .retain(|b| *b != id);
+                    return BusStopAction::Retire;
+                }
+                if stop_idx != 0 {
+                    return BusStopAction::Depart;
+                }
+                let route = bus.route;
+                let cfg = match self.layovers {
+                    Some(ref cfg) => cfg,
+                    None => {
+                        return BusStopAction::Depart;
+                    }
+                };
+                let capacity = cfg.spaces.get(&stop1).cloned().unwrap_or(0);
+                let used = self
+                    .in_layover_space
+                    .keys()
+                    .filter(|b| self.routes[&self.buses[b].route].stops[0].id == stop1)
+                    .count();
+                let in_space = used < capacity;
+                self.events.push(Event::BusLaidOver(
+                    id,
+                    route,
+                    stop1,
+                    cfg.recovery_time,
+                    in_space,
+                ));
+                BusStopAction::LayOver(cfg.recovery_time, in_space)
             }
             BusState::AtStop(_) => unreachable!(),
+        }
+    }
+
+    pub fn bus_pulled_into_layover_space(&mut self, vehicle: Vehicle, pos: Position) {
+        self.in_layover_space.insert(vehicle.id, (vehicle, pos));
+    }
+
+    // How to put a bus back on the road after laying over in a bay. It hasn't left yet; call
+    // bus_left_layover_space once there's room.
+    pub fn bus_ending_layover(&self, id: CarID) -> CreateCar {
+        let (vehicle, pos) = self.in_layover_space[&id].clone();
+        let bus = &self.buses[&id];
+        let route = &self.routes[&bus.route];
+        let stop = match bus.state {
+            BusState::AtStop(idx) => &route.stops[idx],
+            BusState::DrivingToStop(_) => unreachable!(),
         };
+        CreateCar {
+            vehicle,
+            router: Router::follow_bus_route(
+                stop.path_to_next_stop.clone(),
+                route.stops[stop.next_stop_idx].driving_pos.dist_along(),
+            ),
+            req: stop.req.clone(),
+            start_dist: pos.dist_along(),
+            maybe_parked_car: None,
+            trip_and_person: None,
+        }
+    }
+
+    pub fn bus_left_layover_space(&mut self, id: CarID) {
+        self.in_layover_space.remove(&id).unwrap();
+        // bus_ending_layover already made the router
+        self.bus_departed_from_stop(id);
+    }
+
+    // Where a bus is waiting in a layover bay, off the road
+    pub fn get_layover_space(&self, id: CarID) -> Option<Position> {
+        self.in_layover_space.get(&id).map(|(_, pos)| *pos)
     }
 
     pub fn bus_departed_from_stop(&mut self, id: CarID) -> Router {
@@ -244,24 +440,30 @@ impl TransitSimState {
         if let Some(route) = self.routes.get(&route_id) {
             for bus in &route.buses {
                 if let BusState::AtStop(idx) = self.buses[bus].state {
-                    if route.stops[idx].id == stop1 {
-                        self.buses
-                            .get_mut(bus)
-                            .unwrap()
-                            .passengers
-                            .push((person, stop2));
-                        self.events.push(Event::TripPhaseStarting(
-                            trip,
-                            person,
-                            Some(PathRequest {
-                                start: map.get_bs(stop1).driving_pos,
-                                end: map.get_bs(stop2).driving_pos,
-                                constraints: PathConstraints::Bus,
-                            }),
-                            TripPhaseType::RidingBus(route_id, stop1, *bus),
-                        ));
-                        return Some(*bus);
+                    if route.stops[idx].id != stop1
+                        || !reaches_before_end_of_run(&self.buses[bus], route, idx, stop2)
+                    {
+                        continue;
+                    }
+                    if refuse_if_full(&self.buses[bus], ped, stop1, &mut self.events) {
+                        continue;
                     }
+                    self.buses
+                        .get_mut(bus)
+                        .unwrap()
+                        .passengers
+                        .push((person, stop2));
+                    self.events.push(Event::TripPhaseStarting(
+                        trip,
+                        person,
+                        Some(PathRequest {
+                            start: map.get_bs(stop1).driving_pos,
+                            end: map.get_bs(stop2).driving_pos,
+                            constraints: PathConstraints::Bus,
+                        }),
+                        TripPhaseType::RidingBus(route_id, stop1, *bus),
+                    ));
+                    return Some(*bus);
                 }
             }
         } else {
@@ -286,10 +488,33 @@ impl TransitSimState {
         &self.buses[&bus].passengers
     }
 
+    pub fn bus_capacity(&self, bus: CarID) -> usize {
+        self.buses[&bus].capacity
+    }
+
     pub fn bus_route(&self, bus: CarID) -> BusRouteID {
         self.buses[&bus].route
     }
 
+    // The paths a bus still has to follow after reaching the stop it's headed to (or waiting at)
+    // now, before it gets to this stop. None if the bus never visits the stop.
+    pub fn legs_until(&self, bus: CarID, stop: BusStopID) -> Option<Vec<&Path>> {
+        let bus = &self.buses[&bus];
+        let route = &self.routes[&bus.route];
+        let mut idx = match bus.state {
+            BusState::DrivingToStop(idx) | BusState::AtStop(idx) => idx,
+        };
+        let mut legs = Vec::new();
+        for _ in 0..route.stops.len() {
+            if route.stops[idx].id == stop {
+                return Some(legs);
+            }
+            legs.push(&route.stops[idx].path_to_next_stop);
+            idx = route.stops[idx].next_stop_idx;
+        }
+        None
+    }
+
     // also stop idx
     pub fn buses_for_route(&self, route: BusRouteID) -> Vec<(CarID, usize)> {
         if let Some(ref r) = self.routes.get(&route) {
@@ -314,3 +539,23 @@ impl TransitSimState {
         }
     }
 }
+
+// Scheduled buses only take passengers going somewhere before the end of their run. Looping buses
+// go everywhere eventually.
+fn reaches_before_end_of_run(bus: &Bus, route: &Route, at: StopIdx, stop2: BusStopID) -> bool {
+    !bus.scheduled
+        || route.stops[0].id == stop2
+        || route.stops[at + 1..].iter().any(|s| s.id == stop2)
+}
+
+// Nobody boards a full bus; they keep waiting for the next one. This is the one place that
+// decides, so every refused rider is recorded here.
+fn refuse_if_full(bus: &Bus, ped: PedestrianID, stop: BusStopID, events: &mut Vec<Event>) -> bool {
+    if bus.passengers.len() < bus.capacity {
+        return false;
+    }
+    events.push(Event::PassengerDeniedBoarding(
+        ped, bus.car, bus.route, stop,
+    ));
+    true
+}
@@ -1,7 +1,9 @@
+use crate::router::delivery_stop;
 use crate::{
-    AgentID, AlertLocation, CarID, Command, CreateCar, CreatePedestrian, DrivingGoal, Event,
-    OffMapLocation, OrigPersonID, ParkedCar, ParkingSimState, ParkingSpot, PedestrianID, PersonID,
-    Scheduler, SidewalkPOI, SidewalkSpot, TransitSimState, TripID, TripPhaseType, TripSpec,
+    can_reach, deadhead_dist, ridehail_curb, AgentID, AlertLocation, CarID, Command, CreateCar,
+    CreatePedestrian, DrivingGoal, Event, OffMapLocation, OrigPersonID, ParkedCar, ParkingSimState,
+    ParkingSpot, PedestrianID, PersonID, RideHailSimState, RideRequest, Router, Scheduler,
+    ScooterSimState, SidewalkPOI, SidewalkSpot, TransitSimState, TripID, TripPhaseType, TripSpec,
     Vehicle, VehicleSpec, VehicleType, WalkingSimState,
 };
 use abstutil::{deserialize_btreemap, serialize_btreemap, Counter};
@@ -25,22 +27,35 @@ pub struct TripManager {
     active_trip_mode: BTreeMap<AgentID, TripID>,
     unfinished_trips: usize,
     pub pathfinding_upfront: bool,
+    // From SimOptions
+    drop_off_share: f64,
 
     car_id_counter: usize,
+    pub(crate) scooters: ScooterSimState,
+    pub(crate) ridehail: RideHailSimState,
 
     events: Vec<Event>,
 }
 
 impl TripManager {
-    pub fn new(pathfinding_upfront: bool) -> TripManager {
+    pub fn new(
+        pathfinding_upfront: bool,
+        drop_off_share: f64,
+        scooters: ScooterSimState,
+        ridehail: RideHailSimState,
+    ) -> TripManager {
         TripManager {
             trips: Vec::new(),
             people: Vec::new(),
             active_trip_mode: BTreeMap::new(),
             unfinished_trips: 0,
-            car_id_counter: 0,
+            // The ride-hail fleet already took the first IDs
+            car_id_counter: ridehail.fleet_size(),
+            scooters,
+            ridehail,
             events: Vec::new(),
             pathfinding_upfront,
+            drop_off_share,
         }
     }
 
@@ -111,6 +126,7 @@ impl TripManager {
             Some(TripLeg::Remote(ref to)) => {
                 TripEndpoint::Border(map.all_incoming_borders()[0].id, Some(to.clone()))
             }
+            Some(TripLeg::RideHail(b)) => TripEndpoint::Bldg(*b),
             _ => unreachable!(),
         };
         let trip = Trip {
@@ -165,6 +181,12 @@ impl TripManager {
         self.active_trip_mode.insert(agent, t);
     }
 
+    // A delivery truck pulled into a loading zone, so it's off the road until it spawns again
+    pub fn truck_pulled_into_loading_zone(&mut self, car: CarID, blocked_time: Duration) {
+        let trip = self.active_trip_mode.remove(&AgentID::Car(car)).unwrap();
+        self.trips[trip.0].total_blocked_time += blocked_time;
+    }
+
     pub fn car_reached_parking_spot(
         &mut self,
         now: Time,
@@ -286,9 +308,12 @@ impl TripManager {
             return;
         };
 
-        let router = drive_to
+        let mut router = drive_to
             .make_router(path, map, parked_car.vehicle.vehicle_type)
             .unwrap();
+        if drops_off(trip.id, self.drop_off_share) {
+            router.drop_off_first(parked_car.vehicle.length, map);
+        }
         scheduler.push(
             now,
             Command::SpawnCar(
@@ -346,7 +371,11 @@ impl TripManager {
                 now,
                 Command::SpawnCar(
                     CreateCar::for_appearing(
-                        self.people[trip.person.0].get_vehicle(bike),
+                        if self.scooters.is_scooter(bike) {
+                            self.scooters.make_vehicle(bike, trip.person)
+                        } else {
+                            self.people[trip.person.0].get_vehicle(bike)
+                        },
                         driving_pos,
                         router,
                         req,
@@ -392,6 +421,9 @@ impl TripManager {
             }
             _ => unreachable!(),
         };
+        if self.scooters.is_scooter(bike) {
+            self.scooters.leave(bike, bike_rack.sidewalk_pos.lane());
+        }
 
         if !trip.spawn_ped(
             now,
@@ -529,12 +561,30 @@ impl TripManager {
             .remove(&AgentID::BusPassenger(person, bus))
             .unwrap()
             .0];
-        let start = match trip.legs.pop_front().unwrap() {
-            TripLeg::RideBus(_, stop) => SidewalkSpot::bus_stop(stop, map),
+        let (route, start) = match trip.legs.pop_front().unwrap() {
+            TripLeg::RideBus(route, stop) => (route, SidewalkSpot::bus_stop(stop, map)),
             _ => unreachable!(),
         };
         self.people[person.0].on_bus.take().unwrap();
 
+        // Trip planning only picks the first bus to ride. If a different route gets closer to the
+        // goal from here, walk to it and transfer.
+        if trip.legs.len() == 1 {
+            let goal = match trip.legs[0] {
+                TripLeg::Walk(ref spot) => spot.sidewalk_pos,
+                _ => unreachable!(),
+            };
+            if let Some((stop1, stop2, next_route)) =
+                map.should_use_transit(start.sidewalk_pos, goal)
+            {
+                if next_route != route {
+                    trip.legs.push_front(TripLeg::RideBus(next_route, stop2));
+                    trip.legs
+                        .push_front(TripLeg::Walk(SidewalkSpot::bus_stop(stop1, map)));
+                }
+            }
+        }
+
         if !trip.spawn_ped(
             now,
             start,
@@ -682,6 +732,14 @@ impl TripManager {
         trip.aborted = true;
         self.events.push(Event::TripAborted(trip.id));
         let person = trip.person;
+        for leg in &trip.legs {
+            if let TripLeg::Drive(c, _) = leg {
+                if self.scooters.is_scooter(*c) {
+                    self.scooters.abandon(*c);
+                }
+            }
+        }
+        self.ridehail.cancel(id);
 
         // Maintain consistentency for anyone listening to events
         if let PersonState::Inside(b) = self.people[person.0].state {
@@ -712,6 +770,7 @@ impl TripManager {
                             Position::new(driving_lane, Distance::ZERO),
                             &vehicle,
                             b,
+                            now,
                             map,
                         )
                         // TODO Could pick something closer, but meh, aborted trips are bugs anyway
@@ -719,7 +778,7 @@ impl TripManager {
                         .map(|(spot, _)| spot.clone())
                         .or_else(|| {
                             parking
-                                .path_to_free_parking_spot(driving_lane, &vehicle, b, map)
+                                .path_to_free_parking_spot(driving_lane, &vehicle, b, now, map)
                                 .map(|(_, spot, _)| spot)
                         })
                     {
@@ -756,6 +815,307 @@ impl TripManager {
         self.person_finished_trip(now, person, parking, scheduler, map);
     }
 
+    // If there's a free scooter close to the start of a long walk, rent it. Returns the new legs of
+    // the trip and how to walk to the scooter.
+    fn scooter_legs(
+        &mut self,
+        start: &SidewalkSpot,
+        goal: &SidewalkSpot,
+        map: &Map,
+    ) -> Option<(VecDeque<TripLeg>, SidewalkSpot, PathRequest, Path)> {
+        let goal_bldg = match goal.connection {
+            SidewalkPOI::Building(b) => b,
+            _ => {
+                return None;
+            }
+        };
+        let start_pt = start.sidewalk_pos.pt(map);
+        if !self
+            .scooters
+            .worth_riding(start_pt, goal.sidewalk_pos.pt(map))
+        {
+            return None;
+        }
+        let pickup = self.scooters.find_nearby(start_pt, map)?;
+        let walk_to = SidewalkSpot::bike_rack(pickup, map)?;
+        if let SidewalkPOI::BikeRack(driving_pos) = walk_to.connection {
+            // Don't start riding on a blackhole
+            if map.get_l(driving_pos.lane()).parking_blackhole.is_some() {
+                return None;
+            }
+        }
+        let drop_off = self.scooters.find_drop_off(goal_bldg, map)?;
+        let ride_to = DrivingGoal::ParkNear(drop_off);
+        let last_lane = ride_to.goal_pos(PathConstraints::Bike, map).lane();
+        let end_sidewalk = map.get_parent(last_lane).bike_to_sidewalk(last_lane)?;
+        if end_sidewalk == pickup {
+            return None;
+        }
+        let req = PathRequest {
+            start: start.sidewalk_pos,
+            end: walk_to.sidewalk_pos,
+            constraints: PathConstraints::Pedestrian,
+        };
+        let path = map.pathfind(req.clone())?;
+
+        let scooter = CarID(self.new_car_id(), VehicleType::Bike);
+        self.scooters.rent(scooter, pickup);
+        let legs = VecDeque::from(vec![
+            TripLeg::Walk(walk_to.clone()),
+            TripLeg::Drive(scooter, ride_to),
+            TripLeg::Walk(goal.clone()),
+        ]);
+        Some((legs, walk_to, req, path))
+    }
+
+    // Somebody walking the last leg of their trip just stepped onto a new sidewalk. If there's now
+    // a free scooter close by (maybe one the van just dropped off), switch to it. Returns the new
+    // goal of the walk and how to reach it from here.
+    pub fn ped_maybe_switch_to_scooter(
+        &mut self,
+        ped: PedestrianID,
+        pos: Position,
+        map: &Map,
+    ) -> Option<(SidewalkSpot, Path)> {
+        if !self.scooters.is_enabled() {
+            return None;
+        }
+        let trip = *self.active_trip_mode.get(&AgentID::Pedestrian(ped))?;
+        let goal = match self.trips[trip.0].legs.front() {
+            Some(TripLeg::Walk(ref spot)) if self.trips[trip.0].legs.len() == 1 => spot.clone(),
+            _ => {
+                return None;
+            }
+        };
+        let start = SidewalkSpot::suddenly_appear(pos.lane(), pos.dist_along(), map);
+        let (legs, walk_to, _, path) = self.scooter_legs(&start, &goal, map)?;
+        self.trips[trip.0].legs = legs;
+        Some((walk_to, path))
+    }
+
+    // Returns when to do this next
+    pub fn rebalance_scooters(&mut self, map: &Map) -> Duration {
+        let (moved, dist) = self.scooters.rebalance(map);
+        self.events.push(Event::ScootersRebalanced(moved, dist));
+        self.scooters.rebalance_every().unwrap()
+    }
+
+    // A ride-hail vehicle finished stopping at the curb. If it was picking somebody up, returns the
+    // route to their destination and who's now riding. Otherwise the passenger gets out, and the
+    // vehicle heads to whoever's been waiting longest. None means the vehicle has nothing to do.
+    pub fn ridehail_vehicle_ready(
+        &mut self,
+        now: Time,
+        car: CarID,
+        at: Position,
+        map: &Map,
+        parking: &mut ParkingSimState,
+        scheduler: &mut Scheduler,
+    ) -> Option<(Router, Option<(TripID, PersonID)>)> {
+        match self.ridehail.current_job(car) {
+            Some((req, false)) if !self.trips[req.trip.0].aborted => {
+                if let Some((router, path_req)) = self.ridehail_route(car, at, req.goal, false, map)
+                {
+                    self.ridehail.picked_up(car);
+                    self.events.push(Event::TripPhaseStarting(
+                        req.trip,
+                        req.person,
+                        Some(path_req),
+                        TripPhaseType::Driving,
+                    ));
+                    self.agent_starting_trip_leg(AgentID::Car(car), req.trip);
+                    return Some((router, Some((req.trip, req.person))));
+                }
+                self.events.push(Event::Alert(
+                    AlertLocation::Person(req.person),
+                    format!(
+                        "{} picked up {}, but can't reach {}",
+                        car, req.person, req.goal
+                    ),
+                ));
+                self.abort_trip(now, req.trip, None, parking, scheduler, map);
+            }
+            Some((req, true)) => {
+                self.ridehail_passenger_exits(now, car, req, at, map, scheduler);
+            }
+            _ => {}
+        }
+
+        let next = self
+            .ridehail_next_job(now, car, at, map, parking, scheduler)
+            .map(|(router, _)| (router, None));
+        if next.is_none() {
+            self.ridehail.finished_job(car);
+        }
+        next
+    }
+
+    // A ride-hail vehicle with nothing to do pulled off the road. Maybe somebody's already waiting.
+    pub fn ridehail_vehicle_idle(
+        &mut self,
+        now: Time,
+        car: CarID,
+        at: Position,
+        map: &Map,
+        parking: &mut ParkingSimState,
+        scheduler: &mut Scheduler,
+    ) {
+        if let Some((router, req)) = self.ridehail_next_job(now, car, at, map, parking, scheduler) {
+            self.spawn_ridehail(now, car, at, router, req, scheduler);
+        } else {
+            self.ridehail.go_idle(car, at);
+        }
+    }
+
+    // Returns when to do this next
+    pub fn reposition_ridehail(
+        &mut self,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> Duration {
+        for (car, from, to) in self.ridehail.reposition(map) {
+            let req = PathRequest {
+                start: from,
+                end: to,
+                constraints: PathConstraints::Car,
+            };
+            if let Some(path) = map.pathfind(req.clone()) {
+                self.events.push(Event::RideHailDeadhead(
+                    car,
+                    deadhead_dist(&path, from, to, map),
+                ));
+                let router = Router::ridehail_reposition(path, to.dist_along());
+                self.spawn_ridehail(now, car, from, router, req, scheduler);
+            } else {
+                self.ridehail.go_idle(car, from);
+            }
+        }
+        self.ridehail.reposition_every().unwrap()
+    }
+
+    // Sends a free ride-hail vehicle to the oldest request it can reach.
+    fn ridehail_next_job(
+        &mut self,
+        now: Time,
+        car: CarID,
+        at: Position,
+        map: &Map,
+        parking: &mut ParkingSimState,
+        scheduler: &mut Scheduler,
+    ) -> Option<(Router, PathRequest)> {
+        while let Some((req, _)) = self
+            .ridehail
+            .next_job(car, at, |b| ridehail_curb(b, map).unwrap().0)
+        {
+            if let Some(route) = self.ridehail_route(car, at, req.pickup, true, map) {
+                return Some(route);
+            }
+            self.events.push(Event::Alert(
+                AlertLocation::Person(req.person),
+                format!(
+                    "{} can't reach {} to pick up {}",
+                    car, req.pickup, req.person
+                ),
+            ));
+            self.abort_trip(now, req.trip, None, parking, scheduler, map);
+        }
+        None
+    }
+
+    // How a ride-hail vehicle gets to the curb in front of a building. Driving without a passenger
+    // counts as deadheading.
+    fn ridehail_route(
+        &mut self,
+        car: CarID,
+        from: Position,
+        to: BuildingID,
+        empty: bool,
+        map: &Map,
+    ) -> Option<(Router, PathRequest)> {
+        let (end, in_zone) = ridehail_curb(to, map)?;
+        if !can_reach(from, end) {
+            return None;
+        }
+        let req = PathRequest {
+            start: from,
+            end,
+            constraints: PathConstraints::Car,
+        };
+        let path = map.pathfind(req.clone())?;
+        if empty {
+            self.events.push(Event::RideHailDeadhead(
+                car,
+                deadhead_dist(&path, from, end, map),
+            ));
+        }
+        Some((Router::ridehail_stop(path, end.dist_along(), in_zone), req))
+    }
+
+    fn spawn_ridehail(
+        &mut self,
+        now: Time,
+        car: CarID,
+        from: Position,
+        router: Router,
+        req: PathRequest,
+        scheduler: &mut Scheduler,
+    ) {
+        scheduler.push(
+            now,
+            Command::SpawnCar(
+                CreateCar {
+                    vehicle: self.ridehail.make_vehicle(car),
+                    router,
+                    req,
+                    start_dist: from.dist_along(),
+                    maybe_parked_car: None,
+                    trip_and_person: None,
+                },
+                true,
+            ),
+        );
+    }
+
+    // Like car_reached_parking_spot, but the passenger gets out right at the curb.
+    fn ridehail_passenger_exits(
+        &mut self,
+        now: Time,
+        car: CarID,
+        req: RideRequest,
+        at: Position,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) {
+        self.active_trip_mode.remove(&AgentID::Car(car));
+        let trip = &mut self.trips[req.trip.0];
+        match trip.legs.pop_front() {
+            Some(TripLeg::RideHail(b)) => {
+                assert_eq!(b, req.goal);
+            }
+            _ => unreachable!(),
+        }
+
+        // Step out onto the sidewalk next to the car, if there's one on this road
+        let bldg_sidewalk = map.get_b(req.goal).front_path.sidewalk;
+        let start = if map.get_l(bldg_sidewalk.lane()).parent == map.get_l(at.lane()).parent {
+            let pos = at.equiv_pos(bldg_sidewalk.lane(), Distance::ZERO, map);
+            SidewalkSpot::suddenly_appear(pos.lane(), pos.dist_along(), map)
+        } else {
+            SidewalkSpot::suddenly_appear(bldg_sidewalk.lane(), bldg_sidewalk.dist_along(), map)
+        };
+        if !trip.spawn_ped(
+            now,
+            start,
+            &self.people[trip.person.0],
+            map,
+            scheduler,
+            &mut self.events,
+        ) {
+            self.unfinished_trips -= 1;
+        }
+    }
+
     pub fn active_agents(&self) -> Vec<AgentID> {
         self.active_trip_mode.keys().cloned().collect()
     }
@@ -788,6 +1148,12 @@ impl TripManager {
             TripLeg::Remote(_) => {
                 return TripResult::RemoteTrip;
             }
+            TripLeg::RideHail(_) => match self.ridehail.vehicle_for(id) {
+                Some(car) => AgentID::Car(car),
+                None => {
+                    return TripResult::ModeChange;
+                }
+            },
         };
         if self.active_trip_mode.get(&a) == Some(&id) {
             TripResult::Ok(a)
@@ -965,9 +1331,12 @@ impl TripManager {
                 let vehicle = person.get_vehicle(use_vehicle);
                 assert!(parking.lookup_parked_car(vehicle.id).is_none());
                 let req = maybe_req.unwrap();
-                if let Some(router) =
+                if let Some(mut router) =
                     maybe_path.and_then(|path| goal.make_router(path, map, vehicle.vehicle_type))
                 {
+                    if drops_off(trip, self.drop_off_share) {
+                        router.drop_off_first(vehicle.length, map);
+                    }
                     scheduler.push(
                         now,
                         Command::SpawnCar(
@@ -989,6 +1358,44 @@ impl TripManager {
                     self.abort_trip(now, trip, Some(vehicle), parking, scheduler, map);
                 }
             }
+            TripSpec::Delivery {
+                start_pos,
+                truck,
+                bldg,
+                dwell,
+                goal,
+            } => {
+                assert_eq!(person.state, PersonState::OffMap);
+                self.events.push(Event::PersonEntersMap(
+                    person.id,
+                    TripMode::Drive,
+                    map.get_l(start_pos.lane()).src_i,
+                    None,
+                ));
+                person.state = PersonState::Trip(trip);
+
+                let vehicle = person.get_vehicle(truck);
+                let req = maybe_req.unwrap();
+                if let Some(path) = maybe_path {
+                    let (stop, in_zone) = delivery_stop(bldg, map).unwrap();
+                    let router = Router::deliver(path, stop.dist_along(), in_zone, dwell, goal);
+                    scheduler.push(
+                        now,
+                        Command::SpawnCar(
+                            CreateCar::for_appearing(
+                                vehicle, start_pos, router, req, trip, person.id,
+                            ),
+                            true,
+                        ),
+                    );
+                } else {
+                    self.events.push(Event::Alert(
+                        AlertLocation::Person(person.id),
+                        format!("Delivery trip couldn't find the first path {}", req),
+                    ));
+                    self.abort_trip(now, trip, None, parking, scheduler, map);
+                }
+            }
             TripSpec::NoRoomToSpawn { i, use_vehicle, .. } => {
                 self.events.push(Event::Alert(
                     AlertLocation::Intersection(i),
@@ -1090,25 +1497,45 @@ impl TripManager {
                     }
                 );
                 person.state = PersonState::Trip(trip);
+                let (person, ped, ped_speed) = (person.id, person.ped, person.ped_speed);
+
+                // Long walks might switch to a nearby scooter
+                if let Some((legs, walk_to, req, path)) = self.scooter_legs(&start, &goal, map) {
+                    self.trips[trip.0].legs = legs;
+                    scheduler.push(
+                        now,
+                        Command::SpawnPed(CreatePedestrian {
+                            id: ped,
+                            speed: ped_speed,
+                            start,
+                            goal: walk_to,
+                            path,
+                            req,
+                            trip,
+                            person,
+                        }),
+                    );
+                    return;
+                }
 
                 let req = maybe_req.unwrap();
                 if let Some(path) = maybe_path {
                     scheduler.push(
                         now,
                         Command::SpawnPed(CreatePedestrian {
-                            id: person.ped,
-                            speed: person.ped_speed,
+                            id: ped,
+                            speed: ped_speed,
                             start,
                             goal,
                             path,
                             req,
                             trip,
-                            person: person.id,
+                            person,
                         }),
                     );
                 } else {
                     self.events.push(Event::Alert(
-                        AlertLocation::Person(person.id),
+                        AlertLocation::Person(person),
                         format!("JustWalking trip couldn't find the first path {}", req),
                     ));
                     self.abort_trip(now, trip, None, parking, scheduler, map);
@@ -1238,6 +1665,53 @@ impl TripManager {
                     TripPhaseType::Remote,
                 ));
             }
+            TripSpec::UsingRideHail { start, goal } => {
+                assert_eq!(person.state, PersonState::Inside(start));
+                person.state = PersonState::Trip(trip);
+                let person = person.id;
+                // Wait at the curb
+                self.events.push(Event::PersonLeavesBuilding(person, start));
+                self.events.push(Event::TripPhaseStarting(
+                    trip,
+                    person,
+                    None,
+                    TripPhaseType::WaitingForRideHail,
+                ));
+
+                let pickup = match (ridehail_curb(start, map), ridehail_curb(goal, map)) {
+                    (Some((pickup, _)), Some(_)) if start != goal => pickup,
+                    _ => {
+                        self.events.push(Event::Alert(
+                            AlertLocation::Person(person),
+                            format!(
+                                "UsingRideHail trip from {} to {} has nowhere to stop",
+                                start, goal
+                            ),
+                        ));
+                        self.abort_trip(now, trip, None, parking, scheduler, map);
+                        return;
+                    }
+                };
+                let req = RideRequest {
+                    trip,
+                    person,
+                    pickup: start,
+                    goal,
+                };
+                if let Some((car, at)) = self.ridehail.request(req, pickup, map) {
+                    if let Some((router, req)) = self.ridehail_route(car, at, start, true, map) {
+                        self.spawn_ridehail(now, car, at, router, req, scheduler);
+                    } else {
+                        // No path from where the vehicle was waiting
+                        self.ridehail.go_idle(car, at);
+                        self.events.push(Event::Alert(
+                            AlertLocation::Person(person),
+                            format!("{} can't reach {} to pick up {}", car, start, person),
+                        ));
+                        self.abort_trip(now, trip, None, parking, scheduler, map);
+                    }
+                }
+            }
         }
     }
 }
@@ -1326,6 +1800,8 @@ pub enum TripLeg {
     Drive(CarID, DrivingGoal),
     RideBus(BusRouteID, BusStopID),
     Remote(OffMapLocation),
+    // Wait for a ride-hail vehicle, then ride to this building
+    RideHail(BuildingID),
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord)]
@@ -1488,3 +1964,10 @@ impl TripEndpoint {
         }
     }
 }
+
+// Does this car trip stop to let somebody out before parking? Always the same answer for the same
+// trip, so runs stay comparable. Consecutive IDs are spread out, so it's not just the first trips
+// of the day.
+fn drops_off(trip: TripID, share: f64) -> bool {
+    (((trip.0 * 7919) % 1000) as f64) < share * 1000.0
+}
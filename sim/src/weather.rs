@@ -0,0 +1,169 @@
+use crate::{IndividTrip, PersonID, PersonSpec, SpawnTrip};
+use geom::Time;
+use map_model::{Map, RoadID, Traversable};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Rain, snow, or ice during parts of the day. Usually loaded from a JSON file, either with
+// --weather or from data/system/weather/<map>/<scenario>.json when seeding a scenario, like:
+//
+// {
+//   "spells": [
+//     { "conditions": "Rain", "start": 25200.0, "end": 36000.0 },
+//     { "conditions": "Ice", "start": 0.0, "end": 21600.0, "roads": [12, 13] }
+//   ]
+// }
+//
+// Times are seconds since midnight. No spells means clear skies all day.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Weather {
+    pub spells: Vec<WeatherSpell>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeatherSpell {
+    pub conditions: WeatherConditions,
+    pub start: Time,
+    pub end: Time,
+    // Empty means everywhere
+    #[serde(default)]
+    pub roads: BTreeSet<RoadID>,
+}
+
+// Ordered from best to worst
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum WeatherConditions {
+    Clear,
+    Rain,
+    Snow,
+    Ice,
+}
+
+impl WeatherConditions {
+    pub fn describe(self) -> &'static str {
+        match self {
+            WeatherConditions::Clear => "clear",
+            WeatherConditions::Rain => "rain",
+            WeatherConditions::Snow => "snow",
+            WeatherConditions::Ice => "ice",
+        }
+    }
+
+    // Multiplies the speed of vehicles
+    fn driving_factor(self) -> f64 {
+        match self {
+            WeatherConditions::Clear => 1.0,
+            WeatherConditions::Rain => 0.85,
+            WeatherConditions::Snow => 0.6,
+            WeatherConditions::Ice => 0.4,
+        }
+    }
+
+    // Multiplies the speed of pedestrians
+    fn walking_factor(self) -> f64 {
+        match self {
+            WeatherConditions::Clear => 1.0,
+            WeatherConditions::Rain => 0.9,
+            WeatherConditions::Snow => 0.75,
+            WeatherConditions::Ice => 0.5,
+        }
+    }
+
+    // What fraction of people who'd walk or bike take the bus instead
+    fn transit_shift(self) -> f64 {
+        match self {
+            WeatherConditions::Clear => 0.0,
+            WeatherConditions::Rain => 0.2,
+            WeatherConditions::Snow => 0.4,
+            WeatherConditions::Ice => 0.5,
+        }
+    }
+}
+
+impl Weather {
+    // The worst conditions on one road
+    pub fn conditions_at(&self, r: RoadID, now: Time) -> WeatherConditions {
+        self.spells
+            .iter()
+            .filter(|s| {
+                s.start <= now && now < s.end && (s.roads.is_empty() || s.roads.contains(&r))
+            })
+            .map(|s| s.conditions)
+            .max()
+            .unwrap_or(WeatherConditions::Clear)
+    }
+
+    // The worst conditions anywhere
+    pub fn current(&self, now: Time) -> WeatherConditions {
+        self.spells
+            .iter()
+            .filter(|s| s.start <= now && now < s.end)
+            .map(|s| s.conditions)
+            .max()
+            .unwrap_or(WeatherConditions::Clear)
+    }
+
+    // Snow routes get plowed, so vehicles there only slow down like it's raining.
+    pub(crate) fn driving_factor(&self, on: Traversable, map: &Map, now: Time) -> f64 {
+        let r = map.get_r(road_of(on, map));
+        let conditions = self.conditions_at(r.id, now);
+        if r.snow_route {
+            conditions.min(WeatherConditions::Rain).driving_factor()
+        } else {
+            conditions.driving_factor()
+        }
+    }
+
+    pub(crate) fn walking_factor(&self, on: Traversable, map: &Map, now: Time) -> f64 {
+        self.conditions_at(road_of(on, map), now).walking_factor()
+    }
+
+    // Some walking and biking trips that start in bad weather switch to transit, when there's a
+    // useful route. Who switches is fixed by their ID, so it doesn't depend on the RNG.
+    pub(crate) fn adjust_schedule(&self, person: &PersonSpec, map: &Map) -> PersonSpec {
+        let mut person = person.clone();
+        if self.spells.is_empty() {
+            return person;
+        }
+        let id = person.id;
+        for trip in &mut person.trips {
+            if let Some(spawn) = self.maybe_take_transit(id, trip, map) {
+                trip.trip = spawn;
+            }
+        }
+        person
+    }
+
+    fn maybe_take_transit(
+        &self,
+        person: PersonID,
+        trip: &IndividTrip,
+        map: &Map,
+    ) -> Option<SpawnTrip> {
+        let (start, goal) = match trip.trip {
+            SpawnTrip::JustWalking(ref start, ref goal) => (start.clone(), goal.clone()),
+            SpawnTrip::UsingBike(ref start, _) => {
+                (start.clone(), trip.trip.end(map).end_sidewalk_spot(map))
+            }
+            _ => {
+                return None;
+            }
+        };
+        let conditions =
+            self.conditions_at(map.get_l(start.sidewalk_pos.lane()).parent, trip.depart);
+        if (person.0 % 100) as f64 >= conditions.transit_shift() * 100.0 {
+            return None;
+        }
+        let (stop1, stop2, route) =
+            map.should_use_transit(start.sidewalk_pos, goal.sidewalk_pos)?;
+        Some(SpawnTrip::UsingTransit(start, goal, route, stop1, stop2))
+    }
+}
+
+// A turn counts as part of the road it leads to.
+fn road_of(on: Traversable, map: &Map) -> RoadID {
+    match on {
+        Traversable::Lane(l) => map.get_l(l).parent,
+        Traversable::Turn(t) => map.get_l(t.dst).parent,
+    }
+}